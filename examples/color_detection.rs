@@ -0,0 +1,24 @@
+use perple::color::{DrawOptions, YoloDetector, load_image};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+    
+    let image = load_image("data/test/1562400315184.jpg")?;
+    // 启用embedded-model特性时使用内嵌模型，不需要模型文件
+    #[cfg(feature = "embedded-model")]
+    let detector = YoloDetector::embedded()?;
+    #[cfg(not(feature = "embedded-model"))]
+    let detector = YoloDetector::new("module/color/yolo11n.onnx", 640, 640)?;
+    let mut detector = detector.with_confidence_threshold(0.5);
+    
+    // 检测并保存绘制结果
+    let bounds = detector.detect_and_save(&image, "results/color_detection_result.jpg")?;
+    println!("{}", bounds);
+    
+    // 只绘制行人（类别0）
+    let only_people = DrawOptions { show_classes: Some(vec![0]), ..Default::default() };
+    let (_, annotated) = detector.detect_and_draw(&image, &only_people)?;
+    annotated.save("results/color_detection_people.jpg")?;
+    
+    Ok(())
+}
@@ -37,6 +37,8 @@ impl Counter {
 
 #[tokio::main]
 async fn main() {
+    env_logger::init();
+    
     let counter = Counter::new();
     
     // 为我们的任务克隆计数器
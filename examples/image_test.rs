@@ -1,15 +1,10 @@
-use perple::perple::Perple;
-use perple::LoopMode;
-use perple::{
-    color::Bounds, draw_detections, load_image
-};
+use perple::prelude::*;
 use std::sync::{Arc, Mutex};
-use std::time::{Instant, Duration};
-use std::thread;
-use perple::utils::stream::Stream;
-use image::DynamicImage;
+use std::time::Instant;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+    
     println!("Perple 图像测试示例");
     println!("===================");
     
@@ -29,7 +24,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         Arc::clone(&img_stream),
         Arc::clone(&bounds_stream),
         "module/color/yolo11n.onnx",
-    );
+    )?;
     
     // 更新图像到流中
     perple.update_image(image.clone());
@@ -57,22 +52,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // 从结果流中获取检测结果
     let bounds = {
         let mut bounds_stream = bounds_stream.lock().unwrap();
-        bounds_stream.read().unwrap_or_else(|| Bounds::new())
+        bounds_stream.read().unwrap_or_default()
     };
     
-    println!("检测到 {} 个目标", bounds.len());
-    
     // 显示检测结果
-    for (i, detection) in bounds.iter().enumerate() {
-        println!("  目标 {}: {} - 置信度: {:.2} - 位置: ({:.1}, {:.1}, {:.1}, {:.1})", 
-                i + 1, 
-                detection.class_name, 
-                detection.confidence,
-                detection.bbox.x1,
-                detection.bbox.y1,
-                detection.bbox.x2,
-                detection.bbox.y2);
-    }
+    println!("{}", bounds);
     
     // 在图像上绘制检测框
     println!("\n正在绘制检测结果...");
@@ -1,15 +1,15 @@
 use perple::perple::Perple;
-use perple::LoopMode;
 use perple::{
-    color::Bounds, load_image
+    load_image
 };
 use std::sync::{Arc, Mutex};
 use std::time::{Instant, Duration};
 use std::thread;
 use perple::utils::stream::Stream;
-use image::DynamicImage;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+    
     println!("Perple 循环模式示例");
     println!("===================");
     
@@ -27,7 +27,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             Arc::clone(&img_stream),
             Arc::clone(&bounds_stream),
             "module/color/yolo11n.onnx",
-        );
+        )?;
         
         // 更新图像到流中
         perple.update_image(image.clone());
@@ -46,9 +46,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         // 检查结果
         let bounds = {
             let mut bounds_stream = bounds_stream.lock().unwrap();
-            bounds_stream.read().unwrap_or_else(|| Bounds::new())
+            bounds_stream.read().unwrap_or_default()
         };
-        println!("  检测到 {} 个目标", bounds.len());
+        println!("  检测结果: {}", bounds.summary());
     }
     
     println!("\n2. 按时间循环模式（执行2秒）");
@@ -62,7 +62,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             Arc::clone(&img_stream),
             Arc::clone(&bounds_stream),
             "module/color/yolo11n.onnx",
-        );
+        )?;
         
         // 更新图像到流中
         perple.update_image(image.clone());
@@ -81,9 +81,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         // 检查结果
         let bounds = {
             let mut bounds_stream = bounds_stream.lock().unwrap();
-            bounds_stream.read().unwrap_or_else(|| Bounds::new())
+            bounds_stream.read().unwrap_or_default()
         };
-        println!("  检测到 {} 个目标", bounds.len());
+        println!("  检测结果: {}", bounds.summary());
     }
     
     println!("\n3. 持续循环模式（手动停止）");
@@ -97,7 +97,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             Arc::clone(&img_stream),
             Arc::clone(&bounds_stream),
             "module/color/yolo11n.onnx",
-        );
+        )?;
         
         // 更新图像到流中
         perple.update_image(image.clone());
@@ -120,9 +120,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         // 检查结果
         let bounds = {
             let mut bounds_stream = bounds_stream.lock().unwrap();
-            bounds_stream.read().unwrap_or_else(|| Bounds::new())
+            bounds_stream.read().unwrap_or_default()
         };
-        println!("  检测到 {} 个目标", bounds.len());
+        println!("  检测结果: {}", bounds.summary());
     }
     
     println!("\n4. 等待结果模式（获得结果后立即停止）");
@@ -136,7 +136,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             Arc::clone(&img_stream),
             Arc::clone(&bounds_stream),
             "module/color/yolo11n.onnx",
-        );
+        )?;
         
         // 更新图像到流中
         perple.update_image(image.clone());
@@ -163,9 +163,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         // 检查结果
         let bounds = {
             let mut bounds_stream = bounds_stream.lock().unwrap();
-            bounds_stream.read().unwrap_or_else(|| Bounds::new())
+            bounds_stream.read().unwrap_or_default()
         };
-        println!("  检测到 {} 个目标", bounds.len());
+        println!("  检测结果: {}", bounds.summary());
     }
     
     println!("\n所有示例完成!");
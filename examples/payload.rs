@@ -0,0 +1,51 @@
+use perple::{load_image, LoopMode, Perple, UserPayload};
+use perple::utils::stream::Stream;
+use std::sync::{Arc, Mutex};
+
+/// 随每帧图像传递的相机信息
+#[derive(Debug, Clone, Default)]
+struct CameraInfo {
+    camera_id: u32,
+    pan: f32,
+    tilt: f32,
+}
+
+impl UserPayload for CameraInfo {}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+    
+    println!("Perple 用户数据示例");
+    println!("===================");
+    
+    let image = load_image("data/test/1562400315184.jpg")?;
+    
+    // 图像流元素为(CameraInfo, Frame)，结果流元素为(CameraInfo, Bounds)
+    let img_stream = Arc::new(Mutex::new(Stream::new()));
+    let bounds_stream = Arc::new(Mutex::new(Stream::new()));
+    let mut perple = Perple::<CameraInfo>::from_streams(
+        Arc::clone(&img_stream),
+        Arc::clone(&bounds_stream),
+        "module/color/yolo11n.onnx",
+    )?;
+    
+    // 两台相机各写入一帧
+    perple.update_frame(CameraInfo { camera_id: 1, pan: 30.0, tilt: -5.0 }, image.clone());
+    perple.update_frame(CameraInfo { camera_id: 2, pan: -45.0, tilt: 10.0 }, image.clone());
+    
+    // 阻塞运行两次检测
+    let stats = perple.run_color_loop_blocking(LoopMode::Count(2))?;
+    println!("检测循环结束: {:?}", stats);
+    
+    // 每帧结果都带回写入时的相机信息
+    let mut bounds_stream = bounds_stream.lock().unwrap();
+    while let Some((camera, bounds)) = bounds_stream.read() {
+        println!(
+            "帧{} 相机{} (pan={:.1}, tilt={:.1}): {}",
+            bounds.source_frame_seq(), camera.camera_id, camera.pan, camera.tilt, bounds.summary()
+        );
+    }
+    
+    println!("\n示例完成!");
+    Ok(())
+}
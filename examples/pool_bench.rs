@@ -0,0 +1,60 @@
+use image::{DynamicImage, RgbImage};
+use perple::color::{ModelOptions, ModelPool};
+use std::time::{Duration, Instant};
+
+const MODEL_PATH: &str = "module/color/yolo11n.onnx";
+const FRAMES: usize = 64;
+const THREADS: usize = 4;
+
+/// 生成确定性的测试图像，避免依赖图像文件
+fn synthetic_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+    }))
+}
+
+/// 用`THREADS`个线程共同处理`FRAMES`帧，返回总耗时
+fn run(pool: &ModelPool, image: &DynamicImage) -> Duration {
+    let start = Instant::now();
+    std::thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| {
+                for _ in 0..FRAMES / THREADS {
+                    std::hint::black_box(pool.detect(image).expect("检测失败"));
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("ModelPool 多实例/单实例吞吐量对比");
+    println!("=================================");
+    
+    let image = synthetic_image(1280, 720);
+    let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    
+    let single = ModelPool::new(MODEL_PATH, 1, 640, 640)?;
+    // 预热，排除首次推理的初始化开销
+    single.detect(&image)?;
+    let baseline = run(&single, &image);
+    println!("单实例: {:.1} 帧/秒", FRAMES as f64 / baseline.as_secs_f64());
+    
+    for pool_size in [2, 4] {
+        // 线程总数与CPU核数相当
+        let options = ModelOptions::default().with_intra_threads((cores / pool_size).max(1));
+        let pool = ModelPool::with_model_options(MODEL_PATH, pool_size, 640, 640, &options)?;
+        for _ in 0..pool_size {
+            pool.detect(&image)?;
+        }
+        let elapsed = run(&pool, &image);
+        println!(
+            "{}个实例: {:.1} 帧/秒（{:.2}x）",
+            pool_size,
+            FRAMES as f64 / elapsed.as_secs_f64(),
+            baseline.as_secs_f64() / elapsed.as_secs_f64()
+        );
+    }
+    Ok(())
+}
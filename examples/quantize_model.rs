@@ -0,0 +1,15 @@
+use perple::color::model::quantize_weights;
+
+/// 生成embedded-model特性内嵌的int8模型
+/// 
+/// 用法：cargo run --example quantize_model --features onnx-export [输入模型] [输出模型]
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let input = args.next().unwrap_or_else(|| "module/color/yolo11n.onnx".to_string());
+    let output = args.next().unwrap_or_else(|| "module/color/yolo11n_int8.onnx".to_string());
+    
+    let count = quantize_weights(&input, &output)?;
+    let size = |path: &str| std::fs::metadata(path).map(|m| m.len() as f64 / 1024.0 / 1024.0);
+    println!("量化了{}个权重: {} ({:.1} MB) -> {} ({:.1} MB)", count, input, size(&input)?, output, size(&output)?);
+    Ok(())
+}
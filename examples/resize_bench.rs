@@ -0,0 +1,47 @@
+use image::{DynamicImage, RgbImage};
+use perple::color::image::{resize_image, InputResizer};
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 20;
+
+/// 生成确定性的测试图像，避免依赖图像文件
+fn synthetic_image(width: u32, height: u32) -> RgbImage {
+    RgbImage::from_fn(width, height, |x, y| {
+        image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+    })
+}
+
+/// 多次运行并返回平均耗时
+fn average<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    println!("固定分辨率输入缩放到640x640：resize_image / InputResizer");
+    println!("=========================================================");
+    
+    for (width, height) in [(1280u32, 720u32), (1920, 1080)] {
+        let image = synthetic_image(width, height);
+        let dynamic = DynamicImage::ImageRgb8(image.clone());
+        let mut resizer = InputResizer::new();
+        
+        // 先校验两种方式输出一致
+        assert!(resizer.resize(&image, 640, 640) == &resize_image(&dynamic, 640, 640).to_rgb8());
+        
+        let allocating = average(|| {
+            std::hint::black_box(resize_image(&dynamic, 640, 640));
+        });
+        let reusing = average(|| {
+            std::hint::black_box(resizer.resize(&image, 640, 640));
+        });
+        
+        println!("{}x{}:", width, height);
+        println!("  resize_image: {:?}", allocating);
+        println!("  InputResizer: {:?}", reusing);
+        println!("  加速比: {:.2}x", allocating.as_secs_f64() / reusing.as_secs_f64());
+    }
+}
@@ -0,0 +1,64 @@
+use perple::utils::sort::{group_sort_by, insertion_sort_groups_by, adaptive_group_sort_by};
+use std::time::{Duration, Instant};
+
+/// 每组参数个数（x1, y1, x2, y2, 置信度, 类别），第4个为排序键
+const SPLIT: usize = 6;
+const OFFSET: usize = 4;
+
+/// 生成确定性的伪随机检测数据，避免引入随机数依赖
+fn synthetic_groups(groups: usize) -> Vec<f32> {
+    let mut state = 0x2545_f491u32;
+    (0..groups * SPLIT)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state % 10_000) as f32 / 10_000.0
+        })
+        .collect()
+}
+
+fn descending(a: &f32, b: &f32) -> std::cmp::Ordering {
+    b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal)
+}
+
+/// 多次对同一份数据的副本排序并返回平均耗时（不含拷贝时间）
+fn average(data: &[f32], iterations: u32, sort: fn(&mut [f32])) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..iterations {
+        let mut copy = data.to_vec();
+        let start = Instant::now();
+        sort(&mut copy);
+        total += start.elapsed();
+        std::hint::black_box(&copy);
+    }
+    total / iterations
+}
+
+fn main() {
+    println!("按组排序性能对比（插入排序 / 快速排序 / 自适应）");
+    println!("================================================");
+    
+    for groups in [8usize, 32, 256, 8400] {
+        let data = synthetic_groups(groups);
+        // 插入排序是O(n²)，组数多时减少迭代次数
+        let iterations = if groups > 1000 { 5 } else { 200 };
+        
+        // 先校验各版本的排序键顺序一致
+        let mut expected = data.clone();
+        group_sort_by(&mut expected, SPLIT, OFFSET, descending);
+        let mut actual = data.clone();
+        insertion_sort_groups_by(&mut actual, SPLIT, OFFSET, descending);
+        let keys = |v: &[f32]| v.chunks_exact(SPLIT).map(|g| g[OFFSET]).collect::<Vec<_>>();
+        assert_eq!(keys(&expected), keys(&actual));
+        
+        let insertion = average(&data, iterations, |v| insertion_sort_groups_by(v, SPLIT, OFFSET, descending));
+        let quick = average(&data, iterations, |v| group_sort_by(v, SPLIT, OFFSET, descending));
+        let adaptive = average(&data, iterations, |v| adaptive_group_sort_by(v, SPLIT, OFFSET, descending));
+        
+        println!("{}组:", groups);
+        println!("  插入排序: {:?}", insertion);
+        println!("  快速排序: {:?}", quick);
+        println!("  自适应:   {:?}", adaptive);
+    }
+}
@@ -0,0 +1,57 @@
+use perple::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// 超过该时间没有收到检测结果即视为检测流停滞
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+    
+    println!("Perple 异步检测流示例");
+    println!("=====================");
+    
+    let image = load_image("data/test/1562400315184.jpg")?;
+    
+    // 创建数据流和Perple实例
+    let img_stream = Arc::new(Mutex::new(Stream::new()));
+    let bounds_stream = Arc::new(Mutex::new(Stream::new()));
+    let mut perple = Perple::new(
+        Arc::clone(&img_stream),
+        Arc::clone(&bounds_stream),
+        "module/color/yolo11n.onnx",
+    )?;
+    
+    // 先注册通道再启动循环，避免错过第一帧
+    let detections = ReceiverStream::new(perple.stream_detections()).timeout(STALL_TIMEOUT);
+    tokio::pin!(detections);
+    
+    // 只提供5帧图像，之后检测流会停滞并触发超时
+    for _ in 0..5 {
+        let _ = perple.update_image(image.clone());
+    }
+    perple.start_color_loop()?;
+    
+    while let Some(item) = detections.next().await {
+        match item {
+            Ok(bounds) => {
+                // 同时取走结果流中的这一帧，避免结果流写满
+                let _ = bounds_stream.lock().unwrap().read();
+                println!("收到检测结果: {}", bounds.summary());
+            }
+            Err(_) => {
+                println!("{:?}内没有新的检测结果，检测流已停滞", STALL_TIMEOUT);
+                break;
+            }
+        }
+    }
+    
+    perple.stop_color_loop();
+    perple.join_color_thread()?;
+    
+    println!("\n示例完成!");
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use image::{DynamicImage, RgbImage};
+use perple::color::image::{image_to_tensor, image_to_tensor_parallel};
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 20;
+
+/// 生成确定性的测试图像，避免依赖图像文件
+fn synthetic_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+    }))
+}
+
+/// 多次运行并返回平均耗时
+fn average<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    println!("image_to_tensor 串行/并行性能对比");
+    println!("==================================");
+    
+    for (width, height) in [(640u32, 640u32), (3840, 2160)] {
+        let image = synthetic_image(width, height);
+        let (h, w) = (height as usize, width as usize);
+        
+        // 先校验两个版本输出一致
+        assert_eq!(image_to_tensor(&image, h, w), image_to_tensor_parallel(&image, h, w));
+        
+        let serial = average(|| {
+            std::hint::black_box(image_to_tensor(&image, h, w));
+        });
+        let parallel = average(|| {
+            std::hint::black_box(image_to_tensor_parallel(&image, h, w));
+        });
+        
+        println!("{}x{}:", width, height);
+        println!("  串行: {:?}", serial);
+        println!("  并行: {:?}", parallel);
+        println!("  加速比: {:.2}x", serial.as_secs_f64() / parallel.as_secs_f64());
+    }
+}
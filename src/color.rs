@@ -25,13 +25,12 @@
 //! # 示例
 //! 
 //! ```
-//! use perple::color::{YoloDetector, load_model, load_image, draw_detections};
+//! use perple::color::{YoloDetector, load_image, draw_detections};
 //! 
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
-//! let model = load_model("path/to/model.onnx")?;
 //! let image = load_image("path/to/image.jpg")?;
 //! 
-//! let mut detector = YoloDetector::new(model, 640, 640)
+//! let mut detector = YoloDetector::new("path/to/model.onnx", 640, 640)?
 //!     .with_confidence_threshold(0.5)
 //!     .with_nms_threshold(0.7);
 //! 
@@ -48,10 +47,40 @@ pub mod utils;
 pub mod bounds;
 pub mod array;
 pub mod core;
+pub mod reid;
+pub mod transform;
+pub mod counter;
+pub mod snapshot;
+pub mod fusion;
+pub mod preprocess;
+pub mod profile;
+pub mod payload;
+pub mod sink;
+pub mod trajectory;
+pub mod debug;
+pub mod labels;
+pub mod pool;
 
 // 重新导出主要类型，方便外部使用
-pub use model::load_model;
-pub use image::{load_image, resize_image, image_to_tensor, input_image, fill_input_image};
-pub use detect::YoloDetector;
-pub use bounds::{Bounds, Detection, BoundingBox};
-pub use utils::{nms_tensor, process_detections, to_bounds, draw_detections};
\ No newline at end of file
+pub use model::{load_model, load_model_with_options, load_model_autodetect, ModelFormat, ModelOptions, ModelPool, ExecutionMode};
+pub use image::{load_image, load_image_with_progress, resize_image, InputResizer, image_to_tensor, image_to_tensor_with_background, to_rgb_input, input_image, fill_input_image, fill_input_image_nhwc, InputLayout, LetterboxPadder, crop_detections, image_diff, image_diff_map, tensor_to_image, tensor_to_rgb_image};
+#[cfg(feature = "parallel")]
+pub use image::{image_to_tensor_parallel, resize_batch, resize_batch_to_tensors};
+pub use detect::{YoloDetector, YoloDetectorN, DetectorBuilder, DetectorConfig};
+pub use bounds::{Bounds, BoundsN, FLAT_ROW_LEN, BoundsIntoIter, Detection, BoundingBox, OrientedBoundingBox, OutputSpace, ThresholdZone, Keypoint, Mask, iou_weighted_average_box, dedup_detections};
+pub use transform::{Affine2, SpaceGeometry};
+pub use counter::PersonCounter;
+pub use trajectory::TrajectoryRenderer;
+pub use debug::{render_model_view, render_comparison};
+pub use labels::ClassMap;
+pub use pool::{DetectorPool, PoolConfig, ResultHandle, ShutdownMode};
+pub use snapshot::{SnapshotSink, SnapshotConfig, SnapshotTrigger};
+pub use fusion::{Fuser, FusionMode};
+pub use payload::{Frame, Payload, UserPayload};
+pub use sink::{ResultSink, SinkRunner, SinkPolicy, FrameMeta};
+#[cfg(feature = "serde")]
+pub use sink::JsonlSink;
+pub use profile::{ProfileReport, NodeTiming, parse_node_timings};
+pub use preprocess::{Preprocessor, ChainedPreprocessor, Crop, Gamma, BrightnessContrast};
+pub use reid::{ReidExtractor, Gallery, cosine_similarity};
+pub use utils::{nms_tensor, process_detections, process_detections_calibrated, to_bounds, draw_detections, draw_detections_checked, draw_detections_with_options, draw_obb_detections, draw_trajectories, window_nms, TemporalBoundsFilter, render_to_png_bytes, draw_detections_on_frame, DrawOptions, DrawStyle, PixelFormat, redact_detections, decode_mask, confidence_histogram, suggested_threshold, compute_ap, compute_map, COCO_IOU_THRESHOLDS, fit_temperature_scaling, dbscan_cluster, nms_tensor_with_options, resolve_class_scores, transpose_raw_output, is_raw_output, CalibrationConfig, ConfidenceCalibration, CoordFormat, NmsMode, NmsOptions, OutputLayout};
\ No newline at end of file
@@ -5,6 +5,9 @@
 use ndarray::Array4;
 use ort::value::{Tensor, TensorValueType, Value};
 
+use crate::color::image::InputLayout;
+use crate::error::PerpleError;
+
 /// 将ndarray数组转换为ONNX Runtime张量
 /// 
 /// # 参数
@@ -15,9 +18,72 @@ use ort::value::{Tensor, TensorValueType, Value};
 pub fn to_input(mats: &Array4<f32>) -> Value<TensorValueType<f32>> {
     let shape: Vec<usize> = mats.shape().to_vec();
     let (data, _offset) = mats.clone().into_raw_vec_and_offset();
-    let result = Tensor::from_array((
+    Tensor::from_array((
         [shape[0], shape[1], shape[2], shape[3]],
         data
-    )).unwrap();
-    result
-}
\ No newline at end of file
+    )).unwrap()
+}
+/// 将NCHW排列的ndarray数组按指定布局转换为ONNX Runtime张量
+/// 
+/// # 参数
+/// * `mats` - 四维数组，形状为(1, 3, height, width)
+/// * `layout` - 模型期望的输入布局，NHWC时会转置为(1, height, width, 3)
+/// 
+/// # 错误处理
+/// 张量创建失败时返回Err
+pub fn to_input_with_layout(mats: &Array4<f32>, layout: InputLayout) -> Result<Value<TensorValueType<f32>>, PerpleError> {
+    match layout {
+        InputLayout::Nchw => Ok(to_input(mats)),
+        InputLayout::Nhwc => {
+            let nhwc = mats.view().permuted_axes([0, 2, 3, 1]);
+            let shape = nhwc.shape().to_vec();
+            let data: Vec<f32> = nhwc.iter().copied().collect();
+            Ok(Tensor::from_array(([shape[0], shape[1], shape[2], shape[3]], data))?)
+        }
+    }
+}
+
+/// 按指定布局创建全零的模型输入张量
+/// 
+/// # 错误处理
+/// 张量创建失败时返回Err
+pub fn empty_input(input_height: usize, input_width: usize, layout: InputLayout) -> Result<Value<TensorValueType<f32>>, PerpleError> {
+    let data = vec![0.0f32; 3 * input_height * input_width];
+    let shape = match layout {
+        InputLayout::Nchw => [1, 3, input_height, input_width],
+        InputLayout::Nhwc => [1, input_height, input_width, 3],
+    };
+    Ok(Tensor::from_array((shape, data))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nhwc_input_moves_channels_last() {
+        // 像素(x=2, y=1)的三个通道分别为1、2、3
+        let mut mats = Array4::<f32>::zeros((1, 3, 3, 4));
+        for channel in 0..3 {
+            mats[[0, channel, 1, 2]] = channel as f32 + 1.0;
+        }
+        let nchw = to_input_with_layout(&mats, InputLayout::Nchw).unwrap();
+        let (shape, data) = nchw.try_extract_tensor::<f32>().unwrap();
+        assert_eq!(&shape[..], &[1, 3, 3, 4]);
+        assert_eq!([data[6], data[18], data[30]], [1.0, 2.0, 3.0]);
+
+        let nhwc = to_input_with_layout(&mats, InputLayout::Nhwc).unwrap();
+        let (shape, data) = nhwc.try_extract_tensor::<f32>().unwrap();
+        assert_eq!(&shape[..], &[1, 3, 4, 3]);
+        assert_eq!(&data[18..21], &[1.0, 2.0, 3.0]);
+        assert_eq!(data.iter().sum::<f32>(), 6.0);
+    }
+
+    #[test]
+    fn empty_input_follows_layout() {
+        let nchw = empty_input(2, 5, InputLayout::Nchw).unwrap();
+        assert_eq!(&nchw.try_extract_tensor::<f32>().unwrap().0[..], &[1, 3, 2, 5]);
+        let nhwc = empty_input(2, 5, InputLayout::Nhwc).unwrap();
+        assert_eq!(&nhwc.try_extract_tensor::<f32>().unwrap().0[..], &[1, 2, 5, 3]);
+    }
+}
@@ -1,9 +1,23 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ptr;
+use std::sync::OnceLock;
+
+use crate::color::transform::{Affine2, SpaceGeometry};
+use crate::color::utils::{self, apply_nms_with_options, dbscan_cluster, NmsOptions};
 use crate::config::DETECTIONS_CAPACITY;
 
 /// 边界框结构
 /// 
 /// 表示一个矩形边界框，用于包围检测到的目标。
+/// 
+/// 约定`x1 <= x2`且`y1 <= y2`。后处理（[process_detections](crate::color::process_detections)、
+/// [nms_tensor](crate::color::nms_tensor)等）在接收模型输出时会先用[normalized](Self::normalized)
+/// 整理角点顺序，因此其输出的框总是满足该约定；手动构造的框可能角点颠倒，
+/// 此时[is_valid](Self::is_valid)返回`false`，需要先调用[normalized](Self::normalized)。
 #[derive(Debug, Clone, Default, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoundingBox {
     /// 左上角x坐标
     pub x1: f32,
@@ -21,11 +35,6 @@ impl BoundingBox {
         Self { x1, y1, x2, y2 }
     }
     
-    /// 创建一个默认的边界框
-    pub fn default() -> Self {
-        Self { x1: 0.0, y1: 0.0, x2: 0.0, y2: 0.0 }
-    }
-    
     /// 计算边界框的宽度
     pub fn width(&self) -> f32 {
         (self.x2 - self.x1).abs()
@@ -41,12 +50,374 @@ impl BoundingBox {
         self.width() * self.height()
     }
     
-    /// 检查边界框是否有效（宽度和高度都大于0）
+    /// 检查边界框是否有效：角点顺序正确且宽高都大于0
+    /// 
+    /// 与NMS的判断标准一致：NMS按`(x2 - x1) * (y2 - y1)`计算面积并丢弃面积不大于0的框，
+    /// 因此角点颠倒的框即使[width](Self::width)和[height](Self::height)为正也视为无效。
+    /// 包含NaN坐标的框同样无效。
     pub fn is_valid(&self) -> bool {
-        self.width() > 0.0 && self.height() > 0.0
+        self.x2 > self.x1 && self.y2 > self.y1
+    }
+    
+    /// 返回角点按`x1 <= x2`、`y1 <= y2`重新排列后的边界框
+    pub fn normalized(&self) -> BoundingBox {
+        BoundingBox::new(
+            self.x1.min(self.x2),
+            self.y1.min(self.y2),
+            self.x1.max(self.x2),
+            self.y1.max(self.y2),
+        )
+    }
+    
+    /// 计算边界框的中心点
+    pub fn center(&self) -> (f32, f32) {
+        ((self.x1 + self.x2) / 2.0, (self.y1 + self.y2) / 2.0)
+    }
+    
+    /// 计算与另一个边界框的交集面积
+    /// 
+    /// 任一框角点颠倒时返回0.0，需要时先调用[normalized](Self::normalized)
+    pub fn intersection_area(&self, other: &BoundingBox) -> f32 {
+        utils::intersection(self, other)
+    }
+    
+    /// 计算与另一个边界框的交并比(IoU)
+    /// 
+    /// 与NMS使用同一实现：两个框的并集面积为0或任一框角点颠倒时返回0.0
+    pub fn iou(&self, other: &BoundingBox) -> f32 {
+        utils::iou(self, other)
+    }
+    
+    /// 检查点是否位于边界框内（包含边界）
+    pub fn contains_point(&self, x: f32, y: f32) -> bool {
+        x >= self.x1.min(self.x2) && x <= self.x1.max(self.x2)
+            && y >= self.y1.min(self.y2) && y <= self.y1.max(self.y2)
+    }
+    
+    /// 检查另一个边界框是否完全位于该边界框内（包含边界）
+    pub fn contains_bbox(&self, other: &BoundingBox) -> bool {
+        self.contains_point(other.x1, other.y1) && self.contains_point(other.x2, other.y2)
+    }
+    
+    /// 将像素坐标归一化到`[0.0, 1.0]`（相对于图像宽高）
+    pub fn normalize(&self, img_width: f32, img_height: f32) -> BoundingBox {
+        BoundingBox::new(self.x1 / img_width, self.y1 / img_height, self.x2 / img_width, self.y2 / img_height)
+    }
+    
+    /// 将归一化坐标还原为像素坐标，[normalize](Self::normalize)的逆操作
+    pub fn denormalize(&self, img_width: f32, img_height: f32) -> BoundingBox {
+        BoundingBox::new(self.x1 * img_width, self.y1 * img_height, self.x2 * img_width, self.y2 * img_height)
+    }
+    
+    /// 图像顺时针旋转90°后的边界框（旋转后图像宽高互换）
+    pub fn rotate90(&self, img_width: f32, img_height: f32) -> BoundingBox {
+        self.transform(&Affine2::rotate90(img_width, img_height))
+    }
+    
+    /// 图像旋转180°后的边界框
+    pub fn rotate180(&self, img_width: f32, img_height: f32) -> BoundingBox {
+        self.transform(&Affine2::rotate180(img_width, img_height))
+    }
+    
+    /// 图像顺时针旋转270°后的边界框（旋转后图像宽高互换）
+    pub fn rotate270(&self, img_width: f32, img_height: f32) -> BoundingBox {
+        self.transform(&Affine2::rotate270(img_width, img_height))
+    }
+    
+    /// 图像水平翻转后的边界框
+    pub fn flip_horizontal(&self, img_width: f32) -> BoundingBox {
+        self.transform(&Affine2::flip_horizontal(img_width))
+    }
+    
+    /// 图像垂直翻转后的边界框
+    pub fn flip_vertical(&self, img_height: f32) -> BoundingBox {
+        self.transform(&Affine2::flip_vertical(img_height))
+    }
+    
+    /// 对边界框应用仿射变换
+    /// 
+    /// 变换四个角点后取其轴对齐外接矩形，因此对90°旋转和翻转是精确的。
+    pub fn transform(&self, transform: &Affine2) -> BoundingBox {
+        let corners = self.to_polygon().map(|(x, y)| transform.apply(x, y));
+        BoundingBox::from_polygon(&corners).expect("四个角点非空")
+    }
+    
+    /// 返回四个角点，按左上、右上、右下、左下的顺时针顺序排列（图像坐标系，y轴向下）
+    pub fn to_polygon(&self) -> [(f32, f32); 4] {
+        [(self.x1, self.y1), (self.x2, self.y1), (self.x2, self.y2), (self.x1, self.y2)]
+    }
+    
+    /// 计算包围多边形所有顶点的轴对齐边界框，没有顶点时返回`None`
+    pub fn from_polygon(points: &[(f32, f32)]) -> Option<BoundingBox> {
+        let (&(x, y), rest) = points.split_first()?;
+        Some(rest.iter().fold(
+            BoundingBox::new(x, y, x, y),
+            |acc, &(x, y)| BoundingBox::new(acc.x1.min(x), acc.y1.min(y), acc.x2.max(x), acc.y2.max(y)),
+        ))
+    }
+}
+
+/// 计算一组边界框按权重加权的平均框
+/// 
+/// 每个坐标分别计算`Σ(w_i * coord_i) / Σw_i`。`boxes`与`weights`按较短者对齐；
+/// 权重之和不为正时退化为算术平均，`boxes`为空时返回默认框。
+pub fn iou_weighted_average_box(boxes: &[&BoundingBox], weights: &[f32]) -> BoundingBox {
+    let weight_sum: f32 = boxes.iter().zip(weights).map(|(_, &w)| w).sum();
+    let uniform = weight_sum <= 0.0;
+    let (count, total) = if uniform {
+        (boxes.len(), boxes.len() as f32)
+    } else {
+        (boxes.len().min(weights.len()), weight_sum)
+    };
+    if count == 0 {
+        return BoundingBox::default();
+    }
+    
+    let mut sum = [0.0f32; 4];
+    for (i, bbox) in boxes.iter().take(count).enumerate() {
+        let weight = if uniform { 1.0 } else { weights[i] };
+        sum[0] += weight * bbox.x1;
+        sum[1] += weight * bbox.y1;
+        sum[2] += weight * bbox.x2;
+        sum[3] += weight * bbox.y2;
     }
+    BoundingBox::new(sum[0] / total, sum[1] / total, sum[2] / total, sum[3] / total)
 }
 
+/// 去除重复的检测结果，保留置信度较高者，其余检测结果保持原有顺序
+/// 
+/// 类别相同且四个坐标之差都不超过`eps`像素的两个框视为重复；`eps`为0时只去除完全相同的框。
+/// 先按`x1`排序，再只比较`x1`相差不超过`eps`的相邻元素，无需两两比较。
+/// 适合在融合分块检测、测试时增强或多个模型的结果之前清理重复框。
+pub fn dedup_detections(detections: Vec<Detection>, eps: f32) -> Vec<Detection> {
+    let mut order: Vec<usize> = (0..detections.len()).collect();
+    order.sort_by(|&a, &b| detections[a].bbox.x1.total_cmp(&detections[b].bbox.x1));
+    
+    let is_duplicate = |a: &Detection, b: &Detection| {
+        a.class_id == b.class_id
+            && (a.bbox.x1 - b.bbox.x1).abs() <= eps
+            && (a.bbox.y1 - b.bbox.y1).abs() <= eps
+            && (a.bbox.x2 - b.bbox.x2).abs() <= eps
+            && (a.bbox.y2 - b.bbox.y2).abs() <= eps
+    };
+    
+    let mut removed = vec![false; detections.len()];
+    for (position, &i) in order.iter().enumerate() {
+        if removed[i] {
+            continue;
+        }
+        for &j in &order[position + 1..] {
+            if detections[j].bbox.x1 - detections[i].bbox.x1 > eps {
+                break;
+            }
+            if removed[j] || !is_duplicate(&detections[i], &detections[j]) {
+                continue;
+            }
+            if detections[j].confidence > detections[i].confidence {
+                removed[i] = true;
+                break;
+            }
+            removed[j] = true;
+        }
+    }
+    
+    detections.into_iter().zip(removed).filter(|(_, r)| !r).map(|(d, _)| d).collect()
+}
+
+/// 旋转边界框
+/// 
+/// 由中心点、宽高和旋转角表示，对应旋转目标检测（OBB）模型的输出。
+/// 角度按图像坐标系（y轴向下）从x轴正方向顺时针计，单位为弧度。
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OrientedBoundingBox {
+    /// 中心点x坐标
+    pub cx: f32,
+    /// 中心点y坐标
+    pub cy: f32,
+    /// 旋转前的宽度
+    pub w: f32,
+    /// 旋转前的高度
+    pub h: f32,
+    /// 旋转角（弧度）
+    pub angle_rad: f32,
+}
+
+impl OrientedBoundingBox {
+    /// 创建一个新的旋转边界框
+    pub fn new(cx: f32, cy: f32, w: f32, h: f32, angle_rad: f32) -> Self {
+        Self { cx, cy, w, h, angle_rad }
+    }
+    
+    /// 计算四个角点，按左上、右上、右下、左下（旋转前的位置）的顺序排列
+    pub fn to_corners(&self) -> [(f32, f32); 4] {
+        let (sin, cos) = self.angle_rad.sin_cos();
+        let (hw, hh) = (self.w / 2.0, self.h / 2.0);
+        [(-hw, -hh), (hw, -hh), (hw, hh), (-hw, hh)].map(|(dx, dy)| {
+            (self.cx + dx * cos - dy * sin, self.cy + dx * sin + dy * cos)
+        })
+    }
+    
+    /// 包含旋转框的最小轴对齐边界框
+    pub fn bounding_rect(&self) -> BoundingBox {
+        let corners = self.to_corners();
+        let fold = |init: f32, f: fn(f32, f32) -> f32, pick: fn(&(f32, f32)) -> f32| {
+            corners.iter().map(pick).fold(init, f)
+        };
+        BoundingBox::new(
+            fold(f32::INFINITY, f32::min, |c| c.0),
+            fold(f32::INFINITY, f32::min, |c| c.1),
+            fold(f32::NEG_INFINITY, f32::max, |c| c.0),
+            fold(f32::NEG_INFINITY, f32::max, |c| c.1),
+        )
+    }
+}
+
+/// 置信度阈值区域
+/// 
+/// 为图像中的某个矩形区域指定独立的置信度阈值，
+/// 例如为远处走廊设置更低的阈值以检出较小的行人。
+/// 区域坐标使用原始图像坐标系。
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ThresholdZone {
+    /// 区域范围（原始图像坐标）
+    pub region: BoundingBox,
+    /// 区域内使用的置信度阈值
+    pub confidence: f32,
+}
+
+impl ThresholdZone {
+    /// 创建一个新的阈值区域
+    pub fn new(region: BoundingBox, confidence: f32) -> Self {
+        Self { region, confidence }
+    }
+}
+
+/// 根据中心点查找检测框应使用的置信度阈值
+/// 
+/// 区域按顺序匹配，中心点落在多个重叠区域内时使用第一个匹配区域的阈值；
+/// 没有匹配的区域时使用全局阈值。
+/// 
+/// # 参数
+/// * `zones` - 阈值区域列表
+/// * `center` - 检测框中心点（原始图像坐标）
+/// * `default_threshold` - 全局置信度阈值
+pub fn zone_threshold(zones: &[ThresholdZone], center: (f32, f32), default_threshold: f32) -> f32 {
+    zones.iter()
+        .find(|zone| zone.region.contains_point(center.0, center.1))
+        .map(|zone| zone.confidence)
+        .unwrap_or(default_threshold)
+}
+
+
+/// 人体关键点
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Keypoint {
+    /// x坐标
+    pub x: f32,
+    /// y坐标
+    pub y: f32,
+    /// 关键点置信度（可见性）
+    pub confidence: f32,
+}
+
+impl Keypoint {
+    /// 创建一个新的关键点
+    pub fn new(x: f32, y: f32, confidence: f32) -> Self {
+        Self { x, y, confidence }
+    }
+}
+
+/// 实例分割掩码
+/// 
+/// 以位图形式紧凑存储（每像素1位），覆盖原始图像中左上角为`(x, y)`、
+/// 大小为`width`x`height`的矩形区域，通常即检测框所在区域。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mask {
+    /// 区域左上角x坐标（原始图像像素）
+    pub x: u32,
+    /// 区域左上角y坐标（原始图像像素）
+    pub y: u32,
+    /// 区域宽度
+    pub width: u32,
+    /// 区域高度
+    pub height: u32,
+    bits: Vec<u8>,
+}
+
+impl Mask {
+    /// 创建一个全空的掩码
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        let len = (width as usize * height as usize).div_ceil(8);
+        Self { x, y, width, height, bits: vec![0; len] }
+    }
+    
+    /// 将图像坐标换算为位索引，超出掩码区域时返回`None`
+    fn bit_index(&self, px: u32, py: u32) -> Option<usize> {
+        if px < self.x || py < self.y || px - self.x >= self.width || py - self.y >= self.height {
+            return None;
+        }
+        Some((py - self.y) as usize * self.width as usize + (px - self.x) as usize)
+    }
+    
+    /// 查询图像坐标`(px, py)`处是否属于目标，区域外恒为`false`
+    pub fn get(&self, px: u32, py: u32) -> bool {
+        self.bit_index(px, py)
+            .is_some_and(|i| self.bits[i / 8] & (1 << (i % 8)) != 0)
+    }
+    
+    /// 设置图像坐标`(px, py)`处的掩码值，区域外的坐标将被忽略
+    pub fn set(&mut self, px: u32, py: u32, value: bool) {
+        if let Some(i) = self.bit_index(px, py) {
+            if value {
+                self.bits[i / 8] |= 1 << (i % 8);
+            } else {
+                self.bits[i / 8] &= !(1 << (i % 8));
+            }
+        }
+    }
+    
+    /// 属于目标的像素个数
+    pub fn count(&self) -> usize {
+        self.bits.iter().map(|b| b.count_ones() as usize).sum()
+    }
+    
+    /// 对掩码应用仿射变换
+    /// 
+    /// 对变换后区域内的每个像素，用逆变换找到原掩码中对应的像素（最近邻采样）。
+    /// 变换不可逆时返回`None`。
+    pub fn transform(&self, transform: &Affine2) -> Option<Mask> {
+        let inverse = transform.inverse()?;
+        let region = BoundingBox::new(
+            self.x as f32,
+            self.y as f32,
+            (self.x + self.width) as f32,
+            (self.y + self.height) as f32,
+        ).transform(transform);
+        
+        let x = region.x1.max(0.0).round() as u32;
+        let y = region.y1.max(0.0).round() as u32;
+        let x2 = region.x2.max(0.0).round() as u32;
+        let y2 = region.y2.max(0.0).round() as u32;
+        let mut mask = Mask::new(x, y, x2.saturating_sub(x), y2.saturating_sub(y));
+        for py in y..y2 {
+            for px in x..x2 {
+                let (sx, sy) = inverse.apply(px as f32 + 0.5, py as f32 + 0.5);
+                if sx >= 0.0 && sy >= 0.0 && self.get(sx as u32, sy as u32) {
+                    mask.set(px, py, true);
+                }
+            }
+        }
+        Some(mask)
+    }
+    
+    /// 遍历所有属于目标的像素（图像坐标）
+    pub fn pixels(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        (self.y..self.y + self.height)
+            .flat_map(move |py| (self.x..self.x + self.width).map(move |px| (px, py)))
+            .filter(move |&(px, py)| self.get(px, py))
+    }
+}
 
 /// 检测结果结构
 /// 
@@ -58,43 +429,225 @@ pub struct Detection {
     /// 类别ID
     pub class_id: usize,
     /// 类别名称
-    pub class_name: String,
+    /// 
+    /// 内置的类别名称（如[PERSON_CLASS_LABEL](crate::config::PERSON_CLASS_LABEL)）以`Cow::Borrowed`保存，
+    /// 逐帧产生检测结果时不会为名称分配内存。
+    pub class_name: Cow<'static, str>,
     /// 置信度
     pub confidence: f32,
+    /// 姿态模型输出的关键点（COCO 17点顺序），非姿态模型为`None`
+    pub keypoints: Option<Vec<Keypoint>>,
+    /// 分割模型输出的实例掩码（原始图像坐标），非分割模型为`None`
+    pub mask: Option<Mask>,
+    /// 跟踪或聚类编号，模型直接输出的检测结果为`None`
+    /// 
+    /// [BoundsN::cluster_summary]的簇摘要在此记录簇内的检测数。
+    pub track_id: Option<usize>,
+    /// 产生该结果的原始输出张量行号（锚框/网格单元），用于与导出脚本的输出逐行对照
+    /// 
+    /// 只在启用[YoloDetectorN::with_debug_indices]（或[NmsOptions::record_source_index]）时记录，默认为`None`；
+    /// 未内置NMS的原始导出转置后，行号即锚点编号。
+    /// 
+    /// [NmsOptions::record_source_index]: crate::color::utils::NmsOptions::record_source_index
+    /// 
+    /// [YoloDetectorN::with_debug_indices]: crate::color::detect::YoloDetectorN::with_debug_indices
+    pub source_index: Option<usize>,
+    /// 模型预测的附加属性（如性别、年龄、姿态、颜色），没有附加属性时为`None`
+    /// 
+    /// 映射在第一次调用[with_attribute](Self::with_attribute)或[set_attribute](Self::set_attribute)时才分配。
+    pub attributes: Option<HashMap<String, String>>,
 }
 
 impl Detection {
     /// 创建一个新的检测结果
-    pub fn new(bbox: BoundingBox, class_id: usize, class_name: String, confidence: f32) -> Self {
-        Self { bbox, class_id, class_name, confidence }
+    /// 
+    /// `class_name`可以是`&'static str`（不分配内存）或`String`。
+    pub fn new(bbox: BoundingBox, class_id: usize, class_name: impl Into<Cow<'static, str>>, confidence: f32) -> Self {
+        Self { bbox, class_id, class_name: class_name.into(), confidence, keypoints: None, mask: None, track_id: None, source_index: None, attributes: None }
+    }
+    
+    /// 添加一个附加属性，已有同名属性时覆盖
+    pub fn with_attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set_attribute(key, value);
+        self
+    }
+    
+    /// 设置一个附加属性，已有同名属性时覆盖
+    pub fn set_attribute(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.attributes.get_or_insert_with(HashMap::new).insert(key.into(), value.into());
+    }
+    
+    /// 获取附加属性，不存在时返回`None`
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.as_ref()?.get(key).map(String::as_str)
+    }
+    
+    /// 计算与一组检测结果的最大IoU
+    /// 
+    /// # 返回值
+    /// 返回最大IoU及对应检测结果在`bounds`中的索引；`bounds`为空时返回`(0.0, None)`。
+    /// 与所有框的IoU都为0（例如全是零面积框）时返回`(0.0, None)`。
+    pub fn max_iou_with<const N: usize>(&self, bounds: &BoundsN<N>) -> (f32, Option<usize>) {
+        let mut best = (0.0, None);
+        for (index, other) in bounds.iter().enumerate() {
+            let iou = self.bbox.iou(&other.bbox);
+            if iou > best.0 {
+                best = (iou, Some(index));
+            }
+        }
+        best
     }
     
-    /// 创建一个默认的检测结果
-    pub fn default() -> Self {
-        Self { 
-            bbox: BoundingBox::default(), 
-            class_id: 0, 
-            class_name: String::new(), 
-            confidence: 0.0 
+    /// 检查是否与一组检测结果中的任意一个显著重叠
+    /// 
+    /// 一旦发现IoU超过`threshold`的检测结果即停止遍历并返回`true`。
+    pub fn has_significant_overlap_with<const N: usize>(&self, bounds: &BoundsN<N>, threshold: f32) -> bool {
+        bounds.iter().any(|other| self.bbox.iou(&other.bbox) > threshold)
+    }
+    
+    /// 转换为与分辨率无关的相对坐标（`[0.0, 1.0]`）
+    /// 
+    /// 边界框和关键点被归一化，掩码仍保留原始像素坐标。
+    pub fn to_relative_coords(&self, img_width: f32, img_height: f32) -> Self {
+        let mut detection = self.clone();
+        detection.bbox = self.bbox.normalize(img_width, img_height);
+        if let Some(keypoints) = detection.keypoints.as_mut() {
+            for keypoint in keypoints {
+                keypoint.x /= img_width;
+                keypoint.y /= img_height;
+            }
+        }
+        detection
+    }
+    
+    /// 由相对坐标还原为指定分辨率下的像素坐标，[to_relative_coords](Self::to_relative_coords)的逆操作
+    pub fn from_relative_coords(&self, img_width: f32, img_height: f32) -> Self {
+        let mut detection = self.clone();
+        detection.bbox = self.bbox.denormalize(img_width, img_height);
+        if let Some(keypoints) = detection.keypoints.as_mut() {
+            for keypoint in keypoints {
+                keypoint.x *= img_width;
+                keypoint.y *= img_height;
+            }
+        }
+        detection
+    }
+    
+    /// 将检测结果（边界框、关键点和掩码）映射到另一个坐标空间
+    /// 
+    /// 变换不可逆时掩码无法重采样，将被丢弃。
+    pub fn transform(&mut self, transform: &Affine2) {
+        self.bbox = self.bbox.transform(transform);
+        if let Some(keypoints) = self.keypoints.as_mut() {
+            for keypoint in keypoints {
+                (keypoint.x, keypoint.y) = transform.apply(keypoint.x, keypoint.y);
+            }
         }
+        self.mask = self.mask.as_ref().and_then(|mask| mask.transform(transform));
+    }
+    
+    /// 将两个重叠的检测结果合并为一个，例如重叠图块中同一目标的两次检测
+    /// 
+    /// 边界框为两者按置信度加权的平均（[iou_weighted_average_box]），置信度取较大值，
+    /// 其余字段（类别、关键点、掩码等）取自置信度较高者，置信度相同时取`a`。
+    /// 两者类别不同时原样返回置信度较高者的副本。
+    pub fn merge(a: &Detection, b: &Detection) -> Detection {
+        let (higher, lower) = if b.confidence > a.confidence { (b, a) } else { (a, b) };
+        let mut merged = higher.clone();
+        if a.class_id != b.class_id {
+            return merged;
+        }
+        merged.bbox = iou_weighted_average_box(&[&higher.bbox, &lower.bbox], &[higher.confidence, lower.confidence]);
+        merged
     }
 }
 
+impl std::fmt::Display for Detection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:.2} [x1={:.1}, y1={:.1}, x2={:.1}, y2={:.1}]",
+            self.class_name, self.confidence, self.bbox.x1, self.bbox.y1, self.bbox.x2, self.bbox.y2
+        )
+    }
+}
+
+/// 检测结果的坐标空间
+/// 
+/// 归一化时掩码保留原始图像像素坐标；转换到模型输入坐标时掩码随边界框一起重采样。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OutputSpace {
+    /// 原始图像的像素坐标
+    #[default]
+    OriginalPixels,
+    /// 相对于原始图像宽高归一化到`[0.0, 1.0]`的坐标，参见[Detection::to_relative_coords]
+    Normalized,
+    /// 模型输入（缩放后图像）的像素坐标
+    ModelInputPixels,
+}
+
+impl OutputSpace {
+    /// 供序列化使用的名称：`original_pixels`、`normalized`或`model_input_pixels`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputSpace::OriginalPixels => "original_pixels",
+            OutputSpace::Normalized => "normalized",
+            OutputSpace::ModelInputPixels => "model_input_pixels",
+        }
+    }
+}
+
+/// [Bounds]的`Display`输出最多列出的检测结果个数
+pub const BOUNDS_DISPLAY_LIMIT: usize = 10;
+
+/// [BoundsN::merge_overlapping]的最大合并轮数
+pub const MERGE_MAX_PASSES: usize = 10;
+
+/// [BoundsN::as_xyxy_slice]中每个检测结果占用的元素个数：`[x1, y1, x2, y2, confidence, class_id]`
+pub const FLAT_ROW_LEN: usize = 6;
+
+/// 默认容量（[DETECTIONS_CAPACITY]）的检测结果容器，参见[BoundsN]
+pub type Bounds = BoundsN<DETECTIONS_CAPACITY>;
+
 /// 固定容量的检测结果容器
 /// 
-/// 这是一个类似于Vec的容器，但具有固定的最大容量，避免了动态分配内存的开销。
+/// 这是一个类似于Vec的容器，但具有固定的最大容量`N`（编译期确定），避免了动态分配内存的开销。
 /// 它实现了常用的集合操作，如push、clear、len等，并支持迭代器。
-pub struct Bounds {
-    bounds: [Detection; DETECTIONS_CAPACITY],
+/// 
+/// 存储空间不做初始化，创建容器不会构造任何[Detection]；
+/// 只有`[0, len)`范围内的元素已初始化，移除元素时会立即释放它。
+/// 
+/// 通常使用默认容量的[Bounds]；需要其他容量时配合[YoloDetectorN]使用，例如`BoundsN<8>`。
+/// 
+/// [YoloDetectorN]: crate::color::detect::YoloDetectorN
+pub struct BoundsN<const N: usize> {
+    bounds: [MaybeUninit<Detection>; N],
     len: usize,
+    /// 本帧因超出处理预算而降级（限制候选框数量），或因输入图像无法检测而跳过
+    degraded: bool,
+    /// 检测结果的坐标空间
+    space: OutputSpace,
+    /// 产生本结果的输入帧序号
+    source_frame_seq: u64,
+    /// 产生本结果的原始图像尺寸`(宽, 高)`
+    source_dims: Option<(u32, u32)>,
+    /// [as_xyxy_slice](Self::as_xyxy_slice)的缓存，任何修改检测结果的操作都会将其清除
+    flat_buffer: OnceLock<Vec<f32>>,
 }
 
-impl Bounds {
+impl<const N: usize> BoundsN<N> {
     /// 创建一个新的空Bounds容器
     pub fn new() -> Self {
         Self {
-            bounds: std::array::from_fn(|_| Detection::default()),
+            bounds: [const { MaybeUninit::uninit() }; N],
             len: 0,
+            degraded: false,
+            space: OutputSpace::OriginalPixels,
+            source_frame_seq: 0,
+            source_dims: None,
+            flat_buffer: OnceLock::new(),
         }
     }
     
@@ -102,15 +655,29 @@ impl Bounds {
     /// 
     /// 如果容器已满，则不会添加新元素
     pub fn push(&mut self, detection: Detection) {
-        if self.len < DETECTIONS_CAPACITY {
-            self.bounds[self.len] = detection;
+        if self.len < N {
+            self.flat_buffer.take();
+            self.bounds[self.len].write(detection);
             self.len += 1;
         }
     }
     
-    /// 清空容器中的所有检测结果
+    /// 清空容器中的所有检测结果，同时清除降级标记、帧序号、图像尺寸并将坐标空间恢复为[OutputSpace::OriginalPixels]
     pub fn clear(&mut self) {
+        self.degraded = false;
+        self.space = OutputSpace::OriginalPixels;
+        self.source_frame_seq = 0;
+        self.source_dims = None;
+        let initialized = self.as_mut_slice() as *mut [Detection];
+        // 先将长度置0，即使某个元素的析构发生panic也不会再次释放
         self.len = 0;
+        // 安全性：析构前[0, len)范围内的元素均已初始化，且此后不再被视为已初始化
+        unsafe { ptr::drop_in_place(initialized) };
+    }
+    
+    /// 容器的最大容量
+    pub const fn capacity(&self) -> usize {
+        N
     }
     
     /// 返回容器中检测结果的数量
@@ -123,50 +690,152 @@ impl Bounds {
         self.len == 0
     }
     
+    /// 本帧是否降级：因超出处理预算只包含置信度最高的部分目标，或因输入图像无法检测而为空
+    /// 
+    /// 参见[Color::set_frame_budget](crate::color::core::Color::set_frame_budget)和
+    /// [YoloDetector::validate_image](crate::color::YoloDetector::validate_image)。
+    pub fn is_degraded(&self) -> bool {
+        self.degraded
+    }
+    
+    /// 设置降级标记
+    pub fn set_degraded(&mut self, degraded: bool) {
+        self.degraded = degraded;
+    }
+    
+    /// 检测结果的坐标空间
+    pub fn space(&self) -> OutputSpace {
+        self.space
+    }
+    
+    /// 只修改坐标空间标记，不转换坐标；需要转换时使用[convert_space](Self::convert_space)
+    pub fn set_space(&mut self, space: OutputSpace) {
+        self.space = space;
+    }
+    
+    /// 产生本结果的输入帧序号，见[Frame::seq](crate::color::Frame::seq)；未关联输入帧时为0
+    pub fn source_frame_seq(&self) -> u64 {
+        self.source_frame_seq
+    }
+    
+    /// 设置产生本结果的输入帧序号
+    pub fn set_source_frame_seq(&mut self, seq: u64) {
+        self.source_frame_seq = seq;
+    }
+    
+    /// 产生本结果的原始图像尺寸`(宽, 高)`，由检测流程填写；手动组装的结果为`None`
+    /// 
+    /// 用于发现把结果绘制到另一幅图像上的错误，见[draw_detections_checked](crate::color::utils::draw_detections_checked)。
+    pub fn source_dims(&self) -> Option<(u32, u32)> {
+        self.source_dims
+    }
+    
+    /// 设置产生本结果的原始图像尺寸
+    pub fn set_source_dims(&mut self, dims: Option<(u32, u32)>) {
+        self.source_dims = dims;
+    }
+    
+    /// 将所有检测结果从当前坐标空间转换到`space`
+    /// 
+    /// 先还原到原始图像像素坐标，再转换到目标空间。归一化使用
+    /// [Detection::to_relative_coords]/[Detection::from_relative_coords]，
+    /// 模型输入坐标使用`geometry`中的变换。
+    pub fn convert_space(&mut self, space: OutputSpace, geometry: &SpaceGeometry) {
+        if self.space == space {
+            return;
+        }
+        let (width, height) = (geometry.original_width, geometry.original_height);
+        let to_model = geometry.to_original.inverse().unwrap_or_default();
+        let current = self.space;
+        for detection in self.iter_mut() {
+            match current {
+                OutputSpace::OriginalPixels => {}
+                OutputSpace::Normalized => *detection = detection.from_relative_coords(width, height),
+                OutputSpace::ModelInputPixels => detection.transform(&geometry.to_original),
+            }
+            match space {
+                OutputSpace::OriginalPixels => {}
+                OutputSpace::Normalized => *detection = detection.to_relative_coords(width, height),
+                OutputSpace::ModelInputPixels => detection.transform(&to_model),
+            }
+        }
+        self.space = space;
+    }
+    
     /// 获取容器中所有检测结果的切片引用
     pub fn as_slice(&self) -> &[Detection] {
-        &self.bounds[..self.len]
+        // 安全性：[0, len)范围内的元素均已初始化，MaybeUninit<T>与T内存布局相同
+        unsafe { std::slice::from_raw_parts(self.bounds.as_ptr().cast::<Detection>(), self.len) }
     }
     
     /// 获取容器中所有检测结果的可变切片引用
     pub fn as_mut_slice(&mut self) -> &mut [Detection] {
-        &mut self.bounds[..self.len]
+        // 检测结果可能被修改，扁平缓存失效
+        self.flat_buffer.take();
+        // 安全性：同as_slice
+        unsafe { std::slice::from_raw_parts_mut(self.bounds.as_mut_ptr().cast::<Detection>(), self.len) }
+    }
+    
+    /// 将检测结果按行展开为扁平数组，每行依次为`[x1, y1, x2, y2]`、可选的置信度和可选的类别ID
+    /// 
+    /// 用于向OpenCV绑定或通过FFI向C/C++代码传递检测结果，类别ID以`f32`表示。
+    /// 
+    /// # 参数
+    /// * `include_confidence` - 每行是否包含置信度
+    /// * `include_class_id` - 每行是否包含类别ID
+    pub fn as_flat_f32(&self, include_confidence: bool, include_class_id: bool) -> Vec<f32> {
+        let row_len = 4 + include_confidence as usize + include_class_id as usize;
+        let mut flat = Vec::with_capacity(self.len * row_len);
+        for detection in self.iter() {
+            let bbox = &detection.bbox;
+            flat.extend_from_slice(&[bbox.x1, bbox.y1, bbox.x2, bbox.y2]);
+            if include_confidence {
+                flat.push(detection.confidence);
+            }
+            if include_class_id {
+                flat.push(detection.class_id as f32);
+            }
+        }
+        flat
+    }
+    
+    /// 按[FLAT_ROW_LEN]列`[x1, y1, x2, y2, confidence, class_id]`展开的扁平数组，长度为`len() * FLAT_ROW_LEN`
+    /// 
+    /// 首次调用时生成并缓存，检测结果未被修改时再次调用不会重新生成或分配。
+    pub fn as_xyxy_slice(&self) -> &[f32] {
+        self.flat_buffer.get_or_init(|| self.as_flat_f32(true, true))
+    }
+    
+    /// [as_xyxy_slice](Self::as_xyxy_slice)的首元素指针，供FFI使用
+    /// 
+    /// 指向`len() * FLAT_ROW_LEN`个连续的`f32`，按行排列，每行为`[x1, y1, x2, y2, confidence, class_id]`。
+    /// 
+    /// # Safety
+    /// 指针只在容器未被修改且未被释放期间有效：调用[push](Self::push)、[clear](Self::clear)、
+    /// [retain](Self::retain)或任何取得可变引用的方法后，缓存被释放，不得再通过该指针读取。
+    /// 容器为空时指针不可解引用。
+    pub unsafe fn as_flat_ptr(&self) -> *const f32 {
+        self.as_xyxy_slice().as_ptr()
     }
     
     /// 根据索引获取检测结果的引用
     pub fn get(&self, index: usize) -> Option<&Detection> {
-        if index < self.len {
-            Some(&self.bounds[index])
-        } else {
-            None
-        }
+        self.as_slice().get(index)
     }
     
     /// 根据索引获取检测结果的可变引用
     pub fn get_mut(&mut self, index: usize) -> Option<&mut Detection> {
-        if index < self.len {
-            Some(&mut self.bounds[index])
-        } else {
-            None
-        }
+        self.as_mut_slice().get_mut(index)
     }
     
     /// 获取第一个检测结果的引用
     pub fn first(&self) -> Option<&Detection> {
-        if self.len > 0 {
-            Some(&self.bounds[0])
-        } else {
-            None
-        }
+        self.as_slice().first()
     }
     
     /// 获取最后一个检测结果的引用
     pub fn last(&self) -> Option<&Detection> {
-        if self.len > 0 {
-            Some(&self.bounds[self.len - 1])
-        } else {
-            None
-        }
+        self.as_slice().last()
     }
     
     /// 对检测结果按置信度进行排序（降序）
@@ -194,28 +863,271 @@ impl Bounds {
         self.as_mut_slice().iter_mut()
     }
     
-    /// 保留满足条件的检测结果
+    /// 将所有检测结果转换为相对坐标，参见[Detection::to_relative_coords]
+    /// 
+    /// 降级标记、帧序号和图像尺寸保持不变，坐标空间设为[OutputSpace::Normalized]。
+    pub fn to_relative_coords(mut self, img_width: f32, img_height: f32) -> Self {
+        for detection in self.iter_mut() {
+            *detection = detection.to_relative_coords(img_width, img_height);
+        }
+        self.space = OutputSpace::Normalized;
+        self
+    }
+    
+    /// 将所有检测结果由相对坐标还原为像素坐标，参见[Detection::from_relative_coords]
+    /// 
+    /// 降级标记、帧序号和图像尺寸保持不变，坐标空间设为[OutputSpace::OriginalPixels]。
+    pub fn from_relative_coords(mut self, img_width: f32, img_height: f32) -> Self {
+        for detection in self.iter_mut() {
+            *detection = detection.from_relative_coords(img_width, img_height);
+        }
+        self.space = OutputSpace::OriginalPixels;
+        self
+    }
+    
+    /// 图像顺时针旋转90°后的检测结果，参见[BoundingBox::rotate90]
+    /// 
+    /// 旋转后图像宽高互换，[source_dims](Self::source_dims)随之交换。
+    pub fn rotate90(mut self, img_width: f32, img_height: f32) -> Self {
+        self.transform_all(&Affine2::rotate90(img_width, img_height));
+        self.source_dims = self.source_dims.map(|(width, height)| (height, width));
+        self
+    }
+    
+    /// 图像旋转180°后的检测结果
+    pub fn rotate180(mut self, img_width: f32, img_height: f32) -> Self {
+        self.transform_all(&Affine2::rotate180(img_width, img_height));
+        self
+    }
+    
+    /// 图像顺时针旋转270°后的检测结果
+    /// 
+    /// 旋转后图像宽高互换，[source_dims](Self::source_dims)随之交换。
+    pub fn rotate270(mut self, img_width: f32, img_height: f32) -> Self {
+        self.transform_all(&Affine2::rotate270(img_width, img_height));
+        self.source_dims = self.source_dims.map(|(width, height)| (height, width));
+        self
+    }
+    
+    /// 图像水平翻转后的检测结果
+    pub fn flip_horizontal(mut self, img_width: f32) -> Self {
+        self.transform_all(&Affine2::flip_horizontal(img_width));
+        self
+    }
+    
+    /// 图像垂直翻转后的检测结果
+    pub fn flip_vertical(mut self, img_height: f32) -> Self {
+        self.transform_all(&Affine2::flip_vertical(img_height));
+        self
+    }
+    
+    /// 将所有检测结果映射到另一个坐标空间，参见[Detection::transform]
+    pub fn transform_all(&mut self, transform: &Affine2) {
+        for detection in self.iter_mut() {
+            detection.transform(transform);
+        }
+    }
+    
+    /// 将重叠的检测结果聚类并合并为每簇一个检测结果（简化版加权框融合）
+    /// 
+    /// 按置信度从高到低依次处理，检测结果只有与某个簇内所有成员的IoU都不低于
+    /// `iou_threshold`且类别相同时才加入该簇。每簇输出一个检测结果：
+    /// 坐标为按置信度加权的平均值，置信度取簇内最大值，其余字段取自置信度最高的成员。
+    pub fn cluster_and_average(&self, iou_threshold: f32) -> Vec<Detection> {
+        let mut sorted: Vec<&Detection> = self.iter().collect();
+        sorted.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        
+        let mut clusters: Vec<Vec<&Detection>> = Vec::new();
+        for detection in sorted {
+            let cluster = clusters.iter_mut().find(|cluster| {
+                cluster[0].class_id == detection.class_id
+                    && cluster.iter().all(|member| member.bbox.iou(&detection.bbox) >= iou_threshold)
+            });
+            match cluster {
+                Some(cluster) => cluster.push(detection),
+                None => clusters.push(vec![detection]),
+            }
+        }
+        
+        clusters.into_iter().map(|cluster| {
+            let boxes: Vec<&BoundingBox> = cluster.iter().map(|d| &d.bbox).collect();
+            let weights: Vec<f32> = cluster.iter().map(|d| d.confidence).collect();
+            let mut merged = cluster[0].clone();
+            merged.bbox = iou_weighted_average_box(&boxes, &weights);
+            merged
+        }).collect()
+    }
+    
+    /// 反复用[Detection::merge]合并IoU超过`iou_threshold`的检测结果对
+    /// 
+    /// 每轮按顺序检查所有结果对，超过阈值的一对立即合并，合并结果继续参与本轮之后的比较；
+    /// 某一轮没有发生合并或达到[MERGE_MAX_PASSES]轮后停止。
+    /// 与[Detection::merge]一致，类别不同的重叠结果只保留置信度较高者。
+    pub fn merge_overlapping(&self, iou_threshold: f32) -> Self {
+        let mut detections: Vec<Detection> = self.iter().cloned().collect();
+        for _ in 0..MERGE_MAX_PASSES {
+            let mut merged_any = false;
+            let mut i = 0;
+            while i < detections.len() {
+                let mut j = i + 1;
+                while j < detections.len() {
+                    if detections[i].bbox.iou(&detections[j].bbox) > iou_threshold {
+                        let other = detections.remove(j);
+                        detections[i] = Detection::merge(&detections[i], &other);
+                        merged_any = true;
+                    } else {
+                        j += 1;
+                    }
+                }
+                i += 1;
+            }
+            if !merged_any {
+                break;
+            }
+        }
+        let mut bounds: Self = detections.into_iter().collect();
+        bounds.copy_metadata(self);
+        bounds
+    }
+    
+    /// 对容器内的检测结果执行非极大值抑制
+    /// 
+    /// 先按置信度从高到低排序，再按`options`指定的准则去除同一类别内的重复框，
+    /// 可用于合并多个来源的结果或对已有结果换用更严格的准则。
+    /// 坐标空间、降级标记、帧序号和图像尺寸保持不变。
+    pub fn nms(&mut self, options: &NmsOptions) {
+        let mut detections = self.take_detections();
+        detections.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        for detection in apply_nms_with_options(&mut detections, options) {
+            self.push(detection);
+        }
+    }
+    
+    /// 返回与区域有重叠（IoU大于0）的检测结果
+    /// 
+    /// 只在边界上接触、交集面积为0的框不计入。
+    pub fn overlap_with_region(&self, region: &BoundingBox) -> Self {
+        self.filtered(|d| d.bbox.iou(region) > 0.0)
+    }
+    
+    /// 返回完全位于区域内的检测结果，与区域边界重合的框也计入
+    pub fn fully_within_region(&self, region: &BoundingBox) -> Self {
+        self.filtered(|d| region.contains_bbox(&d.bbox))
+    }
+    
+    /// 返回中心点位于区域内的检测结果，中心点落在区域边界上也计入
+    pub fn center_within_region(&self, region: &BoundingBox) -> Self {
+        self.filtered(|d| {
+            let (cx, cy) = d.bbox.center();
+            region.contains_point(cx, cy)
+        })
+    }
+    
+    /// 去除重复的检测结果，规则见[dedup_detections]；坐标空间等元数据保持不变
+    pub fn dedup(&mut self, eps: f32) {
+        let detections = self.take_detections();
+        for detection in dedup_detections(detections, eps) {
+            self.push(detection);
+        }
+    }
+    
+    /// 复制满足条件的检测结果及全部元数据
+    fn filtered<F: FnMut(&Detection) -> bool>(&self, mut predicate: F) -> Self {
+        let mut bounds: Self = self.iter().filter(|d| predicate(d)).cloned().collect();
+        bounds.copy_metadata(self);
+        bounds
+    }
+    
+    /// 取出全部检测结果，保留降级标记、坐标空间、帧序号和图像尺寸
+    fn take_detections(&mut self) -> Vec<Detection> {
+        let taken = std::mem::take(self);
+        self.copy_metadata(&taken);
+        taken.into_iter().collect()
+    }
+    
+    /// 从另一个容器复制降级标记、坐标空间、帧序号和图像尺寸
+    fn copy_metadata(&mut self, other: &Self) {
+        self.degraded = other.degraded;
+        self.space = other.space;
+        self.source_frame_seq = other.source_frame_seq;
+        self.source_dims = other.source_dims;
+    }
+    
+    /// 用DBSCAN把检测结果聚为人群团，每团输出一个摘要检测结果
+    /// 
+    /// 每个簇输出一个检测结果：边界框为簇内所有框的外接矩形，置信度取簇内最大值，
+    /// 类别取自置信度最高的成员，[track_id](Detection::track_id)记录簇内检测数。
+    /// 未归入任何簇的框原样输出（`track_id`为`Some(1)`），排在所有簇之后。
+    /// 
+    /// # 参数
+    /// * `eps_pixels` - 中心点邻域半径（像素），见[dbscan_cluster]
+    /// * `min_samples` - 成为核心点所需的最少邻居数（含自身）
+    pub fn cluster_summary(&self, eps_pixels: f32, min_samples: usize) -> Vec<Detection> {
+        let detections = self.as_slice();
+        let clusters = dbscan_cluster(self, eps_pixels, min_samples);
+        let mut clustered = vec![false; detections.len()];
+        
+        let mut summaries: Vec<Detection> = clusters.iter().map(|members| {
+            let best = members.iter()
+                .map(|&i| &detections[i])
+                .max_by(|a, b| a.confidence.total_cmp(&b.confidence))
+                .expect("簇非空");
+            let bbox = members.iter().fold(best.bbox, |acc, &i| {
+                clustered[i] = true;
+                let b = &detections[i].bbox;
+                BoundingBox::new(acc.x1.min(b.x1), acc.y1.min(b.y1), acc.x2.max(b.x2), acc.y2.max(b.y2))
+            });
+            Detection {
+                track_id: Some(members.len()),
+                ..Detection::new(bbox, best.class_id, best.class_name.clone(), best.confidence)
+            }
+        }).collect();
+        
+        summaries.extend(detections.iter().zip(&clustered).filter(|(_, c)| !**c).map(|(d, _)| Detection {
+            track_id: Some(1),
+            ..d.clone()
+        }));
+        summaries
+    }
+    
+    /// 单行摘要，包含目标数量和最高置信度
+    pub fn summary(&self) -> String {
+        match self.iter().map(|d| d.confidence).max_by(|a, b| a.total_cmp(b)) {
+            Some(max_confidence) => format!("{}个目标, 最高置信度: {:.2}", self.len, max_confidence),
+            None => "0个目标".to_string(),
+        }
+    }
+    
+    /// 保留满足条件的检测结果，保持剩余元素的相对顺序
     pub fn retain<F>(&mut self, mut f: F) 
     where 
         F: FnMut(&Detection) -> bool,
     {
-        let mut i = 0;
-        while i < self.len {
-            if !f(&self.bounds[i]) {
-                // 移动后续元素
-                for j in i..(self.len - 1) {
-                    self.bounds[j] = self.bounds[j + 1].clone();
+        self.flat_buffer.take();
+        let len = self.len;
+        let base = self.bounds.as_mut_ptr().cast::<Detection>();
+        // 处理过程中len只覆盖已整理好的前缀，f发生panic时其余元素被泄漏而不会被重复释放
+        self.len = 0;
+        let mut kept = 0;
+        for i in 0..len {
+            // 安全性：i位于原[0, len)范围内且尚未被移动或释放
+            unsafe {
+                if f(&*base.add(i)) {
+                    if i != kept {
+                        ptr::copy_nonoverlapping(base.add(i), base.add(kept), 1);
+                    }
+                    kept += 1;
+                    self.len = kept;
+                } else {
+                    ptr::drop_in_place(base.add(i));
                 }
-                self.len -= 1;
-            } else {
-                i += 1;
             }
         }
     }
 }
 
 // 实现只读迭代器支持
-impl<'a> IntoIterator for &'a Bounds {
+impl<'a, const N: usize> IntoIterator for &'a BoundsN<N> {
     type Item = &'a Detection;
     type IntoIter = std::slice::Iter<'a, Detection>;
     
@@ -225,7 +1137,7 @@ impl<'a> IntoIterator for &'a Bounds {
 }
 
 // 实现可变迭代器支持
-impl<'a> IntoIterator for &'a mut Bounds {
+impl<'a, const N: usize> IntoIterator for &'a mut BoundsN<N> {
     type Item = &'a mut Detection;
     type IntoIter = std::slice::IterMut<'a, Detection>;
     
@@ -234,19 +1146,635 @@ impl<'a> IntoIterator for &'a mut Bounds {
     }
 }
 
+/// [Bounds]的所有权迭代器
+/// 
+/// 逐个取出检测结果，迭代器被丢弃时释放尚未取出的元素。
+pub struct BoundsIntoIter<const N: usize = DETECTIONS_CAPACITY> {
+    bounds: [MaybeUninit<Detection>; N],
+    index: usize,
+    len: usize,
+}
+
+impl<const N: usize> Iterator for BoundsIntoIter<N> {
+    type Item = Detection;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.len {
+            return None;
+        }
+        // 安全性：[index, len)范围内的元素已初始化，取出后index前移，不会被再次读取
+        let detection = unsafe { self.bounds[self.index].assume_init_read() };
+        self.index += 1;
+        Some(detection)
+    }
+    
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<const N: usize> ExactSizeIterator for BoundsIntoIter<N> {}
+
+impl<const N: usize> Drop for BoundsIntoIter<N> {
+    fn drop(&mut self) {
+        let remaining = &mut self.bounds[self.index..self.len];
+        // 安全性：尚未取出的元素均已初始化
+        unsafe { ptr::drop_in_place(remaining as *mut [MaybeUninit<Detection>] as *mut [Detection]) };
+    }
+}
+
+impl<const N: usize> IntoIterator for BoundsN<N> {
+    type Item = Detection;
+    type IntoIter = BoundsIntoIter<N>;
+    
+    fn into_iter(self) -> Self::IntoIter {
+        // 元素的所有权整体转移给迭代器，原容器不再执行析构，扁平缓存需单独释放
+        let mut bounds = ManuallyDrop::new(self);
+        bounds.flat_buffer.take();
+        BoundsIntoIter {
+            // 安全性：bounds不会再被使用或析构，存储空间只被读取一次
+            bounds: unsafe { ptr::read(&bounds.bounds) },
+            index: 0,
+            len: bounds.len,
+        }
+    }
+}
+
+// 超出容量的元素会被丢弃，与push行为一致
+impl<const N: usize> FromIterator<Detection> for BoundsN<N> {
+    fn from_iter<I: IntoIterator<Item = Detection>>(iter: I) -> Self {
+        let mut bounds = Self::new();
+        for detection in iter.into_iter().take(N) {
+            bounds.push(detection);
+        }
+        bounds
+    }
+}
+
+impl<const N: usize> Drop for BoundsN<N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<const N: usize> Clone for BoundsN<N> {
+    fn clone(&self) -> Self {
+        let mut bounds: Self = self.iter().cloned().collect();
+        bounds.copy_metadata(self);
+        bounds
+    }
+}
+
 // 实现默认trait
-impl Default for Bounds {
+impl<const N: usize> Default for BoundsN<N> {
     fn default() -> Self {
         Self::new()
     }
 }
 
+// 列出前BOUNDS_DISPLAY_LIMIT个检测结果，其余只显示数量
+impl<const N: usize> std::fmt::Display for BoundsN<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "共{}个目标", self.len)?;
+        for detection in self.iter().take(BOUNDS_DISPLAY_LIMIT) {
+            write!(f, "\n  {}", detection)?;
+        }
+        if self.len > BOUNDS_DISPLAY_LIMIT {
+            write!(f, "\n  … 及其余{}个", self.len - BOUNDS_DISPLAY_LIMIT)?;
+        }
+        Ok(())
+    }
+}
+
 // 实现Debug trait
-impl std::fmt::Debug for Bounds {
+impl<const N: usize> std::fmt::Debug for BoundsN<N> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Bounds")
             .field("len", &self.len)
+            .field("degraded", &self.degraded)
+            .field("space", &self.space)
+            .field("source_frame_seq", &self.source_frame_seq)
+            .field("source_dims", &self.source_dims)
             .field("bounds", &self.as_slice())
             .finish()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::image::ScaleMessage;
+
+    fn detection(x1: f32, y1: f32, x2: f32, y2: f32, confidence: f32) -> Detection {
+        Detection::new(BoundingBox::new(x1, y1, x2, y2), 0, "person", confidence)
+    }
+
+    /// 1280x720的图像letterbox到640x640：缩放0.5，上下各填充140
+    fn geometry() -> SpaceGeometry {
+        let message = ScaleMessage::builder()
+            .original_size(1280, 720)
+            .scaled_size(640, 360)
+            .padding(0, 140)
+            .build();
+        SpaceGeometry::from_message(&message)
+    }
+
+    /// 同一组原始图像坐标的模拟输出，含一个重叠框和一个完全重复的框
+    fn pixel_bounds() -> Bounds {
+        let mut bounds: Bounds = [
+            detection(100.0, 200.0, 300.0, 600.0, 0.9),
+            detection(110.0, 210.0, 310.0, 610.0, 0.6),
+            detection(800.0, 100.0, 1000.0, 500.0, 0.8),
+            detection(800.0, 100.0, 1000.0, 500.0, 0.8),
+        ].into_iter().collect();
+        bounds.set_degraded(true);
+        bounds.set_source_frame_seq(7);
+        bounds.set_source_dims(Some((1280, 720)));
+        bounds
+    }
+
+    fn assert_close(a: &BoundingBox, b: &BoundingBox) {
+        for (x, y) in [(a.x1, b.x1), (a.y1, b.y1), (a.x2, b.x2), (a.y2, b.y2)] {
+            assert!((x - y).abs() < 1e-3, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn nms_and_dedup_keep_metadata() {
+        let mut bounds = pixel_bounds();
+        bounds.set_space(OutputSpace::Normalized);
+        bounds.nms(&NmsOptions::new(0.5));
+        assert_eq!(bounds.len(), 2);
+        bounds.dedup(1e-3);
+        let filtered = bounds.overlap_with_region(&BoundingBox::new(0.0, 0.0, 400.0, 720.0));
+        for bounds in [&bounds, &filtered] {
+            assert_eq!(bounds.space(), OutputSpace::Normalized);
+            assert!(bounds.is_degraded());
+            assert_eq!(bounds.source_frame_seq(), 7);
+            assert_eq!(bounds.source_dims(), Some((1280, 720)));
+        }
+        assert_eq!(filtered.len(), 1);
+    }
+
+    #[test]
+    fn all_output_spaces_agree_after_conversion() {
+        let geometry = geometry();
+        let expected = pixel_bounds();
+        let expected = expected.as_slice();
+        let mut per_space = Vec::new();
+        for space in [OutputSpace::OriginalPixels, OutputSpace::Normalized, OutputSpace::ModelInputPixels] {
+            let mut bounds = pixel_bounds();
+            bounds.convert_space(space, &geometry);
+            bounds.nms(&NmsOptions::new(0.5));
+            bounds.dedup(1e-3);
+            assert_eq!(bounds.space(), space);
+            per_space.push(bounds);
+        }
+
+        let normalized = &per_space[1].as_slice()[0].bbox;
+        assert_close(normalized, &BoundingBox::new(100.0 / 1280.0, 200.0 / 720.0, 300.0 / 1280.0, 600.0 / 720.0));
+        let model = &per_space[2].as_slice()[0].bbox;
+        assert_close(model, &BoundingBox::new(50.0, 240.0, 150.0, 440.0));
+
+        for mut bounds in per_space {
+            bounds.convert_space(OutputSpace::OriginalPixels, &geometry);
+            assert_eq!(bounds.len(), 2);
+            assert_close(&bounds.as_slice()[0].bbox, &expected[0].bbox);
+            assert_close(&bounds.as_slice()[1].bbox, &expected[2].bbox);
+        }
+    }
+
+    #[test]
+    fn small_capacity_drops_detections_beyond_n() {
+        let mut bounds = BoundsN::<4>::new();
+        assert_eq!(bounds.capacity(), 4);
+        for (i, confidence) in [0.9, 0.8, 0.7, 0.6, 0.5, 0.4].into_iter().enumerate() {
+            bounds.push(detection(i as f32 * 20.0, 0.0, i as f32 * 20.0 + 10.0, 10.0, confidence));
+        }
+        // 超出容量的结果被丢弃，已有结果不受影响
+        assert_eq!(bounds.len(), 4);
+        let confidences: Vec<f32> = bounds.iter().map(|d| d.confidence).collect();
+        assert_eq!(confidences, [0.9, 0.8, 0.7, 0.6]);
+
+        let collected: BoundsN<4> = (0..6).map(|i| detection(i as f32, 0.0, i as f32 + 1.0, 1.0, 0.5)).collect();
+        assert_eq!(collected.len(), 4);
+        assert_eq!(collected.clone().into_iter().count(), 4);
+    }
+
+    #[test]
+    fn merge_averages_coordinates_by_confidence() {
+        let a = Detection::new(BoundingBox::new(0.0, 0.0, 100.0, 100.0), 0, "person", 0.6);
+        let b = Detection::new(BoundingBox::new(20.0, 10.0, 120.0, 110.0), 0, "person", 0.2).with_attribute("tile", "b");
+        // 权重0.6和0.2：x1 = (0.6 * 0 + 0.2 * 20) / 0.8 = 5
+        let expected = BoundingBox::new(5.0, 2.5, 105.0, 102.5);
+        for merged in [Detection::merge(&a, &b), Detection::merge(&b, &a)] {
+            assert_close(&merged.bbox, &expected);
+            assert_eq!(merged.confidence, 0.6);
+            // 其余字段取自置信度较高者
+            assert_eq!(merged.attribute("tile"), None);
+        }
+
+        // 置信度相同时为算术平均，其余字段取自a
+        let c = Detection::new(BoundingBox::new(20.0, 10.0, 120.0, 110.0), 0, "person", 0.6).with_attribute("tile", "c");
+        let merged = Detection::merge(&c, &a);
+        assert_close(&merged.bbox, &BoundingBox::new(10.0, 5.0, 110.0, 105.0));
+        assert_eq!(merged.attribute("tile"), Some("c"));
+    }
+
+    #[test]
+    fn merge_keeps_higher_confidence_across_classes() {
+        let person = Detection::new(BoundingBox::new(0.0, 0.0, 100.0, 100.0), 0, "person", 0.4);
+        let car = Detection::new(BoundingBox::new(10.0, 10.0, 110.0, 110.0), 2, "car", 0.7);
+        let merged = Detection::merge(&person, &car);
+        assert_eq!((merged.class_id, merged.confidence), (2, 0.7));
+        assert_close(&merged.bbox, &car.bbox);
+    }
+
+    #[test]
+    fn merge_overlapping_combines_tile_duplicates() {
+        // 相邻图块对同一目标的两次检测，外加一个不相关的目标
+        let mut bounds: Bounds = [
+            detection(100.0, 100.0, 200.0, 300.0, 0.9),
+            detection(800.0, 100.0, 900.0, 200.0, 0.5),
+            detection(110.0, 100.0, 210.0, 300.0, 0.3),
+        ].into_iter().collect();
+        bounds.set_source_frame_seq(3);
+        let merged = bounds.merge_overlapping(0.5);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged.source_frame_seq(), 3);
+        let first = merged.iter().next().unwrap();
+        // x1 = (0.9 * 100 + 0.3 * 110) / 1.2 = 102.5
+        assert_close(&first.bbox, &BoundingBox::new(102.5, 100.0, 202.5, 300.0));
+        assert_eq!(first.confidence, 0.9);
+        // 阈值高于IoU时不合并
+        assert_eq!(bounds.merge_overlapping(0.95).len(), 3);
+    }
+
+    #[test]
+    fn dedup_removes_exact_duplicates_keeping_higher_confidence() {
+        let detections = vec![
+            detection(10.0, 10.0, 50.0, 50.0, 0.6),
+            detection(200.0, 10.0, 250.0, 50.0, 0.7),
+            detection(10.0, 10.0, 50.0, 50.0, 0.9),
+            detection(10.0, 10.0, 50.0, 50.0, 0.3),
+        ];
+        let kept = dedup_detections(detections, 0.0);
+        let confidences: Vec<f32> = kept.iter().map(|d| d.confidence).collect();
+        // 未被去除的检测结果保持原有顺序
+        assert_eq!(confidences, [0.7, 0.9]);
+    }
+
+    #[test]
+    fn dedup_compares_every_coordinate_against_eps() {
+        let base = detection(100.0, 100.0, 200.0, 200.0, 0.9);
+        // 每个坐标分别落在eps之内或之外
+        let inside = [
+            detection(101.5, 100.0, 200.0, 200.0, 0.5),
+            detection(100.0, 98.5, 200.0, 200.0, 0.5),
+            detection(100.0, 100.0, 201.5, 200.0, 0.5),
+            detection(100.0, 100.0, 200.0, 201.5, 0.5),
+        ];
+        let outside = [
+            detection(102.5, 100.0, 200.0, 200.0, 0.5),
+            detection(100.0, 97.5, 200.0, 200.0, 0.5),
+            detection(100.0, 100.0, 202.5, 200.0, 0.5),
+            detection(100.0, 100.0, 200.0, 202.5, 0.5),
+        ];
+        for near in inside {
+            let kept = dedup_detections(vec![near.clone(), base.clone()], 2.0);
+            assert_eq!(kept.len(), 1, "{:?}", near.bbox);
+            assert_eq!((kept[0].bbox, kept[0].confidence), (base.bbox, base.confidence));
+        }
+        for far in outside {
+            assert_eq!(dedup_detections(vec![base.clone(), far.clone()], 2.0).len(), 2, "{:?}", far.bbox);
+        }
+    }
+
+    #[test]
+    fn dedup_keeps_same_box_with_different_class() {
+        let person = detection(10.0, 10.0, 50.0, 50.0, 0.9);
+        let car = Detection::new(person.bbox, 2, "car", 0.8);
+        let mut bounds: Bounds = [person.clone(), car.clone(), person.clone()].into_iter().collect();
+        bounds.dedup(0.0);
+        let classes: Vec<usize> = bounds.iter().map(|d| d.class_id).collect();
+        assert_eq!(classes, [0, 2]);
+        assert_eq!(dedup_detections(vec![person, car], 5.0).len(), 2);
+    }
+
+    #[test]
+    fn max_iou_with_picks_best_matching_index() {
+        let probe = detection(0.0, 0.0, 10.0, 10.0, 0.9);
+        let bounds: Bounds = [
+            detection(50.0, 50.0, 60.0, 60.0, 0.9),
+            detection(5.0, 0.0, 15.0, 10.0, 0.9),
+            detection(1.0, 0.0, 11.0, 10.0, 0.9),
+        ].into_iter().collect();
+        let (iou, index) = probe.max_iou_with(&bounds);
+        assert_eq!(index, Some(2));
+        assert!((iou - 90.0 / 110.0).abs() < 1e-6, "{}", iou);
+        assert!(probe.has_significant_overlap_with(&bounds, 0.5));
+        assert!(!probe.has_significant_overlap_with(&bounds, 0.9));
+
+        assert_eq!(probe.max_iou_with(&Bounds::new()), (0.0, None));
+        assert!(!probe.has_significant_overlap_with(&Bounds::new(), 0.0));
+    }
+
+    #[test]
+    fn max_iou_with_zero_area_boxes_finds_no_match() {
+        let probe = detection(0.0, 0.0, 10.0, 10.0, 0.9);
+        // 点状和线状的零面积框，包括位于探测框内部的
+        let degenerate: Bounds = [
+            detection(5.0, 5.0, 5.0, 5.0, 0.9),
+            detection(0.0, 5.0, 10.0, 5.0, 0.9),
+            detection(20.0, 20.0, 20.0, 20.0, 0.9),
+        ].into_iter().collect();
+        assert_eq!(probe.max_iou_with(&degenerate), (0.0, None));
+        assert!(!probe.has_significant_overlap_with(&degenerate, 0.0));
+
+        // 探测框本身为零面积时同样没有匹配
+        let point = detection(5.0, 5.0, 5.0, 5.0, 0.9);
+        assert_eq!(point.max_iou_with(&degenerate), (0.0, None));
+    }
+
+    #[test]
+    fn owned_into_iter_round_trips_through_collect() {
+        let original: Bounds = (0..5)
+            .map(|i| Detection::new(BoundingBox::new(i as f32, 0.0, i as f32 + 10.0, 10.0), i, format!("class{}", i), 0.5 + i as f32 * 0.1))
+            .collect();
+        let expected: Vec<(usize, String, f32)> = original.iter().map(|d| (d.class_id, d.class_name.to_string(), d.bbox.x1)).collect();
+
+        let round_trip: Bounds = original.into_iter().collect();
+        let actual: Vec<(usize, String, f32)> = round_trip.iter().map(|d| (d.class_id, d.class_name.to_string(), d.bbox.x1)).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn owned_into_iter_reports_exact_remaining_size() {
+        let bounds: Bounds = (0..3).map(|i| detection(i as f32, 0.0, 10.0, 10.0, 0.9)).collect();
+        let mut iter = bounds.into_iter();
+        assert_eq!(iter.size_hint(), (3, Some(3)));
+        let first = iter.next().unwrap();
+        assert_eq!(first.bbox.x1, 0.0);
+        assert_eq!((iter.size_hint(), iter.len()), ((2, Some(2)), 2));
+        // 未取完就丢弃迭代器时剩余元素被正常释放
+        drop(iter);
+
+        let mut iter = Bounds::new().into_iter();
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn from_iter_truncates_at_capacity() {
+        let small: BoundsN<4> = (0..10).map(|i| detection(i as f32, 0.0, 10.0, 10.0, 0.9)).collect();
+        assert_eq!(small.len(), 4);
+        let x1: Vec<f32> = small.into_iter().map(|d| d.bbox.x1).collect();
+        assert_eq!(x1, [0.0, 1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn detection_display_snapshot() {
+        let detection = Detection::new(BoundingBox::new(12.345, 6.0, 100.0, 200.25), 0, "person", 0.8765);
+        assert_eq!(detection.to_string(), "person 0.88 [x1=12.3, y1=6.0, x2=100.0, y2=200.2]");
+    }
+
+    #[test]
+    fn bounds_display_snapshot() {
+        let mut bounds: Bounds = [
+            detection(0.0, 0.0, 10.0, 20.0, 0.9),
+            Detection::new(BoundingBox::new(30.0, 40.0, 50.0, 60.0), 2, "car", 0.5),
+        ].into_iter().collect();
+        assert_eq!(bounds.to_string(), "共2个目标\n  person 0.90 [x1=0.0, y1=0.0, x2=10.0, y2=20.0]\n  car 0.50 [x1=30.0, y1=40.0, x2=50.0, y2=60.0]");
+        assert_eq!(bounds.summary(), "2个目标, 最高置信度: 0.90");
+
+        bounds.clear();
+        assert_eq!(bounds.to_string(), "共0个目标");
+        assert_eq!(bounds.summary(), "0个目标");
+    }
+
+    #[test]
+    fn bounds_display_truncates_after_limit() {
+        let bounds: Bounds = (0..BOUNDS_DISPLAY_LIMIT + 3)
+            .map(|i| detection(i as f32, 0.0, i as f32 + 1.0, 1.0, 0.5))
+            .collect();
+        let text = bounds.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 1 + BOUNDS_DISPLAY_LIMIT + 1);
+        assert_eq!(lines[0], format!("共{}个目标", BOUNDS_DISPLAY_LIMIT + 3));
+        assert_eq!(lines[BOUNDS_DISPLAY_LIMIT], "  person 0.50 [x1=9.0, y1=0.0, x2=10.0, y2=1.0]");
+        assert_eq!(lines[BOUNDS_DISPLAY_LIMIT + 1], "  … 及其余3个");
+    }
+
+    fn corners(bbox: &BoundingBox) -> (f32, f32, f32, f32) {
+        (bbox.x1, bbox.y1, bbox.x2, bbox.y2)
+    }
+
+    #[test]
+    fn rotate90_maps_corners_clockwise() {
+        // 640x480的图像顺时针旋转90°后为480x640
+        let bbox = BoundingBox::new(100.0, 50.0, 300.0, 150.0);
+        let rotated = bbox.rotate90(640.0, 480.0);
+        // 新x1 = 原图高 - 原y2，新y1 = 原x1
+        assert_eq!(corners(&rotated), (330.0, 100.0, 430.0, 300.0));
+        assert_eq!(corners(&bbox.rotate270(640.0, 480.0)), (50.0, 340.0, 150.0, 540.0));
+        assert_eq!(corners(&bbox.rotate180(640.0, 480.0)), (340.0, 330.0, 540.0, 430.0));
+        assert_eq!(corners(&bbox.flip_horizontal(640.0)), (340.0, 50.0, 540.0, 150.0));
+        assert_eq!(corners(&bbox.flip_vertical(480.0)), (100.0, 330.0, 300.0, 430.0));
+    }
+
+    #[test]
+    fn four_rotations_reproduce_original_box() {
+        let bbox = BoundingBox::new(100.0, 50.0, 300.0, 150.0);
+        let (mut width, mut height) = (640.0, 480.0);
+        let mut rotated = bbox;
+        for _ in 0..4 {
+            rotated = rotated.rotate90(width, height);
+            assert!(rotated.x1 < rotated.x2 && rotated.y1 < rotated.y2, "{:?}", rotated);
+            // 每次旋转后图像宽高互换
+            (width, height) = (height, width);
+        }
+        assert_eq!(rotated, bbox);
+        assert_eq!(bbox.rotate90(640.0, 480.0).rotate270(480.0, 640.0), bbox);
+        assert_eq!(bbox.rotate180(640.0, 480.0).rotate180(640.0, 480.0), bbox);
+        assert_eq!(bbox.flip_horizontal(640.0).flip_horizontal(640.0), bbox);
+        assert_eq!(bbox.flip_vertical(480.0).flip_vertical(480.0), bbox);
+    }
+
+    #[test]
+    fn bounds_rotations_apply_to_every_detection() {
+        let mut bounds: Bounds = [detection(100.0, 50.0, 300.0, 150.0, 0.9), detection(0.0, 0.0, 640.0, 480.0, 0.5)].into_iter().collect();
+        bounds.set_source_dims(Some((640, 480)));
+        let original: Vec<_> = bounds.iter().map(|d| corners(&d.bbox)).collect();
+        let rotated = bounds.rotate90(640.0, 480.0);
+        assert_eq!(corners(&rotated.as_slice()[1].bbox), (0.0, 0.0, 480.0, 640.0));
+        assert_eq!(rotated.source_dims(), Some((480, 640)));
+        let restored = rotated.rotate90(480.0, 640.0).rotate90(640.0, 480.0).rotate90(480.0, 640.0);
+        assert_eq!(restored.iter().map(|d| corners(&d.bbox)).collect::<Vec<_>>(), original);
+        assert_eq!(restored.source_dims(), Some((640, 480)));
+        let mirrored = restored.flip_horizontal(640.0).flip_vertical(480.0).rotate180(640.0, 480.0);
+        assert_eq!(mirrored.iter().map(|d| corners(&d.bbox)).collect::<Vec<_>>(), original);
+        assert_eq!(mirrored.source_dims(), Some((640, 480)));
+    }
+
+    #[test]
+    fn rotated_bounds_draw_on_rotated_image() {
+        let mut bounds: Bounds = [detection(100.0, 50.0, 300.0, 150.0, 0.9)].into_iter().collect();
+        bounds.set_source_dims(Some((640, 480)));
+        let image = image::DynamicImage::new_rgb8(640, 480);
+        let draw = crate::color::utils::draw_detections_checked::<DETECTIONS_CAPACITY>;
+
+        let clockwise = bounds.clone().rotate90(640.0, 480.0);
+        assert!(draw(&image.rotate90(), &clockwise).is_ok());
+        let counter_clockwise = bounds.clone().rotate270(640.0, 480.0);
+        assert_eq!(counter_clockwise.source_dims(), Some((480, 640)));
+        assert!(draw(&image.rotate270(), &counter_clockwise).is_ok());
+        assert!(matches!(draw(&image, &counter_clockwise), Err(crate::error::PerpleError::ShapeMismatch { .. })));
+        assert!(draw(&image.rotate180(), &bounds.rotate180(640.0, 480.0)).is_ok());
+    }
+
+    /// 区域`(100, 100)-(200, 200)`及其周围的检测结果，置信度用作编号
+    fn region_scene() -> (BoundingBox, Bounds) {
+        let mut bounds: Bounds = [
+            // 完全在内部
+            detection(120.0, 120.0, 180.0, 180.0, 0.1),
+            // 在外侧与右边界重合，交集面积为0
+            detection(200.0, 120.0, 250.0, 180.0, 0.2),
+            // 部分重叠，中心在区域外
+            detection(180.0, 180.0, 240.0, 240.0, 0.3),
+            // 上、左、右边与区域边界重合
+            detection(100.0, 100.0, 200.0, 150.0, 0.4),
+            // 中心恰好落在区域右下角
+            detection(150.0, 180.0, 250.0, 220.0, 0.5),
+            // 远离区域
+            detection(300.0, 300.0, 310.0, 310.0, 0.6),
+            // 包住整个区域
+            detection(50.0, 50.0, 250.0, 250.0, 0.7),
+            // 区域内面积为0的框
+            detection(150.0, 150.0, 150.0, 150.0, 0.8),
+        ].into_iter().collect();
+        bounds.set_source_frame_seq(9);
+        (BoundingBox::new(100.0, 100.0, 200.0, 200.0), bounds)
+    }
+
+    fn confidences(bounds: &Bounds) -> Vec<f32> {
+        bounds.iter().map(|d| d.confidence).collect()
+    }
+
+    #[test]
+    fn overlap_with_region_requires_positive_intersection() {
+        let (region, bounds) = region_scene();
+        let overlapping = bounds.overlap_with_region(&region);
+        assert_eq!(confidences(&overlapping), [0.1, 0.3, 0.4, 0.5, 0.7]);
+        assert_eq!(overlapping.source_frame_seq(), 9);
+        assert_eq!(bounds.len(), 8);
+        // 角点颠倒的区域不与任何框重叠
+        assert!(bounds.overlap_with_region(&BoundingBox::new(200.0, 200.0, 100.0, 100.0)).is_empty());
+    }
+
+    #[test]
+    fn fully_within_region_includes_boundary() {
+        let (region, bounds) = region_scene();
+        assert_eq!(confidences(&bounds.fully_within_region(&region)), [0.1, 0.4, 0.8]);
+        // 区域本身视为位于区域内
+        assert!(region.contains_bbox(&region));
+        assert!(!region.contains_bbox(&BoundingBox::new(100.0, 100.0, 200.001, 200.0)));
+    }
+
+    #[test]
+    fn center_within_region_includes_boundary() {
+        let (region, bounds) = region_scene();
+        let centered = bounds.center_within_region(&region);
+        assert_eq!(confidences(&centered), [0.1, 0.4, 0.5, 0.7, 0.8]);
+        assert_eq!(centered.source_frame_seq(), 9);
+        assert_eq!(confidences(&bounds), [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8]);
+    }
+
+    #[test]
+    fn relative_coords_keep_metadata_and_track_space() {
+        let mut bounds: Bounds = [detection(64.0, 48.0, 320.0, 240.0, 0.9)].into_iter().collect();
+        bounds.set_degraded(true);
+        bounds.set_source_frame_seq(42);
+        bounds.set_source_dims(Some((640, 480)));
+
+        let relative = bounds.to_relative_coords(640.0, 480.0);
+        assert_eq!(relative.first().unwrap().bbox, BoundingBox::new(0.1, 0.1, 0.5, 0.5));
+        assert_eq!(relative.space(), OutputSpace::Normalized);
+        assert!(relative.is_degraded());
+        assert_eq!((relative.source_frame_seq(), relative.source_dims()), (42, Some((640, 480))));
+        // 归一化结果不能直接当作像素坐标绘制
+        let image = image::DynamicImage::new_rgb8(640, 480);
+        assert!(crate::color::utils::draw_detections_checked(&image, &relative).is_err());
+
+        let restored = relative.from_relative_coords(640.0, 480.0);
+        assert_eq!(restored.first().unwrap().bbox, BoundingBox::new(64.0, 48.0, 320.0, 240.0));
+        assert_eq!(restored.space(), OutputSpace::OriginalPixels);
+        assert!(restored.is_degraded());
+        assert_eq!((restored.source_frame_seq(), restored.source_dims()), (42, Some((640, 480))));
+        assert!(crate::color::utils::draw_detections_checked(&image, &restored).is_ok());
+    }
+
+    #[test]
+    fn polygon_corners_are_clockwise() {
+        let bbox = BoundingBox::new(10.0, 20.0, 50.0, 80.0);
+        assert_eq!(bbox.to_polygon(), [(10.0, 20.0), (50.0, 20.0), (50.0, 80.0), (10.0, 80.0)]);
+        // 图像坐标系中按顶点顺序计算的有向面积为正即为顺时针
+        let corners = bbox.to_polygon();
+        let doubled_area: f32 = (0..4)
+            .map(|i| {
+                let ((x0, y0), (x1, y1)) = (corners[i], corners[(i + 1) % 4]);
+                x0 * y1 - x1 * y0
+            })
+            .sum();
+        assert_eq!(doubled_area / 2.0, bbox.area());
+    }
+
+    #[test]
+    fn polygon_round_trip_preserves_valid_boxes() {
+        // 确定性生成的一组合法框，包括退化为线段和点的框
+        let mut seed = 7u32;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) as f32 / 65_536.0 * 2000.0 - 1000.0
+        };
+        let mut boxes: Vec<BoundingBox> = (0..200)
+            .map(|_| {
+                let (x, y) = (next(), next());
+                BoundingBox::new(x, y, x + next().abs(), y + next().abs())
+            })
+            .collect();
+        boxes.extend([BoundingBox::new(5.0, 5.0, 5.0, 9.0), BoundingBox::new(3.0, 4.0, 3.0, 4.0), BoundingBox::default()]);
+        for bbox in boxes {
+            assert_eq!(BoundingBox::from_polygon(&bbox.to_polygon()), Some(bbox));
+        }
+    }
+
+    #[test]
+    fn from_polygon_encloses_arbitrary_points() {
+        assert_eq!(BoundingBox::from_polygon(&[]), None);
+        assert_eq!(BoundingBox::from_polygon(&[(3.0, 4.0)]), Some(BoundingBox::new(3.0, 4.0, 3.0, 4.0)));
+        let triangle = [(30.0, 10.0), (50.0, 60.0), (-5.0, 40.0)];
+        assert_eq!(BoundingBox::from_polygon(&triangle), Some(BoundingBox::new(-5.0, 10.0, 50.0, 60.0)));
+        // 角点颠倒的框经过多边形后被规范化
+        let swapped = BoundingBox::new(50.0, 80.0, 10.0, 20.0);
+        assert_eq!(BoundingBox::from_polygon(&swapped.to_polygon()), Some(swapped.normalized()));
+    }
+
+    #[test]
+    fn nms_on_bounds_follows_selected_mode() {
+        use crate::color::utils::NmsMode;
+        // 按置信度升序放入，nms先排序再抑制
+        let bounds: Bounds = [
+            detection(300.0, 100.0, 400.0, 400.0, 0.7),
+            detection(120.0, 110.0, 180.0, 250.0, 0.8),
+            detection(100.0, 100.0, 200.0, 400.0, 0.9),
+        ].into_iter().collect();
+        let confidences = |mut bounds: Bounds, options: NmsOptions| {
+            bounds.nms(&options);
+            bounds.iter().map(|d| d.confidence).collect::<Vec<_>>()
+        };
+        assert_eq!(confidences(bounds.clone(), NmsOptions::new(0.5)), [0.9, 0.8, 0.7]);
+        assert_eq!(confidences(bounds.clone(), NmsOptions::new(0.5).with_mode(NmsMode::IoMin)), [0.9, 0.7]);
+        assert_eq!(confidences(bounds, NmsOptions::new(0.5).with_containment_threshold(Some(0.9))), [0.9, 0.7]);
+    }
+}
@@ -1,10 +1,116 @@
 use image::DynamicImage;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use std::thread;
 
-use crate::{YoloDetector, color::{bounds::Bounds, image::{ScaleMessage}}, config::{DEFAULT_INPUT_WIDTH, DEFAULT_INPUT_HEIGHT}, utils::stream::Stream};
-use ort::value::{TensorValueType, Value, Tensor};
+use crate::{color::{bounds::BoundsN, detect::YoloDetectorN, image::{ScaleMessage}, transform::Affine2}, config::{DETECTIONS_CAPACITY, STREAM_CAPACITY, DEFAULT_INPUT_WIDTH, DEFAULT_INPUT_HEIGHT, DEFAULT_INFERENCE_TIMEOUT, DEGRADED_MAX_CANDIDATES}, utils::{stream::{Stream, SignalStream}, stats::PipelineStats}};
+use ort::value::{TensorValueType, Value};
+use crate::color::array::empty_input;
+use crate::color::image::{image_diff, InputResizer};
+use crate::color::payload::{Frame, Payload};
+use crate::color::detect::validate_image_dimensions;
+use crate::error::PerpleError;
+
+/// 自定义检测函数，参数为输入图像，返回原始图像坐标下的检测结果
+pub type DetectFn<const N: usize = DETECTIONS_CAPACITY> = Box<dyn FnMut(&DynamicImage) -> Result<BoundsN<N>, PerpleError> + Send>;
+
+/// 执行推理的后端
+enum Engine<const N: usize> {
+    /// YOLO检测器
+    Model {
+        model: Box<YoloDetectorN<N>>,
+        /// Tensor Value缓存，用于避免拷贝
+        tensor_value: Value<TensorValueType<f32>>,
+    },
+    /// 自定义检测函数，见[Color::from_fn]
+    Custom(DetectFn<N>),
+}
+
+/// 推理所需的全部可移动状态
+/// 
+/// 推理时整体移入工作线程，完成后通过通道交还给[Color]。
+struct InferenceState<const N: usize> {
+    /// 推理后端
+    engine: Engine<N>,
+    /// 推理结果缓存，与输出流中的槽位交换以避免拷贝
+    bounds: BoundsN<N>,
+}
+
+impl<const N: usize> InferenceState<N> {
+    /// 检查输入图像能否检测；自定义检测函数只拒绝面积为0的图像
+    fn validate_image(&self, image: &DynamicImage) -> Result<(), PerpleError> {
+        match &self.engine {
+            Engine::Model { model, .. } => model.validate_image(image),
+            Engine::Custom(_) => validate_image_dimensions(image.width(), image.height(), 0),
+        }
+    }
+    
+    /// 执行一次推理，结果写入`bounds`
+    /// 
+    /// 检测器使用已填充的输入张量；自定义检测函数使用`image`，此时它总是`Some`。
+    fn run(&mut self, message: &ScaleMessage, image: Option<&DynamicImage>) -> Result<(), String> {
+        match (&mut self.engine, image) {
+            (Engine::Model { model, tensor_value }, _) => model.infer(tensor_value, &mut self.bounds, message)
+                .map_err(|e| e.to_string()),
+            (Engine::Custom(detect), Some(image)) => {
+                self.bounds.clear();
+                self.bounds = detect(image).map_err(|e| e.to_string())?;
+                Ok(())
+            }
+            (Engine::Custom(_), None) => Err("自定义检测函数缺少输入图像".to_string()),
+        }
+    }
+}
+
+/// 按输入图像尺寸缓存的几何信息，固定分辨率的输入流每帧复用
+#[derive(Debug, Clone, Copy)]
+struct FrameGeometry {
+    /// 输入图像的`(宽, 高)`
+    input_size: (u32, u32),
+    /// 预处理后图像坐标到原始图像坐标的变换
+    source_transform: Affine2,
+}
+
+impl FrameGeometry {
+    /// 输入尺寸与缓存相同时返回缓存的变换，否则用`compute`重新计算并更新缓存
+    fn source_transform(cache: &mut Option<Self>, input_size: (u32, u32), compute: impl FnOnce() -> Affine2) -> Affine2 {
+        match *cache {
+            Some(geometry) if geometry.input_size == input_size => geometry.source_transform,
+            previous => {
+                if let Some(previous) = previous {
+                    log::debug!(
+                        "输入分辨率变化: {}x{} -> {}x{}",
+                        previous.input_size.0, previous.input_size.1, input_size.0, input_size.1
+                    );
+                }
+                let source_transform = compute();
+                *cache = Some(Self { input_size, source_transform });
+                source_transform
+            }
+        }
+    }
+}
+
+/// 推理线程的返回值：交还的状态、推理结果及自定义检测函数的输入图像
+type InferenceOutcome<const N: usize> = (InferenceState<N>, Result<(), String>, Option<DynamicImage>);
+
+/// 单帧处理时间预算
+/// 
+/// 从读取到图像开始计时，在各阶段之间检查已用时间；超出预算时本帧降级：
+/// 推理前超出则只对置信度最高的[DEGRADED_MAX_CANDIDATES]个候选框做NMS，
+/// 推理后超出则截断结果到同样的数量，并将输出的[BoundsN]标记为[降级](BoundsN::is_degraded)。
+/// 下游的绘制、输出等可选步骤可据此跳过，[Perple](crate::Perple)的主流水线对降级帧不执行人数统计、回调和输出端。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameBudget(pub Duration);
+
+impl FrameBudget {
+    /// 从开始计时起是否已超出预算
+    fn exceeded(&self, start_time: Instant) -> bool {
+        start_time.elapsed() > self.0
+    }
+}
 
 /// Color模块的核心结构，用于执行目标检测
 /// 
@@ -12,22 +118,51 @@ use ort::value::{TensorValueType, Value, Tensor};
 /// - 图像输入管理
 /// - 模型推理
 /// - 检测结果输出
-pub struct Color {
+/// 
+/// 图像附带的[用户数据](Payload)原样随检测结果输出，默认不附带数据。
+/// 常量参数`N`为每帧检测结果的容量，检测器和输出流中的结果使用同一容量，默认为[DETECTIONS_CAPACITY]。
+pub struct Color<P: Payload<N> = (), const N: usize = DETECTIONS_CAPACITY> {
     /// 输入图像流（线程安全）
-    input_stream: Arc<Mutex<Stream<DynamicImage>>>,
+    input_stream: Arc<Mutex<Stream<P::Frame>>>,
     /// 输出检测结果流（线程安全）
-    output_stream: Arc<Mutex<Stream<Bounds>>>,
-    /// YOLO检测器
-    model: YoloDetector,
+    output_stream: Arc<Mutex<Stream<P::Output>>>,
+    /// 推理状态，推理线程超时未返回期间为`None`
+    state: Option<InferenceState<N>>,
+    /// 超时后仍在运行的推理线程的返回通道
+    pending: Option<Receiver<InferenceOutcome<N>>>,
     /// 图像缩放信息
     message: ScaleMessage,
+    /// 上一帧输入尺寸对应的几何信息，尺寸变化或检测器被修改时重新计算
+    geometry: Option<FrameGeometry>,
+    /// 缩放到模型输入尺寸所用的缓冲区，输入尺寸不变时每帧复用
+    resizer: InputResizer,
     /// 控制循环运行的标志
     running: bool,
-    /// Tensor Value缓存，用于避免拷贝
-    tensor_value: Value<TensorValueType<f32>>,
+    /// 单次推理的超时时间
+    inference_timeout: Duration,
+    /// 推理超时的累计次数
+    hung_inference_count: usize,
+    /// 推理线程异常退出时正在处理的帧序号，此后无法再检测
+    engine_lost_at: Option<u64>,
+    /// 运行统计（推理延迟等）
+    stats: Arc<PipelineStats>,
+    /// 已读取的帧序号，用于日志关联
+    frame_id: u64,
+    /// 上一个已编号输入帧的[序号](Frame::seq)，用于发现丢帧或重复帧
+    last_frame_seq: Option<u64>,
+    /// 输出流有新结果时发出的信号，读取方无需锁定输出流即可得知
+    detection_signal: Arc<SignalStream<STREAM_CAPACITY>>,
+    /// 运动检测阈值，设置后与上一帧的变化量低于该值时跳过推理
+    motion_threshold: Option<f32>,
+    /// 运动检测的参考帧，即最近一次执行推理的帧，仅在设置了运动检测阈值时保存
+    prev_frame: Option<DynamicImage>,
+    /// 单帧处理时间预算，`None`表示不限制
+    frame_budget: Option<FrameBudget>,
+    /// 输出暂存区：每帧结果先在此组装完整，写入时与输出流的槽位交换
+    scratch_output: Option<P::Output>,
 }
 
-impl Color { 
+impl<P: Payload<N>, const N: usize> Color<P, N> {
     // 构造函数和初始化方法
     // ------------------------------------------------------------------------
 
@@ -40,32 +175,102 @@ impl Color {
     /// 
     /// # 返回值
     /// 返回新的Color实例
+    /// 
+    /// # 错误处理
+    /// 模型加载失败或与检测流程不兼容时返回Err，错误来自[YoloDetectorN::new]
     pub fn new(
-        input_stream: Arc<Mutex<Stream<DynamicImage>>>,
-        output_stream: Arc<Mutex<Stream<Bounds>>>,
+        input_stream: Arc<Mutex<Stream<P::Frame>>>,
+        output_stream: Arc<Mutex<Stream<P::Output>>>,
         model_path: &str,
-    ) -> Self {
-        let input_width = DEFAULT_INPUT_WIDTH;
-        let input_height = DEFAULT_INPUT_HEIGHT;
+    ) -> Result<Self, PerpleError> {
+        let model = YoloDetectorN::new(model_path, DEFAULT_INPUT_WIDTH, DEFAULT_INPUT_HEIGHT)?;
+        Self::from_detector(input_stream, output_stream, model)
+    }
+
+    /// 使用已配置好的检测器创建Color实例
+    /// 
+    /// 检测器的输入尺寸、阈值等配置保持不变。
+    /// 
+    /// # 参数
+    /// * `input_stream` - 输入图像流的线程安全引用
+    /// * `output_stream` - 输出结果流的线程安全引用
+    /// * `model` - YOLO检测器
+    /// 
+    /// # 错误处理
+    /// 创建模型输入张量失败时返回Err
+    pub fn from_detector(
+        input_stream: Arc<Mutex<Stream<P::Frame>>>,
+        output_stream: Arc<Mutex<Stream<P::Output>>>,
+        model: YoloDetectorN<N>,
+    ) -> Result<Self, PerpleError> {
+        let input_width = model.input_width();
+        let input_height = model.input_height();
         
-        // 初始化一个空的tensor value
-        let initial_data = vec![0.0f32; 3 * input_height * input_width];
-        let tensor_value = Tensor::from_array(
-            ([1, 3, input_height, input_width], initial_data)
-        ).unwrap();
+        // 按模型的输入布局初始化一个空的tensor value
+        let tensor_value = empty_input(input_height, input_width, model.input_layout())?;
         
+        Ok(Self::from_engine(input_stream, output_stream, Engine::Model { model: Box::new(model), tensor_value }, (input_width as u32, input_height as u32)))
+    }
+
+    /// 使用自定义检测函数创建Color实例，例如包装其他推理后端或在测试中模拟检测器
+    /// 
+    /// 检测函数在推理线程中对每帧原始图像调用一次，返回的结果应位于原始图像坐标中；
+    /// 推理超时、处理预算、运动检测、帧序号检查等与使用检测器时相同。
+    /// 没有检测器，因此[model](Self::model)总是返回`None`，预处理和坐标空间设置不适用。
+    /// 
+    /// ```
+    /// use perple::color::{Bounds, core::Color};
+    /// use perple::utils::stream::Stream;
+    /// use std::sync::{Arc, Mutex};
+    /// 
+    /// let input = Arc::new(Mutex::new(Stream::new()));
+    /// let output = Arc::new(Mutex::new(Stream::new()));
+    /// let color: Color = Color::from_fn(input, output, |_image| Ok(Bounds::new()));
+    /// assert!(color.model().is_none());
+    /// ```
+    pub fn from_fn<F>(
+        input_stream: Arc<Mutex<Stream<P::Frame>>>,
+        output_stream: Arc<Mutex<Stream<P::Output>>>,
+        detect: F,
+    ) -> Self
+    where
+        F: FnMut(&DynamicImage) -> Result<BoundsN<N>, PerpleError> + Send + 'static,
+    {
+        Self::from_engine(input_stream, output_stream, Engine::Custom(Box::new(detect)), (0, 0))
+    }
+
+    fn from_engine(
+        input_stream: Arc<Mutex<Stream<P::Frame>>>,
+        output_stream: Arc<Mutex<Stream<P::Output>>>,
+        engine: Engine<N>,
+        (input_width, input_height): (u32, u32),
+    ) -> Self {
         Self {
             input_stream,
             output_stream,
-            model: YoloDetector::new(model_path, input_width, input_height),
-            message: ScaleMessage {
-                o_width: 0,
-                o_height: 0,
-                s_width: input_width as u32,
-                s_height: input_height as u32,
-            },
+            state: Some(InferenceState {
+                engine,
+                bounds: BoundsN::new(),
+            }),
+            pending: None,
+            message: ScaleMessage::builder()
+                .original_size(0, 0)
+                .scaled_size(input_width, input_height)
+                .build(),
+            geometry: None,
+            resizer: InputResizer::new(),
             running: false,
-            tensor_value,
+            inference_timeout: DEFAULT_INFERENCE_TIMEOUT,
+            hung_inference_count: 0,
+            engine_lost_at: None,
+            stats: Arc::new(PipelineStats::new()),
+            frame_id: 0,
+            last_frame_seq: None,
+            detection_signal: Arc::new(SignalStream::new()),
+            motion_threshold: None,
+            prev_frame: None,
+            frame_budget: None,
+            scratch_output: None,
         }
     }
 
@@ -76,47 +281,266 @@ impl Color {
     /// 
     /// 该方法会：
     /// 1. 从输入流获取图像
-    /// 2. 准备模型输入张量
-    /// 3. 执行模型推理
+    /// 2. 准备模型输入张量（设置了[运动检测阈值](Self::set_motion_threshold)且画面静止时跳过推理，输出空结果）
+    /// 3. 在工作线程中执行模型推理，最多等待[set_inference_timeout](Self::set_inference_timeout)设置的时间
     /// 4. 将结果写入输出流
-    pub fn act(&mut self) {
+    /// 
+    /// 设置了[处理预算](Self::set_frame_budget)时，超出预算的帧只保留置信度最高的部分目标并标记为降级。
+    /// 输入图像面积为0或小于检测器的[最小尺寸](YoloDetectorN::min_input_dimension)时跳过推理，
+    /// 输出标记为降级的空结果并计入[PipelineStats::invalid_frames]。
+    /// 输入帧的[序号](Frame::seq)与上一帧不连续时记录警告，序号为0的帧视为未编号，不做检查；
+    /// 结果通过[BoundsN::source_frame_seq]关联到输入帧。
+    /// 
+    /// 推理超时时放弃本帧并返回`None`。无法强制终止卡住的推理线程，
+    /// 因此在它返回之前模型不可用，后续调用会直接返回`None`，直到状态被回收。
+    /// 推理线程异常退出时检测器状态随之丢失，见[health](Self::health)。
+    /// 
+    /// # 返回值
+    /// 处理了一帧图像时返回本帧的检测数量，输入流为空或推理未完成时返回`None`
+    pub fn act(&mut self) -> Option<usize> {
+        self.act_with(|_| {})
+    }
+    
+    /// 执行一次检测操作，并在结果写入输出流之前用本帧结果调用`inspect`
+    /// 
+    /// `inspect`收到的正是本次产生的结果，不需要再从输出流中读取，
+    /// 因此不会因其他读取方先取走结果而漏掉或重复处理某一帧。
+    /// 输出流已满、本帧结果被丢弃时`inspect`同样会被调用；输入流为空或推理未完成时不会调用。
+    /// 其余行为与[act](Self::act)相同。
+    pub fn act_with<F: FnOnce(&P::Output)>(&mut self, inspect: F) -> Option<usize> {
+        self.act_with_image(|_, output| inspect(output))
+    }
+    
+    /// 与[act_with](Self::act_with)相同，`inspect`额外收到本帧的输入图像
+    /// 
+    /// 用于需要原始画面的处理（如[快照](crate::color::SnapshotSink)），图像只以引用交出，不会被复制。
+    pub fn act_with_image<F: FnOnce(&DynamicImage, &P::Output)>(&mut self, inspect: F) -> Option<usize> {
+        if !self.recover_pending() {
+            return None;
+        }
+        let mut state = self.state.take()?;
+        
         // 从输入流中读取图像
         let mut input_stream = self.input_stream.lock().unwrap();
-        if let Some(input) = input_stream.read() {
-            drop(input_stream); // 释放锁
-            
-            // 处理图像
-            self.message.o_width = input.width();
-            self.message.o_height = input.height();
-            
-            // 填充tensor value，避免拷贝
-            crate::color::image::fill_input_image(&input, self.model.input_height(), self.model.input_width(), &mut self.tensor_value);
+        let Some(frame) = input_stream.read() else {
+            self.state = Some(state);
+            return None;
+        };
+        drop(input_stream); // 释放锁
+        let (payload, Frame { image: input, seq, .. }) = P::from_frame(frame);
+        
+        // 处理图像
+        self.frame_id += 1;
+        let frame_id = self.frame_id;
+        // 序号为0的帧未编号（如直接写入流的`Frame::default()`），不参与连续性检查
+        if seq != 0 {
+            if let Some(last_seq) = self.last_frame_seq && seq != last_seq + 1 {
+                log::warn!("输入帧序号不连续，可能丢帧或重复: frame_id={} seq={} previous_seq={}", frame_id, seq, last_seq);
+            }
+            self.last_frame_seq = Some(seq);
+        }
+        // 画面静止时跳过推理，输出空结果
+        let start_time = Instant::now();
+        let (width, height) = (input.width(), input.height());
+        let (mut state, input) = if self.is_still(&input) {
+            log::debug!("画面无明显变化，跳过推理: frame_id={}", frame_id);
+            state.bounds.clear();
+            (state, input)
+        } else if let Err(e) = state.validate_image(&input) {
+            // 标记为降级，与正常的空结果区分，并计入统计
+            log::error!("跳过无法检测的输入帧: frame_id={} error={}", frame_id, e);
+            state.bounds.clear();
+            state.bounds.set_degraded(true);
+            self.stats.record_invalid_frame();
+            (state, input)
+        } else {
+            let mut source_transform = Affine2::identity();
+            let mut max_candidates = None;
+            let mut degraded = false;
+            // 自定义检测函数直接接收原始图像，结果已位于原始图像坐标中，图像随推理线程一起返回
+            let (custom_input, input) = match &mut state.engine {
+                Engine::Model { model, tensor_value } => {
+                    // 预处理后的尺寸决定缩放比例，改变几何形状的预处理在推理后还原坐标
+                    let processed = model.preprocess(&input);
+                    source_transform = self.source_transform(model, width, height);
+                    self.message.o_width = processed.width();
+                    self.message.o_height = processed.height();
+                    
+                    // 原地填充tensor value，复用缩放缓冲区，避免拷贝和重新分配
+                    let filled = crate::color::image::fill_input_image_resized(
+                        &processed,
+                        model.input_height(),
+                        model.input_width(),
+                        model.input_layout(),
+                        model.alpha_background(),
+                        &mut self.resizer,
+                        tensor_value,
+                    );
+                    if let Err(e) = filled {
+                        log::error!("填充模型输入张量失败，已放弃本帧: frame_id={} error={}", frame_id, e);
+                        self.state = Some(state);
+                        return None;
+                    }
+                    
+                    // 预处理已超出预算时只对少量候选框做NMS，推理线程返回前恢复原设置
+                    degraded = self.frame_budget.is_some_and(|budget| budget.exceeded(start_time));
+                    max_candidates = model.max_candidates();
+                    if degraded {
+                        let capped = max_candidates.map_or(DEGRADED_MAX_CANDIDATES, |m| m.min(DEGRADED_MAX_CANDIDATES));
+                        model.set_max_candidates(Some(capped));
+                    }
+                    (None, input)
+                }
+                Engine::Custom(_) => (Some(input), DynamicImage::default()),
+            };
             
             // 执行推理并计时
-            let start_time = Instant::now();
+            let message = self.message;
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| state.run(&message, custom_input.as_ref())))
+                    .unwrap_or_else(|_| Err("推理线程发生panic".to_string()));
+                if let Engine::Model { model, .. } = &mut state.engine {
+                    model.set_max_candidates(max_candidates);
+                }
+                state.bounds.set_degraded(degraded);
+                let _ = sender.send((state, result, custom_input));
+            });
             
-            // 使用新添加的直接引用方法优化性能
-            let mut output_stream = self.output_stream.lock().unwrap();
-            if let Ok(slot) = output_stream.get_write_mut() {
-                // 初始化或获取Bounds对象
-                let bounds = slot.get_or_insert_with(Bounds::new);
-                bounds.clear(); // 清空之前的数据
-                
-                // 执行推理
-                if let Err(e) = self.model.infer(&self.tensor_value, bounds, &self.message) {
-                    eprintln!("推理过程中发生错误: {:?}", e);
+            match receiver.recv_timeout(self.inference_timeout) {
+                Ok((mut state, result, custom_input)) => {
+                    if let Err(e) = result {
+                        log::error!("推理过程中发生错误: frame_id={} error={}", frame_id, e);
+                    }
+                    if source_transform != Affine2::identity() {
+                        state.bounds.transform_all(&source_transform);
+                    }
+                    if let Engine::Model { model, .. } = &state.engine {
+                        model.apply_output_space(&mut state.bounds, &message, width, height);
+                    }
+                    self.apply_frame_budget(&mut state.bounds, start_time, frame_id);
+                    self.stats.latency_histogram().record(start_time.elapsed());
+                    (state, custom_input.unwrap_or(input))
                 }
-                
-                // 提交写入操作
-                if let Err(e) = output_stream.commit_write() {
-                    eprintln!("提交写入操作时发生错误: {:?}", e);
+                Err(RecvTimeoutError::Timeout) => {
+                    // 超时只说明推理至少耗时这么久，不是真实延迟，不计入延迟直方图
+                    self.hung_inference_count += 1;
+                    log::error!(
+                        "推理超时，已放弃本帧，推理线程返回前模型不可用: frame_id={} timeout_ms={} hung_count={}",
+                        frame_id, self.inference_timeout.as_millis(), self.hung_inference_count
+                    );
+                    self.pending = Some(receiver);
+                    return None;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.mark_engine_lost(frame_id);
+                    return None;
                 }
-            } else {
-                eprintln!("获取输出流写入位置失败: 缓冲区已满");
             }
-            
-            let duration = start_time.elapsed();
-            println!("模型推理耗时: {:?}", duration);
+        };
+        
+        // 先在暂存区中组装完整的输出，再与输出流中的槽位整体交换，避免拷贝
+        let output = self.scratch_output.get_or_insert_with(Default::default);
+        let bounds = P::bounds_mut(output);
+        bounds.clear();
+        std::mem::swap(bounds, &mut state.bounds);
+        bounds.set_source_frame_seq(seq);
+        bounds.set_source_dims(Some((width, height)));
+        let count = bounds.len();
+        payload.attach(output);
+        inspect(&input, output);
+        
+        // 槽位中残留的旧结果随交换回到暂存区，下一帧组装前被清空，不会混入新结果
+        let mut detections = None;
+        let mut output_stream = self.output_stream.lock().unwrap();
+        match output_stream.write_direct(|slot| std::mem::swap(slot, &mut self.scratch_output)) {
+            Ok(()) => {
+                detections = Some(count);
+                self.detection_signal.signal();
+            }
+            Err(_) => log::warn!("输出流已满，丢弃本帧检测结果: frame_id={}", frame_id),
+        }
+        drop(output_stream);
+        self.state = Some(state);
+        
+        let duration = start_time.elapsed();
+        log::debug!(
+            "模型推理完成: frame_id={} duration_ms={:.2} detections={:?}",
+            frame_id, duration.as_secs_f64() * 1000.0, detections
+        );
+        
+        detections
+    }
+    
+    /// 预处理后图像坐标到原始图像坐标的变换，输入尺寸与上一帧相同时直接复用
+    fn source_transform(&mut self, model: &YoloDetectorN<N>, width: u32, height: u32) -> Affine2 {
+        FrameGeometry::source_transform(&mut self.geometry, (width, height), || model.source_transform(width, height))
+    }
+    
+    /// 推理结束后检查处理预算，超出时截断结果并记录降级帧
+    fn apply_frame_budget(&self, bounds: &mut BoundsN<N>, start_time: Instant, frame_id: u64) {
+        let Some(budget) = self.frame_budget else {
+            return;
+        };
+        if budget.exceeded(start_time) && !bounds.is_degraded() {
+            // NMS的输出已按置信度降序排列，保留前面的部分即可
+            let mut index = 0;
+            bounds.retain(|_| {
+                index += 1;
+                index <= DEGRADED_MAX_CANDIDATES
+            });
+            bounds.set_degraded(true);
+        }
+        if bounds.is_degraded() {
+            self.stats.record_degraded_frame();
+            log::debug!(
+                "超出单帧处理预算，本帧已降级: frame_id={} budget_ms={} elapsed_ms={:.2}",
+                frame_id, budget.0.as_millis(), start_time.elapsed().as_secs_f64() * 1000.0
+            );
+        }
+    }
+    
+    /// 尝试回收此前超时的推理线程交还的状态
+    /// 
+    /// # 返回值
+    /// 没有未完成的推理或已成功回收时返回`true`
+    fn recover_pending(&mut self) -> bool {
+        let Some(receiver) = &self.pending else {
+            return true;
+        };
+        match receiver.try_recv() {
+            Ok((state, _, _)) => {
+                log::warn!("超时的推理线程已返回，恢复检测");
+                self.state = Some(state);
+                self.pending = None;
+                true
+            }
+            Err(TryRecvError::Empty) => false,
+            Err(TryRecvError::Disconnected) => {
+                // 超时后不再读取新帧，仍在运行的就是最近一帧的推理
+                self.pending = None;
+                self.mark_engine_lost(self.frame_id);
+                false
+            }
+        }
+    }
+    
+    /// 记录推理线程异常退出，此后[health](Self::health)返回错误
+    fn mark_engine_lost(&mut self, frame_id: u64) {
+        log::error!("推理线程异常退出，检测器状态已丢失，流水线停止检测: frame_id={}", frame_id);
+        self.engine_lost_at = Some(frame_id);
+        self.stats.record_engine_lost();
+    }
+    
+    /// 检查流水线能否继续检测
+    /// 
+    /// # 错误处理
+    /// 推理线程异常退出（例如panic未能被捕获）导致检测器状态丢失时返回[PerpleError::EngineLost]，
+    /// 这一状态无法恢复，此后[act](Self::act)总是返回`None`，需要重新创建Color实例
+    pub fn health(&self) -> Result<(), PerpleError> {
+        match self.engine_lost_at {
+            Some(frame_id) => Err(PerpleError::EngineLost { frame_id }),
+            None => Ok(()),
         }
     }
     
@@ -153,29 +577,454 @@ impl Color {
         self.running
     }
 
+    /// 改为从另一个输入流读取图像，已在原输入流中的图像不受影响
+    pub(crate) fn set_input_stream(&mut self, input_stream: Arc<Mutex<Stream<P::Frame>>>) {
+        self.input_stream = input_stream;
+    }
+
     // Getter方法
     // ------------------------------------------------------------------------
 
-    /// 获取模型引用
-    pub fn model(&self) -> &YoloDetector {
-        &self.model
+    /// 获取模型引用，推理线程超时未返回期间或使用[自定义检测函数](Self::from_fn)时为`None`
+    pub fn model(&self) -> Option<&YoloDetectorN<N>> {
+        match &self.state.as_ref()?.engine {
+            Engine::Model { model, .. } => Some(model),
+            Engine::Custom(_) => None,
+        }
     }
     
-    /// 获取可变模型引用
-    pub fn model_mut(&mut self) -> &mut YoloDetector {
-        &mut self.model
+    /// 获取可变模型引用，推理线程超时未返回期间或使用[自定义检测函数](Self::from_fn)时为`None`
+    /// 
+    /// 预处理等设置可能被修改，因此缓存的坐标变换随之失效，下一帧重新计算。
+    pub fn model_mut(&mut self) -> Option<&mut YoloDetectorN<N>> {
+        self.geometry = None;
+        match &mut self.state.as_mut()?.engine {
+            Engine::Model { model, .. } => Some(model),
+            Engine::Custom(_) => None,
+        }
+    }
+    
+    /// 获取单次推理的超时时间
+    pub fn inference_timeout(&self) -> Duration {
+        self.inference_timeout
+    }
+    
+    /// 获取运行统计
+    pub fn stats(&self) -> &Arc<PipelineStats> {
+        &self.stats
+    }
+    
+    /// 获取检测结果信号，每写入一帧结果发出一个信号
+    pub fn detection_signal(&self) -> &Arc<SignalStream<STREAM_CAPACITY>> {
+        &self.detection_signal
+    }
+    
+    /// 获取推理超时的累计次数
+    pub fn hung_inference_count(&self) -> usize {
+        self.hung_inference_count
+    }
+    
+    /// 已读取的帧数，即最近一帧的序号（与日志中的`frame_id`一致）
+    pub fn frame_id(&self) -> u64 {
+        self.frame_id
     }
 
     // 模型参数设置方法
     // ------------------------------------------------------------------------
 
-    /// 更新模型置信度阈值
+    /// 更新模型置信度阈值，模型不可用时忽略
     pub fn set_confidence_threshold(&mut self, threshold: f32) {
-        self.model.set_confidence_threshold(threshold);
+        if let Some(model) = self.model_mut() {
+            model.set_confidence_threshold(threshold);
+        }
     }
     
-    /// 更新模型NMS阈值
+    /// 更新模型NMS阈值，模型不可用时忽略
     pub fn set_nms_threshold(&mut self, threshold: f32) {
-        self.model.set_nms_threshold(threshold);
+        if let Some(model) = self.model_mut() {
+            model.set_nms_threshold(threshold);
+        }
+    }
+    
+    /// 设置单次推理的超时时间，默认为[DEFAULT_INFERENCE_TIMEOUT]
+    pub fn set_inference_timeout(&mut self, timeout: Duration) {
+        self.inference_timeout = timeout;
+    }
+    
+    /// 设置运动检测阈值
+    /// 
+    /// 设置后每帧先用[image_diff]与最近一次执行推理的帧比较，变化量低于阈值时跳过推理并输出空的检测结果。
+    /// 跳过的帧不会替换参考帧，缓慢的累积变化超过阈值后仍会触发推理。
+    /// 传入`None`关闭运动检测并释放保存的参考帧。
+    pub fn set_motion_threshold(&mut self, threshold: Option<f32>) {
+        self.motion_threshold = threshold;
+        if threshold.is_none() {
+            self.prev_frame = None;
+        }
+    }
+    
+    /// 设置单帧处理时间预算，`None`表示不限制，参见[FrameBudget]
+    pub fn set_frame_budget(&mut self, budget: Option<FrameBudget>) {
+        self.frame_budget = budget;
+    }
+    
+    /// 获取单帧处理时间预算
+    pub fn frame_budget(&self) -> Option<FrameBudget> {
+        self.frame_budget
+    }
+    
+    /// 获取运动检测阈值
+    pub fn motion_threshold(&self) -> Option<f32> {
+        self.motion_threshold
+    }
+    
+    /// 判断当前帧与参考帧相比是否静止，不静止时将当前帧保存为新的参考帧
+    /// 
+    /// 未设置运动检测阈值、没有参考帧或两帧尺寸不同时都视为有变化。
+    fn is_still(&mut self, input: &DynamicImage) -> bool {
+        let Some(threshold) = self.motion_threshold else {
+            return false;
+        };
+        let still = self.prev_frame.as_ref()
+            .and_then(|prev| image_diff(prev, input).ok())
+            .is_some_and(|diff| diff < threshold);
+        if !still {
+            self.prev_frame = Some(input.clone());
+        }
+        still
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::bounds::{BoundingBox, Bounds, Detection};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 析构时再次panic的载荷，使推理线程的panic无法被`catch_unwind`完整处理
+    struct PanicOnDrop;
+
+    impl Drop for PanicOnDrop {
+        fn drop(&mut self) {
+            panic!("析构载荷时panic");
+        }
+    }
+
+    fn color<F>(detect: F) -> Color
+    where
+        F: FnMut(&DynamicImage) -> Result<Bounds, PerpleError> + Send + 'static,
+    {
+        Color::from_fn(Arc::new(Mutex::new(Stream::new())), Arc::new(Mutex::new(Stream::new())), detect)
+    }
+
+    fn push_frame(color: &Color) {
+        color.input_stream.lock().unwrap().write(Frame::new(DynamicImage::new_rgb8(8, 8), color.frame_id() + 1)).unwrap();
+    }
+
+    #[test]
+    fn lost_engine_is_reported_as_terminal_error() {
+        let mut color = color(|_| std::panic::panic_any(PanicOnDrop));
+        push_frame(&color);
+        assert_eq!(color.act(), None);
+        assert!(matches!(color.health(), Err(PerpleError::EngineLost { frame_id: 1 })));
+        assert!(color.stats().engine_lost());
+
+        // 之后的帧不再被读取或检测
+        push_frame(&color);
+        assert_eq!(color.act(), None);
+        assert_eq!(color.frame_id(), 1);
+        assert!(color.health().is_err());
+    }
+
+    #[test]
+    fn caught_panic_keeps_engine_alive() {
+        let mut calls = 0;
+        let mut color = color(move |_| {
+            calls += 1;
+            if calls == 1 {
+                panic!("第一帧panic");
+            }
+            Ok(Bounds::new())
+        });
+        push_frame(&color);
+        assert_eq!(color.act(), Some(0));
+        push_frame(&color);
+        assert_eq!(color.act(), Some(0));
+        assert!(color.health().is_ok());
+        assert!(!color.stats().engine_lost());
+    }
+
+    #[test]
+    fn engine_lost_after_timeout_is_reported() {
+        let mut color = color(|_| {
+            thread::sleep(Duration::from_millis(50));
+            std::panic::panic_any(PanicOnDrop)
+        });
+        color.set_inference_timeout(Duration::from_millis(5));
+        push_frame(&color);
+        assert_eq!(color.act(), None);
+        assert_eq!(color.hung_inference_count(), 1);
+        assert!(color.health().is_ok());
+
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(color.act(), None);
+        assert!(matches!(color.health(), Err(PerpleError::EngineLost { frame_id: 1 })));
+        assert!(color.stats().engine_lost());
+    }
+
+    #[test]
+    fn timed_out_inference_is_not_a_latency_sample() {
+        let mut color = color(|_| {
+            thread::sleep(Duration::from_millis(50));
+            Ok(Bounds::new())
+        });
+        color.set_inference_timeout(Duration::from_millis(5));
+        push_frame(&color);
+        assert_eq!(color.act(), None);
+        assert_eq!(color.stats().latency_histogram().count(), 0);
+
+        // 推理线程返回后恢复检测，正常完成的帧计入直方图
+        thread::sleep(Duration::from_millis(100));
+        color.set_inference_timeout(Duration::from_secs(5));
+        push_frame(&color);
+        assert_eq!(color.act(), Some(0));
+        assert_eq!(color.stats().latency_histogram().count(), 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn slow_frame_is_truncated_and_flagged_degraded() {
+        let mut calls = 0;
+        let mut color = color(move |_| {
+            calls += 1;
+            if calls == 2 {
+                thread::sleep(Duration::from_millis(30));
+            }
+            Ok((0..DEGRADED_MAX_CANDIDATES + 5)
+                .map(|i| Detection::new(BoundingBox::new(i as f32, 0.0, i as f32 + 1.0, 1.0), 0, "person", 0.9))
+                .collect())
+        });
+        color.set_frame_budget(Some(FrameBudget(Duration::from_millis(15))));
+
+        let mut outputs = Vec::new();
+        for _ in 0..3 {
+            push_frame(&color);
+            color.act_with(|bounds| outputs.push((bounds.len(), bounds.is_degraded())));
+        }
+        let full = DEGRADED_MAX_CANDIDATES + 5;
+        assert_eq!(outputs, vec![(full, false), (DEGRADED_MAX_CANDIDATES, true), (full, false)]);
+        assert_eq!(color.stats().degraded_frames(), 1);
+    }
+
+    #[test]
+    fn geometry_cache_follows_resolution_changes() {
+        let mut cache = None;
+        let mut computed = Vec::new();
+        let mut transform = |size: (u32, u32)| {
+            FrameGeometry::source_transform(&mut cache, size, || {
+                computed.push(size);
+                Affine2::scale(size.0 as f32, size.1 as f32)
+            })
+        };
+        let sizes = [(64, 48), (64, 48), (32, 16), (32, 16), (64, 48)];
+        let transforms: Vec<Affine2> = sizes.iter().map(|&size| transform(size)).collect();
+        for (&(width, height), transform) in sizes.iter().zip(&transforms) {
+            assert_eq!(transform.apply(1.0, 1.0), (width as f32, height as f32));
+        }
+        assert_eq!(computed, vec![(64, 48), (32, 16), (64, 48)]);
+    }
+
+    #[test]
+    fn model_mut_invalidates_cached_geometry() {
+        let mut color = color(|_| Ok(Bounds::new()));
+        color.geometry = Some(FrameGeometry { input_size: (64, 48), source_transform: Affine2::identity() });
+        assert!(color.model_mut().is_none());
+        assert!(color.geometry.is_none());
+    }
+
+    #[test]
+    fn resolution_change_keeps_output_in_source_coordinates() {
+        // 检测函数返回覆盖整幅图像的框，结果应始终与当前帧的尺寸一致
+        let mut color = color(|image| {
+            let (width, height) = (image.width() as f32, image.height() as f32);
+            Ok(std::iter::once(Detection::new(BoundingBox::new(0.0, 0.0, width, height), 0, "person", 0.9)).collect())
+        });
+        let mut outputs = Vec::new();
+        for (seq, (width, height)) in [(64, 48), (32, 16), (64, 48)].into_iter().enumerate() {
+            color.input_stream.lock().unwrap().write(Frame::new(DynamicImage::new_rgb8(width, height), seq as u64 + 1)).unwrap();
+            color.act_with(|bounds| outputs.push((bounds.source_dims(), bounds.as_slice()[0].bbox.x2, bounds.as_slice()[0].bbox.y2)));
+        }
+        assert_eq!(outputs, vec![
+            (Some((64, 48)), 64.0, 48.0),
+            (Some((32, 16)), 32.0, 16.0),
+            (Some((64, 48)), 64.0, 48.0),
+        ]);
+    }
+
+    #[test]
+    fn invalid_frames_are_flagged_and_counted() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = Arc::clone(&calls);
+        let mut color = color(move |_| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            Ok(Bounds::new())
+        });
+        let sizes = [(0, 0), (8, 8), (0, 5), (1, 1), (10000, 2)];
+        let mut outputs = Vec::new();
+        for (seq, (width, height)) in sizes.into_iter().enumerate() {
+            color.input_stream.lock().unwrap().write(Frame::new(DynamicImage::new_rgb8(width, height), seq as u64 + 1)).unwrap();
+            color.act_with(|bounds| outputs.push(((width, height), bounds.is_degraded())));
+        }
+        // 自定义检测函数只拒绝面积为0的图像，被拒绝的帧不会调用检测函数
+        assert_eq!(outputs, [((0, 0), true), ((8, 8), false), ((0, 5), true), ((1, 1), false), ((10000, 2), false)]);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(color.stats().invalid_frames(), 2);
+        assert_eq!(color.stats().degraded_frames(), 0);
+    }
+
+    #[test]
+    fn unsequenced_frames_skip_continuity_check() {
+        let mut color = color(|_| Ok(Bounds::new()));
+        for _ in 0..3 {
+            color.input_stream.lock().unwrap().write(Frame::new(DynamicImage::new_rgb8(8, 8), 0)).unwrap();
+            assert_eq!(color.act(), Some(0));
+            assert_eq!(color.last_frame_seq, None);
+        }
+
+        // 未编号的帧不打断已编号帧的连续性
+        for seq in [5, 0, 6] {
+            color.input_stream.lock().unwrap().write(Frame::new(DynamicImage::new_rgb8(8, 8), seq)).unwrap();
+            color.act();
+        }
+        assert_eq!(color.last_frame_seq, Some(6));
+        assert_eq!(color.frame_id(), 6);
+    }
+
+    #[test]
+    fn slow_drift_eventually_triggers_detection() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = Arc::clone(&calls);
+        let mut color = color(move |_| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            Ok(Bounds::new())
+        });
+        // 相邻帧每次变亮2级（约0.008），低于阈值；相对参考帧累积到6级后超过阈值
+        color.set_motion_threshold(Some(0.02));
+        let mut detected = Vec::new();
+        for (seq, level) in (0..=12u8).step_by(2).enumerate() {
+            let frame = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(8, 8, image::Rgb([level; 3])));
+            color.input_stream.lock().unwrap().write(Frame::new(frame, seq as u64 + 1)).unwrap();
+            let before = calls.load(Ordering::SeqCst);
+            color.act();
+            if calls.load(Ordering::SeqCst) > before {
+                detected.push(level);
+            }
+        }
+        assert_eq!(detected, vec![0, 6, 12]);
+    }
+
+    #[test]
+    fn unread_output_slots_never_mix_frames() {
+        // 检测数量和类别名称都由输入图像的像素值（即帧序号）决定
+        let mut color = color(|image| {
+            let seq = image.to_luma8().get_pixel(0, 0).0[0] as usize;
+            Ok((0..seq % 4 + 1)
+                .map(|i| Detection::new(BoundingBox::new(i as f32, 0.0, i as f32 + 1.0, 1.0), seq, format!("frame{}", seq), 0.9))
+                .collect())
+        });
+        let run = |color: &mut Color, seqs: std::ops::RangeInclusive<u64>| {
+            for seq in seqs {
+                let image = DynamicImage::ImageLuma8(image::GrayImage::from_pixel(8, 8, image::Luma([seq as u8])));
+                color.input_stream.lock().unwrap().write(Frame::new(image, seq)).unwrap();
+                color.act();
+            }
+        };
+        let check = |outputs: Vec<Bounds>| {
+            for bounds in &outputs {
+                let seq = bounds.source_frame_seq() as usize;
+                assert_eq!(bounds.len(), seq % 4 + 1, "frame {}", seq);
+                assert!(bounds.iter().all(|d| d.class_id == seq && d.class_name == format!("frame{}", seq)), "frame {}: {}", seq, bounds);
+            }
+            outputs.iter().map(|bounds| bounds.source_frame_seq()).collect::<Vec<_>>()
+        };
+        let drain = |color: &Color| std::iter::from_fn(|| color.output_stream.lock().unwrap().read()).collect::<Vec<_>>();
+
+        // 没有读取方时写满输出流，之后的帧被丢弃
+        let frames = 3 * STREAM_CAPACITY as u64;
+        run(&mut color, 1..=frames);
+        let kept = check(drain(&color));
+        assert_eq!(kept, (1..STREAM_CAPACITY as u64).collect::<Vec<_>>());
+
+        // 槽位中残留的旧结果不会混入之后复用这些槽位的帧
+        run(&mut color, frames + 1..=frames + STREAM_CAPACITY as u64);
+        let kept = check(drain(&color));
+        assert_eq!(kept, (frames + 1..frames + STREAM_CAPACITY as u64).collect::<Vec<_>>());
+    }
+
+    /// 只记录开启了捕获的线程所产生日志的全局日志器，不影响并行运行的其他测试
+    struct CaptureLogger;
+
+    thread_local! {
+        static CAPTURED: std::cell::RefCell<Option<Vec<(log::Level, String)>>> = const { std::cell::RefCell::new(None) };
+    }
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            CAPTURED.with(|captured| {
+                if let Some(records) = captured.borrow_mut().as_mut() {
+                    records.push((record.level(), record.args().to_string()));
+                }
+            });
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// 执行`f`并返回当前线程在此期间产生的日志
+    fn capture_logs(f: impl FnOnce()) -> Vec<(log::Level, String)> {
+        static LOGGER: CaptureLogger = CaptureLogger;
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INSTALL.call_once(|| {
+            // 其他测试已安装日志器时无法捕获，此时让测试失败而不是静默通过
+            log::set_logger(&LOGGER).expect("日志器已被安装");
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+        CAPTURED.with(|captured| *captured.borrow_mut() = Some(Vec::new()));
+        f();
+        CAPTURED.with(|captured| captured.borrow_mut().take().unwrap_or_default())
+    }
+
+    #[test]
+    fn dropped_frame_emits_exactly_one_warning() {
+        let mut color = color(|_| Ok(Bounds::new()));
+        // 没有读取方时先写满输出流
+        for _ in 1..STREAM_CAPACITY {
+            push_frame(&color);
+            assert_eq!(color.act(), Some(0));
+        }
+        assert!(color.output_stream.lock().unwrap().is_full());
+
+        let logs = capture_logs(|| {
+            push_frame(&color);
+            color.act();
+        });
+        let warnings: Vec<&String> = logs.iter().filter(|(level, _)| *level == log::Level::Warn).map(|(_, message)| message).collect();
+        assert_eq!(warnings.len(), 1, "{:?}", logs);
+        assert!(warnings[0].contains("输出流已满"), "{}", warnings[0]);
+        assert!(warnings[0].contains(&format!("frame_id={}", STREAM_CAPACITY)), "{}", warnings[0]);
+        // 没有错误日志，每帧的耗时以debug级别输出
+        assert!(logs.iter().all(|(level, _)| *level >= log::Level::Warn));
+        assert!(logs.iter().any(|(level, _)| *level == log::Level::Debug));
+
+        // 读出一帧后不再告警
+        color.output_stream.lock().unwrap().read();
+        let logs = capture_logs(|| {
+            push_frame(&color);
+            color.act();
+        });
+        assert!(logs.iter().all(|(level, _)| *level > log::Level::Warn), "{:?}", logs);
+    }
+}
@@ -0,0 +1,198 @@
+//! 人数统计模块
+//!
+//! 从每帧的检测结果中得到去抖动后的人数，
+//! 避免检测结果在相邻帧间闪烁导致计数频繁跳变。
+
+use std::collections::VecDeque;
+
+use crate::color::bounds::BoundsN;
+use crate::config::PERSON_CLASS_LABEL;
+
+/// 人数变化回调，参数依次为变化前和变化后的人数
+pub type CountChangeCallback = Box<dyn FnMut(usize, usize) + Send>;
+
+/// 带滞回的人数统计器
+///
+/// 每帧先按类别、置信度和框面积过滤检测结果得到原始人数，
+/// 再取最近`window`帧原始人数的中位数作为当前人数。
+pub struct PersonCounter {
+    class_name: String,
+    min_confidence: f32,
+    min_box_area: f32,
+    window: usize,
+    history: VecDeque<usize>,
+    current: usize,
+    max_since_reset: usize,
+    callbacks: Vec<CountChangeCallback>,
+}
+
+impl PersonCounter {
+    /// 创建一个新的统计器
+    ///
+    /// # 参数
+    /// * `window` - 取中位数的帧数，至少为1
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            class_name: PERSON_CLASS_LABEL.to_string(),
+            min_confidence: 0.0,
+            min_box_area: 0.0,
+            window,
+            history: VecDeque::with_capacity(window),
+            current: 0,
+            max_since_reset: 0,
+            callbacks: Vec::new(),
+        }
+    }
+
+    /// 设置计入人数的类别名，默认为`person`
+    pub fn with_class_name(mut self, class_name: impl Into<String>) -> Self {
+        self.class_name = class_name.into();
+        self
+    }
+
+    /// 设置计入人数的最低置信度
+    pub fn with_min_confidence(mut self, confidence: f32) -> Self {
+        self.min_confidence = confidence;
+        self
+    }
+
+    /// 设置计入人数的最小框面积（原始图像像素）
+    pub fn with_min_box_area(mut self, area: f32) -> Self {
+        self.min_box_area = area;
+        self
+    }
+
+    /// 注册人数变化回调
+    pub fn on_change<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(usize, usize) + Send + 'static,
+    {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// 输入一帧检测结果并返回更新后的人数
+    ///
+    /// 人数发生变化时依次调用已注册的回调。
+    pub fn update<const N: usize>(&mut self, bounds: &BoundsN<N>) -> usize {
+        let raw = bounds.iter()
+            .filter(|d| d.class_name == self.class_name.as_str())
+            .filter(|d| d.confidence >= self.min_confidence && d.bbox.area() >= self.min_box_area)
+            .count();
+        if self.history.len() == self.window {
+            self.history.pop_front();
+        }
+        self.history.push_back(raw);
+
+        let mut sorted: Vec<usize> = self.history.iter().copied().collect();
+        sorted.sort_unstable();
+        let count = sorted[sorted.len() / 2];
+
+        if count != self.current {
+            let previous = self.current;
+            self.current = count;
+            for callback in self.callbacks.iter_mut() {
+                callback(previous, count);
+            }
+        }
+        self.max_since_reset = self.max_since_reset.max(count);
+        count
+    }
+
+    /// 当前（去抖动后的）人数
+    pub fn current_count(&self) -> usize {
+        self.current
+    }
+
+    /// 上次重置以来的最大人数
+    pub fn max_count_since_reset(&self) -> usize {
+        self.max_since_reset
+    }
+
+    /// 重置最大人数统计为当前人数
+    pub fn reset(&mut self) {
+        self.max_since_reset = self.current;
+    }
+}
+
+impl std::fmt::Debug for PersonCounter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PersonCounter")
+            .field("class_name", &self.class_name)
+            .field("min_confidence", &self.min_confidence)
+            .field("min_box_area", &self.min_box_area)
+            .field("window", &self.window)
+            .field("current", &self.current)
+            .field("max_since_reset", &self.max_since_reset)
+            .field("callbacks", &self.callbacks.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::bounds::{BoundingBox, Bounds, Detection};
+    use std::sync::{Arc, Mutex};
+
+    fn frame(persons: usize, others: usize) -> Bounds {
+        let mut bounds = Bounds::new();
+        for i in 0..persons + others {
+            let x = i as f32 * 20.0;
+            let (class_id, label) = if i < persons { (0, PERSON_CLASS_LABEL) } else { (2, "car") };
+            bounds.push(Detection::new(BoundingBox::new(x, 0.0, x + 10.0, 10.0), class_id, label, 0.9));
+        }
+        bounds
+    }
+
+    #[test]
+    fn counts_only_person_class() {
+        let mut counter = PersonCounter::new(1);
+        assert_eq!(counter.update(&frame(2, 3)), 2);
+        assert_eq!(counter.update(&frame(0, 5)), 0);
+    }
+
+    #[test]
+    fn custom_class_name_is_counted_instead() {
+        let mut counter = PersonCounter::new(1).with_class_name("car");
+        assert_eq!(counter.update(&frame(2, 3)), 3);
+    }
+
+    #[test]
+    fn single_frame_flicker_is_debounced() {
+        let mut counter = PersonCounter::new(3);
+        let counts: Vec<usize> = [2, 2, 0, 2, 3, 2, 2]
+            .iter()
+            .map(|&n| counter.update(&frame(n, 1)))
+            .collect();
+        assert_eq!(counts, vec![2, 2, 2, 2, 2, 2, 2]);
+        assert_eq!(counter.max_count_since_reset(), 2);
+    }
+
+    #[test]
+    fn sustained_change_is_followed_and_reported() {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&changes);
+        let mut counter = PersonCounter::new(3)
+            .on_change(move |before, after| recorded.lock().unwrap().push((before, after)));
+        for n in [1, 1, 1, 4, 4, 4, 0, 0] {
+            counter.update(&frame(n, 0));
+        }
+        assert_eq!(counter.current_count(), 0);
+        assert_eq!(counter.max_count_since_reset(), 4);
+        assert_eq!(*changes.lock().unwrap(), vec![(0, 1), (1, 4), (4, 0)]);
+
+        counter.reset();
+        assert_eq!(counter.max_count_since_reset(), 0);
+    }
+
+    #[test]
+    fn confidence_and_area_filters_apply() {
+        let mut bounds = frame(1, 0);
+        bounds.push(Detection::new(BoundingBox::new(50.0, 0.0, 51.0, 1.0), 0, PERSON_CLASS_LABEL, 0.9));
+        bounds.push(Detection::new(BoundingBox::new(80.0, 0.0, 90.0, 10.0), 0, PERSON_CLASS_LABEL, 0.1));
+        let mut counter = PersonCounter::new(1).with_min_confidence(0.5).with_min_box_area(50.0);
+        assert_eq!(counter.update(&bounds), 1);
+    }
+}
@@ -0,0 +1,158 @@
+//! 调试可视化模块
+//!
+//! 重现模型实际看到的letterbox输入图像，并把原始图像坐标的检测结果换算回模型输入坐标绘制在上面，
+//! 便于检查预处理和坐标还原是否一致（例如半像素偏移、填充偏移算错）。
+
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+use crate::color::bounds::Detection;
+use crate::color::image::{ScaleMessage, resize_image};
+use crate::color::transform::Affine2;
+use crate::color::utils::draw_detections;
+use crate::config::DEFAULT_LETTERBOX_COLOR;
+
+/// 按`message`重现模型输入图像，并在其上绘制换算到模型输入坐标的检测结果
+///
+/// 图像缩放到`message`记录的内容尺寸后按填充偏移居中，其余区域用[DEFAULT_LETTERBOX_COLOR]填充；
+/// 检测框通过[Affine2::letterbox_inverse]的逆变换从原始图像坐标换算回模型输入坐标。
+/// 缩放信息不可逆（缩放后尺寸为0）时不绘制检测框。
+///
+/// # 参数
+/// * `image` - 原始图像，尺寸应与`message`中的原始尺寸一致
+/// * `message` - 预处理时得到的缩放信息
+/// * `detections` - 原始图像坐标的检测结果
+///
+/// # 返回值
+/// 返回尺寸为[ScaleMessage::input_size]的图像
+pub fn render_model_view(image: &DynamicImage, message: &ScaleMessage, detections: &[Detection]) -> DynamicImage {
+    let (width, height) = message.input_size();
+    let [r, g, b] = DEFAULT_LETTERBOX_COLOR;
+    let mut canvas = RgbaImage::from_pixel(width, height, Rgba([r, g, b, 0xFF]));
+    if message.s_width > 0 && message.s_height > 0 {
+        let resized = resize_image(image, message.s_width, message.s_height).to_rgba8();
+        image::imageops::replace(&mut canvas, &resized, message.pad_left as i64, message.pad_top as i64);
+    }
+    let canvas = DynamicImage::ImageRgba8(canvas);
+
+    let Some(to_model) = Affine2::letterbox_inverse(message).inverse() else {
+        return canvas;
+    };
+    let model_detections: Vec<Detection> = detections.iter().map(|detection| {
+        let mut detection = detection.clone();
+        detection.transform(&to_model);
+        detection
+    }).collect();
+    draw_detections(&canvas, &model_detections)
+}
+
+/// 将两张图像左右拼接，高度不足的一侧下方用黑色填充
+pub fn side_by_side(left: &DynamicImage, right: &DynamicImage) -> DynamicImage {
+    let (left_width, left_height) = left.dimensions();
+    let (right_width, right_height) = right.dimensions();
+    let mut canvas = RgbaImage::from_pixel(
+        left_width + right_width,
+        left_height.max(right_height),
+        Rgba([0, 0, 0, 0xFF]),
+    );
+    image::imageops::replace(&mut canvas, &left.to_rgba8(), 0, 0);
+    image::imageops::replace(&mut canvas, &right.to_rgba8(), left_width as i64, 0);
+    DynamicImage::ImageRgba8(canvas)
+}
+
+/// 左侧为在原始图像上绘制的检测结果，右侧为[render_model_view]的模型输入视图
+///
+/// 两侧的框应框住相同的内容，不一致时说明预处理与坐标还原不匹配。
+pub fn render_comparison(image: &DynamicImage, message: &ScaleMessage, detections: &[Detection]) -> DynamicImage {
+    side_by_side(&draw_detections(image, detections), &render_model_view(image, message, detections))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::bounds::BoundingBox;
+    use crate::color::image::LetterboxPadder;
+
+    /// 在`row`行上`with`与`without`不同的第一列和最后一列
+    fn changed_span(without: &RgbaImage, with: &RgbaImage, row: u32) -> Option<(u32, u32)> {
+        let changed: Vec<u32> = (0..with.width()).filter(|&x| with.get_pixel(x, row) != without.get_pixel(x, row)).collect();
+        Some((*changed.first()?, *changed.last()?))
+    }
+
+    /// 用`LetterboxPadder`缩放`width`x`height`的渐变图像到模型输入尺寸
+    fn letterboxed(width: u32, height: u32, input_width: u32, input_height: u32) -> (DynamicImage, ScaleMessage, RgbaImage) {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([(x * 255 / width) as u8, (y * 255 / height) as u8, 128, 255])
+        }));
+        let mut padder = LetterboxPadder::with_default_color(input_width, input_height).unwrap();
+        let (padded, message) = padder.pad(&image);
+        let padded = DynamicImage::ImageRgb8(padded.clone()).to_rgba8();
+        (image, message, padded)
+    }
+
+    #[test]
+    fn model_view_matches_letterbox_with_odd_padding() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 5, Rgba([255, 0, 0, 255])));
+        let mut padder = LetterboxPadder::with_default_color(8, 8).unwrap();
+        let message = padder.pad(&image).1;
+        let view = render_model_view(&image, &message, &[]).to_rgba8();
+        let [r, g, b] = DEFAULT_LETTERBOX_COLOR;
+        assert_eq!(view.dimensions(), (8, 8));
+        let column: Vec<[u8; 4]> = (0..8).map(|y| view.get_pixel(4, y).0).collect();
+        let pad = [r, g, b, 255];
+        let content = [255, 0, 0, 255];
+        assert_eq!(column, vec![pad, content, content, content, content, content, pad, pad]);
+    }
+
+    #[test]
+    fn non_square_model_view_reproduces_letterbox_and_maps_boxes() {
+        // 300x200缩放到64x43，上下填充2和3像素
+        let (image, message, padded) = letterboxed(300, 200, 64, 48);
+        assert_eq!((message.s_width, message.s_height, message.pad_top, message.pad_bottom), (64, 43, 2, 3));
+        let without = render_model_view(&image, &message, &[]).to_rgba8();
+        assert_eq!(without, padded);
+
+        // 原始图像右半部分中间的框，换算到模型输入坐标为x∈[32, 64)
+        let detection = Detection::new(BoundingBox::new(150.0, 50.0, 296.0, 150.0), 0, "person", 0.9);
+        let with = render_model_view(&image, &message, std::slice::from_ref(&detection)).to_rgba8();
+        assert_eq!(with.dimensions(), (64, 48));
+        let row = 2 + 100 * 43 / 200;
+        let (left, right) = changed_span(&without, &with, row).unwrap();
+        let scale = 64.0 / 300.0;
+        assert!((left as f32 - 150.0 * scale).abs() <= 1.5, "{}", left);
+        assert!((right as f32 - 296.0 * scale).abs() <= 1.5, "{}", right);
+
+        let comparison = render_comparison(&image, &message, &[detection]);
+        assert_eq!(comparison.dimensions(), (300 + 64, 200));
+    }
+
+    #[test]
+    fn extreme_aspect_ratios_keep_full_input_size() {
+        // 宽图缩放后只剩1行内容，填充上31下32
+        let (image, message, padded) = letterboxed(2000, 20, 64, 64);
+        assert_eq!((message.s_width, message.s_height, message.pad_top, message.pad_bottom), (64, 1, 31, 32));
+        let view = render_model_view(&image, &message, &[]).to_rgba8();
+        assert_eq!(view, padded);
+        let [r, g, b] = DEFAULT_LETTERBOX_COLOR;
+        let pad = Rgba([r, g, b, 255]);
+        assert_eq!((view.get_pixel(10, 30), view.get_pixel(10, 32)), (&pad, &pad));
+        assert_ne!(view.get_pixel(10, 31), &pad);
+
+        // 覆盖整幅原始图像的框换算后恰好覆盖内容区域
+        let to_model = Affine2::letterbox_inverse(&message).inverse().unwrap();
+        let mut detection = Detection::new(BoundingBox::new(0.0, 0.0, 2000.0, 20.0), 0, "person", 0.9);
+        detection.transform(&to_model);
+        let bbox = detection.bbox;
+        for (actual, expected) in [(bbox.x1, 0.0), (bbox.y1, 31.0), (bbox.x2, 64.0), (bbox.y2, 32.0)] {
+            assert!((actual - expected).abs() < 1e-3, "{:?}", bbox);
+        }
+
+        // 高图缩放后只剩1列内容，填充左31右32
+        let (image, message, padded) = letterboxed(10, 1000, 64, 64);
+        assert_eq!((message.s_width, message.s_height, message.pad_left, message.pad_right), (1, 64, 31, 32));
+        let detection = Detection::new(BoundingBox::new(0.0, 0.0, 10.0, 1000.0), 0, "person", 0.9);
+        let view = render_model_view(&image, &message, &[detection]);
+        assert_eq!(view.dimensions(), (64, 64));
+        assert_eq!(render_model_view(&image, &message, &[]).to_rgba8(), padded);
+        assert_eq!(render_comparison(&image, &message, &[]).dimensions(), (10 + 64, 1000));
+    }
+}
@@ -1,31 +1,45 @@
-use ort::{session::{Session, input}, value::{TensorValueType, Value}};
+use ort::{session::Session, value::{TensorValueType, Value}};
 use image::{DynamicImage, GenericImageView};
-use raqote::{DrawOptions, DrawTarget, LineJoin, PathBuilder, SolidSource, Source, StrokeStyle};
-use std::time::Instant;
-use crate::{color::{array::to_input, bounds::{Bounds, Detection}, image::{ScaleMessage, input_image, resize_image, image_to_tensor}, utils::{nms_tensor}}, config::{DETECTIONS_CAPACITY, DEFAULT_INPUT_WIDTH, DEFAULT_INPUT_HEIGHT, DEFAULT_CONFIDENCE_THRESHOLD, DEFAULT_NMS_THRESHOLD}, load_model};
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::Arc;
+use crate::{color::{array::{to_input, to_input_with_layout}, bounds::{BoundsN, Detection, OutputSpace, ThresholdZone}, image::{InputLayout, ScaleMessage, resize_image, image_to_tensor_with_background}, labels::ClassMap, utils::{nms_tensor_labeled, calibrate_tensor, process_detections_calibrated, convert_tensor_coords, apply_nms_with_options, CalibrationConfig, ConfidenceCalibration, CoordFormat, NmsMode, NmsOptions, OutputLayout, resolve_class_scores, transpose_raw_output, DrawOptions, draw_detections_with_options}}, config::{DETECTIONS_CAPACITY, DEFAULT_ALPHA_BACKGROUND, DEFAULT_MIN_INPUT_DIMENSION, DEFAULT_INPUT_WIDTH, DEFAULT_INPUT_HEIGHT, DEFAULT_CONFIDENCE_THRESHOLD, DEFAULT_NMS_THRESHOLD}, color::model::{load_checked_model, load_checked_model_with_options, load_profiled_model, ExecutionMode, ModelOptions, DEFAULT_INTRA_THREADS}, color::profile::{parse_node_timings, ProfileReport, PROFILE_SUMMARY_NODES}};
 use ndarray::{Array2, Array4, s};
-use ort::{value::Tensor, inputs};
+use crate::error::PerpleError;
+use crate::color::transform::{Affine2, SpaceGeometry};
+use crate::color::preprocess::Preprocessor;
+use ort::inputs;
+
+/// 默认结果容量（[DETECTIONS_CAPACITY]）的YOLO目标检测器，参见[YoloDetectorN]
+pub type YoloDetector = YoloDetectorN<DETECTIONS_CAPACITY>;
 
 /// YOLO目标检测器
 /// 
 /// 封装了完整的检测流程，包括图像预处理、模型推理和结果后处理。
 /// 
+/// 每帧至多输出`N`个检测结果（[BoundsN]），NMS也只考察置信度最高的`N`个候选框。
+/// 通常使用默认容量的[YoloDetector]；资源受限时可用较小的容量，
+/// 人群分析等场景可用更大的容量，例如`YoloDetectorN::<256>::new(...)`。
+/// 
 /// # 示例
 /// 
 /// ```
-/// use perple::color::{YoloDetector, load_model};
+/// use perple::color::YoloDetector;
 /// 
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-/// let model = load_model("path/to/model.onnx")?;
-/// let mut detector = YoloDetector::new(model, 640, 640)
+/// let mut detector = YoloDetector::new("path/to/model.onnx", 640, 640)?
 ///     .with_confidence_threshold(0.5)
 ///     .with_nms_threshold(0.7);
 /// # Ok(())
 /// # }
 /// ```
-pub struct YoloDetector {
+pub struct YoloDetectorN<const N: usize> {
     /// ONNX模型会话
     model: Session,
+    /// 模型文件路径，用于保存和恢复检测器状态
+    model_path: String,
+    /// 加载会话时使用的配置，性能分析会话按同样的配置加载
+    model_options: ModelOptions,
     /// 模型输入宽度
     input_width: usize,
     /// 模型输入高度
@@ -34,41 +48,348 @@ pub struct YoloDetector {
     confidence_threshold: f32,
     /// NMS（非极大值抑制）阈值，用于去除重复检测
     nms_threshold: f32,
+    /// NMS的重叠度度量
+    nms_mode: NmsMode,
+    /// NMS包含率阈值，设置后低置信度框大部分位于已保留框内时也被抑制
+    containment_threshold: Option<f32>,
+    /// 参与NMS的候选框数量上限
+    max_candidates: Option<usize>,
+    /// 是否为每个结果记录原始输出张量行号
+    debug_indices: bool,
     /// NMS处理中使用的缓存数组，避免重复分配内存
-    picked_indices: [bool; DETECTIONS_CAPACITY],
+    picked_indices: [bool; N],
+    /// 置信度校准方式，在置信度过滤和NMS之前应用
+    calibration: ConfidenceCalibration,
+    /// 置信度阈值区域，按顺序匹配
+    threshold_zones: Vec<ThresholdZone>,
+    /// 金字塔检测中放大层只保留高度不超过该值（原始图像像素）的框
+    pyramid_small_box_limit: Option<f32>,
+    /// 模型输出的坐标格式
+    coord_format: CoordFormat,
+    /// 模型输入张量的布局，默认从模型输入形状推断
+    input_layout: InputLayout,
+    /// 模型输出的参数排列方式，`None`时每次推理按输出形状推断
+    output_layout: Option<OutputLayout>,
+    /// 带透明通道的输入图像合成所用的背景色（RGB）
+    alpha_background: [u8; 3],
+    /// 输入图像宽高的下限（像素）
+    min_input_dimension: u32,
+    /// 检测结果的坐标空间
+    output_space: OutputSpace,
+    /// 缩放到模型输入尺寸之前执行的预处理
+    preprocessor: Option<Arc<dyn Preprocessor>>,
+    /// 类别名称映射，`None`时类别0为行人，其余为`class_{id}`
+    class_map: Option<ClassMap>,
+}
+
+/// 检测器全部可调参数的快照，不含模型会话本身
+/// 
+/// 由[YoloDetectorN::config]生成，用[YoloDetectorN::apply_config]应用到另一个检测器，
+/// 或用[YoloDetectorN::from_config]加载模型并应用。启用`serde`特性时可序列化，
+/// 缺少的字段使用检测器的默认值。
+/// 预处理器（[with_preprocessor](YoloDetectorN::with_preprocessor)）无法保存，应用快照时保持不变。
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct DetectorConfig {
+    /// 模型文件路径，[apply_config](YoloDetectorN::apply_config)不会重新加载模型
+    pub model_path: String,
+    /// 模型输入宽度
+    pub input_width: usize,
+    /// 模型输入高度
+    pub input_height: usize,
+    /// 置信度阈值
+    pub confidence_threshold: f32,
+    /// NMS阈值
+    pub nms_threshold: f32,
+    /// NMS的重叠度度量
+    pub nms_mode: NmsMode,
+    /// NMS包含率阈值
+    pub containment_threshold: Option<f32>,
+    /// 参与NMS的候选框数量上限
+    pub max_candidates: Option<usize>,
+    /// 是否记录原始输出张量行号
+    pub debug_indices: bool,
+    /// 置信度校准方式；检测器使用自定义校准函数时为`None`，应用时保持检测器当前的校准方式
+    pub calibration: Option<CalibrationConfig>,
+    /// 置信度阈值区域
+    pub threshold_zones: Vec<ThresholdZone>,
+    /// 金字塔检测中放大层保留的最大框高度
+    pub pyramid_small_box_limit: Option<f32>,
+    /// 模型输出的坐标格式
+    pub coord_format: CoordFormat,
+    /// 模型输入张量的布局
+    pub input_layout: InputLayout,
+    /// 显式设置的输出排列方式
+    pub output_layout: Option<OutputLayout>,
+    /// 透明像素合成所用的背景色
+    pub alpha_background: [u8; 3],
+    /// 输入图像宽高的下限
+    pub min_input_dimension: u32,
+    /// 检测结果的坐标空间
+    pub output_space: OutputSpace,
+    /// 按类别ID顺序的类别名称，见[ClassMap]
+    pub class_names: Option<Vec<String>>,
+}
+
+impl DetectorConfig {
+    /// 检查参数能否应用到检测器，[apply_config](YoloDetectorN::apply_config)在修改检测器之前调用
+    /// 
+    /// # 错误处理
+    /// 输入尺寸不合法，置信度阈值、NMS阈值不在`[0, 1]`内，或校准参数不合法时返回[PerpleError::InvalidParameter]
+    pub fn validate(&self) -> Result<(), PerpleError> {
+        validate_input_size(self.input_width, self.input_height)?;
+        for (name, value) in [("置信度阈值", self.confidence_threshold), ("NMS阈值", self.nms_threshold)] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(PerpleError::InvalidParameter(format!("{}应在[0, 1]内，实际为{}", name, value)));
+            }
+        }
+        if let Some(calibration) = &self.calibration {
+            calibration.validate()?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            model_path: String::new(),
+            input_width: DEFAULT_INPUT_WIDTH,
+            input_height: DEFAULT_INPUT_HEIGHT,
+            confidence_threshold: DEFAULT_CONFIDENCE_THRESHOLD,
+            nms_threshold: DEFAULT_NMS_THRESHOLD,
+            nms_mode: NmsMode::default(),
+            containment_threshold: None,
+            max_candidates: None,
+            debug_indices: false,
+            calibration: Some(CalibrationConfig::None),
+            threshold_zones: Vec::new(),
+            pyramid_small_box_limit: None,
+            coord_format: CoordFormat::default(),
+            input_layout: InputLayout::default(),
+            output_layout: None,
+            alpha_background: DEFAULT_ALPHA_BACKGROUND,
+            min_input_dimension: DEFAULT_MIN_INPUT_DIMENSION,
+            output_space: OutputSpace::default(),
+            class_names: None,
+        }
+    }
+}
+
+/// [YoloDetectorN]的构建器
+/// 
+/// 先在[DetectorConfig]和[ModelOptions]中准备好全部参数，[build](Self::build)时统一检查、
+/// 按会话配置加载模型并应用检测参数，参数不合法时不会加载模型。
+/// 未设置会话配置时与[YoloDetector::new]相同，使用[DEFAULT_INTRA_THREADS]个推理线程。
+/// 
+/// # 示例
+/// 
+/// ```
+/// use perple::color::{DetectorBuilder, ExecutionMode};
+/// 
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let detector = DetectorBuilder::<32>::new("path/to/model.onnx")
+///     .with_intra_threads(8)
+///     .with_execution_mode(ExecutionMode::Parallel)
+///     .with_inter_threads(2)
+///     .with_confidence_threshold(0.4)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DetectorBuilder<const N: usize = DETECTIONS_CAPACITY> {
+    config: DetectorConfig,
+    model_options: ModelOptions,
+}
+
+impl<const N: usize> DetectorBuilder<N> {
+    /// 使用默认参数和指定模型创建构建器
+    pub fn new(model_path: &str) -> Self {
+        let config = DetectorConfig { model_path: model_path.to_string(), ..DetectorConfig::default() };
+        Self::from_config(config)
+    }
+    
+    /// 以参数快照为起点创建构建器
+    pub fn from_config(config: DetectorConfig) -> Self {
+        Self { config, model_options: ModelOptions::default().with_intra_threads(DEFAULT_INTRA_THREADS) }
+    }
+    
+    /// 设置模型输入尺寸，必须是32的正整数倍
+    pub fn with_input_size(mut self, input_width: usize, input_height: usize) -> Self {
+        self.config.input_width = input_width;
+        self.config.input_height = input_height;
+        self
+    }
+    
+    /// 设置置信度阈值
+    pub fn with_confidence_threshold(mut self, threshold: f32) -> Self {
+        self.config.confidence_threshold = threshold;
+        self
+    }
+    
+    /// 设置NMS阈值
+    pub fn with_nms_threshold(mut self, threshold: f32) -> Self {
+        self.config.nms_threshold = threshold;
+        self
+    }
+    
+    /// 设置检测器参数，模型路径一并替换
+    pub fn with_detector_config(mut self, config: DetectorConfig) -> Self {
+        self.config = config;
+        self
+    }
+    
+    /// 替换全部会话配置
+    pub fn with_model_options(mut self, options: ModelOptions) -> Self {
+        self.model_options = options;
+        self
+    }
+    
+    /// 设置intra-op线程数，参见[ModelOptions::with_intra_threads]
+    pub fn with_intra_threads(mut self, threads: usize) -> Self {
+        self.model_options = self.model_options.with_intra_threads(threads);
+        self
+    }
+    
+    /// 设置inter-op线程数，参见[ModelOptions::with_inter_threads]
+    pub fn with_inter_threads(mut self, threads: usize) -> Self {
+        self.model_options = self.model_options.with_inter_threads(threads);
+        self
+    }
+    
+    /// 设置算子的执行方式，参见[ModelOptions::with_execution_mode]
+    pub fn with_execution_mode(mut self, mode: ExecutionMode) -> Self {
+        self.model_options = self.model_options.with_execution_mode(mode);
+        self
+    }
+    
+    /// 设置是否启用内存模式优化，参见[ModelOptions::with_memory_pattern]
+    pub fn with_memory_pattern(mut self, enable: bool) -> Self {
+        self.model_options = self.model_options.with_memory_pattern(enable);
+        self
+    }
+    
+    /// 设置是否启用CPU内存池，参见[ModelOptions::with_cpu_arena]
+    pub fn with_cpu_arena(mut self, enable: bool) -> Self {
+        self.model_options = self.model_options.with_cpu_arena(enable);
+        self
+    }
+    
+    /// 当前累积的检测参数
+    pub fn config(&self) -> &DetectorConfig {
+        &self.config
+    }
+    
+    /// 当前累积的会话配置
+    pub fn model_options(&self) -> &ModelOptions {
+        &self.model_options
+    }
+    
+    /// 检查参数、按会话配置加载模型并应用检测参数
+    /// 
+    /// # 错误处理
+    /// 参数不合法时返回[PerpleError::InvalidParameter]且不加载模型；
+    /// 模型加载失败或不兼容时返回的错误与[YoloDetector::new]相同
+    pub fn build(self) -> Result<YoloDetectorN<N>, PerpleError> {
+        self.load_with(|config, options| {
+            let mut detector = YoloDetectorN::with_model_options(&config.model_path, config.input_width, config.input_height, options)?;
+            detector.apply_config(config)?;
+            Ok(detector)
+        })
+    }
+    
+    /// 检查参数后用`load`按累积的参数创建结果
+    fn load_with<T>(&self, load: impl FnOnce(&DetectorConfig, &ModelOptions) -> Result<T, PerpleError>) -> Result<T, PerpleError> {
+        self.config.validate()?;
+        load(&self.config, &self.model_options)
+    }
 }
 
-impl YoloDetector {
+impl<const N: usize> YoloDetectorN<N> {
     /// 创建新的YoloDetector实例
     /// 
     /// # 参数
-    /// * `model` - 已加载的ONNX模型
-    /// * `input_width` - 模型输入图像宽度
-    /// * `input_height` - 模型输入图像高度
+    /// * `model_path` - ONNX模型文件路径
+    /// * `input_width` - 模型输入图像宽度，必须是32的正整数倍
+    /// * `input_height` - 模型输入图像高度，必须是32的正整数倍
     /// 
     /// # 返回值
     /// 返回新的YoloDetector实例
     /// 
+    /// # 错误处理
+    /// 输入尺寸不合法时返回[PerpleError::InvalidParameter]（在加载模型之前检查），
+    /// 模型加载失败或与检测流程不兼容时返回对应的错误
+    /// 
     /// # 示例
     /// 
     /// ```
-    /// use perple::color::{YoloDetector, load_model};
+    /// use perple::color::YoloDetector;
     /// 
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let model = load_model("path/to/model.onnx")?;
-    /// let detector = YoloDetector::new(model, 640, 640);
+    /// let detector = YoloDetector::new("path/to/model.onnx", 640, 640)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn new(model_path: &str, input_width: usize, input_height: usize) -> Self {
-        let model = load_model(model_path).expect("模型加载失败");
+    pub fn new(model_path: &str, input_width: usize, input_height: usize) -> Result<Self, PerpleError> {
+        validate_input_size(input_width, input_height)?;
+        let model = load_checked_model(model_path)?;
+        Ok(Self::from_session(model, model_path, input_width, input_height))
+    }
+
+    /// 按指定的会话配置（线程数、执行方式、内存设置）创建检测器
+    /// 
+    /// 参数和错误处理与[YoloDetector::new]相同，`new`使用[load_model](crate::color::load_model)的默认配置。
+    pub fn with_model_options(model_path: &str, input_width: usize, input_height: usize, options: &ModelOptions) -> Result<Self, PerpleError> {
+        validate_input_size(input_width, input_height)?;
+        let model = load_checked_model_with_options(model_path, options)?;
+        let mut detector = Self::from_session(model, model_path, input_width, input_height);
+        detector.model_options = options.clone();
+        Ok(detector)
+    }
+
+    /// 使用内嵌的默认模型创建检测器（输入尺寸640x640）
+    /// 
+    /// 需要启用`embedded-model`特性。内嵌模型没有文件路径，
+    /// [model_path](Self::model_path)返回`"<embedded>"`。
+    #[cfg(feature = "embedded-model")]
+    pub fn embedded() -> Result<Self, PerpleError> {
+        let model = crate::color::model::load_static_model()?;
+        crate::color::model::validate_model(&model)?;
+        Ok(Self::from_session(model, "<embedded>", DEFAULT_INPUT_WIDTH, DEFAULT_INPUT_HEIGHT))
+    }
+
+    /// 使用已加载的模型会话创建检测器
+    pub(crate) fn from_session(model: Session, model_path: &str, input_width: usize, input_height: usize) -> Self {
+        let input_layout = model.inputs.first()
+            .and_then(|input| input.input_type.tensor_shape())
+            .and_then(|shape| InputLayout::from_shape(shape))
+            .unwrap_or_default();
         Self {
             model,
+            model_path: model_path.to_string(),
+            model_options: ModelOptions::default().with_intra_threads(DEFAULT_INTRA_THREADS),
             input_width,
             input_height,
             confidence_threshold: DEFAULT_CONFIDENCE_THRESHOLD,
             nms_threshold: DEFAULT_NMS_THRESHOLD,
-            picked_indices: [false; DETECTIONS_CAPACITY],
+            nms_mode: NmsMode::default(),
+            containment_threshold: None,
+            max_candidates: None,
+            debug_indices: false,
+            picked_indices: [false; N],
+            calibration: ConfidenceCalibration::None,
+            threshold_zones: Vec::new(),
+            pyramid_small_box_limit: None,
+            coord_format: CoordFormat::default(),
+            input_layout,
+            output_layout: None,
+            alpha_background: DEFAULT_ALPHA_BACKGROUND,
+            min_input_dimension: DEFAULT_MIN_INPUT_DIMENSION,
+            output_space: OutputSpace::default(),
+            preprocessor: None,
+            class_map: None,
         }
     }
 
@@ -78,8 +399,8 @@ impl YoloDetector {
     /// * `model_path` - 模型文件路径
     /// 
     /// # 返回值
-    /// 返回新的YoloDetector实例
-    pub fn with_default_size(model_path: &str) -> Self {
+    /// 返回新的YoloDetector实例，错误情况与[YoloDetector::new]相同
+    pub fn with_default_size(model_path: &str) -> Result<Self, PerpleError> {
         Self::new(model_path, DEFAULT_INPUT_WIDTH, DEFAULT_INPUT_HEIGHT)
     }
 
@@ -94,47 +415,516 @@ impl YoloDetector {
     /// 返回推理结果
     pub fn infer(&mut self,
         input: &Value<TensorValueType<f32>>,
-        outputs: &mut Bounds,
+        outputs: &mut BoundsN<N>,
         message: &ScaleMessage,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(self.run_inference(input, outputs, message)?)
+    }
+
+    fn run_inference(
+        &mut self,
+        input: &Value<TensorValueType<f32>>,
+        outputs: &mut BoundsN<N>,
+        message: &ScaleMessage,
+    ) -> Result<(), PerpleError> {
         outputs.clear();
+        let nms_options = self.nms_options();
         let mut result = self.model.run(inputs!["images" => input])?;
-        nms_tensor(&mut result, outputs, message, &mut self.picked_indices, self.confidence_threshold, self.nms_threshold);
+        let raw = transpose_raw_output(&mut result)?;
+        // 原始导出没有单独的目标置信度，未显式设置排列方式时按类别分数处理
+        let raw_layout = OutputLayout::ClassScores { classes: None };
+        let layout_hint = self.output_layout.as_ref().or(raw.then_some(&raw_layout));
+        let layout = resolve_class_scores(&mut result, layout_hint)?;
+        convert_tensor_coords(&mut result, self.coord_format)?;
+        calibrate_tensor(&mut result, &self.calibration)?;
+        nms_tensor_labeled(&mut result, outputs, message, &mut self.picked_indices, self.confidence_threshold, &nms_options, &self.threshold_zones, &layout, self.class_map.as_ref())?;
         Ok(())
     }
 
     /// 设置置信度阈值
     /// 
     /// # 参数
-    /// * `threshold` - 置信度阈值 (0.0 - 1.0)
+    /// * `threshold` - 置信度阈值 (0.0 - 1.0)，超出范围时在debug构建中断言失败，release构建中截断到该范围
     /// 
     /// # 返回值
     /// 返回配置了新置信度阈值的YoloDetector实例
     pub fn with_confidence_threshold(mut self, threshold: f32) -> Self {
-        self.confidence_threshold = threshold;
+        self.set_confidence_threshold(threshold);
         self
     }
 
     /// 设置NMS阈值
     /// 
     /// # 参数
-    /// * `threshold` - NMS阈值 (0.0 - 1.0)
+    /// * `threshold` - NMS阈值 (0.0 - 1.0)，超出范围时的处理与置信度阈值相同
     /// 
     /// # 返回值
     /// 返回配置了新NMS阈值的YoloDetector实例
     pub fn with_nms_threshold(mut self, threshold: f32) -> Self {
-        self.nms_threshold = threshold;
+        self.set_nms_threshold(threshold);
+        self
+    }
+    
+    /// 设置置信度校准方式
+    /// 
+    /// 校准在提取每个框的置信度之后、置信度过滤和NMS之前应用，
+    /// 因此置信度阈值作用于校准后的分数。
+    /// 
+    /// # 参数
+    /// * `calibration` - 校准方式；温度不大于0或为NaN时按[f32::EPSILON]计算
+    /// 
+    /// # 返回值
+    /// 返回配置了校准方式的YoloDetector实例
+    pub fn with_calibration(mut self, calibration: ConfidenceCalibration) -> Self {
+        self.calibration = calibration;
+        self
+    }
+    
+    /// 设置置信度校准方式（可变引用版本）
+    pub fn set_calibration(&mut self, calibration: ConfidenceCalibration) {
+        self.calibration = calibration;
+    }
+    
+    /// 使用温度缩放校准置信度
+    ///
+    /// 等价于`set_calibration(ConfidenceCalibration::Temperature(temperature))`，
+    /// 温度可用[fit_temperature_scaling](crate::color::fit_temperature_scaling)在校准数据集上拟合。
+    /// 温度为1.0时恢复为不做校准。[detect](Self::detect)和[process_detections](Self::process_detections)
+    /// 都会应用该校准。
+    ///
+    /// # 参数
+    /// * `temperature` - 温度，必须为正数；大于1时置信度向0.5收缩
+    pub fn calibrate_temperature(&mut self, temperature: f32) {
+        debug_assert!(temperature > 0.0, "温度必须为正数: {}", temperature);
+        self.calibration = if temperature == 1.0 {
+            ConfidenceCalibration::None
+        } else {
+            ConfidenceCalibration::Temperature(temperature.max(f32::EPSILON))
+        };
+    }
+
+    /// 获取当前置信度校准方式
+    pub fn calibration(&self) -> &ConfidenceCalibration {
+        &self.calibration
+    }
+    
+    /// 设置模型输出的坐标格式
+    /// 
+    /// 默认为[CoordFormat::Xyxy]，与项目以`nms=True`导出的模型一致；
+    /// 使用未内置NMS的原始YOLO输出时应设置为[CoordFormat::CxCyWh]。
+    /// 
+    /// # 参数
+    /// * `format` - 坐标格式
+    /// 
+    /// # 返回值
+    /// 返回配置了坐标格式的YoloDetector实例
+    pub fn with_coord_format(mut self, format: CoordFormat) -> Self {
+        self.coord_format = format;
+        self
+    }
+    
+    /// 获取模型输出的坐标格式
+    pub fn coord_format(&self) -> CoordFormat {
+        self.coord_format
+    }
+    
+    /// 显式设置模型输入张量的布局
+    /// 
+    /// 默认从模型输入形状自动推断，只有在推断结果不正确时（例如动态通道维）才需要设置。
+    pub fn with_input_layout(mut self, layout: InputLayout) -> Self {
+        self.input_layout = layout;
+        self
+    }
+    
+    /// 获取模型输入张量的布局
+    pub fn input_layout(&self) -> InputLayout {
+        self.input_layout
+    }
+    
+    /// 显式设置模型输出的参数排列方式
+    /// 
+    /// 默认按每个框的参数个数推断（参见[OutputLayout::guess]），无法推断时视为[OutputLayout::BoxConfidence]；
+    /// 未内置NMS的原始导出（`[1, 4 + 类别数, 锚点数]`）没有单独的目标置信度，总是视为[OutputLayout::ClassScores]。
+    /// 使用[OutputLayout::ClassScores]时可同时限定参与选取的类别。
+    pub fn with_output_layout(mut self, layout: OutputLayout) -> Self {
+        self.output_layout = Some(layout);
+        self
+    }
+    
+    /// 获取显式设置的输出排列方式，未设置时返回`None`
+    pub fn output_layout(&self) -> Option<&OutputLayout> {
+        self.output_layout.as_ref()
+    }
+    
+    /// 设置带透明通道的输入图像合成所用的背景色
+    /// 
+    /// 默认为白色（[DEFAULT_ALPHA_BACKGROUND]）。透明区域合成到不同背景上会改变检测结果，
+    /// 应与模型训练数据的背景保持一致。早期版本直接丢弃透明通道，相当于合成到黑色上，
+    /// 需要与之一致的结果时传入`[0, 0, 0]`。
+    /// 
+    /// 该设置作用于所有推理路径，包括[Color](crate::color::core::Color)流水线原地填充输入张量的路径。
+    pub fn with_alpha_background(mut self, background: [u8; 3]) -> Self {
+        self.alpha_background = background;
+        self
+    }
+    
+    /// 获取透明像素合成所用的背景色
+    pub fn alpha_background(&self) -> [u8; 3] {
+        self.alpha_background
+    }
+    
+    /// 设置输入图像宽高的下限（像素），默认为[DEFAULT_MIN_INPUT_DIMENSION]
+    /// 
+    /// 宽或高低于该值的图像在检测前被拒绝，见[validate_image](Self::validate_image)。
+    /// 设置为0时只拒绝面积为0的图像。
+    pub fn with_min_input_dimension(mut self, min_dimension: u32) -> Self {
+        self.min_input_dimension = min_dimension;
+        self
+    }
+    
+    /// 设置输入图像宽高的下限，参见[with_min_input_dimension](Self::with_min_input_dimension)
+    pub fn set_min_input_dimension(&mut self, min_dimension: u32) {
+        self.min_input_dimension = min_dimension;
+    }
+    
+    /// 获取输入图像宽高的下限
+    pub fn min_input_dimension(&self) -> u32 {
+        self.min_input_dimension
+    }
+    
+    /// 检查输入图像能否检测
+    /// 
+    /// # 错误处理
+    /// 图像面积为0，或宽、高低于[min_input_dimension](Self::min_input_dimension)时
+    /// 返回带有图像尺寸的[PerpleError::InvalidInput]
+    pub fn validate_image(&self, image: &DynamicImage) -> Result<(), PerpleError> {
+        let (width, height) = image.dimensions();
+        validate_image_dimensions(width, height, self.min_input_dimension)
+    }
+    
+    /// 设置检测结果的坐标空间，默认为原始图像像素坐标
+    /// 
+    /// 后处理（NMS、阈值区域等）始终在原始图像坐标中进行，最后再统一转换，
+    /// 所选空间记录在输出的[BoundsN::space]中。
+    pub fn with_output_space(mut self, space: OutputSpace) -> Self {
+        self.output_space = space;
+        self
+    }
+    
+    /// 设置检测结果的坐标空间，参见[with_output_space](Self::with_output_space)
+    pub fn set_output_space(&mut self, space: OutputSpace) {
+        self.output_space = space;
+    }
+    
+    /// 获取检测结果的坐标空间
+    pub fn output_space(&self) -> OutputSpace {
+        self.output_space
+    }
+    
+    /// 将原始图像坐标下的检测结果转换到[输出坐标空间](Self::output_space)
+    /// 
+    /// # 参数
+    /// * `bounds` - 已还原到原始图像坐标的检测结果
+    /// * `message` - 预处理后图像到模型输入的缩放信息
+    /// * `width` - 原始图像宽度
+    /// * `height` - 原始图像高度
+    pub(crate) fn apply_output_space(&self, bounds: &mut BoundsN<N>, message: &ScaleMessage, width: u32, height: u32) {
+        if self.output_space == bounds.space() {
+            return;
+        }
+        let geometry = SpaceGeometry::from_message(message)
+            .with_source_transform(&self.source_transform(width, height), width, height);
+        bounds.convert_space(self.output_space, &geometry);
+    }
+    
+    /// 设置缩放到模型输入尺寸之前执行的预处理
+    /// 
+    /// 多个步骤可用[ChainedPreprocessor](crate::color::ChainedPreprocessor)组合。
+    /// 裁剪等改变几何形状的预处理会在检测完成后按[Preprocessor::to_source]
+    /// 还原坐标，检测结果始终位于原始图像坐标系中。
+    pub fn with_preprocessor<P: Preprocessor + 'static>(mut self, preprocessor: P) -> Self {
+        self.preprocessor = Some(Arc::new(preprocessor));
+        self
+    }
+    
+    /// 对图像执行预处理，未设置预处理时直接借用原图
+    pub fn preprocess<'a>(&self, image: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        match &self.preprocessor {
+            Some(preprocessor) => preprocessor.apply(image),
+            None => Cow::Borrowed(image),
+        }
+    }
+    
+    /// 预处理后图像坐标到原始图像坐标的变换
+    /// 
+    /// # 参数
+    /// * `width` - 原始图像宽度
+    /// * `height` - 原始图像高度
+    pub fn source_transform(&self, width: u32, height: u32) -> Affine2 {
+        self.preprocessor.as_ref()
+            .map_or_else(Affine2::identity, |preprocessor| preprocessor.to_source(width, height))
+    }
+    
+    /// 设置置信度阈值区域
+    /// 
+    /// 检测框使用其中心点所在区域的阈值，未落入任何区域时使用全局置信度阈值。
+    /// 区域重叠时按列表顺序取第一个匹配的区域。
+    /// 
+    /// # 参数
+    /// * `zones` - 阈值区域列表（原始图像坐标）
+    /// 
+    /// # 返回值
+    /// 返回配置了阈值区域的YoloDetector实例
+    pub fn with_threshold_zones(mut self, zones: Vec<ThresholdZone>) -> Self {
+        self.threshold_zones = zones;
+        self
+    }
+    
+    /// 追加一个置信度阈值区域，优先级低于已有区域
+    pub fn add_threshold_zone(&mut self, zone: ThresholdZone) {
+        self.threshold_zones.push(zone);
+    }
+    
+    /// 清除所有置信度阈值区域
+    pub fn clear_threshold_zones(&mut self) {
+        self.threshold_zones.clear();
+    }
+    
+    /// 获取当前的置信度阈值区域
+    pub fn threshold_zones(&self) -> &[ThresholdZone] {
+        &self.threshold_zones
+    }
+    
+    /// 设置金字塔检测中放大层保留框的最大高度
+    /// 
+    /// 放大层的目的是检出远处的小目标，大目标已由原始尺度检出，
+    /// 只保留放大层中的小框可以避免重复的大框。
+    /// 
+    /// # 参数
+    /// * `max_height` - 放大层保留框的最大高度（原始图像像素），`None`表示全部保留
+    /// 
+    /// # 返回值
+    /// 返回配置后的YoloDetector实例
+    pub fn with_pyramid_small_box_limit(mut self, max_height: Option<f32>) -> Self {
+        self.pyramid_small_box_limit = max_height;
         self
     }
     
     /// 设置置信度阈值（可变引用版本）
+    /// 
+    /// 阈值应在`[0, 1]`内：debug构建中超出范围会断言失败，release构建中截断到该范围。
     pub fn set_confidence_threshold(&mut self, threshold: f32) {
-        self.confidence_threshold = threshold;
+        debug_assert!((0.0..=1.0).contains(&threshold), "置信度阈值应在[0, 1]内: {}", threshold);
+        self.confidence_threshold = threshold.clamp(0.0, 1.0);
     }
     
     /// 设置NMS阈值（可变引用版本）
+    /// 
+    /// 阈值应在`[0, 1]`内：debug构建中超出范围会断言失败，release构建中截断到该范围。
     pub fn set_nms_threshold(&mut self, threshold: f32) {
-        self.nms_threshold = threshold;
+        debug_assert!((0.0..=1.0).contains(&threshold), "NMS阈值应在[0, 1]内: {}", threshold);
+        self.nms_threshold = threshold.clamp(0.0, 1.0);
+    }
+    
+    /// 设置NMS的重叠度度量，默认为[NmsMode::Iou]
+    /// 
+    /// 人群场景中出现嵌套的重复框时可改用[NmsMode::IoMin]。
+    pub fn with_nms_mode(mut self, mode: NmsMode) -> Self {
+        self.nms_mode = mode;
+        self
+    }
+    
+    /// 设置NMS包含率阈值，`None`表示不按包含率抑制
+    /// 
+    /// 低置信度框有不低于该比例的面积位于已保留的框内时被抑制。
+    pub fn with_containment_threshold(mut self, threshold: Option<f32>) -> Self {
+        self.containment_threshold = threshold;
+        self
+    }
+    
+    /// 获取当前NMS参数
+    pub fn nms_options(&self) -> NmsOptions {
+        NmsOptions::new(self.nms_threshold)
+            .with_mode(self.nms_mode)
+            .with_containment_threshold(self.containment_threshold)
+            .with_max_candidates(self.max_candidates)
+            .with_max_detections(Some(N))
+            .with_source_index(self.debug_indices)
+    }
+    
+    /// 设置参与NMS的候选框数量上限，`None`表示不额外限制
+    /// 
+    /// 只按置信度取前`max_candidates`个框做NMS，拥挤场景中可以缩短后处理时间。
+    pub fn with_max_candidates(mut self, max_candidates: Option<usize>) -> Self {
+        self.max_candidates = max_candidates;
+        self
+    }
+    
+    /// 设置参与NMS的候选框数量上限，参见[with_max_candidates](Self::with_max_candidates)
+    pub fn set_max_candidates(&mut self, max_candidates: Option<usize>) {
+        self.max_candidates = max_candidates;
+    }
+    
+    /// 获取参与NMS的候选框数量上限
+    pub fn max_candidates(&self) -> Option<usize> {
+        self.max_candidates
+    }
+    
+    /// 设置是否在[Detection::source_index]中记录产生每个结果的原始输出张量行号，默认关闭
+    /// 
+    /// 用于与导出脚本的输出逐行对照，定位偏移的框来自哪个锚框或网格单元。
+    /// 启用后每帧需要额外复制一份模型输出；金字塔检测的各层结果来自不同的输出张量，行号只在各自的层内有效。
+    pub fn with_debug_indices(mut self, enabled: bool) -> Self {
+        self.debug_indices = enabled;
+        self
+    }
+    
+    /// 设置是否记录原始输出张量行号，参见[with_debug_indices](Self::with_debug_indices)
+    pub fn set_debug_indices(&mut self, enabled: bool) {
+        self.debug_indices = enabled;
+    }
+    
+    /// 是否记录原始输出张量行号
+    pub fn debug_indices(&self) -> bool {
+        self.debug_indices
+    }
+    
+    /// 设置类别名称映射，检测结果的[class_name](Detection::class_name)按映射填写
+    /// 
+    /// 映射中没有的类别ID显示为`class_{id}`。未设置时类别0为行人，其余为`class_{id}`。
+    pub fn with_class_map(mut self, class_map: ClassMap) -> Self {
+        self.class_map = Some(class_map);
+        self
+    }
+    
+    /// 设置或清除类别名称映射，参见[with_class_map](Self::with_class_map)
+    pub fn set_class_map(&mut self, class_map: Option<ClassMap>) {
+        self.class_map = class_map;
+    }
+    
+    /// 获取类别名称映射
+    pub fn class_map(&self) -> Option<&ClassMap> {
+        self.class_map.as_ref()
+    }
+    
+    /// 当前全部可调参数的快照，参见[DetectorConfig]
+    pub fn config(&self) -> DetectorConfig {
+        // 逐字段解构而不使用`..`：新增字段时必须在这里决定是否保存，不会被遗漏
+        let Self {
+            model: _,
+            model_path,
+            // 会话配置只在加载模型时使用，不属于可调参数
+            model_options: _,
+            input_width,
+            input_height,
+            confidence_threshold,
+            nms_threshold,
+            nms_mode,
+            containment_threshold,
+            max_candidates,
+            debug_indices,
+            picked_indices: _,
+            calibration,
+            threshold_zones,
+            pyramid_small_box_limit,
+            coord_format,
+            input_layout,
+            output_layout,
+            alpha_background,
+            min_input_dimension,
+            output_space,
+            preprocessor: _,
+            class_map,
+        } = self;
+        DetectorConfig {
+            model_path: model_path.clone(),
+            input_width: *input_width,
+            input_height: *input_height,
+            confidence_threshold: *confidence_threshold,
+            nms_threshold: *nms_threshold,
+            nms_mode: *nms_mode,
+            containment_threshold: *containment_threshold,
+            max_candidates: *max_candidates,
+            debug_indices: *debug_indices,
+            calibration: calibration.to_config(),
+            threshold_zones: threshold_zones.clone(),
+            pyramid_small_box_limit: *pyramid_small_box_limit,
+            coord_format: *coord_format,
+            input_layout: *input_layout,
+            output_layout: output_layout.clone(),
+            alpha_background: *alpha_background,
+            min_input_dimension: *min_input_dimension,
+            output_space: *output_space,
+            class_names: class_map.as_ref().map(|map| map.names().to_vec()),
+        }
+    }
+    
+    /// 应用参数快照，`model_path`除外
+    /// 
+    /// # 错误处理
+    /// 输入尺寸不合法，或置信度阈值、NMS阈值不在`[0, 1]`内时返回[PerpleError::InvalidParameter]，
+    /// 此时检测器保持不变
+    pub fn apply_config(&mut self, config: &DetectorConfig) -> Result<(), PerpleError> {
+        let DetectorConfig {
+            model_path: _,
+            input_width,
+            input_height,
+            confidence_threshold,
+            nms_threshold,
+            nms_mode,
+            containment_threshold,
+            max_candidates,
+            debug_indices,
+            calibration,
+            threshold_zones,
+            pyramid_small_box_limit,
+            coord_format,
+            input_layout,
+            output_layout,
+            alpha_background,
+            min_input_dimension,
+            output_space,
+            class_names,
+        } = config;
+        config.validate()?;
+        
+        self.input_width = *input_width;
+        self.input_height = *input_height;
+        self.confidence_threshold = *confidence_threshold;
+        self.nms_threshold = *nms_threshold;
+        self.nms_mode = *nms_mode;
+        self.containment_threshold = *containment_threshold;
+        self.max_candidates = *max_candidates;
+        self.debug_indices = *debug_indices;
+        if let Some(calibration) = calibration {
+            self.calibration = (*calibration).into();
+        }
+        self.threshold_zones = threshold_zones.clone();
+        self.pyramid_small_box_limit = *pyramid_small_box_limit;
+        self.coord_format = *coord_format;
+        self.input_layout = *input_layout;
+        self.output_layout = output_layout.clone();
+        self.alpha_background = *alpha_background;
+        self.min_input_dimension = *min_input_dimension;
+        self.output_space = *output_space;
+        self.class_map = class_names.as_ref().map(|names| ClassMap::new(names.iter().cloned()));
+        Ok(())
+    }
+    
+    /// 加载快照中`model_path`指定的模型并应用其余参数
+    /// 
+    /// # 错误处理
+    /// 与[YoloDetector::new]和[apply_config](Self::apply_config)相同
+    pub fn from_config(config: &DetectorConfig) -> Result<Self, PerpleError> {
+        config.validate()?;
+        let mut detector = Self::new(&config.model_path, config.input_width, config.input_height)?;
+        detector.apply_config(config)?;
+        Ok(detector)
+    }
+    
+    /// 创建指定模型的[DetectorBuilder]
+    pub fn builder(model_path: &str) -> DetectorBuilder<N> {
+        DetectorBuilder::new(model_path)
     }
     
     /// 获取当前置信度阈值
@@ -156,6 +946,16 @@ impl YoloDetector {
     pub fn input_height(&self) -> usize {
         self.input_height
     }
+    
+    /// 获取模型文件路径
+    pub fn model_path(&self) -> &str {
+        &self.model_path
+    }
+
+    /// 获取加载会话时使用的配置
+    pub fn model_options(&self) -> &ModelOptions {
+        &self.model_options
+    }
 
     /// 运行模型推理
     /// 
@@ -196,6 +996,32 @@ impl YoloDetector {
         Ok(array)
     }
 
+    /// 用检测器的阈值、坐标格式和置信度校准处理[infer_old](Self::infer_old)的输出
+    /// 
+    /// 与[detect](Self::detect)相同，先应用[calibration](Self::calibration)再做置信度过滤和NMS，
+    /// 参见[process_detections_calibrated]。
+    /// 
+    /// # 参数
+    /// * `output` - 模型输出，形状为(num_boxes, 5)
+    /// * `img_width` - 原始图像宽度
+    /// * `img_height` - 原始图像高度
+    /// 
+    /// # 错误处理
+    /// `output`少于5列时返回[PerpleError::ShapeMismatch]
+    pub fn process_detections(&self, output: Array2<f32>, img_width: f32, img_height: f32) -> Result<Vec<Detection>, PerpleError> {
+        process_detections_calibrated(
+            output,
+            img_width,
+            img_height,
+            self.input_width,
+            self.input_height,
+            self.confidence_threshold,
+            self.nms_threshold,
+            self.coord_format,
+            &self.calibration,
+        )
+    }
+
     /// 完整的检测流程：从图像到检测结果（旧版）
     /// 
     /// 对输入图像执行完整的检测流程，包括预处理、推理和后处理。
@@ -207,29 +1033,157 @@ impl YoloDetector {
     /// 返回检测结果列表
     /// 
     /// # 错误处理
-    /// 如果检测过程中发生错误会返回Err
-    pub fn detect(&mut self, image: &DynamicImage) -> Result<Bounds, Box<dyn std::error::Error>> {
+    /// 如果检测过程中发生错误会返回Err；图像未通过[validate_image](Self::validate_image)时
+    /// 返回[PerpleError::InvalidInput]
+    pub fn detect(&mut self, image: &DynamicImage) -> Result<BoundsN<N>, Box<dyn std::error::Error>> {
+        Ok(self.detect_image(image)?)
+    }
+
+    pub(crate) fn detect_image(&mut self, image: &DynamicImage) -> Result<BoundsN<N>, PerpleError> {
+        self.validate_image(image)?;
+        let processed = self.preprocess(image);
+        let mut outputs = self.detect_processed(&processed)?;
+        let transform = self.source_transform(image.width(), image.height());
+        if transform != Affine2::identity() {
+            outputs.transform_all(&transform);
+        }
+        let message = self.scale_message(processed.width(), processed.height());
+        self.apply_output_space(&mut outputs, &message, image.width(), image.height());
+        outputs.set_source_dims(Some((image.width(), image.height())));
+        Ok(outputs)
+    }
+    
+    /// 将指定尺寸的图像直接缩放到模型输入尺寸时的缩放信息
+    fn scale_message(&self, width: u32, height: u32) -> ScaleMessage {
+        ScaleMessage::builder()
+            .original_size(width, height)
+            .scaled_size(self.input_width as u32, self.input_height as u32)
+            .build()
+    }
+    
+    /// 对已预处理的图像执行检测，结果位于该图像的坐标系中
+    fn detect_processed(&mut self, image: &DynamicImage) -> Result<BoundsN<N>, PerpleError> {
         // 调整图像大小
         let resized = resize_image(image, self.input_width as u32, self.input_height as u32);
         
         // 转换为张量
-        let tensor = image_to_tensor(&resized, self.input_height, self.input_width);
+        let tensor = image_to_tensor_with_background(&resized, self.input_height, self.input_width, self.alpha_background);
         
         // 运行推理
-        let input_tensor = to_input(&tensor);
-        let mut outputs = Bounds::new();
-        let scale_message = ScaleMessage {
-            o_width: image.width(),
-            o_height: image.height(),
-            s_width: self.input_width as u32,
-            s_height: self.input_height as u32,
-        };
+        let input_tensor = to_input_with_layout(&tensor, self.input_layout)?;
+        let mut outputs = BoundsN::new();
+        let scale_message = self.scale_message(image.width(), image.height());
         
-        self.infer(&input_tensor, &mut outputs, &scale_message)?;
+        self.run_inference(&input_tensor, &mut outputs, &scale_message)?;
         
         Ok(outputs)
     }
     
+    /// 检测并在图像上绘制检测结果
+    /// 
+    /// 输入图像不会被复制，绘制结果是新分配的图像。
+    /// 
+    /// # 参数
+    /// * `image` - 输入图像
+    /// * `draw_opts` - 绘制选项，例如只绘制部分类别
+    /// 
+    /// # 返回值
+    /// 返回检测结果和绘制后的图像
+    pub fn detect_and_draw(&mut self, image: &DynamicImage, draw_opts: &DrawOptions) -> Result<(BoundsN<N>, DynamicImage), PerpleError> {
+        let bounds = self.detect_image(image)?;
+        let annotated = draw_detections_with_options(image, bounds.as_slice(), draw_opts);
+        Ok((bounds, annotated))
+    }
+    
+    /// 检测、绘制全部检测结果并保存到文件
+    /// 
+    /// 图像格式由`output_path`的扩展名决定。
+    /// 
+    /// # 参数
+    /// * `image` - 输入图像
+    /// * `output_path` - 输出图像路径
+    /// 
+    /// # 返回值
+    /// 返回检测结果
+    pub fn detect_and_save(&mut self, image: &DynamicImage, output_path: &str) -> Result<BoundsN<N>, PerpleError> {
+        let (bounds, annotated) = self.detect_and_draw(image, &DrawOptions::default())?;
+        annotated.save(output_path).map_err(std::io::Error::other)?;
+        Ok(bounds)
+    }
+    
+    /// 开启ONNX Runtime性能分析执行一次检测，用于查看各算子节点的耗时
+    /// 
+    /// 按[model_path](Self::model_path)单独加载一个开启性能分析的会话执行本次检测，
+    /// 完成后切换回原会话；常规检测不受任何影响。分析会话使用与检测器相同的[ModelOptions]，
+    /// 内嵌模型没有文件路径，无法使用该方法。
+    /// 
+    /// # 参数
+    /// * `image` - 输入图像
+    /// * `output_json_path` - 性能分析JSON的保存路径
+    /// 
+    /// # 返回值
+    /// 返回JSON路径、检测结果和耗时最长的节点
+    /// 
+    /// # 错误处理
+    /// 分析会话加载失败、检测失败或JSON文件读写失败时返回对应的错误
+    pub fn run_profiled(&mut self, image: &DynamicImage, output_json_path: &str) -> Result<ProfileReport<N>, PerpleError> {
+        let output = Path::new(output_json_path);
+        // ONNX Runtime会在前缀后追加时间戳，生成在同一目录下便于随后重命名
+        let profiled = load_profiled_model(&self.model_path, &output.with_extension(""), &self.model_options)?;
+        let original = std::mem::replace(&mut self.model, profiled);
+        let result = self.detect_image(image);
+        let mut profiled = std::mem::replace(&mut self.model, original);
+        let generated = profiled.end_profiling()?;
+        let bounds = result?;
+        
+        std::fs::rename(&generated, output)?;
+        let mut slowest_nodes = parse_node_timings(&std::fs::read_to_string(output)?)?;
+        slowest_nodes.truncate(PROFILE_SUMMARY_NODES);
+        Ok(ProfileReport { path: output.to_path_buf(), bounds, slowest_nodes })
+    }
+    
+    /// 多尺度（图像金字塔）检测，用于提升小目标召回率
+    /// 
+    /// 对每个尺度依次执行检测：
+    /// - 尺度不大于1.0时对整幅图像检测（模型输入尺寸固定，更小的尺度与1.0等价，只执行一次）
+    /// - 尺度大于1.0时，等价于将图像放大后按模型输入尺寸分块：把原图切分为
+    ///   `宽/尺度 x 高/尺度`的重叠图块，逐块检测后平移回原图坐标
+    /// 
+    /// 所有尺度的检测结果在原图坐标系中合并，再统一执行一次NMS。
+    /// 尺度按顺序逐个处理，同一时刻只保留一个图块的数据。
+    /// 
+    /// # 参数
+    /// * `image` - 输入图像
+    /// * `scales` - 尺度列表，例如`&[1.0, 1.5, 2.0]`
+    /// 
+    /// # 返回值
+    /// 返回合并后的检测结果（超出容量的部分按置信度截断）
+    pub fn detect_pyramid(&mut self, image: &DynamicImage, scales: &[f32]) -> Result<BoundsN<N>, Box<dyn std::error::Error>> {
+        self.validate_image(image)?;
+        
+        // 预处理只在整幅图像上执行一次，图块检测结果最后统一还原到原始图像坐标
+        let source_transform = self.source_transform(image.width(), image.height());
+        let original_size = (image.width(), image.height());
+        let processed = self.preprocess(image);
+        let image = processed.as_ref();
+        let (img_width, img_height) = (image.width(), image.height());
+        let small_box_limit = self.pyramid_small_box_limit;
+        let mut merged = pyramid_passes(image, scales, small_box_limit, |tile| self.detect_processed(tile))?;
+        
+        merged.sort_unstable_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        let mut result = BoundsN::new();
+        for detection in apply_nms_with_options(&mut merged, &self.nms_options()) {
+            result.push(detection);
+        }
+        if source_transform != Affine2::identity() {
+            result.transform_all(&source_transform);
+        }
+        // 模型输入坐标按整幅图像缩放计算
+        let message = self.scale_message(img_width, img_height);
+        self.apply_output_space(&mut result, &message, original_size.0, original_size.1);
+        Ok(result)
+    }
+    
     /// 对一批图像执行检测
     /// 
     /// # 参数
@@ -237,7 +1191,7 @@ impl YoloDetector {
     /// 
     /// # 返回值
     /// 返回每张图像的检测结果
-    pub fn detect_batch(&mut self, images: &[DynamicImage]) -> Result<Vec<Bounds>, Box<dyn std::error::Error>> {
+    pub fn detect_batch(&mut self, images: &[DynamicImage]) -> Result<Vec<BoundsN<N>>, Box<dyn std::error::Error>> {
         let mut results = Vec::with_capacity(images.len());
         
         for image in images {
@@ -249,14 +1203,398 @@ impl YoloDetector {
     }
 }
 
+/// 按尺度依次检测整幅图像或图块，返回原图坐标系中合并前的全部检测结果
+/// 
+/// 规则见[YoloDetectorN::detect_pyramid]，`detect`对整幅图像或单个图块执行检测。
+/// `small_box_limit`只作用于尺度大于1.0的图块检测结果。
+fn pyramid_passes<const N: usize, F>(
+    image: &DynamicImage,
+    scales: &[f32],
+    small_box_limit: Option<f32>,
+    mut detect: F,
+) -> Result<Vec<Detection>, PerpleError>
+where
+    F: FnMut(&DynamicImage) -> Result<BoundsN<N>, PerpleError>,
+{
+    // 相邻图块的重叠比例，避免目标被图块边界截断
+    const TILE_OVERLAP: f32 = 0.2;
+    
+    let (img_width, img_height) = (image.width(), image.height());
+    let mut merged: Vec<Detection> = Vec::new();
+    let mut full_pass_done = false;
+    
+    for &scale in scales {
+        if scale <= 1.0 {
+            if !full_pass_done {
+                merged.extend(detect(image)?.iter().cloned());
+                full_pass_done = true;
+            }
+            continue;
+        }
+        
+        let tile_width = ((img_width as f32 / scale).ceil() as u32).clamp(1, img_width);
+        let tile_height = ((img_height as f32 / scale).ceil() as u32).clamp(1, img_height);
+        let xs = tile_offsets(img_width, tile_width, TILE_OVERLAP);
+        let ys = tile_offsets(img_height, tile_height, TILE_OVERLAP);
+        
+        for &y in &ys {
+            for &x in &xs {
+                let tile = image.crop_imm(x, y, tile_width, tile_height);
+                for detection in detect(&tile)?.iter() {
+                    if small_box_limit.is_some_and(|limit| detection.bbox.height() > limit) {
+                        continue;
+                    }
+                    let mut detection = detection.clone();
+                    detection.transform(&Affine2::translate(x as f32, y as f32));
+                    merged.push(detection);
+                }
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// YOLO模型的最大下采样步长，输入尺寸必须是它的整数倍
+const YOLO_STRIDE: usize = 32;
+
+/// 检查模型输入尺寸：宽高都必须是[YOLO_STRIDE]的正整数倍
+fn validate_input_size(input_width: usize, input_height: usize) -> Result<(), PerpleError> {
+    for (name, value) in [("宽度", input_width), ("高度", input_height)] {
+        if value == 0 || !value.is_multiple_of(YOLO_STRIDE) {
+            return Err(PerpleError::InvalidParameter(format!(
+                "模型输入{}必须是{}的正整数倍，实际为{}", name, YOLO_STRIDE, value
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 检查图像尺寸：面积不能为0，宽、高都不能低于`min_dimension`
+pub(crate) fn validate_image_dimensions(width: u32, height: u32, min_dimension: u32) -> Result<(), PerpleError> {
+    let reason = if width == 0 || height == 0 {
+        "图像面积为0".to_string()
+    } else if width.min(height) < min_dimension {
+        format!("图像宽高不能小于{}像素", min_dimension)
+    } else {
+        return Ok(());
+    };
+    Err(PerpleError::InvalidInput { width, height, reason })
+}
+
+/// 计算沿一个方向切分图块的起始偏移
+/// 
+/// 图块之间按`overlap`比例重叠，最后一块与图像边缘对齐。
+fn tile_offsets(length: u32, tile: u32, overlap: f32) -> Vec<u32> {
+    if tile >= length {
+        return vec![0];
+    }
+    let stride = ((tile as f32 * (1.0 - overlap)) as u32).max(1);
+    let mut offsets: Vec<u32> = (0..length - tile).step_by(stride as usize).collect();
+    offsets.push(length - tile);
+    offsets
+}
+
+#[cfg(feature = "config-file")]
+impl<const N: usize> YoloDetectorN<N> {
+    /// 将检测器配置保存为TOML文件
+    /// 
+    /// 保存[config](Self::config)返回的全部可调参数，包括NMS设置和类别名称；
+    /// 模型权重本身不保存，仅记录模型路径。
+    /// 
+    /// # 参数
+    /// * `path` - 输出的TOML文件路径
+    pub fn save_state(&self, path: &str) -> Result<(), PerpleError> {
+        write_state(path, &self.config())
+    }
+
+    /// 从TOML文件恢复检测器配置
+    /// 
+    /// 通过[apply_config](Self::apply_config)恢复全部可调参数，不会重新加载模型。
+    /// 如需同时加载文件中记录的模型，请使用[YoloDetector::from_state_file]。
+    /// 
+    /// # 参数
+    /// * `path` - 由[YoloDetector::save_state]生成的TOML文件路径
+    pub fn load_state(&mut self, path: &str) -> Result<(), PerpleError> {
+        self.apply_config(&read_state(path)?)
+    }
+
+    /// 从状态文件创建检测器
+    /// 
+    /// 读取状态文件，加载其中记录的模型并恢复全部配置。
+    /// 
+    /// # 参数
+    /// * `state_path` - 由[YoloDetector::save_state]生成的TOML文件路径
+    pub fn from_state_file(state_path: &str) -> Result<Self, PerpleError> {
+        Self::from_config(&read_state(state_path)?)
+    }
+}
+
+/// 将参数快照写入TOML状态文件
+#[cfg(feature = "config-file")]
+fn write_state(path: &str, config: &DetectorConfig) -> Result<(), PerpleError> {
+    let content = toml::to_string_pretty(config)
+        .map_err(|e| PerpleError::Config(format!("无法序列化检测器状态: {}", e)))?;
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// 读取TOML状态文件，缺少的字段使用默认值
+#[cfg(feature = "config-file")]
+fn read_state(path: &str) -> Result<DetectorConfig, PerpleError> {
+    let content = std::fs::read_to_string(path)?;
+    toml::from_str(&content)
+        .map_err(|e| PerpleError::Config(format!("无法解析检测器状态文件 {}: {}", path, e)))
+}
+
 // 为YoloDetector实现Debug trait
-impl std::fmt::Debug for YoloDetector {
+impl<const N: usize> std::fmt::Debug for YoloDetectorN<N> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("YoloDetector")
             .field("input_width", &self.input_width)
             .field("input_height", &self.input_height)
             .field("confidence_threshold", &self.confidence_threshold)
             .field("nms_threshold", &self.nms_threshold)
+            .field("coord_format", &self.coord_format)
+            .field("nms_mode", &self.nms_mode)
+            .field("containment_threshold", &self.containment_threshold)
+            .field("max_candidates", &self.max_candidates)
+            .field("debug_indices", &self.debug_indices)
+            .field("input_layout", &self.input_layout)
+            .field("output_layout", &self.output_layout)
+            .field("alpha_background", &self.alpha_background)
+            .field("min_input_dimension", &self.min_input_dimension)
+            .field("output_space", &self.output_space)
+            .field("preprocessor", &self.preprocessor.is_some())
+            .field("calibration", &self.calibration)
+            .field("threshold_zones", &self.threshold_zones)
+            .field("class_map", &self.class_map)
             .finish()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::bounds::{BoundingBox, Bounds};
+
+    #[test]
+    fn builder_passes_session_options_through() {
+        let builder = YoloDetector::builder("model.onnx")
+            .with_input_size(320, 256)
+            .with_confidence_threshold(0.4)
+            .with_intra_threads(8)
+            .with_inter_threads(3)
+            .with_execution_mode(ExecutionMode::Parallel)
+            .with_memory_pattern(false)
+            .with_cpu_arena(false);
+        let (config, options) = builder.load_with(|config, options| Ok((config.clone(), options.clone()))).unwrap();
+        assert_eq!(config.model_path, "model.onnx");
+        assert_eq!((config.input_width, config.input_height), (320, 256));
+        assert_eq!(config.confidence_threshold, 0.4);
+        assert_eq!(options.intra_threads(), 8);
+        assert_eq!(options.inter_threads(), Some(3));
+        assert_eq!(options.execution_mode(), ExecutionMode::Parallel);
+        assert!(!options.memory_pattern());
+        assert!(!options.cpu_arena());
+    }
+
+    #[test]
+    fn builder_defaults_match_new() {
+        let builder = DetectorBuilder::<DETECTIONS_CAPACITY>::new("model.onnx");
+        assert_eq!(builder.model_options(), &ModelOptions::default().with_intra_threads(DEFAULT_INTRA_THREADS));
+        assert_eq!(builder.config(), &DetectorConfig { model_path: "model.onnx".to_string(), ..DetectorConfig::default() });
+        // 整体替换会话配置后，逐项设置在其基础上修改
+        let options = ModelOptions::default().with_execution_mode(ExecutionMode::Parallel);
+        let builder = builder.with_model_options(options.clone()).with_inter_threads(2);
+        assert_eq!(builder.model_options(), &options.with_inter_threads(2));
+    }
+
+    #[test]
+    fn builder_rejects_invalid_parameters_before_loading() {
+        let builder = YoloDetector::builder("model.onnx").with_nms_threshold(1.5);
+        let result = builder.load_with(|_, _| -> Result<(), PerpleError> { panic!("参数不合法时不应加载模型") });
+        assert!(matches!(result, Err(PerpleError::InvalidParameter(_))));
+        assert!(matches!(YoloDetector::builder("model.onnx").with_input_size(0, 640).build(), Err(PerpleError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn image_validation_reports_offending_dimensions() {
+        let check = |width, height, min| validate_image_dimensions(width, height, min);
+        assert!(matches!(check(0, 0, DEFAULT_MIN_INPUT_DIMENSION), Err(PerpleError::InvalidInput { width: 0, height: 0, .. })));
+        // 最小尺寸为0时仍拒绝面积为0的图像
+        assert!(matches!(check(0, 0, 0), Err(PerpleError::InvalidInput { width: 0, height: 0, .. })));
+        assert!(matches!(check(1, 1, DEFAULT_MIN_INPUT_DIMENSION), Err(PerpleError::InvalidInput { width: 1, height: 1, .. })));
+        assert!(check(1, 1, 1).is_ok());
+        // 极端长宽比只受最短边的限制
+        assert!(check(10000, 2, DEFAULT_MIN_INPUT_DIMENSION).is_ok());
+        assert!(matches!(check(10000, 2, 4), Err(PerpleError::InvalidInput { width: 10000, height: 2, .. })));
+        assert!(matches!(check(2, 10000, 4), Err(PerpleError::InvalidInput { width: 2, height: 10000, .. })));
+        // 错误信息包含尺寸
+        let message = check(10000, 2, 4).unwrap_err().to_string();
+        assert!(message.contains("10000") && message.contains('4'), "{}", message);
+    }
+
+    #[test]
+    fn detect_rejects_degenerate_images() {
+        let mut detector = YoloDetector::new("module/color/yolo11n.onnx", 640, 640).unwrap();
+        for (width, height) in [(0, 0), (1, 1)] {
+            let error = detector.detect(&DynamicImage::new_rgb8(width, height)).unwrap_err();
+            assert!(matches!(error.downcast_ref(), Some(PerpleError::InvalidInput { .. })), "{}x{}", width, height);
+        }
+        assert!(detector.detect(&DynamicImage::new_rgb8(10000, 2)).is_ok());
+    }
+
+    #[test]
+    fn new_rejects_zero_input_size() {
+        // 输入尺寸在加载模型之前校验，不存在的路径也不会被访问
+        let result = YoloDetector::new("path", 0, 640);
+        assert!(matches!(result, Err(PerpleError::InvalidParameter(_))));
+        let result = YoloDetector::new("path", 640, 0);
+        assert!(matches!(result, Err(PerpleError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn new_rejects_input_size_off_stride() {
+        let result = YoloDetector::new("path", 641, 640);
+        assert!(matches!(result, Err(PerpleError::InvalidParameter(_))));
+    }
+
+    #[cfg(feature = "config-file")]
+    #[test]
+    fn state_file_round_trips_every_field() {
+        use crate::color::BoundingBox;
+
+        let config = DetectorConfig {
+            model_path: "models/custom.onnx".to_string(),
+            input_width: 320,
+            input_height: 256,
+            confidence_threshold: 0.4,
+            nms_threshold: 0.6,
+            nms_mode: NmsMode::IoMin,
+            containment_threshold: Some(0.8),
+            max_candidates: Some(100),
+            debug_indices: true,
+            calibration: Some(CalibrationConfig::Platt { a: 1.5, b: -0.5 }),
+            threshold_zones: vec![ThresholdZone::new(BoundingBox::new(0.0, 0.0, 100.0, 50.0), 0.7)],
+            pyramid_small_box_limit: Some(32.0),
+            coord_format: CoordFormat::CxCyWh,
+            input_layout: InputLayout::Nhwc,
+            output_layout: Some(OutputLayout::ClassScores { classes: Some(vec![0, 2]) }),
+            alpha_background: [0, 0, 0],
+            min_input_dimension: 8,
+            output_space: OutputSpace::Normalized,
+            class_names: Some(vec!["person".to_string(), "bicycle".to_string(), "car".to_string()]),
+        };
+        assert_ne!(config, DetectorConfig::default());
+
+        let path = std::env::temp_dir().join(format!("perple_state_{}.toml", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_state(path, &config).unwrap();
+        let loaded = read_state(path);
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.unwrap(), config);
+    }
+
+    #[cfg(feature = "config-file")]
+    #[test]
+    fn state_file_fills_missing_fields_with_defaults() {
+        let path = std::env::temp_dir().join(format!("perple_state_partial_{}.toml", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "model_path = \"m.onnx\"\nconfidence_threshold = 0.3\n").unwrap();
+        let loaded = read_state(path);
+        std::fs::remove_file(path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.confidence_threshold, 0.3);
+        assert_eq!(loaded.nms_threshold, DetectorConfig::default().nms_threshold);
+    }
+
+    #[test]
+    fn small_capacity_detector_keeps_top_results() {
+        // 阈值极低时通过NMS的框远多于4个
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_fn(640, 480, |x, y| image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x * y) % 256) as u8])));
+        let mut full = YoloDetector::new("module/color/yolo11n.onnx", 640, 640).unwrap().with_confidence_threshold(0.001);
+        let mut small = YoloDetectorN::<4>::new("module/color/yolo11n.onnx", 640, 640).unwrap().with_confidence_threshold(0.001);
+        assert_eq!(small.nms_options().max_detections, Some(4));
+
+        let expected: Vec<f32> = full.detect(&image).unwrap().iter().take(4).map(|d| d.confidence).collect();
+        assert_eq!(expected.len(), 4);
+        let bounds: BoundsN<4> = small.detect(&image).unwrap();
+        assert_eq!(bounds.len(), 4);
+        assert_eq!(bounds.iter().map(|d| d.confidence).collect::<Vec<_>>(), expected);
+    }
+
+    /// 模拟检测函数：把图像中每个亮块的外接框作为检测结果（图块坐标），
+    /// 置信度随块的宽度增大，便于区分不同尺度的结果
+    fn bright_blocks(image: &DynamicImage) -> Result<Bounds, PerpleError> {
+        let gray = image.to_luma8();
+        let mut bounds = Bounds::new();
+        for (level, class_id) in [(255u8, 0), (128, 1)] {
+            let pixels: Vec<(u32, u32)> = gray.enumerate_pixels()
+                .filter(|(_, _, p)| p.0[0] == level)
+                .map(|(x, y, _)| (x, y))
+                .collect();
+            if pixels.is_empty() {
+                continue;
+            }
+            let x1 = pixels.iter().map(|p| p.0).min().unwrap() as f32;
+            let y1 = pixels.iter().map(|p| p.1).min().unwrap() as f32;
+            let x2 = pixels.iter().map(|p| p.0).max().unwrap() as f32 + 1.0;
+            let y2 = pixels.iter().map(|p| p.1).max().unwrap() as f32 + 1.0;
+            bounds.push(Detection::new(BoundingBox::new(x1, y1, x2, y2), class_id, "block", 0.5 + (x2 - x1) / 1000.0));
+        }
+        Ok(bounds)
+    }
+
+    /// 200x100的图像，(150, 60)处有一个4x4的小亮块（类别0），(10, 5)处有一个30x30的大块（类别1），
+    /// 2.0尺度下的图块不会截断大块
+    fn pyramid_image() -> DynamicImage {
+        DynamicImage::ImageLuma8(image::GrayImage::from_fn(200, 100, |x, y| {
+            if (150..154).contains(&x) && (60..64).contains(&y) {
+                image::Luma([255])
+            } else if (10..40).contains(&x) && (5..35).contains(&y) {
+                image::Luma([128])
+            } else {
+                image::Luma([0])
+            }
+        }))
+    }
+
+    fn boxes_of(detections: &[Detection], class_id: usize) -> Vec<(f32, f32, f32, f32)> {
+        let mut boxes: Vec<_> = detections.iter()
+            .filter(|d| d.class_id == class_id)
+            .map(|d| (d.bbox.x1, d.bbox.y1, d.bbox.x2, d.bbox.y2))
+            .collect();
+        boxes.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        boxes.dedup();
+        boxes
+    }
+
+    #[test]
+    fn pyramid_tiles_map_back_to_original_coordinates() {
+        let image = pyramid_image();
+        for scales in [&[1.0][..], &[1.5], &[2.0], &[1.0, 1.5, 2.0]] {
+            let detections = pyramid_passes(&image, scales, None, bright_blocks).unwrap();
+            // 每个尺度、每个包含小亮块的图块都还原到同一个原图位置
+            assert_eq!(boxes_of(&detections, 0), [(150.0, 60.0, 154.0, 64.0)], "{:?}", scales);
+        }
+
+        // 放大尺度下图块更多，检测函数按图块调用
+        let mut calls = 0;
+        pyramid_passes(&image, &[1.0, 0.5, 2.0], None, |tile| { calls += 1; bright_blocks(tile) }).unwrap();
+        // 不大于1.0的尺度只检测一次整幅图像，2.0时为3x3个图块
+        assert_eq!(calls, 1 + 9);
+    }
+
+    #[test]
+    fn pyramid_small_box_limit_only_filters_upscaled_passes() {
+        let image = pyramid_image();
+        let detections = pyramid_passes(&image, &[1.0, 2.0], Some(20.0), bright_blocks).unwrap();
+        // 大块只来自整幅图像的检测，小块在各尺度都保留
+        assert_eq!(detections.iter().filter(|d| d.class_id == 1).count(), 1);
+        assert_eq!(boxes_of(&detections, 1), [(10.0, 5.0, 40.0, 35.0)]);
+        assert!(detections.iter().filter(|d| d.class_id == 0).count() > 1);
+
+        let upscaled_only = pyramid_passes(&image, &[2.0], Some(20.0), bright_blocks).unwrap();
+        assert!(boxes_of(&upscaled_only, 1).is_empty());
+        assert_eq!(boxes_of(&upscaled_only, 0), [(150.0, 60.0, 154.0, 64.0)]);
+    }
+}
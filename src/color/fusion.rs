@@ -0,0 +1,417 @@
+//! 多模型结果融合模块
+//!
+//! 将多条检测流水线对同一帧的检测结果合并为一份，
+//! 例如同时运行速度快和精度高的两个行人模型时互相补充漏检。
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::color::bounds::{Bounds, Detection};
+use crate::color::utils::apply_nms;
+use crate::error::PerpleError;
+use crate::utils::stream::Stream;
+
+/// 融合方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FusionMode {
+    /// 合并后执行NMS，参数为IoU阈值
+    Nms(f32),
+    /// 加权框融合：同类且重叠的框按置信度加权平均，参数为IoU阈值
+    Wbf(f32),
+}
+
+/// 一帧尚未输出的结果
+struct PendingFrame {
+    /// 第一份结果到达的时间，用于判断超时
+    first_arrival: Instant,
+    /// 按来源顺序存放的结果
+    results: Vec<Option<Bounds>>,
+}
+
+/// 多流水线结果融合器
+///
+/// 通过[submit](Self::submit)提交各来源对某一帧的结果，
+/// 再调用[poll](Self::poll)输出已就绪的帧：所有来源都已到达，
+/// 或第一份结果到达后已超过超时时间（此时只融合已到达的结果）。
+/// 帧按`frame_id`从小到大输出，某帧输出之后才到达的结果会被丢弃并计数。
+///
+/// `frame_id`由调用方分配，各来源对同一幅图像必须使用相同的编号。
+/// 也可以用[connect](Self::connect)接入各来源的结果流，由[act](Self::act)读取并融合，
+/// 此时以结果的[source_frame_seq](Bounds::source_frame_seq)作为帧号，未编号（帧序号为0）的结果不等待其他来源而直接单独输出；
+/// [Perple::fuser](crate::perple::Perple::fuser)按流水线名称完成接入。
+pub struct Fuser {
+    sources: Vec<String>,
+    /// 已接入的结果流及其来源下标
+    inputs: Vec<(usize, Arc<Mutex<Stream<Bounds>>>)>,
+    mode: FusionMode,
+    timeout: Duration,
+    pending: BTreeMap<u64, PendingFrame>,
+    output_stream: Arc<Mutex<Stream<Bounds>>>,
+    /// 已输出的最大帧号
+    last_emitted: Option<u64>,
+    late_count: usize,
+}
+
+impl Fuser {
+    /// 创建融合器，默认超时200ms
+    ///
+    /// # 参数
+    /// * `sources` - 参与融合的来源名称，例如流水线名称
+    /// * `mode` - 融合方式
+    /// * `output_stream` - 融合结果的输出流
+    pub fn new(sources: &[&str], mode: FusionMode, output_stream: Arc<Mutex<Stream<Bounds>>>) -> Self {
+        Self {
+            sources: sources.iter().map(|s| s.to_string()).collect(),
+            inputs: Vec::new(),
+            mode,
+            timeout: Duration::from_millis(200),
+            pending: BTreeMap::new(),
+            output_stream,
+            last_emitted: None,
+            late_count: 0,
+        }
+    }
+
+    /// 设置等待其余来源的超时时间
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 提交某个来源对一帧的检测结果
+    ///
+    /// 同一来源对同一帧重复提交时以最后一次为准。
+    ///
+    /// # 错误处理
+    /// 来源名称不在构造时给出的列表中时返回[PerpleError::InvalidParameter]
+    pub fn submit(&mut self, source: &str, frame_id: u64, bounds: Bounds) -> Result<(), PerpleError> {
+        let index = self.source_index(source)?;
+        self.submit_index(index, frame_id, bounds);
+        Ok(())
+    }
+
+    /// 将来源的结果流接入融合器，此后由[act](Self::act)读取该流中的结果
+    ///
+    /// 结果流中的元素会被取走，接入后不应再由其他消费者读取。
+    ///
+    /// # 错误处理
+    /// 来源名称不在构造时给出的列表中时返回[PerpleError::InvalidParameter]
+    pub fn connect(&mut self, source: &str, stream: Arc<Mutex<Stream<Bounds>>>) -> Result<(), PerpleError> {
+        let index = self.source_index(source)?;
+        self.inputs.retain(|(i, _)| *i != index);
+        self.inputs.push((index, stream));
+        Ok(())
+    }
+
+    /// 读取已接入结果流中的全部结果并提交，再输出已就绪的帧，适合放在[MultiLoop](crate::utils::muloop::MultiLoop)中循环调用
+    ///
+    /// # 返回值
+    /// 本次写入输出流的帧数
+    pub fn act(&mut self) -> usize {
+        let mut emitted = 0;
+        for input in 0..self.inputs.len() {
+            let (index, stream) = (self.inputs[input].0, Arc::clone(&self.inputs[input].1));
+            let mut stream = stream.lock().unwrap();
+            let mut passed_through = 0;
+            while let Some(bounds) = stream.read() {
+                match bounds.source_frame_seq() {
+                    // 未编号的结果无法与其他来源对齐，单独输出
+                    0 => passed_through += usize::from(self.emit(0, vec![bounds])),
+                    seq => self.submit_index(index, seq, bounds),
+                }
+            }
+            emitted += passed_through;
+        }
+        emitted + self.poll()
+    }
+
+    fn source_index(&self, source: &str) -> Result<usize, PerpleError> {
+        self.sources.iter()
+            .position(|s| s == source)
+            .ok_or_else(|| PerpleError::InvalidParameter(format!("未知的融合来源: {}", source)))
+    }
+
+    fn submit_index(&mut self, index: usize, frame_id: u64, bounds: Bounds) {
+        if self.last_emitted.is_some_and(|last| frame_id <= last) {
+            self.late_count += 1;
+            log::debug!("丢弃迟到的检测结果: source={} frame_id={}", self.sources[index], frame_id);
+            return;
+        }
+
+        let source_count = self.sources.len();
+        let frame = self.pending.entry(frame_id).or_insert_with(|| PendingFrame {
+            first_arrival: Instant::now(),
+            results: (0..source_count).map(|_| None).collect(),
+        });
+        frame.results[index] = Some(bounds);
+    }
+
+    /// 输出所有已就绪的帧
+    ///
+    /// 帧按编号顺序输出：编号较大的帧即使已就绪，也要等较早的帧就绪或超时之后才输出。
+    ///
+    /// # 返回值
+    /// 本次写入输出流的帧数
+    pub fn poll(&mut self) -> usize {
+        let mut emitted = 0;
+        while let Some(entry) = self.pending.first_entry() {
+            let frame = entry.get();
+            let complete = frame.results.iter().all(Option::is_some);
+            if !complete && frame.first_arrival.elapsed() < self.timeout {
+                break;
+            }
+            let frame_id = *entry.key();
+            let frame = entry.remove();
+            if !complete {
+                log::debug!("融合等待超时，使用部分结果: frame_id={}", frame_id);
+            }
+
+            self.last_emitted = Some(frame_id);
+            if self.emit(frame_id, frame.results.into_iter().flatten().collect()) {
+                emitted += 1;
+            }
+        }
+        emitted
+    }
+
+    /// 融合一帧的结果并写入输出流，返回是否写入成功
+    fn emit(&self, frame_id: u64, results: Vec<Bounds>) -> bool {
+        let mut fused = self.fuse(results);
+        fused.set_source_frame_seq(frame_id);
+        match self.output_stream.lock().unwrap().write(fused) {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!("融合结果写入失败: frame_id={} error={}", frame_id, e);
+                false
+            }
+        }
+    }
+
+    /// 合并多个来源的结果
+    ///
+    /// 任一来源降级时融合结果也标记为降级；坐标空间和图像尺寸取自第一个来源（帧序号由调用方设置）。
+    fn fuse(&self, results: Vec<Bounds>) -> Bounds {
+        let degraded = results.iter().any(Bounds::is_degraded);
+        let space = results.first().map(Bounds::space).unwrap_or_default();
+        if results.iter().any(|bounds| bounds.space() != space) {
+            log::warn!("融合来源的坐标空间不一致，按第一个来源的{:?}输出", space);
+        }
+        let source_dims = results.iter().find_map(Bounds::source_dims);
+
+        let mut detections: Vec<Detection> = results.into_iter().flat_map(|bounds| bounds.into_iter()).collect();
+        detections.sort_unstable_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        let mut fused: Bounds = match self.mode {
+            FusionMode::Nms(iou_threshold) => apply_nms(&mut detections, iou_threshold, None).into_iter().collect(),
+            FusionMode::Wbf(iou_threshold) => {
+                let merged: Bounds = detections.into_iter().collect();
+                merged.cluster_and_average(iou_threshold).into_iter().collect()
+            }
+        };
+        fused.set_degraded(degraded);
+        fused.set_space(space);
+        fused.set_source_dims(source_dims);
+        fused
+    }
+
+    /// 超时后才到达而被丢弃的结果数
+    pub fn late_count(&self) -> usize {
+        self.late_count
+    }
+
+    /// 尚未输出的帧数
+    pub fn pending_frames(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// 融合结果的输出流
+    pub fn output_stream(&self) -> &Arc<Mutex<Stream<Bounds>>> {
+        &self.output_stream
+    }
+}
+
+impl std::fmt::Debug for Fuser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Fuser")
+            .field("sources", &self.sources)
+            .field("inputs", &self.inputs.len())
+            .field("mode", &self.mode)
+            .field("timeout", &self.timeout)
+            .field("pending_frames", &self.pending.len())
+            .field("last_emitted", &self.last_emitted)
+            .field("late_count", &self.late_count)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::bounds::{BoundingBox, OutputSpace};
+    use crate::config::PERSON_CLASS_LABEL;
+    use std::thread;
+
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    fn person(x: f32, confidence: f32) -> Detection {
+        Detection::new(BoundingBox::new(x, 0.0, x + 10.0, 10.0), 0, PERSON_CLASS_LABEL, confidence)
+    }
+
+    fn frame(seq: u64, detections: &[Detection]) -> Bounds {
+        let mut bounds: Bounds = detections.iter().cloned().collect();
+        bounds.set_source_frame_seq(seq);
+        bounds
+    }
+
+    fn fuser(mode: FusionMode) -> Fuser {
+        Fuser::new(&["fast", "accurate"], mode, Arc::new(Mutex::new(Stream::new()))).with_timeout(TIMEOUT)
+    }
+
+    /// 取出输出流中的全部结果，返回每帧的帧号和按左上角横坐标排序的框
+    fn drain(fuser: &Fuser) -> Vec<(u64, Vec<f32>)> {
+        let mut stream = fuser.output_stream().lock().unwrap();
+        std::iter::from_fn(|| stream.read())
+            .map(|bounds| {
+                let mut xs: Vec<f32> = bounds.iter().map(|d| d.bbox.x1).collect();
+                xs.sort_by(f32::total_cmp);
+                (bounds.source_frame_seq(), xs)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matching_frames_are_fused_with_nms() {
+        let mut fuser = fuser(FusionMode::Nms(0.5));
+        fuser.submit("fast", 1, frame(0, &[person(0.0, 0.6), person(50.0, 0.8)])).unwrap();
+        assert_eq!(fuser.poll(), 0);
+        fuser.submit("accurate", 1, frame(0, &[person(1.0, 0.9)])).unwrap();
+        assert_eq!(fuser.poll(), 1);
+        // 两个模型重叠的框只保留置信度较高的一个
+        assert_eq!(drain(&fuser), [(1, vec![1.0, 50.0])]);
+        assert_eq!(fuser.pending_frames(), 0);
+    }
+
+    #[test]
+    fn wbf_averages_overlapping_boxes() {
+        let mut fuser = fuser(FusionMode::Wbf(0.5));
+        fuser.submit("fast", 3, frame(0, &[person(0.0, 0.5)])).unwrap();
+        fuser.submit("accurate", 3, frame(0, &[person(2.0, 0.5), person(40.0, 0.7)])).unwrap();
+        assert_eq!(fuser.poll(), 1);
+        let fused = fuser.output_stream().lock().unwrap().read().unwrap();
+        assert_eq!(fused.len(), 2);
+        let merged = fused.iter().find(|d| d.bbox.x1 < 20.0).unwrap();
+        assert!((merged.bbox.x1 - 1.0).abs() < 1e-4 && (merged.bbox.x2 - 11.0).abs() < 1e-4, "{:?}", merged.bbox);
+        assert_eq!(merged.confidence, 0.5);
+    }
+
+    #[test]
+    fn missing_source_is_fused_after_timeout_and_late_result_dropped() {
+        let mut fuser = fuser(FusionMode::Nms(0.5));
+        fuser.submit("fast", 1, frame(0, &[person(0.0, 0.6)])).unwrap();
+        assert_eq!(fuser.poll(), 0);
+        assert_eq!(fuser.pending_frames(), 1);
+
+        thread::sleep(TIMEOUT * 2);
+        assert_eq!(fuser.poll(), 1);
+        assert_eq!(drain(&fuser), [(1, vec![0.0])]);
+
+        // 超时输出之后才到达的结果被丢弃，不会再产生一帧
+        fuser.submit("accurate", 1, frame(0, &[person(30.0, 0.9)])).unwrap();
+        assert_eq!(fuser.late_count(), 1);
+        assert_eq!(fuser.pending_frames(), 0);
+        assert_eq!(fuser.poll(), 0);
+    }
+
+    #[test]
+    fn later_frame_waits_for_earlier_frame() {
+        let mut fuser = fuser(FusionMode::Nms(0.5));
+        fuser.submit("fast", 1, frame(0, &[person(0.0, 0.6)])).unwrap();
+        fuser.submit("fast", 2, frame(0, &[person(10.0, 0.6)])).unwrap();
+        fuser.submit("accurate", 2, frame(0, &[person(40.0, 0.6)])).unwrap();
+        assert_eq!(fuser.poll(), 0);
+
+        fuser.submit("accurate", 1, frame(0, &[])).unwrap();
+        assert_eq!(fuser.poll(), 2);
+        assert_eq!(drain(&fuser), [(1, vec![0.0]), (2, vec![10.0, 40.0])]);
+    }
+
+    #[test]
+    fn unknown_source_is_rejected() {
+        let mut fuser = fuser(FusionMode::Nms(0.5));
+        assert!(matches!(fuser.submit("slow", 1, Bounds::new()), Err(PerpleError::InvalidParameter(_))));
+        let stream = Arc::new(Mutex::new(Stream::new()));
+        assert!(matches!(fuser.connect("slow", stream), Err(PerpleError::InvalidParameter(_))));
+        assert_eq!(fuser.pending_frames(), 0);
+    }
+
+    #[test]
+    fn act_aligns_connected_streams_by_frame_seq() {
+        let fast = Arc::new(Mutex::new(Stream::new()));
+        let accurate = Arc::new(Mutex::new(Stream::new()));
+        let mut fuser = fuser(FusionMode::Nms(0.5));
+        fuser.connect("fast", Arc::clone(&fast)).unwrap();
+        fuser.connect("accurate", Arc::clone(&accurate)).unwrap();
+
+        // 精确模型缺少第2帧
+        for (seq, x) in [(1, 0.0), (2, 20.0)] {
+            fast.lock().unwrap().write(frame(seq, &[person(x, 0.6)])).unwrap();
+        }
+        accurate.lock().unwrap().write(frame(1, &[person(60.0, 0.9)])).unwrap();
+        assert_eq!(fuser.act(), 1);
+        assert_eq!(drain(&fuser), [(1, vec![0.0, 60.0])]);
+        assert!(fast.lock().unwrap().read().is_none());
+
+        thread::sleep(TIMEOUT * 2);
+        assert_eq!(fuser.act(), 1);
+        assert_eq!(drain(&fuser), [(2, vec![20.0])]);
+
+        accurate.lock().unwrap().write(frame(2, &[person(60.0, 0.9)])).unwrap();
+        assert_eq!(fuser.act(), 0);
+        assert_eq!(fuser.late_count(), 1);
+    }
+
+    #[test]
+    fn fused_frame_keeps_source_metadata() {
+        let mut fuser = fuser(FusionMode::Nms(0.5));
+        let mut fast = frame(0, &[person(0.0, 0.6)]);
+        fast.set_space(OutputSpace::ModelInputPixels);
+        fast.set_source_dims(Some((640, 480)));
+        let mut accurate = frame(0, &[person(40.0, 0.9)]);
+        accurate.set_space(OutputSpace::ModelInputPixels);
+        accurate.set_degraded(true);
+        fuser.submit("fast", 4, fast).unwrap();
+        fuser.submit("accurate", 4, accurate).unwrap();
+        assert_eq!(fuser.poll(), 1);
+
+        let fused = fuser.output_stream().lock().unwrap().read().unwrap();
+        assert_eq!(fused.source_frame_seq(), 4);
+        assert!(fused.is_degraded());
+        assert_eq!(fused.space(), OutputSpace::ModelInputPixels);
+        assert_eq!(fused.source_dims(), Some((640, 480)));
+    }
+
+    #[test]
+    fn act_passes_unsequenced_frames_through() {
+        let fast = Arc::new(Mutex::new(Stream::new()));
+        let accurate = Arc::new(Mutex::new(Stream::new()));
+        let mut fuser = fuser(FusionMode::Nms(0.5));
+        fuser.connect("fast", Arc::clone(&fast)).unwrap();
+        fuser.connect("accurate", Arc::clone(&accurate)).unwrap();
+
+        for x in [0.0, 20.0] {
+            fast.lock().unwrap().write(frame(0, &[person(x, 0.6)])).unwrap();
+        }
+        accurate.lock().unwrap().write(frame(0, &[person(60.0, 0.9)])).unwrap();
+        // 未编号的结果不会合并到同一帧，也不会被当作迟到结果丢弃
+        assert_eq!(fuser.act(), 3);
+        assert_eq!(drain(&fuser), [(0, vec![0.0]), (0, vec![20.0]), (0, vec![60.0])]);
+        assert_eq!(fuser.late_count(), 0);
+        assert_eq!(fuser.pending_frames(), 0);
+
+        // 之后到达的编号结果照常对齐
+        fast.lock().unwrap().write(frame(1, &[person(0.0, 0.6)])).unwrap();
+        accurate.lock().unwrap().write(frame(1, &[person(60.0, 0.9)])).unwrap();
+        assert_eq!(fuser.act(), 1);
+        assert_eq!(drain(&fuser), [(1, vec![0.0, 60.0])]);
+    }
+}
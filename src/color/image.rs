@@ -2,17 +2,151 @@
 //! 
 //! 提供图像加载、调整大小、转换为张量等图像处理功能。
 
-use image::{DynamicImage, imageops::FilterType};
-use ndarray::{Array, Array4};
+use image::{DynamicImage, GenericImageView, GrayImage, Rgb, RgbImage, RgbaImage, imageops::FilterType};
+use ndarray::{Array, Array4, s};
 use ort::value::{Tensor, TensorValueType, Value};
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read, Seek, SeekFrom};
 use std::path::Path;
 
+use crate::color::bounds::Detection;
+use crate::config::{DEFAULT_ALPHA_BACKGROUND, DEFAULT_LETTERBOX_COLOR, LOAD_PROGRESS_INTERVAL};
+use crate::error::PerpleError;
 
+
+/// 模型输入张量的内存布局
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum InputLayout {
+    /// `[1, 3, H, W]`，YOLO导出模型的默认布局
+    #[default]
+    Nchw,
+    /// `[1, H, W, 3]`，常见于TensorFlow导出的模型
+    Nhwc,
+}
+
+impl InputLayout {
+    /// 根据模型输入形状推断布局：第2维为3时为NCHW，最后一维为3时为NHWC，无法判断时返回`None`
+    pub fn from_shape(shape: &[i64]) -> Option<Self> {
+        if shape.len() != 4 {
+            return None;
+        }
+        match (shape[1], shape[3]) {
+            (3, _) => Some(InputLayout::Nchw),
+            (_, 3) => Some(InputLayout::Nhwc),
+            _ => None,
+        }
+    }
+}
+
+/// 原始图像与模型输入之间的缩放信息，用于将检测结果还原到原始图像坐标
+/// 
+/// 推荐使用[ScaleMessage::builder]构造。
+/// 
+/// # 示例
+/// 
+/// ```
+/// use perple::color::image::ScaleMessage;
+/// 
+/// let message = ScaleMessage::builder()
+///     .original_size(1920, 1080)
+///     .scaled_size(640, 640)
+///     .build();
+/// assert_eq!(message, ScaleMessage {
+///     o_width: 1920, o_height: 1080, s_width: 640, s_height: 640,
+///     pad_left: 0, pad_top: 0, pad_right: 0, pad_bottom: 0,
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ScaleMessage {
+    /// 原始图像宽度
     pub o_width: u32,
+    /// 原始图像高度
     pub o_height: u32,
+    /// 缩放后图像内容的宽度（不含填充）
     pub s_width: u32,
+    /// 缩放后图像内容的高度（不含填充）
     pub s_height: u32,
+    /// 模型输入左侧的填充宽度
+    pub pad_left: u32,
+    /// 模型输入上方的填充高度
+    pub pad_top: u32,
+    /// 模型输入右侧的填充宽度，宽度差为奇数时比左侧多1
+    pub pad_right: u32,
+    /// 模型输入下方的填充高度，高度差为奇数时比上方多1
+    pub pad_bottom: u32,
+}
+
+impl ScaleMessage {
+    /// 创建缩放信息构建器
+    pub fn builder() -> ScaleMessageBuilder {
+        ScaleMessageBuilder::default()
+    }
+    
+    /// 模型输入尺寸`(宽, 高)`，即缩放后的内容加上四周的填充
+    pub fn input_size(&self) -> (u32, u32) {
+        (
+            self.pad_left + self.s_width + self.pad_right,
+            self.pad_top + self.s_height + self.pad_bottom,
+        )
+    }
+}
+
+/// [ScaleMessage]的构建器
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScaleMessageBuilder {
+    original_size: Option<(u32, u32)>,
+    scaled_size: Option<(u32, u32)>,
+    padding: (u32, u32),
+    input_size: Option<(u32, u32)>,
+}
+
+impl ScaleMessageBuilder {
+    /// 设置原始图像尺寸
+    pub fn original_size(mut self, width: u32, height: u32) -> Self {
+        self.original_size = Some((width, height));
+        self
+    }
+    
+    /// 设置缩放后图像内容的尺寸（不含填充）
+    pub fn scaled_size(mut self, width: u32, height: u32) -> Self {
+        self.scaled_size = Some((width, height));
+        self
+    }
+    
+    /// 设置保持宽高比缩放时左侧和上方的填充，默认无填充
+    /// 
+    /// 未设置[input_size](Self::input_size)时右侧和下方的填充与左侧和上方相同。
+    pub fn padding(mut self, left: u32, top: u32) -> Self {
+        self.padding = (left, top);
+        self
+    }
+    
+    /// 设置模型输入尺寸（含填充），右侧和下方的填充由此计算，用于宽高差为奇数的不对称填充
+    pub fn input_size(mut self, width: u32, height: u32) -> Self {
+        self.input_size = Some((width, height));
+        self
+    }
+    
+    /// 构建缩放信息
+    /// 
+    /// # Panics
+    /// 未调用[original_size](Self::original_size)或[scaled_size](Self::scaled_size)时panic
+    pub fn build(self) -> ScaleMessage {
+        let (o_width, o_height) = self.original_size.expect("ScaleMessage缺少原始图像尺寸");
+        let (s_width, s_height) = self.scaled_size.expect("ScaleMessage缺少缩放后尺寸");
+        let (pad_left, pad_top) = self.padding;
+        let (pad_right, pad_bottom) = match self.input_size {
+            Some((width, height)) => (
+                width.saturating_sub(pad_left + s_width),
+                height.saturating_sub(pad_top + s_height),
+            ),
+            None => (pad_left, pad_top),
+        };
+        ScaleMessage { o_width, o_height, s_width, s_height, pad_left, pad_top, pad_right, pad_bottom }
+    }
 }
 
 /// 加载图像文件
@@ -50,6 +184,122 @@ pub fn load_image(path: &str) -> Result<DynamicImage, Box<dyn std::error::Error>
     Ok(img)
 }
 
+/// 加载图像文件，读取过程中报告已读取的字节数
+/// 
+/// 用于从慢速存储加载大图像时显示进度：每读取[LOAD_PROGRESS_INTERVAL]字节调用一次`on_bytes_read`，
+/// 解码结束时再报告一次剩余部分。参数为累计读取的字节数，识别格式时的回退重读也计入在内，
+/// 因此可能略大于文件大小。图像格式按文件内容识别。
+/// 
+/// # 参数
+/// * `path` - 图像文件路径
+/// * `on_bytes_read` - 进度回调，参数为累计读取的字节数
+/// 
+/// # 错误处理
+/// - 文件无法打开或读取失败时返回[PerpleError::Io]
+/// - 格式无法识别、不受支持或内容无法解码（包括文件被截断）时返回[PerpleError::UnsupportedFormat]，
+///   `format`为识别出的格式（无法识别时为文件路径）
+pub fn load_image_with_progress<F: Fn(usize)>(path: &str, on_bytes_read: F) -> Result<DynamicImage, PerpleError> {
+    let progress = Progress { bytes_read: Cell::new(0), reported: Cell::new(0), on_bytes_read };
+    let reader = ProgressReader { inner: File::open(path)?, progress: &progress };
+    let reader = image::ImageReader::new(BufReader::new(reader)).with_guessed_format()?;
+    let format = reader.format().map_or_else(|| path.to_string(), |format| format!("{:?}", format));
+    let decoded = reader.decode();
+    progress.finish();
+    decoded.map_err(|e| match e {
+        image::ImageError::IoError(e) if !matches!(e.kind(), ErrorKind::UnexpectedEof | ErrorKind::InvalidData) => PerpleError::Io(e),
+        e => PerpleError::UnsupportedFormat { format, suggestion: format!("无法解码图像: {}", e) },
+    })
+}
+
+/// 累计读取的字节数，按[LOAD_PROGRESS_INTERVAL]报告进度
+struct Progress<F> {
+    bytes_read: Cell<usize>,
+    /// 上次报告时的字节数
+    reported: Cell<usize>,
+    on_bytes_read: F,
+}
+
+impl<F: Fn(usize)> Progress<F> {
+    /// 记录新读取的字节，单次读取可能跨过多个间隔，每跨过一个间隔报告一次
+    fn add(&self, read: usize) {
+        self.bytes_read.set(self.bytes_read.get() + read);
+        while self.bytes_read.get() - self.reported.get() >= LOAD_PROGRESS_INTERVAL {
+            self.reported.set(self.reported.get() + LOAD_PROGRESS_INTERVAL);
+            (self.on_bytes_read)(self.reported.get());
+        }
+    }
+    
+    /// 报告最后不足一个间隔的部分
+    fn finish(&self) {
+        if self.bytes_read.get() > self.reported.get() {
+            self.reported.set(self.bytes_read.get());
+            (self.on_bytes_read)(self.bytes_read.get());
+        }
+    }
+}
+
+/// 统计读取字节数的读取器
+struct ProgressReader<'a, R, F> {
+    inner: R,
+    progress: &'a Progress<F>,
+}
+
+impl<R: Read, F: Fn(usize)> Read for ProgressReader<'_, R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.add(read);
+        Ok(read)
+    }
+}
+
+impl<R: Seek, F> Seek for ProgressReader<'_, R, F> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// 将任意格式的图像转换为模型输入所需的RGB8图像
+/// 
+/// - RGB8：直接拷贝
+/// - 灰度（Luma8）：将单通道复制到三个通道
+/// - 带透明通道（RGBA8、LumaA8以及16位/浮点的带透明通道格式）：与`background`按透明度合成，
+///   而不是直接丢弃透明通道
+/// - 其余格式（16位灰度、16位RGB等）：回退到`to_rgb8()`
+/// 
+/// # 参数
+/// * `img` - 输入图像
+/// * `background` - 透明像素合成所用的背景色（RGB）
+/// 
+/// # 返回值
+/// 返回与输入尺寸相同的RGB8图像
+pub fn to_rgb_input(img: &DynamicImage, background: [u8; 3]) -> RgbImage {
+    match img {
+        DynamicImage::ImageRgb8(rgb) => rgb.clone(),
+        DynamicImage::ImageLuma8(gray) => {
+            let data = gray.as_raw().iter().flat_map(|&v| [v, v, v]).collect();
+            RgbImage::from_raw(gray.width(), gray.height(), data).expect("缓冲区大小与图像尺寸一致")
+        }
+        DynamicImage::ImageRgba8(rgba) => composite_rgba(rgba, background),
+        img if img.color().has_alpha() => composite_rgba(&img.to_rgba8(), background),
+        img => img.to_rgb8(),
+    }
+}
+
+/// 将RGBA8图像按透明度与背景色合成为RGB8图像
+/// 
+/// 计算`c * a + bg * (255 - a)`后除以255并四舍五入，完全不透明的像素保持原值。
+fn composite_rgba(rgba: &RgbaImage, background: [u8; 3]) -> RgbImage {
+    let mut data = Vec::with_capacity(rgba.as_raw().len() / 4 * 3);
+    for pixel in rgba.as_raw().chunks_exact(4) {
+        let alpha = pixel[3] as u32;
+        for channel in 0..3 {
+            let blended = pixel[channel] as u32 * alpha + background[channel] as u32 * (255 - alpha);
+            data.push(((blended + 127) / 255) as u8);
+        }
+    }
+    RgbImage::from_raw(rgba.width(), rgba.height(), data).expect("缓冲区大小与图像尺寸一致")
+}
+
 /// 调整图像大小以适应模型输入
 /// 
 /// 使用CatmullRom插值算法将图像调整为指定尺寸。
@@ -65,25 +315,258 @@ pub fn resize_image(img: &DynamicImage, width: u32, height: u32) -> DynamicImage
     img.resize_exact(width, height, FilterType::CatmullRom)
 }
 
-pub fn scale_image(img: &DynamicImage, target_width: u32, target_height: u32) -> (DynamicImage, ScaleMessage) {
-    let original_width = img.width();
-    let original_height = img.height();
-    
-    let scale_width = target_width;
-    let scale_height = target_height;
+/// Catmull-Rom卷积核，与`image`库的[FilterType::CatmullRom]相同
+fn catmull_rom(x: f32) -> f32 {
+    let a = x.abs();
+    let k = if a < 1.0 {
+        9.0 * a.powi(3) - 15.0 * a.powi(2) + 6.0
+    } else if a < 2.0 {
+        -3.0 * a.powi(3) + 15.0 * a.powi(2) - 24.0 * a + 12.0
+    } else {
+        0.0
+    };
+    k / 6.0
+}
+
+/// 一个方向上的重采样权重，只取决于缩放前后的长度
+#[derive(Debug, Clone, Default)]
+struct SampleWeights {
+    /// 每个输出位置的`(首个输入位置, 权重起始下标, 权重个数)`
+    spans: Vec<(usize, usize, usize)>,
+    /// 归一化后的权重
+    weights: Vec<f32>,
+}
+
+impl SampleWeights {
+    /// 按`image`库的采样方式计算从`src`缩放到`dst`的权重
+    fn catmull_rom(src: u32, dst: u32) -> Self {
+        let ratio = src as f32 / dst as f32;
+        let sratio = ratio.max(1.0);
+        let support = 2.0 * sratio;
+        let mut result = Self::default();
+        for out in 0..dst {
+            let center = (out as f32 + 0.5) * ratio;
+            let left = ((center - support).floor() as i64).clamp(0, src as i64 - 1);
+            let right = ((center + support).ceil() as i64).clamp(left + 1, src as i64);
+            let center = center - 0.5;
+            let start = result.weights.len();
+            result.weights.extend((left..right).map(|i| catmull_rom((i as f32 - center) / sratio)));
+            let sum: f32 = result.weights[start..].iter().sum();
+            for weight in &mut result.weights[start..] {
+                *weight /= sum;
+            }
+            result.spans.push((left as usize, start, (right - left) as usize));
+        }
+        result
+    }
+}
+
+/// 可复用缓冲区的RGB8图像缩放器，结果与[resize_image]一致
+/// 
+/// 采样权重按`(原始尺寸, 目标尺寸)`缓存，中间结果和输出图像的缓冲区在尺寸不变时复用，
+/// 因此固定分辨率的输入流每帧缩放不再分配内存；尺寸变化时自动重新计算。
+#[derive(Debug, Clone, Default)]
+pub struct InputResizer {
+    /// 当前权重对应的`(原始宽, 原始高, 目标宽, 目标高)`
+    key: Option<(u32, u32, u32, u32)>,
+    horizontal: SampleWeights,
+    vertical: SampleWeights,
+    /// 纵向缩放后的中间结果，按行存储的RGB浮点值
+    rows: Vec<f32>,
+    output: RgbImage,
+}
+
+impl InputResizer {
+    /// 创建一个尚未分配缓冲区的缩放器
+    pub fn new() -> Self {
+        Self::default()
+    }
     
+    /// 将图像缩放到`width`x`height`，返回内部输出缓冲区的引用
+    /// 
+    /// 尺寸相同时只复制像素；原始图像或目标尺寸为0时返回全黑图像。
+    pub fn resize(&mut self, image: &RgbImage, width: u32, height: u32) -> &RgbImage {
+        if self.output.dimensions() != (width, height) {
+            self.output = RgbImage::new(width, height);
+        }
+        let (src_width, src_height) = image.dimensions();
+        if (src_width, src_height) == (width, height) {
+            self.output.copy_from_slice(image.as_raw());
+            return &self.output;
+        }
+        if src_width == 0 || src_height == 0 || width == 0 || height == 0 {
+            self.output.fill(0);
+            return &self.output;
+        }
+        
+        let key = (src_width, src_height, width, height);
+        if self.key != Some(key) {
+            self.horizontal = SampleWeights::catmull_rom(src_width, width);
+            self.vertical = SampleWeights::catmull_rom(src_height, height);
+            self.key = Some(key);
+        }
+        
+        // 与image库相同，先纵向缩放到浮点中间结果，再横向缩放并四舍五入
+        let (src_width, width) = (src_width as usize, width as usize);
+        let src = image.as_raw();
+        self.rows.resize(src_width * height as usize * 3, 0.0);
+        for (row, &(top, start, len)) in self.rows.chunks_exact_mut(src_width * 3).zip(&self.vertical.spans) {
+            let weights = &self.vertical.weights[start..start + len];
+            for (x, value) in row.chunks_exact_mut(3).enumerate() {
+                let mut sum = [0.0f32; 3];
+                for (i, weight) in weights.iter().enumerate() {
+                    let offset = ((top + i) * src_width + x) * 3;
+                    for c in 0..3 {
+                        sum[c] += src[offset + c] as f32 * weight;
+                    }
+                }
+                value.copy_from_slice(&sum);
+            }
+        }
+        for (out_row, row) in self.output.chunks_exact_mut(width * 3).zip(self.rows.chunks_exact(src_width * 3)) {
+            for (pixel, &(left, start, len)) in out_row.chunks_exact_mut(3).zip(&self.horizontal.spans) {
+                let weights = &self.horizontal.weights[start..start + len];
+                let mut sum = [0.0f32; 3];
+                for (i, weight) in weights.iter().enumerate() {
+                    let offset = (left + i) * 3;
+                    for c in 0..3 {
+                        sum[c] += row[offset + c] * weight;
+                    }
+                }
+                for c in 0..3 {
+                    pixel[c] = sum[c].clamp(0.0, 255.0).round() as u8;
+                }
+            }
+        }
+        &self.output
+    }
+}
+
+pub fn scale_image(img: &DynamicImage, target_width: u32, target_height: u32) -> (DynamicImage, ScaleMessage) {
     let resized_img = img.resize_exact(target_width, target_height, FilterType::CatmullRom);
     
-    let scale_message = ScaleMessage {
-        o_width: original_width,
-        o_height: original_height,
-        s_width: scale_width,
-        s_height: scale_height,
-    };
+    let scale_message = ScaleMessage::builder()
+        .original_size(img.width(), img.height())
+        .scaled_size(target_width, target_height)
+        .build();
     
     (resized_img, scale_message)
 }
 
+/// 保持宽高比缩放并填充到固定尺寸（letterbox），复用同一块画布
+/// 
+/// 画布在创建时分配一次，之后每帧只覆盖其内容，避免为每帧分配一幅模型输入大小的图像。
+/// 缩放后的内容居中放置，宽高差为奇数时多出的1像素填充在右侧/下方。
+/// 
+/// # 示例
+/// 
+/// ```
+/// use image::{DynamicImage, RgbImage};
+/// use perple::color::image::LetterboxPadder;
+/// 
+/// let mut padder = LetterboxPadder::new(640, 640, [114, 114, 114]).unwrap();
+/// let frame = DynamicImage::ImageRgb8(RgbImage::new(1280, 720));
+/// let (canvas, message) = padder.pad(&frame);
+/// assert_eq!(canvas.dimensions(), (640, 640));
+/// assert_eq!((message.s_width, message.s_height, message.pad_top), (640, 360, 140));
+/// ```
+pub struct LetterboxPadder {
+    target_width: u32,
+    target_height: u32,
+    pad_color: [u8; 3],
+    alpha_background: [u8; 3],
+    buffer: RgbImage,
+}
+
+impl LetterboxPadder {
+    /// 创建填充器并分配画布
+    /// 
+    /// # 参数
+    /// * `target_width` - 画布宽度（模型输入宽度）
+    /// * `target_height` - 画布高度（模型输入高度）
+    /// * `pad_color` - 填充色（RGB），通常为[DEFAULT_LETTERBOX_COLOR]
+    /// 
+    /// # 错误处理
+    /// 画布宽度或高度为0时返回[PerpleError::InvalidParameter]
+    pub fn new(target_width: u32, target_height: u32, pad_color: [u8; 3]) -> Result<Self, PerpleError> {
+        if target_width == 0 || target_height == 0 {
+            return Err(PerpleError::InvalidParameter(format!(
+                "填充画布尺寸不能为0: {}x{}", target_width, target_height
+            )));
+        }
+        Ok(Self {
+            target_width,
+            target_height,
+            pad_color,
+            alpha_background: DEFAULT_ALPHA_BACKGROUND,
+            buffer: RgbImage::from_pixel(target_width, target_height, Rgb(pad_color)),
+        })
+    }
+    
+    /// 设置带透明通道的图像合成所用的背景色，默认为[DEFAULT_ALPHA_BACKGROUND]
+    /// 
+    /// 应与检测器的[alpha_background](crate::color::YoloDetectorN::alpha_background)一致。
+    pub fn with_alpha_background(mut self, background: [u8; 3]) -> Self {
+        self.alpha_background = background;
+        self
+    }
+    
+    /// 使用[DEFAULT_LETTERBOX_COLOR]填充的填充器
+    pub fn with_default_color(target_width: u32, target_height: u32) -> Result<Self, PerpleError> {
+        Self::new(target_width, target_height, DEFAULT_LETTERBOX_COLOR)
+    }
+    
+    /// 画布尺寸`(宽, 高)`
+    pub fn target_size(&self) -> (u32, u32) {
+        (self.target_width, self.target_height)
+    }
+    
+    /// 填充色
+    pub fn pad_color(&self) -> [u8; 3] {
+        self.pad_color
+    }
+    
+    /// 将图像保持宽高比缩放后居中写入画布，其余区域用填充色覆盖
+    /// 
+    /// 带透明通道的图像先与[with_alpha_background](Self::with_alpha_background)设置的背景色合成。
+    /// 
+    /// # 返回值
+    /// 返回画布引用及缩放信息（含填充偏移），可直接用于还原检测框坐标
+    pub fn pad(&mut self, img: &DynamicImage) -> (&RgbImage, ScaleMessage) {
+        let (width, height) = (img.width().max(1), img.height().max(1));
+        let scale = (self.target_width as f32 / width as f32).min(self.target_height as f32 / height as f32);
+        let scaled_width = ((width as f32 * scale).round() as u32).clamp(1, self.target_width);
+        let scaled_height = ((height as f32 * scale).round() as u32).clamp(1, self.target_height);
+        let pad_left = (self.target_width - scaled_width) / 2;
+        let pad_top = (self.target_height - scaled_height) / 2;
+        
+        let resized = to_rgb_input(&resize_image(img, scaled_width, scaled_height), self.alpha_background);
+        for pixel in self.buffer.pixels_mut() {
+            *pixel = Rgb(self.pad_color);
+        }
+        image::imageops::replace(&mut self.buffer, &resized, pad_left as i64, pad_top as i64);
+        
+        let message = ScaleMessage::builder()
+            .original_size(width, height)
+            .scaled_size(scaled_width, scaled_height)
+            .padding(pad_left, pad_top)
+            .input_size(self.target_width, self.target_height)
+            .build();
+        (&self.buffer, message)
+    }
+}
+
+impl std::fmt::Debug for LetterboxPadder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LetterboxPadder")
+            .field("target_width", &self.target_width)
+            .field("target_height", &self.target_height)
+            .field("pad_color", &self.pad_color)
+            .field("alpha_background", &self.alpha_background)
+            .finish()
+    }
+}
+
 /// 将图像转换为模型输入张量
 /// 
 /// 将图像转换为模型所需的四维张量格式，包括：
@@ -98,12 +581,31 @@ pub fn scale_image(img: &DynamicImage, target_width: u32, target_height: u32) ->
 /// 
 /// # 返回值
 /// 返回形状为(1, 3, height, width)的四维张量，通道顺序为RGB，像素值范围[0, 1]
+/// 
+/// 带透明通道的图像与[DEFAULT_ALPHA_BACKGROUND]合成，
+/// 需要其他背景色时使用[image_to_tensor_with_background]。
 pub fn image_to_tensor(img: &DynamicImage, input_height: usize, input_width: usize) -> Array4<f32> {
+    image_to_tensor_with_background(img, input_height, input_width, DEFAULT_ALPHA_BACKGROUND)
+}
+
+/// 将图像转换为模型输入张量，透明像素与指定背景色合成
+/// 
+/// # 参数
+/// * `img` - 图像
+/// * `input_height` - 输入图像高度
+/// * `input_width` - 输入图像宽度
+/// * `background` - 透明像素合成所用的背景色（RGB）
+pub fn image_to_tensor_with_background(
+    img: &DynamicImage,
+    input_height: usize,
+    input_width: usize,
+    background: [u8; 3],
+) -> Array4<f32> {
     // 创建用于模型输入的张量，形状为(1, 3, input_height, input_width)
     let mut tensor = Array::zeros((1, 3, input_height, input_width));
     
-    // 获取图像的RGB数据，避免多次调用to_rgb8()
-    let rgb_img = img.to_rgb8();
+    // 获取图像的RGB数据，只转换一次
+    let rgb_img = to_rgb_input(img, background);
     
     // 使用enumerate来同时获取坐标和像素值，避免像素坐标转换开销
     for (y, row) in rgb_img.rows().enumerate() {
@@ -121,6 +623,81 @@ pub fn image_to_tensor(img: &DynamicImage, input_height: usize, input_width: usi
     tensor
 }
 
+/// 将图像转换为模型输入张量（按行并行）
+/// 
+/// 与[image_to_tensor]的输出逐字节一致（透明像素同样与[DEFAULT_ALPHA_BACKGROUND]合成），
+/// 适合预处理前的超大图像（如4K帧）。
+/// 对于640x640这样的小图，线程调度开销可能使其慢于串行版本。
+/// 
+/// # 参数
+/// * `img` - 输入图像
+/// * `input_height` - 模型输入高度
+/// * `input_width` - 模型输入宽度
+/// 
+/// # 返回值
+/// 返回形状为(1, 3, input_height, input_width)的张量
+#[cfg(feature = "parallel")]
+pub fn image_to_tensor_parallel(img: &DynamicImage, input_height: usize, input_width: usize) -> Array4<f32> {
+    use ndarray::Axis;
+    use rayon::prelude::*;
+    
+    let mut tensor = Array::zeros((1, 3, input_height, input_width));
+    let rgb_img = to_rgb_input(img, DEFAULT_ALPHA_BACKGROUND);
+    let (img_width, img_height) = (rgb_img.width() as usize, rgb_img.height() as usize);
+    
+    // 沿高度维拆分，每个任务负责一行的三个通道
+    tensor.axis_iter_mut(Axis(2))
+        .into_par_iter()
+        .enumerate()
+        .filter(|(y, _)| *y < img_height)
+        .for_each(|(y, mut row)| {
+            for x in 0..img_width.min(input_width) {
+                let [r, g, b] = rgb_img.get_pixel(x as u32, y as u32).0;
+                row[[0, 0, x]] = (r as f32) / 255.0;  // R通道
+                row[[0, 1, x]] = (g as f32) / 255.0;  // G通道
+                row[[0, 2, x]] = (b as f32) / 255.0;  // B通道
+            }
+        });
+    
+    tensor
+}
+
+/// 并行调整一批图像的大小
+/// 
+/// 每张图像的结果与[resize_image]一致，输出顺序与输入相同。
+/// 
+/// # 参数
+/// * `images` - 原始图像
+/// * `target_width` - 目标宽度
+/// * `target_height` - 目标高度
+#[cfg(feature = "parallel")]
+pub fn resize_batch(images: &[DynamicImage], target_width: u32, target_height: u32) -> Vec<DynamicImage> {
+    use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+    
+    images.par_iter()
+        .map(|img| resize_image(img, target_width, target_height))
+        .collect()
+}
+
+/// 并行调整一批图像的大小并转换为模型输入张量
+/// 
+/// 每个张量与先调用[resize_image]再调用[image_to_tensor]的结果一致，
+/// 形状为(1, 3, target_height, target_width)，输出顺序与输入相同。
+#[cfg(feature = "parallel")]
+pub fn resize_batch_to_tensors(images: &[DynamicImage], target_width: u32, target_height: u32) -> Vec<Array4<f32>> {
+    use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+    
+    images.par_iter()
+        .map(|img| {
+            let resized = resize_image(img, target_width, target_height);
+            image_to_tensor(&resized, target_height as usize, target_width as usize)
+        })
+        .collect()
+}
+
+/// 将图像缩放到模型输入尺寸并转换为NCHW布局的ONNX Runtime张量
+/// 
+/// 带透明通道的图像与[DEFAULT_ALPHA_BACKGROUND]合成，需要其他背景色时使用[fill_input_image]。
 pub fn input_image(img: &DynamicImage, input_height: usize, input_width: usize) -> Value<TensorValueType<f32>> {
     // 调整图像大小以适应模型输入
     let resized_img = resize_image(img, input_width as u32, input_height as u32);
@@ -129,7 +706,7 @@ pub fn input_image(img: &DynamicImage, input_height: usize, input_width: usize)
     let mut nchw_data = vec![0.0f32; input_height * input_width * 3];
     
     // 获取RGB图像数据
-    let rgb_img = resized_img.to_rgb8();
+    let rgb_img = to_rgb_input(&resized_img, DEFAULT_ALPHA_BACKGROUND);
     
     // 一次性遍历所有像素，并直接按NCHW格式写入
     for (y, row) in rgb_img.rows().enumerate() {
@@ -161,11 +738,13 @@ pub fn input_image(img: &DynamicImage, input_height: usize, input_width: usize)
 /// * `img` - 输入图像
 /// * `input_height` - 输入图像高度
 /// * `input_width` - 输入图像宽度
+/// * `background` - 透明像素合成所用的背景色（RGB），通常取检测器的[alpha_background](crate::color::YoloDetectorN::alpha_background)
 /// * `tensor_value` - 预创建的Tensor Value对象，会被直接填充
 pub fn fill_input_image(
     img: &DynamicImage, 
     input_height: usize, 
     input_width: usize,
+    background: [u8; 3],
     tensor_value: &mut Value<TensorValueType<f32>>
 ) {
     // 调整图像大小以适应模型输入
@@ -175,7 +754,7 @@ pub fn fill_input_image(
     let mut nchw_data = vec![0.0f32; input_height * input_width * 3];
     
     // 获取RGB图像数据
-    let rgb_img = resized_img.to_rgb8();
+    let rgb_img = to_rgb_input(&resized_img, background);
     
     // 一次性遍历所有像素，并直接按NCHW格式写入
     for (y, row) in rgb_img.rows().enumerate() {
@@ -199,4 +778,572 @@ pub fn fill_input_image(
     
     // 更新 ONNX Tensor 的值
     *tensor_value = Tensor::from_array(([1, 3, input_height, input_width], nchw_data)).unwrap();
-}
\ No newline at end of file
+}
+
+/// 按NHWC布局填充预创建的Value<TensorValueType<f32>>对象
+/// 
+/// 与[fill_input_image]相同，但张量形状为`[1, input_height, input_width, 3]`，
+/// 像素`(x, y)`的三个通道位于下标`(y * input_width + x) * 3`起的连续位置。
+/// 
+/// # 参数
+/// * `img` - 输入图像
+/// * `input_height` - 输入图像高度
+/// * `input_width` - 输入图像宽度
+/// * `background` - 透明像素合成所用的背景色（RGB）
+/// * `tensor_value` - 预创建的Tensor Value对象，会被直接填充
+/// 
+/// # 错误处理
+/// 张量创建失败时返回Err，`tensor_value`保持不变
+pub fn fill_input_image_nhwc(
+    img: &DynamicImage, 
+    input_height: usize, 
+    input_width: usize,
+    background: [u8; 3],
+    tensor_value: &mut Value<TensorValueType<f32>>
+) -> Result<(), PerpleError> {
+    let resized_img = resize_image(img, input_width as u32, input_height as u32);
+    let rgb_img = to_rgb_input(&resized_img, background);
+    
+    // RGB8本身就是按行交错存储，与NHWC的内存顺序一致，逐字节归一化即可
+    let nhwc_data: Vec<f32> = rgb_img.as_raw().iter().map(|&v| v as f32 / 255.0).collect();
+    
+    *tensor_value = Tensor::from_array(([1, input_height, input_width, 3], nhwc_data))?;
+    Ok(())
+}
+
+/// 按指定布局填充模型输入张量
+/// 
+/// # 错误处理
+/// 张量创建失败时返回Err
+pub fn fill_input_image_with_layout(
+    img: &DynamicImage,
+    input_height: usize,
+    input_width: usize,
+    layout: InputLayout,
+    background: [u8; 3],
+    tensor_value: &mut Value<TensorValueType<f32>>
+) -> Result<(), PerpleError> {
+    match layout {
+        InputLayout::Nchw => {
+            fill_input_image(img, input_height, input_width, background, tensor_value);
+            Ok(())
+        }
+        InputLayout::Nhwc => fill_input_image_nhwc(img, input_height, input_width, background, tensor_value),
+    }
+}
+
+/// 将图像写入已有的模型输入张量，形状相符时原地覆盖，不重新分配张量
+/// 
+/// 张量的每个元素都会被覆盖，因此不预先清零；图像已是模型输入尺寸时跳过缩放，
+/// 已是RGB8时不再复制像素。张量形状与输入尺寸或布局不符时（例如检测器的输入尺寸被修改），
+/// 回退到[fill_input_image_with_layout]重新创建张量。
+/// 每帧都需要缩放时使用[fill_input_image_resized]复用缩放缓冲区。
+/// 
+/// # 参数
+/// * `img` - 输入图像
+/// * `input_height` - 输入图像高度
+/// * `input_width` - 输入图像宽度
+/// * `layout` - 张量布局
+/// * `background` - 透明像素合成所用的背景色（RGB）
+/// * `tensor_value` - 预创建的Tensor Value对象，会被直接填充
+/// 
+/// # 错误处理
+/// 需要重新创建张量且创建失败时返回Err
+pub fn fill_input_image_in_place(
+    img: &DynamicImage,
+    input_height: usize,
+    input_width: usize,
+    layout: InputLayout,
+    background: [u8; 3],
+    tensor_value: &mut Value<TensorValueType<f32>>
+) -> Result<(), PerpleError> {
+    fill_input_image_resized(img, input_height, input_width, layout, background, &mut InputResizer::new(), tensor_value)
+}
+
+/// 与[fill_input_image_in_place]相同，但用`resizer`的缓冲区缩放图像
+/// 
+/// 输入为RGB8且尺寸不变时，稳定状态下不分配内存；其他像素格式仍按[fill_input_image_in_place]的方式缩放和转换。
+pub fn fill_input_image_resized(
+    img: &DynamicImage,
+    input_height: usize,
+    input_width: usize,
+    layout: InputLayout,
+    background: [u8; 3],
+    resizer: &mut InputResizer,
+    tensor_value: &mut Value<TensorValueType<f32>>
+) -> Result<(), PerpleError> {
+    let expected = match layout {
+        InputLayout::Nchw => [1, 3, input_height, input_width],
+        InputLayout::Nhwc => [1, input_height, input_width, 3],
+    };
+    let data = match tensor_value.try_extract_tensor_mut::<f32>() {
+        Ok((shape, data)) if shape.iter().map(|&d| d as usize).eq(expected) => data,
+        _ => return fill_input_image_with_layout(img, input_height, input_width, layout, background, tensor_value),
+    };
+    
+    // 其他像素格式保持先缩放、再合成透明通道的顺序，与其余填充函数一致
+    let (target_width, target_height) = (input_width as u32, input_height as u32);
+    let converted;
+    let rgb_img = match img {
+        DynamicImage::ImageRgb8(rgb) if rgb.dimensions() == (target_width, target_height) => rgb,
+        DynamicImage::ImageRgb8(rgb) => resizer.resize(rgb, target_width, target_height),
+        other => {
+            converted = to_rgb_input(&resize_image(other, target_width, target_height), background);
+            &converted
+        }
+    };
+    
+    write_input_pixels(rgb_img, layout, data);
+    Ok(())
+}
+
+/// 将RGB8图像按`layout`归一化写入长度为`3 * 宽 * 高`的张量数据
+fn write_input_pixels(rgb_img: &RgbImage, layout: InputLayout, data: &mut [f32]) {
+    match layout {
+        InputLayout::Nchw => {
+            let plane = (rgb_img.width() * rgb_img.height()) as usize;
+            for (index, pixel) in rgb_img.as_raw().chunks_exact(3).enumerate() {
+                data[index] = pixel[0] as f32 / 255.0;
+                data[plane + index] = pixel[1] as f32 / 255.0;
+                data[2 * plane + index] = pixel[2] as f32 / 255.0;
+            }
+        }
+        InputLayout::Nhwc => {
+            for (dst, &src) in data.iter_mut().zip(rgb_img.as_raw()) {
+                *dst = src as f32 / 255.0;
+            }
+        }
+    }
+}
+
+/// 计算两帧图像的平均像素变化量
+/// 
+/// 先将两幅图像转换为RGB8（与模型输入相同的转换），
+/// 再计算所有像素所有通道差值绝对值的平均值。
+/// 
+/// # 参数
+/// * `frame1` - 前一帧
+/// * `frame2` - 当前帧
+/// 
+/// # 返回值
+/// 返回`[0.0, 1.0]`范围内的变化量，0表示完全相同
+/// 
+/// # 错误处理
+/// 两幅图像尺寸不一致时返回[PerpleError::ShapeMismatch]
+pub fn image_diff(frame1: &DynamicImage, frame2: &DynamicImage) -> Result<f32, PerpleError> {
+    let (rgb1, rgb2) = rgb_pair(frame1, frame2)?;
+    let total: u64 = rgb1.as_raw().iter()
+        .zip(rgb2.as_raw())
+        .map(|(&a, &b)| a.abs_diff(b) as u64)
+        .sum();
+    let samples = rgb1.as_raw().len();
+    if samples == 0 {
+        return Ok(0.0);
+    }
+    Ok(total as f32 / (samples as f32 * 255.0))
+}
+
+/// 生成两帧图像的差异灰度图
+/// 
+/// 每个像素的灰度值为三个通道差值绝对值的平均值，变化越大越亮。
+/// 
+/// # 参数
+/// * `frame1` - 前一帧
+/// * `frame2` - 当前帧
+/// 
+/// # 错误处理
+/// 两幅图像尺寸不一致时返回[PerpleError::ShapeMismatch]
+pub fn image_diff_map(frame1: &DynamicImage, frame2: &DynamicImage) -> Result<DynamicImage, PerpleError> {
+    let (rgb1, rgb2) = rgb_pair(frame1, frame2)?;
+    let data = rgb1.as_raw().chunks_exact(3)
+        .zip(rgb2.as_raw().chunks_exact(3))
+        .map(|(a, b)| {
+            let sum: u16 = a.iter().zip(b).map(|(&x, &y)| x.abs_diff(y) as u16).sum();
+            (sum / 3) as u8
+        })
+        .collect();
+    let map = GrayImage::from_raw(rgb1.width(), rgb1.height(), data).expect("缓冲区大小与图像尺寸一致");
+    Ok(DynamicImage::ImageLuma8(map))
+}
+
+/// 检查两幅图像尺寸一致并转换为RGB8
+fn rgb_pair(frame1: &DynamicImage, frame2: &DynamicImage) -> Result<(RgbImage, RgbImage), PerpleError> {
+    let (expected, actual) = (frame1.dimensions(), frame2.dimensions());
+    if expected != actual {
+        return Err(PerpleError::ShapeMismatch { expected, actual });
+    }
+    Ok((to_rgb_input(frame1, DEFAULT_ALPHA_BACKGROUND), to_rgb_input(frame2, DEFAULT_ALPHA_BACKGROUND)))
+}
+
+/// 将NCHW张量第一个样本的单个通道转换为灰度图，用于查看特征图
+/// 
+/// 通道值按其最小值、最大值线性归一化到`[0, 255]`；所有值相同（例如全零）时输出全黑图像，
+/// 非有限值（NaN、无穷）按0处理。
+/// 
+/// # 参数
+/// * `tensor` - 形状为`[N, C, H, W]`的张量
+/// * `channel` - 通道索引
+/// 
+/// # 错误处理
+/// 张量为空或通道索引越界时返回[PerpleError::InvalidParameter]
+pub fn tensor_to_image(tensor: &Array4<f32>, channel: usize) -> Result<DynamicImage, PerpleError> {
+    let (width, height) = feature_map_size(tensor)?;
+    let data = normalized_channel(tensor, channel)?;
+    let image = GrayImage::from_raw(width, height, data).expect("缓冲区大小与图像尺寸一致");
+    Ok(DynamicImage::ImageLuma8(image))
+}
+
+/// 将NCHW张量第一个样本的三个通道分别作为R、G、B合成彩色图像
+/// 
+/// 每个通道独立归一化，规则与[tensor_to_image]相同。
+/// 
+/// # 参数
+/// * `tensor` - 形状为`[N, C, H, W]`的张量
+/// * `channels` - 作为R、G、B的通道索引，可以重复
+/// 
+/// # 错误处理
+/// 张量为空或任一通道索引越界时返回[PerpleError::InvalidParameter]
+pub fn tensor_to_rgb_image(tensor: &Array4<f32>, channels: (usize, usize, usize)) -> Result<DynamicImage, PerpleError> {
+    let (width, height) = feature_map_size(tensor)?;
+    let r = normalized_channel(tensor, channels.0)?;
+    let g = normalized_channel(tensor, channels.1)?;
+    let b = normalized_channel(tensor, channels.2)?;
+    let data = r.iter().zip(&g).zip(&b).flat_map(|((&r, &g), &b)| [r, g, b]).collect();
+    let image = RgbImage::from_raw(width, height, data).expect("缓冲区大小与图像尺寸一致");
+    Ok(DynamicImage::ImageRgb8(image))
+}
+
+/// 特征图的`(宽, 高)`，张量没有样本或尺寸超出图像范围时返回错误
+fn feature_map_size(tensor: &Array4<f32>) -> Result<(u32, u32), PerpleError> {
+    let (batch, _, height, width) = tensor.dim();
+    if batch == 0 {
+        return Err(PerpleError::InvalidParameter("张量中没有样本".to_string()));
+    }
+    match (u32::try_from(width), u32::try_from(height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(PerpleError::InvalidParameter(format!("特征图尺寸过大: {}x{}", width, height))),
+    }
+}
+
+/// 取第一个样本的指定通道并按最小值、最大值归一化到`[0, 255]`
+fn normalized_channel(tensor: &Array4<f32>, channel: usize) -> Result<Vec<u8>, PerpleError> {
+    let channels = tensor.dim().1;
+    if channel >= channels {
+        return Err(PerpleError::InvalidParameter(format!(
+            "通道索引{}越界，张量共有{}个通道", channel, channels
+        )));
+    }
+    let plane = tensor.slice(s![0, channel, .., ..]);
+    let finite = || plane.iter().copied().filter(|v| v.is_finite());
+    let min = finite().fold(f32::INFINITY, f32::min);
+    let max = finite().fold(f32::NEG_INFINITY, f32::max);
+    let range = max - min;
+    Ok(plane.iter().map(|&v| {
+        if !v.is_finite() || range <= 0.0 || !range.is_finite() {
+            0
+        } else {
+            ((v - min) / range * 255.0).round() as u8
+        }
+    }).collect())
+}
+
+/// 按检测框从原图中裁剪出目标区域
+/// 
+/// 检测框会被限制在图像范围内，宽高至少为1像素。
+/// 
+/// # 参数
+/// * `image` - 原始图像
+/// * `detections` - 检测结果（原始图像坐标）
+/// 
+/// # 返回值
+/// 按检测结果顺序返回裁剪出的图像
+pub fn crop_detections(image: &DynamicImage, detections: &[Detection]) -> Vec<DynamicImage> {
+    let (img_width, img_height) = (image.width(), image.height());
+    detections.iter().map(|detection| {
+        let bbox = &detection.bbox;
+        let x1 = (bbox.x1.min(bbox.x2).max(0.0) as u32).min(img_width.saturating_sub(1));
+        let y1 = (bbox.y1.min(bbox.y2).max(0.0) as u32).min(img_height.saturating_sub(1));
+        let x2 = (bbox.x1.max(bbox.x2).ceil() as u32).min(img_width);
+        let y2 = (bbox.y1.max(bbox.y2).ceil() as u32).min(img_height);
+        image.crop_imm(x1, y1, x2.saturating_sub(x1).max(1), y2.saturating_sub(y1).max(1))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// 左半边完全透明、右半边不透明红色的RGBA图像
+    fn half_transparent(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, _| {
+            if x < width / 2 { Rgba([0, 0, 0, 0]) } else { Rgba([200, 0, 0, 255]) }
+        }))
+    }
+
+    #[test]
+    fn rgba_composites_on_requested_background() {
+        let image = half_transparent(4, 2);
+        let white = to_rgb_input(&image, DEFAULT_ALPHA_BACKGROUND);
+        let black = to_rgb_input(&image, [0, 0, 0]);
+        assert_eq!(white.get_pixel(0, 0).0, [255, 255, 255]);
+        assert_eq!(black.get_pixel(0, 0).0, [0, 0, 0]);
+        // 不透明像素不受背景色影响
+        assert_eq!(white.get_pixel(3, 1).0, [200, 0, 0]);
+        assert_eq!(black.get_pixel(3, 1).0, [200, 0, 0]);
+    }
+
+    #[test]
+    fn partial_alpha_is_blended_and_rounded() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([100, 0, 255, 128])));
+        // 100 * 128 + 255 * 127 = 45185，除以255四舍五入为177
+        assert_eq!(to_rgb_input(&image, [255, 255, 255]).get_pixel(0, 0).0, [177, 127, 255]);
+    }
+
+    #[test]
+    fn tensor_differs_between_backgrounds() {
+        let image = half_transparent(4, 2);
+        let white = image_to_tensor_with_background(&image, 2, 4, [255, 255, 255]);
+        let black = image_to_tensor_with_background(&image, 2, 4, [0, 0, 0]);
+        assert_eq!(image_to_tensor(&image, 2, 4), white);
+        assert_eq!(white[[0, 1, 0, 0]], 1.0);
+        assert_eq!(black[[0, 1, 0, 0]], 0.0);
+        assert_eq!(white[[0, 0, 0, 3]], black[[0, 0, 0, 3]]);
+    }
+
+    #[test]
+    fn letterbox_uses_configured_alpha_background() {
+        let image = half_transparent(8, 8);
+        let mut default = LetterboxPadder::new(8, 8, DEFAULT_LETTERBOX_COLOR).unwrap();
+        let mut black = LetterboxPadder::new(8, 8, DEFAULT_LETTERBOX_COLOR).unwrap().with_alpha_background([0, 0, 0]);
+        assert_eq!(default.pad(&image).0.get_pixel(0, 4).0, [255, 255, 255]);
+        assert_eq!(black.pad(&image).0.get_pixel(0, 4).0, [0, 0, 0]);
+    }
+
+    #[test]
+    fn letterbox_rejects_zero_target() {
+        for (width, height) in [(0, 8), (8, 0), (0, 0)] {
+            assert!(matches!(LetterboxPadder::new(width, height, DEFAULT_LETTERBOX_COLOR), Err(PerpleError::InvalidParameter(_))));
+        }
+        assert!(LetterboxPadder::with_default_color(0, 640).is_err());
+    }
+
+    #[test]
+    fn letterbox_handles_extreme_aspect_ratios() {
+        let mut padder = LetterboxPadder::with_default_color(1, 1).unwrap();
+        assert_eq!(padder.pad(&DynamicImage::new_rgb8(1000, 1)).0.dimensions(), (1, 1));
+        let mut padder = LetterboxPadder::with_default_color(8, 8).unwrap();
+        let (canvas, message) = padder.pad(&DynamicImage::new_rgb8(1, 1000));
+        assert_eq!(canvas.dimensions(), (8, 8));
+        assert_eq!((message.s_width, message.s_height, message.pad_left, message.pad_top), (1, 8, 3, 0));
+    }
+
+    #[test]
+    fn odd_letterbox_padding_keeps_full_input_size() {
+        let mut padder = LetterboxPadder::with_default_color(8, 8).unwrap();
+        let (_, message) = padder.pad(&DynamicImage::new_rgb8(8, 5));
+        assert_eq!((message.pad_top, message.pad_bottom), (1, 2));
+        assert_eq!(message.input_size(), (8, 8));
+
+        let symmetric = ScaleMessage::builder().original_size(8, 5).scaled_size(8, 5).padding(0, 1).build();
+        assert_eq!(symmetric.input_size(), (8, 7));
+    }
+
+    /// 确定性的非均匀RGB图像
+    fn gradient(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| Rgb([(x * 7 % 256) as u8, (y * 13 % 256) as u8, ((x * y) % 251) as u8]))
+    }
+
+    #[test]
+    fn input_resizer_matches_resize_image() {
+        let mut resizer = InputResizer::new();
+        // 缩小、放大、尺寸变化后再回到原尺寸，结果都应与resize_image一致
+        for ((src_w, src_h), (dst_w, dst_h)) in [((97, 61), (32, 32)), ((20, 10), (64, 48)), ((97, 61), (32, 32)), ((8, 8), (8, 8))] {
+            let image = gradient(src_w, src_h);
+            let expected = resize_image(&DynamicImage::ImageRgb8(image.clone()), dst_w, dst_h).to_rgb8();
+            assert!(resizer.resize(&image, dst_w, dst_h) == &expected, "{}x{} -> {}x{}", src_w, src_h, dst_w, dst_h);
+        }
+    }
+
+    #[test]
+    fn input_resizer_reuses_buffers_for_same_size() {
+        let mut resizer = InputResizer::new();
+        let image = gradient(40, 30);
+        let first = resizer.resize(&image, 16, 16).as_ptr();
+        assert_eq!(resizer.resize(&gradient(40, 30), 16, 16).as_ptr(), first);
+        assert_eq!(resizer.resize(&RgbImage::new(0, 0), 4, 4).as_raw(), &vec![0; 48]);
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("perple_image_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn progress_is_reported_at_least_every_interval() {
+        // 未压缩的BMP，文件大小约为像素数据的大小
+        let original = DynamicImage::ImageRgb8(gradient(640, 480));
+        let path = temp_path("large.bmp");
+        original.save(&path).unwrap();
+        let file_size = std::fs::metadata(&path).unwrap().len() as usize;
+        assert!(file_size > 10 * LOAD_PROGRESS_INTERVAL);
+
+        let reports = std::cell::RefCell::new(Vec::new());
+        let loaded = load_image_with_progress(path.to_str().unwrap(), |bytes| reports.borrow_mut().push(bytes)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.to_rgb8(), original.to_rgb8());
+
+        let reports = reports.into_inner();
+        assert!(reports.len() >= file_size / LOAD_PROGRESS_INTERVAL, "{} reports for {} bytes", reports.len(), file_size);
+        // 相邻两次报告之间不超过一个间隔，最后一次覆盖整个文件
+        let mut previous = 0;
+        for &bytes in &reports {
+            assert!(bytes > previous && bytes - previous <= LOAD_PROGRESS_INTERVAL, "{} -> {}", previous, bytes);
+            previous = bytes;
+        }
+        assert!(previous >= file_size);
+    }
+
+    #[test]
+    fn load_errors_distinguish_io_and_format() {
+        assert!(matches!(load_image_with_progress("/nonexistent/perple.png", |_| {}), Err(PerpleError::Io(_))));
+
+        // 无法识别的内容以文件路径作为格式
+        let path = temp_path("garbage.bin");
+        std::fs::write(&path, [0x42u8; 1024]).unwrap();
+        let result = load_image_with_progress(path.to_str().unwrap(), |_| {});
+        assert!(matches!(&result, Err(PerpleError::UnsupportedFormat { format, .. }) if format.as_str() == path.to_str().unwrap()), "{:?}", result);
+
+        // 识别出格式但内容被截断
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(gradient(64, 64)).write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+        std::fs::write(&path, &png[..png.len() / 2]).unwrap();
+        let result = load_image_with_progress(path.to_str().unwrap(), |_| {});
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(&result, Err(PerpleError::UnsupportedFormat { format, .. }) if format == "Png"), "{:?}", result);
+    }
+
+    #[test]
+    fn all_zero_tensor_is_uniformly_dark() {
+        let tensor = Array4::<f32>::zeros((1, 3, 4, 6));
+        let gray = tensor_to_image(&tensor, 1).unwrap();
+        assert_eq!(gray.dimensions(), (6, 4));
+        assert!(gray.to_luma8().pixels().all(|pixel| pixel.0 == [0]));
+
+        let rgb = tensor_to_rgb_image(&tensor, (0, 1, 2)).unwrap();
+        assert_eq!(rgb.dimensions(), (6, 4));
+        assert!(rgb.to_rgb8().pixels().all(|pixel| pixel.0 == [0, 0, 0]));
+    }
+
+    #[test]
+    fn tensor_channel_is_normalized_to_full_range() {
+        let tensor = Array4::from_shape_fn((1, 2, 2, 2), |(_, c, y, x)| (c * 10 + y * 2 + x) as f32 - 3.0);
+        let gray = tensor_to_image(&tensor, 1).unwrap().to_luma8();
+        assert_eq!(gray.as_raw(), &[0, 85, 170, 255]);
+        assert!(matches!(tensor_to_image(&tensor, 2), Err(PerpleError::InvalidParameter(_))));
+    }
+
+    /// 尺寸和宽高比各不相同的一批图像，含带透明通道的图像
+    #[cfg(feature = "parallel")]
+    fn mixed_batch() -> Vec<DynamicImage> {
+        vec![
+            DynamicImage::ImageRgb8(gradient(64, 48)),
+            DynamicImage::ImageRgb8(gradient(13, 97)),
+            half_transparent(40, 40),
+            DynamicImage::ImageRgb8(gradient(1, 1)),
+        ]
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn resize_batch_matches_serial_resize() {
+        let images = mixed_batch();
+        let resized = resize_batch(&images, 32, 24);
+        assert_eq!(resized.len(), images.len());
+        for (parallel, image) in resized.iter().zip(&images) {
+            let serial = resize_image(image, 32, 24);
+            assert_eq!(parallel.dimensions(), serial.dimensions());
+            assert_eq!(parallel.to_rgba8().as_raw(), serial.to_rgba8().as_raw());
+        }
+        assert!(resize_batch(&[], 32, 24).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn resize_batch_to_tensors_matches_serial_conversion() {
+        let images = mixed_batch();
+        let tensors = resize_batch_to_tensors(&images, 32, 24);
+        assert_eq!(tensors.len(), images.len());
+        for (parallel, image) in tensors.iter().zip(&images) {
+            let serial = image_to_tensor(&resize_image(image, 32, 24), 24, 32);
+            assert_eq!(parallel.shape(), &[1, 3, 24, 32]);
+            assert_eq!(parallel, &serial);
+        }
+    }
+
+    /// 4x3的黑色图像，只有`(2, 1)`处为`(255, 51, 102)`
+    fn marked_pixel() -> RgbImage {
+        let mut image = RgbImage::new(4, 3);
+        image.put_pixel(2, 1, Rgb([255, 51, 102]));
+        image
+    }
+
+    /// `marked_pixel`中标记像素三个通道在`layout`下的下标
+    fn marked_indices(layout: InputLayout) -> [usize; 3] {
+        let (width, height, x, y) = (4, 3, 2, 1);
+        match layout {
+            InputLayout::Nchw => [0, 1, 2].map(|c| c * width * height + y * width + x),
+            InputLayout::Nhwc => [0, 1, 2].map(|c| (y * width + x) * 3 + c),
+        }
+    }
+
+    fn assert_marked(data: &[f32], layout: InputLayout) {
+        let indices = marked_indices(layout);
+        for (index, &value) in data.iter().enumerate() {
+            let expected = match indices.iter().position(|&i| i == index) {
+                Some(channel) => [1.0, 0.2, 0.4][channel],
+                None => 0.0,
+            };
+            assert!((value - expected).abs() < 1e-6, "{:?}下标{}: {} != {}", layout, index, value, expected);
+        }
+    }
+
+    #[test]
+    fn input_layout_from_shape() {
+        assert_eq!(InputLayout::from_shape(&[1, 3, 640, 640]), Some(InputLayout::Nchw));
+        assert_eq!(InputLayout::from_shape(&[1, 640, 640, 3]), Some(InputLayout::Nhwc));
+        assert_eq!(InputLayout::from_shape(&[1, 1, 640, 640]), None);
+        assert_eq!(InputLayout::from_shape(&[3, 640, 640]), None);
+    }
+
+    #[test]
+    fn known_pixel_lands_at_layout_index() {
+        let image = marked_pixel();
+        assert_eq!(marked_indices(InputLayout::Nchw), [6, 18, 30]);
+        assert_eq!(marked_indices(InputLayout::Nhwc), [18, 19, 20]);
+        for layout in [InputLayout::Nchw, InputLayout::Nhwc] {
+            let mut data = vec![0.0; 36];
+            write_input_pixels(&image, layout, &mut data);
+            assert_marked(&data, layout);
+        }
+        // NCHW的ndarray张量与写入结果一致
+        let tensor = image_to_tensor(&DynamicImage::ImageRgb8(image), 3, 4);
+        assert_marked(tensor.as_slice().unwrap(), InputLayout::Nchw);
+    }
+
+    #[test]
+    fn fill_input_tensor_places_pixel_by_layout() {
+        let image = DynamicImage::ImageRgb8(marked_pixel());
+        for (layout, shape) in [(InputLayout::Nchw, [1, 3, 3, 4]), (InputLayout::Nhwc, [1, 3, 4, 3])] {
+            let mut tensor = crate::color::array::empty_input(1, 1, InputLayout::Nchw).unwrap();
+            fill_input_image_with_layout(&image, 3, 4, layout, DEFAULT_ALPHA_BACKGROUND, &mut tensor).unwrap();
+            let (tensor_shape, data) = tensor.try_extract_tensor::<f32>().unwrap();
+            assert!(tensor_shape.iter().map(|&d| d as usize).eq(shape), "{:?}", tensor_shape);
+            assert_marked(data, layout);
+
+            // 形状相符时原地覆盖
+            let mut resizer = InputResizer::new();
+            fill_input_image_resized(&image, 3, 4, layout, DEFAULT_ALPHA_BACKGROUND, &mut resizer, &mut tensor).unwrap();
+            assert_marked(tensor.try_extract_tensor::<f32>().unwrap().1, layout);
+        }
+    }
+}
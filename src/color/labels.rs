@@ -0,0 +1,252 @@
+//! 类别名称模块
+//!
+//! 从文件加载类别ID到名称的映射，用于自行训练的多类别模型，
+//! 检测结果的[class_name](crate::color::Detection::class_name)按映射填写。
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use crate::error::PerpleError;
+
+/// 类别ID到类别名称的映射，第`i`个名称对应类别ID `i`
+///
+/// 支持两种文件格式：
+/// - 纯文本，每行一个名称，空行被忽略
+/// - YOLO数据集yaml中的`names:`部分，可以是列表（`- person`或`[person, forklift]`）
+///   或以类别ID为键的映射（`0: person`），其余内容被忽略
+///
+/// 文件开头的UTF-8 BOM会被去掉。映射中没有的类别ID显示为`class_{id}`。
+///
+/// # 示例
+///
+/// ```
+/// use perple::color::ClassMap;
+///
+/// let map: ClassMap = "names:\n  0: person\n  1: forklift\n  2: pallet\n".parse().unwrap();
+/// assert_eq!(map.name(1), Some("forklift"));
+/// assert_eq!(map.label(7), "class_7");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClassMap {
+    names: Vec<String>,
+}
+
+impl ClassMap {
+    /// 按类别ID顺序的名称列表创建映射
+    pub fn new(names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self { names: names.into_iter().map(Into::into).collect() }
+    }
+
+    /// 从文件加载映射，格式见[ClassMap]
+    ///
+    /// # 错误处理
+    /// 文件读取失败时返回[PerpleError::Io]，yaml的`names:`部分格式不正确时返回[PerpleError::Config]
+    pub fn from_file(path: &str) -> Result<Self, PerpleError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// 类别数量
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// 是否没有任何类别
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// 按类别ID顺序排列的全部名称
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// 类别ID对应的名称，映射中没有该ID时返回`None`
+    pub fn name(&self, class_id: usize) -> Option<&str> {
+        self.names.get(class_id).map(String::as_str)
+    }
+
+    /// 类别ID对应的显示名称，映射中没有该ID时为`class_{id}`
+    pub fn label(&self, class_id: usize) -> Cow<'static, str> {
+        match self.name(class_id) {
+            Some(name) => Cow::Owned(name.to_string()),
+            None => Cow::Owned(format!("class_{}", class_id)),
+        }
+    }
+
+    /// 按名称查找类别ID
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+}
+
+impl FromStr for ClassMap {
+    type Err = PerpleError;
+
+    /// 解析文件内容：存在顶层的`names:`时按yaml解析，否则按每行一个名称解析
+    fn from_str(text: &str) -> Result<Self, PerpleError> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let mut lines = text.lines();
+        while let Some(line) = lines.next() {
+            if let Some(rest) = line.strip_prefix("names:") {
+                return parse_yaml_names(rest, lines).map(|names| Self { names });
+            }
+        }
+        Ok(Self::new(text.lines().map(str::trim).filter(|line| !line.is_empty())))
+    }
+}
+
+/// 解析yaml中`names:`之后的内容
+///
+/// # 参数
+/// * `rest` - 与`names:`同一行的剩余内容
+/// * `lines` - 之后的各行，遇到下一个顶层键时停止
+fn parse_yaml_names<'a>(rest: &str, lines: impl Iterator<Item = &'a str>) -> Result<Vec<String>, PerpleError> {
+    let rest = strip_comment(rest).trim();
+    if let Some(inner) = rest.strip_prefix('[') {
+        let inner = inner.strip_suffix(']')
+            .ok_or_else(|| PerpleError::Config("names列表应在同一行内以]结束".to_string()))?;
+        return Ok(split_inline(inner).map(unquote).collect());
+    }
+    if let Some(inner) = rest.strip_prefix('{') {
+        let inner = inner.strip_suffix('}')
+            .ok_or_else(|| PerpleError::Config("names映射应在同一行内以}结束".to_string()))?;
+        return collect_indexed(split_inline(inner).map(parse_entry));
+    }
+    if !rest.is_empty() {
+        return Err(PerpleError::Config(format!("无法解析names: {}", rest)));
+    }
+
+    // 块格式：缩进的`- 名称`或`ID: 名称`，直到下一个顶层键
+    let mut items = Vec::new();
+    let mut entries = Vec::new();
+    for line in lines {
+        let content = strip_comment(line).trim();
+        if content.is_empty() {
+            continue;
+        }
+        if !line.starts_with([' ', '\t']) && !content.starts_with('-') {
+            break;
+        }
+        match content.strip_prefix('-') {
+            Some(item) => items.push(unquote(item)),
+            None => entries.push(parse_entry(content)),
+        }
+    }
+    match (items.is_empty(), entries.is_empty()) {
+        (_, true) => Ok(items),
+        (true, false) => collect_indexed(entries.into_iter()),
+        (false, false) => Err(PerpleError::Config("names不能同时包含列表项和映射项".to_string())),
+    }
+}
+
+/// 按逗号拆分单行列表或映射，忽略空项（如末尾的逗号）
+fn split_inline(inner: &str) -> impl Iterator<Item = &str> {
+    inner.split(',').filter(|item| !item.trim().is_empty())
+}
+
+/// 解析`ID: 名称`形式的一项
+fn parse_entry(entry: &str) -> Result<(usize, String), PerpleError> {
+    let (key, value) = entry.split_once(':')
+        .ok_or_else(|| PerpleError::Config(format!("names映射项应为`ID: 名称`: {}", entry.trim())))?;
+    let id = unquote(key).parse::<usize>()
+        .map_err(|_| PerpleError::Config(format!("类别ID应为非负整数: {}", key.trim())))?;
+    Ok((id, unquote(value)))
+}
+
+/// 将映射项按类别ID排列，ID必须从0开始连续
+fn collect_indexed(entries: impl Iterator<Item = Result<(usize, String), PerpleError>>) -> Result<Vec<String>, PerpleError> {
+    let mut entries = entries.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|(id, _)| *id);
+    entries.into_iter().enumerate().map(|(index, (id, name))| {
+        if id == index {
+            Ok(name)
+        } else {
+            Err(PerpleError::Config(format!("类别ID应从0开始连续，缺少{}", index)))
+        }
+    }).collect()
+}
+
+/// 去掉行尾注释（引号外的`#`及其后内容）
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// 去掉首尾空白及成对的引号
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['\'', '"'] {
+        if let Some(inner) = value.strip_prefix(quote).and_then(|v| v.strip_suffix(quote)) {
+            return inner.to_string();
+        }
+    }
+    value.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 将`contents`写入临时文件后用[ClassMap::from_file]加载
+    fn load(name: &str, contents: &[u8]) -> ClassMap {
+        let path = std::env::temp_dir().join(format!("perple_labels_{}_{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        let map = ClassMap::from_file(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        map.unwrap()
+    }
+
+    #[test]
+    fn loads_plain_text_with_blank_lines() {
+        let map = load("plain.txt", b"person\n\nforklift\r\n  \npallet\n\n");
+        assert_eq!(map.names(), ["person", "forklift", "pallet"]);
+    }
+
+    #[test]
+    fn loads_plain_text_with_bom() {
+        let map = load("bom.txt", "\u{feff}person\nforklift\npallet\n".as_bytes());
+        assert_eq!(map.name(0), Some("person"));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn loads_yaml_map_names() {
+        let yaml = "\u{feff}path: ../datasets\ntrain: images/train\n\nnames:\n  0: person\n\n  1: forklift  # 叉车\n  2: 'pallet'\nnc: 3\n";
+        let map = load("data.yaml", yaml.as_bytes());
+        assert_eq!(map.names(), ["person", "forklift", "pallet"]);
+    }
+
+    #[test]
+    fn loads_yaml_list_names() {
+        let map = load("list.yaml", b"names:\n  - person\n  - forklift\n  - pallet\n");
+        assert_eq!(map.names(), ["person", "forklift", "pallet"]);
+        let map = load("inline.yaml", b"nc: 3\nnames: [person, 'forklift', \"pallet\"]\n");
+        assert_eq!(map.names(), ["person", "forklift", "pallet"]);
+    }
+
+    #[test]
+    fn unknown_class_id_has_fallback_label() {
+        let map = ClassMap::new(["person", "forklift", "pallet"]);
+        assert_eq!(map.label(1), "forklift");
+        assert_eq!(map.label(42), "class_42");
+        assert_eq!(map.id("pallet"), Some(2));
+    }
+
+    #[test]
+    fn rejects_gaps_in_yaml_ids() {
+        assert!(matches!("names:\n  0: person\n  2: pallet\n".parse::<ClassMap>(), Err(PerpleError::Config(_))));
+    }
+
+    #[test]
+    fn missing_file_is_io_error() {
+        assert!(matches!(ClassMap::from_file("/nonexistent/perple/labels.txt"), Err(PerpleError::Io(_))));
+    }
+}
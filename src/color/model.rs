@@ -1,13 +1,165 @@
 //! 模型加载模块
 //! 
 //! 提供加载ONNX格式YOLO模型的功能。
+//! 
+//! # 内嵌模型
+//! 
+//! 启用`embedded-model`特性后，`module/color/yolo11n_int8.onnx`会通过`include_bytes!`
+//! 编译进二进制文件，[load_static_model]和`YoloDetector::embedded`无需任何模型文件即可使用。
+//! 该文件由`cargo run --example quantize_model --features onnx-export`从`module/color/yolo11n.onnx`
+//! 量化得到（权重int8，推理仍为FP32，见`quantize_weights`）。
+//! 
+//! - 体积：二进制文件增大约2.8 MB（原FP32模型约10.1 MB）；未启用该特性时不受影响
+//! - 许可：权重来自Ultralytics YOLO11n，遵循AGPL-3.0许可，分发内嵌模型的程序需遵守其条款
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use image::DynamicImage;
+use ort::execution_providers::CPUExecutionProvider;
+use ort::session::{builder::{GraphOptimizationLevel, SessionBuilder}, Session};
+
+use crate::color::bounds::Bounds;
+use crate::color::detect::YoloDetector;
+use crate::error::PerpleError;
+
+/// 检测模型输出每个框的最少参数个数 [x1, y1, x2, y2, conf]
+pub const MIN_OUTPUT_PARAMS: usize = 5;
+
+/// [load_model]使用的推理线程数
+pub const DEFAULT_INTRA_THREADS: usize = 4;
+
+/// 算子的执行方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionMode {
+    /// 按顺序执行算子，只使用算子内部的并行（intra-op线程）
+    #[default]
+    Sequential,
+    /// 互不依赖的算子并行执行，使用inter-op线程
+    Parallel,
+}
+
+/// 模型会话的线程和内存配置
+/// 
+/// 默认值：intra-op线程数等于可用CPU核心数，inter-op线程数由ONNX Runtime决定，
+/// 顺序执行，启用内存模式优化和CPU内存池。
+/// 
+/// # 示例
+/// 
+/// ```
+/// use perple::color::model::{ModelOptions, ExecutionMode};
+/// 
+/// let options = ModelOptions::default()
+///     .with_intra_threads(2)
+///     .with_execution_mode(ExecutionMode::Parallel)
+///     .with_inter_threads(2);
+/// assert_eq!(options.intra_threads(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelOptions {
+    /// 算子内部并行的线程数，`None`表示可用CPU核心数
+    intra_threads: Option<usize>,
+    /// 算子之间并行的线程数，只在[ExecutionMode::Parallel]下生效，`None`由ONNX Runtime决定
+    inter_threads: Option<usize>,
+    execution_mode: ExecutionMode,
+    /// 是否根据输入形状预先规划内存分配
+    memory_pattern: bool,
+    /// 是否为CPU执行提供者启用内存池
+    cpu_arena: bool,
+}
 
-use ort::session::{builder::GraphOptimizationLevel, Session};
+impl Default for ModelOptions {
+    fn default() -> Self {
+        Self {
+            intra_threads: None,
+            inter_threads: None,
+            execution_mode: ExecutionMode::Sequential,
+            memory_pattern: true,
+            cpu_arena: true,
+        }
+    }
+}
+
+impl ModelOptions {
+    /// 设置intra-op线程数，0视为1
+    pub fn with_intra_threads(mut self, threads: usize) -> Self {
+        self.intra_threads = Some(threads.max(1));
+        self
+    }
+    
+    /// 设置inter-op线程数，0视为1
+    pub fn with_inter_threads(mut self, threads: usize) -> Self {
+        self.inter_threads = Some(threads.max(1));
+        self
+    }
+    
+    /// 设置算子的执行方式
+    pub fn with_execution_mode(mut self, mode: ExecutionMode) -> Self {
+        self.execution_mode = mode;
+        self
+    }
+    
+    /// 设置是否启用内存模式优化，输入形状经常变化时可关闭以减少内存占用
+    pub fn with_memory_pattern(mut self, enable: bool) -> Self {
+        self.memory_pattern = enable;
+        self
+    }
+    
+    /// 设置是否启用CPU内存池，关闭后空闲内存会及时归还系统，但分配开销更大
+    pub fn with_cpu_arena(mut self, enable: bool) -> Self {
+        self.cpu_arena = enable;
+        self
+    }
+    
+    /// 实际使用的intra-op线程数
+    pub fn intra_threads(&self) -> usize {
+        self.intra_threads.unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(DEFAULT_INTRA_THREADS, |n| n.get())
+        })
+    }
+    
+    /// 设置的inter-op线程数
+    pub fn inter_threads(&self) -> Option<usize> {
+        self.inter_threads
+    }
+    
+    /// 算子的执行方式
+    pub fn execution_mode(&self) -> ExecutionMode {
+        self.execution_mode
+    }
+    
+    /// 是否启用内存模式优化
+    pub fn memory_pattern(&self) -> bool {
+        self.memory_pattern
+    }
+    
+    /// 是否启用CPU内存池
+    pub fn cpu_arena(&self) -> bool {
+        self.cpu_arena
+    }
+    
+    /// 按配置创建会话构建器
+    fn session_builder(&self) -> Result<SessionBuilder, ort::Error> {
+        let mut builder = Session::builder()?
+            .with_optimization_level(GraphOptimizationLevel::Level3)?
+            .with_intra_threads(self.intra_threads())?
+            .with_parallel_execution(self.execution_mode == ExecutionMode::Parallel)?
+            .with_memory_pattern(self.memory_pattern)?
+            .with_execution_providers([
+                CPUExecutionProvider::default().with_arena_allocator(self.cpu_arena).build(),
+            ])?;
+        if let Some(threads) = self.inter_threads {
+            builder = builder.with_inter_threads(threads)?;
+        }
+        Ok(builder)
+    }
+}
 
 /// 加载YOLO模型（只检测person类别）
 /// 
 /// 加载ONNX格式的YOLO模型，并应用优化配置。
-/// 模型默认配置为使用4个线程进行推理，这对于大多数场景已经足够。
+/// 模型使用[DEFAULT_INTRA_THREADS]个线程进行推理，其余配置与[ModelOptions::default]相同；
+/// 需要按机器核心数调整时使用[load_model_with_options]。
 /// 
 /// # 参数
 /// * `model_path` - 模型文件路径
@@ -29,11 +181,27 @@ use ort::session::{builder::GraphOptimizationLevel, Session};
 /// # }
 /// ```
 pub fn load_model(model_path: &str) -> Result<Session, ort::Error> {
-    let model = Session::builder()?
-        .with_optimization_level(GraphOptimizationLevel::Level3)?
-        .with_intra_threads(4)?
-        .commit_from_file(model_path)?;
-    Ok(model)
+    load_model_with_options(model_path, &ModelOptions::default().with_intra_threads(DEFAULT_INTRA_THREADS))
+}
+
+/// 按指定的线程和内存配置加载YOLO模型
+/// 
+/// # 参数
+/// * `model_path` - 模型文件路径
+/// * `options` - 会话配置
+pub fn load_model_with_options(model_path: &str, options: &ModelOptions) -> Result<Session, ort::Error> {
+    options.session_builder()?.commit_from_file(model_path)
+}
+
+/// 加载开启了ONNX Runtime性能分析的模型会话，其余配置与[load_model_with_options]相同
+/// 
+/// 分析结果在调用[Session::end_profiling]后写入以`profile_prefix`开头的JSON文件，
+/// 文件名由ONNX Runtime追加时间戳生成。性能分析会拖慢推理，只应用于单独的分析会话。
+pub fn load_profiled_model(model_path: &str, profile_prefix: &std::path::Path, options: &ModelOptions) -> Result<Session, ort::Error> {
+    options
+        .session_builder()?
+        .with_profiling(profile_prefix)?
+        .commit_from_file(model_path)
 }
 
 /// 从内存数据加载YOLO模型
@@ -62,30 +230,1577 @@ pub fn load_model(model_path: &str) -> Result<Session, ort::Error> {
 /// # }
 /// ```
 pub fn load_model_from_memory(model_data: &[u8]) -> Result<Session, ort::Error> {
-    let model = Session::builder()?
-        .with_optimization_level(GraphOptimizationLevel::Level3)?
-        .with_intra_threads(4)?
-        .commit_from_memory(&model_data)?;
-    Ok(model)
+    ModelOptions::default()
+        .with_intra_threads(DEFAULT_INTRA_THREADS)
+        .session_builder()?
+        .commit_from_memory(model_data)
 }
 
-/// 静态加载YOLO模型（示例）
+/// 内嵌的默认模型数据
+#[cfg(feature = "embedded-model")]
+pub const EMBEDDED_MODEL: &[u8] = include_bytes!("../../module/color/yolo11n_int8.onnx");
+
+/// 静态加载内嵌的YOLO模型
 /// 
-/// 展示如何使用include_bytes!宏静态嵌入模型文件并加载
-/// 注意：这只是一种示例实现，实际使用时需要根据模型文件的实际路径调整
+/// 需要启用`embedded-model`特性，内嵌模型的体积和许可见模块文档。
 /// 
 /// # 返回值
 /// 返回加载的Session对象
 /// 
 /// # 错误处理
-/// 如果模型加载失败会返回Err
-#[allow(dead_code)]
+/// 未启用`embedded-model`特性时总是返回Err
+#[cfg(feature = "embedded-model")]
+pub fn load_static_model() -> Result<Session, ort::Error> {
+    load_model_from_memory(EMBEDDED_MODEL)
+}
+
+/// 静态加载内嵌的YOLO模型
+/// 
+/// 需要启用`embedded-model`特性，内嵌模型的体积和许可见模块文档。
+/// 
+/// # 返回值
+/// 返回加载的Session对象
+/// 
+/// # 错误处理
+/// 未启用`embedded-model`特性时总是返回Err
+#[cfg(not(feature = "embedded-model"))]
 pub fn load_static_model() -> Result<Session, ort::Error> {
-    // 使用include_bytes!宏在编译时将模型文件嵌入到二进制文件中
-    // 注意：需要根据实际的模型文件路径进行调整
-    // const MODEL_BYTES: &[u8] = include_bytes!("../../module/color/yolo11n.onnx");
-    // load_model_from_memory(MODEL_BYTES)
-    
-    // 为了防止编译错误，这里暂时返回一个错误
-    Err(ort::Error::new("Static model not configured. Please adjust the path in the source code."))
-}
\ No newline at end of file
+    Err(ort::Error::new("未启用embedded-model特性，没有内嵌模型可加载"))
+}
+
+/// 加载YOLO模型并校验输入输出形状
+/// 
+/// 与[load_model]相同，但会在加载后调用[validate_model]，
+/// 以便在误用分类模型等不兼容模型时尽早给出明确的错误。
+/// 
+/// # 参数
+/// * `model_path` - 模型文件路径
+/// 
+/// # 错误处理
+/// 模型加载失败返回`PerpleError::Ort`，形状不兼容返回`PerpleError::IncompatibleModel`
+pub fn load_checked_model(model_path: &str) -> Result<Session, PerpleError> {
+    let model = load_model(model_path)?;
+    validate_model(&model)?;
+    Ok(model)
+}
+
+/// 按指定配置加载YOLO模型并校验输入输出形状
+/// 
+/// 与[load_checked_model]相同，但使用[load_model_with_options]加载。
+pub fn load_checked_model_with_options(model_path: &str, options: &ModelOptions) -> Result<Session, PerpleError> {
+    let model = load_model_with_options(model_path, options)?;
+    validate_model(&model)?;
+    Ok(model)
+}
+
+/// 多个独立检测器实例组成的推理池
+/// 
+/// 每个实例拥有自己的ONNX会话和锁，[detect](Self::detect)按轮询顺序选择实例，
+/// 多个线程同时调用时可以在不同实例上并行推理，不需要组批。
+/// 每个会话默认使用[DEFAULT_INTRA_THREADS]个推理线程，实例较多时可用
+/// [with_model_options](Self::with_model_options)减少每个会话的线程数，避免线程总数超过CPU核数。
+pub struct ModelPool {
+    sessions: Vec<Mutex<YoloDetector>>,
+    next: AtomicUsize,
+}
+
+impl ModelPool {
+    /// 加载`pool_size`个相互独立的检测器实例
+    /// 
+    /// # 参数
+    /// * `model_path` - ONNX模型文件路径
+    /// * `pool_size` - 实例数，至少为1
+    /// * `input_width` - 模型输入宽度
+    /// * `input_height` - 模型输入高度
+    /// 
+    /// # 错误处理
+    /// 与[YoloDetector::new]相同，任一实例创建失败时返回该错误
+    pub fn new(model_path: &str, pool_size: usize, input_width: usize, input_height: usize) -> Result<Self, PerpleError> {
+        Self::from_fn(pool_size, || YoloDetector::new(model_path, input_width, input_height))
+    }
+    
+    /// 按指定的会话配置加载`pool_size`个检测器实例，参见[YoloDetector::with_model_options]
+    pub fn with_model_options(
+        model_path: &str,
+        pool_size: usize,
+        input_width: usize,
+        input_height: usize,
+        options: &ModelOptions,
+    ) -> Result<Self, PerpleError> {
+        Self::from_fn(pool_size, || YoloDetector::with_model_options(model_path, input_width, input_height, options))
+    }
+    
+    fn from_fn(pool_size: usize, mut create: impl FnMut() -> Result<YoloDetector, PerpleError>) -> Result<Self, PerpleError> {
+        let sessions = (0..pool_size.max(1))
+            .map(|_| create().map(Mutex::new))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { sessions, next: AtomicUsize::new(0) })
+    }
+    
+    /// 实例数
+    pub fn pool_size(&self) -> usize {
+        self.sessions.len()
+    }
+    
+    /// 对每个实例执行一次配置，例如统一设置阈值
+    pub fn configure(&self, mut configure: impl FnMut(&mut YoloDetector)) {
+        for session in &self.sessions {
+            configure(&mut session.lock().unwrap_or_else(PoisonError::into_inner));
+        }
+    }
+    
+    /// 按轮询顺序选择一个实例检测图像
+    /// 
+    /// 只锁定被选中的实例；该实例正忙时等待它完成，不会改选其他实例。
+    pub fn detect(&self, image: &DynamicImage) -> Result<Bounds, PerpleError> {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.sessions.len();
+        self.session(index).detect_image(image)
+    }
+    
+    /// 使用第`index % 实例数`个实例检测图像，供固定使用某个实例的调用方（如[DetectorPool](crate::color::pool::DetectorPool)的工作线程）使用
+    pub(crate) fn detect_on(&self, index: usize, image: &DynamicImage) -> Result<Bounds, PerpleError> {
+        self.session(index).detect_image(image)
+    }
+    
+    /// 锁定第`index % 实例数`个实例
+    /// 
+    /// 某次检测panic会毒化该实例的锁。检测器不在两次检测之间保留中间结果，
+    /// 因此忽略毒化标记继续使用该实例，而不是让之后分配到它的每个任务都失败。
+    fn session(&self, index: usize) -> MutexGuard<'_, YoloDetector> {
+        self.sessions[index % self.sessions.len()].lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl std::fmt::Debug for ModelPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModelPool")
+            .field("pool_size", &self.sessions.len())
+            .field("next", &self.next.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// 模型文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelFormat {
+    /// ONNX模型（`.onnx`）
+    Onnx,
+    /// PyTorch TorchScript模型（`.pt`、`.torchscript`）
+    TorchScript,
+    /// TensorFlow SavedModel（`saved_model.pb`或包含它的目录）
+    SavedModel,
+}
+
+impl ModelFormat {
+    /// 根据文件扩展名（或SavedModel的固定文件名）推断模型格式，无法识别时返回`None`
+    pub fn from_path(path: &str) -> Option<Self> {
+        let path = std::path::Path::new(path);
+        if path.file_name().is_some_and(|name| name == "saved_model.pb")
+            || path.join("saved_model.pb").is_file()
+        {
+            return Some(ModelFormat::SavedModel);
+        }
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "onnx" => Some(ModelFormat::Onnx),
+            "pt" | "torchscript" => Some(ModelFormat::TorchScript),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ModelFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelFormat::Onnx => write!(f, "ONNX"),
+            ModelFormat::TorchScript => write!(f, "TorchScript"),
+            ModelFormat::SavedModel => write!(f, "TensorFlow SavedModel"),
+        }
+    }
+}
+
+/// 根据文件路径自动识别模型格式并加载
+/// 
+/// ONNX Runtime只能直接加载ONNX模型，TorchScript和SavedModel
+/// 会返回`PerpleError::UnsupportedFormat`并附带转换建议。
+/// 
+/// # 参数
+/// * `path` - 模型文件路径
+/// 
+/// # 返回值
+/// 返回加载的Session对象及识别出的格式
+pub fn load_model_autodetect(path: &str) -> Result<(Session, ModelFormat), PerpleError> {
+    match ModelFormat::from_path(path) {
+        Some(ModelFormat::Onnx) => Ok((load_checked_model(path)?, ModelFormat::Onnx)),
+        Some(ModelFormat::TorchScript) => Err(PerpleError::UnsupportedFormat {
+            format: ModelFormat::TorchScript.to_string(),
+            suggestion: "请使用`yolo export format=onnx`或scripts/dev/to_onnx.py导出为ONNX".to_string(),
+        }),
+        Some(ModelFormat::SavedModel) => Err(PerpleError::UnsupportedFormat {
+            format: ModelFormat::SavedModel.to_string(),
+            suggestion: "请使用`python -m tf2onnx.convert --saved-model <目录>`转换为ONNX".to_string(),
+        }),
+        None => Err(PerpleError::UnsupportedFormat {
+            format: path.to_string(),
+            suggestion: "支持的扩展名: .onnx、.pt、.torchscript、saved_model.pb".to_string(),
+        }),
+    }
+}
+
+/// 校验模型会话的输入输出形状是否符合检测流程的要求
+/// 
+/// 只检查第一个输入和第一个输出，具体规则见[validate_shapes]。
+pub fn validate_model(model: &Session) -> Result<(), PerpleError> {
+    let (input_shape, output_shape) = model_shapes(model);
+    validate_shapes(&input_shape, &output_shape)
+}
+
+/// 模型第一个输入和第一个输出的形状`(输入, 输出)`，动态维度为-1，无法获取时为空
+pub(crate) fn model_shapes(model: &Session) -> (Vec<i64>, Vec<i64>) {
+    let input_shape: Vec<i64> = model.inputs.first()
+        .and_then(|input| input.input_type.tensor_shape())
+        .map(|shape| shape.to_vec())
+        .unwrap_or_default();
+    let output_shape: Vec<i64> = model.outputs.first()
+        .and_then(|output| output.output_type.tensor_shape())
+        .map(|shape| shape.to_vec())
+        .unwrap_or_default();
+    (input_shape, output_shape)
+}
+
+/// 校验输入输出形状
+/// 
+/// 动态维度（值为-1）视为满足任何约束。规则如下：
+/// - 输入必须为4维，且通道维（NCHW的第2维或NHWC的第4维）为1或3
+/// - 输出必须为3维，其中一维不少于[MIN_OUTPUT_PARAMS]（每个框的参数），另一维至少为1（框数量）
+/// 
+/// 输出可以是每行一个框的`[1, 框数量, 参数个数]`，也可以是未内置NMS的原始导出排列
+/// `[1, 参数个数, 锚点数]`（见[is_raw_output](crate::color::utils::is_raw_output)），
+/// 后者由检测器在后处理前用[transpose_raw_output](crate::color::utils::transpose_raw_output)转置。
+/// 
+/// # 参数
+/// * `input_shape` - 模型输入形状
+/// * `output_shape` - 模型输出形状
+pub fn validate_shapes(input_shape: &[i64], output_shape: &[i64]) -> Result<(), PerpleError> {
+    let incompatible = |reason: String| PerpleError::IncompatibleModel {
+        input_shape: input_shape.to_vec(),
+        output_shape: output_shape.to_vec(),
+        reason,
+    };
+    let is_channels = |dim: i64| dim == -1 || dim == 1 || dim == 3;
+
+    if input_shape.len() != 4 {
+        return Err(incompatible(format!("输入应为4维张量，实际为{}维", input_shape.len())));
+    }
+    if !is_channels(input_shape[1]) && !is_channels(input_shape[3]) {
+        return Err(incompatible("输入通道数应为1或3".to_string()));
+    }
+
+    if output_shape.len() != 3 {
+        return Err(incompatible(format!("输出应为3维张量，实际为{}维", output_shape.len())));
+    }
+    let (a, b) = (output_shape[1], output_shape[2]);
+    let is_params = |dim: i64| dim == -1 || dim >= MIN_OUTPUT_PARAMS as i64;
+    let is_anchors = |dim: i64| dim == -1 || dim >= 1;
+    let layout_ok = (is_params(b) && is_anchors(a)) || (is_params(a) && is_anchors(b));
+    if !layout_ok {
+        return Err(incompatible(format!(
+            "输出中应有一维不少于{}（每个框的参数个数），另一维为框数量",
+            MIN_OUTPUT_PARAMS
+        )));
+    }
+    Ok(())
+}
+
+/// 模型库的默认下载地址，可通过环境变量`PERPLE_MODEL_BASE_URL`覆盖
+#[cfg(feature = "download")]
+pub const DEFAULT_MODEL_BASE_URL: &str = "https://github.com/vesita/perple/raw/main/module/color";
+
+/// 模型库中的已知模型：(名称, 文件名, SHA-256)
+#[cfg(feature = "download")]
+const MODEL_ZOO: &[(&str, &str, &str)] = &[
+    ("yolo11n", "yolo11n.onnx", "ee24ba34c8219af20e63628716e0f12947556a72037b838a00aa4a4c258b9edc"),
+];
+
+/// 下载失败时的最大尝试次数
+#[cfg(feature = "download")]
+const FETCH_ATTEMPTS: usize = 3;
+
+/// 按名称获取模型库中的模型，返回本地路径
+/// 
+/// 模型缓存在`PERPLE_MODEL_CACHE`指定的目录，未设置时使用平台缓存目录下的`perple/models`。
+/// 缓存文件存在且校验和正确时不会访问网络。
+/// 
+/// # 示例
+/// 
+/// ```no_run
+/// use perple::color::model::{fetch, load_model};
+/// 
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let path = fetch("yolo11n")?;
+/// let model = load_model(path.to_str().unwrap())?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "download")]
+pub fn fetch(name: &str) -> Result<std::path::PathBuf, PerpleError> {
+    let base_url = std::env::var("PERPLE_MODEL_BASE_URL")
+        .unwrap_or_else(|_| DEFAULT_MODEL_BASE_URL.to_string());
+    fetch_with(name, &base_url, &model_cache_dir())
+}
+
+/// 从指定地址获取模型并缓存到指定目录
+/// 
+/// 先下载到同目录下的临时文件，校验SHA-256通过后再重命名，
+/// 因此中断的下载不会留下损坏的缓存文件；失败时最多重试[FETCH_ATTEMPTS]次。
+/// 
+/// # 参数
+/// * `name` - 模型名称，例如`"yolo11n"`
+/// * `base_url` - 模型文件所在目录的URL
+/// * `cache_dir` - 缓存目录
+#[cfg(feature = "download")]
+pub fn fetch_with(name: &str, base_url: &str, cache_dir: &std::path::Path) -> Result<std::path::PathBuf, PerpleError> {
+    let &(_, file_name, checksum) = MODEL_ZOO.iter()
+        .find(|(known, _, _)| *known == name)
+        .ok_or_else(|| PerpleError::Download(format!(
+            "模型库中没有名为{}的模型，可用模型: {}",
+            name,
+            MODEL_ZOO.iter().map(|(n, _, _)| *n).collect::<Vec<_>>().join(", ")
+        )))?;
+    fetch_file(file_name, checksum, base_url, cache_dir)
+}
+
+/// 获取`base_url`下的`file_name`并缓存到`cache_dir`，规则见[fetch_with]
+#[cfg(feature = "download")]
+fn fetch_file(file_name: &str, checksum: &str, base_url: &str, cache_dir: &std::path::Path) -> Result<std::path::PathBuf, PerpleError> {
+    let path = cache_dir.join(file_name);
+    if path.is_file() && file_sha256(&path)? == checksum {
+        return Ok(path);
+    }
+    
+    std::fs::create_dir_all(cache_dir)?;
+    let url = format!("{}/{}", base_url.trim_end_matches('/'), file_name);
+    let part = cache_dir.join(format!("{}.part", file_name));
+    let mut last_error = None;
+    for attempt in 1..=FETCH_ATTEMPTS {
+        match download_verified(&url, &part, checksum) {
+            Ok(()) => {
+                std::fs::rename(&part, &path)?;
+                return Ok(path);
+            }
+            Err(e) => {
+                log::warn!("下载模型失败: url={} attempt={} error={}", url, attempt, e);
+                let _ = std::fs::remove_file(&part);
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| PerpleError::Download(format!("下载{}失败", url))))
+}
+
+/// 下载文件到`dest`并校验SHA-256
+#[cfg(feature = "download")]
+fn download_verified(url: &str, dest: &std::path::Path, checksum: &str) -> Result<(), PerpleError> {
+    use sha2::{Digest, Sha256};
+    use std::io::{Read, Write};
+    
+    let response = ureq::get(url).call()
+        .map_err(|e| PerpleError::Download(format!("请求{}失败: {}", url, e)))?;
+    let mut reader = response.into_reader();
+    let mut file = std::io::BufWriter::new(std::fs::File::create(dest)?);
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        file.write_all(&buffer[..n])?;
+    }
+    file.flush()?;
+    
+    let actual = to_hex(&hasher.finalize());
+    if actual != checksum {
+        return Err(PerpleError::Download(format!(
+            "校验和不匹配: 期望{}，实际{}", checksum, actual
+        )));
+    }
+    Ok(())
+}
+
+/// 计算文件的SHA-256（十六进制小写）
+#[cfg(feature = "download")]
+fn file_sha256(path: &std::path::Path) -> Result<String, PerpleError> {
+    use sha2::{Digest, Sha256};
+    
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(to_hex(&hasher.finalize()))
+}
+
+#[cfg(feature = "download")]
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 模型缓存目录
+/// 
+/// 优先使用环境变量`PERPLE_MODEL_CACHE`，否则按平台选择：
+/// Linux为`$XDG_CACHE_HOME`或`~/.cache`，macOS为`~/Library/Caches`，Windows为`%LOCALAPPDATA%`。
+#[cfg(feature = "download")]
+pub fn model_cache_dir() -> std::path::PathBuf {
+    use std::path::PathBuf;
+    
+    if let Some(dir) = std::env::var_os("PERPLE_MODEL_CACHE") {
+        return PathBuf::from(dir);
+    }
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home.map(|h| h.join("Library").join("Caches"))
+    } else {
+        std::env::var_os("XDG_CACHE_HOME").map(PathBuf::from).or(home.map(|h| h.join(".cache")))
+    };
+    base.unwrap_or_else(std::env::temp_dir).join("perple").join("models")
+}
+
+/// 在YOLO模型输出之后追加NMS节点，导出无需后处理代码即可部署的模型
+/// 
+/// 需要启用`onnx-export`特性。输入模型的第一个输出必须是未内置NMS的原始输出`[1, 4 + 类别数, 框数]`，
+/// 坐标为`[cx, cy, w, h]`，opset不低于11。导出的模型保留原有输出，并追加三个输出：
+/// 
+/// - [NMS_INDICES_OUTPUT]：`[保留框数, 3]`（int64），每行为`[batch, 类别, 框索引]`
+/// - [NMS_BOXES_OUTPUT]：`[1, 保留框数, 4]`，保留框的`[cx, cy, w, h]`（模型输入坐标）
+/// - [NMS_SCORES_OUTPUT]：`[保留框数]`，保留框的置信度
+/// 
+/// 新增的节点、初始化张量和输出都以`perple_nms_`为前缀，不会与原模型中的名称冲突；
+/// 已包含该前缀的模型（例如已导出过一次）会被拒绝。
+/// NMS在每个类别内进行，之后按置信度在全部类别中取前[DETECTIONS_CAPACITY](crate::config::DETECTIONS_CAPACITY)个，
+/// 与[YoloDetector](crate::color::YoloDetector)的结果数量上限一致，保留框按置信度从高到低排列。
+/// 同一个框在多个类别上的分数都超过阈值时会按每个类别各保留一次，Rust端只取分数最高的类别。
+/// 
+/// # 参数
+/// * `input_model_path` - 原始模型路径
+/// * `output_model_path` - 导出模型路径
+/// * `nms_threshold` - NMS的IoU阈值，范围`[0, 1]`
+/// * `confidence_threshold` - 置信度阈值，范围`[0, 1]`
+/// 
+/// # 错误处理
+/// 阈值超出范围时返回[PerpleError::InvalidParameter]，模型文件无法解析、没有输出或已包含
+/// `perple_nms_`前缀的名称时返回[PerpleError::IncompatibleModel]，文件读写失败时返回[PerpleError::Io]
+#[cfg(feature = "onnx-export")]
+pub fn export_onnx_with_nms(
+    input_model_path: &str,
+    output_model_path: &str,
+    nms_threshold: f32,
+    confidence_threshold: f32,
+) -> Result<(), PerpleError> {
+    for (name, value) in [("NMS阈值", nms_threshold), ("置信度阈值", confidence_threshold)] {
+        if !(0.0..=1.0).contains(&value) {
+            return Err(PerpleError::InvalidParameter(format!("{}必须在[0, 1]范围内: {}", name, value)));
+        }
+    }
+    
+    let model = std::fs::read(input_model_path)?;
+    let output_name = onnx_proto::first_output_name(&model)
+        .ok_or_else(|| incompatible("无法从模型文件中读取输出名称"))?;
+    if onnx_proto::has_prefixed_names(&model) {
+        return Err(incompatible("模型已包含perple_nms_前缀的名称，可能已经追加过NMS节点"));
+    }
+    let nms_graph = onnx_proto::nms_graph(
+        &output_name,
+        nms_threshold,
+        confidence_threshold,
+        crate::config::DETECTIONS_CAPACITY as i64,
+    );
+    let exported = onnx_proto::merge_into_graph(&model, &nms_graph)
+        .ok_or_else(|| incompatible("无法解析模型文件中的图"))?;
+    std::fs::write(output_model_path, exported)?;
+    Ok(())
+}
+
+/// [quantize_weights]量化的权重至少包含的元素数，更小的权重（如第一层卷积）保持FP32
+#[cfg(feature = "onnx-export")]
+pub const QUANTIZE_MIN_ELEMENTS: usize = 1024;
+
+/// 将模型的权重量化为int8，导出体积约为原来四分之一的模型
+/// 
+/// 需要启用`onnx-export`特性，模型的opset不低于13。元素数不少于[QUANTIZE_MIN_ELEMENTS]的多维float权重
+/// 按输出通道对称量化，由新增的`DequantizeLinear`节点在加载时还原，因此只减小模型文件，
+/// 推理仍以FP32进行，检测结果与原模型相比只有量化误差带来的细微差别。
+/// 内嵌模型（见模块文档）即由此生成。
+/// 
+/// # 参数
+/// * `input_model_path` - 原始模型路径
+/// * `output_model_path` - 量化模型路径
+/// 
+/// # 返回值
+/// 被量化的权重个数
+/// 
+/// # 错误处理
+/// 模型文件无法解析、opset低于13或没有可量化的权重时返回[PerpleError::IncompatibleModel]，
+/// 文件读写失败时返回[PerpleError::Io]
+#[cfg(feature = "onnx-export")]
+pub fn quantize_weights(input_model_path: &str, output_model_path: &str) -> Result<usize, PerpleError> {
+    let model = std::fs::read(input_model_path)?;
+    match onnx_proto::default_opset(&model) {
+        Some(version) if version >= 13 => {}
+        version => return Err(incompatible(&format!("逐通道量化需要opset 13及以上，模型为{:?}", version))),
+    }
+    let (quantized, count) = onnx_proto::quantize_initializers(&model, QUANTIZE_MIN_ELEMENTS)
+        .ok_or_else(|| incompatible("无法解析模型文件中的图"))?;
+    if count == 0 {
+        return Err(incompatible("模型中没有可量化的float权重"));
+    }
+    std::fs::write(output_model_path, quantized)?;
+    Ok(count)
+}
+
+/// 模型文件不符合导出要求时的错误
+#[cfg(feature = "onnx-export")]
+fn incompatible(reason: &str) -> PerpleError {
+    PerpleError::IncompatibleModel {
+        input_shape: Vec::new(),
+        output_shape: Vec::new(),
+        reason: reason.to_string(),
+    }
+}
+
+/// [export_onnx_with_nms]导出模型中NMS保留框的索引输出
+#[cfg(feature = "onnx-export")]
+pub const NMS_INDICES_OUTPUT: &str = "perple_nms_indices";
+
+/// [export_onnx_with_nms]导出模型中NMS保留框的坐标输出
+#[cfg(feature = "onnx-export")]
+pub const NMS_BOXES_OUTPUT: &str = "perple_nms_boxes";
+
+/// [export_onnx_with_nms]导出模型中NMS保留框的置信度输出
+#[cfg(feature = "onnx-export")]
+pub const NMS_SCORES_OUTPUT: &str = "perple_nms_scores";
+
+/// 追加NMS节点和量化权重所需的ONNX protobuf消息，只声明用到的字段
+/// 
+/// 解码时未声明的字段被忽略，因此这些消息只用于读取名称、权重和编码新增的内容；
+/// 原模型中未改动部分的字节由[rewrite_graph]原样保留。
+#[cfg(feature = "onnx-export")]
+mod onnx_proto {
+    use prost::bytes::Buf;
+    use prost::encoding::{decode_key, decode_varint, encode_key, encode_varint, skip_field, DecodeContext, WireType};
+    use prost::Message;
+    
+    const TENSOR_FLOAT: i32 = 1;
+    const TENSOR_INT8: i32 = 3;
+    const TENSOR_INT64: i32 = 7;
+    const ATTRIBUTE_INT: i32 = 2;
+    const ATTRIBUTE_INTS: i32 = 7;
+    
+    const PREFIX: &str = "perple_nms_";
+    
+    /// ModelProto.graph的字段编号
+    const MODEL_GRAPH: u32 = 7;
+    /// GraphProto.node的字段编号
+    const GRAPH_NODE: u32 = 1;
+    /// GraphProto.initializer的字段编号
+    const GRAPH_INITIALIZER: u32 = 5;
+    /// 量化权重新增的名称后缀
+    const QUANTIZED_SUFFIX: &str = "_perple_int8";
+    /// 第2个输入为权重的算子
+    const WEIGHT_OPS: [&str; 4] = ["Conv", "ConvTranspose", "Gemm", "MatMul"];
+    
+    /// ModelProto，只声明graph和opset_import
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct ModelProto {
+        #[prost(message, optional, tag = "7")]
+        pub graph: Option<GraphProto>,
+        #[prost(message, repeated, tag = "8")]
+        pub opset_import: Vec<OperatorSetIdProto>,
+    }
+    
+    /// OperatorSetIdProto
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct OperatorSetIdProto {
+        #[prost(string, tag = "1")]
+        pub domain: String,
+        #[prost(int64, tag = "2")]
+        pub version: i64,
+    }
+    
+    /// GraphProto
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct GraphProto {
+        #[prost(message, repeated, tag = "1")]
+        pub node: Vec<NodeProto>,
+        #[prost(string, tag = "2")]
+        pub name: String,
+        #[prost(message, repeated, tag = "5")]
+        pub initializer: Vec<TensorProto>,
+        #[prost(message, repeated, tag = "11")]
+        pub input: Vec<ValueInfoProto>,
+        #[prost(message, repeated, tag = "12")]
+        pub output: Vec<ValueInfoProto>,
+    }
+    
+    /// NodeProto
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct NodeProto {
+        #[prost(string, repeated, tag = "1")]
+        pub input: Vec<String>,
+        #[prost(string, repeated, tag = "2")]
+        pub output: Vec<String>,
+        #[prost(string, tag = "3")]
+        pub name: String,
+        #[prost(string, tag = "4")]
+        pub op_type: String,
+        #[prost(message, repeated, tag = "5")]
+        pub attribute: Vec<AttributeProto>,
+    }
+    
+    /// AttributeProto，只声明整数和整数列表属性
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct AttributeProto {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(int64, optional, tag = "3")]
+        pub i: Option<i64>,
+        #[prost(int64, repeated, packed = "false", tag = "8")]
+        pub ints: Vec<i64>,
+        #[prost(int32, tag = "20")]
+        pub r#type: i32,
+    }
+    
+    /// TensorProto，只声明数据保存在模型文件内的初始化张量所需的字段
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct TensorProto {
+        #[prost(int64, repeated, packed = "false", tag = "1")]
+        pub dims: Vec<i64>,
+        #[prost(int32, tag = "2")]
+        pub data_type: i32,
+        #[prost(float, repeated, tag = "4")]
+        pub float_data: Vec<f32>,
+        #[prost(int64, repeated, tag = "7")]
+        pub int64_data: Vec<i64>,
+        #[prost(string, tag = "8")]
+        pub name: String,
+        #[prost(bytes = "vec", tag = "9")]
+        pub raw_data: Vec<u8>,
+        /// 非0表示数据保存在外部文件中
+        #[prost(int32, tag = "14")]
+        pub data_location: i32,
+    }
+    
+    /// ValueInfoProto
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct ValueInfoProto {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(message, optional, tag = "2")]
+        pub r#type: Option<TypeProto>,
+    }
+    
+    /// TypeProto，只声明张量类型
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct TypeProto {
+        #[prost(message, optional, tag = "1")]
+        pub tensor_type: Option<TensorTypeProto>,
+    }
+    
+    /// TypeProto.Tensor
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct TensorTypeProto {
+        #[prost(int32, tag = "1")]
+        pub elem_type: i32,
+        #[prost(message, optional, tag = "2")]
+        pub shape: Option<TensorShapeProto>,
+    }
+    
+    /// TensorShapeProto
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct TensorShapeProto {
+        #[prost(message, repeated, tag = "1")]
+        pub dim: Vec<Dimension>,
+    }
+    
+    /// TensorShapeProto.Dimension，`dim_param`为符号维度
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct Dimension {
+        #[prost(int64, optional, tag = "1")]
+        pub dim_value: Option<i64>,
+        #[prost(string, optional, tag = "2")]
+        pub dim_param: Option<String>,
+    }
+    
+    /// 解码模型中的图，模型无法解析或没有图时返回`None`
+    fn decode_graph(model: &[u8]) -> Option<GraphProto> {
+        ModelProto::decode(model).ok()?.graph
+    }
+    
+    /// 读取ModelProto.graph.output[0].name
+    pub(super) fn first_output_name(model: &[u8]) -> Option<String> {
+        let graph = decode_graph(model)?;
+        graph.output.into_iter().next().map(|output| output.name)
+    }
+    
+    /// 模型的节点输出、初始化张量或图输出中是否已有以[PREFIX]开头的名称
+    pub(super) fn has_prefixed_names(model: &[u8]) -> bool {
+        let Some(graph) = decode_graph(model) else {
+            return false;
+        };
+        let prefixed = |name: &String| name.starts_with(PREFIX);
+        graph.node.iter().flat_map(|node| &node.output).any(prefixed)
+            || graph.initializer.iter().map(|tensor| &tensor.name).any(prefixed)
+            || graph.output.iter().map(|output| &output.name).any(prefixed)
+    }
+    
+    /// 消息中的一个字段：完整编码，以及指定编号的长度分隔字段的内容
+    struct Field<'a> {
+        raw: &'a [u8],
+        payload: Option<&'a [u8]>,
+    }
+    
+    /// 按原顺序拆分消息的字段，编号为`tag`的字段额外给出内容，无法解析时返回`None`
+    fn fields(message: &[u8], tag: u32) -> Option<Vec<Field<'_>>> {
+        let mut fields = Vec::new();
+        let mut rest = message;
+        while rest.has_remaining() {
+            let start = rest;
+            let (field_tag, wire_type) = decode_key(&mut rest).ok()?;
+            let payload = if field_tag == tag && wire_type == WireType::LengthDelimited {
+                let len = usize::try_from(decode_varint(&mut rest).ok()?).ok()?;
+                let payload = rest.get(..len)?;
+                rest.advance(len);
+                Some(payload)
+            } else {
+                skip_field(wire_type, field_tag, &mut rest, DecodeContext::default()).ok()?;
+                None
+            };
+            fields.push(Field { raw: &start[..start.len() - rest.len()], payload });
+        }
+        Some(fields)
+    }
+    
+    /// 用`rewrite`改写模型的图，返回只包含一个`graph`字段的新模型
+    /// 
+    /// 多个`graph`字段按protobuf的规则先拼接为一个图再交给`rewrite`，
+    /// 其余字段按原顺序原样保留，改写后的图写在原图的位置。
+    fn rewrite_graph(model: &[u8], rewrite: impl FnOnce(Vec<u8>) -> Option<Vec<u8>>) -> Option<Vec<u8>> {
+        let mut graph = Vec::new();
+        let mut others = Vec::new();
+        let mut graph_at = None;
+        for field in fields(model, MODEL_GRAPH)? {
+            match field.payload {
+                Some(payload) => {
+                    graph_at.get_or_insert(others.len());
+                    graph.extend_from_slice(payload);
+                }
+                None => others.extend_from_slice(field.raw),
+            }
+        }
+        let graph_at = graph_at?;
+        let graph = rewrite(graph)?;
+    
+        let mut rewritten = Vec::with_capacity(others.len() + graph.len() + 10);
+        rewritten.extend_from_slice(&others[..graph_at]);
+        encode_key(MODEL_GRAPH, WireType::LengthDelimited, &mut rewritten);
+        encode_varint(graph.len() as u64, &mut rewritten);
+        rewritten.extend_from_slice(&graph);
+        rewritten.extend_from_slice(&others[graph_at..]);
+        Some(rewritten)
+    }
+    
+    /// 将`extra`并入模型的图
+    /// 
+    /// protobuf解析时按字段合并子消息，把新字段拼接到原图内容之后与之等价。
+    pub(super) fn merge_into_graph(model: &[u8], extra: &GraphProto) -> Option<Vec<u8>> {
+        rewrite_graph(model, |mut graph| {
+            extra.encode(&mut graph).ok()?;
+            Some(graph)
+        })
+    }
+    
+    /// 模型默认算子集（`ai.onnx`）的版本，没有声明时返回`None`
+    pub(super) fn default_opset(model: &[u8]) -> Option<i64> {
+        ModelProto::decode(model).ok()?
+            .opset_import
+            .into_iter()
+            .find(|opset| opset.domain.is_empty() || opset.domain == "ai.onnx")
+            .map(|opset| opset.version)
+    }
+    
+    /// 将模型中元素数不少于`min_elements`的多维float权重按第0维逐通道量化为int8
+    /// 
+    /// 只量化仅被[WEIGHT_OPS]用作权重（第2个输入）的初始化张量，锚点网格等其他常量保持不变。
+    /// 每个被量化的张量替换为int8数据、逐通道的scale和零点三个初始化张量，
+    /// 再由插在所有节点之前的`DequantizeLinear`节点还原为原名称的float张量，其余节点不需要改动。
+    /// 同时也是图输入的初始化张量、数据在外部文件中或含有非有限值的张量保持不变。
+    /// 
+    /// # 返回值
+    /// 改写后的模型及被量化的张量个数，模型无法解析时返回`None`
+    pub(super) fn quantize_initializers(model: &[u8], min_elements: usize) -> Option<(Vec<u8>, usize)> {
+        let mut quantized = 0;
+        let rewritten = rewrite_graph(model, |graph| {
+            let decoded = GraphProto::decode(graph.as_slice()).ok()?;
+            let is_weight = |name: &str| {
+                let mut uses = decoded.node.iter()
+                    .flat_map(|node| node.input.iter().enumerate().map(move |(i, input)| (node, i, input)))
+                    .filter(|(_, _, input)| *input == name)
+                    .peekable();
+                uses.peek().is_some() && uses.all(|(node, i, _)| i == 1 && WEIGHT_OPS.contains(&node.op_type.as_str()))
+                    && !decoded.input.iter().any(|input| input.name == name)
+            };
+            let mut nodes = Vec::new();
+            let mut rest = Vec::with_capacity(graph.len());
+            for field in fields(&graph, GRAPH_INITIALIZER)? {
+                let weight = field.payload
+                    .and_then(|payload| TensorProto::decode(payload).ok())
+                    .filter(|tensor| is_weight(&tensor.name))
+                    .and_then(|tensor| quantize_tensor(&tensor, min_elements));
+                let Some((tensors, node)) = weight else {
+                    rest.extend_from_slice(field.raw);
+                    continue;
+                };
+                for tensor in &tensors {
+                    prost::encoding::message::encode(GRAPH_INITIALIZER, tensor, &mut rest);
+                }
+                prost::encoding::message::encode(GRAPH_NODE, &node, &mut nodes);
+                quantized += 1;
+            }
+            // 节点按拓扑顺序排列，还原节点只依赖初始化张量，放在最前面即可
+            nodes.extend_from_slice(&rest);
+            Some(nodes)
+        })?;
+        Some((rewritten, quantized))
+    }
+    
+    /// 量化一个float初始化张量，返回int8数据、scale、零点三个张量和还原节点
+    /// 
+    /// 每个通道使用对称量化，scale为该通道绝对值最大值除以127，零点为0。
+    fn quantize_tensor(tensor: &TensorProto, min_elements: usize) -> Option<([TensorProto; 3], NodeProto)> {
+        if tensor.data_type != TENSOR_FLOAT || tensor.dims.len() < 2 || tensor.data_location != 0 {
+            return None;
+        }
+        let dims: Vec<usize> = tensor.dims.iter().map(|&dim| usize::try_from(dim).ok()).collect::<Option<_>>()?;
+        let count = dims.iter().try_fold(1usize, |count, &dim| count.checked_mul(dim))?;
+        if count < min_elements || dims[0] == 0 {
+            return None;
+        }
+        let values: Vec<f32> = if !tensor.raw_data.is_empty() {
+            if tensor.raw_data.len() != count * 4 {
+                return None;
+            }
+            tensor.raw_data.chunks_exact(4).map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).collect()
+        } else if tensor.float_data.len() == count {
+            tensor.float_data.clone()
+        } else {
+            return None;
+        };
+        if values.iter().any(|value| !value.is_finite()) {
+            return None;
+        }
+    
+        let channels = dims[0];
+        let mut scales = Vec::with_capacity(channels);
+        let mut data = Vec::with_capacity(count);
+        for channel in values.chunks_exact(count / channels) {
+            let max_abs = channel.iter().fold(0.0f32, |max, value| max.max(value.abs()));
+            let scale = if max_abs > 0.0 { max_abs / 127.0 } else { 1.0 };
+            scales.push(scale);
+            data.extend(channel.iter().map(|value| (value / scale).round().clamp(-127.0, 127.0) as i8 as u8));
+        }
+    
+        let name = |suffix: &str| format!("{}{}{}", tensor.name, QUANTIZED_SUFFIX, suffix);
+        let (data_name, scale_name, zero_point_name) = (name(""), name("_scale"), name("_zero_point"));
+        let data = TensorProto { dims: tensor.dims.clone(), data_type: TENSOR_INT8, raw_data: data, name: data_name.clone(), ..Default::default() };
+        let scale = float_tensor(&scale_name, &[channels as i64], &scales);
+        let zero_point = TensorProto {
+            dims: vec![channels as i64],
+            data_type: TENSOR_INT8,
+            raw_data: vec![0; channels],
+            name: zero_point_name.clone(),
+            ..Default::default()
+        };
+        let dequantize = NodeProto {
+            input: vec![data_name, scale_name, zero_point_name],
+            output: vec![tensor.name.clone()],
+            name: name("_dequantize"),
+            op_type: "DequantizeLinear".to_string(),
+            attribute: vec![int_attribute("axis", 0)],
+        };
+        Some(([data, scale, zero_point], dequantize))
+    }
+    
+    /// 一维int64初始化张量
+    fn int64_tensor(name: &str, values: &[i64]) -> TensorProto {
+        TensorProto {
+            dims: vec![values.len() as i64],
+            data_type: TENSOR_INT64,
+            int64_data: values.to_vec(),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+    
+    /// float初始化张量
+    fn float_tensor(name: &str, dims: &[i64], values: &[f32]) -> TensorProto {
+        TensorProto {
+            dims: dims.to_vec(),
+            data_type: TENSOR_FLOAT,
+            float_data: values.to_vec(),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+    
+    /// 整数属性
+    fn int_attribute(name: &str, value: i64) -> AttributeProto {
+        AttributeProto { name: name.to_string(), i: Some(value), r#type: ATTRIBUTE_INT, ..Default::default() }
+    }
+    
+    /// 整数列表属性
+    fn ints_attribute(name: &str, values: &[i64]) -> AttributeProto {
+        AttributeProto { name: name.to_string(), ints: values.to_vec(), r#type: ATTRIBUTE_INTS, ..Default::default() }
+    }
+    
+    /// 计算节点，节点名由第一个输出名加`_node`得到
+    fn node(op_type: &str, inputs: &[&str], outputs: &[&str], attributes: Vec<AttributeProto>) -> NodeProto {
+        NodeProto {
+            input: inputs.iter().map(|input| input.to_string()).collect(),
+            output: outputs.iter().map(|output| output.to_string()).collect(),
+            name: format!("{}_node", outputs[0]),
+            op_type: op_type.to_string(),
+            attribute: attributes,
+        }
+    }
+    
+    /// 张量类型描述，`None`维度使用符号名`num_selected`
+    fn value_info(name: &str, elem_type: i32, dims: &[Option<i64>]) -> ValueInfoProto {
+        let dim = dims.iter()
+            .map(|dim| match dim {
+                Some(value) => Dimension { dim_value: Some(*value), dim_param: None },
+                None => Dimension { dim_value: None, dim_param: Some("num_selected".to_string()) },
+            })
+            .collect();
+        ValueInfoProto {
+            name: name.to_string(),
+            r#type: Some(TypeProto {
+                tensor_type: Some(TensorTypeProto { elem_type, shape: Some(TensorShapeProto { dim }) }),
+            }),
+        }
+    }
+    
+    /// 生成NMS相关的节点、初始化张量和输出，由[merge_into_graph]并入原图
+    /// 
+    /// `NonMaxSuppression`按类别各保留至多`max_boxes`个框，之后再用`TopK`按置信度
+    /// 取全部类别中的前`max_boxes`个，与Rust端NMS的总数上限一致。
+    pub(super) fn nms_graph(output_name: &str, nms_threshold: f32, confidence_threshold: f32, max_boxes: i64) -> GraphProto {
+        let name = |suffix: &str| format!("{}{}", PREFIX, suffix);
+        let (indices, selected_boxes, selected_scores) =
+            (super::NMS_INDICES_OUTPUT, super::NMS_BOXES_OUTPUT, super::NMS_SCORES_OUTPUT);
+        let (axis_1, zero, four, two, three, flatten, max_int, max_output, iou, score) = (
+            name("axis_1"), name("zero"), name("four"), name("two"), name("three"), name("flatten"),
+            name("max_int"), name("max_output"), name("iou_threshold"), name("score_threshold"),
+        );
+        let (raw_boxes, boxes, scores, candidates, candidate_scores) = (
+            name("raw_boxes"), name("all_boxes"), name("all_scores"), name("candidates"), name("candidate_scores"),
+        );
+        let (candidate_count, limits, top_k, order, column, box_index) = (
+            name("candidate_count"), name("limits"), name("top_k"), name("order"), name("box_index_column"), name("box_index"),
+        );
+    
+        let nodes = vec![
+            // [1, 4 + nc, N] -> 前4行为框坐标，其余为各类别分数
+            node("Slice", &[output_name, &zero, &four, &axis_1], &[&raw_boxes], vec![]),
+            node("Transpose", &[&raw_boxes], &[&boxes], vec![ints_attribute("perm", &[0, 2, 1])]),
+            node("Slice", &[output_name, &four, &max_int, &axis_1], &[&scores], vec![]),
+            node(
+                "NonMaxSuppression",
+                &[&boxes, &scores, &max_output, &iou, &score],
+                &[&candidates],
+                vec![int_attribute("center_point_box", 1)],
+            ),
+            // 按置信度从高到低取前min(候选数, max_boxes)个
+            node("GatherND", &[&scores, &candidates], &[&candidate_scores], vec![]),
+            node("Shape", &[&candidate_scores], &[&candidate_count], vec![]),
+            node("Concat", &[&candidate_count, &max_output], &[&limits], vec![int_attribute("axis", 0)]),
+            node("ReduceMin", &[&limits], &[&top_k], vec![int_attribute("keepdims", 1)]),
+            node("TopK", &[&candidate_scores, &top_k], &[selected_scores, &order], vec![int_attribute("axis", 0)]),
+            node("Gather", &[&candidates, &order], &[indices], vec![int_attribute("axis", 0)]),
+            // 取每行的框索引，收集保留框的坐标
+            node("Slice", &[indices, &two, &three, &axis_1], &[&column], vec![]),
+            node("Reshape", &[&column, &flatten], &[&box_index], vec![]),
+            node("Gather", &[&boxes, &box_index], &[selected_boxes], vec![int_attribute("axis", 1)]),
+        ];
+        let initializer = vec![
+            int64_tensor(&axis_1, &[1]),
+            int64_tensor(&zero, &[0]),
+            int64_tensor(&four, &[4]),
+            int64_tensor(&two, &[2]),
+            int64_tensor(&three, &[3]),
+            int64_tensor(&flatten, &[-1]),
+            int64_tensor(&max_int, &[i64::MAX]),
+            int64_tensor(&max_output, &[max_boxes]),
+            float_tensor(&iou, &[1], &[nms_threshold]),
+            float_tensor(&score, &[1], &[confidence_threshold]),
+        ];
+        let output = vec![
+            value_info(indices, TENSOR_INT64, &[None, Some(3)]),
+            value_info(selected_boxes, TENSOR_FLOAT, &[Some(1), None, Some(4)]),
+            value_info(selected_scores, TENSOR_FLOAT, &[None]),
+        ];
+        GraphProto { node: nodes, initializer, output, ..Default::default() }
+    }
+    
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::color::model::{export_onnx_with_nms, quantize_weights, NMS_BOXES_OUTPUT, NMS_INDICES_OUTPUT, NMS_SCORES_OUTPUT, QUANTIZE_MIN_ELEMENTS};
+        use crate::config::DETECTIONS_CAPACITY;
+        use crate::error::PerpleError;
+    
+        /// 输入图像的边长
+        const INPUT_SIZE: i64 = 32;
+        /// 合成模型的类别数
+        const CLASSES: usize = 2;
+    
+        /// 合成模型顶层字段：ir_version、producer_name、graph和opset_import
+        #[derive(Clone, PartialEq, Message)]
+        struct TestModel {
+            #[prost(int64, tag = "1")]
+            ir_version: i64,
+            #[prost(string, tag = "2")]
+            producer_name: String,
+            #[prost(message, optional, tag = "7")]
+            graph: Option<GraphProto>,
+            #[prost(message, repeated, tag = "8")]
+            opset_import: Vec<OperatorSetIdProto>,
+        }
+    
+        /// 原始导出排列`[4 + 类别数, 锚点数]`的输出数据，每个框为`([cx, cy, w, h], 类别, 分数)`，
+        /// 其余锚点全为0
+        fn raw_output(boxes: &[([f32; 4], usize, f32)], anchors: usize) -> Vec<f32> {
+            let mut raw = vec![0.0; (4 + CLASSES) * anchors];
+            for (anchor, (bbox, class, score)) in boxes.iter().enumerate() {
+                for (param, value) in bbox.iter().enumerate() {
+                    raw[param * anchors + anchor] = *value;
+                }
+                raw[(4 + class) * anchors + anchor] = *score;
+            }
+            raw
+        }
+    
+        /// 输出固定的YOLO原始输出模型：`raw_output -> Identity -> output0`，
+        /// 输入`images`不参与计算；opset_import位于graph之后
+        fn synthetic_model(raw: &[f32]) -> Vec<u8> {
+            let anchors = (raw.len() / (4 + CLASSES)) as i64;
+            let output_dims = [1, 4 + CLASSES as i64, anchors];
+            let graph = GraphProto {
+                node: vec![node("Identity", &["raw_output"], &["output0"], vec![])],
+                name: "yolo".to_string(),
+                initializer: vec![float_tensor("raw_output", &output_dims, raw)],
+                input: vec![value_info("images", TENSOR_FLOAT, &[Some(1), Some(3), Some(INPUT_SIZE), Some(INPUT_SIZE)])],
+                output: vec![value_info("output0", TENSOR_FLOAT, &output_dims.map(Some))],
+            };
+            TestModel {
+                ir_version: 8,
+                producer_name: "perple-test".to_string(),
+                graph: Some(graph),
+                opset_import: vec![OperatorSetIdProto { domain: String::new(), version: 17 }],
+            }
+            .encode_to_vec()
+        }
+    
+        /// 8个锚点的合成模型，阈值0.25/0.45下Rust端NMS保留3个框
+        fn clustered_model() -> Vec<u8> {
+            let boxes = [
+                ([10.0, 10.0, 8.0, 8.0], 0, 0.9),
+                // 与第一个框同类且高度重叠，被抑制
+                ([11.0, 10.0, 8.0, 8.0], 0, 0.8),
+                // 位置相同但类别不同，保留
+                ([11.0, 10.0, 8.0, 8.0], 1, 0.7),
+                ([25.0, 25.0, 6.0, 6.0], 0, 0.6),
+                // 低于置信度阈值
+                ([25.0, 25.0, 6.0, 6.0], 0, 0.1),
+            ];
+            synthetic_model(&raw_output(&boxes, 8))
+        }
+    
+        /// 将`model`写入临时文件后导出，返回导出文件的内容
+        fn export(name: &str, model: &[u8]) -> Result<Vec<u8>, PerpleError> {
+            let (input, output) = temp_paths(name);
+            std::fs::write(&input, model).unwrap();
+            let result = export_onnx_with_nms(input.to_str().unwrap(), output.to_str().unwrap(), 0.45, 0.25);
+            let exported = result.map(|_| std::fs::read(&output).unwrap());
+            let _ = std::fs::remove_file(&input);
+            let _ = std::fs::remove_file(&output);
+            exported
+        }
+    
+        fn temp_paths(name: &str) -> (std::path::PathBuf, std::path::PathBuf) {
+            let dir = std::env::temp_dir();
+            let path = |suffix: &str| dir.join(format!("perple_export_{}_{}_{}.onnx", std::process::id(), name, suffix));
+            (path("in"), path("out"))
+        }
+    
+        /// 除graph外的顶层字段的完整编码
+        fn non_graph_fields(model: &[u8]) -> Vec<Vec<u8>> {
+            fields(model, MODEL_GRAPH).unwrap()
+                .into_iter()
+                .filter(|field| field.payload.is_none())
+                .map(|field| field.raw.to_vec())
+                .collect()
+        }
+    
+        #[test]
+        fn export_round_trip_keeps_model_and_adds_outputs() {
+            let model = clustered_model();
+            let exported = export("round_trip", &model).unwrap();
+    
+            // 只有一个graph字段，其余字段按原顺序原样保留
+            let tags: Vec<u32> = fields(&exported, MODEL_GRAPH).unwrap()
+                .iter()
+                .map(|field| decode_key(&mut { field.raw }).unwrap().0)
+                .collect();
+            assert_eq!(tags, vec![1, 2, MODEL_GRAPH, 8]);
+            assert_eq!(non_graph_fields(&exported), non_graph_fields(&model));
+    
+            // 原图内容是合并后图的前缀，原有输出仍是第一个输出
+            let graph_of = |model: &[u8]| fields(model, MODEL_GRAPH).unwrap().into_iter().find_map(|field| field.payload).unwrap().to_vec();
+            assert!(graph_of(&exported).starts_with(&graph_of(&model)));
+            assert_eq!(first_output_name(&exported).as_deref(), Some("output0"));
+            let graph = decode_graph(&exported).unwrap();
+            let outputs: Vec<&str> = graph.output.iter().map(|output| output.name.as_str()).collect();
+            assert_eq!(outputs, ["output0", NMS_INDICES_OUTPUT, NMS_BOXES_OUTPUT, NMS_SCORES_OUTPUT]);
+            assert_eq!(graph.initializer[0].float_data.len(), (4 + CLASSES) * 8);
+        }
+    
+        #[test]
+        fn export_graph_is_well_formed() {
+            let graph = decode_graph(&export("well_formed", &clustered_model()).unwrap()).unwrap();
+    
+            let mut known: Vec<&str> = vec!["images"];
+            known.extend(graph.initializer.iter().map(|tensor| tensor.name.as_str()));
+            for (i, node) in graph.node.iter().enumerate() {
+                // 每个节点的输入都已由图输入、初始化张量或之前的节点定义
+                for input in &node.input {
+                    assert!(known.contains(&input.as_str()), "节点{}的输入{}未定义", i, input);
+                }
+                for output in &node.output {
+                    assert!(!known.contains(&output.as_str()), "名称{}重复", output);
+                    // 新增的名称都带有前缀
+                    assert!(i == 0 || output.starts_with(PREFIX), "{}", output);
+                    known.push(output);
+                }
+            }
+            for output in [NMS_INDICES_OUTPUT, NMS_BOXES_OUTPUT, NMS_SCORES_OUTPUT] {
+                assert!(known.contains(&output));
+            }
+    
+            // 阈值和总数上限写入对应的初始化张量
+            let initializer = |name: &str| graph.initializer.iter().find(|tensor| tensor.name == name).unwrap();
+            assert_eq!(initializer("perple_nms_iou_threshold").float_data, [0.45]);
+            assert_eq!(initializer("perple_nms_score_threshold").float_data, [0.25]);
+            assert_eq!(initializer("perple_nms_max_output").int64_data, [DETECTIONS_CAPACITY as i64]);
+    
+            // NMS之后按置信度取全局前K个，分数输出来自TopK
+            let top_k = graph.node.iter().find(|node| node.op_type == "TopK").unwrap();
+            assert_eq!(top_k.output[0], NMS_SCORES_OUTPUT);
+            let nms = graph.node.iter().position(|node| node.op_type == "NonMaxSuppression").unwrap();
+            assert!(graph.node.iter().position(|node| node.op_type == "TopK").unwrap() > nms);
+        }
+    
+        #[test]
+        fn export_rejects_already_exported_model() {
+            let exported = export("first", &clustered_model()).unwrap();
+            assert!(has_prefixed_names(&exported));
+            assert!(matches!(export("second", &exported), Err(PerpleError::IncompatibleModel { .. })));
+        }
+    
+        #[test]
+        fn merge_joins_repeated_graph_fields() {
+            // 早期导出在模型末尾追加第二个graph字段，合并后应只剩一个
+            let mut model = clustered_model();
+            prost::encoding::message::encode(MODEL_GRAPH, &nms_graph("output0", 0.5, 0.5, 10), &mut model);
+            let merged = merge_into_graph(&model, &GraphProto::default()).unwrap();
+            assert_eq!(fields(&merged, MODEL_GRAPH).unwrap().iter().filter(|field| field.payload.is_some()).count(), 1);
+            assert_eq!(decode_graph(&merged).unwrap().output.len(), 4);
+        }
+    
+        #[test]
+        fn export_rejects_thresholds_out_of_range() {
+            let result = export_onnx_with_nms("unused.onnx", "unused_out.onnx", f32::NAN, 0.25);
+            assert!(matches!(result, Err(PerpleError::InvalidParameter(_))));
+        }
+    
+        /// 导出`model`后分别用Rust端NMS处理原模型的输出、直接运行导出模型，返回两边保留的框数
+        fn rust_and_exported_counts(name: &str, model: &[u8]) -> (usize, usize) {
+            use crate::color::utils::CoordFormat;
+            use crate::color::YoloDetector;
+            use ort::{inputs, value::Tensor};
+    
+            let (input, output) = temp_paths(name);
+            std::fs::write(&input, model).unwrap();
+            export_onnx_with_nms(input.to_str().unwrap(), output.to_str().unwrap(), 0.45, 0.25).unwrap();
+    
+            let size = INPUT_SIZE as usize;
+            let mut detector = YoloDetector::new(input.to_str().unwrap(), size, size).unwrap()
+                .with_coord_format(CoordFormat::CxCyWh)
+                .with_confidence_threshold(0.25)
+                .with_nms_threshold(0.45);
+            let rust = detector.detect(&image::DynamicImage::new_rgb8(size as u32, size as u32)).unwrap().len();
+    
+            let mut session = crate::color::model::load_model(output.to_str().unwrap()).unwrap();
+            let images = Tensor::from_array(([1, 3, size, size], vec![0.0f32; 3 * size * size])).unwrap();
+            let outputs = session.run(inputs!["images" => images]).unwrap();
+            let (_, scores) = outputs[NMS_SCORES_OUTPUT].try_extract_tensor::<f32>().unwrap();
+            let (shape, _) = outputs[NMS_BOXES_OUTPUT].try_extract_tensor::<f32>().unwrap();
+            assert_eq!(shape[1] as usize, scores.len());
+            let exported = scores.len();
+    
+            let _ = std::fs::remove_file(&input);
+            let _ = std::fs::remove_file(&output);
+            (rust, exported)
+        }
+    
+        #[test]
+        fn exported_nms_matches_rust_nms() {
+            assert_eq!(rust_and_exported_counts("clustered", &clustered_model()), (3, 3));
+        }
+    
+        #[test]
+        fn exported_nms_caps_total_boxes() {
+            // 两个类别共40个互不重叠的框，超过结果容量
+            let boxes: Vec<([f32; 4], usize, f32)> = (0..40)
+                .map(|i| {
+                    let (column, row) = ((i % 8) as f32, (i / 8) as f32);
+                    ([2.0 + column * 4.0, 2.0 + row * 4.0, 2.0, 2.0], i % CLASSES, 0.5 + i as f32 * 0.01)
+                })
+                .collect();
+            let model = synthetic_model(&raw_output(&boxes, 48));
+            let capped = DETECTIONS_CAPACITY.min(boxes.len());
+            assert_eq!(rust_and_exported_counts("capped", &model), (capped, capped));
+        }
+    
+        /// 卷积模型的权重形状，元素数恰好为[QUANTIZE_MIN_ELEMENTS]
+        const WEIGHT_DIMS: [i64; 4] = [16, 4, 4, 4];
+        /// 权重全为0的通道
+        const ZERO_CHANNEL: usize = 3;
+    
+        /// 单个卷积的模型：`Conv(images, weight, bias) -> output0`，另有不参与计算的小矩阵`small`，
+        /// 以及与权重一样大、但只经`Identity`输出的常量`grid`
+        /// 
+        /// 权重以raw_data保存，各通道取值范围不同，第[ZERO_CHANNEL]个通道全为0。
+        fn conv_model(opset: i64) -> (Vec<u8>, Vec<f32>) {
+            let per_channel = QUANTIZE_MIN_ELEMENTS / WEIGHT_DIMS[0] as usize;
+            let weight: Vec<f32> = (0..QUANTIZE_MIN_ELEMENTS)
+                .map(|i| {
+                    let channel = i / per_channel;
+                    if channel == ZERO_CHANNEL {
+                        return 0.0;
+                    }
+                    ((i * 37 % 101) as f32 - 50.0) / 50.0 * (channel + 1) as f32
+                })
+                .collect();
+            let weight_tensor = TensorProto {
+                dims: WEIGHT_DIMS.to_vec(),
+                data_type: TENSOR_FLOAT,
+                raw_data: weight.iter().flat_map(|value| value.to_le_bytes()).collect(),
+                name: "weight".to_string(),
+                ..Default::default()
+            };
+            let graph = GraphProto {
+                node: vec![
+                    node("Conv", &["images", "weight", "bias"], &["output0"], vec![]),
+                    node("Identity", &["grid"], &["grid_copy"], vec![]),
+                ],
+                name: "conv".to_string(),
+                initializer: vec![
+                    weight_tensor,
+                    float_tensor("bias", &[16], &[0.5; 16]),
+                    float_tensor("small", &[2, 8], &[1.0; 16]),
+                    float_tensor("grid", &[2, 512], &weight[..QUANTIZE_MIN_ELEMENTS]),
+                ],
+                input: vec![value_info("images", TENSOR_FLOAT, &[Some(1), Some(4), Some(8), Some(8)])],
+                output: vec![
+                    value_info("output0", TENSOR_FLOAT, &[Some(1), Some(16), Some(5), Some(5)]),
+                    value_info("grid_copy", TENSOR_FLOAT, &[Some(2), Some(512)]),
+                ],
+            };
+            let model = TestModel {
+                ir_version: 8,
+                producer_name: "perple-test".to_string(),
+                graph: Some(graph),
+                opset_import: vec![OperatorSetIdProto { domain: String::new(), version: opset }],
+            }
+            .encode_to_vec();
+            (model, weight)
+        }
+    
+        #[test]
+        fn quantize_replaces_large_weights_with_dequantize() {
+            let (model, weight) = conv_model(17);
+            let (quantized, count) = quantize_initializers(&model, QUANTIZE_MIN_ELEMENTS).unwrap();
+            assert_eq!(count, 1);
+            assert_eq!(non_graph_fields(&quantized), non_graph_fields(&model));
+    
+            // 还原节点在最前面，输出原权重名，原有节点不变
+            let graph = decode_graph(&quantized).unwrap();
+            let ops: Vec<&str> = graph.node.iter().map(|node| node.op_type.as_str()).collect();
+            assert_eq!(ops, ["DequantizeLinear", "Conv", "Identity"]);
+            let dequantize = &graph.node[0];
+            assert_eq!(dequantize.input, ["weight_perple_int8", "weight_perple_int8_scale", "weight_perple_int8_zero_point"]);
+            assert_eq!(dequantize.output, ["weight"]);
+            assert_eq!(dequantize.attribute[0].i, Some(0));
+    
+            // 量化后的张量替换原权重的位置，偏置、小矩阵和不作为权重使用的常量原样保留
+            let names: Vec<&str> = graph.initializer.iter().map(|tensor| tensor.name.as_str()).collect();
+            assert_eq!(names, ["weight_perple_int8", "weight_perple_int8_scale", "weight_perple_int8_zero_point", "bias", "small", "grid"]);
+            let decoded = decode_graph(&model).unwrap();
+            assert_eq!(graph.initializer[3..], decoded.initializer[1..]);
+    
+            let (data, scale, zero_point) = (&graph.initializer[0], &graph.initializer[1], &graph.initializer[2]);
+            assert_eq!((data.data_type, data.dims.as_slice()), (TENSOR_INT8, WEIGHT_DIMS.as_slice()));
+            assert_eq!(scale.float_data.len(), 16);
+            assert_eq!((zero_point.data_type, zero_point.raw_data.as_slice()), (TENSOR_INT8, [0u8; 16].as_slice()));
+            assert_eq!(scale.float_data[ZERO_CHANNEL], 1.0);
+            // 每个值的量化误差不超过半个量化步长
+            let per_channel = weight.len() / 16;
+            for (i, (&q, &value)) in data.raw_data.iter().zip(&weight).enumerate() {
+                let step = scale.float_data[i / per_channel];
+                let restored = q as i8 as f32 * step;
+                assert!((restored - value).abs() <= step / 2.0 + 1e-6, "{}: {} vs {}", i, restored, value);
+            }
+        }
+    
+        #[test]
+        fn quantize_weights_checks_opset_and_remaining_weights() {
+            let (input, output) = temp_paths("quantize");
+            std::fs::write(&input, conv_model(11).0).unwrap();
+            let (input_path, output_path) = (input.to_str().unwrap(), output.to_str().unwrap());
+            assert!(matches!(quantize_weights(input_path, output_path), Err(PerpleError::IncompatibleModel { .. })));
+    
+            std::fs::write(&input, conv_model(13).0).unwrap();
+            assert_eq!(quantize_weights(input_path, output_path).unwrap(), 1);
+            // 已量化的模型中没有可量化的float权重
+            assert!(matches!(quantize_weights(output_path, input_path), Err(PerpleError::IncompatibleModel { .. })));
+            let _ = std::fs::remove_file(&input);
+            let _ = std::fs::remove_file(&output);
+        }
+    
+        #[test]
+        fn quantized_model_output_matches_original() {
+            use ort::{inputs, value::Tensor};
+    
+            let (model, _) = conv_model(17);
+            let (quantized, _) = quantize_initializers(&model, QUANTIZE_MIN_ELEMENTS).unwrap();
+            let run = |model: &[u8]| {
+                let mut session = crate::color::model::load_model_from_memory(model).unwrap();
+                let values: Vec<f32> = (0..4 * 8 * 8).map(|i| (i % 7) as f32 / 7.0).collect();
+                let images = Tensor::from_array(([1, 4, 8, 8], values)).unwrap();
+                let outputs = session.run(inputs!["images" => images]).unwrap();
+                outputs["output0"].try_extract_tensor::<f32>().unwrap().1.to_vec()
+            };
+            let (original, restored) = (run(&model), run(&quantized));
+            assert_eq!(original.len(), 16 * 5 * 5);
+            let max_diff = original.iter().zip(&restored).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+            assert!(max_diff < 0.1, "{}", max_diff);
+        }
+    
+        #[test]
+        #[cfg(feature = "embedded-model")]
+        fn embedded_model_is_quantized_yolo() {
+            use crate::color::model::EMBEDDED_MODEL;
+    
+            assert!(EMBEDDED_MODEL.len() < 4 * 1024 * 1024);
+            assert!(default_opset(EMBEDDED_MODEL).unwrap() >= 13);
+            let graph = decode_graph(EMBEDDED_MODEL).unwrap();
+            let dims = |info: &ValueInfoProto| -> Vec<Option<i64>> {
+                let shape = info.r#type.as_ref().unwrap().tensor_type.as_ref().unwrap().shape.as_ref().unwrap();
+                shape.dim.iter().map(|dim| dim.dim_value).collect()
+            };
+            assert_eq!(dims(&graph.input[0]), [Some(1), Some(3), Some(640), Some(640)]);
+            // 内置NMS的导出：[1, 300, 6]
+            assert_eq!(dims(&graph.output[0]), [Some(1), Some(300), Some(6)]);
+    
+            // 大的权重都已量化，每个都有对应的还原节点
+            assert!(graph.initializer.iter().all(|tensor| quantize_tensor(tensor, QUANTIZE_MIN_ELEMENTS).is_none()));
+            let int8_weights = graph.initializer.iter().filter(|tensor| tensor.name.ends_with(QUANTIZED_SUFFIX)).count();
+            let dequantize_nodes = graph.node.iter().filter(|node| node.op_type == "DequantizeLinear").count();
+            assert!(int8_weights > 0);
+            assert_eq!(int8_weights, dequantize_nodes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(result: Result<(), PerpleError>) -> String {
+        match result {
+            Err(PerpleError::IncompatibleModel { reason, .. }) => reason,
+            other => panic!("应返回IncompatibleModel，实际为{:?}", other),
+        }
+    }
+
+    /// 本地HTTP桩服务：依次返回`bodies`中的内容，用完后重复最后一个，返回基础URL和请求计数
+    #[cfg(feature = "download")]
+    fn serve(bodies: Vec<Vec<u8>>) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use std::io::{BufRead, BufReader, Write};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/models", listener.local_addr().unwrap());
+        let hits = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = std::sync::Arc::clone(&hits);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).is_ok_and(|n| n > 0) && line != "\r\n" {
+                    line.clear();
+                }
+                let hit = counter.fetch_add(1, Ordering::SeqCst);
+                let body = &bodies[hit.min(bodies.len() - 1)];
+                let header = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+                let _ = stream.write_all(header.as_bytes()).and_then(|_| stream.write_all(body));
+            }
+        });
+        (base_url, hits)
+    }
+
+    #[cfg(feature = "download")]
+    fn cache_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("perple_fetch_{}_{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// 假模型内容及其SHA-256
+    #[cfg(feature = "download")]
+    fn fake_model() -> (Vec<u8>, String) {
+        use sha2::{Digest, Sha256};
+
+        let bytes = b"fake onnx model".to_vec();
+        let checksum = to_hex(&Sha256::digest(&bytes));
+        (bytes, checksum)
+    }
+
+    #[test]
+    #[cfg(feature = "download")]
+    fn fetch_downloads_once_then_serves_cache_offline() {
+        use std::sync::atomic::Ordering;
+
+        let (model, checksum) = fake_model();
+        let (base_url, hits) = serve(vec![model.clone()]);
+        let dir = cache_dir("cache");
+        let path = fetch_file("fake.onnx", &checksum, &base_url, &dir).unwrap();
+        assert_eq!(path, dir.join("fake.onnx"));
+        assert_eq!(std::fs::read(&path).unwrap(), model);
+        assert!(!dir.join("fake.onnx.part").exists());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // 缓存有效时不访问网络，连不可达的地址也能返回
+        assert_eq!(fetch_file("fake.onnx", &checksum, "http://127.0.0.1:1", &dir).unwrap(), path);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // 缓存文件损坏时重新下载
+        std::fs::write(&path, b"corrupted").unwrap();
+        fetch_file("fake.onnx", &checksum, &base_url, &dir).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), model);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "download")]
+    fn fetch_retries_until_checksum_matches() {
+        use std::sync::atomic::Ordering;
+
+        let (model, checksum) = fake_model();
+        // 第一次返回被截断的内容
+        let (base_url, hits) = serve(vec![model[..4].to_vec(), model.clone()]);
+        let dir = cache_dir("retry");
+        let path = fetch_file("fake.onnx", &checksum, &base_url, &dir).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), model);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "download")]
+    fn fetch_leaves_no_files_after_checksum_failures() {
+        use std::sync::atomic::Ordering;
+
+        let (_, checksum) = fake_model();
+        let (base_url, hits) = serve(vec![b"wrong model".to_vec()]);
+        let dir = cache_dir("mismatch");
+        let result = fetch_file("fake.onnx", &checksum, &base_url, &dir);
+        assert!(matches!(&result, Err(PerpleError::Download(message)) if message.contains("校验和")), "{:?}", result);
+        assert_eq!(hits.load(Ordering::SeqCst), FETCH_ATTEMPTS);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(fetch_with("unknown", &base_url, &dir), Err(PerpleError::Download(_))));
+    }
+
+    #[test]
+    fn validate_shapes_accepts_detection_layouts() {
+        // 内置NMS导出
+        assert!(validate_shapes(&[1, 3, 640, 640], &[1, 300, 6]).is_ok());
+        // 未内置NMS的原始导出，检测器会先转置
+        assert!(validate_shapes(&[1, 3, 640, 640], &[1, 84, 8400]).is_ok());
+        // NHWC输入和动态维度
+        assert!(validate_shapes(&[1, 640, 640, 3], &[1, -1, 6]).is_ok());
+        assert!(validate_shapes(&[-1, -1, -1, -1], &[-1, -1, -1]).is_ok());
+    }
+
+    #[test]
+    fn validate_shapes_rejects_classification_model() {
+        assert!(reason(validate_shapes(&[1, 3, 224, 224], &[1, 1000])).contains("3维"));
+    }
+
+    #[test]
+    fn validate_shapes_rejects_bad_input() {
+        assert!(reason(validate_shapes(&[3, 640, 640], &[1, 300, 6])).contains("4维"));
+        assert!(reason(validate_shapes(&[1, 5, 640, 640], &[1, 300, 6])).contains("通道"));
+    }
+
+    #[test]
+    fn validate_shapes_rejects_too_few_params() {
+        assert!(reason(validate_shapes(&[1, 3, 640, 640], &[1, 4, 4])).contains("参数"));
+    }
+
+    #[test]
+    fn validate_shapes_requires_an_anchor_dimension() {
+        // 单通道输入可以接受，但输出中没有框数量维度
+        assert!(validate_shapes(&[1, 1, 640, 640], &[1, 300, 6]).is_ok());
+        assert!(reason(validate_shapes(&[1, 1, 640, 640], &[1, 0, 6])).contains("框数量"));
+    }
+
+    #[test]
+    fn incompatible_model_error_names_offending_shapes() {
+        let error = validate_shapes(&[1, 3, 224, 224], &[1, 1000]).unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("[1, 3, 224, 224]"), "{}", message);
+        assert!(message.contains("[1, 1000]"), "{}", message);
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-model")]
+    fn embedded_model_loads_from_memory() {
+        let model = load_model_from_memory(EMBEDDED_MODEL).unwrap();
+        validate_model(&model).unwrap();
+        assert!(load_static_model().is_ok());
+        let mut detector = YoloDetector::embedded().unwrap();
+        assert_eq!(detector.model_path(), "<embedded>");
+        assert!(detector.detect(&DynamicImage::new_rgb8(640, 480)).unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(not(feature = "embedded-model"))]
+    fn static_model_requires_feature() {
+        assert!(load_static_model().is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! 用户数据模块
+//!
+//! 允许在图像上附带自定义数据（如相机编号、云台位置），
+//! 数据随图像经过检测流水线，与该帧的检测结果一起输出。
+
+use std::time::Instant;
+
+use image::{DynamicImage, GenericImageView};
+
+use crate::color::bounds::BoundsN;
+use crate::config::DETECTIONS_CAPACITY;
+
+/// 图像流中的一帧图像
+/// 
+/// 序号由[Perple::update_frame](crate::Perple::update_frame)按写入顺序从1开始分配，
+/// 检测时发现序号不连续说明输入流丢弃了帧（或同一帧被重复写入），
+/// 该帧的检测结果通过[Bounds::source_frame_seq](BoundsN::source_frame_seq)与之对应。
+/// 序号为0表示未编号（如[Frame::default]），不参与连续性检查。
+#[derive(Clone)]
+pub struct Frame {
+    /// 图像
+    pub image: DynamicImage,
+    /// 帧序号
+    pub seq: u64,
+    /// 写入输入流的时间
+    pub timestamp: Instant,
+}
+
+impl Frame {
+    /// 以当前时间创建一帧
+    pub fn new(image: DynamicImage, seq: u64) -> Self {
+        Self { image, seq, timestamp: Instant::now() }
+    }
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self::new(DynamicImage::default(), 0)
+    }
+}
+
+impl std::fmt::Debug for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Frame")
+            .field("seq", &self.seq)
+            .field("dimensions", &self.image.dimensions())
+            .field("timestamp", &self.timestamp)
+            .finish()
+    }
+}
+
+/// 随图像在检测流水线中传递的数据
+/// 
+/// 决定图像流和结果流中的元素类型：`()`表示不附带数据，两个流分别直接传递
+/// [Frame]和[Bounds](crate::color::Bounds)；实现了[UserPayload]的类型`P`对应`(P, Frame)`和`(P, Bounds)`。
+/// 常量参数`N`为每帧检测结果的容量，见[BoundsN]。
+pub trait Payload<const N: usize = DETECTIONS_CAPACITY>: Clone + Send + 'static {
+    /// 图像流中的元素
+    type Frame: Default + Send;
+    /// 结果流中的元素
+    type Output: Default + Clone + Send;
+    
+    /// 将数据与图像组装为图像流元素
+    fn into_frame(self, frame: Frame) -> Self::Frame;
+    
+    /// 将图像流元素拆分为数据和图像
+    fn from_frame(frame: Self::Frame) -> (Self, Frame);
+    
+    /// 结果流元素中的检测结果
+    fn bounds(output: &Self::Output) -> &BoundsN<N>;
+    
+    /// 结果流元素中的检测结果（可变引用）
+    fn bounds_mut(output: &mut Self::Output) -> &mut BoundsN<N>;
+    
+    /// 将数据写入结果流元素
+    fn attach(self, output: &mut Self::Output);
+}
+
+/// 可随图像传递的自定义数据
+/// 
+/// 为自己的类型实现该标记trait后即可使用`Perple<P>`：
+/// 
+/// ```
+/// use perple::color::UserPayload;
+/// 
+/// #[derive(Debug, Clone, Default)]
+/// struct CameraInfo {
+///     camera_id: u32,
+///     pan: f32,
+///     tilt: f32,
+/// }
+/// 
+/// impl UserPayload for CameraInfo {}
+/// ```
+pub trait UserPayload: Clone + Default + Send + 'static {}
+
+impl<const N: usize> Payload<N> for () {
+    type Frame = Frame;
+    type Output = BoundsN<N>;
+    
+    fn into_frame(self, frame: Frame) -> Frame {
+        frame
+    }
+    
+    fn from_frame(frame: Frame) -> (Self, Frame) {
+        ((), frame)
+    }
+    
+    fn bounds(output: &BoundsN<N>) -> &BoundsN<N> {
+        output
+    }
+    
+    fn bounds_mut(output: &mut BoundsN<N>) -> &mut BoundsN<N> {
+        output
+    }
+    
+    fn attach(self, _output: &mut BoundsN<N>) {}
+}
+
+impl<P: UserPayload, const N: usize> Payload<N> for P {
+    type Frame = (P, Frame);
+    type Output = (P, BoundsN<N>);
+    
+    fn into_frame(self, frame: Frame) -> (P, Frame) {
+        (self, frame)
+    }
+    
+    fn from_frame(frame: (P, Frame)) -> (Self, Frame) {
+        frame
+    }
+    
+    fn bounds(output: &(P, BoundsN<N>)) -> &BoundsN<N> {
+        &output.1
+    }
+    
+    fn bounds_mut(output: &mut (P, BoundsN<N>)) -> &mut BoundsN<N> {
+        &mut output.1
+    }
+    
+    fn attach(self, output: &mut (P, BoundsN<N>)) {
+        output.0 = self;
+    }
+}
@@ -0,0 +1,367 @@
+//! 检测线程池模块
+//!
+//! 在固定数量的工作线程上并行检测图像：[DetectorPool::submit]提交单帧并返回结果句柄，
+//! [DetectorPool::map_images]按输入顺序批量检测。
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+
+use image::DynamicImage;
+
+use crate::color::bounds::Bounds;
+use crate::color::model::{ModelOptions, ModelPool, DEFAULT_INTRA_THREADS};
+use crate::config::{DEFAULT_INPUT_HEIGHT, DEFAULT_INPUT_WIDTH};
+use crate::error::PerpleError;
+
+/// 工作线程执行的检测函数，参数为工作线程编号和图像
+type DetectFn = Arc<dyn Fn(usize, &DynamicImage) -> Result<Bounds, PerpleError> + Send + Sync>;
+
+/// 关闭检测池时对尚未开始的任务的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShutdownMode {
+    /// 处理完队列中的全部任务后退出
+    #[default]
+    Drain,
+    /// 丢弃队列中尚未开始的任务，其结果句柄返回错误；正在执行的任务仍会完成
+    Abort,
+}
+
+/// [DetectorPool]的配置
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    workers: Option<usize>,
+    shared_session: bool,
+    input_width: usize,
+    input_height: usize,
+    model_options: Option<ModelOptions>,
+    shutdown_mode: ShutdownMode,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            workers: None,
+            shared_session: false,
+            input_width: DEFAULT_INPUT_WIDTH,
+            input_height: DEFAULT_INPUT_HEIGHT,
+            model_options: None,
+            shutdown_mode: ShutdownMode::default(),
+        }
+    }
+}
+
+impl PoolConfig {
+    /// 设置工作线程数，0视为1；未设置时见[worker_count](Self::worker_count)
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = Some(workers.max(1));
+        self
+    }
+
+    /// 设置是否所有工作线程共用一个模型会话，默认每个工作线程独立加载一个
+    ///
+    /// 共用会话只加载一次模型、节省内存，但同一时刻只有一个线程在推理。
+    pub fn with_shared_session(mut self, shared: bool) -> Self {
+        self.shared_session = shared;
+        self
+    }
+
+    /// 设置模型输入尺寸
+    pub fn with_input_size(mut self, input_width: usize, input_height: usize) -> Self {
+        self.input_width = input_width;
+        self.input_height = input_height;
+        self
+    }
+
+    /// 设置每个会话的配置，未设置时每个会话使用[DEFAULT_INTRA_THREADS]个推理线程
+    pub fn with_model_options(mut self, options: ModelOptions) -> Self {
+        self.model_options = Some(options);
+        self
+    }
+
+    /// 设置检测池被丢弃时的关闭方式，默认[ShutdownMode::Drain]
+    pub fn with_shutdown_mode(mut self, mode: ShutdownMode) -> Self {
+        self.shutdown_mode = mode;
+        self
+    }
+
+    /// 实际使用的工作线程数
+    ///
+    /// 未设置时按CPU核心数除以每个会话的推理线程数计算，使线程总数与核心数相当，至少为1。
+    pub fn worker_count(&self) -> usize {
+        self.workers.unwrap_or_else(|| {
+            let cores = thread::available_parallelism().map_or(1, |n| n.get());
+            let per_session = self.model_options.as_ref().map_or(DEFAULT_INTRA_THREADS, ModelOptions::intra_threads);
+            (cores / per_session.max(1)).max(1)
+        })
+    }
+}
+
+/// 一次检测任务
+struct Job {
+    image: DynamicImage,
+    result: Sender<Result<Bounds, PerpleError>>,
+}
+
+/// [DetectorPool::submit]返回的结果句柄
+#[derive(Debug)]
+pub struct ResultHandle {
+    receiver: Receiver<Result<Bounds, PerpleError>>,
+}
+
+impl ResultHandle {
+    /// 阻塞等待检测结果
+    ///
+    /// # 错误处理
+    /// 检测失败时返回对应的错误，检测函数panic时返回[PerpleError::WorkerPanicked]；
+    /// 任务在执行前被取消（检测池以[ShutdownMode::Abort]关闭或已关闭）时返回[PerpleError::Cancelled]
+    pub fn wait(self) -> Result<Bounds, PerpleError> {
+        self.receiver.recv().unwrap_or(Err(PerpleError::Cancelled))
+    }
+
+    /// 不阻塞地查询结果，任务尚未完成时返回`None`，错误情况与[wait](Self::wait)相同
+    pub fn try_wait(&self) -> Option<Result<Bounds, PerpleError>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(PerpleError::Cancelled)),
+        }
+    }
+}
+
+/// 固定数量工作线程组成的检测池
+///
+/// 各工作线程从同一个队列中取任务，默认每个线程使用[ModelPool]中独立的检测器实例。
+/// 检测池被丢弃时按[PoolConfig::with_shutdown_mode]关闭并等待所有工作线程退出；
+/// 需要在关闭时指定方式可调用[shutdown](Self::shutdown)。
+pub struct DetectorPool {
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    aborted: Arc<AtomicBool>,
+    shutdown_mode: ShutdownMode,
+}
+
+impl DetectorPool {
+    /// 加载模型并启动工作线程
+    ///
+    /// # 参数
+    /// * `model_path` - ONNX模型文件路径
+    /// * `config` - 检测池配置，共用会话时只加载一个实例，否则每个工作线程加载一个
+    ///
+    /// # 错误处理
+    /// 与[ModelPool::new]相同，任一实例加载失败时不启动任何线程
+    pub fn new(model_path: &str, config: PoolConfig) -> Result<Self, PerpleError> {
+        let sessions = if config.shared_session { 1 } else { config.worker_count() };
+        let models = match &config.model_options {
+            Some(options) => ModelPool::with_model_options(model_path, sessions, config.input_width, config.input_height, options)?,
+            None => ModelPool::new(model_path, sessions, config.input_width, config.input_height)?,
+        };
+        Ok(Self::from_model_pool(models, config))
+    }
+
+    /// 使用已加载（并可能已用[ModelPool::configure]调整过阈值）的检测器实例启动工作线程
+    ///
+    /// 第`i`个工作线程使用第`i % 实例数`个实例；`config`中的共用会话、输入尺寸和会话配置不再生效。
+    pub fn from_model_pool(models: ModelPool, config: PoolConfig) -> Self {
+        let models = Arc::new(models);
+        Self::spawn(config, Arc::new(move |worker, image: &DynamicImage| models.detect_on(worker, image)))
+    }
+
+    /// 使用自定义检测函数启动工作线程，例如包装其他推理后端
+    ///
+    /// 所有工作线程共用`detect`，`config`中只有工作线程数和关闭方式生效。
+    pub fn from_fn<F>(config: PoolConfig, detect: F) -> Self
+    where
+        F: Fn(&DynamicImage) -> Result<Bounds, PerpleError> + Send + Sync + 'static,
+    {
+        Self::spawn(config, Arc::new(move |_, image: &DynamicImage| detect(image)))
+    }
+
+    fn spawn(config: PoolConfig, detect: DetectFn) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let aborted = Arc::new(AtomicBool::new(false));
+        let workers = (0..config.worker_count()).map(|worker| {
+            let receiver = Arc::clone(&receiver);
+            let aborted = Arc::clone(&aborted);
+            let detect = Arc::clone(&detect);
+            thread::spawn(move || loop {
+                // 只在取任务时持有队列的锁，持锁期间不会panic
+                let job = receiver.lock().unwrap_or_else(PoisonError::into_inner).recv();
+                let Ok(job) = job else {
+                    break;
+                };
+                // 已中止时丢弃任务，其结果句柄因发送端被释放而返回错误
+                if aborted.load(Ordering::Acquire) {
+                    continue;
+                }
+                let result = panic::catch_unwind(AssertUnwindSafe(|| detect(worker, &job.image)))
+                    .unwrap_or_else(|_| Err(PerpleError::WorkerPanicked(format!("检测池工作线程{}", worker))));
+                let _ = job.result.send(result);
+            })
+        }).collect();
+        Self { sender: Some(sender), workers, aborted, shutdown_mode: config.shutdown_mode }
+    }
+
+    /// 工作线程数
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// 提交一帧图像，立即返回结果句柄
+    ///
+    /// 检测池已关闭时句柄的[wait](ResultHandle::wait)立即返回取消错误。
+    pub fn submit(&self, image: DynamicImage) -> ResultHandle {
+        let (result, receiver) = mpsc::channel();
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(Job { image, result });
+        }
+        ResultHandle { receiver }
+    }
+
+    /// 并行检测一组图像并阻塞等待，结果与输入顺序一致
+    ///
+    /// 每幅图像会被复制一份提交给工作线程。
+    ///
+    /// # 错误处理
+    /// 任一图像检测失败时返回按输入顺序的第一个错误
+    pub fn map_images(&self, images: &[DynamicImage]) -> Result<Vec<Bounds>, PerpleError> {
+        let handles: Vec<ResultHandle> = images.iter().map(|image| self.submit(image.clone())).collect();
+        handles.into_iter().map(ResultHandle::wait).collect()
+    }
+
+    /// 按指定方式关闭检测池，返回时所有工作线程均已退出
+    ///
+    /// 返回后每个已提交任务的结果句柄要么已有结果，要么返回取消错误。
+    pub fn shutdown(mut self, mode: ShutdownMode) {
+        self.stop(mode);
+    }
+
+    fn stop(&mut self, mode: ShutdownMode) {
+        if mode == ShutdownMode::Abort {
+            self.aborted.store(true, Ordering::Release);
+        }
+        // 释放发送端后，工作线程取完队列中剩余的任务即退出
+        self.sender = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for DetectorPool {
+    fn drop(&mut self) {
+        self.stop(self.shutdown_mode);
+    }
+}
+
+impl std::fmt::Debug for DetectorPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DetectorPool")
+            .field("workers", &self.workers.len())
+            .field("running", &self.sender.is_some())
+            .field("shutdown_mode", &self.shutdown_mode)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::bounds::{BoundingBox, Detection};
+    use std::time::{Duration, Instant};
+
+    /// 以图像宽度作为检测框宽度的模拟检测结果，便于核对结果与输入的对应关系
+    fn width_marker(image: &DynamicImage) -> Bounds {
+        let width = image.width() as f32;
+        std::iter::once(Detection::new(BoundingBox::new(0.0, 0.0, width, 1.0), 0, "person", 0.9)).collect()
+    }
+
+    fn images(count: u32) -> Vec<DynamicImage> {
+        (1..=count).map(|width| DynamicImage::new_rgb8(width, 1)).collect()
+    }
+
+    fn sleeping_pool(workers: usize, sleep: Duration) -> DetectorPool {
+        DetectorPool::from_fn(PoolConfig::default().with_workers(workers), move |image| {
+            thread::sleep(sleep);
+            Ok(width_marker(image))
+        })
+    }
+
+    #[test]
+    fn map_images_preserves_input_order() {
+        // 越靠前的图像耗时越长，完成顺序与提交顺序相反
+        let pool = DetectorPool::from_fn(PoolConfig::default().with_workers(4), |image| {
+            thread::sleep(Duration::from_millis(5 * (9 - image.width() as u64)));
+            Ok(width_marker(image))
+        });
+        let widths: Vec<f32> = pool.map_images(&images(8)).unwrap()
+            .iter()
+            .map(|bounds| bounds.as_slice()[0].bbox.x2)
+            .collect();
+        assert_eq!(widths, (1..=8).map(|w| w as f32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn workers_run_in_parallel() {
+        let images = images(8);
+        let elapsed = |workers| {
+            let pool = sleeping_pool(workers, Duration::from_millis(30));
+            assert_eq!(pool.workers(), workers);
+            let start = Instant::now();
+            pool.map_images(&images).unwrap();
+            start.elapsed()
+        };
+        let serial = elapsed(1);
+        let parallel = elapsed(4);
+        assert!(serial >= Duration::from_millis(240));
+        assert!(parallel * 2 < serial, "serial {:?}, parallel {:?}", serial, parallel);
+    }
+
+    #[test]
+    fn drain_shutdown_finishes_queued_jobs() {
+        let pool = sleeping_pool(1, Duration::from_millis(5));
+        let handles: Vec<ResultHandle> = images(5).into_iter().map(|image| pool.submit(image)).collect();
+        pool.shutdown(ShutdownMode::Drain);
+        for handle in handles {
+            assert!(handle.try_wait().unwrap().is_ok());
+        }
+    }
+
+    #[test]
+    fn abort_shutdown_cancels_queued_jobs() {
+        let (started, on_start) = mpsc::channel();
+        let started = Mutex::new(started);
+        let pool = DetectorPool::from_fn(PoolConfig::default().with_workers(1), move |image| {
+            let _ = started.lock().unwrap().send(());
+            thread::sleep(Duration::from_millis(30));
+            Ok(width_marker(image))
+        });
+        let mut handles: Vec<ResultHandle> = images(4).into_iter().map(|image| pool.submit(image)).collect();
+        on_start.recv().unwrap();
+        pool.shutdown(ShutdownMode::Abort);
+
+        // 正在执行的任务完成，其余任务被取消
+        assert!(handles.remove(0).wait().is_ok());
+        for handle in handles {
+            assert!(matches!(handle.wait(), Err(PerpleError::Cancelled)));
+        }
+    }
+
+    #[test]
+    fn panicking_job_does_not_stop_worker() {
+        let pool = DetectorPool::from_fn(PoolConfig::default().with_workers(1), |image| {
+            if image.width() == 2 {
+                panic!("模拟检测失败");
+            }
+            Ok(width_marker(image))
+        });
+        let handles: Vec<ResultHandle> = images(3).into_iter().map(|image| pool.submit(image)).collect();
+        let results: Vec<Result<Bounds, PerpleError>> = handles.into_iter().map(ResultHandle::wait).collect();
+        assert!(results[0].is_ok());
+        assert!(matches!(&results[1], Err(PerpleError::WorkerPanicked(_))));
+        assert!(results[2].is_ok());
+    }
+}
@@ -0,0 +1,315 @@
+//! 图像预处理模块
+//!
+//! 在缩放到模型输入尺寸之前对图像进行自定义处理（裁剪、伽马校正、亮度/对比度等）。
+//! 改变几何形状的预处理（如裁剪）通过[Preprocessor::to_source]提供坐标映射，
+//! 检测结果会据此还原到原始图像坐标。
+
+use std::borrow::Cow;
+
+use image::DynamicImage;
+
+use crate::color::image::to_rgb_input;
+use crate::color::transform::Affine2;
+use crate::config::DEFAULT_ALPHA_BACKGROUND;
+
+/// 图像预处理步骤
+pub trait Preprocessor: Send + Sync {
+    /// 处理图像，不需要修改时返回`Cow::Borrowed`以避免拷贝
+    fn apply<'a>(&self, image: &'a DynamicImage) -> Cow<'a, DynamicImage>;
+
+    /// 处理后图像的尺寸，默认与输入相同
+    fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        (width, height)
+    }
+
+    /// 处理后图像坐标到输入图像坐标的变换，默认为恒等变换
+    ///
+    /// # 参数
+    /// * `width` - 输入图像宽度
+    /// * `height` - 输入图像高度
+    fn to_source(&self, _width: u32, _height: u32) -> Affine2 {
+        Affine2::identity()
+    }
+}
+
+/// 按顺序执行多个预处理步骤
+#[derive(Default)]
+pub struct ChainedPreprocessor {
+    steps: Vec<Box<dyn Preprocessor>>,
+}
+
+impl ChainedPreprocessor {
+    /// 创建空的预处理链
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在末尾追加一个步骤
+    pub fn then<P: Preprocessor + 'static>(mut self, step: P) -> Self {
+        self.steps.push(Box::new(step));
+        self
+    }
+}
+
+impl Preprocessor for ChainedPreprocessor {
+    fn apply<'a>(&self, image: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        self.steps.iter().fold(Cow::Borrowed(image), |current, step| {
+            match current {
+                Cow::Borrowed(image) => step.apply(image),
+                Cow::Owned(image) => Cow::Owned(step.apply(&image).into_owned()),
+            }
+        })
+    }
+
+    fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        self.steps.iter().fold((width, height), |(w, h), step| step.output_size(w, h))
+    }
+
+    fn to_source(&self, width: u32, height: u32) -> Affine2 {
+        // 从最后一步依次映射回第一步的输入
+        let mut size = (width, height);
+        let mut transform = Affine2::identity();
+        for step in &self.steps {
+            transform = step.to_source(size.0, size.1).then(&transform);
+            size = step.output_size(size.0, size.1);
+        }
+        transform
+    }
+}
+
+/// 裁剪出固定区域，超出图像的部分被截断
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Crop {
+    /// 创建裁剪步骤
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// 截断到图像范围内的裁剪区域`(x, y, 宽, 高)`，宽高至少为1像素
+    fn clamped(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let x = self.x.min(width.saturating_sub(1));
+        let y = self.y.min(height.saturating_sub(1));
+        let crop_width = self.width.min(width - x).max(1);
+        let crop_height = self.height.min(height - y).max(1);
+        (x, y, crop_width, crop_height)
+    }
+}
+
+impl Preprocessor for Crop {
+    fn apply<'a>(&self, image: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        let (x, y, width, height) = self.clamped(image.width(), image.height());
+        if (x, y, width, height) == (0, 0, image.width(), image.height()) {
+            return Cow::Borrowed(image);
+        }
+        Cow::Owned(image.crop_imm(x, y, width, height))
+    }
+
+    fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (_, _, crop_width, crop_height) = self.clamped(width, height);
+        (crop_width, crop_height)
+    }
+
+    fn to_source(&self, width: u32, height: u32) -> Affine2 {
+        let (x, y, _, _) = self.clamped(width, height);
+        Affine2::translate(x as f32, y as f32)
+    }
+}
+
+/// 伽马校正：`out = in^(1/gamma)`，`gamma`大于1时提亮暗部
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gamma {
+    pub gamma: f32,
+}
+
+impl Gamma {
+    /// 创建伽马校正步骤
+    pub fn new(gamma: f32) -> Self {
+        Self { gamma }
+    }
+}
+
+impl Preprocessor for Gamma {
+    fn apply<'a>(&self, image: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        let exponent = 1.0 / self.gamma;
+        Cow::Owned(map_channels(image, |v| (v / 255.0).powf(exponent) * 255.0))
+    }
+}
+
+/// 亮度/对比度调整：`out = (in - 0.5) * contrast + 0.5 + brightness`（按`[0, 1]`范围计算）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BrightnessContrast {
+    /// 亮度偏移，范围`[-1, 1]`，0为不变
+    pub brightness: f32,
+    /// 对比度倍数，1为不变
+    pub contrast: f32,
+}
+
+impl BrightnessContrast {
+    /// 创建亮度/对比度调整步骤
+    pub fn new(brightness: f32, contrast: f32) -> Self {
+        Self { brightness, contrast }
+    }
+}
+
+impl Preprocessor for BrightnessContrast {
+    fn apply<'a>(&self, image: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        let (brightness, contrast) = (self.brightness, self.contrast);
+        Cow::Owned(map_channels(image, |v| ((v / 255.0 - 0.5) * contrast + 0.5 + brightness) * 255.0))
+    }
+}
+
+/// 用查找表逐通道映射颜色，`f`的输入输出均为`[0, 255]`范围
+/// 
+/// 带透明通道的图像转换为RGBA8并保留透明度，由检测器按其背景色合成；其余图像转换为RGB8。
+fn map_channels(image: &DynamicImage, f: impl Fn(f32) -> f32) -> DynamicImage {
+    let table: Vec<u8> = (0..=255u8).map(|v| f(v as f32).round().clamp(0.0, 255.0) as u8).collect();
+    if image.color().has_alpha() {
+        let mut rgba = image.to_rgba8();
+        for pixel in rgba.pixels_mut() {
+            for value in &mut pixel.0[..3] {
+                *value = table[*value as usize];
+            }
+        }
+        return DynamicImage::ImageRgba8(rgba);
+    }
+    // 没有透明通道时背景色不起作用
+    let mut rgb = to_rgb_input(image, DEFAULT_ALPHA_BACKGROUND);
+    for value in rgb.iter_mut() {
+        *value = table[*value as usize];
+    }
+    DynamicImage::ImageRgb8(rgb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::bounds::BoundingBox;
+    use image::{Luma, Rgb, RgbImage, Rgba, RgbaImage};
+
+    /// 200x100的黑色图像，`(120, 40)`起有一个30x30的白色方块
+    fn block_image() -> DynamicImage {
+        let mut image = RgbImage::new(200, 100);
+        for y in 40..70 {
+            for x in 120..150 {
+                image.put_pixel(x, y, Rgb([255, 255, 255]));
+            }
+        }
+        DynamicImage::ImageRgb8(image)
+    }
+
+    /// 包围所有亮像素的框
+    fn bright_box(image: &DynamicImage) -> BoundingBox {
+        let gray = image.to_luma8();
+        let points: Vec<(f32, f32)> = gray.enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel.0[0] > 128)
+            .flat_map(|(x, y, _)| [(x as f32, y as f32), (x as f32 + 1.0, y as f32 + 1.0)])
+            .collect();
+        BoundingBox::from_polygon(&points).expect("图像中应有亮像素")
+    }
+
+    /// 与检测器相同的流程：预处理后检测，再按`to_source`还原到原始图像坐标
+    fn detect_through(preprocessor: &dyn Preprocessor, image: &DynamicImage) -> (BoundingBox, BoundingBox) {
+        let processed = preprocessor.apply(image);
+        assert_eq!(preprocessor.output_size(image.width(), image.height()), (processed.width(), processed.height()));
+        let local = bright_box(&processed);
+        (local, local.transform(&preprocessor.to_source(image.width(), image.height())))
+    }
+
+    #[test]
+    fn crop_maps_boxes_back_to_original_frame() {
+        let image = block_image();
+        let (local, restored) = detect_through(&Crop::new(100, 30, 80, 60), &image);
+        assert_eq!(local, BoundingBox::new(20.0, 10.0, 50.0, 40.0));
+        assert_eq!(restored, BoundingBox::new(120.0, 40.0, 150.0, 70.0));
+        assert_eq!(restored, bright_box(&image));
+    }
+
+    #[test]
+    fn crop_geometry_maps_model_input_back_to_original_frame() {
+        use crate::color::bounds::{Bounds, Detection, OutputSpace};
+        use crate::color::image::ScaleMessage;
+        use crate::color::transform::SpaceGeometry;
+
+        // 裁剪后的80x60图像缩放到640x640的模型输入
+        let crop = Crop::new(100, 30, 80, 60);
+        let message = ScaleMessage::builder().original_size(80, 60).scaled_size(640, 640).build();
+        let geometry = SpaceGeometry::from_message(&message).with_source_transform(&crop.to_source(200, 100), 200, 100);
+
+        let model_box = BoundingBox::new(160.0, 10.0 * 640.0 / 60.0, 400.0, 40.0 * 640.0 / 60.0);
+        let mut bounds: Bounds = [Detection::new(model_box, 0, "person", 0.9)].into_iter().collect();
+        bounds.set_space(OutputSpace::ModelInputPixels);
+        bounds.convert_space(OutputSpace::OriginalPixels, &geometry);
+        let restored = bounds.first().unwrap().bbox;
+        for (actual, expected) in [restored.x1, restored.y1, restored.x2, restored.y2].into_iter().zip([120.0, 40.0, 150.0, 70.0]) {
+            assert!((actual - expected).abs() < 1e-3, "{:?}", restored);
+        }
+        // 归一化坐标相对原始图像而非裁剪区域
+        bounds.convert_space(OutputSpace::Normalized, &geometry);
+        let normalized = bounds.first().unwrap().bbox;
+        assert!((normalized.x1 - 0.6).abs() < 1e-5 && (normalized.y2 - 0.7).abs() < 1e-5, "{:?}", normalized);
+    }
+
+    #[test]
+    fn crop_is_clamped_to_image() {
+        let image = block_image();
+        // 超出右下边界的部分被截断
+        let crop = Crop::new(110, 35, 500, 500);
+        assert_eq!(crop.output_size(200, 100), (90, 65));
+        assert_eq!(detect_through(&crop, &image).1, bright_box(&image));
+        // 起点在图像外时只剩最后一个像素
+        let outside = Crop::new(500, 500, 10, 10);
+        assert_eq!(outside.output_size(200, 100), (1, 1));
+        assert_eq!(outside.to_source(200, 100), Affine2::translate(199.0, 99.0));
+        // 覆盖整幅图像的裁剪不复制图像
+        assert!(matches!(Crop::new(0, 0, 200, 100).apply(&image), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn chained_crops_compose_offsets() {
+        let image = block_image();
+        let chain = ChainedPreprocessor::new()
+            .then(Crop::new(50, 20, 140, 70))
+            .then(Gamma::new(2.0))
+            .then(Crop::new(60, 10, 50, 50));
+        assert_eq!(chain.output_size(200, 100), (50, 50));
+        let (local, restored) = detect_through(&chain, &image);
+        assert_eq!(local, BoundingBox::new(10.0, 10.0, 40.0, 40.0));
+        assert_eq!(restored, bright_box(&image));
+        // 只调整颜色的链不改变坐标
+        let colors = ChainedPreprocessor::new().then(Gamma::new(2.0)).then(BrightnessContrast::new(0.1, 1.5));
+        assert_eq!(colors.to_source(200, 100), Affine2::identity());
+        assert!(matches!(ChainedPreprocessor::new().apply(&image), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn color_steps_follow_their_formulas() {
+        let image = DynamicImage::ImageLuma8(image::GrayImage::from_pixel(2, 2, Luma([64])));
+        let value = |preprocessor: &dyn Preprocessor| preprocessor.apply(&image).to_rgb8().get_pixel(1, 1).0;
+        // (64 / 255)^(1 / 2) * 255 ≈ 127.75
+        assert_eq!(value(&Gamma::new(2.0)), [128; 3]);
+        assert_eq!(value(&Gamma::new(1.0)), [64; 3]);
+        // ((64 / 255 - 0.5) * 2 + 0.5 + 0.1) * 255 ≈ 26.0
+        assert_eq!(value(&BrightnessContrast::new(0.1, 2.0)), [26; 3]);
+        assert_eq!(value(&BrightnessContrast::new(0.0, 1.0)), [64; 3]);
+        // 超出范围的结果被截断
+        assert_eq!(value(&BrightnessContrast::new(1.0, 1.0)), [255; 3]);
+        assert_eq!(value(&BrightnessContrast::new(-1.0, 1.0)), [0; 3]);
+    }
+
+    #[test]
+    fn color_adjustment_keeps_alpha_for_detector_composite() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([100, 100, 100, 0])));
+        let adjusted = BrightnessContrast::new(0.0, 1.0).apply(&image);
+        let DynamicImage::ImageRgba8(rgba) = adjusted.as_ref() else { panic!("应保留透明通道") };
+        assert_eq!(rgba.get_pixel(0, 0).0, [100, 100, 100, 0]);
+        // 透明区域最终由检测器的背景色决定
+        assert_eq!(to_rgb_input(&adjusted, [0, 0, 0]).get_pixel(0, 0).0, [0, 0, 0]);
+    }
+}
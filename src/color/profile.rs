@@ -0,0 +1,102 @@
+//! 推理性能分析模块
+//!
+//! 解析ONNX Runtime性能分析输出的JSON文件，汇总每个算子节点的耗时。
+//! 由[YoloDetector::run_profiled](crate::color::YoloDetector::run_profiled)生成。
+
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use crate::color::bounds::BoundsN;
+use crate::config::DETECTIONS_CAPACITY;
+use crate::error::PerpleError;
+
+/// 摘要中保留的最慢节点个数
+pub const PROFILE_SUMMARY_NODES: usize = 10;
+
+/// 单个算子节点的耗时
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeTiming {
+    /// 节点事件名称，例如`/model.0/conv/Conv_kernel_time`
+    pub name: String,
+    /// 耗时（微秒）
+    pub duration_us: u64,
+}
+
+/// 一次性能分析的结果，`N`为检测器的结果容量
+#[derive(Debug)]
+pub struct ProfileReport<const N: usize = DETECTIONS_CAPACITY> {
+    /// 性能分析JSON文件的路径
+    pub path: PathBuf,
+    /// 本次检测的结果
+    pub bounds: BoundsN<N>,
+    /// 耗时最长的节点，按耗时从高到低排列，至多[PROFILE_SUMMARY_NODES]个
+    pub slowest_nodes: Vec<NodeTiming>,
+}
+
+/// 从性能分析JSON中提取所有节点事件（`"cat": "Node"`）的耗时，按耗时从高到低排列
+///
+/// ONNX Runtime的输出是扁平的事件数组，也接受Chrome trace格式的`{"traceEvents": [...]}`；
+/// 缺少`name`或`dur`的事件会被跳过。
+///
+/// # 错误处理
+/// 内容不是合法的JSON时返回[PerpleError::Io]
+pub fn parse_node_timings(json: &str) -> Result<Vec<NodeTiming>, PerpleError> {
+    let value: Value = serde_json::from_str(json).map_err(std::io::Error::from)?;
+    let events = value.as_array()
+        .or_else(|| value.get("traceEvents")?.as_array())
+        .map_or(&[][..], Vec::as_slice);
+    let mut timings: Vec<NodeTiming> = events.iter()
+        .filter(|event| event.get("cat").and_then(Value::as_str) == Some("Node"))
+        .filter_map(|event| Some(NodeTiming {
+            name: event.get("name")?.as_str()?.to_string(),
+            duration_us: event.get("dur")?.as_u64()?,
+        }))
+        .collect();
+    timings.sort_by(|a, b| b.duration_us.cmp(&a.duration_us).then_with(|| a.name.cmp(&b.name)));
+    Ok(timings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ONNX Runtime性能分析输出的节选，包含会话事件和节点事件
+    const PROFILE: &str = r#"[
+{"cat" : "Session","pid" :4242,"tid" :4242,"dur" :3061,"ts" :5,"ph" : "X","name" :"model_loading_uri","args" : {}},
+{"cat" : "Node","pid" :4242,"tid" :4242,"dur" :120,"ts" :3100,"ph" : "X","name" :"/model.0/conv/Conv_kernel_time","args" : {"op_name" : "Conv","provider" : "CPUExecutionProvider"}},
+{"cat" : "Node","pid" :4242,"tid" :4242,"dur" :0,"ts" :3221,"ph" : "X","name" :"/model.0/act/Mul_fence_before","args" : {"op_name" : "Mul"}},
+{"cat" : "Node","pid" :4242,"tid" :4242,"dur" :845,"ts" :3222,"ph" : "X","name" :"/model.23/Concat_kernel_time","args" : {"op_name" : "Concat"}},
+{"cat" : "Node","pid" :4242,"tid" :4242,"ts" :4100,"ph" : "X","name" :"/model.23/Sigmoid_kernel_time","args" : {}},
+{"cat" : "Session","pid" :4242,"tid" :4242,"dur" :1500,"ts" :3090,"ph" : "X","name" :"SequentialExecutor::Execute","args" : {}}
+]
+"#;
+
+    #[test]
+    fn parses_node_events_sorted_by_duration() {
+        let timings = parse_node_timings(PROFILE).unwrap();
+        let summary: Vec<(&str, u64)> = timings.iter().map(|t| (t.name.as_str(), t.duration_us)).collect();
+        assert_eq!(summary, [
+            ("/model.23/Concat_kernel_time", 845),
+            ("/model.0/conv/Conv_kernel_time", 120),
+            ("/model.0/act/Mul_fence_before", 0),
+        ]);
+    }
+
+    #[test]
+    fn accepts_reordered_fields_and_trace_events_object() {
+        let json = r#"{"traceEvents": [
+            {"name": "b", "dur": 7, "cat": "Node"},
+            {"args": {"cat": "Node"}, "name": "session", "dur": 100, "cat": "Session"},
+            {"ph": "X", "cat": "Node", "name": "a", "dur": 7}
+        ]}"#;
+        let names: Vec<String> = parse_node_timings(json).unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, ["a", "b"]);
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        assert!(matches!(parse_node_timings(r#"[{"cat" : "Node", "dur" :"#), Err(PerpleError::Io(_))));
+        assert!(parse_node_timings("{}").unwrap().is_empty());
+    }
+}
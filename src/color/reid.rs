@@ -0,0 +1,333 @@
+//! 行人重识别(ReID)模块
+//! 
+//! 使用独立的ReID嵌入模型为每个检测结果提取外观特征向量，
+//! 用于在不同摄像头之间匹配同一个人。
+//! 
+//! 特征向量与检测结果按索引一一对应保存在单独的`Vec`中，
+//! 避免为每个[Detection]增加堆内存开销。
+
+use image::DynamicImage;
+use image::imageops::FilterType;
+use ort::inputs;
+use ort::session::Session;
+use ort::value::Tensor;
+
+use crate::color::bounds::Detection;
+use crate::color::image::crop_detections;
+use crate::color::model::load_model;
+use crate::error::PerpleError;
+
+/// ReID模型默认输入宽度
+pub const DEFAULT_REID_WIDTH: usize = 128;
+/// ReID模型默认输入高度
+pub const DEFAULT_REID_HEIGHT: usize = 256;
+
+/// ImageNet归一化均值（RGB）
+const REID_MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+/// ImageNet归一化标准差（RGB）
+const REID_STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+/// ReID特征提取器
+/// 
+/// 输入为NCHW格式的行人裁剪图，输出为形状[N, D]的特征矩阵。
+pub struct ReidExtractor {
+    /// ONNX模型会话
+    model: Session,
+    /// 模型输入宽度
+    input_width: usize,
+    /// 模型输入高度
+    input_height: usize,
+}
+
+impl ReidExtractor {
+    /// 加载ReID模型
+    /// 
+    /// 输入尺寸优先从模型输入形状读取，动态尺寸时使用默认的256x128。
+    /// 
+    /// # 参数
+    /// * `model_path` - ReID模型文件路径
+    pub fn new(model_path: &str) -> Result<Self, PerpleError> {
+        let model = load_model(model_path)?;
+        let shape: Vec<i64> = model.inputs.first()
+            .and_then(|input| input.input_type.tensor_shape())
+            .map(|shape| shape.to_vec())
+            .unwrap_or_default();
+        if shape.len() != 4 {
+            return Err(PerpleError::IncompatibleModel {
+                input_shape: shape,
+                output_shape: Vec::new(),
+                reason: "ReID模型输入应为4维张量[N, 3, H, W]".to_string(),
+            });
+        }
+        let dim = |value: i64, default: usize| if value > 0 { value as usize } else { default };
+        Ok(Self {
+            model,
+            input_width: dim(shape[3], DEFAULT_REID_WIDTH),
+            input_height: dim(shape[2], DEFAULT_REID_HEIGHT),
+        })
+    }
+    
+    /// 为每个检测结果提取L2归一化的特征向量
+    /// 
+    /// 所有检测框会被裁剪、缩放后组成一个批次一次性推理。
+    /// 
+    /// # 参数
+    /// * `image` - 产生检测结果的原始图像
+    /// * `detections` - 检测结果
+    /// 
+    /// # 返回值
+    /// 与`detections`按索引一一对应的特征向量
+    pub fn extract(&mut self, image: &DynamicImage, detections: &[Detection]) -> Result<Vec<Vec<f32>>, PerpleError> {
+        if detections.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let crops = crop_detections(image, detections);
+        let (width, height) = (self.input_width, self.input_height);
+        let plane = width * height;
+        let mut data = vec![0.0f32; crops.len() * 3 * plane];
+        
+        for (n, crop) in crops.iter().enumerate() {
+            let resized = crop.resize_exact(width as u32, height as u32, FilterType::Triangle).to_rgb8();
+            let base = n * 3 * plane;
+            for (y, row) in resized.rows().enumerate() {
+                for (x, pixel) in row.enumerate() {
+                    for c in 0..3 {
+                        let value = pixel.0[c] as f32 / 255.0;
+                        data[base + c * plane + y * width + x] = (value - REID_MEAN[c]) / REID_STD[c];
+                    }
+                }
+            }
+        }
+        
+        let input = Tensor::from_array(([crops.len(), 3, height, width], data))?;
+        let outputs = self.model.run(inputs![input])?;
+        let (shape, features) = outputs[0].try_extract_tensor::<f32>()?;
+        if shape.len() != 2 || shape[0] as usize != crops.len() {
+            return Err(PerpleError::IncompatibleModel {
+                input_shape: vec![crops.len() as i64, 3, height as i64, width as i64],
+                output_shape: shape.to_vec(),
+                reason: "ReID模型输出应为[N, D]特征矩阵".to_string(),
+            });
+        }
+        
+        split_features(features, crops.len(), shape[1])
+    }
+}
+
+/// 把[N, D]特征矩阵拆分为N个L2归一化的特征向量
+/// 
+/// 特征维度不是正数或数据长度与N×D不符时返回[PerpleError::ShapeMismatch]，
+/// 其中尺寸以`(N, D)`表示，维度为0时期望的维度记为1。
+fn split_features(features: &[f32], count: usize, dim: i64) -> Result<Vec<Vec<f32>>, PerpleError> {
+    let dim = dim.max(0) as usize;
+    if dim == 0 || features.len() != count * dim {
+        let rows = features.len().checked_div(dim).unwrap_or(0);
+        return Err(PerpleError::ShapeMismatch {
+            expected: (count as u32, dim.max(1) as u32),
+            actual: (rows as u32, dim as u32),
+        });
+    }
+    Ok(features.chunks_exact(dim).map(l2_normalize).collect())
+}
+
+/// 对特征向量做L2归一化，零向量保持不变
+pub fn l2_normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter().map(|v| v / norm).collect()
+    } else {
+        vector.to_vec()
+    }
+}
+
+/// 计算两个特征向量的余弦相似度
+/// 
+/// 长度不一致或任一向量为零向量时返回0.0
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|v| v * v).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// 特征库
+/// 
+/// 保存已知身份的特征向量，用于在新画面中查找最相似的身份。
+/// [assign_track_ids](Self::assign_track_ids)把匹配到的身份写入[Detection::track_id]，
+/// 可直接作为[TrajectoryRenderer](crate::color::TrajectoryRenderer)等按跟踪编号工作的模块的输入。
+#[derive(Debug, Clone, Default)]
+pub struct Gallery {
+    entries: Vec<(usize, Vec<f32>)>,
+}
+
+impl Gallery {
+    /// 创建一个空的特征库
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+    
+    /// 添加一个身份的特征向量，同一身份可以添加多个特征
+    pub fn insert(&mut self, identity: usize, embedding: Vec<f32>) {
+        self.entries.push((identity, embedding));
+    }
+    
+    /// 查找与给定特征最相似的身份
+    /// 
+    /// # 参数
+    /// * `embedding` - 待匹配的特征向量
+    /// * `threshold` - 最低余弦相似度
+    /// 
+    /// # 返回值
+    /// 返回相似度不低于`threshold`的最佳匹配身份及其相似度
+    pub fn match_embedding(&self, embedding: &[f32], threshold: f32) -> Option<(usize, f32)> {
+        self.entries.iter()
+            .map(|(identity, stored)| (*identity, cosine_similarity(embedding, stored)))
+            .filter(|(_, similarity)| *similarity >= threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+    
+    /// 按外观特征为一帧检测结果分配跟踪编号，并把本帧特征加入特征库
+    /// 
+    /// 按相似度从高到低贪心匹配，同一帧内每个身份最多分配给一个检测；
+    /// 相似度低于`threshold`的检测被视为新身份，编号为特征库中已有的最大编号加1。
+    /// 
+    /// # 参数
+    /// * `detections` - 一帧检测结果，其`track_id`会被覆盖
+    /// * `embeddings` - 与`detections`按索引一一对应的特征向量，例如[ReidExtractor::extract]的返回值
+    /// * `threshold` - 视为同一身份的最低余弦相似度
+    /// 
+    /// # 错误处理
+    /// `embeddings`与`detections`数量不一致时返回Err，此时不修改检测结果和特征库
+    pub fn assign_track_ids(&mut self, detections: &mut [Detection], embeddings: &[Vec<f32>], threshold: f32) -> Result<(), PerpleError> {
+        if detections.len() != embeddings.len() {
+            return Err(PerpleError::InvalidParameter(format!(
+                "特征数量{}与检测数量{}不一致", embeddings.len(), detections.len()
+            )));
+        }
+        
+        let mut candidates: Vec<(usize, usize, f32)> = embeddings.iter()
+            .enumerate()
+            .flat_map(|(index, embedding)| {
+                self.entries.iter().map(move |(identity, stored)| (index, *identity, cosine_similarity(embedding, stored)))
+            })
+            .filter(|(_, _, similarity)| *similarity >= threshold)
+            .collect();
+        candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+        
+        let mut assigned = vec![None; detections.len()];
+        let mut used = Vec::new();
+        for (index, identity, _) in candidates {
+            if assigned[index].is_none() && !used.contains(&identity) {
+                assigned[index] = Some(identity);
+                used.push(identity);
+            }
+        }
+        
+        let mut next_identity = self.entries.iter().map(|(identity, _)| identity + 1).max().unwrap_or(0);
+        for ((detection, embedding), identity) in detections.iter_mut().zip(embeddings).zip(assigned) {
+            let identity = identity.unwrap_or_else(|| {
+                next_identity += 1;
+                next_identity - 1
+            });
+            detection.track_id = Some(identity);
+            self.insert(identity, embedding.clone());
+        }
+        Ok(())
+    }
+    
+    /// 特征库中的特征数量
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    
+    /// 特征库是否为空
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    
+    /// 清空特征库
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::bounds::BoundingBox;
+
+    fn detections(count: usize) -> Vec<Detection> {
+        (0..count)
+            .map(|i| Detection::new(BoundingBox::new(i as f32 * 10.0, 0.0, i as f32 * 10.0 + 5.0, 5.0), 0, "person", 0.9))
+            .collect()
+    }
+
+    #[test]
+    fn split_features_normalizes_each_row() {
+        let features = split_features(&[3.0, 4.0, 0.0, 0.0, 0.0, 2.0], 2, 3).unwrap();
+        assert_eq!(features, vec![vec![0.6, 0.8, 0.0], vec![0.0, 0.0, 1.0]]);
+    }
+
+    #[test]
+    fn split_features_rejects_bad_dimensions() {
+        assert!(matches!(
+            split_features(&[], 2, 0),
+            Err(PerpleError::ShapeMismatch { expected: (2, 1), actual: (0, 0) })
+        ));
+        assert!(matches!(split_features(&[], 2, -1), Err(PerpleError::ShapeMismatch { .. })));
+        assert!(matches!(
+            split_features(&[1.0; 5], 2, 3),
+            Err(PerpleError::ShapeMismatch { expected: (2, 3), actual: (1, 3) })
+        ));
+    }
+
+    #[test]
+    fn cosine_similarity_handles_degenerate_vectors() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(l2_normalize(&[0.0, 0.0]), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn gallery_matches_best_identity_above_threshold() {
+        let mut gallery = Gallery::new();
+        gallery.insert(7, vec![1.0, 0.0]);
+        gallery.insert(9, vec![0.6, 0.8]);
+        assert_eq!(gallery.match_embedding(&[0.8, 0.6], 0.5).map(|(id, _)| id), Some(9));
+        assert_eq!(gallery.match_embedding(&[0.0, -1.0], 0.5), None);
+    }
+
+    #[test]
+    fn assign_track_ids_reuses_and_creates_identities() {
+        let mut gallery = Gallery::new();
+        let mut first = detections(2);
+        gallery.assign_track_ids(&mut first, &[vec![1.0, 0.0], vec![0.0, 1.0]], 0.9).unwrap();
+        assert_eq!(first.iter().map(|d| d.track_id).collect::<Vec<_>>(), vec![Some(0), Some(1)]);
+
+        // 两个检测都与身份1最相似时只有更相似的那个得到该身份，另一个成为新身份
+        let mut second = detections(3);
+        let embeddings = [vec![0.0, 1.0], vec![0.1, 0.99], vec![0.99, 0.1]];
+        gallery.assign_track_ids(&mut second, &embeddings, 0.9).unwrap();
+        assert_eq!(second.iter().map(|d| d.track_id).collect::<Vec<_>>(), vec![Some(1), Some(2), Some(0)]);
+        assert_eq!(gallery.len(), 5);
+    }
+
+    #[test]
+    fn assign_track_ids_rejects_mismatched_lengths() {
+        let mut gallery = Gallery::new();
+        let mut detections = detections(2);
+        assert!(gallery.assign_track_ids(&mut detections, &[vec![1.0]], 0.5).is_err());
+        assert!(detections.iter().all(|d| d.track_id.is_none()));
+        assert!(gallery.is_empty());
+    }
+}
@@ -0,0 +1,414 @@
+//! 结果输出模块
+//!
+//! 将每帧检测结果交给可插拔的输出端（文件、数据库、消息队列等）。
+//! 每个输出端在独立线程中运行并通过有界队列接收结果，写出过程不会阻塞推理。
+
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::color::bounds::BoundsN;
+use crate::config::DETECTIONS_CAPACITY;
+use crate::error::PerpleError;
+
+#[cfg(feature = "serde")]
+use std::{collections::BTreeMap, fs::File, io::{BufWriter, Write}, path::Path};
+#[cfg(feature = "serde")]
+use crate::color::bounds::OutputSpace;
+
+/// 输出线程待处理结果队列的默认容量
+pub const DEFAULT_SINK_QUEUE_CAPACITY: usize = 16;
+
+/// 一帧检测结果的元数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameMeta {
+    /// 帧序号，与检测日志中的`frame_id`一致
+    pub frame_id: u64,
+    /// 结果产生的时间
+    pub timestamp: SystemTime,
+}
+
+impl FrameMeta {
+    /// 以当前时间创建元数据
+    pub fn now(frame_id: u64) -> Self {
+        Self { frame_id, timestamp: SystemTime::now() }
+    }
+    
+    /// Unix毫秒时间戳
+    pub fn timestamp_millis(&self) -> u128 {
+        self.timestamp.duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0)
+    }
+}
+
+/// 检测结果输出端
+/// 
+/// 由[SinkRunner]在独立线程中按结果产生的顺序调用。常量参数`N`为每帧检测结果的容量，见[BoundsN]。
+pub trait ResultSink<const N: usize = DETECTIONS_CAPACITY>: Send {
+    /// 处理一帧检测结果，返回的错误会转发给[SinkRunner::try_recv_error]，之后的结果照常送达
+    fn on_result(&mut self, frame: &FrameMeta, bounds: &BoundsN<N>) -> Result<(), PerpleError>;
+    
+    /// 将缓冲的数据写出，默认不做任何处理
+    fn flush(&mut self) -> Result<(), PerpleError> {
+        Ok(())
+    }
+    
+    /// 输出线程退出前调用一次，默认调用[flush](Self::flush)
+    fn close(&mut self) -> Result<(), PerpleError> {
+        self.flush()
+    }
+}
+
+/// 输出线程的队列已满时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SinkPolicy {
+    /// 丢弃新结果，不阻塞提交方
+    #[default]
+    DropNewest,
+    /// 阻塞提交方直到队列有空位，保证每帧结果都送达
+    /// 
+    /// 由[Perple](crate::Perple)提交时会阻塞检测线程，输出端的写出速度将限制检测帧率。
+    Block,
+}
+
+/// 在独立线程中运行一个[ResultSink]
+/// 
+/// 通过[submit](Self::submit)提交结果。被drop时关闭队列，等待线程处理完剩余结果并调用
+/// [ResultSink::close]后返回。
+pub struct SinkRunner<const N: usize = DETECTIONS_CAPACITY> {
+    sender: Option<SyncSender<(FrameMeta, BoundsN<N>)>>,
+    errors: Receiver<PerpleError>,
+    handle: Option<JoinHandle<()>>,
+    policy: SinkPolicy,
+    dropped: u64,
+}
+
+impl<const N: usize> SinkRunner<N> {
+    /// 使用默认队列容量和[SinkPolicy::DropNewest]启动输出线程
+    pub fn spawn(sink: Box<dyn ResultSink<N>>) -> Self {
+        Self::spawn_with(sink, DEFAULT_SINK_QUEUE_CAPACITY, SinkPolicy::default())
+    }
+    
+    /// 启动输出线程
+    /// 
+    /// # 参数
+    /// * `sink` - 输出端
+    /// * `capacity` - 待处理结果队列的容量
+    /// * `policy` - 队列已满时的处理方式
+    pub fn spawn_with(mut sink: Box<dyn ResultSink<N>>, capacity: usize, policy: SinkPolicy) -> Self {
+        let (sender, results) = mpsc::sync_channel::<(FrameMeta, BoundsN<N>)>(capacity);
+        let (error_sender, errors) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            for (frame, bounds) in results {
+                if let Err(e) = sink.on_result(&frame, &bounds) {
+                    log::warn!("输出检测结果失败: frame_id={} error={}", frame.frame_id, e);
+                    let _ = error_sender.send(e);
+                }
+            }
+            if let Err(e) = sink.close() {
+                log::warn!("关闭结果输出端失败: error={}", e);
+                let _ = error_sender.send(e);
+            }
+        });
+        
+        Self {
+            sender: Some(sender),
+            errors,
+            handle: Some(handle),
+            policy,
+            dropped: 0,
+        }
+    }
+    
+    /// 提交一帧结果
+    /// 
+    /// # 返回值
+    /// 结果因队列已满被丢弃或输出线程已退出时返回`false`
+    pub fn submit(&mut self, frame: FrameMeta, bounds: BoundsN<N>) -> bool {
+        let Some(sender) = &self.sender else {
+            return false;
+        };
+        let sent = match self.policy {
+            SinkPolicy::DropNewest => match sender.try_send((frame, bounds)) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => false,
+            },
+            SinkPolicy::Block => sender.send((frame, bounds)).is_ok(),
+        };
+        if !sent {
+            self.dropped += 1;
+        }
+        sent
+    }
+    
+    /// 未能送达的结果数
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+    
+    /// 取出一个输出错误，没有错误时返回`None`
+    pub fn try_recv_error(&self) -> Option<PerpleError> {
+        self.errors.try_recv().ok()
+    }
+}
+
+impl<const N: usize> Drop for SinkRunner<N> {
+    fn drop(&mut self) {
+        // 关闭发送端后线程处理完剩余结果、调用close后退出
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// 将每帧结果写为一行JSON（JSON Lines格式），需要启用`serde`特性
+/// 
+/// 每行形如：
+/// ```text
+/// {"frame_id":1,"timestamp_ms":1700000000000,"degraded":false,"space":"original_pixels","detections":[{"class_id":0,"class_name":"person","confidence":0.91,"bbox":[10.0,20.0,110.0,220.0]}]}
+/// ```
+/// 带有[附加属性](crate::color::Detection::attributes)的检测结果额外写出`"attributes"`对象（按键排序）。
+/// 非有限的数值写为`null`。
+#[cfg(feature = "serde")]
+pub struct JsonlSink {
+    writer: BufWriter<File>,
+}
+
+#[cfg(feature = "serde")]
+impl JsonlSink {
+    /// 创建（或截断）输出文件
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, PerpleError> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+    
+    /// 将缓冲的结果写入文件
+    pub fn flush(&mut self) -> Result<(), PerpleError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// [JsonlSink]写出的一行
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonlLine<'a> {
+    frame_id: u64,
+    timestamp_ms: u128,
+    degraded: bool,
+    space: OutputSpace,
+    detections: Vec<JsonlDetection<'a>>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonlDetection<'a> {
+    class_id: usize,
+    class_name: &'a str,
+    confidence: f32,
+    bbox: [f32; 4],
+    /// 按键排序，相同的结果总是写出相同的行
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes: Option<BTreeMap<&'a str, &'a str>>,
+}
+
+#[cfg(feature = "serde")]
+impl<const N: usize> ResultSink<N> for JsonlSink {
+    fn on_result(&mut self, frame: &FrameMeta, bounds: &BoundsN<N>) -> Result<(), PerpleError> {
+        let line = JsonlLine {
+            frame_id: frame.frame_id,
+            timestamp_ms: frame.timestamp_millis(),
+            degraded: bounds.is_degraded(),
+            space: bounds.space(),
+            detections: bounds.iter()
+                .map(|detection| JsonlDetection {
+                    class_id: detection.class_id,
+                    class_name: &detection.class_name,
+                    confidence: detection.confidence,
+                    bbox: [detection.bbox.x1, detection.bbox.y1, detection.bbox.x2, detection.bbox.y2],
+                    attributes: detection.attributes.as_ref()
+                        .map(|attributes| attributes.iter().map(|(key, value)| (key.as_str(), value.as_str())).collect()),
+                })
+                .collect(),
+        };
+        serde_json::to_writer(&mut self.writer, &line).map_err(std::io::Error::from)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+    
+    fn flush(&mut self) -> Result<(), PerpleError> {
+        JsonlSink::flush(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::bounds::Bounds;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// 记录收到的帧序号，可选地在每帧处理前等待放行
+    type Shared<T> = Arc<Mutex<T>>;
+
+    struct RecordingSink {
+        frames: Arc<Mutex<Vec<u64>>>,
+        closed: Arc<Mutex<bool>>,
+        /// 每收到一帧发出一个信号
+        started: Option<mpsc::Sender<u64>>,
+        /// 每帧处理前等待一个放行信号，发送端被丢弃后不再等待
+        gate: Option<mpsc::Receiver<()>>,
+    }
+
+    impl RecordingSink {
+        fn new() -> (Self, Shared<Vec<u64>>, Shared<bool>) {
+            let frames = Arc::new(Mutex::new(Vec::new()));
+            let closed = Arc::new(Mutex::new(false));
+            let sink = Self { frames: Arc::clone(&frames), closed: Arc::clone(&closed), started: None, gate: None };
+            (sink, frames, closed)
+        }
+
+        /// 在处理每帧前阻塞，直到放行发送端发出信号或被丢弃
+        fn gated(mut self) -> (Self, mpsc::Receiver<u64>, mpsc::Sender<()>) {
+            let (started, started_receiver) = mpsc::channel();
+            let (release, gate) = mpsc::channel();
+            self.started = Some(started);
+            self.gate = Some(gate);
+            (self, started_receiver, release)
+        }
+    }
+
+    impl ResultSink for RecordingSink {
+        fn on_result(&mut self, frame: &FrameMeta, _bounds: &Bounds) -> Result<(), PerpleError> {
+            if let Some(started) = &self.started {
+                let _ = started.send(frame.frame_id);
+            }
+            if let Some(gate) = &self.gate {
+                let _ = gate.recv();
+            }
+            self.frames.lock().unwrap().push(frame.frame_id);
+            Ok(())
+        }
+
+        fn close(&mut self) -> Result<(), PerpleError> {
+            *self.closed.lock().unwrap() = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn delivers_results_in_submission_order() {
+        let (sink, frames, closed) = RecordingSink::new();
+        let mut runner = SinkRunner::spawn_with(Box::new(sink), 4, SinkPolicy::Block);
+        for frame_id in 1..=20 {
+            assert!(runner.submit(FrameMeta::now(frame_id), Bounds::new()));
+        }
+        drop(runner);
+        assert_eq!(*frames.lock().unwrap(), (1..=20).collect::<Vec<_>>());
+        assert!(*closed.lock().unwrap());
+    }
+
+    #[test]
+    fn drop_newest_discards_when_queue_full() {
+        let (sink, frames, _) = RecordingSink::new();
+        let (sink, started, release) = sink.gated();
+        let mut runner = SinkRunner::spawn_with(Box::new(sink), 1, SinkPolicy::DropNewest);
+        assert!(runner.submit(FrameMeta::now(1), Bounds::new()));
+        // 第1帧已被输出线程取走并阻塞在处理中，队列只能再容纳1帧
+        assert_eq!(started.recv().unwrap(), 1);
+        assert!(runner.submit(FrameMeta::now(2), Bounds::new()));
+        assert!(!runner.submit(FrameMeta::now(3), Bounds::new()));
+        assert_eq!(runner.dropped(), 1);
+        drop(release);
+        drop(runner);
+        assert_eq!(*frames.lock().unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn block_policy_waits_for_queue_space() {
+        let (sink, frames, _) = RecordingSink::new();
+        let (sink, started, release) = sink.gated();
+        let mut runner = SinkRunner::spawn_with(Box::new(sink), 1, SinkPolicy::Block);
+        assert!(runner.submit(FrameMeta::now(1), Bounds::new()));
+        assert_eq!(started.recv().unwrap(), 1);
+        assert!(runner.submit(FrameMeta::now(2), Bounds::new()));
+        
+        let (done_sender, done) = mpsc::channel();
+        let submitter = thread::spawn(move || {
+            let sent = runner.submit(FrameMeta::now(3), Bounds::new());
+            done_sender.send(()).unwrap();
+            (runner, sent)
+        });
+        // 队列已满，第3帧的提交应一直阻塞到输出线程腾出空位
+        assert!(done.recv_timeout(Duration::from_millis(100)).is_err());
+        drop(release);
+        let (runner, sent) = submitter.join().unwrap();
+        assert!(sent);
+        assert_eq!(runner.dropped(), 0);
+        drop(runner);
+        assert_eq!(*frames.lock().unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn forwards_sink_errors_and_keeps_delivering() {
+        struct FailingSink;
+        impl ResultSink for FailingSink {
+            fn on_result(&mut self, frame: &FrameMeta, _bounds: &Bounds) -> Result<(), PerpleError> {
+                if frame.frame_id == 2 {
+                    return Err(PerpleError::Config("写入失败".to_string()));
+                }
+                Ok(())
+            }
+        }
+        let mut runner = SinkRunner::spawn_with(Box::new(FailingSink), 4, SinkPolicy::Block);
+        for frame_id in 1..=3 {
+            runner.submit(FrameMeta::now(frame_id), Bounds::new());
+        }
+        // 关闭发送端并等待输出线程退出后，错误已全部转发
+        runner.sender.take();
+        runner.handle.take().unwrap().join().unwrap();
+        assert!(matches!(runner.try_recv_error(), Some(PerpleError::Config(_))));
+        assert!(runner.try_recv_error().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn jsonl_attributes_round_trip() {
+        use crate::color::bounds::{BoundingBox, Detection};
+        use std::collections::HashMap;
+
+        let mut bounds = Bounds::new();
+        bounds.push(Detection::new(BoundingBox::new(10.0, 20.0, 110.0, 220.0), 0, "person", 0.5)
+            .with_attribute("gender", "female")
+            .with_attribute("age", "30-40")
+            .with_attribute("note", "say \"hi\"\n"));
+        bounds.push(Detection::new(BoundingBox::new(0.0, 0.0, 5.0, 5.0), 2, "car", 0.25));
+        bounds.push(Detection::new(BoundingBox::new(0.0, 0.0, f32::INFINITY, 5.0), 2, "car\t\u{1}", f32::NAN));
+
+        let path = std::env::temp_dir().join(format!("perple_sink_{}_attributes.jsonl", std::process::id()));
+        let mut sink = JsonlSink::create(&path).unwrap();
+        sink.on_result(&FrameMeta::now(7), &bounds).unwrap();
+        sink.flush().unwrap();
+        drop(sink);
+        let line = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(value["frame_id"], 7);
+        let detections = value["detections"].as_array().unwrap();
+        // 附加属性写为扁平的JSON对象，解析后与原属性一致
+        let attributes: HashMap<String, String> = detections[0]["attributes"].as_object().unwrap()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.as_str().unwrap().to_string()))
+            .collect();
+        assert_eq!(Some(&attributes), bounds.iter().next().unwrap().attributes.as_ref());
+        assert_eq!(attributes.get("note").map(String::as_str), Some("say \"hi\"\n"));
+        // 没有附加属性时不写出该字段
+        assert!(detections[1].get("attributes").is_none());
+        // 控制字符被转义，非有限的数值写为null
+        assert_eq!(detections[2]["class_name"], "car\t\u{1}");
+        assert!(detections[2]["confidence"].is_null() && detections[2]["bbox"][2].is_null());
+        assert_eq!(detections[2]["bbox"][3], 5.0);
+    }
+}
@@ -0,0 +1,326 @@
+//! 检测快照模块
+//!
+//! 在画面中出现目标时自动保存绘制了检测框的JPEG快照。
+//! 编码和写盘在独立线程中进行，不会阻塞推理。
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use image::DynamicImage;
+use image::codecs::jpeg::JpegEncoder;
+
+use crate::color::bounds::{BoundsN, Detection};
+use crate::color::sink::FrameMeta;
+use crate::color::utils::draw_detections;
+use crate::config::PERSON_CLASS_LABEL;
+use crate::error::PerpleError;
+
+/// 快照线程待处理帧队列的容量，队列满时新快照被丢弃
+const SNAPSHOT_QUEUE_CAPACITY: usize = 4;
+
+/// 快照触发策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotTrigger {
+    /// 仅在画面中从没有目标类别变为有目标类别时保存
+    OnEnter,
+    /// 目标出现时保存，此后只要目标仍在画面中，每隔指定时间再保存一次
+    OnEnterAndEvery(Duration),
+}
+
+/// 快照配置
+#[derive(Debug, Clone)]
+pub struct SnapshotConfig {
+    output_dir: PathBuf,
+    class_name: String,
+    filename_template: String,
+    jpeg_quality: u8,
+    cooldown: Duration,
+    trigger: SnapshotTrigger,
+}
+
+impl SnapshotConfig {
+    /// 创建默认配置：目标类别`person`，文件名`{timestamp}_{frame_id}.jpg`，JPEG质量90，冷却5秒，仅在有人进入时触发
+    pub fn new(output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            output_dir: output_dir.into(),
+            class_name: PERSON_CLASS_LABEL.to_string(),
+            filename_template: "{timestamp}_{frame_id}.jpg".to_string(),
+            jpeg_quality: 90,
+            cooldown: Duration::from_secs(5),
+            trigger: SnapshotTrigger::OnEnter,
+        }
+    }
+
+    /// 设置触发快照的类别名，其他类别的检测结果不会触发快照
+    pub fn with_class_name(mut self, class_name: impl Into<String>) -> Self {
+        self.class_name = class_name.into();
+        self
+    }
+
+    /// 设置文件名模板，支持`{timestamp}`（Unix毫秒时间戳）和`{frame_id}`占位符
+    pub fn with_filename_template(mut self, template: &str) -> Self {
+        self.filename_template = template.to_string();
+        self
+    }
+
+    /// 设置JPEG质量（1-100）
+    pub fn with_jpeg_quality(mut self, quality: u8) -> Self {
+        self.jpeg_quality = quality.clamp(1, 100);
+        self
+    }
+
+    /// 设置两次保存之间的最短间隔
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// 设置触发策略
+    pub fn with_trigger(mut self, trigger: SnapshotTrigger) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
+    /// 根据模板生成文件路径
+    fn file_path(&self, frame: &FrameMeta) -> PathBuf {
+        let name = self.filename_template
+            .replace("{timestamp}", &frame.timestamp_millis().to_string())
+            .replace("{frame_id}", &frame.frame_id.to_string());
+        self.output_dir.join(name)
+    }
+}
+
+/// 快照保存器
+///
+/// 通过[observe](Self::observe)输入每帧图像及其检测结果，按触发策略和冷却时间判断是否保存，
+/// 只有需要保存的帧才会被复制并交给后台线程绘制、编码和写盘。
+/// 添加到[Perple](crate::Perple::add_snapshot_sink)后由检测循环自动输入每帧结果。
+/// 写盘失败（目录不可写、磁盘已满等）的错误可通过[try_recv_error](Self::try_recv_error)获取。
+pub struct SnapshotSink {
+    class_name: String,
+    cooldown: Duration,
+    trigger: SnapshotTrigger,
+    /// 上一帧中是否有目标类别
+    occupied: bool,
+    /// 上次提交快照的帧时间
+    last_saved: Option<SystemTime>,
+    sender: Option<SyncSender<(FrameMeta, DynamicImage, Vec<Detection>)>>,
+    errors: Receiver<PerpleError>,
+    handle: Option<JoinHandle<()>>,
+    dropped: u64,
+}
+
+impl SnapshotSink {
+    /// 创建输出目录并启动快照线程
+    pub fn spawn(config: SnapshotConfig) -> Result<Self, PerpleError> {
+        std::fs::create_dir_all(&config.output_dir)?;
+
+        let (sender, frames) = mpsc::sync_channel::<(FrameMeta, DynamicImage, Vec<Detection>)>(SNAPSHOT_QUEUE_CAPACITY);
+        let (error_sender, errors) = mpsc::channel();
+        let (class_name, cooldown, trigger) = (config.class_name.clone(), config.cooldown, config.trigger);
+        let handle = thread::spawn(move || {
+            for (frame, image, detections) in frames {
+                if let Err(e) = save_snapshot(&config, &frame, &image, &detections) {
+                    log::warn!("保存快照失败: frame_id={} error={}", frame.frame_id, e);
+                    let _ = error_sender.send(e);
+                }
+            }
+        });
+
+        Ok(Self {
+            class_name,
+            cooldown,
+            trigger,
+            occupied: false,
+            last_saved: None,
+            sender: Some(sender),
+            errors,
+            handle: Some(handle),
+            dropped: 0,
+        })
+    }
+
+    /// 输入一帧图像及其检测结果，需要保存时复制图像并提交给快照线程，不会阻塞
+    ///
+    /// 只有配置的类别计入是否有目标；冷却时间和保存间隔按[FrameMeta::timestamp]计算。
+    ///
+    /// # 返回值
+    /// 本帧被提交保存时返回`true`；不需要保存或快照线程队列已满时返回`false`
+    pub fn observe<const N: usize>(&mut self, frame: &FrameMeta, image: &DynamicImage, bounds: &BoundsN<N>) -> bool {
+        if !self.is_due(frame, bounds) {
+            return false;
+        }
+        let Some(sender) = &self.sender else {
+            return false;
+        };
+        match sender.try_send((*frame, image.clone(), bounds.as_slice().to_vec())) {
+            Ok(()) => {
+                self.last_saved = Some(frame.timestamp);
+                true
+            }
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                self.dropped += 1;
+                false
+            }
+        }
+    }
+
+    /// 按触发策略和冷却时间判断本帧是否需要保存，同时更新是否有目标的状态
+    fn is_due<const N: usize>(&mut self, frame: &FrameMeta, bounds: &BoundsN<N>) -> bool {
+        let was_occupied = self.occupied;
+        self.occupied = bounds.iter().any(|d| d.class_name == self.class_name.as_str());
+        if !self.occupied {
+            return false;
+        }
+
+        // 帧时间早于上次保存（系统时钟回拨）时按刚刚保存处理
+        let since_last = self.last_saved.map(|t| frame.timestamp.duration_since(t).unwrap_or_default());
+        let cooled_down = since_last.is_none_or(|elapsed| elapsed >= self.cooldown);
+        let due = match self.trigger {
+            SnapshotTrigger::OnEnter => !was_occupied,
+            SnapshotTrigger::OnEnterAndEvery(interval) => {
+                !was_occupied || since_last.is_none_or(|elapsed| elapsed >= interval)
+            }
+        };
+        due && cooled_down
+    }
+
+    /// 因快照线程队列已满而未能保存的快照数
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// 取出一个写盘错误，没有错误时返回`None`
+    pub fn try_recv_error(&self) -> Option<PerpleError> {
+        self.errors.try_recv().ok()
+    }
+}
+
+impl Drop for SnapshotSink {
+    fn drop(&mut self) {
+        // 关闭发送端后线程处理完剩余帧即退出
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// 绘制检测框并编码为JPEG写入文件
+fn save_snapshot(config: &SnapshotConfig, frame: &FrameMeta, image: &DynamicImage, detections: &[Detection]) -> Result<(), PerpleError> {
+    let annotated = draw_detections(image, detections).to_rgb8();
+    let mut writer = BufWriter::new(File::create(config.file_path(frame))?);
+    JpegEncoder::new_with_quality(&mut writer, config.jpeg_quality)
+        .encode_image(&annotated)
+        .map_err(std::io::Error::other)?;
+    // 显式flush，避免磁盘已满等错误在drop时被忽略
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::bounds::{BoundingBox, Bounds};
+    use image::GenericImageView;
+
+    /// 本测试独占的空输出目录
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("perple_snapshot_{}_{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// 第`second`秒的第`frame_id`帧
+    fn frame_at(frame_id: u64, second: u64) -> FrameMeta {
+        FrameMeta { frame_id, timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + second) }
+    }
+
+    /// 包含给定类别各一个目标的检测结果
+    fn detections(classes: &[&str]) -> Bounds {
+        classes.iter()
+            .enumerate()
+            .map(|(i, class)| Detection::new(BoundingBox::new(i as f32 * 20.0, 4.0, i as f32 * 20.0 + 12.0, 20.0), i, class.to_string(), 0.9))
+            .collect()
+    }
+
+    /// 依次输入`(秒, 类别)`脚本，等待快照线程写完后返回已保存的帧序号
+    fn run_script(config: SnapshotConfig, script: &[(u64, &[&str])]) -> (Vec<u64>, Vec<bool>) {
+        let dir = config.output_dir.clone();
+        let image = DynamicImage::new_rgb8(64, 48);
+        let mut sink = SnapshotSink::spawn(config.with_filename_template("{frame_id}.jpg")).unwrap();
+        let submitted = script.iter()
+            .enumerate()
+            .map(|(i, (second, classes))| sink.observe(&frame_at(i as u64 + 1, *second), &image, &detections(classes)))
+            .collect();
+        drop(sink);
+
+        let mut saved: Vec<u64> = std::fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .map(|path| {
+                assert_eq!(image::open(&path).unwrap().dimensions(), (64, 48));
+                path.file_stem().unwrap().to_str().unwrap().parse().unwrap()
+            })
+            .collect();
+        saved.sort_unstable();
+        std::fs::remove_dir_all(&dir).unwrap();
+        (saved, submitted)
+    }
+
+    #[test]
+    fn only_configured_class_triggers_snapshot() {
+        let config = SnapshotConfig::new(temp_dir("class")).with_cooldown(Duration::ZERO);
+        let script: [(u64, &[&str]); 5] = [(0, &["car"]), (1, &["car", "dog"]), (2, &["car", PERSON_CLASS_LABEL]), (3, &["car"]), (4, &[PERSON_CLASS_LABEL])];
+        let (saved, submitted) = run_script(config, &script);
+        assert_eq!(saved, [3, 5]);
+        assert_eq!(submitted, [false, false, true, false, true]);
+
+        let config = SnapshotConfig::new(temp_dir("custom_class")).with_cooldown(Duration::ZERO).with_class_name("car");
+        assert_eq!(run_script(config, &script).0, [1]);
+    }
+
+    #[test]
+    fn cooldown_suppresses_quick_reentry() {
+        let config = SnapshotConfig::new(temp_dir("cooldown")).with_cooldown(Duration::from_secs(5));
+        let person: &[&str] = &[PERSON_CLASS_LABEL];
+        // 第2、4秒再次进入仍在冷却期内，第10秒进入时已过冷却期
+        let script = [(0, person), (1, &[]), (2, person), (3, &[]), (4, person), (9, &[]), (10, person)];
+        assert_eq!(run_script(config, &script).0, [1, 7]);
+    }
+
+    #[test]
+    fn periodic_trigger_saves_while_occupied() {
+        let config = SnapshotConfig::new(temp_dir("periodic"))
+            .with_cooldown(Duration::from_secs(2))
+            .with_trigger(SnapshotTrigger::OnEnterAndEvery(Duration::from_secs(3)));
+        let person: &[&str] = &[PERSON_CLASS_LABEL];
+        let script: Vec<(u64, &[&str])> = (0..8).map(|second| (second, person)).collect();
+        assert_eq!(run_script(config, &script).0, [1, 4, 7]);
+
+        // 冷却时间长于保存间隔时以冷却时间为准
+        let config = SnapshotConfig::new(temp_dir("periodic_cooldown"))
+            .with_cooldown(Duration::from_secs(5))
+            .with_trigger(SnapshotTrigger::OnEnterAndEvery(Duration::from_secs(1)));
+        assert_eq!(run_script(config, &script).0, [1, 6]);
+    }
+
+    #[test]
+    fn write_errors_go_to_error_channel() {
+        let dir = temp_dir("unwritable");
+        // 模板指向不存在的子目录，每次写盘都会失败
+        let config = SnapshotConfig::new(&dir).with_cooldown(Duration::ZERO).with_filename_template("missing/{frame_id}.jpg");
+        let mut sink = SnapshotSink::spawn(config).unwrap();
+        let image = DynamicImage::new_rgb8(16, 16);
+        assert!(sink.observe(&frame_at(1, 0), &image, &detections(&[PERSON_CLASS_LABEL])));
+        // 关闭发送端并等待快照线程退出后，错误已全部转发
+        sink.sender.take();
+        sink.handle.take().unwrap().join().unwrap();
+        assert!(matches!(sink.try_recv_error(), Some(PerpleError::Io(_))));
+        assert!(sink.try_recv_error().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
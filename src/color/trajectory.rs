@@ -0,0 +1,217 @@
+//! 轨迹记录模块
+//!
+//! 按跟踪编号记录每个目标最近若干帧的中心点，供[draw_trajectories](crate::color::utils::draw_trajectories)
+//! 绘制逐渐淡出的运动轨迹。
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::color::bounds::BoundsN;
+use crate::config::{DEFAULT_TRAJECTORY_MAX_MISSED, DEFAULT_TRAJECTORY_POINTS};
+
+/// 单条轨迹
+#[derive(Debug, Clone)]
+pub(crate) struct Track {
+    /// 中心点，从旧到新排列
+    pub(crate) points: VecDeque<(f32, f32)>,
+    /// 最近一次出现时的类别
+    pub(crate) class_id: usize,
+    /// 连续未出现的帧数
+    missed: usize,
+}
+
+/// 跟踪轨迹记录器
+///
+/// 每帧输入一次检测结果，只记录带有[track_id](crate::color::Detection::track_id)的检测。
+/// 检测器本身不分配跟踪编号，需由外部跟踪器写入后再调用[update](Self::update)，
+/// 例如在[Perple::on_detection](crate::perple::Perple::on_detection)回调中完成。
+/// 每条轨迹最多保留`max_points`个中心点，连续`max_missed`帧未出现的轨迹被移除。
+/// 轨迹按跟踪编号排序，相同的输入总是得到相同的绘制顺序。
+#[derive(Clone)]
+pub struct TrajectoryRenderer {
+    max_points: usize,
+    max_missed: usize,
+    tracks: BTreeMap<usize, Track>,
+}
+
+impl Default for TrajectoryRenderer {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRAJECTORY_POINTS)
+    }
+}
+
+impl TrajectoryRenderer {
+    /// 创建一个新的记录器
+    ///
+    /// # 参数
+    /// * `max_points` - 每条轨迹保留的中心点数，至少为1
+    pub fn new(max_points: usize) -> Self {
+        Self {
+            max_points: max_points.max(1),
+            max_missed: DEFAULT_TRAJECTORY_MAX_MISSED,
+            tracks: BTreeMap::new(),
+        }
+    }
+
+    /// 设置轨迹被移除前允许连续未出现的帧数，为0时轨迹在第一次未出现时即被移除
+    pub fn with_max_missed(mut self, frames: usize) -> Self {
+        self.max_missed = frames;
+        self
+    }
+
+    /// 设置轨迹被移除前允许连续未出现的帧数
+    pub fn set_max_missed(&mut self, frames: usize) {
+        self.max_missed = frames;
+    }
+
+    /// 每条轨迹保留的中心点数
+    pub fn max_points(&self) -> usize {
+        self.max_points
+    }
+
+    /// 轨迹被移除前允许连续未出现的帧数
+    pub fn max_missed(&self) -> usize {
+        self.max_missed
+    }
+
+    /// 输入一帧检测结果，追加各轨迹的中心点并移除过期的轨迹
+    ///
+    /// 中心坐标不是有限值的检测被忽略，但仍算作该轨迹在本帧出现。
+    pub fn update<const N: usize>(&mut self, bounds: &BoundsN<N>) {
+        for track in self.tracks.values_mut() {
+            track.missed += 1;
+        }
+        for detection in bounds.iter() {
+            let Some(track_id) = detection.track_id else { continue };
+            let track = self.tracks.entry(track_id).or_insert_with(|| Track {
+                points: VecDeque::with_capacity(self.max_points),
+                class_id: detection.class_id,
+                missed: 0,
+            });
+            track.missed = 0;
+            track.class_id = detection.class_id;
+            let (x, y) = detection.bbox.center();
+            if !(x.is_finite() && y.is_finite()) {
+                continue;
+            }
+            if track.points.len() == self.max_points {
+                track.points.pop_front();
+            }
+            track.points.push_back((x, y));
+        }
+        let max_missed = self.max_missed;
+        self.tracks.retain(|_, track| track.missed <= max_missed);
+    }
+
+    /// 指定轨迹的中心点，从旧到新排列；轨迹不存在时返回`None`
+    pub fn points(&self, track_id: usize) -> Option<&VecDeque<(f32, f32)>> {
+        self.tracks.get(&track_id).map(|track| &track.points)
+    }
+
+    /// 当前记录的跟踪编号，从小到大排列
+    pub fn track_ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.tracks.keys().copied()
+    }
+
+    /// 当前记录的轨迹数
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    /// 是否没有任何轨迹
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    /// 移除所有轨迹
+    pub fn clear(&mut self) {
+        self.tracks.clear();
+    }
+
+    /// 按跟踪编号排列的全部轨迹
+    pub(crate) fn tracks(&self) -> impl Iterator<Item = &Track> {
+        self.tracks.values()
+    }
+}
+
+impl std::fmt::Debug for TrajectoryRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrajectoryRenderer")
+            .field("max_points", &self.max_points)
+            .field("max_missed", &self.max_missed)
+            .field("tracks", &self.tracks.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::bounds::{BoundingBox, Bounds, Detection};
+
+    /// 指定跟踪编号和中心点的检测结果
+    fn tracked(track_id: usize, x: f32, y: f32) -> Detection {
+        let mut detection = Detection::new(BoundingBox::new(x - 5.0, y - 5.0, x + 5.0, y + 5.0), 0, "person", 0.9);
+        detection.track_id = Some(track_id);
+        detection
+    }
+
+    fn frame(detections: impl IntoIterator<Item = Detection>) -> Bounds {
+        detections.into_iter().collect()
+    }
+
+    #[test]
+    fn untracked_detections_are_ignored() {
+        let mut renderer = TrajectoryRenderer::new(4);
+        renderer.update(&frame([Detection::new(BoundingBox::new(0.0, 0.0, 10.0, 10.0), 0, "person", 0.9)]));
+        assert!(renderer.is_empty());
+    }
+
+    #[test]
+    fn keeps_only_latest_points() {
+        let mut renderer = TrajectoryRenderer::new(3);
+        for step in 0..5 {
+            renderer.update(&frame([tracked(7, step as f32 * 10.0, 20.0)]));
+        }
+        let points: Vec<_> = renderer.points(7).unwrap().iter().copied().collect();
+        assert_eq!(points, vec![(20.0, 20.0), (30.0, 20.0), (40.0, 20.0)]);
+    }
+
+    #[test]
+    fn missing_tracks_are_evicted_after_max_missed() {
+        let mut renderer = TrajectoryRenderer::new(4).with_max_missed(2);
+        renderer.update(&frame([tracked(1, 10.0, 10.0), tracked(2, 50.0, 50.0)]));
+        renderer.update(&frame([tracked(2, 52.0, 50.0)]));
+        renderer.update(&frame([tracked(2, 54.0, 50.0)]));
+        assert_eq!(renderer.track_ids().collect::<Vec<_>>(), vec![1, 2]);
+
+        renderer.update(&frame([tracked(2, 56.0, 50.0)]));
+        assert_eq!(renderer.track_ids().collect::<Vec<_>>(), vec![2]);
+        assert!(renderer.points(1).is_none());
+    }
+
+    #[test]
+    fn reappearing_track_resets_missed_count() {
+        let mut renderer = TrajectoryRenderer::new(4).with_max_missed(1);
+        renderer.update(&frame([tracked(3, 0.0, 0.0)]));
+        renderer.update(&Bounds::new());
+        renderer.update(&frame([tracked(3, 5.0, 0.0)]));
+        renderer.update(&Bounds::new());
+        assert_eq!(renderer.points(3).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn zero_max_missed_evicts_immediately() {
+        let mut renderer = TrajectoryRenderer::new(4).with_max_missed(0);
+        renderer.update(&frame([tracked(1, 0.0, 0.0)]));
+        renderer.update(&Bounds::new());
+        assert!(renderer.is_empty());
+    }
+
+    #[test]
+    fn non_finite_centers_keep_track_alive_without_points() {
+        let mut renderer = TrajectoryRenderer::new(4).with_max_missed(0);
+        renderer.update(&frame([tracked(1, 0.0, 0.0)]));
+        renderer.update(&frame([tracked(1, f32::NAN, 0.0)]));
+        assert_eq!(renderer.points(1).unwrap().len(), 1);
+    }
+}
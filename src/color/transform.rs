@@ -0,0 +1,250 @@
+//! 二维仿射变换
+//!
+//! 用于在不同坐标空间之间映射检测结果，例如模型输入坐标到原始图像坐标、
+//! 原始图像到显示画布，或图像旋转/翻转之后的坐标。
+
+use crate::color::image::ScaleMessage;
+
+/// 二维仿射变换
+///
+/// 将点`(x, y)`映射为`(a*x + b*y + tx, c*x + d*y + ty)`。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine2 {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub tx: f32,
+    pub ty: f32,
+}
+
+impl Affine2 {
+    /// 恒等变换
+    pub fn identity() -> Self {
+        Self { a: 1.0, b: 0.0, c: 0.0, d: 1.0, tx: 0.0, ty: 0.0 }
+    }
+
+    /// 缩放变换
+    pub fn scale(sx: f32, sy: f32) -> Self {
+        Self { a: sx, d: sy, ..Self::identity() }
+    }
+
+    /// 平移变换
+    pub fn translate(tx: f32, ty: f32) -> Self {
+        Self { tx, ty, ..Self::identity() }
+    }
+
+    /// 将图像顺时针旋转90°后的坐标变换
+    ///
+    /// # 参数
+    /// * `_img_width` - 旋转前的图像宽度
+    /// * `img_height` - 旋转前的图像高度（旋转后成为宽度）
+    pub fn rotate90(_img_width: f32, img_height: f32) -> Self {
+        Self { a: 0.0, b: -1.0, c: 1.0, d: 0.0, tx: img_height, ty: 0.0 }
+    }
+
+    /// 将图像旋转180°后的坐标变换
+    pub fn rotate180(img_width: f32, img_height: f32) -> Self {
+        Self { a: -1.0, b: 0.0, c: 0.0, d: -1.0, tx: img_width, ty: img_height }
+    }
+
+    /// 将图像顺时针旋转270°（即逆时针90°）后的坐标变换
+    ///
+    /// # 参数
+    /// * `img_width` - 旋转前的图像宽度（旋转后成为高度）
+    /// * `_img_height` - 旋转前的图像高度
+    pub fn rotate270(img_width: f32, _img_height: f32) -> Self {
+        Self { a: 0.0, b: 1.0, c: -1.0, d: 0.0, tx: 0.0, ty: img_width }
+    }
+
+    /// 水平翻转（左右镜像）宽度为`img_width`的图像
+    pub fn flip_horizontal(img_width: f32) -> Self {
+        Self { a: -1.0, tx: img_width, ..Self::identity() }
+    }
+
+    /// 垂直翻转（上下镜像）高度为`img_height`的图像
+    pub fn flip_vertical(img_height: f32) -> Self {
+        Self { d: -1.0, ty: img_height, ..Self::identity() }
+    }
+
+    /// 从模型输入坐标还原到原始图像坐标的变换
+    ///
+    /// 先减去填充偏移，再按两个方向各自的比例缩放。
+    /// 项目的预处理将原始图像直接缩放到模型输入尺寸（不保持宽高比、无填充），此时只有缩放。
+    pub fn letterbox_inverse(message: &ScaleMessage) -> Self {
+        Self::translate(-(message.pad_left as f32), -(message.pad_top as f32)).then(&Self::scale(
+            message.o_width as f32 / message.s_width as f32,
+            message.o_height as f32 / message.s_height as f32,
+        ))
+    }
+
+    /// 组合变换：先应用`self`，再应用`next`
+    pub fn then(&self, next: &Affine2) -> Self {
+        Self {
+            a: next.a * self.a + next.b * self.c,
+            b: next.a * self.b + next.b * self.d,
+            c: next.c * self.a + next.d * self.c,
+            d: next.c * self.b + next.d * self.d,
+            tx: next.a * self.tx + next.b * self.ty + next.tx,
+            ty: next.c * self.tx + next.d * self.ty + next.ty,
+        }
+    }
+
+    /// 逆变换，矩阵不可逆时返回`None`
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        let (a, b, c, d) = (self.d / det, -self.b / det, -self.c / det, self.a / det);
+        Some(Self {
+            a,
+            b,
+            c,
+            d,
+            tx: -(a * self.tx + b * self.ty),
+            ty: -(c * self.tx + d * self.ty),
+        })
+    }
+
+    /// 变换一个点
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.a * x + self.b * y + self.tx, self.c * x + self.d * y + self.ty)
+    }
+}
+
+impl Default for Affine2 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// 在[输出坐标空间](crate::color::OutputSpace)之间换算所需的几何信息
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpaceGeometry {
+    /// 模型输入坐标到原始图像坐标的变换
+    pub to_original: Affine2,
+    /// 原始图像宽度
+    pub original_width: f32,
+    /// 原始图像高度
+    pub original_height: f32,
+}
+
+impl SpaceGeometry {
+    /// 由图像缩放信息创建，原始图像即缩放前的图像
+    pub fn from_message(message: &ScaleMessage) -> Self {
+        Self {
+            to_original: Affine2::letterbox_inverse(message),
+            original_width: message.o_width as f32,
+            original_height: message.o_height as f32,
+        }
+    }
+
+    /// 缩放前还经过了改变几何形状的预处理时，追加预处理结果到原始图像的变换
+    ///
+    /// # 参数
+    /// * `source` - 预处理后图像坐标到原始图像坐标的变换
+    /// * `width` - 原始图像宽度
+    /// * `height` - 原始图像高度
+    pub fn with_source_transform(self, source: &Affine2, width: u32, height: u32) -> Self {
+        Self {
+            to_original: self.to_original.then(source),
+            original_width: width as f32,
+            original_height: height as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::bounds::{BoundingBox, Bounds, Detection, Keypoint};
+
+    const EPSILON: f32 = 1e-3;
+
+    fn assert_close(actual: (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (actual.0 - expected.0).abs() < EPSILON && (actual.1 - expected.1).abs() < EPSILON,
+            "{:?} != {:?}", actual, expected
+        );
+    }
+
+    fn letterbox_message() -> ScaleMessage {
+        // 1280x720的图像letterbox到640x640：缩放0.5，上下各填充140
+        ScaleMessage::builder().original_size(1280, 720).scaled_size(640, 360).padding(0, 140).build()
+    }
+
+    #[test]
+    fn every_transform_round_trips_through_inverse() {
+        let transforms = [
+            Affine2::identity(),
+            Affine2::scale(2.5, 0.4),
+            Affine2::translate(-30.0, 12.5),
+            Affine2::rotate90(640.0, 480.0),
+            Affine2::rotate180(640.0, 480.0),
+            Affine2::rotate270(640.0, 480.0),
+            Affine2::flip_horizontal(640.0),
+            Affine2::flip_vertical(480.0),
+            Affine2::letterbox_inverse(&letterbox_message()),
+            Affine2::rotate90(640.0, 480.0).then(&Affine2::scale(0.5, 0.5)).then(&Affine2::translate(7.0, -3.0)),
+        ];
+        for transform in transforms {
+            let inverse = transform.inverse().unwrap();
+            for point in [(0.0, 0.0), (123.4, 56.7), (639.0, 479.0)] {
+                let mapped = transform.apply(point.0, point.1);
+                assert_close(inverse.apply(mapped.0, mapped.1), point);
+            }
+            let identity = transform.then(&inverse);
+            assert_close(identity.apply(10.0, 20.0), (10.0, 20.0));
+        }
+        assert!(Affine2::scale(0.0, 1.0).inverse().is_none());
+    }
+
+    #[test]
+    fn rotations_and_flips_map_image_corners() {
+        // 640x480的图像顺时针旋转90°后为480x640，左上角移到右上角
+        assert_close(Affine2::rotate90(640.0, 480.0).apply(0.0, 0.0), (480.0, 0.0));
+        assert_close(Affine2::rotate90(640.0, 480.0).apply(640.0, 0.0), (480.0, 640.0));
+        assert_close(Affine2::rotate270(640.0, 480.0).apply(0.0, 0.0), (0.0, 640.0));
+        assert_close(Affine2::rotate180(640.0, 480.0).apply(0.0, 0.0), (640.0, 480.0));
+        assert_close(Affine2::flip_horizontal(640.0).apply(100.0, 50.0), (540.0, 50.0));
+        assert_close(Affine2::flip_vertical(480.0).apply(100.0, 50.0), (100.0, 430.0));
+        // 先旋转两次90°等价于旋转180°
+        let twice = Affine2::rotate90(640.0, 480.0).then(&Affine2::rotate90(480.0, 640.0));
+        assert_close(twice.apply(100.0, 50.0), Affine2::rotate180(640.0, 480.0).apply(100.0, 50.0));
+    }
+
+    #[test]
+    fn letterbox_inverse_removes_padding_then_scales() {
+        let transform = Affine2::letterbox_inverse(&letterbox_message());
+        assert_close(transform.apply(0.0, 140.0), (0.0, 0.0));
+        assert_close(transform.apply(640.0, 500.0), (1280.0, 720.0));
+        assert_close(transform.apply(320.0, 320.0), (640.0, 360.0));
+    }
+
+    #[test]
+    fn detections_round_trip_with_keypoints() {
+        let mut detection = Detection::new(BoundingBox::new(100.0, 50.0, 300.0, 250.0), 0, "person", 0.9);
+        detection.keypoints = Some(vec![Keypoint::new(150.0, 80.0, 0.9), Keypoint::new(280.0, 240.0, 0.4)]);
+        let original = detection.clone();
+        let mut bounds: Bounds = [detection.clone(), detection].into_iter().collect();
+
+        let transform = Affine2::rotate90(640.0, 480.0).then(&Affine2::scale(1.5, 1.5));
+        bounds.transform_all(&transform);
+        let rotated = &bounds.as_slice()[0].bbox;
+        // 旋转后框的宽高互换
+        assert!((rotated.width() - 300.0).abs() < EPSILON && (rotated.height() - 300.0).abs() < EPSILON);
+        assert_close((rotated.x1, rotated.y1), ((480.0 - 250.0) * 1.5, 100.0 * 1.5));
+
+        bounds.transform_all(&transform.inverse().unwrap());
+        for detection in bounds.iter() {
+            let (bbox, expected) = (&detection.bbox, &original.bbox);
+            assert_close((bbox.x1, bbox.y1), (expected.x1, expected.y1));
+            assert_close((bbox.x2, bbox.y2), (expected.x2, expected.y2));
+            for (keypoint, expected) in detection.keypoints.as_ref().unwrap().iter().zip(original.keypoints.as_ref().unwrap()) {
+                assert_close((keypoint.x, keypoint.y), (expected.x, expected.y));
+                assert_eq!(keypoint.confidence, expected.confidence);
+            }
+        }
+    }
+}
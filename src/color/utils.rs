@@ -2,23 +2,103 @@
 //! 
 //! 负责处理模型输出，进行坐标转换、置信度过滤和非极大值抑制(NMS)等后处理操作。
 
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
 use image::GenericImageView;
 use ndarray::Array2;
+use ndarray::{ArrayView1, ArrayView2};
 use ndarray::Axis;
 use ort::session::SessionOutputs;
+use ort::value::Tensor;
 
 use crate::color::bounds::BoundingBox;
-use crate::color::bounds::Bounds;
+use crate::color::bounds::{Bounds, BoundsN};
 use crate::color::bounds::Detection;
+use crate::color::bounds::Keypoint;
+use crate::color::bounds::Mask;
+use crate::color::bounds::OrientedBoundingBox;
+use crate::color::bounds::OutputSpace;
+use crate::color::bounds::{ThresholdZone, zone_threshold};
 use crate::color::image::ScaleMessage;
-use crate::config::DETECTIONS_CAPACITY;
+use crate::color::labels::ClassMap;
+use crate::color::transform::Affine2;
+use crate::color::trajectory::TrajectoryRenderer;
+use crate::color::model::MIN_OUTPUT_PARAMS;
 use crate::config::PERSON_CLASS_LABEL;
-use crate::utils::sort::group_sort;
-use crate::utils::sort::group_sort_by;
+use crate::error::PerpleError;
+use crate::utils::sort::partial_group_sort_by;
 
 use image::DynamicImage;
-use raqote::{DrawOptions, DrawTarget, LineJoin, PathBuilder, SolidSource, Source, StrokeStyle};
+use raqote::{DrawOptions as RasterOptions, DrawTarget, LineJoin, PathBuilder, SolidSource, Source, StrokeStyle};
+
+
+/// 姿态模型每个目标的关键点数量（COCO格式）
+pub const POSE_KEYPOINTS: usize = 17;
+
+/// COCO人体骨架的连接关系（关键点索引对）
+pub const POSE_SKELETON: [(usize, usize); 19] = [
+    (15, 13), (13, 11), (16, 14), (14, 12), (11, 12),
+    (5, 11), (6, 12), (5, 6), (5, 7), (6, 8),
+    (7, 9), (8, 10), (1, 2), (0, 1), (0, 2),
+    (1, 3), (2, 4), (3, 5), (4, 6),
+];
+
+/// 根据每个框的参数个数判断关键点数据的起始位置
+/// 
+/// - `4 + 1 + 17*3 = 56`：原始姿态模型输出`[box, conf, keypoints...]`
+/// - `4 + 1 + 1 + 17*3 = 57`：内置NMS导出的姿态模型输出`[box, conf, class, keypoints...]`
+/// 
+/// 其他参数个数视为非姿态模型，返回`None`
+pub fn keypoint_offset(num_params: usize) -> Option<usize> {
+    match num_params {
+        n if n == 5 + POSE_KEYPOINTS * 3 => Some(5),
+        n if n == 6 + POSE_KEYPOINTS * 3 => Some(6),
+        _ => None,
+    }
+}
+
+/// 从单个框的输出行中解析关键点并缩放到原始图像坐标
+/// 
+/// # 参数
+/// * `row` - 单个框的全部输出参数
+/// * `scale_x` - 横向缩放比例（原始宽度 / 模型输入宽度）
+/// * `scale_y` - 纵向缩放比例（原始高度 / 模型输入高度）
+pub fn parse_keypoints(row: &[f32], scale_x: f32, scale_y: f32) -> Option<Vec<Keypoint>> {
+    let offset = keypoint_offset(row.len())?;
+    Some(row[offset..].chunks_exact(3)
+        .map(|kp| Keypoint::new(kp[0] * scale_x, kp[1] * scale_y, kp[2]))
+        .collect())
+}
+
+/// 模型输出中边界框的坐标格式
+/// 
+/// 项目自带的模型由`scripts/dev/to_onnx.py`以`nms=True`导出，
+/// 输出形状为[1, 300, 6]，每行为`[x1, y1, x2, y2, conf, class]`，即[CoordFormat::Xyxy]格式，
+/// 因此默认值为`Xyxy`。未内置NMS导出的YOLOv5/v8/v11原始输出使用[CoordFormat::CxCyWh]格式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CoordFormat {
+    /// 左上角和右下角坐标 `[x1, y1, x2, y2]`
+    #[default]
+    Xyxy,
+    /// 中心点和宽高 `[cx, cy, w, h]`
+    CxCyWh,
+}
 
+impl CoordFormat {
+    /// 将该格式的四个坐标值转换为`[x1, y1, x2, y2]`
+    pub fn to_xyxy(&self, values: [f32; 4]) -> [f32; 4] {
+        match self {
+            CoordFormat::Xyxy => values,
+            CoordFormat::CxCyWh => {
+                let [cx, cy, w, h] = values;
+                [cx - w / 2.0, cy - h / 2.0, cx + w / 2.0, cy + h / 2.0]
+            }
+        }
+    }
+}
 
 /// 处理模型输出，应用置信度和NMS阈值
 /// 
@@ -34,7 +114,7 @@ use raqote::{DrawOptions, DrawTarget, LineJoin, PathBuilder, SolidSource, Source
 /// * `nms_threshold` - NMS阈值
 /// 
 /// # 返回值
-/// 返回处理后的检测结果列表
+/// 返回处理后的检测结果列表；`output`少于[MIN_OUTPUT_PARAMS]列时返回[PerpleError::ShapeMismatch]
 /// 
 /// # 示例
 /// 
@@ -51,7 +131,7 @@ use raqote::{DrawOptions, DrawTarget, LineJoin, PathBuilder, SolidSource, Source
 ///     640,     // 模型输入高度
 ///     0.5,     // 置信度阈值
 ///     0.7      // NMS阈值
-/// );
+/// ).unwrap();
 /// ```
 pub fn process_detections(
     output: Array2<f32>,
@@ -61,26 +141,86 @@ pub fn process_detections(
     input_height: usize,
     confidence_threshold: f32,
     nms_threshold: f32,
-) -> Vec<Detection> {
-    let mut detections = Vec::new();
-    
+) -> Result<Vec<Detection>, PerpleError> {
+    process_detections_with_format(
+        output,
+        img_width,
+        img_height,
+        input_width,
+        input_height,
+        confidence_threshold,
+        nms_threshold,
+        CoordFormat::Xyxy,
+    )
+}
+
+/// 处理指定坐标格式的模型输出
+/// 
+/// 与[process_detections]相同，但前四列按`format`解释，
+/// 在缩放到原始图像坐标之前统一转换为`[x1, y1, x2, y2]`。
+#[allow(clippy::too_many_arguments)]
+pub fn process_detections_with_format(
+    output: Array2<f32>,
+    img_width: f32,
+    img_height: f32,
+    input_width: usize,
+    input_height: usize,
+    confidence_threshold: f32,
+    nms_threshold: f32,
+    format: CoordFormat,
+) -> Result<Vec<Detection>, PerpleError> {
+    process_detections_calibrated(
+        output,
+        img_width,
+        img_height,
+        input_width,
+        input_height,
+        confidence_threshold,
+        nms_threshold,
+        format,
+        &ConfidenceCalibration::None,
+    )
+}
+
+/// 处理模型输出，先对置信度应用校准
+/// 
+/// 与[process_detections_with_format]相同，但每个框的置信度先经过`calibration`，
+/// 置信度阈值作用于校准后的分数，与[calibrate_tensor]之后再做NMS的结果一致。
+/// [YoloDetector::process_detections](crate::color::YoloDetector::process_detections)
+/// 使用检测器自身的校准方式调用该函数。
+#[allow(clippy::too_many_arguments)]
+pub fn process_detections_calibrated(
+    output: Array2<f32>,
+    img_width: f32,
+    img_height: f32,
+    input_width: usize,
+    input_height: usize,
+    confidence_threshold: f32,
+    nms_threshold: f32,
+    format: CoordFormat,
+    calibration: &ConfidenceCalibration,
+) -> Result<Vec<Detection>, PerpleError> {
+    let (rows, columns) = output.dim();
+    if columns < MIN_OUTPUT_PARAMS {
+        return Err(PerpleError::ShapeMismatch {
+            expected: (rows as u32, MIN_OUTPUT_PARAMS as u32),
+            actual: (rows as u32, columns as u32),
+        });
+    }
     // 预分配容量以减少重新分配
-    detections.reserve(output.len_of(Axis(0)));
+    let mut detections = Vec::with_capacity(rows);
     
     for row in output.axis_iter(Axis(0)) {
-        let row_slice = row.as_slice().expect("Row should be contiguous");
         // 对于只有一个人物检测类别的情况，直接获取置信度
-        let prob = row_slice[4]; // 第5个元素是person类别的置信度
-            
-        if prob < confidence_threshold {
+        let prob = calibration.apply(row[4]); // 第5个元素是person类别的置信度
+        
+        // 校准函数可能产生NaN，NaN不应通过过滤
+        if prob.is_nan() || prob < confidence_threshold {
             continue;
         }
         // YOLO模型输出的是相对于输入图像尺寸的坐标 (640x640)
         // 需要将其转换为相对于原始图像尺寸的坐标
-        let x1 = row[0];  // 左上角x坐标 (相对于640)
-        let y1 = row[1];  // 左上角y坐标 (相对于640)
-        let x2 = row[2];   // 右下角x坐标 (相对于640)
-        let y2 = row[3];   // 右下角y坐标 (相对于640)
+        let [x1, y1, x2, y2] = format.to_xyxy([row[0], row[1], row[2], row[3]]);
 
         // 转换为相对于原始图像的坐标
         let scale_x = img_width / input_width as f32;
@@ -92,15 +232,16 @@ pub fn process_detections(
         let s_y2 = y2 * scale_y;
 
         detections.push(Detection {
-            bbox: BoundingBox {
-                x1: s_x1,
-                y1: s_y1,
-                x2: s_x2,
-                y2: s_y2
-            },
+            // 统一角点顺序，避免NMS按负面积丢弃角点颠倒的框
+            bbox: BoundingBox::new(s_x1, s_y1, s_x2, s_y2).normalized(),
             class_id: 0, // 只有一个类别，ID为0
-            class_name: PERSON_CLASS_LABEL.to_string(),
-            confidence: prob
+            class_name: Cow::Borrowed(PERSON_CLASS_LABEL),
+            confidence: prob,
+            keypoints: None,
+            mask: None,
+            track_id: None,
+            source_index: None,
+            attributes: None,
         });
     }
 
@@ -108,28 +249,38 @@ pub fn process_detections(
     detections.sort_unstable_by(|a, b| b.confidence.total_cmp(&a.confidence));
     
     // 应用非极大值抑制(NMS)
-    apply_nms(&mut detections, nms_threshold)
+    Ok(apply_nms(&mut detections, nms_threshold, None))
 }
 
+/// 直接处理会话输出的第一个张量：置信度过滤、映射回原始图像坐标并应用NMS
+/// 
+/// 输出形状不是`[1, 框数量, 参数个数]`或原始导出排列时返回空结果；
+/// 输出张量不是f32时返回[PerpleError::Ort]。
 pub fn to_bounds(
     output: &SessionOutputs,
     message: &ScaleMessage,
     confidence_threshold: f32,
     nms_threshold: f32,
-) -> Vec<Detection> {
+) -> Result<Vec<Detection>, PerpleError> {
     let mut detections = Vec::new();
-    let (img_width, img_height) = (message.o_width as f32, message.o_height as f32);
-    let (input_width, input_height) = (message.s_width, message.s_height);
+    let to_image = Affine2::letterbox_inverse(message);
     
     // 从SessionOutputs中直接提取张量数据
     let output_tensor = &output[0];
-    let extracted_tensor = output_tensor.try_extract_tensor::<f32>().expect("无法提取张量");
+    let extracted_tensor = output_tensor.try_extract_tensor::<f32>()?;
     let shape = extracted_tensor.0.clone();
-    let data = extracted_tensor.1; // 直接使用引用，避免to_vec()的内存复制
-    
-    // 直接处理原始数据，绕过Array2中间环节
-    let num_boxes = shape[1] as usize;
-    let num_params = shape[2] as usize;
+    // 原始导出排列先转置为按行存储，其余情况直接使用引用，避免to_vec()的内存复制
+    let transposed = is_raw_output(&shape)
+        .then(|| transpose_params(extracted_tensor.1, shape[1] as usize, shape[2] as usize));
+    let (num_boxes, num_params) = match &transposed {
+        Some(_) => (shape[2] as usize, shape[1] as usize),
+        // 输出形状不符合[1, num_boxes, num_params]时直接返回空结果，避免越界访问
+        None => match output_rows(&shape) {
+            Some(rows) => rows,
+            None => return Ok(detections),
+        },
+    };
+    let data = transposed.as_deref().unwrap_or(extracted_tensor.1);
     
     // 遍历每个检测框
     for i in 0..num_boxes {
@@ -148,33 +299,28 @@ pub fn to_bounds(
             continue;
         }
         
-        // 转换为相对于原始图像的坐标
-        let scale_x = img_width / input_width as f32;
-        let scale_y = img_height / input_height as f32;
-        
-        let scaled_x1 = x1 * scale_x;
-        let scaled_y1 = y1 * scale_y;
-        let scaled_x2 = x2 * scale_x;
-        let scaled_y2 = y2 * scale_y;
-        
-        detections.push(Detection {
-            bbox: BoundingBox {
-                x1: scaled_x1,
-                y1: scaled_y1,
-                x2: scaled_x2,
-                y2: scaled_y2,
-            },
+        let mut detection = Detection {
+            // 统一角点顺序，避免NMS按负面积丢弃角点颠倒的框
+            bbox: BoundingBox::new(x1, y1, x2, y2).normalized(),
             class_id: 0,
-            class_name: PERSON_CLASS_LABEL.to_string(),
+            class_name: Cow::Borrowed(PERSON_CLASS_LABEL),
             confidence,
-        });
+            keypoints: parse_keypoints(&data[start_index..start_index + num_params], 1.0, 1.0),
+            mask: None,
+            track_id: None,
+            source_index: None,
+            attributes: None,
+        };
+        // 转换为相对于原始图像的坐标
+        detection.transform(&to_image);
+        detections.push(detection);
     }
     
     // 按置信度排序
     detections.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
     
     // 应用非极大值抑制(NMS)
-    apply_nms(&mut detections, nms_threshold)
+    Ok(apply_nms(&mut detections, nms_threshold, None))
 }
 
 
@@ -185,51 +331,54 @@ pub fn to_bounds(
 /// # 参数
 /// * `detections` - 检测结果列表（会被修改）
 /// * `nms_threshold` - NMS阈值
+/// * `max_detections` - 结果数量上限，达到后立即停止处理；`None`表示不限制
 /// 
 /// # 返回值
 /// 返回应用NMS后的检测结果列表
-fn apply_nms(detections: &mut Vec<Detection>, nms_threshold: f32) -> Vec<Detection> {
+pub(crate) fn apply_nms(detections: &mut Vec<Detection>, nms_threshold: f32, max_detections: Option<usize>) -> Vec<Detection> {
+    apply_nms_with_options(detections, &NmsOptions::new(nms_threshold).with_max_detections(max_detections))
+}
+
+/// 按指定的抑制准则应用非极大值抑制
+/// 
+/// `detections`应已按置信度从高到低排序。与[nms_tensor_with_options]一致，只在类别相同的框之间抑制。
+pub(crate) fn apply_nms_with_options(detections: &mut Vec<Detection>, options: &NmsOptions) -> Vec<Detection> {
+    if let Some(max_candidates) = options.max_candidates {
+        detections.truncate(max_candidates);
+    }
+    let max_detections = options.max_detections.unwrap_or(usize::MAX);
     let mut result = Vec::new();
     let mut picked_indices = vec![false; detections.len()];
 
     for i in 0..detections.len() {
+        // 结果已达上限，剩余的框无需再考察
+        if result.len() >= max_detections {
+            break;
+        }
         if picked_indices[i] {
             continue;
         }
         
         result.push(detections[i].clone());
         
-        // 缓存当前检测框的面积以避免重复计算
-        let area_i = (detections[i].bbox.x2 - detections[i].bbox.x1) * (detections[i].bbox.y2 - detections[i].bbox.y1);
-        
         // 提前检查，如果框的面积为0，则跳过
-        if area_i <= 0.0 {
+        if signed_area(&detections[i].bbox) <= 0.0 {
             picked_indices[i] = true;
             continue;
         }
         
         for j in (i + 1)..detections.len() {
-            if picked_indices[j] {
+            if picked_indices[j] || detections[j].class_id != detections[i].class_id {
                 continue;
             }
             
-            let area_j = (detections[j].bbox.x2 - detections[j].bbox.x1) * (detections[j].bbox.y2 - detections[j].bbox.y1);
-            
             // 提前检查，如果框的面积为0，则跳过
-            if area_j <= 0.0 {
+            if signed_area(&detections[j].bbox) <= 0.0 {
                 picked_indices[j] = true;
                 continue;
             }
             
-            let inter = intersection(&detections[i].bbox, &detections[j].bbox);
-            // 如果交集为0，直接跳过
-            if inter == 0.0 {
-                continue;
-            }
-            
-            let union_area = area_i + area_j - inter;
-            let iou = inter / union_area;
-            if iou >= nms_threshold {
+            if options.suppresses(&detections[i].bbox, &detections[j].bbox) {
                 picked_indices[j] = true;
             }
         }
@@ -238,177 +387,1468 @@ fn apply_nms(detections: &mut Vec<Detection>, nms_threshold: f32) -> Vec<Detecti
     result
 }
 
-pub fn nms_tensor(
-    from_model: &mut SessionOutputs,
-    bounds: &mut Bounds,
-    message: &ScaleMessage,
-    picked_indices: &mut [bool; DETECTIONS_CAPACITY],
-    confidence_threshold: f32,
-    nms_threshold: f32,
-) {
-    bounds.clear();
+/// 对相邻两帧的检测结果做跨帧非极大值抑制
+/// 
+/// 汇总两帧的全部检测结果，类别相同且IoU不小于`iou_threshold`的跨帧重复结果只保留置信度较高者，
+/// 没有跨帧重复的结果全部保留；同一帧内的结果之间不做抑制。
+/// 两帧应使用相同的坐标空间，结果沿用`frame_b`的坐标空间，并按置信度从高到低排列。
+/// 
+/// # 参数
+/// * `frame_a` - 较早一帧的检测结果
+/// * `frame_b` - 较晚一帧的检测结果
+/// * `iou_threshold` - 判定为跨帧重复的IoU阈值
+pub fn window_nms(frame_a: &Bounds, frame_b: &Bounds, iou_threshold: f32) -> Bounds {
+    temporal_nms([frame_a, frame_b], iou_threshold)
+}
+
+/// 对多帧检测结果做跨帧非极大值抑制，规则与[window_nms]相同，结果沿用最后一帧的坐标空间
+fn temporal_nms<'a>(frames: impl IntoIterator<Item = &'a Bounds>, iou_threshold: f32) -> Bounds {
+    let mut space = None;
+    let mut pooled: Vec<(usize, &Detection)> = Vec::new();
+    for (frame, bounds) in frames.into_iter().enumerate() {
+        space = Some(bounds.space());
+        pooled.extend(bounds.iter().map(|detection| (frame, detection)));
+    }
+    pooled.sort_by(|a, b| b.1.confidence.total_cmp(&a.1.confidence));
     
-    let (img_width, img_height) = (message.o_width as f32, message.o_height as f32);
-    let (input_width, input_height) = (message.s_width, message.s_height);
-    let width_scale = img_width / input_width as f32;
-    let height_scale = img_height / input_height as f32;
+    let mut suppressed = vec![false; pooled.len()];
+    let mut result = Bounds::new();
+    for i in 0..pooled.len() {
+        if suppressed[i] {
+            continue;
+        }
+        let (frame, kept) = pooled[i];
+        result.push(kept.clone());
+        for j in (i + 1)..pooled.len() {
+            let (other_frame, other) = pooled[j];
+            if !suppressed[j] && other_frame != frame && other.class_id == kept.class_id
+                && iou(&kept.bbox, &other.bbox) >= iou_threshold {
+                suppressed[j] = true;
+            }
+        }
+    }
+    result.set_space(space.unwrap_or_default());
+    result
+}
 
-    // 从SessionOutputs中直接提取张量数据
-    let output_tensor = &mut from_model[0];
-    let extracted_tensor = output_tensor.try_extract_tensor_mut::<f32>().expect("无法提取张量");
-    let shape = extracted_tensor.0;
-    let mut data = extracted_tensor.1; // 直接使用引用，避免to_vec()的内存复制
-    // 直接处理原始数据，绕过Array2中间环节
-    let num_boxes = shape[1] as usize;
-    let num_params = shape[2] as usize;
-    
-    // 按置信度排序，将置信度高的框排在前面
-    group_sort_by(&mut data, num_params, 4, |a, b| 
-        b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+/// 滑动窗口的跨帧检测结果过滤器
+/// 
+/// 保存最近`window_size`帧的检测结果，每输入一帧就对窗口内的所有帧做跨帧非极大值抑制
+/// （规则见[window_nms]），用于稳定相邻帧间抖动的检测框。
+/// 注意窗口内较早帧中的目标在消失后仍会保留至多`window_size - 1`帧。
+#[derive(Debug, Clone)]
+pub struct TemporalBoundsFilter {
+    window: VecDeque<Bounds>,
+    window_size: usize,
+    iou_threshold: f32,
+}
 
-    // 初始化picked_indices数组，但不超过DETECTIONS_CAPACITY的大小
-    picked_indices.fill(false);
+impl TemporalBoundsFilter {
+    /// 创建一个新的过滤器
+    /// 
+    /// # 参数
+    /// * `window_size` - 窗口帧数，至少为1；为1时不做跨帧抑制
+    /// * `iou_threshold` - 判定为跨帧重复的IoU阈值
+    pub fn new(window_size: usize, iou_threshold: f32) -> Self {
+        let window_size = window_size.max(1);
+        Self {
+            window: VecDeque::with_capacity(window_size),
+            window_size,
+            iou_threshold,
+        }
+    }
+    
+    /// 窗口帧数
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+    
+    /// 判定为跨帧重复的IoU阈值
+    pub fn iou_threshold(&self) -> f32 {
+        self.iou_threshold
+    }
+    
+    /// 设置判定为跨帧重复的IoU阈值
+    pub fn set_iou_threshold(&mut self, iou_threshold: f32) {
+        self.iou_threshold = iou_threshold;
+    }
+    
+    /// 输入一帧检测结果，返回对当前窗口做跨帧抑制后的结果
+    pub fn update(&mut self, new_bounds: Bounds) -> Bounds {
+        if self.window.len() == self.window_size {
+            self.window.pop_front();
+        }
+        self.window.push_back(new_bounds);
+        temporal_nms(&self.window, self.iou_threshold)
+    }
+    
+    /// 清空窗口
+    pub fn reset(&mut self) {
+        self.window.clear();
+    }
+}
 
-    // NMS处理
-    for i in 0..num_boxes.min(DETECTIONS_CAPACITY) {
-        // 如果当前框已经被抑制，则跳过
-        if picked_indices[i] {
-            continue;
+/// NMS的重叠度度量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum NmsMode {
+    /// 交并比：交集面积 / 并集面积
+    #[default]
+    Iou,
+    /// 交集面积 / 较小框的面积
+    /// 
+    /// 小框完全位于大框内部时为1.0，适合抑制人群场景中嵌套的重复框；
+    /// 普通IoU在这种情况下可能很低而保留两个框。
+    IoMin,
+}
+
+/// NMS参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NmsOptions {
+    /// 重叠度阈值，重叠度不低于该值的低置信度框被抑制
+    pub threshold: f32,
+    /// 重叠度度量
+    pub mode: NmsMode,
+    /// 包含率阈值：低置信度框有不低于该比例的面积位于已保留的框内时也被抑制
+    pub containment_threshold: Option<f32>,
+    /// 参与NMS的候选框数量上限（按置信度取前K个），`None`表示最多考察结果容量（默认[DETECTIONS_CAPACITY](crate::config::DETECTIONS_CAPACITY)）个
+    pub max_candidates: Option<usize>,
+    /// 保留结果的数量上限，达到后立即停止NMS；`None`表示不限制
+    /// 
+    /// [nms_tensor_with_options]总是不超过结果容量`N`，超出的框不会再被考察，而不是在写入时被丢弃。
+    pub max_detections: Option<usize>,
+    /// 是否在[Detection::source_index]中记录结果在原始输出张量中的行号
+    /// 
+    /// [nms_tensor_with_options]为此需要复制一份完整的输出，只应在调试时启用。
+    pub record_source_index: bool,
+}
+
+impl NmsOptions {
+    /// 创建使用IoU度量、不检查包含率的参数
+    pub fn new(threshold: f32) -> Self {
+        Self { threshold, mode: NmsMode::Iou, containment_threshold: None, max_candidates: None, max_detections: None, record_source_index: false }
+    }
+
+    /// 设置重叠度度量
+    pub fn with_mode(mut self, mode: NmsMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// 设置包含率阈值
+    pub fn with_containment_threshold(mut self, threshold: Option<f32>) -> Self {
+        self.containment_threshold = threshold;
+        self
+    }
+
+    /// 设置候选框数量上限
+    pub fn with_max_candidates(mut self, max_candidates: Option<usize>) -> Self {
+        self.max_candidates = max_candidates;
+        self
+    }
+
+    /// 设置保留结果的数量上限
+    pub fn with_max_detections(mut self, max_detections: Option<usize>) -> Self {
+        self.max_detections = max_detections;
+        self
+    }
+
+    /// 设置是否记录原始输出张量行号
+    pub fn with_source_index(mut self, enabled: bool) -> Self {
+        self.record_source_index = enabled;
+        self
+    }
+
+    /// 实际考察的候选框数量，不超过结果容量`capacity`
+    fn candidate_limit(&self, available: usize, capacity: usize) -> usize {
+        available.min(capacity).min(self.max_candidates.unwrap_or(usize::MAX))
+    }
+
+    /// 实际保留的结果数量上限，不超过结果容量`capacity`
+    fn detection_limit(&self, capacity: usize) -> usize {
+        capacity.min(self.max_detections.unwrap_or(usize::MAX))
+    }
+
+    /// 已保留的框`kept`是否抑制置信度更低的框`candidate`
+    pub fn suppresses(&self, kept: &BoundingBox, candidate: &BoundingBox) -> bool {
+        let overlap = match self.mode {
+            NmsMode::Iou => iou(kept, candidate),
+            NmsMode::IoMin => intersection_over_min(kept, candidate),
+        };
+        overlap >= self.threshold
+            || self.containment_threshold.is_some_and(|t| containment_ratio(candidate, kept) >= t)
+    }
+}
+
+/// 置信度校准方式
+/// 
+/// 在提取出每个框的原始置信度之后、置信度过滤和NMS之前应用，
+/// 因此置信度阈值作用于校准后的分数。
+#[derive(Default)]
+pub enum ConfidenceCalibration {
+    /// 不做校准，直接使用模型输出
+    #[default]
+    None,
+    /// 温度缩放：`sigmoid(logit(p) / T)`，T大于1时置信度向0.5收缩
+    /// 
+    /// T应为正数，不大于0或为NaN时按[f32::EPSILON]计算，与
+    /// [YoloDetector::calibrate_temperature](crate::color::YoloDetector::calibrate_temperature)一致。
+    Temperature(f32),
+    /// Platt缩放：`sigmoid(a * logit(p) + b)`
+    Platt { a: f32, b: f32 },
+    /// 自定义校准函数
+    Custom(Box<dyn Fn(f32) -> f32 + Send + Sync>),
+}
+
+impl ConfidenceCalibration {
+    /// 对单个置信度应用校准
+    pub fn apply(&self, confidence: f32) -> f32 {
+        match self {
+            ConfidenceCalibration::None => confidence,
+            ConfidenceCalibration::Temperature(t) => sigmoid(logit(confidence) / t.max(f32::EPSILON)),
+            ConfidenceCalibration::Platt { a, b } => sigmoid(a * logit(confidence) + b),
+            ConfidenceCalibration::Custom(f) => f(confidence),
         }
+    }
+    
+    /// 是否为不做任何处理的校准方式
+    pub fn is_none(&self) -> bool {
+        matches!(self, ConfidenceCalibration::None)
+    }
+}
 
-        let i_start = i * num_params;
-        let i_confidence = data[i_start + 4];
-        
-        // 置信度过滤
-        if i_confidence < confidence_threshold {
-            picked_indices[i] = true;
-            continue;
+impl std::fmt::Debug for ConfidenceCalibration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfidenceCalibration::None => write!(f, "None"),
+            ConfidenceCalibration::Temperature(t) => f.debug_tuple("Temperature").field(t).finish(),
+            ConfidenceCalibration::Platt { a, b } => f.debug_struct("Platt").field("a", a).field("b", b).finish(),
+            ConfidenceCalibration::Custom(_) => write!(f, "Custom(..)"),
         }
+    }
+}
 
-        // 计算当前框的坐标和面积
-        let i_x1 = data[i_start];
-        let i_y1 = data[i_start + 1];
-        let i_x2 = data[i_start + 2];
-        let i_y2 = data[i_start + 3];
-        let i_area = (i_x2 - i_x1) * (i_y2 - i_y1);
+/// 可保存的置信度校准方式，对应[ConfidenceCalibration]中除`Custom`以外的各种方式
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CalibrationConfig {
+    /// 不做校准
+    #[default]
+    None,
+    /// 温度缩放，参见[ConfidenceCalibration::Temperature]
+    Temperature(f32),
+    /// Platt缩放，参见[ConfidenceCalibration::Platt]
+    Platt { a: f32, b: f32 },
+}
 
-        // 如果面积为0，标记为已选择并跳过
-        if i_area <= 0.0 {
-            picked_indices[i] = true;
-            continue;
+impl ConfidenceCalibration {
+    /// 转换为可保存的形式，自定义校准函数无法保存，返回`None`
+    pub fn to_config(&self) -> Option<CalibrationConfig> {
+        match self {
+            ConfidenceCalibration::None => Some(CalibrationConfig::None),
+            ConfidenceCalibration::Temperature(t) => Some(CalibrationConfig::Temperature(*t)),
+            ConfidenceCalibration::Platt { a, b } => Some(CalibrationConfig::Platt { a: *a, b: *b }),
+            ConfidenceCalibration::Custom(_) => None,
         }
+    }
+}
 
-        // 将未被抑制的边界框添加到bounds中
-        bounds.push(Detection {
-            bbox: BoundingBox {
-                x1: i_x1 * width_scale,
-                y1: i_y1 * height_scale,
-                x2: i_x2 * width_scale,
-                y2: i_y2 * height_scale,
-            },
-            class_id: 0,
-            class_name: PERSON_CLASS_LABEL.to_string(),
-            confidence: i_confidence,
-        });
-
-        // 检查后续的框是否与当前框重叠过多
-        for j in (i + 1)..num_boxes.min(DETECTIONS_CAPACITY) {
-            if picked_indices[j] {
-                continue;
-            }
+impl CalibrationConfig {
+    /// 检查参数：温度必须为有限正数，Platt缩放的参数必须为有限值
+    /// 
+    /// # 错误处理
+    /// 参数不合法时返回[PerpleError::InvalidParameter]
+    pub fn validate(&self) -> Result<(), PerpleError> {
+        match *self {
+            CalibrationConfig::None => Ok(()),
+            CalibrationConfig::Temperature(t) if t.is_finite() && t > 0.0 => Ok(()),
+            CalibrationConfig::Temperature(t) => Err(PerpleError::InvalidParameter(format!(
+                "校准温度必须为有限正数，实际为{}", t
+            ))),
+            CalibrationConfig::Platt { a, b } if a.is_finite() && b.is_finite() => Ok(()),
+            CalibrationConfig::Platt { a, b } => Err(PerpleError::InvalidParameter(format!(
+                "Platt缩放参数必须为有限值，实际为a={} b={}", a, b
+            ))),
+        }
+    }
+}
 
-            let j_start = j * num_params;
-            let j_confidence = data[j_start + 4];
-            
-            // 提前进行置信度过滤
-            if j_confidence < confidence_threshold {
-                picked_indices[j] = true;
-                continue;
-            }
+impl From<CalibrationConfig> for ConfidenceCalibration {
+    fn from(config: CalibrationConfig) -> Self {
+        match config {
+            CalibrationConfig::None => ConfidenceCalibration::None,
+            CalibrationConfig::Temperature(t) => ConfidenceCalibration::Temperature(t),
+            CalibrationConfig::Platt { a, b } => ConfidenceCalibration::Platt { a, b },
+        }
+    }
+}
 
-            let j_x1 = data[j_start];
-            let j_y1 = data[j_start + 1];
-            let j_x2 = data[j_start + 2];
-            let j_y2 = data[j_start + 3];
-            
-            // 计算交集区域
-            let x_left = i_x1.max(j_x1);
-            let y_top = i_y1.max(j_y1);
-            let x_right = i_x2.min(j_x2);
-            let y_bottom = i_y2.min(j_y2);
+/// 将概率转换为logit，概率会被限制在(0, 1)开区间内避免无穷大
+fn logit(p: f32) -> f32 {
+    let p = p.clamp(1e-6, 1.0 - 1e-6);
+    (p / (1.0 - p)).ln()
+}
 
-            // 如果有交集
-            if x_right > x_left && y_bottom > y_top {
-                let inter_area = (x_right - x_left) * (y_bottom - y_top);
-                let j_area = (j_x2 - j_x1) * (j_y2 - j_y1);
-                
-                // 如果任一框面积为0则跳过
-                if j_area <= 0.0 {
-                    picked_indices[j] = true;
-                    continue;
-                }
-                
-                let union_area = i_area + j_area - inter_area;
-                let iou = inter_area / union_area;
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
 
-                // 如果IOU超过阈值，则抑制这个框
-                if iou >= nms_threshold {
-                    picked_indices[j] = true;
-                }
-            }
+/// 用温度缩放拟合置信度校准的温度
+/// 
+/// 在`[0.1, 10.0]`范围内二分搜索使负对数似然最小的温度`T`，
+/// 结果可用于[ConfidenceCalibration::Temperature]或
+/// [YoloDetector::calibrate_temperature](crate::color::YoloDetector::calibrate_temperature)。
+/// 
+/// 校准数据必须与训练集不同（例如单独保留的验证集），
+/// 否则模型在训练集上的过度自信会使拟合出的温度偏小。
+/// 
+/// # 参数
+/// * `raw_confidences` - 未校准的置信度（经过sigmoid的模型输出）
+/// * `ground_truth_labels` - 对应的检测是否为真阳性
+/// 
+/// # 返回值
+/// 拟合出的温度，没有样本时返回1.0（不做校准）
+/// 
+/// # Panics
+/// 两个切片长度不一致时panic
+pub fn fit_temperature_scaling(raw_confidences: &[f32], ground_truth_labels: &[bool]) -> f32 {
+    const MIN_TEMPERATURE: f64 = 0.1;
+    const MAX_TEMPERATURE: f64 = 10.0;
+    const ITERATIONS: usize = 60;
+    
+    assert_eq!(raw_confidences.len(), ground_truth_labels.len(), "置信度与标签数量不一致");
+    if raw_confidences.is_empty() {
+        return 1.0;
+    }
+    
+    // 以s = 1/T为变量时负对数似然是凸函数，其导数随s单调递增，二分求导数的零点
+    let logits: Vec<f64> = raw_confidences.iter().map(|&p| logit(p) as f64).collect();
+    let gradient = |s: f64| -> f64 {
+        logits.iter().zip(ground_truth_labels).map(|(&z, &label)| {
+            let p = 1.0 / (1.0 + (-s * z).exp());
+            (p - if label { 1.0 } else { 0.0 }) * z
+        }).sum()
+    };
+    
+    let (mut lo, mut hi) = (1.0 / MAX_TEMPERATURE, 1.0 / MIN_TEMPERATURE);
+    if gradient(lo) >= 0.0 {
+        return MAX_TEMPERATURE as f32;
+    }
+    if gradient(hi) <= 0.0 {
+        return MIN_TEMPERATURE as f32;
+    }
+    for _ in 0..ITERATIONS {
+        let mid = (lo + hi) / 2.0;
+        if gradient(mid) < 0.0 {
+            lo = mid;
+        } else {
+            hi = mid;
         }
     }
+    (2.0 / (lo + hi)) as f32
 }
 
-/// 计算两个边界框的交集面积
+/// 对模型输出中每个框的置信度原地应用校准
+/// 
+/// 应在[nms_tensor]之前调用，使后续的置信度过滤和NMS基于校准后的分数。
 /// 
 /// # 参数
-/// * `box1` - 第一个边界框
-/// * `box2` - 第二个边界框
+/// * `from_model` - 模型输出
+/// * `calibration` - 校准方式
+pub fn calibrate_tensor(
+    from_model: &mut SessionOutputs,
+    calibration: &ConfidenceCalibration,
+) -> Result<(), PerpleError> {
+    if calibration.is_none() {
+        return Ok(());
+    }
+    
+    let (shape, data) = from_model[0].try_extract_tensor_mut::<f32>()?;
+    calibrate_rows(shape, data, calibration);
+    Ok(())
+}
+
+/// [calibrate_tensor]对张量数据的处理
+fn calibrate_rows(shape: &[i64], data: &mut [f32], calibration: &ConfidenceCalibration) {
+    // 形状不合法时交由nms_tensor报告错误
+    let Some((_, num_params)) = output_rows(shape) else {
+        return;
+    };
+    for row in data.chunks_exact_mut(num_params) {
+        row[4] = calibration.apply(row[4]);
+    }
+}
+
+/// 统计多帧检测结果的置信度分布，用于挑选置信度阈值
+/// 
+/// 将`[0.0, 1.0]`等分为`num_bins`个区间，超出范围的置信度计入首尾区间，
+/// 恰好为1.0的置信度计入最后一个区间。
+/// 
+/// # 参数
+/// * `all_bounds` - 多帧检测结果，例如[YoloDetector::detect_batch](crate::color::YoloDetector::detect_batch)的返回值
+/// * `num_bins` - 区间个数，为0时返回空列表
 /// 
 /// # 返回值
-/// 返回交集面积
-fn intersection(box1: &BoundingBox, box2: &BoundingBox) -> f32 {
-    let x_left = box1.x1.max(box2.x1);
-    let y_top = box1.y1.max(box2.y1);
-    let x_right = box1.x2.min(box2.x2);
-    let y_bottom = box1.y2.min(box2.y2);
+/// 按区间顺序返回`(区间中心, 检测数)`
+pub fn confidence_histogram(all_bounds: &[Bounds], num_bins: usize) -> Vec<(f32, usize)> {
+    if num_bins == 0 {
+        return Vec::new();
+    }
+    let mut counts = vec![0usize; num_bins];
+    for detection in all_bounds.iter().flat_map(|bounds| bounds.iter()) {
+        let position = detection.confidence.clamp(0.0, 1.0) * num_bins as f32;
+        counts[(position as usize).min(num_bins - 1)] += 1;
+    }
+    let bin_width = 1.0 / num_bins as f32;
+    counts.into_iter()
+        .enumerate()
+        .map(|(i, count)| ((i as f32 + 0.5) * bin_width, count))
+        .collect()
+}
 
-    if x_right <= x_left || y_bottom <= y_top {
-        0.0
-    } else {
-        (x_right - x_left) * (y_bottom - y_top)
+/// 根据置信度分布推荐置信度阈值
+/// 
+/// 阈值`t`的累积精确率定义为置信度不低于`t`的检测占全部检测的比例。
+/// 将置信度从高到低排序后逐个降低阈值，返回累积精确率首次达到`target_precision`时的阈值，
+/// 即保留至少该比例检测的最高阈值；置信度相同的检测总是同时保留。
+/// 
+/// # 参数
+/// * `all_bounds` - 多帧检测结果
+/// * `target_precision` - 目标精确率 (0.0 - 1.0)
+/// 
+/// # 返回值
+/// 推荐的阈值；没有检测结果或目标大于1.0时返回1.0
+pub fn suggested_threshold(all_bounds: &[Bounds], target_precision: f32) -> f32 {
+    let mut confidences: Vec<f32> = all_bounds.iter()
+        .flat_map(|bounds| bounds.iter().map(|d| d.confidence))
+        .collect();
+    confidences.sort_unstable_by(|a, b| b.total_cmp(a));
+    
+    let total = confidences.len() as f32;
+    for (i, &confidence) in confidences.iter().enumerate() {
+        // 置信度相同的检测会被同一个阈值同时保留，只在一组相同值的末尾判断
+        if confidences.get(i + 1) == Some(&confidence) {
+            continue;
+        }
+        if (i + 1) as f32 / total >= target_precision {
+            return confidence;
+        }
     }
+    1.0
 }
 
-/// 计算两个边界框的并集面积
+/// COCO评估使用的IoU阈值：0.5到0.95，步长0.05
+pub const COCO_IOU_THRESHOLDS: [f32; 10] = [0.5, 0.55, 0.6, 0.65, 0.7, 0.75, 0.8, 0.85, 0.9, 0.95];
+
+/// 计算单幅图像检测结果的平均精确率（AP），使用11点插值的精确率-召回率曲线
+/// 
+/// 按类别分别计算后取平均，统计标注或检测结果中出现的全部类别。预测按置信度从高到低依次与同类别、
+/// IoU最大的标注框匹配：IoU不低于`iou_threshold`且该标注框尚未被匹配时为真阳性，否则为假阳性，
+/// 因此每个标注框最多被匹配一次。标注中没有的类别的检测结果全部为假阳性，该类别的AP为0.0。
 /// 
 /// # 参数
-/// * `box1` - 第一个边界框
-/// * `box2` - 第二个边界框
+/// * `ground_truth` - 标注框
+/// * `predictions` - 检测结果
+/// * `iou_threshold` - 判定为真阳性的IoU阈值
 /// 
 /// # 返回值
-/// 返回并集面积
-fn union(box1: &BoundingBox, box2: &BoundingBox) -> f32 {
-    let area1 = (box1.x2 - box1.x1) * (box1.y2 - box1.y1);
-    let area2 = (box2.x2 - box2.x1) * (box2.y2 - box2.y1);
-    area1 + area2 - intersection(box1, box2)
+/// AP (0.0 - 1.0)；没有标注框时，没有检测结果返回1.0，否则返回0.0
+pub fn compute_ap(ground_truth: &[Detection], predictions: &[Detection], iou_threshold: f32) -> f32 {
+    average_precision(&[(ground_truth, predictions)], iou_threshold)
 }
 
-/// 在图像上绘制检测结果
+/// 计算数据集上的平均精确率均值（mAP）
+/// 
+/// 对每个IoU阈值，汇总所有图像的检测结果计算AP（规则见[compute_ap]），再对各阈值取平均；
+/// 使用[COCO_IOU_THRESHOLDS]即为COCO的mAP@0.5:0.95。
 /// 
 /// # 参数
-/// * `image` - 原始图像
-/// * `detections` - 检测结果
+/// * `ground_truth` - 每幅图像的标注框
+/// * `predictions` - 每幅图像的检测结果，与`ground_truth`按下标对应，缺少的图像视为没有检测结果
+/// * `iou_thresholds` - IoU阈值列表
 /// 
 /// # 返回值
-/// 返回绘制了检测框的图像
-pub fn draw_detections(image: &DynamicImage, detections: &[Detection]) -> DynamicImage {
-    let (img_width, img_height) = image.dimensions();
-    let mut dt = DrawTarget::new(img_width as i32, img_height as i32);
+/// mAP (0.0 - 1.0)，`iou_thresholds`为空时返回0.0
+pub fn compute_map(ground_truth: &[Vec<Detection>], predictions: &[Vec<Detection>], iou_thresholds: &[f32]) -> f32 {
+    if iou_thresholds.is_empty() {
+        return 0.0;
+    }
+    let images: Vec<(&[Detection], &[Detection])> = (0..ground_truth.len().max(predictions.len()))
+        .map(|i| (
+            ground_truth.get(i).map_or(&[][..], Vec::as_slice),
+            predictions.get(i).map_or(&[][..], Vec::as_slice),
+        ))
+        .collect();
+    iou_thresholds.iter().map(|&threshold| average_precision(&images, threshold)).sum::<f32>()
+        / iou_thresholds.len() as f32
+}
+
+/// 汇总多幅图像`(标注框, 检测结果)`，按类别计算11点插值AP并取平均
+fn average_precision(images: &[(&[Detection], &[Detection])], iou_threshold: f32) -> f32 {
+    let mut classes: Vec<usize> = images.iter()
+        .flat_map(|(ground_truth, predictions)| ground_truth.iter().chain(predictions.iter()).map(|d| d.class_id))
+        .collect();
+    classes.sort_unstable();
+    classes.dedup();
+    if classes.is_empty() {
+        return 1.0;
+    }
     
-    // 将原始图像绘制到DrawTarget上
-    let rgba_image = image.to_rgba8();
+    let class_ap = |class_id: usize| -> f32 {
+        let mut predictions: Vec<(usize, &Detection)> = images.iter().enumerate()
+            .flat_map(|(image, (_, predictions))| predictions.iter().map(move |d| (image, d)))
+            .filter(|(_, d)| d.class_id == class_id)
+            .collect();
+        predictions.sort_by(|a, b| b.1.confidence.total_cmp(&a.1.confidence));
+        let ground_truth: Vec<Vec<&Detection>> = images.iter()
+            .map(|(ground_truth, _)| ground_truth.iter().filter(|d| d.class_id == class_id).collect())
+            .collect();
+        let total = ground_truth.iter().map(Vec::len).sum::<usize>() as f32;
+        // 没有标注框时所有预测都是假阳性
+        if total == 0.0 {
+            return 0.0;
+        }
+        let mut matched: Vec<Vec<bool>> = ground_truth.iter().map(|g| vec![false; g.len()]).collect();
+        
+        // 依次判定真阳性/假阳性，记录每个位置的(召回率, 精确率)
+        let mut true_positives = 0;
+        let mut curve = Vec::with_capacity(predictions.len());
+        for (rank, (image, prediction)) in predictions.iter().enumerate() {
+            let best = ground_truth[*image].iter().enumerate()
+                .map(|(index, g)| (index, iou(&g.bbox, &prediction.bbox)))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((index, overlap)) = best && overlap >= iou_threshold && !matched[*image][index] {
+                matched[*image][index] = true;
+                true_positives += 1;
+            }
+            curve.push((true_positives as f32 / total, true_positives as f32 / (rank + 1) as f32));
+        }
+        
+        // 11点插值：召回率不低于r时的最大精确率
+        (0..=10).map(|step| {
+            let recall = step as f32 / 10.0;
+            curve.iter().filter(|(r, _)| *r >= recall).map(|(_, p)| *p).fold(0.0, f32::max)
+        }).sum::<f32>() / 11.0
+    };
+    classes.iter().map(|&class_id| class_ap(class_id)).sum::<f32>() / classes.len() as f32
+}
+
+/// 按检测框中心点距离进行DBSCAN聚类，用于把密集人群中的检测结果归为若干团
+/// 
+/// 中心点距离不超过`eps_pixels`的两个框互为邻居；邻居数（含自身）不少于`min_samples`的框为核心点，
+/// 从核心点出发可达的框归入同一簇。不属于任何簇的框视为噪声，不出现在返回值中。
+/// 
+/// # 参数
+/// * `bounds` - 检测结果
+/// * `eps_pixels` - 邻域半径（像素）
+/// * `min_samples` - 成为核心点所需的最少邻居数（含自身），0按1处理
+/// 
+/// # 返回值
+/// 每个簇的检测索引（升序），簇按首个核心点的索引排列
+pub fn dbscan_cluster<const N: usize>(bounds: &BoundsN<N>, eps_pixels: f32, min_samples: usize) -> Vec<Vec<usize>> {
+    let centers: Vec<(f32, f32)> = bounds.iter().map(|d| d.bbox.center()).collect();
+    let eps_squared = eps_pixels * eps_pixels;
+    let neighbors = |i: usize| -> Vec<usize> {
+        let (x, y) = centers[i];
+        (0..centers.len())
+            .filter(|&j| {
+                let (dx, dy) = (centers[j].0 - x, centers[j].1 - y);
+                dx * dx + dy * dy <= eps_squared
+            })
+            .collect()
+    };
+    
+    let min_samples = min_samples.max(1);
+    let mut visited = vec![false; centers.len()];
+    let mut assigned = vec![false; centers.len()];
+    let mut clusters = Vec::new();
+    for i in 0..centers.len() {
+        if visited[i] {
+            continue;
+        }
+        visited[i] = true;
+        let mut frontier = neighbors(i);
+        if frontier.len() < min_samples {
+            continue;
+        }
+        
+        let mut members = Vec::new();
+        assigned[i] = true;
+        members.push(i);
+        while let Some(j) = frontier.pop() {
+            // 边界点只归入最先到达它的簇
+            if !assigned[j] {
+                assigned[j] = true;
+                members.push(j);
+            }
+            if !visited[j] {
+                visited[j] = true;
+                let next = neighbors(j);
+                if next.len() >= min_samples {
+                    frontier.extend(next);
+                }
+            }
+        }
+        members.sort_unstable();
+        clusters.push(members);
+    }
+    clusters
+}
+
+/// 模型输出中每个框的参数排列方式
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OutputLayout {
+    /// `[box, conf, ...]`：第5个参数为置信度，其后可能有类别、关键点或掩码系数，
+    /// 与项目自带的`nms=True`导出模型一致
+    #[default]
+    BoxConfidence,
+    /// `[box, 类别0分数, 类别1分数, ...]`：每个类别一个分数，没有单独的目标置信度
+    /// 
+    /// 取最高分数作为置信度、对应类别作为类别ID；分数相同时取ID较小的类别。
+    /// `classes`不为`None`时只在这些类别中选取。
+    ClassScores {
+        /// 参与选取的类别ID，`None`表示全部类别
+        classes: Option<Vec<usize>>,
+    },
+}
+
+impl OutputLayout {
+    /// 根据每个框的参数个数推断排列方式，无法确定时返回`None`
+    /// 
+    /// - 5、6、56、57：[OutputLayout::BoxConfidence]（检测、内置NMS、姿态模型）
+    /// - 84（4 + COCO的80个类别）：[OutputLayout::ClassScores]
+    /// 
+    /// 检测器遇到未内置NMS的原始导出（见[is_raw_output]）时不调用此函数，总是按[OutputLayout::ClassScores]处理。
+    pub fn guess(num_params: usize) -> Option<Self> {
+        match num_params {
+            n if n == 4 + COCO_CLASS_COUNT => Some(OutputLayout::ClassScores { classes: None }),
+            n if n == 5 || n == 6 || keypoint_offset(n).is_some() => Some(OutputLayout::BoxConfidence),
+            _ => None,
+        }
+    }
+}
+
+/// COCO数据集的类别数
+const COCO_CLASS_COUNT: usize = 80;
+
+/// 将类别分数排列的输出原地整理为`[box, conf, class, ...]`排列
+/// 
+/// 应在[calibrate_tensor]和[nms_tensor_with_options]之前调用，
+/// 并将返回的排列方式传给[nms_tensor_with_options]。
+/// 
+/// # 参数
+/// * `from_model` - 模型输出
+/// * `layout` - 排列方式，`None`时按[OutputLayout::guess]推断，无法推断时视为[OutputLayout::BoxConfidence]
+/// 
+/// # 返回值
+/// 实际使用的排列方式
+pub fn resolve_class_scores(
+    from_model: &mut SessionOutputs,
+    layout: Option<&OutputLayout>,
+) -> Result<OutputLayout, PerpleError> {
+    // 分割模型每行末尾为掩码系数，不属于类别分数
+    let proto_channels = match from_model.len() {
+        0 | 1 => 0,
+        _ => from_model[1].try_extract_tensor::<f32>()?.0.get(1).map_or(0, |&c| c.max(0) as usize),
+    };
+    let (shape, data) = from_model[0].try_extract_tensor_mut::<f32>()?;
+    Ok(resolve_class_score_rows(shape, data, proto_channels, layout))
+}
+
+/// [resolve_class_scores]对张量数据的处理，`proto_channels`为每行末尾的掩码系数个数
+fn resolve_class_score_rows(
+    shape: &[i64],
+    data: &mut [f32],
+    proto_channels: usize,
+    layout: Option<&OutputLayout>,
+) -> OutputLayout {
+    // 形状不合法时交由nms_tensor报告错误
+    let Some((_, num_params)) = output_rows(shape) else {
+        return layout.cloned().unwrap_or_default();
+    };
+    let layout = match layout {
+        Some(layout) => layout.clone(),
+        None => OutputLayout::guess(num_params).unwrap_or_default(),
+    };
+    let OutputLayout::ClassScores { classes } = &layout else {
+        return layout;
+    };
+    
+    let num_classes = num_params.saturating_sub(4 + proto_channels);
+    for row in data.chunks_exact_mut(num_params) {
+        let (class_id, score) = best_class(&row[4..4 + num_classes], classes.as_deref());
+        row[4] = score;
+        if num_params > 5 {
+            row[5] = class_id as f32;
+        }
+    }
+    layout
+}
+
+/// 在类别分数中选出分数最高的类别，分数相同时取ID较小者
+/// 
+/// 没有可选类别时返回`(0, 0.0)`，该框会被置信度过滤掉。
+fn best_class(scores: &[f32], classes: Option<&[usize]>) -> (usize, f32) {
+    let mut best: Option<(usize, f32)> = None;
+    let mut consider = |class_id: usize, score: f32| {
+        let better = best.is_none_or(|(best_id, best_score)| {
+            score > best_score || (score == best_score && class_id < best_id)
+        });
+        if better {
+            best = Some((class_id, score));
+        }
+    };
+    match classes {
+        Some(classes) => classes.iter()
+            .filter_map(|&class_id| scores.get(class_id).map(|&score| (class_id, score)))
+            .for_each(|(class_id, score)| consider(class_id, score)),
+        None => scores.iter().enumerate().for_each(|(class_id, &score)| consider(class_id, score)),
+    }
+    best.unwrap_or((0, 0.0))
+}
+
+/// 未设置[ClassMap]时类别ID对应的名称：0为行人，其余以`class_{id}`表示
+/// 
+/// 行人类别直接借用常量，只有其他类别需要分配内存。
+fn class_label(class_id: usize) -> Cow<'static, str> {
+    if class_id == 0 {
+        Cow::Borrowed(PERSON_CLASS_LABEL)
+    } else {
+        Cow::Owned(format!("class_{}", class_id))
+    }
+}
+
+/// 将模型输出张量中每个框的坐标原地转换为`[x1, y1, x2, y2]`格式
+/// 
+/// 应在[nms_tensor]之前调用，`format`为[CoordFormat::Xyxy]时不做任何处理。
+pub fn convert_tensor_coords(
+    from_model: &mut SessionOutputs,
+    format: CoordFormat,
+) -> Result<(), PerpleError> {
+    if format == CoordFormat::Xyxy {
+        return Ok(());
+    }
+    
+    let (shape, data) = from_model[0].try_extract_tensor_mut::<f32>()?;
+    convert_coord_rows(shape, data, format);
+    Ok(())
+}
+
+/// [convert_tensor_coords]对张量数据的处理
+fn convert_coord_rows(shape: &[i64], data: &mut [f32], format: CoordFormat) {
+    // 形状不合法时交由nms_tensor报告错误
+    let Some((_, num_params)) = output_rows(shape) else {
+        return;
+    };
+    for row in data.chunks_exact_mut(num_params) {
+        let converted = format.to_xyxy([row[0], row[1], row[2], row[3]]);
+        row[..4].copy_from_slice(&converted);
+    }
+}
+
+/// 输出是否为未内置NMS的原始导出排列`[1, 参数个数, 锚点数]`
+/// 
+/// YOLOv8/v11的原始导出（如`[1, 84, 8400]`）每一列是一个框，参数个数远小于锚点数；
+/// 中间一维不少于[MIN_OUTPUT_PARAMS]且小于最后一维时视为这种排列，
+/// 否则按每行一个框的`[1, 框数量, 参数个数]`处理。
+pub fn is_raw_output(shape: &[i64]) -> bool {
+    shape.len() == 3 && shape[1] >= MIN_OUTPUT_PARAMS as i64 && shape[2] > shape[1]
+}
+
+/// 按`[1, 框数量, 参数个数]`解释输出形状，返回`(框数量, 参数个数)`
+/// 
+/// 形状不是3维、参数个数少于[MIN_OUTPUT_PARAMS]，或是尚未转置的原始导出排列时返回`None`。
+fn output_rows(shape: &[i64]) -> Option<(usize, usize)> {
+    if shape.len() != 3 || shape[2] < MIN_OUTPUT_PARAMS as i64 || is_raw_output(shape) {
+        return None;
+    }
+    Some((shape[1].max(0) as usize, shape[2] as usize))
+}
+
+/// 将原始导出排列`[参数个数, 锚点数]`的数据转置为每行一个框的`[锚点数, 参数个数]`
+fn transpose_params(data: &[f32], num_params: usize, num_anchors: usize) -> Vec<f32> {
+    let mut rows = vec![0.0; num_params * num_anchors];
+    for (param, column) in data.chunks_exact(num_anchors).take(num_params).enumerate() {
+        for (anchor, &value) in column.iter().enumerate() {
+            rows[anchor * num_params + param] = value;
+        }
+    }
+    rows
+}
+
+/// 将未内置NMS的原始导出输出`[1, 参数个数, 锚点数]`转置为`[1, 锚点数, 参数个数]`
+/// 
+/// 其余后处理函数（[resolve_class_scores]、[nms_tensor]等）按每行一个框读取输出，
+/// 应在它们之前调用。输出不是原始导出排列（见[is_raw_output]）时不做任何处理。
+/// 原始导出的坐标为`[cx, cy, w, h]`，应配合[CoordFormat::CxCyWh]使用。
+/// 
+/// # 返回值
+/// 是否进行了转置
+pub fn transpose_raw_output(from_model: &mut SessionOutputs) -> Result<bool, PerpleError> {
+    let (shape, data) = from_model[0].try_extract_tensor::<f32>()?;
+    if !is_raw_output(shape) {
+        return Ok(false);
+    }
+    let (num_params, num_anchors) = (shape[1] as usize, shape[2] as usize);
+    let rows = transpose_params(data, num_params, num_anchors);
+    from_model[0] = Tensor::from_array(([1, num_anchors, num_params], rows))?.into_dyn();
+    Ok(true)
+}
+
+/// 由掩码系数与原型掩码解码出检测框内的实例分割掩码
+/// 
+/// 计算`sigmoid(系数 · 原型)`，以0.5为阈值二值化，
+/// 再用最近邻插值放大到原始图像坐标并裁剪到检测框范围内。
+/// 
+/// # 参数
+/// * `coefficients` - 检测结果的掩码系数，长度等于原型通道数
+/// * `protos` - 原型掩码，形状为[通道数, 高*宽]
+/// * `proto_size` - 原型掩码的(高, 宽)
+/// * `bbox` - 原始图像坐标系下的检测框
+/// * `message` - 图像缩放信息
+pub fn decode_mask(
+    coefficients: &[f32],
+    protos: ArrayView2<f32>,
+    proto_size: (usize, usize),
+    bbox: &BoundingBox,
+    message: &ScaleMessage,
+) -> Mask {
+    let (proto_height, proto_width) = proto_size;
+    let (img_width, img_height) = (message.o_width, message.o_height);
+    // 原型掩码覆盖整个模型输入（含填充），像素中心先映射到模型输入坐标
+    let (input_width, input_height) = message.input_size();
+    let to_model = Affine2::letterbox_inverse(message).inverse().unwrap_or_default();
+    
+    let x1 = bbox.x1.max(0.0).floor() as u32;
+    let y1 = bbox.y1.max(0.0).floor() as u32;
+    let x2 = (bbox.x2.ceil().max(0.0) as u32).min(img_width);
+    let y2 = (bbox.y2.ceil().max(0.0) as u32).min(img_height);
+    let mut mask = Mask::new(x1, y1, x2.saturating_sub(x1), y2.saturating_sub(y1));
+    if mask.width == 0 || mask.height == 0 || proto_height == 0 || proto_width == 0 {
+        return mask;
+    }
+    
+    // sigmoid(v) > 0.5 等价于 v > 0，无需显式计算sigmoid
+    let logits = ArrayView1::from(coefficients).dot(&protos);
+    for py in y1..y2 {
+        for px in x1..x2 {
+            let (model_x, model_y) = to_model.apply(px as f32 + 0.5, py as f32 + 0.5);
+            let proto_x = ((model_x * proto_width as f32 / input_width as f32) as usize).min(proto_width - 1);
+            let proto_y = ((model_y * proto_height as f32 / input_height as f32) as usize).min(proto_height - 1);
+            if logits[proto_y * proto_width + proto_x] > 0.0 {
+                mask.set(px, py, true);
+            }
+        }
+    }
+    mask
+}
+
+/// 对模型输出张量执行置信度过滤和NMS，结果写入`bounds`
+/// 
+/// # 参数
+/// * `from_model` - 模型输出，形状为[1, num_boxes, num_params]；
+///   分割模型的第二个输出为原型掩码[1, 通道数, 高, 宽]，此时每行末尾为掩码系数
+/// * `bounds` - 输出结果容器
+/// * `message` - 图像缩放信息
+/// * `picked_indices` - NMS使用的缓存数组
+/// * `confidence_threshold` - 全局置信度阈值
+/// * `nms_threshold` - NMS阈值
+/// * `zones` - 置信度阈值区域，检测框中心点所在区域的阈值优先于全局阈值
+pub fn nms_tensor<const N: usize>(
+    from_model: &mut SessionOutputs,
+    bounds: &mut BoundsN<N>,
+    message: &ScaleMessage,
+    picked_indices: &mut [bool; N],
+    confidence_threshold: f32,
+    nms_threshold: f32,
+    zones: &[ThresholdZone],
+) -> Result<(), PerpleError> {
+    nms_tensor_with_options(
+        from_model,
+        bounds,
+        message,
+        picked_indices,
+        confidence_threshold,
+        &NmsOptions::new(nms_threshold).with_max_detections(Some(N)),
+        zones,
+        &OutputLayout::BoxConfidence,
+    )
+}
+
+/// 与[nms_tensor]相同，但使用`nms_options`指定的抑制准则（IoU/IoMin、包含率）
+/// 
+/// `layout`为[OutputLayout::ClassScores]时，输出应已由[resolve_class_scores]整理，
+/// 每行第6个参数为类别ID。NMS按类别进行，不同类别的框即使重叠也互不抑制。
+#[allow(clippy::too_many_arguments)]
+pub fn nms_tensor_with_options<const N: usize>(
+    from_model: &mut SessionOutputs,
+    bounds: &mut BoundsN<N>,
+    message: &ScaleMessage,
+    picked_indices: &mut [bool; N],
+    confidence_threshold: f32,
+    nms_options: &NmsOptions,
+    zones: &[ThresholdZone],
+    layout: &OutputLayout,
+) -> Result<(), PerpleError> {
+    nms_tensor_labeled(from_model, bounds, message, picked_indices, confidence_threshold, nms_options, zones, layout, None)
+}
+
+/// 与[nms_tensor_with_options]相同，`class_map`不为`None`时按其填写类别名称
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn nms_tensor_labeled<const N: usize>(
+    from_model: &mut SessionOutputs,
+    bounds: &mut BoundsN<N>,
+    message: &ScaleMessage,
+    picked_indices: &mut [bool; N],
+    confidence_threshold: f32,
+    nms_options: &NmsOptions,
+    zones: &[ThresholdZone],
+    layout: &OutputLayout,
+    class_map: Option<&ClassMap>,
+) -> Result<(), PerpleError> {
+    // 从SessionOutputs中直接提取张量数据；分割模型的第二个输出为原型掩码
+    let mut values = from_model.values_mut();
+    let mut output_tensor = values.next().ok_or_else(|| PerpleError::IncompatibleModel {
+        input_shape: Vec::new(),
+        output_shape: Vec::new(),
+        reason: "模型没有输出".to_string(),
+    })?;
+    let proto_value = values.next();
+    let protos = match &proto_value {
+        Some(value) => Some(value.try_extract_tensor::<f32>()?),
+        None => None,
+    };
+    let (shape, data) = output_tensor.try_extract_tensor_mut::<f32>()?;
+    let rows = ModelRows { shape, data, protos: protos.map(|(shape, data)| (&shape[..], data)) };
+    nms_rows(rows, bounds, message, picked_indices, confidence_threshold, nms_options, zones, layout, class_map)
+}
+
+/// 一次推理的输出张量数据，由[nms_rows]处理
+struct ModelRows<'a> {
+    /// 主输出的形状
+    shape: &'a [i64],
+    /// 主输出的数据，处理过程中会被原地排序
+    data: &'a mut [f32],
+    /// 分割模型的原型掩码`(形状, 数据)`
+    protos: Option<(&'a [i64], &'a [f32])>,
+}
+
+/// [nms_tensor_labeled]对张量数据的处理
+#[allow(clippy::too_many_arguments)]
+fn nms_rows<const N: usize>(
+    rows: ModelRows<'_>,
+    bounds: &mut BoundsN<N>,
+    message: &ScaleMessage,
+    picked_indices: &mut [bool; N],
+    confidence_threshold: f32,
+    nms_options: &NmsOptions,
+    zones: &[ThresholdZone],
+    layout: &OutputLayout,
+    class_map: Option<&ClassMap>,
+) -> Result<(), PerpleError> {
+    bounds.clear();
+    let ModelRows { shape, data, protos } = rows;
+    
+    // 模型输入坐标到原始图像坐标的变换
+    let to_image = Affine2::letterbox_inverse(message);
+    
+    // 校验输出形状，避免对不兼容模型的输出越界索引
+    let Some((num_boxes, num_params)) = output_rows(shape) else {
+        let reason = if is_raw_output(shape) {
+            "输出为未内置NMS的原始导出排列[1, 参数个数, 锚点数]，需先用transpose_raw_output转置".to_string()
+        } else {
+            format!("输出应为[1, 框数量, 参数个数]且参数个数不少于{}", MIN_OUTPUT_PARAMS)
+        };
+        return Err(PerpleError::IncompatibleModel {
+            input_shape: Vec::new(),
+            output_shape: shape.to_vec(),
+            reason,
+        });
+    };
+    // 数据长度与形状不符时只处理完整的行
+    let num_boxes = num_boxes.min(data.len() / num_params);
+    
+    // 原型掩码形状为[1, 通道数, 高, 宽]，每行末尾的`通道数`个参数为掩码系数
+    let protos = match protos {
+        Some((proto_shape, proto_data)) => {
+            if proto_shape.len() != 4 || proto_shape[1] as usize + 6 > num_params {
+                return Err(PerpleError::IncompatibleModel {
+                    input_shape: Vec::new(),
+                    output_shape: proto_shape.to_vec(),
+                    reason: format!("原型掩码应为[1, 通道数, 高, 宽]且通道数不超过{}", num_params.saturating_sub(6)),
+                });
+            }
+            let (channels, proto_height, proto_width) =
+                (proto_shape[1] as usize, proto_shape[2] as usize, proto_shape[3] as usize);
+            let view = ArrayView2::from_shape((channels, proto_height * proto_width), proto_data)
+                .map_err(|e| PerpleError::IncompatibleModel {
+                    input_shape: Vec::new(),
+                    output_shape: proto_shape.to_vec(),
+                    reason: format!("原型掩码数据长度不匹配: {}", e),
+                })?;
+            Some((view, (proto_height, proto_width)))
+        }
+        None => None,
+    };
+    
+    // 排序会打乱行顺序，需要记录原始行号时先保存一份排序前的数据
+    let mut source_rows = nms_options.record_source_index.then(|| SourceRows::new(data, num_params));
+    
+    // 按置信度排序，将置信度高的框排在前面；NMS只会考察前N个框，只需部分排序（组数少时用插入排序）
+    partial_group_sort_by(data, num_params, 4, N, |a, b| 
+        b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+    // 统一NMS考察范围内各框的角点顺序，角点颠倒的框不应因面积为负被丢弃
+    for row in data.chunks_exact_mut(num_params).take(N) {
+        normalize_corners(row);
+    }
+
+    // 初始化picked_indices数组，但不超过结果容量N的大小
+    picked_indices.fill(false);
+    
+    // 根据框中心点（换算到原始图像坐标）确定该框适用的置信度阈值
+    let threshold_at = |start: usize| -> f32 {
+        if zones.is_empty() {
+            return confidence_threshold;
+        }
+        let center = to_image.apply(
+            (data[start] + data[start + 2]) / 2.0,
+            (data[start + 1] + data[start + 3]) / 2.0,
+        );
+        zone_threshold(zones, center, confidence_threshold)
+    };
+
+    // 每行中类别ID所在的列，没有类别列的输出全部视为类别0
+    let class_column = match layout {
+        OutputLayout::ClassScores { .. } => (num_params > 5).then_some(5),
+        // 内置NMS导出的检测`[box, conf, class]`、姿态`[box, conf, class, 关键点...]`
+        // 和分割`[box, conf, class, 掩码系数...]`模型的第6个参数为类别ID
+        OutputLayout::BoxConfidence => {
+            let mask_channels = protos.as_ref().map(|(view, _)| view.nrows());
+            let has_class = num_params == 6
+                || keypoint_offset(num_params) == Some(6)
+                || mask_channels.is_some_and(|channels| num_params == 6 + channels);
+            has_class.then_some(5)
+        }
+    };
+    let class_at = |row: &[f32]| class_column.map_or(0, |column| row[column] as usize);
+
+    // NMS处理，只在同一类别的框之间抑制
+    let candidates = nms_options.candidate_limit(num_boxes, N);
+    let max_detections = nms_options.detection_limit(N);
+    for i in 0..candidates {
+        // 结果已达上限，剩余的框无需再考察
+        if bounds.len() >= max_detections {
+            break;
+        }
+        // 如果当前框已经被抑制，则跳过
+        if picked_indices[i] {
+            continue;
+        }
+
+        let i_start = i * num_params;
+        let i_confidence = data[i_start + 4];
+        
+        // 置信度过滤
+        // 校准函数可能产生NaN，NaN不应通过过滤
+        if i_confidence.is_nan() || i_confidence < threshold_at(i_start) {
+            picked_indices[i] = true;
+            continue;
+        }
+
+        // 计算当前框的坐标和面积
+        let i_box = BoundingBox::new(data[i_start], data[i_start + 1], data[i_start + 2], data[i_start + 3]);
+
+        // 如果面积为0，标记为已选择并跳过
+        if signed_area(&i_box) <= 0.0 {
+            picked_indices[i] = true;
+            continue;
+        }
+
+        // 将未被抑制的边界框还原到原始图像坐标后添加到bounds中
+        let row = &data[i_start..i_start + num_params];
+        let class_scores = matches!(layout, OutputLayout::ClassScores { .. });
+        let class_id = class_at(row);
+        let mut detection = Detection {
+            bbox: i_box,
+            class_id,
+            class_name: class_map.map_or_else(|| class_label(class_id), |map| map.label(class_id)),
+            confidence: i_confidence,
+            keypoints: if protos.is_some() || class_scores { None } else { parse_keypoints(row, 1.0, 1.0) },
+            mask: None,
+            track_id: None,
+            source_index: source_rows.as_mut().and_then(|rows| rows.take(row)),
+            attributes: None,
+        };
+        detection.transform(&to_image);
+        detection.mask = protos.as_ref().map(|(view, proto_size)| {
+            let coefficients = &row[num_params - view.nrows()..];
+            decode_mask(coefficients, view.view(), *proto_size, &detection.bbox, message)
+        });
+        bounds.push(detection);
+
+        // 检查后续的框是否与当前框重叠过多
+        for (j, picked) in picked_indices.iter_mut().enumerate().take(candidates).skip(i + 1) {
+            if *picked {
+                continue;
+            }
+
+            let j_start = j * num_params;
+            let j_confidence = data[j_start + 4];
+            
+            // 提前进行置信度过滤
+            if j_confidence.is_nan() || j_confidence < threshold_at(j_start) {
+                *picked = true;
+                continue;
+            }
+
+            // 不同类别的框互不抑制
+            if class_at(&data[j_start..j_start + num_params]) != class_id {
+                continue;
+            }
+
+            let j_box = BoundingBox::new(data[j_start], data[j_start + 1], data[j_start + 2], data[j_start + 3]);
+
+            // 如果有交集
+            if intersection(&i_box, &j_box) > 0.0 {
+                // 如果任一框面积为0则跳过
+                if signed_area(&j_box) <= 0.0 {
+                    *picked = true;
+                    continue;
+                }
+
+                // 如果重叠度或包含率超过阈值，则抑制这个框
+                if nms_options.suppresses(&i_box, &j_box) {
+                    *picked = true;
+                }
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// 排序前的模型输出副本，用于查找排序后的行在原始输出中的行号
+struct SourceRows {
+    rows: Vec<f32>,
+    num_params: usize,
+    /// 已被认领的行，内容完全相同的多行依次对应
+    claimed: Vec<bool>,
+}
+
+impl SourceRows {
+    /// 复制排序前的输出，角点顺序按[normalize_corners]统一，与NMS看到的行一致
+    fn new(data: &[f32], num_params: usize) -> Self {
+        let mut rows = data.to_vec();
+        for row in rows.chunks_exact_mut(num_params) {
+            normalize_corners(row);
+        }
+        let claimed = vec![false; rows.len() / num_params];
+        Self { rows, num_params, claimed }
+    }
+    
+    /// 查找与`row`逐位相同且尚未被认领的第一行，返回其行号
+    fn take(&mut self, row: &[f32]) -> Option<usize> {
+        let index = self.rows.chunks_exact(self.num_params).enumerate().position(|(index, candidate)| {
+            !self.claimed[index] && candidate.iter().zip(row).all(|(a, b)| a.to_bits() == b.to_bits())
+        })?;
+        self.claimed[index] = true;
+        Some(index)
+    }
+}
+
+/// 将一行模型输出的前四个值（`[x1, y1, x2, y2]`）按`x1 <= x2`、`y1 <= y2`重新排列
+/// 
+/// 与[BoundingBox::normalized]的规则相同，直接作用于原始张量数据。
+fn normalize_corners(row: &mut [f32]) {
+    if row[0] > row[2] {
+        row.swap(0, 2);
+    }
+    if row[1] > row[3] {
+        row.swap(1, 3);
+    }
+}
+
+/// 计算两个边界框的交集面积
+/// 
+/// # 参数
+/// * `box1` - 第一个边界框
+/// * `box2` - 第二个边界框
+/// 
+/// # 返回值
+/// 返回交集面积，任一框角点颠倒时为0.0
+pub(crate) fn intersection(box1: &BoundingBox, box2: &BoundingBox) -> f32 {
+    let x_left = box1.x1.max(box2.x1);
+    let y_top = box1.y1.max(box2.y1);
+    let x_right = box1.x2.min(box2.x2);
+    let y_bottom = box1.y2.min(box2.y2);
+
+    if x_right <= x_left || y_bottom <= y_top {
+        0.0
+    } else {
+        (x_right - x_left) * (y_bottom - y_top)
+    }
+}
+
+/// 按`(x2 - x1) * (y2 - y1)`计算的面积，角点颠倒时为负
+fn signed_area(bbox: &BoundingBox) -> f32 {
+    (bbox.x2 - bbox.x1) * (bbox.y2 - bbox.y1)
+}
+
+/// 计算两个边界框的交并比，并集面积不大于0时返回0.0
+/// 
+/// 项目中唯一的IoU实现，[BoundingBox::iou]、NMS与AP计算都使用这里的结果；
+/// 角点颠倒的框交集为0，IoU也为0。
+pub(crate) fn iou(box1: &BoundingBox, box2: &BoundingBox) -> f32 {
+    let inter = intersection(box1, box2);
+    if inter <= 0.0 {
+        return 0.0;
+    }
+    let union_area = signed_area(box1) + signed_area(box2) - inter;
+    if union_area <= 0.0 { 0.0 } else { inter / union_area }
+}
+
+/// 计算交集面积与两个框中较小面积之比（IoMin），较小面积不大于0时返回0.0
+pub(crate) fn intersection_over_min(box1: &BoundingBox, box2: &BoundingBox) -> f32 {
+    let min_area = signed_area(box1).min(signed_area(box2));
+    if min_area <= 0.0 {
+        return 0.0;
+    }
+    intersection(box1, box2) / min_area
+}
+
+/// 计算`inner`位于`outer`内部的面积占`inner`面积的比例，`inner`面积不大于0时返回0.0
+pub(crate) fn containment_ratio(inner: &BoundingBox, outer: &BoundingBox) -> f32 {
+    let inner_area = signed_area(inner);
+    if inner_area <= 0.0 {
+        return 0.0;
+    }
+    intersection(inner, outer) / inner_area
+}
+
+/// 检测结果绘制选项
+#[derive(Debug, Clone, Default)]
+pub struct DrawOptions {
+    /// 只绘制这些类别，`None`表示不限制；与`hide_classes`同时设置时优先生效
+    pub show_classes: Option<Vec<usize>>,
+    /// 不绘制这些类别
+    pub hide_classes: Option<Vec<usize>>,
+    /// 检测框颜色方案
+    pub style: DrawStyle,
+}
+
+/// 检测框颜色方案
+/// 
+/// 所有方案的颜色都只由类别决定，相同的输入图像和检测结果总是绘制出逐字节相同的图像。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DrawStyle {
+    /// 固定配色：person类别为青色，其余类别为红色
+    #[default]
+    Fixed,
+    /// 按种子为每个类别生成颜色，同一种子在任何平台和运行中得到相同的调色板
+    Seeded(u64),
+}
+
+impl DrawStyle {
+    /// 使用指定种子的按类别配色
+    pub fn deterministic(seed: u64) -> Self {
+        DrawStyle::Seeded(seed)
+    }
+    
+    /// 获取类别对应的颜色
+    fn color(&self, class_id: usize) -> SolidSource {
+        match self {
+            DrawStyle::Fixed => match class_id {
+                0 => SolidSource { r: 0x00, g: 0xFF, b: 0xFF, a: 0xFF }, // 青色 - person类别
+                _ => SolidSource { r: 0xFF, g: 0x00, b: 0x00, a: 0xFF }, // 红色 - 其他类别
+            },
+            DrawStyle::Seeded(seed) => {
+                // splitmix64，只使用整数运算，结果与平台无关
+                let mut z = seed.wrapping_add((class_id as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+                z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                z ^= z >> 31;
+                let [r, g, b, ..] = z.to_le_bytes();
+                SolidSource { r, g, b, a: 0xFF }
+            }
+        }
+    }
+}
+
+impl DrawOptions {
+    /// 判断指定类别的检测结果是否需要绘制
+    pub fn should_draw(&self, class_id: usize) -> bool {
+        match (&self.show_classes, &self.hide_classes) {
+            (Some(show), _) => show.contains(&class_id),
+            (None, Some(hide)) => !hide.contains(&class_id),
+            (None, None) => true,
+        }
+    }
+}
+
+/// 在图像上绘制检测结果
+/// 
+/// # 参数
+/// * `image` - 原始图像
+/// * `detections` - 检测结果
+/// 
+/// # 返回值
+/// 返回绘制了检测框的图像
+pub fn draw_detections(image: &DynamicImage, detections: &[Detection]) -> DynamicImage {
+    draw_detections_with_options(image, detections, &DrawOptions::default())
+}
+
+/// 按绘制选项在图像上绘制检测结果
+/// 
+/// # 参数
+/// * `image` - 原始图像
+/// * `detections` - 检测结果
+/// * `options` - 绘制选项（类别过滤等）
+/// 
+/// # 返回值
+/// 返回绘制了检测框的图像
+pub fn draw_detections_with_options(image: &DynamicImage, detections: &[Detection], options: &DrawOptions) -> DynamicImage {
+    let mut dt = image_to_draw_target(image);
+    draw_shapes(&mut dt, detections, options);
+    draw_target_to_image(&dt)
+}
+
+/// 在图像上绘制检测结果，绘制前检查图像尺寸与产生结果的图像是否一致
+/// 
+/// 结果带有[原始图像尺寸](BoundsN::source_dims)且与`image`的尺寸不同时，说明结果来自另一幅图像
+/// （例如从输出流读到的不是当前帧的结果），此时不绘制并返回错误。
+/// 结果没有尺寸信息时直接绘制。有意将结果绘制到缩放后的图像上时，请使用[draw_detections]。
+/// 
+/// # 错误处理
+/// - 结果不在[OutputSpace::OriginalPixels]坐标空间时返回[PerpleError::InvalidParameter]，
+///   需要先用[BoundsN::convert_space]转换
+/// - 尺寸不一致时返回[PerpleError::ShapeMismatch]，`expected`为结果记录的尺寸
+pub fn draw_detections_checked<const N: usize>(image: &DynamicImage, bounds: &BoundsN<N>) -> Result<DynamicImage, PerpleError> {
+    if bounds.space() != OutputSpace::OriginalPixels {
+        return Err(PerpleError::InvalidParameter(format!(
+            "只能绘制原始图像像素坐标的结果，当前坐标空间为{:?}", bounds.space()
+        )));
+    }
+    if let Some(expected) = bounds.source_dims() {
+        let actual = image.dimensions();
+        if expected != actual {
+            return Err(PerpleError::ShapeMismatch { expected, actual });
+        }
+    }
+    Ok(draw_detections(image, bounds.as_slice()))
+}
+
+/// 在图像上绘制旋转边界框
+/// 
+/// 与[draw_detections]使用相同的固定配色：标签为`person`的框为青色，其余为红色。
+/// 
+/// # 参数
+/// * `image` - 原始图像
+/// * `obbs` - 旋转边界框及其类别标签和置信度
+/// 
+/// # 返回值
+/// 返回绘制了旋转框的图像
+pub fn draw_obb_detections(image: &DynamicImage, obbs: &[(OrientedBoundingBox, &str, f32)]) -> DynamicImage {
+    let mut dt = image_to_draw_target(image);
+    let style = StrokeStyle {
+        join: LineJoin::Round,
+        width: 2.0,
+        ..StrokeStyle::default()
+    };
+    
+    for (obb, label, _confidence) in obbs {
+        let corners = obb.to_corners();
+        let mut pb = PathBuilder::new();
+        pb.move_to(corners[0].0, corners[0].1);
+        for &(x, y) in &corners[1..] {
+            pb.line_to(x, y);
+        }
+        pb.close();
+        
+        let class_id = if *label == PERSON_CLASS_LABEL { 0 } else { 1 };
+        let color = DrawStyle::Fixed.color(class_id);
+        dt.stroke(&pb.finish(), &Source::Solid(color), &style, &RasterOptions::default());
+    }
+    
+    draw_target_to_image(&dt)
+}
+
+/// 在图像上绘制跟踪目标的运动轨迹
+/// 
+/// 每条轨迹按其最近一次出现时的类别取色，绘制为折线；越早的线段越透明，
+/// 最新的线段不透明。超出图像范围的部分被裁剪。只有一个中心点的轨迹不绘制。
+/// 
+/// # 参数
+/// * `image` - 原始图像
+/// * `trajectories` - 轨迹记录器
+/// * `style` - 颜色方案
+/// 
+/// # 返回值
+/// 返回绘制了轨迹的图像
+pub fn draw_trajectories(image: &DynamicImage, trajectories: &TrajectoryRenderer, style: &DrawStyle) -> DynamicImage {
+    let mut dt = image_to_draw_target(image);
+    let stroke = StrokeStyle {
+        join: LineJoin::Round,
+        width: 2.0,
+        ..StrokeStyle::default()
+    };
+    
+    for track in trajectories.tracks() {
+        let segments = track.points.len().saturating_sub(1);
+        let color = style.color(track.class_id);
+        for (index, (from, to)) in track.points.iter().zip(track.points.iter().skip(1)).enumerate() {
+            let alpha = ((index + 1) * 0xFF / segments) as u8;
+            // 坐标过大时raqote的定点运算会溢出，先裁剪到图像附近
+            let Some((from, to)) = clip_segment(*from, *to, dt.width() as f32, dt.height() as f32, stroke.width) else { continue };
+            let mut pb = PathBuilder::new();
+            pb.move_to(from.0, from.1);
+            pb.line_to(to.0, to.1);
+            let source = Source::Solid(SolidSource::from_unpremultiplied_argb(alpha, color.r, color.g, color.b));
+            dt.stroke(&pb.finish(), &source, &stroke, &RasterOptions::default());
+        }
+    }
+    
+    draw_target_to_image(&dt)
+}
+
+/// 将线段裁剪到向外扩展`margin`的图像矩形内，线段完全在矩形外时返回`None`
+fn clip_segment(from: (f32, f32), to: (f32, f32), width: f32, height: f32, margin: f32) -> Option<((f32, f32), (f32, f32))> {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    // Liang-Barsky：依次按左、右、上、下四条边收缩参数区间
+    for (p, q) in [
+        (-dx, from.0 + margin),
+        (dx, width + margin - from.0),
+        (-dy, from.1 + margin),
+        (dy, height + margin - from.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    (t0 <= t1).then_some(((from.0 + t0 * dx, from.1 + t0 * dy), (from.0 + t1 * dx, from.1 + t1 * dy)))
+}
+
+/// 创建与图像同尺寸的DrawTarget并绘制图像内容
+fn image_to_draw_target(image: &DynamicImage) -> DrawTarget {
+    let (img_width, img_height) = image.dimensions();
+    let mut dt = DrawTarget::new(img_width as i32, img_height as i32);
+    
+    // 将原始图像绘制到DrawTarget上
+    let rgba_image = image.to_rgba8();
     let image_data: Vec<u32> = rgba_image.chunks(4).map(|pixel| {
         let b = pixel[2];
         let g = pixel[1];
@@ -423,9 +1863,139 @@ pub fn draw_detections(image: &DynamicImage, detections: &[Detection]) -> Dynami
         data: &image_data,
     };
     
-    dt.draw_image_at(0.0, 0.0, &img, &DrawOptions::new());
+    dt.draw_image_at(0.0, 0.0, &img, &RasterOptions::new());
+    dt
+}
+
+/// 将DrawTarget转换回RGBA图像
+fn draw_target_to_image(dt: &DrawTarget) -> DynamicImage {
+    let (img_width, img_height) = (dt.width() as u32, dt.height() as u32);
+    let pixels: Vec<u8> = dt.get_data().iter().flat_map(|&pixel| {
+        let bytes = pixel.to_le_bytes();
+        vec![bytes[2], bytes[1], bytes[0], bytes[3]] // BGRA to RGBA
+    }).collect();
+    
+    DynamicImage::ImageRgba8(
+        image::ImageBuffer::from_raw(img_width, img_height, pixels)
+            .expect("Failed to create image from rendered data")
+    )
+}
+
+/// 原始帧缓冲区的像素格式（每通道8位）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Rgba,
+    Bgra,
+    Rgb,
+    Bgr,
+}
+
+impl PixelFormat {
+    /// 每个像素的字节数
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::Rgba | PixelFormat::Bgra => 4,
+            PixelFormat::Rgb | PixelFormat::Bgr => 3,
+        }
+    }
+    
+    /// 像素字节转换为`(r, g, b, a)`
+    fn unpack(&self, pixel: &[u8]) -> (u8, u8, u8, u8) {
+        match self {
+            PixelFormat::Rgba => (pixel[0], pixel[1], pixel[2], pixel[3]),
+            PixelFormat::Bgra => (pixel[2], pixel[1], pixel[0], pixel[3]),
+            PixelFormat::Rgb => (pixel[0], pixel[1], pixel[2], 0xFF),
+            PixelFormat::Bgr => (pixel[2], pixel[1], pixel[0], 0xFF),
+        }
+    }
+    
+    /// 将`(r, g, b, a)`写回像素字节，无透明通道的格式忽略`a`
+    fn pack(&self, pixel: &mut [u8], (r, g, b, a): (u8, u8, u8, u8)) {
+        match self {
+            PixelFormat::Rgba => pixel.copy_from_slice(&[r, g, b, a]),
+            PixelFormat::Bgra => pixel.copy_from_slice(&[b, g, r, a]),
+            PixelFormat::Rgb => pixel.copy_from_slice(&[r, g, b]),
+            PixelFormat::Bgr => pixel.copy_from_slice(&[b, g, r]),
+        }
+    }
+}
+
+/// 直接在原始帧缓冲区上绘制检测结果
+/// 
+/// 适用于视频流水线：不需要先把帧转换为`DynamicImage`，绘制结果原地写回缓冲区。
+/// 只有被绘制内容覆盖的像素会被写回，其余像素（包括半透明像素和行尾填充）保持原样。
+/// 
+/// # 参数
+/// * `buffer` - 帧数据，按行存储
+/// * `stride` - 每行的字节数（可包含行尾填充），不小于`width * 每像素字节数`
+/// * `width` - 帧宽度
+/// * `height` - 帧高度
+/// * `format` - 像素格式
+/// * `detections` - 检测结果
+/// * `options` - 绘制选项
+/// 
+/// # 错误处理
+/// 宽度或高度为0、`stride`小于一行像素所需的字节数，或缓冲区长度不等于`stride * height`时
+/// 返回[PerpleError::InvalidParameter]
+pub fn draw_detections_on_frame(
+    buffer: &mut [u8],
+    stride: usize,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    detections: &[Detection],
+    options: &DrawOptions,
+) -> Result<(), PerpleError> {
+    let bpp = format.bytes_per_pixel();
+    let row_bytes = width as usize * bpp;
+    if width == 0 || height == 0 {
+        return Err(PerpleError::InvalidParameter(format!("帧尺寸不能为0: {}x{}", width, height)));
+    }
+    if stride < row_bytes {
+        return Err(PerpleError::InvalidParameter(format!(
+            "行跨度{}小于一行像素所需的{}字节", stride, row_bytes
+        )));
+    }
+    if buffer.len() != stride * height as usize {
+        return Err(PerpleError::InvalidParameter(format!(
+            "帧缓冲区长度{}与行跨度{}x高度{}不一致", buffer.len(), stride, height
+        )));
+    }
+    
+    let mut dt = DrawTarget::new(width as i32, height as i32);
+    {
+        // raqote使用预乘透明度的0xAARRGGBB格式
+        let data = dt.get_data_mut();
+        for (row, target) in buffer.chunks_exact(stride).zip(data.chunks_exact_mut(width as usize)) {
+            for (pixel, value) in row[..row_bytes].chunks_exact(bpp).zip(target) {
+                let (r, g, b, a) = format.unpack(pixel);
+                let premultiply = |c: u8| (c as u32 * a as u32 / 255) as u8;
+                *value = u32::from_be_bytes([a, premultiply(r), premultiply(g), premultiply(b)]);
+            }
+        }
+    }
+    
+    // 预乘再还原会损失半透明像素的精度，因此只写回绘制改变了的像素
+    let original = dt.get_data().to_vec();
+    draw_shapes(&mut dt, detections, options);
+    
+    let rows = dt.get_data().chunks_exact(width as usize).zip(original.chunks_exact(width as usize));
+    for (row, (source, original)) in buffer.chunks_exact_mut(stride).zip(rows) {
+        for ((pixel, &value), &before) in row[..row_bytes].chunks_exact_mut(bpp).zip(source).zip(original) {
+            if value == before {
+                continue;
+            }
+            let [a, r, g, b] = value.to_be_bytes();
+            let unpremultiply = |c: u8| if a == 0 { 0 } else { (c as u32 * 255 / a as u32).min(255) as u8 };
+            format.pack(pixel, (unpremultiply(r), unpremultiply(g), unpremultiply(b), a));
+        }
+    }
+    Ok(())
+}
 
-    for detection in detections {
+/// 在DrawTarget上绘制检测框、掩码和关键点骨架
+fn draw_shapes(dt: &mut DrawTarget, detections: &[Detection], options: &DrawOptions) {
+    for detection in detections.iter().filter(|d| options.should_draw(d.class_id)) {
         let bbox = &detection.bbox;
 
         let mut pb = PathBuilder::new();
@@ -436,10 +2006,7 @@ pub fn draw_detections(image: &DynamicImage, detections: &[Detection]) -> Dynami
         let path = pb.finish();
         
         // 根据类别设置不同颜色
-        let color = match detection.class_id {
-            0 => SolidSource { r: 0x00, g: 0xFF, b: 0xFF, a: 0xFF }, // 青色 - person类别
-            _ => SolidSource { r: 0xFF, g: 0x00, b: 0x00, a: 0xFF }, // 红色 - 其他类别
-        };
+        let color = options.style.color(detection.class_id);
         
         dt.stroke(
             &path,
@@ -449,21 +2016,1107 @@ pub fn draw_detections(image: &DynamicImage, detections: &[Detection]) -> Dynami
                 width: 2.0,
                 ..StrokeStyle::default()
             },
-            &DrawOptions::default()
+            &RasterOptions::default()
         );
         
+        // 以半透明颜色填充实例分割掩码
+        if let Some(mask) = &detection.mask {
+            fill_mask(dt, mask, color);
+        }
+        
+        // 绘制姿态关键点骨架
+        if let Some(keypoints) = &detection.keypoints {
+            draw_skeleton(dt, keypoints, color);
+        }
+        
         // 可以添加文本标签显示类别和置信度
         // 这里暂时省略，如需要可后续添加
     }
+}
 
-    // 将DrawTarget转换回图像
-    let pixels: Vec<u8> = dt.get_data().iter().flat_map(|&pixel| {
-        let bytes = pixel.to_le_bytes();
-        vec![bytes[2], bytes[1], bytes[0], bytes[3]] // BGRA to RGBA
-    }).collect();
+/// 将图像编码为PNG字节
+/// 
+/// 使用固定的压缩级别和滤波方式，相同的图像总是得到逐字节相同的输出，
+/// 可用于与保存的参考图像直接比较。
+/// 
+/// # 错误处理
+/// 编码失败时返回[PerpleError::Io]
+pub fn render_to_png_bytes(image: &DynamicImage) -> Result<Vec<u8>, PerpleError> {
+    use image::codecs::png::{CompressionType, FilterType, PngEncoder};
     
-    DynamicImage::ImageRgba8(
-        image::ImageBuffer::from_raw(img_width, img_height, pixels)
-            .expect("Failed to create image from rendered data")
-    )
-}
\ No newline at end of file
+    let mut bytes = Vec::new();
+    let encoder = PngEncoder::new_with_quality(&mut bytes, CompressionType::Default, FilterType::Adaptive);
+    image.write_with_encoder(encoder).map_err(std::io::Error::other)?;
+    Ok(bytes)
+}
+
+/// 掩码叠加层的不透明度
+const MASK_OVERLAY_ALPHA: f32 = 0.4;
+
+/// 将掩码区域与指定颜色按[MASK_OVERLAY_ALPHA]混合
+fn fill_mask(dt: &mut DrawTarget, mask: &Mask, color: SolidSource) {
+    let width = dt.width() as u32;
+    let height = dt.height() as u32;
+    let data = dt.get_data_mut();
+    let blend = |channel: u32, target: u8| -> u32 {
+        (channel as f32 * (1.0 - MASK_OVERLAY_ALPHA) + target as f32 * MASK_OVERLAY_ALPHA) as u32
+    };
+    
+    for (px, py) in mask.pixels().filter(|&(px, py)| px < width && py < height) {
+        let pixel = &mut data[(py * width + px) as usize];
+        let a = *pixel >> 24;
+        let r = blend((*pixel >> 16) & 0xFF, color.r);
+        let g = blend((*pixel >> 8) & 0xFF, color.g);
+        let b = blend(*pixel & 0xFF, color.b);
+        *pixel = (a << 24) | (r << 16) | (g << 8) | b;
+    }
+}
+
+/// 遮盖图像中的检测目标（例如用于隐私脱敏）
+/// 
+/// 检测结果带有实例掩码时仅遮盖掩码像素，否则遮盖整个检测框。
+/// 
+/// # 参数
+/// * `image` - 原始图像
+/// * `detections` - 检测结果
+/// 
+/// # 返回值
+/// 返回目标区域被填充为黑色的新图像
+pub fn redact_detections(image: &DynamicImage, detections: &[Detection]) -> DynamicImage {
+    let mut output = image.to_rgba8();
+    let (width, height) = output.dimensions();
+    let black = image::Rgba([0, 0, 0, 0xFF]);
+    
+    for detection in detections {
+        match &detection.mask {
+            Some(mask) => {
+                for (px, py) in mask.pixels().filter(|&(px, py)| px < width && py < height) {
+                    output.put_pixel(px, py, black);
+                }
+            }
+            None => {
+                let bbox = &detection.bbox;
+                let x1 = bbox.x1.max(0.0) as u32;
+                let y1 = bbox.y1.max(0.0) as u32;
+                let x2 = (bbox.x2.max(0.0).ceil() as u32).min(width);
+                let y2 = (bbox.y2.max(0.0).ceil() as u32).min(height);
+                for py in y1..y2 {
+                    for px in x1..x2 {
+                        output.put_pixel(px, py, black);
+                    }
+                }
+            }
+        }
+    }
+    
+    DynamicImage::ImageRgba8(output)
+}
+
+/// 关键点置信度低于该值时不绘制
+const KEYPOINT_DRAW_THRESHOLD: f32 = 0.5;
+
+/// 按COCO骨架连接关系绘制关键点连线
+fn draw_skeleton(dt: &mut DrawTarget, keypoints: &[Keypoint], color: SolidSource) {
+    let style = StrokeStyle {
+        join: LineJoin::Round,
+        width: 2.0,
+        ..StrokeStyle::default()
+    };
+    
+    for &(a, b) in POSE_SKELETON.iter() {
+        let (Some(ka), Some(kb)) = (keypoints.get(a), keypoints.get(b)) else {
+            continue;
+        };
+        if ka.confidence < KEYPOINT_DRAW_THRESHOLD || kb.confidence < KEYPOINT_DRAW_THRESHOLD {
+            continue;
+        }
+        let mut pb = PathBuilder::new();
+        pb.move_to(ka.x, ka.y);
+        pb.line_to(kb.x, kb.y);
+        dt.stroke(&pb.finish(), &Source::Solid(color), &style, &RasterOptions::default());
+    }
+    
+    for keypoint in keypoints.iter().filter(|kp| kp.confidence >= KEYPOINT_DRAW_THRESHOLD) {
+        let mut pb = PathBuilder::new();
+        pb.arc(keypoint.x, keypoint.y, 3.0, 0.0, 2.0 * std::f32::consts::PI);
+        dt.fill(&pb.finish(), &Source::Solid(color), &RasterOptions::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DETECTIONS_CAPACITY;
+
+    /// 模型输入与原始图像均为640x640，坐标无需换算
+    fn identity_message() -> ScaleMessage {
+        ScaleMessage::builder().original_size(640, 640).scaled_size(640, 640).build()
+    }
+
+    /// 对合成的输出数据执行NMS
+    fn run_nms(shape: &[i64], data: &mut [f32], options: &NmsOptions, layout: &OutputLayout) -> Result<Bounds, PerpleError> {
+        run_nms_labeled(shape, data, options, layout, None)
+    }
+
+    /// 对合成的输出数据执行NMS，按`class_map`填写类别名称
+    fn run_nms_labeled(
+        shape: &[i64],
+        data: &mut [f32],
+        options: &NmsOptions,
+        layout: &OutputLayout,
+        class_map: Option<&ClassMap>,
+    ) -> Result<Bounds, PerpleError> {
+        let mut bounds = Bounds::new();
+        let mut picked = [false; DETECTIONS_CAPACITY];
+        let rows = ModelRows { shape, data, protos: None };
+        nms_rows(rows, &mut bounds, &identity_message(), &mut picked, 0.25, options, &[], layout, class_map)?;
+        Ok(bounds)
+    }
+
+    fn detection(x1: f32, y1: f32, x2: f32, y2: f32, class_id: usize, confidence: f32) -> Detection {
+        Detection::new(BoundingBox::new(x1, y1, x2, y2), class_id, "test", confidence)
+    }
+
+    /// 每个置信度一个检测框的多帧结果
+    fn frames_with_confidences(frames: &[&[f32]]) -> Vec<Bounds> {
+        frames.iter()
+            .map(|confidences| confidences.iter().map(|&c| detection(0.0, 0.0, 10.0, 10.0, 0, c)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn confidence_histogram_counts_every_detection() {
+        let all_bounds = frames_with_confidences(&[&[0.05, 0.15, 0.15], &[], &[0.55, 0.95, 1.0], &[1.2, -0.1]]);
+        let histogram = confidence_histogram(&all_bounds, 10);
+        assert_eq!(histogram.len(), 10);
+        let counts: Vec<usize> = histogram.iter().map(|&(_, count)| count).collect();
+        // 超出[0, 1]的置信度计入首尾区间，1.0计入最后一个区间
+        assert_eq!(counts, vec![2, 2, 0, 0, 0, 1, 0, 0, 0, 3]);
+        let total: usize = all_bounds.iter().map(Bounds::len).sum();
+        assert_eq!(counts.iter().sum::<usize>(), total);
+        assert!((histogram[0].0 - 0.05).abs() < 1e-6 && (histogram[9].0 - 0.95).abs() < 1e-6);
+        assert!(confidence_histogram(&all_bounds, 0).is_empty());
+    }
+
+    #[test]
+    fn suggested_threshold_keeps_target_fraction() {
+        let all_bounds = frames_with_confidences(&[&[0.9, 0.5], &[0.7, 0.8, 0.6]]);
+        assert_eq!(suggested_threshold(&all_bounds, 0.6), 0.7);
+        assert_eq!(suggested_threshold(&all_bounds, 1.0), 0.5);
+        assert_eq!(suggested_threshold(&all_bounds, 0.0), 0.9);
+        assert_eq!(suggested_threshold(&all_bounds, 1.5), 1.0);
+        assert_eq!(suggested_threshold(&[], 0.5), 1.0);
+        // 相同的置信度同时保留：0.8处已保留3/4
+        let ties = frames_with_confidences(&[&[0.9, 0.8, 0.8, 0.1]]);
+        assert_eq!(suggested_threshold(&ties, 0.5), 0.8);
+    }
+
+    /// 黄金图像测试的场景：渐变背景上的三个检测框，其中两个互相重叠
+    fn golden_scene() -> (DynamicImage, Vec<Detection>) {
+        let image = image::RgbImage::from_fn(48, 32, |x, y| image::Rgb([(x * 5) as u8, (y * 7) as u8, 96]));
+        let detections = vec![
+            detection(4.0, 4.0, 20.0, 28.0, 0, 0.9),
+            detection(24.0, 6.0, 44.0, 20.0, 2, 0.8),
+            detection(16.5, 12.5, 30.5, 26.5, 1, 0.6),
+        ];
+        (DynamicImage::ImageRgb8(image), detections)
+    }
+
+    /// 与`data/golden`下的参考图像逐像素比较，设置`PERPLE_UPDATE_GOLDEN`环境变量时重新生成参考图像
+    fn assert_matches_golden(name: &str, image: &DynamicImage) {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("data/golden").join(name);
+        let png = render_to_png_bytes(image).unwrap();
+        if std::env::var_os("PERPLE_UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &png).unwrap();
+        }
+        let golden = std::fs::read(&path).unwrap_or_else(|e| panic!("无法读取参考图像{}: {}", path.display(), e));
+        let golden = image::load_from_memory(&golden).unwrap().to_rgba8();
+        let actual = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(actual.dimensions(), golden.dimensions());
+        let differing = actual.pixels().zip(golden.pixels()).filter(|(a, b)| a != b).count();
+        assert_eq!(differing, 0, "{}有{}个像素与参考图像不同", name, differing);
+    }
+
+    #[test]
+    fn golden_fixed_style() {
+        let (image, detections) = golden_scene();
+        assert_matches_golden("draw_fixed.png", &draw_detections(&image, &detections));
+    }
+
+    #[test]
+    fn golden_seeded_style() {
+        let (image, detections) = golden_scene();
+        let options = DrawOptions { style: DrawStyle::deterministic(42), ..DrawOptions::default() };
+        let drawn = draw_detections_with_options(&image, &detections, &options);
+        // 相同输入两次编码逐字节相同
+        let again = draw_detections_with_options(&image, &detections, &options);
+        assert_eq!(render_to_png_bytes(&drawn).unwrap(), render_to_png_bytes(&again).unwrap());
+        assert_matches_golden("draw_seeded_42.png", &drawn);
+    }
+
+    #[test]
+    fn compute_ap_perfect_predictions() {
+        let ground_truth = vec![detection(0.0, 0.0, 10.0, 10.0, 0, 1.0), detection(20.0, 20.0, 40.0, 40.0, 1, 1.0)];
+        let predictions = vec![detection(0.0, 0.0, 10.0, 10.0, 0, 0.9), detection(20.0, 20.0, 40.0, 40.0, 1, 0.8)];
+        assert_eq!(compute_ap(&ground_truth, &predictions, 0.5), 1.0);
+        assert_eq!(compute_map(std::slice::from_ref(&ground_truth), &[predictions], &COCO_IOU_THRESHOLDS), 1.0);
+    }
+
+    #[test]
+    fn compute_ap_all_misses() {
+        let ground_truth = vec![detection(0.0, 0.0, 10.0, 10.0, 0, 1.0)];
+        let predictions = vec![detection(50.0, 50.0, 60.0, 60.0, 0, 0.9)];
+        assert_eq!(compute_ap(&ground_truth, &predictions, 0.5), 0.0);
+        assert_eq!(compute_ap(&ground_truth, &[], 0.5), 0.0);
+    }
+
+    #[test]
+    fn compute_ap_counts_unlabeled_class_as_false_positive() {
+        let ground_truth = vec![detection(0.0, 0.0, 10.0, 10.0, 0, 1.0)];
+        let predictions = vec![detection(0.0, 0.0, 10.0, 10.0, 0, 0.9), detection(0.0, 0.0, 10.0, 10.0, 2, 0.8)];
+        // 类别0的AP为1，类别2没有标注，AP为0
+        assert_eq!(compute_ap(&ground_truth, &predictions, 0.5), 0.5);
+    }
+
+    #[test]
+    fn compute_ap_without_ground_truth() {
+        assert_eq!(compute_ap(&[], &[], 0.5), 1.0);
+        assert_eq!(compute_ap(&[], &[detection(0.0, 0.0, 1.0, 1.0, 0, 0.9)], 0.5), 0.0);
+    }
+
+    #[test]
+    fn compute_ap_matches_each_ground_truth_once() {
+        let ground_truth = vec![detection(0.0, 0.0, 10.0, 10.0, 0, 1.0)];
+        let predictions = vec![detection(0.0, 0.0, 10.0, 10.0, 0, 0.9), detection(0.0, 0.0, 10.0, 10.0, 0, 0.8)];
+        // 重复的预测为假阳性，但召回率已在第一个预测处达到1，精确率1
+        assert_eq!(compute_ap(&ground_truth, &predictions, 0.5), 1.0);
+    }
+
+    #[test]
+    fn raw_output_detection() {
+        assert!(is_raw_output(&[1, 84, 8400]));
+        assert!(is_raw_output(&[1, 7, 8400]));
+        assert!(!is_raw_output(&[1, 300, 6]));
+        assert!(!is_raw_output(&[1, 8400, 84]));
+        assert!(!is_raw_output(&[1, 4, 8400]));
+        assert_eq!(output_rows(&[1, 300, 6]), Some((300, 6)));
+        assert_eq!(output_rows(&[1, 84, 8400]), None);
+        assert_eq!(output_rows(&[1, 10, 4]), None);
+        assert_eq!(output_rows(&[1, 1000]), None);
+    }
+
+    #[test]
+    fn transpose_params_moves_columns_to_rows() {
+        // 2个参数 x 3个锚点
+        let data = [1.0, 2.0, 3.0, 10.0, 20.0, 30.0];
+        assert_eq!(transpose_params(&data, 2, 3), vec![1.0, 10.0, 2.0, 20.0, 3.0, 30.0]);
+    }
+
+    #[test]
+    fn nms_rejects_incompatible_shapes() {
+        let options = NmsOptions::new(0.5);
+        let layout = OutputLayout::BoxConfidence;
+        let mut data = vec![0.0; 16];
+        let too_few = run_nms(&[1, 4, 4], &mut data, &options, &layout);
+        assert!(matches!(too_few, Err(PerpleError::IncompatibleModel { .. })));
+        let mut data = vec![0.0; 1000];
+        assert!(matches!(run_nms(&[1, 1000], &mut data, &options, &layout), Err(PerpleError::IncompatibleModel { .. })));
+        let mut data = vec![0.0; 5 * 10];
+        match run_nms(&[1, 5, 10], &mut data, &options, &layout) {
+            Err(PerpleError::IncompatibleModel { reason, .. }) => assert!(reason.contains("transpose_raw_output")),
+            other => panic!("应返回IncompatibleModel，实际为{:?}", other.map(|b| b.len())),
+        }
+    }
+
+    #[test]
+    fn nms_reads_transposed_raw_output() {
+        // 原始导出排列：5个参数 x 6个锚点，只有锚点2的置信度足够高
+        let (num_params, num_anchors) = (5, 6);
+        let mut raw = vec![0.0; num_params * num_anchors];
+        let column = [100.0, 100.0, 200.0, 150.0, 0.9];
+        for (param, value) in column.iter().enumerate() {
+            raw[param * num_anchors + 2] = *value;
+        }
+        let mut rows = transpose_params(&raw, num_params, num_anchors);
+        let bounds = run_nms(&[1, 6, 5], &mut rows, &NmsOptions::new(0.5), &OutputLayout::BoxConfidence).unwrap();
+        assert_eq!(bounds.len(), 1);
+        let bbox = &bounds.as_slice()[0].bbox;
+        assert_eq!((bbox.x1, bbox.y1, bbox.x2, bbox.y2), (100.0, 100.0, 200.0, 150.0));
+    }
+
+    /// 3个类别的类别分数排列：`[x1, y1, x2, y2, 类别0, 类别1, 类别2]`
+    fn class_score_rows(rows: &[[f32; 7]]) -> Vec<f32> {
+        rows.iter().flatten().copied().collect()
+    }
+
+    #[test]
+    fn resolve_class_scores_picks_argmax() {
+        let mut data = class_score_rows(&[
+            [0.0, 0.0, 10.0, 10.0, 0.1, 0.7, 0.2],
+            [0.0, 0.0, 10.0, 10.0, 0.3, 0.3, 0.9],
+            // 分数相同时取ID较小的类别
+            [0.0, 0.0, 10.0, 10.0, 0.4, 0.6, 0.6],
+        ]);
+        let layout = resolve_class_score_rows(&[1, 3, 7], &mut data, 0, Some(&OutputLayout::ClassScores { classes: None }));
+        assert_eq!(layout, OutputLayout::ClassScores { classes: None });
+        let resolved: Vec<(f32, f32)> = data.chunks_exact(7).map(|row| (row[4], row[5])).collect();
+        assert_eq!(resolved, vec![(0.7, 1.0), (0.9, 2.0), (0.6, 1.0)]);
+    }
+
+    #[test]
+    fn resolve_class_scores_respects_whitelist() {
+        let mut data = class_score_rows(&[[0.0, 0.0, 10.0, 10.0, 0.1, 0.7, 0.2]]);
+        let layout = OutputLayout::ClassScores { classes: Some(vec![0, 2]) };
+        resolve_class_score_rows(&[1, 1, 7], &mut data, 0, Some(&layout));
+        assert_eq!((data[4], data[5]), (0.2, 2.0));
+    }
+
+    #[test]
+    fn resolve_class_scores_guesses_from_param_count() {
+        let mut data = vec![0.0; 84];
+        data[4 + 17] = 0.8;
+        let layout = resolve_class_score_rows(&[1, 1, 84], &mut data, 0, None);
+        assert_eq!(layout, OutputLayout::ClassScores { classes: None });
+        assert_eq!((data[4], data[5]), (0.8, 17.0));
+        // 内置NMS导出的6个参数保持原样
+        let mut data = vec![0.0, 0.0, 10.0, 10.0, 0.9, 2.0];
+        assert_eq!(resolve_class_score_rows(&[1, 1, 6], &mut data, 0, None), OutputLayout::BoxConfidence);
+        assert_eq!(data, vec![0.0, 0.0, 10.0, 10.0, 0.9, 2.0]);
+    }
+
+    #[test]
+    fn class_scores_from_transposed_raw_output() {
+        // 3个类别的原始导出[1, 7, 2]：锚点0为类别2，锚点1为类别0
+        let raw = [
+            10.0, 50.0, // x1
+            10.0, 50.0, // y1
+            20.0, 60.0, // x2
+            20.0, 60.0, // y2
+            0.1, 0.8,   // 类别0
+            0.2, 0.1,   // 类别1
+            0.9, 0.3,   // 类别2
+        ];
+        let mut rows = transpose_params(&raw, 7, 2);
+        let layout = resolve_class_score_rows(&[1, 2, 7], &mut rows, 0, Some(&OutputLayout::ClassScores { classes: None }));
+        let bounds = run_nms(&[1, 2, 7], &mut rows, &NmsOptions::new(0.5), &layout).unwrap();
+        let found: Vec<(usize, f32)> = bounds.iter().map(|d| (d.class_id, d.confidence)).collect();
+        assert_eq!(found, vec![(2, 0.9), (0, 0.8)]);
+    }
+
+    #[test]
+    fn nms_is_per_class() {
+        // 类别0和类别2的框几乎完全重叠，另有一个与类别0重叠的低分同类框
+        let mut data = class_score_rows(&[
+            [0.0, 0.0, 100.0, 100.0, 0.9, 0.0, 0.0],
+            [1.0, 1.0, 101.0, 101.0, 0.0, 0.0, 0.8],
+            [2.0, 2.0, 102.0, 102.0, 0.7, 0.0, 0.0],
+        ]);
+        let layout = resolve_class_score_rows(&[1, 3, 7], &mut data, 0, Some(&OutputLayout::ClassScores { classes: None }));
+        let bounds = run_nms(&[1, 3, 7], &mut data, &NmsOptions::new(0.5), &layout).unwrap();
+        let found: Vec<(usize, f32)> = bounds.iter().map(|d| (d.class_id, d.confidence)).collect();
+        assert_eq!(found, vec![(0, 0.9), (2, 0.8)]);
+    }
+
+    #[test]
+    fn apply_nms_is_per_class() {
+        let mut detections = vec![
+            detection(0.0, 0.0, 100.0, 100.0, 0, 0.9),
+            detection(1.0, 1.0, 101.0, 101.0, 2, 0.8),
+            detection(2.0, 2.0, 102.0, 102.0, 0, 0.7),
+        ];
+        let kept: Vec<usize> = apply_nms(&mut detections, 0.5, None).iter().map(|d| d.class_id).collect();
+        assert_eq!(kept, vec![0, 2]);
+    }
+
+    #[test]
+    fn class_map_labels_builtin_nms_output() {
+        // 内置NMS导出的[x1, y1, x2, y2, conf, class]
+        let mut data = vec![
+            0.0, 0.0, 50.0, 50.0, 0.9, 1.0,
+            100.0, 100.0, 150.0, 150.0, 0.8, 2.0,
+            200.0, 200.0, 250.0, 250.0, 0.7, 0.0,
+            300.0, 300.0, 350.0, 350.0, 0.6, 7.0,
+        ];
+        let class_map = ClassMap::new(["person", "forklift", "pallet"]);
+        let bounds = run_nms_labeled(&[1, 4, 6], &mut data, &NmsOptions::new(0.5), &OutputLayout::BoxConfidence, Some(&class_map)).unwrap();
+        let labels: Vec<(usize, &str)> = bounds.iter().map(|d| (d.class_id, d.class_name.as_ref())).collect();
+        assert_eq!(labels, vec![(1, "forklift"), (2, "pallet"), (0, "person"), (7, "class_7")]);
+    }
+
+    #[test]
+    fn class_map_labels_class_score_output() {
+        let mut data = class_score_rows(&[
+            [0.0, 0.0, 50.0, 50.0, 0.1, 0.2, 0.9],
+            [100.0, 100.0, 150.0, 150.0, 0.1, 0.8, 0.2],
+        ]);
+        let class_map = ClassMap::new(["person", "forklift", "pallet"]);
+        let layout = resolve_class_score_rows(&[1, 2, 7], &mut data, 0, Some(&OutputLayout::ClassScores { classes: None }));
+        let bounds = run_nms_labeled(&[1, 2, 7], &mut data, &NmsOptions::new(0.5), &layout, Some(&class_map)).unwrap();
+        let labels: Vec<&str> = bounds.iter().map(|d| d.class_name.as_ref()).collect();
+        assert_eq!(labels, vec!["pallet", "forklift"]);
+    }
+
+    #[test]
+    fn single_class_output_without_class_column_is_person() {
+        let mut data = vec![0.0, 0.0, 50.0, 50.0, 0.9];
+        let bounds = run_nms(&[1, 1, 5], &mut data, &NmsOptions::new(0.5), &OutputLayout::BoxConfidence).unwrap();
+        assert_eq!(bounds.as_slice()[0].class_id, 0);
+        assert_eq!(bounds.as_slice()[0].class_name, PERSON_CLASS_LABEL);
+    }
+
+    /// 置信度乱序的合成输出，第`i`行的x1为`i * 100`，便于从结果反查行号
+    fn indexed_rows() -> Vec<f32> {
+        [0.3, 0.9, 0.5, 0.1, 0.7].iter().enumerate()
+            .flat_map(|(i, &conf)| {
+                let x = i as f32 * 100.0;
+                [x, 0.0, x + 50.0, 50.0, conf]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn source_index_is_off_by_default() {
+        let mut data = indexed_rows();
+        let bounds = run_nms(&[1, 5, 5], &mut data, &NmsOptions::new(0.5), &OutputLayout::BoxConfidence).unwrap();
+        assert_eq!(bounds.len(), 4);
+        assert!(bounds.iter().all(|d| d.source_index.is_none()));
+    }
+
+    #[test]
+    fn source_index_records_input_rows() {
+        let mut data = indexed_rows();
+        let options = NmsOptions::new(0.5).with_source_index(true);
+        let bounds = run_nms(&[1, 5, 5], &mut data, &options, &OutputLayout::BoxConfidence).unwrap();
+        let indices: Vec<Option<usize>> = bounds.iter().map(|d| d.source_index).collect();
+        // 按置信度排列：0.9、0.7、0.5、0.3，第3行低于阈值
+        assert_eq!(indices, vec![Some(1), Some(4), Some(2), Some(0)]);
+        for detection in bounds.iter() {
+            assert_eq!(detection.bbox.x1, detection.source_index.unwrap() as f32 * 100.0);
+        }
+    }
+
+    #[test]
+    fn source_index_distinguishes_identical_rows() {
+        // 两行完全相同，NMS阈值大于1时都被保留，各自对应不同的行号
+        let mut data = vec![
+            0.0, 0.0, 50.0, 50.0, 0.9,
+            0.0, 0.0, 50.0, 50.0, 0.9,
+        ];
+        let options = NmsOptions::new(1.1).with_source_index(true);
+        let bounds = run_nms(&[1, 2, 5], &mut data, &options, &OutputLayout::BoxConfidence).unwrap();
+        let indices: Vec<Option<usize>> = bounds.iter().map(|d| d.source_index).collect();
+        assert_eq!(indices, vec![Some(0), Some(1)]);
+    }
+
+    /// 五个互不重叠的框，原始置信度依次为0.95、0.7、0.65、0.55、0.45
+    fn calibration_rows() -> Vec<f32> {
+        [0.95, 0.7, 0.65, 0.55, 0.45]
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &conf)| {
+                let x = i as f32 * 100.0;
+                [x, 0.0, x + 50.0, 50.0, conf]
+            })
+            .collect()
+    }
+
+    /// 按`calibration`校准后以`threshold`过滤，返回保留框的原始行号
+    fn calibrated_survivors(calibration: &ConfidenceCalibration, threshold: f32) -> Vec<usize> {
+        let shape = [1, 5, 5];
+        let mut data = calibration_rows();
+        calibrate_rows(&shape, &mut data, calibration);
+        let mut bounds = Bounds::new();
+        let mut picked = [false; DETECTIONS_CAPACITY];
+        let rows = ModelRows { shape: &shape, data: &mut data, protos: None };
+        let options = NmsOptions::new(0.5).with_source_index(true);
+        nms_rows(rows, &mut bounds, &identity_message(), &mut picked, threshold, &options, &[], &OutputLayout::BoxConfidence, None)
+            .unwrap();
+        bounds.iter().map(|d| d.source_index.unwrap()).collect()
+    }
+
+    #[test]
+    fn temperature_changes_threshold_survivors() {
+        let temperature = ConfidenceCalibration::Temperature(2.0);
+        // 温度缩放保持logit符号不变，0.5两侧的框不会越过0.5：
+        // 阈值为0.5时保留的框不变，但校准后的分数向0.5收缩
+        assert_eq!(calibrated_survivors(&ConfidenceCalibration::None, 0.5), vec![0, 1, 2, 3]);
+        assert_eq!(calibrated_survivors(&temperature, 0.5), vec![0, 1, 2, 3]);
+        // 阈值高于0.5时温度决定保留哪些框：0.7校准后约0.604，0.65校准后约0.577
+        assert_eq!(calibrated_survivors(&ConfidenceCalibration::None, 0.6), vec![0, 1, 2]);
+        assert_eq!(calibrated_survivors(&temperature, 0.6), vec![0, 1]);
+    }
+
+    #[test]
+    fn vec_and_bounds_paths_agree_on_calibration() {
+        let calibrations = [
+            ConfidenceCalibration::None,
+            ConfidenceCalibration::Temperature(2.0),
+            ConfidenceCalibration::Platt { a: 1.5, b: -0.2 },
+        ];
+        for calibration in &calibrations {
+            for threshold in [0.5, 0.6] {
+                let shape = [1, 5, 5];
+                let mut data = calibration_rows();
+                let output = Array2::from_shape_vec((5, 5), data.clone()).unwrap();
+                let from_vec: Vec<f32> = process_detections_calibrated(
+                    output, 640.0, 640.0, 640, 640, threshold, 0.5, CoordFormat::Xyxy, calibration,
+                )
+                .unwrap()
+                .iter()
+                .map(|d| d.confidence)
+                .collect();
+
+                calibrate_rows(&shape, &mut data, calibration);
+                let mut bounds = Bounds::new();
+                let mut picked = [false; DETECTIONS_CAPACITY];
+                let rows = ModelRows { shape: &shape, data: &mut data, protos: None };
+                nms_rows(rows, &mut bounds, &identity_message(), &mut picked, threshold, &NmsOptions::new(0.5), &[], &OutputLayout::BoxConfidence, None)
+                    .unwrap();
+                let from_bounds: Vec<f32> = bounds.iter().map(|d| d.confidence).collect();
+                assert_eq!(from_vec, from_bounds, "{:?}, 阈值{}", calibration, threshold);
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_temperature_keeps_ranking() {
+        for t in [0.0, -2.0, f32::NAN] {
+            let calibration = ConfidenceCalibration::Temperature(t);
+            let scores: Vec<f32> = [0.95, 0.7, 0.3].iter().map(|&p| calibration.apply(p)).collect();
+            assert!(scores.iter().all(|s| !s.is_nan()), "t={}: {:?}", t, scores);
+            assert!(scores[0] >= scores[1] && scores[1] >= scores[2], "t={}: {:?}", t, scores);
+            // 不大于0的温度按极小正数计算，0.5以下的框被过滤
+            assert_eq!(calibrated_survivors(&calibration, 0.5), vec![0, 1, 2, 3], "t={}", t);
+        }
+    }
+
+    #[test]
+    fn nan_confidence_is_filtered() {
+        let mut data = vec![
+            0.0, 0.0, 50.0, 50.0, f32::NAN,
+            100.0, 0.0, 150.0, 50.0, 0.9,
+        ];
+        let bounds = run_nms(&[1, 2, 5], &mut data, &NmsOptions::new(0.5), &OutputLayout::BoxConfidence).unwrap();
+        assert_eq!(bounds.len(), 1);
+        assert_eq!(bounds.as_slice()[0].confidence, 0.9);
+    }
+
+    #[test]
+    fn calibration_config_rejects_invalid_temperature() {
+        for t in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(matches!(
+                CalibrationConfig::Temperature(t).validate(),
+                Err(PerpleError::InvalidParameter(_))
+            ));
+        }
+        assert!(CalibrationConfig::Temperature(2.0).validate().is_ok());
+        assert!(matches!(
+            CalibrationConfig::Platt { a: f32::NAN, b: 0.0 }.validate(),
+            Err(PerpleError::InvalidParameter(_))
+        ));
+        assert!(CalibrationConfig::Platt { a: 1.0, b: 0.0 }.validate().is_ok());
+    }
+
+    #[test]
+    fn bounding_box_iou_matches_nms_iou() {
+        let a = BoundingBox::new(0.0, 0.0, 100.0, 100.0);
+        let b = BoundingBox::new(50.0, 0.0, 150.0, 100.0);
+        let swapped = BoundingBox::new(100.0, 100.0, 0.0, 0.0);
+        assert_eq!(a.iou(&b), iou(&a, &b));
+        assert!((a.iou(&b) - 1.0 / 3.0).abs() < 1e-6);
+        // 角点颠倒的框在两处都视为无效
+        assert_eq!(a.iou(&swapped), 0.0);
+        assert_eq!(swapped.iou(&a), iou(&swapped, &a));
+        assert_eq!(a.iou(&swapped.normalized()), 1.0);
+    }
+
+    #[test]
+    fn intersection_over_min_uses_smaller_area() {
+        let big = BoundingBox::new(0.0, 0.0, 100.0, 100.0);
+        let nested = BoundingBox::new(10.0, 10.0, 30.0, 30.0);
+        assert_eq!(intersection_over_min(&big, &nested), 1.0);
+        assert_eq!(intersection_over_min(&nested, &big), 1.0);
+        assert!((iou(&big, &nested) - 0.04).abs() < 1e-6);
+
+        // 交集50，较小框面积100
+        let a = BoundingBox::new(0.0, 0.0, 10.0, 10.0);
+        let b = BoundingBox::new(5.0, 0.0, 25.0, 10.0);
+        assert!((intersection_over_min(&a, &b) - 0.5).abs() < 1e-6);
+        assert_eq!(intersection_over_min(&a, &BoundingBox::new(50.0, 50.0, 60.0, 60.0)), 0.0);
+        assert_eq!(intersection_over_min(&a, &BoundingBox::new(2.0, 2.0, 2.0, 8.0)), 0.0);
+        assert_eq!(intersection_over_min(&a, &BoundingBox::new(10.0, 10.0, 0.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn containment_ratio_is_relative_to_inner_box() {
+        let big = BoundingBox::new(0.0, 0.0, 100.0, 100.0);
+        let nested = BoundingBox::new(10.0, 10.0, 30.0, 30.0);
+        assert_eq!(containment_ratio(&nested, &big), 1.0);
+        assert!((containment_ratio(&big, &nested) - 0.04).abs() < 1e-6);
+
+        let a = BoundingBox::new(0.0, 0.0, 10.0, 10.0);
+        let b = BoundingBox::new(5.0, 0.0, 25.0, 10.0);
+        assert!((containment_ratio(&b, &a) - 0.25).abs() < 1e-6);
+        assert!((containment_ratio(&a, &b) - 0.5).abs() < 1e-6);
+        assert_eq!(containment_ratio(&BoundingBox::new(5.0, 5.0, 5.0, 5.0), &a), 0.0);
+    }
+
+    #[test]
+    fn nms_options_combine_mode_and_containment() {
+        let kept = BoundingBox::new(0.0, 0.0, 100.0, 100.0);
+        let nested = BoundingBox::new(10.0, 10.0, 30.0, 30.0);
+        // 一半露在外面的框：IoU约为0.09，IoMin和包含率均为0.5
+        let straddling = BoundingBox::new(80.0, 0.0, 120.0, 50.0);
+
+        let iou_only = NmsOptions::new(0.5);
+        assert!(!iou_only.suppresses(&kept, &nested));
+        assert!(!iou_only.suppresses(&kept, &straddling));
+
+        let io_min = NmsOptions::new(0.5).with_mode(NmsMode::IoMin);
+        assert!(io_min.suppresses(&kept, &nested));
+        assert!(io_min.suppresses(&kept, &straddling));
+
+        let contained = NmsOptions::new(0.5).with_containment_threshold(Some(0.9));
+        assert!(contained.suppresses(&kept, &nested));
+        assert!(!contained.suppresses(&kept, &straddling));
+        // 包含率只看低置信度框：大框不会因为包含已保留的小框而被抑制
+        assert!(!contained.suppresses(&nested, &kept));
+    }
+
+    #[test]
+    fn nested_box_is_suppressed_under_io_min_but_kept_under_iou() {
+        // 人群中同一人的整身框和内部的半身框，以及旁边另一个人
+        let rows = [
+            100.0, 100.0, 200.0, 400.0, 0.9,
+            120.0, 110.0, 180.0, 250.0, 0.8,
+            300.0, 100.0, 400.0, 400.0, 0.7,
+        ];
+        let confidences = |options: &NmsOptions| {
+            let mut data = rows;
+            let bounds = run_nms(&[1, 3, 5], &mut data, options, &OutputLayout::BoxConfidence).unwrap();
+            bounds.iter().map(|d| d.confidence).collect::<Vec<_>>()
+        };
+        assert_eq!(confidences(&NmsOptions::new(0.5)), [0.9, 0.8, 0.7]);
+        assert_eq!(confidences(&NmsOptions::new(0.5).with_mode(NmsMode::IoMin)), [0.9, 0.7]);
+        assert_eq!(confidences(&NmsOptions::new(0.5).with_containment_threshold(Some(0.9))), [0.9, 0.7]);
+
+        // 检测结果列表上的NMS与张量路径一致
+        let mut detections: Vec<Detection> = rows.chunks(5)
+            .map(|r| detection(r[0], r[1], r[2], r[3], 0, r[4]))
+            .collect();
+        let kept = apply_nms_with_options(&mut detections.clone(), &NmsOptions::new(0.5));
+        assert_eq!(kept.len(), 3);
+        let kept = apply_nms_with_options(&mut detections, &NmsOptions::new(0.5).with_mode(NmsMode::IoMin));
+        assert_eq!(kept.iter().map(|d| d.confidence).collect::<Vec<_>>(), [0.9, 0.7]);
+    }
+
+    #[test]
+    fn process_detections_normalizes_swapped_corners() {
+        // 第二行与第一行是同一个框，但角点颠倒；规范化后应被第一行抑制
+        let output = Array2::from_shape_vec((3, 5), vec![
+            0.0, 0.0, 100.0, 100.0, 0.9,
+            100.0, 100.0, 0.0, 0.0, 0.8,
+            300.0, 400.0, 200.0, 300.0, 0.7,
+        ]).unwrap();
+        let detections = process_detections(output, 640.0, 640.0, 640, 640, 0.25, 0.5).unwrap();
+        assert_eq!(detections.len(), 2);
+        assert_eq!(detections[0].confidence, 0.9);
+        assert_eq!(detections[1].bbox, BoundingBox::new(200.0, 300.0, 300.0, 400.0));
+    }
+
+    #[test]
+    fn process_detections_rejects_missing_confidence_column() {
+        let output = Array2::from_shape_vec((2, 4), vec![0.0, 0.0, 100.0, 100.0, 10.0, 10.0, 50.0, 50.0]).unwrap();
+        assert!(matches!(
+            process_detections(output, 640.0, 640.0, 640, 640, 0.25, 0.5),
+            Err(PerpleError::ShapeMismatch { expected: (2, 5), actual: (2, 4) })
+        ));
+
+        // 非连续存储的输出（如转置得到的数组）同样可以处理
+        let columns = Array2::from_shape_vec((5, 2), vec![
+            0.0, 300.0,
+            0.0, 300.0,
+            100.0, 400.0,
+            100.0, 400.0,
+            0.9, 0.1,
+        ]).unwrap();
+        let detections = process_detections(columns.reversed_axes(), 640.0, 640.0, 640, 640, 0.25, 0.5).unwrap();
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].bbox, BoundingBox::new(0.0, 0.0, 100.0, 100.0));
+    }
+
+    #[test]
+    fn nms_rows_normalizes_swapped_corners() {
+        // 张量路径同样先统一角点顺序：颠倒的框正常输出，并按IoU抑制与之重合的框
+        let mut data = vec![
+            100.0, 100.0, 0.0, 0.0, 0.95,
+            0.0, 0.0, 100.0, 100.0, 0.9,
+            300.0, 300.0, 200.0, 400.0, 0.8,
+        ];
+        let bounds = run_nms(&[1, 3, 5], &mut data, &NmsOptions::new(0.5), &OutputLayout::BoxConfidence).unwrap();
+        let boxes: Vec<(f32, BoundingBox)> = bounds.iter().map(|d| (d.confidence, d.bbox)).collect();
+        assert_eq!(boxes, vec![
+            (0.95, BoundingBox::new(0.0, 0.0, 100.0, 100.0)),
+            (0.8, BoundingBox::new(200.0, 300.0, 300.0, 400.0)),
+        ]);
+    }
+
+    #[test]
+    fn draw_trajectories_clips_edge_coordinates() {
+        let mut trajectories = TrajectoryRenderer::new(8);
+        for (x, y) in [(0.0, 0.0), (-500.0, 15.0), (31.0, 23.0), (1.0e9, -1.0e9), (32.0, 24.0)] {
+            let mut detection = Detection::new(BoundingBox::new(x, y, x, y), 1, "car", 0.9);
+            detection.track_id = Some(1);
+            trajectories.update(&std::iter::once(detection).collect::<Bounds>());
+        }
+        for (width, height) in [(32, 24), (1, 1)] {
+            let image = DynamicImage::new_rgb8(width, height);
+            let drawn = draw_trajectories(&image, &trajectories, &DrawStyle::default());
+            assert_eq!(drawn.dimensions(), (width, height));
+        }
+    }
+
+    #[test]
+    fn clip_segment_keeps_visible_part() {
+        assert_eq!(clip_segment((-10.0, 5.0), (110.0, 5.0), 100.0, 50.0, 0.0), Some(((0.0, 5.0), (100.0, 5.0))));
+        assert_eq!(clip_segment((10.0, 10.0), (20.0, 20.0), 100.0, 50.0, 2.0), Some(((10.0, 10.0), (20.0, 20.0))));
+        assert_eq!(clip_segment((-10.0, -10.0), (-5.0, 60.0), 100.0, 50.0, 2.0), None);
+    }
+
+    /// 字节各不相同、带半透明像素和行尾填充的帧缓冲区
+    fn frame_buffer(height: u32, stride: usize) -> Vec<u8> {
+        (0..stride * height as usize).map(|i| (i * 37 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn draw_on_frame_without_detections_is_pass_through() {
+        let (width, height, stride) = (16, 12, 16 * 4 + 3);
+        for format in [PixelFormat::Rgba, PixelFormat::Bgra, PixelFormat::Rgb, PixelFormat::Bgr] {
+            let original = frame_buffer(height, stride);
+            let mut buffer = original.clone();
+            draw_detections_on_frame(&mut buffer, stride, width, height, format, &[], &DrawOptions::default()).unwrap();
+            assert_eq!(buffer, original, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn draw_on_frame_only_touches_drawn_pixels() {
+        let (width, height, stride) = (32, 32, 32 * 4 + 5);
+        let original = frame_buffer(height, stride);
+        let mut buffer = original.clone();
+        let detections = [detection(8.0, 8.0, 20.0, 20.0, 0, 0.9)];
+        draw_detections_on_frame(&mut buffer, stride, width, height, PixelFormat::Rgba, &detections, &DrawOptions::default()).unwrap();
+
+        let pixel = |data: &[u8], x: usize, y: usize| data[y * stride + x * 4..][..4].to_vec();
+        assert_ne!(pixel(&buffer, 8, 14), pixel(&original, 8, 14));
+        for (x, y) in [(0, 0), (31, 31), (14, 14), (2, 25)] {
+            assert_eq!(pixel(&buffer, x, y), pixel(&original, x, y), "({}, {})", x, y);
+        }
+        for y in 0..height as usize {
+            assert_eq!(buffer[y * stride + 128..(y + 1) * stride], original[y * stride + 128..(y + 1) * stride]);
+        }
+    }
+
+    #[test]
+    fn draw_on_frame_rejects_degenerate_geometry() {
+        let options = DrawOptions::default();
+        let mut empty: [u8; 0] = [];
+        for (stride, width, height) in [(0, 0, 4), (0, 4, 0), (4, 0, 0)] {
+            let result = draw_detections_on_frame(&mut empty, stride, width, height, PixelFormat::Rgba, &[], &options);
+            assert!(matches!(result, Err(PerpleError::InvalidParameter(_))), "{}x{} stride {}", width, height, stride);
+        }
+        let mut buffer = vec![0u8; 4 * 4 * 4];
+        assert!(draw_detections_on_frame(&mut buffer, 15, 4, 4, PixelFormat::Rgba, &[], &options).is_err());
+        assert!(draw_detections_on_frame(&mut buffer, 16, 4, 3, PixelFormat::Rgba, &[], &options).is_err());
+    }
+
+    #[test]
+    fn decode_mask_follows_odd_bottom_padding() {
+        // 8x5的图像填充到8x8：上方1行、下方2行
+        let mut padder = crate::color::image::LetterboxPadder::with_default_color(8, 8).unwrap();
+        let (_, message) = padder.pad(&DynamicImage::new_rgb8(8, 5));
+        // 单通道原型只有模型输入第5行为正，对应原始图像第4行
+        let protos = Array2::from_shape_fn((1, 64), |(_, i)| if i / 8 == 5 { 1.0 } else { -1.0 });
+        let mask = decode_mask(&[1.0], protos.view(), (8, 8), &BoundingBox::new(0.0, 0.0, 8.0, 5.0), &message);
+        let rows: Vec<bool> = (0..5).map(|y| mask.get(3, y)).collect();
+        assert_eq!(rows, vec![false, false, false, false, true]);
+    }
+
+    #[test]
+    fn draw_checked_rejects_foreign_results() {
+        let image = DynamicImage::new_rgb8(32, 24);
+        let mut bounds: Bounds = std::iter::once(detection(4.0, 4.0, 20.0, 20.0, 0, 0.9)).collect();
+
+        // 没有尺寸信息时与不检查的绘制结果相同
+        let unchecked = draw_detections(&image, bounds.as_slice());
+        assert_eq!(draw_detections_checked(&image, &bounds).unwrap(), unchecked);
+        assert_ne!(unchecked.to_rgba8(), image.to_rgba8());
+
+        bounds.set_source_dims(Some((32, 24)));
+        assert_eq!(draw_detections_checked(&image, &bounds).unwrap(), unchecked);
+
+        bounds.set_source_dims(Some((64, 48)));
+        assert!(matches!(
+            draw_detections_checked(&image, &bounds),
+            Err(PerpleError::ShapeMismatch { expected: (64, 48), actual: (32, 24) })
+        ));
+
+        bounds.set_source_dims(Some((32, 24)));
+        for space in [OutputSpace::Normalized, OutputSpace::ModelInputPixels] {
+            bounds.set_space(space);
+            assert!(matches!(draw_detections_checked(&image, &bounds), Err(PerpleError::InvalidParameter(_))));
+        }
+    }
+
+    #[test]
+    fn small_capacity_keeps_highest_confidence_survivors() {
+        // 6个互不重叠的框都通过NMS，容量为4时只保留置信度最高的4个
+        let confidences = [0.5, 0.9, 0.3, 0.8, 0.7, 0.6];
+        let shape = [1, confidences.len() as i64, 5];
+        let mut data: Vec<f32> = confidences.iter()
+            .enumerate()
+            .flat_map(|(i, &conf)| {
+                let x = i as f32 * 100.0;
+                [x, 0.0, x + 50.0, 50.0, conf]
+            })
+            .collect();
+        let mut bounds = BoundsN::<4>::new();
+        let mut picked = [false; 4];
+        let rows = ModelRows { shape: &shape, data: &mut data, protos: None };
+        let options = NmsOptions::new(0.5).with_max_detections(Some(4)).with_source_index(true);
+        nms_rows(rows, &mut bounds, &identity_message(), &mut picked, 0.25, &options, &[], &OutputLayout::BoxConfidence, None).unwrap();
+        let kept: Vec<(usize, f32)> = bounds.iter().map(|d| (d.source_index.unwrap(), d.confidence)).collect();
+        assert_eq!(kept, [(1, 0.9), (3, 0.8), (4, 0.7), (5, 0.6)]);
+    }
+
+    /// 按阈值区域对6参数的输出行执行NMS，全局阈值为0.25，返回保留框的左上角横坐标
+    fn zoned_survivors(rows: &[[f32; 6]], zones: &[ThresholdZone], message: &ScaleMessage) -> Vec<f32> {
+        let mut data: Vec<f32> = rows.iter().flatten().copied().collect();
+        let mut bounds = Bounds::new();
+        let mut picked = [false; DETECTIONS_CAPACITY];
+        let rows = ModelRows { shape: &[1, rows.len() as i64, 6], data: &mut data, protos: None };
+        nms_rows(rows, &mut bounds, message, &mut picked, 0.25, &NmsOptions::new(0.5), zones, &OutputLayout::BoxConfidence, None).unwrap();
+        let mut x1: Vec<f32> = bounds.iter().map(|d| d.bbox.x1).collect();
+        x1.sort_by(f32::total_cmp);
+        x1
+    }
+
+    #[test]
+    fn threshold_zones_decide_between_equal_confidence_boxes() {
+        // 两个置信度相同的框分别位于低阈值的远处区域和高阈值的近处区域
+        let rows = [
+            [10.0, 10.0, 30.0, 30.0, 0.4, 0.0],
+            [400.0, 400.0, 420.0, 420.0, 0.4, 0.0],
+        ];
+        let zones = [
+            ThresholdZone::new(BoundingBox::new(0.0, 0.0, 100.0, 100.0), 0.3),
+            ThresholdZone::new(BoundingBox::new(300.0, 300.0, 640.0, 640.0), 0.5),
+        ];
+        assert_eq!(zoned_survivors(&rows, &zones, &identity_message()), [10.0]);
+        // 没有区域时都使用全局阈值
+        assert_eq!(zoned_survivors(&rows, &[], &identity_message()), [10.0, 400.0]);
+        // 不在任何区域内的框使用全局阈值
+        assert_eq!(zoned_survivors(&rows, &zones[1..], &identity_message()), [10.0]);
+    }
+
+    #[test]
+    fn overlapping_threshold_zones_use_first_match() {
+        let rows = [[40.0, 40.0, 60.0, 60.0, 0.4, 0.0]];
+        let strict = ThresholdZone::new(BoundingBox::new(0.0, 0.0, 100.0, 100.0), 0.6);
+        let loose = ThresholdZone::new(BoundingBox::new(0.0, 0.0, 200.0, 200.0), 0.2);
+        assert!(zoned_survivors(&rows, &[strict, loose], &identity_message()).is_empty());
+        assert_eq!(zoned_survivors(&rows, &[loose, strict], &identity_message()), [40.0]);
+    }
+
+    #[test]
+    fn threshold_zones_use_original_image_coordinates() {
+        // 1280x1280的原图缩放到640x640，模型坐标(50, 50)处的框中心在原图的(100, 100)
+        let message = ScaleMessage::builder().original_size(1280, 1280).scaled_size(640, 640).build();
+        let rows = [[40.0, 40.0, 60.0, 60.0, 0.4, 0.0]];
+        let strict = |region| [ThresholdZone::new(region, 0.6)];
+        assert!(zoned_survivors(&rows, &strict(BoundingBox::new(90.0, 90.0, 110.0, 110.0)), &message).is_empty());
+        assert_eq!(zoned_survivors(&rows, &strict(BoundingBox::new(40.0, 40.0, 60.0, 60.0)), &message), [80.0]);
+    }
+
+    /// 一行姿态模型输出：`[box, conf, (class), 17个关键点]`，第`i`个关键点位于`(x + i, y + 2i)`
+    fn pose_row(bbox: [f32; 4], confidence: f32, with_class: bool, origin: (f32, f32)) -> Vec<f32> {
+        let mut row = bbox.to_vec();
+        row.push(confidence);
+        if with_class {
+            row.push(0.0);
+        }
+        for i in 0..POSE_KEYPOINTS {
+            row.extend([origin.0 + i as f32, origin.1 + 2.0 * i as f32, 0.8]);
+        }
+        row
+    }
+
+    #[test]
+    fn parse_keypoints_scales_into_original_coordinates() {
+        let row = pose_row([0.0; 4], 0.9, false, (10.0, 20.0));
+        assert_eq!(keypoint_offset(row.len()), Some(5));
+        let keypoints = parse_keypoints(&row, 2.0, 0.5).unwrap();
+        assert_eq!(keypoints.len(), POSE_KEYPOINTS);
+        assert_eq!(keypoints[3], Keypoint::new(26.0, 13.0, 0.8));
+        // 非姿态输出没有关键点
+        assert!(parse_keypoints(&[0.0; 6], 1.0, 1.0).is_none());
+        assert_eq!(keypoint_offset(6 + POSE_KEYPOINTS * 3), Some(6));
+    }
+
+    #[test]
+    fn pose_keypoints_follow_letterbox_unmapping() {
+        // 1280x720的图像letterbox到640x640：缩放0.5，上下各填充140
+        let message = ScaleMessage::builder().original_size(1280, 720).scaled_size(640, 360).padding(0, 140).build();
+        for with_class in [false, true] {
+            let mut data = pose_row([100.0, 240.0, 200.0, 340.0], 0.9, with_class, (120.0, 250.0));
+            let num_params = data.len() as i64;
+            let mut bounds = Bounds::new();
+            let mut picked = [false; DETECTIONS_CAPACITY];
+            let rows = ModelRows { shape: &[1, 1, num_params], data: &mut data, protos: None };
+            nms_rows(rows, &mut bounds, &message, &mut picked, 0.25, &NmsOptions::new(0.5), &[], &OutputLayout::BoxConfidence, None).unwrap();
+
+            let detection = &bounds.as_slice()[0];
+            let bbox = &detection.bbox;
+            assert_eq!((bbox.x1, bbox.y1, bbox.x2, bbox.y2), (200.0, 200.0, 400.0, 400.0));
+            let keypoints = detection.keypoints.as_ref().unwrap();
+            assert_eq!(keypoints.len(), POSE_KEYPOINTS);
+            for (i, keypoint) in keypoints.iter().enumerate() {
+                let i = i as f32;
+                assert_eq!((keypoint.x, keypoint.y), ((120.0 + i) * 2.0, (250.0 + 2.0 * i - 140.0) * 2.0));
+                assert_eq!(keypoint.confidence, 0.8);
+            }
+        }
+    }
+
+    #[test]
+    fn pose_nms_suppresses_by_box_only() {
+        // 框相同、关键点不同的两行只保留置信度较高者及其关键点
+        let mut data = pose_row([100.0, 100.0, 200.0, 300.0], 0.6, true, (0.0, 0.0));
+        data.extend(pose_row([100.0, 100.0, 200.0, 300.0], 0.9, true, (150.0, 150.0)));
+        let shape = [1, 2, (6 + POSE_KEYPOINTS * 3) as i64];
+        let bounds = run_nms(&shape, &mut data, &NmsOptions::new(0.5), &OutputLayout::BoxConfidence).unwrap();
+        assert_eq!(bounds.len(), 1);
+        let detection = &bounds.as_slice()[0];
+        assert_eq!(detection.confidence, 0.9);
+        assert_eq!(detection.keypoints.as_ref().unwrap()[0], Keypoint::new(150.0, 150.0, 0.8));
+    }
+
+    #[test]
+    fn skeleton_is_drawn_between_confident_keypoints() {
+        let mut keypoints = vec![Keypoint::new(0.0, 0.0, 0.0); POSE_KEYPOINTS];
+        // 左右肩（5, 6）相连且可见，左髋（11）与左肩相连但置信度低
+        keypoints[5] = Keypoint::new(40.0, 50.0, 0.9);
+        keypoints[6] = Keypoint::new(80.0, 50.0, 0.9);
+        keypoints[11] = Keypoint::new(40.0, 90.0, 0.1);
+        let mut detection = detection(10.0, 10.0, 110.0, 110.0, 0, 0.9);
+        detection.keypoints = Some(keypoints);
+
+        let background = DynamicImage::new_rgb8(120, 120);
+        let drawn = draw_detections_with_options(&background, &[detection], &DrawOptions::default()).to_rgb8();
+        let painted = |x: u32, y: u32| drawn.get_pixel(x, y).0 != [0, 0, 0];
+        assert!(painted(60, 50));
+        assert!(!painted(40, 70));
+        assert!(!painted(60, 70));
+    }
+
+    /// 两通道8x8原型：通道0在左半边为正，通道1在上半边为正
+    fn quadrant_protos() -> Array2<f32> {
+        Array2::from_shape_fn((2, 64), |(channel, i)| {
+            let (x, y) = (i % 8, i / 8);
+            let positive = if channel == 0 { x < 4 } else { y < 4 };
+            if positive { 1.0 } else { -1.0 }
+        })
+    }
+
+    /// 16x16的原图缩放到8x8的模型输入
+    fn half_scale_message() -> ScaleMessage {
+        ScaleMessage::builder().original_size(16, 16).scaled_size(8, 8).build()
+    }
+
+    #[test]
+    fn decode_mask_combines_coefficients_and_crops_to_box() {
+        let protos = quadrant_protos();
+        let full = BoundingBox::new(0.0, 0.0, 16.0, 16.0);
+        // 两个通道之和只在左上象限为正，放大2倍后为原图的(0..8, 0..8)
+        let mask = decode_mask(&[1.0, 1.0], protos.view(), (8, 8), &full, &half_scale_message());
+        assert_eq!((mask.x, mask.y, mask.width, mask.height), (0, 0, 16, 16));
+        assert_eq!(mask.pixels().count(), 64);
+        assert!(mask.pixels().all(|(x, y)| x < 8 && y < 8));
+
+        // 系数相减时只有左下象限为正，并裁剪到检测框内
+        let bbox = BoundingBox::new(4.0, 4.0, 12.0, 12.0);
+        let mask = decode_mask(&[1.0, -1.0], protos.view(), (8, 8), &bbox, &half_scale_message());
+        assert_eq!((mask.x, mask.y, mask.width, mask.height), (4, 4, 8, 8));
+        let mut pixels: Vec<(u32, u32)> = mask.pixels().collect();
+        pixels.sort_unstable();
+        let expected: Vec<(u32, u32)> = (4..8).flat_map(|x| (8..12).map(move |y| (x, y))).collect();
+        assert_eq!(pixels, expected);
+    }
+
+    #[test]
+    fn segmentation_rows_carry_decoded_masks() {
+        let protos = quadrant_protos();
+        // [x1, y1, x2, y2, conf, class, 系数0, 系数1]，坐标为8x8的模型输入坐标
+        let mut data = vec![0.0, 0.0, 8.0, 8.0, 0.9, 0.0, 1.0, 1.0];
+        let mut bounds = Bounds::new();
+        let mut picked = [false; DETECTIONS_CAPACITY];
+        let rows = ModelRows { shape: &[1, 1, 8], data: &mut data, protos: Some((&[1, 2, 8, 8], protos.as_slice().unwrap())) };
+        nms_rows(rows, &mut bounds, &half_scale_message(), &mut picked, 0.25, &NmsOptions::new(0.5), &[], &OutputLayout::BoxConfidence, None).unwrap();
+
+        let detection = &bounds.as_slice()[0];
+        assert_eq!(detection.class_id, 0);
+        assert!(detection.keypoints.is_none());
+        let mask = detection.mask.as_ref().unwrap();
+        assert_eq!(mask.pixels().count(), 64);
+
+        // 原型通道数与每行的系数个数不符时报错
+        let mut data = vec![0.0, 0.0, 8.0, 8.0, 0.9, 0.0, 1.0, 1.0];
+        let rows = ModelRows { shape: &[1, 1, 8], data: &mut data, protos: Some((&[1, 3, 8, 8], &[0.0; 192])) };
+        let result = nms_rows(rows, &mut bounds, &half_scale_message(), &mut picked, 0.25, &NmsOptions::new(0.5), &[], &OutputLayout::BoxConfidence, None);
+        assert!(matches!(result, Err(PerpleError::IncompatibleModel { .. })));
+    }
+
+    #[test]
+    fn redaction_and_overlay_follow_mask_instead_of_box() {
+        let mut detection = detection(0.0, 0.0, 16.0, 16.0, 0, 0.9);
+        detection.mask = Some(decode_mask(&[1.0, 1.0], quadrant_protos().view(), (8, 8), &detection.bbox, &half_scale_message()));
+        let white = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(16, 16, image::Rgb([255, 255, 255])));
+
+        let redacted = redact_detections(&white, std::slice::from_ref(&detection)).to_rgb8();
+        assert_eq!(redacted.get_pixel(4, 4).0, [0, 0, 0]);
+        // 检测框内、掩码外的像素保持不变
+        assert_eq!(redacted.get_pixel(12, 12).0, [255, 255, 255]);
+
+        let drawn = draw_detections_with_options(&white, &[detection], &DrawOptions::default()).to_rgb8();
+        // 掩码区域被半透明着色：既不是原色也不是不透明的框线颜色
+        let tinted = drawn.get_pixel(5, 5).0;
+        assert_ne!(tinted, [255, 255, 255]);
+        assert!(tinted.iter().any(|&channel| channel > 100), "{:?}", tinted);
+        assert_eq!(drawn.get_pixel(12, 12).0, [255, 255, 255]);
+    }
+
+    #[test]
+    fn class_filters_change_only_filtered_boxes() {
+        // 类别0的框在左上，类别2的框在右下
+        let detections = [detection(10.0, 10.0, 40.0, 40.0, 0, 0.9), detection(60.0, 60.0, 90.0, 90.0, 2, 0.8)];
+        let background = DynamicImage::new_rgb8(100, 100);
+        let draw = |options: DrawOptions| draw_detections_with_options(&background, &detections, &options).to_rgb8();
+        let (person_edge, car_edge) = ((10, 25), (60, 75));
+
+        let all = draw(DrawOptions::default());
+        let people = draw(DrawOptions { show_classes: Some(vec![0]), ..DrawOptions::default() });
+        assert_ne!(all, people);
+        assert_eq!(all.get_pixel(person_edge.0, person_edge.1), people.get_pixel(person_edge.0, person_edge.1));
+        assert_ne!(all.get_pixel(car_edge.0, car_edge.1), people.get_pixel(car_edge.0, car_edge.1));
+        assert_eq!(people.get_pixel(car_edge.0, car_edge.1).0, [0, 0, 0]);
+
+        let cars = draw(DrawOptions { hide_classes: Some(vec![0]), ..DrawOptions::default() });
+        assert_eq!(cars.get_pixel(person_edge.0, person_edge.1).0, [0, 0, 0]);
+        assert_eq!(cars.get_pixel(car_edge.0, car_edge.1), all.get_pixel(car_edge.0, car_edge.1));
+
+        // 同时设置时show_classes优先
+        let both = draw(DrawOptions { show_classes: Some(vec![0]), hide_classes: Some(vec![0]), ..DrawOptions::default() });
+        assert_eq!(both, people);
+    }
+}
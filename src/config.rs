@@ -6,4 +6,285 @@ pub const PERSON_CLASS_LABEL: &str = "person";
 pub const DEFAULT_INPUT_WIDTH: usize = 640;
 pub const DEFAULT_INPUT_HEIGHT: usize = 640;
 pub const DEFAULT_CONFIDENCE_THRESHOLD: f32 = 0.6;
-pub const DEFAULT_NMS_THRESHOLD: f32 = 0.7;
\ No newline at end of file
+pub const DEFAULT_NMS_THRESHOLD: f32 = 0.7;
+
+// 自适应置信度阈值配置
+pub const ADAPTIVE_THRESHOLD_WINDOW: usize = 8;
+pub const ADAPTIVE_THRESHOLD_STEP: f32 = 0.05;
+pub const ADAPTIVE_THRESHOLD_MIN: f32 = 0.1;
+pub const ADAPTIVE_THRESHOLD_MAX: f32 = 0.9;
+pub const ADAPTIVE_THRESHOLD_HIGH_COUNT: usize = DETECTIONS_CAPACITY * 3 / 4;
+
+// 检测数量直方图默认窗口（帧数）
+pub const DEFAULT_HISTOGRAM_WINDOW: usize = 300;
+
+// 单次推理的默认超时时间
+pub const DEFAULT_INFERENCE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+// 超出单帧处理预算时，参与NMS及输出的检测框数量上限
+pub const DEGRADED_MAX_CANDIDATES: usize = 8;
+
+// 输入限流时允许的突发帧数
+pub const DEFAULT_RATE_LIMIT_BURST: f32 = 3.0;
+
+// 带透明通道的输入图像与该背景色（RGB）合成后再送入模型
+// 注意：早期版本直接丢弃透明通道（相当于合成到黑色上），改为白色后RGBA输入的检测结果会有所不同，
+// 需要旧行为时用YoloDetector::with_alpha_background([0, 0, 0])
+pub const DEFAULT_ALPHA_BACKGROUND: [u8; 3] = [255, 255, 255];
+
+// 输入图像宽高的默认下限（像素），低于该值的图像在检测前被拒绝
+pub const DEFAULT_MIN_INPUT_DIMENSION: u32 = 2;
+
+// 保持宽高比缩放（letterbox）时的填充色（RGB），与YOLO训练时的填充一致
+pub const DEFAULT_LETTERBOX_COLOR: [u8; 3] = [114, 114, 114];
+
+// 轨迹绘制时每条轨迹保留的中心点数
+pub const DEFAULT_TRAJECTORY_POINTS: usize = 32;
+
+// 轨迹连续多少帧未出现后被移除
+pub const DEFAULT_TRAJECTORY_MAX_MISSED: usize = 30;
+
+// 带进度回调加载图像时，每读取该字节数报告一次进度
+pub const LOAD_PROGRESS_INTERVAL: usize = 64 * 1024;
+
+/// 运行时配置
+/// 
+/// 各字段与本模块中同名的大写常量对应，默认值即编译期常量。
+/// `stream_capacity`和`detections_capacity`在编译期确定，配置中只能填写与常量相同的值。
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Config {
+    pub stream_capacity: usize,
+    pub detections_capacity: usize,
+    pub default_input_width: usize,
+    pub default_input_height: usize,
+    pub default_confidence_threshold: f32,
+    pub default_nms_threshold: f32,
+    pub adaptive_threshold_window: usize,
+    pub adaptive_threshold_step: f32,
+    pub adaptive_threshold_min: f32,
+    pub adaptive_threshold_max: f32,
+    pub default_histogram_window: usize,
+    /// 单次推理的超时时间（毫秒）
+    pub default_inference_timeout_ms: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            stream_capacity: STREAM_CAPACITY,
+            detections_capacity: DETECTIONS_CAPACITY,
+            default_input_width: DEFAULT_INPUT_WIDTH,
+            default_input_height: DEFAULT_INPUT_HEIGHT,
+            default_confidence_threshold: DEFAULT_CONFIDENCE_THRESHOLD,
+            default_nms_threshold: DEFAULT_NMS_THRESHOLD,
+            adaptive_threshold_window: ADAPTIVE_THRESHOLD_WINDOW,
+            adaptive_threshold_step: ADAPTIVE_THRESHOLD_STEP,
+            adaptive_threshold_min: ADAPTIVE_THRESHOLD_MIN,
+            adaptive_threshold_max: ADAPTIVE_THRESHOLD_MAX,
+            default_histogram_window: DEFAULT_HISTOGRAM_WINDOW,
+            default_inference_timeout_ms: DEFAULT_INFERENCE_TIMEOUT.as_millis() as u64,
+        }
+    }
+}
+
+/// 部分配置，只包含需要覆盖的字段
+/// 
+/// 字段与[Config]一一对应，为`None`的字段不覆盖，因此也可以把字段改回默认值。
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ConfigOverrides {
+    pub stream_capacity: Option<usize>,
+    pub detections_capacity: Option<usize>,
+    pub default_input_width: Option<usize>,
+    pub default_input_height: Option<usize>,
+    pub default_confidence_threshold: Option<f32>,
+    pub default_nms_threshold: Option<f32>,
+    pub adaptive_threshold_window: Option<usize>,
+    pub adaptive_threshold_step: Option<f32>,
+    pub adaptive_threshold_min: Option<f32>,
+    pub adaptive_threshold_max: Option<f32>,
+    pub default_histogram_window: Option<usize>,
+    /// 单次推理的超时时间（毫秒）
+    pub default_inference_timeout_ms: Option<u64>,
+}
+
+/// 对[Config]和[ConfigOverrides]的全部字段执行同一操作
+macro_rules! for_each_field {
+    ($apply:ident) => {
+        $apply!(
+            stream_capacity, detections_capacity,
+            default_input_width, default_input_height,
+            default_confidence_threshold, default_nms_threshold,
+            adaptive_threshold_window, adaptive_threshold_step,
+            adaptive_threshold_min, adaptive_threshold_max,
+            default_histogram_window, default_inference_timeout_ms
+        )
+    };
+}
+
+impl From<Config> for ConfigOverrides {
+    /// 覆盖全部字段
+    fn from(config: Config) -> Self {
+        macro_rules! all {
+            ($($field:ident),*) => {
+                ConfigOverrides { $($field: Some(config.$field),)* }
+            };
+        }
+        for_each_field!(all)
+    }
+}
+
+impl Config {
+    /// 合并配置，`overrides`中设置了的字段覆盖`base`
+    /// 
+    /// 例如将配置文件中的设置（[ConfigOverrides::load_from_toml]）应用到已有配置之上。
+    pub fn merge(base: Config, overrides: ConfigOverrides) -> Config {
+        macro_rules! pick {
+            ($($field:ident),*) => {
+                Config {
+                    $($field: overrides.$field.unwrap_or(base.$field),)*
+                }
+            };
+        }
+        for_each_field!(pick)
+    }
+
+    /// 单次推理的超时时间
+    pub fn inference_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.default_inference_timeout_ms)
+    }
+}
+
+/// 配置文件的顶层结构，配置项位于`[perple]`节中
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    perple: ConfigOverrides,
+}
+
+#[cfg(feature = "config-file")]
+impl ConfigOverrides {
+    /// 从TOML文件读取需要覆盖的配置项，文件格式见[Config::load_from_toml]
+    /// 
+    /// 文件中出现的键都会覆盖，即使其值与默认值相同。
+    pub fn load_from_toml(path: &str) -> Result<Self, crate::error::PerpleError> {
+        use crate::error::PerpleError;
+        
+        let content = std::fs::read_to_string(path)?;
+        let file: ConfigFile = toml::from_str(&content)
+            .map_err(|e| PerpleError::Config(format!("无法解析配置文件 {}: {}", path, e)))?;
+        Ok(file.perple)
+    }
+}
+
+#[cfg(feature = "config-file")]
+impl Config {
+    /// 从TOML文件读取配置
+    /// 
+    /// 读取`[perple]`节，键名为常量名的小写形式（如`default_confidence_threshold`），
+    /// 缺少的键使用编译期默认值。
+    /// 
+    /// ```toml
+    /// [perple]
+    /// default_confidence_threshold = 0.5
+    /// default_input_width = 320
+    /// default_input_height = 320
+    /// ```
+    /// 
+    /// # 参数
+    /// * `path` - TOML文件路径
+    pub fn load_from_toml(path: &str) -> Result<Self, crate::error::PerpleError> {
+        use crate::error::PerpleError;
+        
+        let config = Config::merge(Config::default(), ConfigOverrides::load_from_toml(path)?);
+        if config.stream_capacity != STREAM_CAPACITY || config.detections_capacity != DETECTIONS_CAPACITY {
+            return Err(PerpleError::Config(format!(
+                "stream_capacity和detections_capacity在编译期确定，只能为{}和{}",
+                STREAM_CAPACITY, DETECTIONS_CAPACITY
+            )));
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_applies_only_set_overrides() {
+        let base = Config { default_input_width: 320, default_nms_threshold: 0.6, ..Config::default() };
+        let overrides = ConfigOverrides { default_input_width: Some(480), default_confidence_threshold: Some(0.4), ..Default::default() };
+        let merged = Config::merge(base.clone(), overrides);
+        assert_eq!(merged.default_input_width, 480);
+        assert_eq!(merged.default_confidence_threshold, 0.4);
+        // 未设置的字段沿用原配置
+        assert_eq!(merged.default_nms_threshold, 0.6);
+        assert_eq!(merged.default_input_height, DEFAULT_INPUT_HEIGHT);
+
+        assert_eq!(Config::merge(base.clone(), ConfigOverrides::default()), base);
+        assert_eq!(Config::merge(base, Config::default().into()), Config::default());
+    }
+
+    #[test]
+    fn merge_can_reset_field_to_default() {
+        let base = Config { default_input_width: 320, ..Config::default() };
+        let overrides = ConfigOverrides { default_input_width: Some(DEFAULT_INPUT_WIDTH), ..Default::default() };
+        assert_eq!(Config::merge(base, overrides).default_input_width, DEFAULT_INPUT_WIDTH);
+    }
+
+    #[cfg(feature = "config-file")]
+    fn load(name: &str, content: &str) -> Result<Config, crate::error::PerpleError> {
+        let path = std::env::temp_dir().join(format!("perple_config_{}_{}.toml", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        let config = Config::load_from_toml(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        config
+    }
+
+    #[test]
+    #[cfg(feature = "config-file")]
+    fn load_from_toml_reads_perple_section() {
+        let config = load("section", "[perple]\ndefault_confidence_threshold = 0.5\ndefault_input_width = 320\ndefault_inference_timeout_ms = 250\n").unwrap();
+        assert_eq!(config.default_confidence_threshold, 0.5);
+        assert_eq!(config.default_input_width, 320);
+        assert_eq!(config.inference_timeout(), std::time::Duration::from_millis(250));
+        // 缺少的键使用编译期默认值
+        assert_eq!(config.default_input_height, DEFAULT_INPUT_HEIGHT);
+        assert_eq!(config.default_nms_threshold, DEFAULT_NMS_THRESHOLD);
+
+        // 没有[perple]节时全部使用默认值
+        assert_eq!(load("empty", "").unwrap(), Config::default());
+    }
+
+    #[test]
+    #[cfg(feature = "config-file")]
+    fn file_overrides_reset_base_to_default_value() {
+        let path = std::env::temp_dir().join(format!("perple_config_{}_reset.toml", std::process::id()));
+        std::fs::write(&path, "[perple]\ndefault_input_width = 640\n").unwrap();
+        let overrides = ConfigOverrides::load_from_toml(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        let overrides = overrides.unwrap();
+        assert_eq!(overrides, ConfigOverrides { default_input_width: Some(640), ..Default::default() });
+
+        let base = Config { default_input_width: 320, default_input_height: 320, ..Config::default() };
+        let merged = Config::merge(base, overrides);
+        assert_eq!((merged.default_input_width, merged.default_input_height), (640, 320));
+    }
+
+    #[test]
+    #[cfg(feature = "config-file")]
+    fn load_from_toml_rejects_invalid_files() {
+        use crate::error::PerpleError;
+
+        let capacity = format!("[perple]\nstream_capacity = {}\n", STREAM_CAPACITY + 1);
+        assert!(matches!(load("capacity", &capacity), Err(PerpleError::Config(_))));
+        assert!(matches!(load("syntax", "[perple\n"), Err(PerpleError::Config(_))));
+        assert!(matches!(load("type", "[perple]\ndefault_input_width = \"wide\"\n"), Err(PerpleError::Config(_))));
+        assert!(matches!(Config::load_from_toml("/nonexistent/perple.toml"), Err(PerpleError::Io(_))));
+    }
+}
@@ -0,0 +1,134 @@
+//! 错误类型模块
+//!
+//! 定义整个crate统一使用的错误类型[PerpleError]。
+
+use std::fmt;
+
+/// Perple统一错误类型
+#[derive(Debug)]
+pub enum PerpleError {
+    /// 模型与检测流程不兼容（输入/输出形状不符合预期）
+    IncompatibleModel {
+        /// 模型输入形状（动态维度为-1）
+        input_shape: Vec<i64>,
+        /// 模型输出形状（动态维度为-1）
+        output_shape: Vec<i64>,
+        /// 不兼容的具体原因
+        reason: String,
+    },
+    /// ONNX Runtime内部错误
+    Ort(ort::Error),
+    /// 缓冲区已满
+    BufferFull {
+        /// 缓冲区写满前成功写入的元素个数
+        count: usize,
+    },
+    /// 文件读写错误
+    Io(std::io::Error),
+    /// 配置内容解析或序列化失败
+    Config(String),
+    /// 不支持的模型或图像格式
+    UnsupportedFormat {
+        /// 识别出的格式名称（无法识别时为文件路径）
+        format: String,
+        /// 解决建议，例如如何转换为ONNX，或图像解码失败的原因
+        suggestion: String,
+    },
+    /// 模型下载或校验失败
+    Download(String),
+    /// 参数不合法，例如输入尺寸为0或不是32的倍数
+    InvalidParameter(String),
+    /// 两幅图像或模型输出的尺寸不一致
+    ShapeMismatch {
+        /// 第一幅图像的尺寸`(宽, 高)`，模型输出为期望的`(行, 列)`
+        expected: (u32, u32),
+        /// 第二幅图像的尺寸`(宽, 高)`，模型输出为实际的`(行, 列)`
+        actual: (u32, u32),
+    },
+    /// 输入图像无法检测，例如面积为0或宽高低于下限
+    InvalidInput {
+        /// 图像宽度
+        width: u32,
+        /// 图像高度
+        height: u32,
+        /// 被拒绝的具体原因
+        reason: String,
+    },
+    /// 循环已在运行，不能重复启动
+    LoopRunning,
+    /// 工作线程发生panic，内容说明是哪个线程
+    WorkerPanicked(String),
+    /// 任务在开始执行前被取消，例如所在的线程池已关闭
+    Cancelled,
+    /// 推理线程异常退出，检测器状态随之丢失，流水线无法继续检测，需要重新创建
+    EngineLost {
+        /// 推理线程退出时正在处理的帧序号
+        frame_id: u64,
+    },
+    /// 启动自检的某一步失败
+    SelfTest {
+        /// 失败的步骤名称
+        step: &'static str,
+        /// 该步骤返回的错误
+        source: Box<PerpleError>,
+    },
+}
+
+impl fmt::Display for PerpleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PerpleError::IncompatibleModel { input_shape, output_shape, reason } => write!(
+                f,
+                "模型不兼容: {} (输入形状: {:?}, 输出形状: {:?})。请确认加载的是YOLO目标检测模型",
+                reason, input_shape, output_shape
+            ),
+            PerpleError::Ort(e) => write!(f, "ONNX Runtime错误: {}", e),
+            PerpleError::BufferFull { count } => write!(f, "缓冲区已满（已写入{}个元素）", count),
+            PerpleError::Io(e) => write!(f, "文件读写错误: {}", e),
+            PerpleError::Config(msg) => write!(f, "配置错误: {}", msg),
+            PerpleError::UnsupportedFormat { format, suggestion } => {
+                write!(f, "不支持的格式: {}。{}", format, suggestion)
+            }
+            PerpleError::Download(msg) => write!(f, "模型下载失败: {}", msg),
+            PerpleError::InvalidParameter(msg) => write!(f, "参数不合法: {}", msg),
+            PerpleError::ShapeMismatch { expected, actual } => write!(
+                f,
+                "尺寸不一致: {}x{} 与 {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            PerpleError::InvalidInput { width, height, reason } => {
+                write!(f, "输入图像不合法: {} (尺寸: {}x{})", reason, width, height)
+            }
+            PerpleError::LoopRunning => write!(f, "循环已在运行"),
+            PerpleError::WorkerPanicked(worker) => write!(f, "工作线程发生panic: {}", worker),
+            PerpleError::Cancelled => write!(f, "任务已被取消"),
+            PerpleError::EngineLost { frame_id } => {
+                write!(f, "推理线程异常退出，检测器状态已丢失 (帧序号: {})", frame_id)
+            }
+            PerpleError::SelfTest { step, source } => write!(f, "自检步骤{}失败: {}", step, source),
+        }
+    }
+}
+
+impl std::error::Error for PerpleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PerpleError::Ort(e) => Some(e),
+            PerpleError::Io(e) => Some(e),
+            PerpleError::SelfTest { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<ort::Error> for PerpleError {
+    fn from(e: ort::Error) -> Self {
+        PerpleError::Ort(e)
+    }
+}
+
+impl From<std::io::Error> for PerpleError {
+    fn from(e: std::io::Error) -> Self {
+        PerpleError::Io(e)
+    }
+}
@@ -3,8 +3,14 @@ pub mod color;
 pub mod lidar;
 pub mod perple;
 pub mod config;
+pub mod error;
+pub mod prelude;
+pub mod selftest;
 
-pub use perple::Perple;
+pub use perple::{Perple, PerpleBuilder, PerpleConfig, PipelineConfig, UpdateResult, RateLimitPolicy};
+pub use color::{Frame, Payload, UserPayload};
+pub use error::PerpleError;
+pub use selftest::SelfTestReport;
 pub use utils::muloop::LoopMode;
 
 // 重新导出color模块中的常用类型和函数
@@ -1,66 +1,863 @@
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 use image::DynamicImage;
 
-use crate::color::{Bounds, core::Color};
-use crate::utils::stream::Stream;
-use crate::utils::muloop::{MultiLoop, LoopMode};
+use crate::color::{Bounds, BoundsN, DetectorConfig, Fuser, FusionMode, PersonCounter, YoloDetectorN, ModelOptions, Frame, Payload, ResultSink, SinkRunner, SnapshotConfig, SnapshotSink, FrameMeta, core::{Color, FrameBudget}};
+use crate::config::{
+    ADAPTIVE_THRESHOLD_WINDOW, ADAPTIVE_THRESHOLD_STEP, ADAPTIVE_THRESHOLD_MIN,
+    ADAPTIVE_THRESHOLD_MAX, ADAPTIVE_THRESHOLD_HIGH_COUNT, STREAM_CAPACITY,
+    DEFAULT_INPUT_WIDTH, DEFAULT_INPUT_HEIGHT, DEFAULT_RATE_LIMIT_BURST, DEFAULT_HISTOGRAM_WINDOW,
+    DEFAULT_INFERENCE_TIMEOUT, DETECTIONS_CAPACITY, Config,
+};
+use crate::utils::stream::{Stream, SignalStream};
+use crate::utils::muloop::{MultiLoop, LoopMode, LoopStats};
+use crate::utils::stats::{DetectionHistogram, PipelineStats};
+use crate::utils::rate::TokenBucket;
+use crate::utils::sched::PipelineScheduler;
+use crate::error::PerpleError;
+use crate::selftest::{self, SelfTestReport, STEP_LOAD_MODEL};
 
-pub struct Perple {
+/// 主检测流水线（[Perple::new]创建的那一条）的名称
+pub const DEFAULT_PIPELINE: &str = "default";
+
+/// 主循环每次检测之间的默认间隔（毫秒）
+pub const DEFAULT_LOOP_INTERVAL_MS: u64 = 100;
+
+/// 附加检测流水线的配置
+/// 
+/// 每条流水线拥有独立的模型、阈值、循环模式和输出流，
+/// 与主流水线共用[Perple::update_image]写入的图像。
+#[derive(Debug, Clone)]
+pub struct PipelineConfig {
+    model_path: String,
+    input_width: usize,
+    input_height: usize,
+    confidence_threshold: Option<f32>,
+    nms_threshold: Option<f32>,
+    loop_mode: LoopMode,
+    interval_ms: u64,
+    model_options: Option<ModelOptions>,
+    scheduler_weight: u32,
+}
+
+impl PipelineConfig {
+    /// 创建默认配置：默认输入尺寸和阈值，持续循环，间隔100ms
+    pub fn new(model_path: &str) -> Self {
+        Self {
+            model_path: model_path.to_string(),
+            input_width: DEFAULT_INPUT_WIDTH,
+            input_height: DEFAULT_INPUT_HEIGHT,
+            confidence_threshold: None,
+            nms_threshold: None,
+            loop_mode: LoopMode::Continuous,
+            interval_ms: 100,
+            model_options: None,
+            scheduler_weight: 1,
+        }
+    }
+
+    /// 按运行时配置创建：使用其中的输入尺寸和阈值，其余与[PipelineConfig::new]相同
+    pub fn from_config(model_path: &str, config: &Config) -> Self {
+        Self::new(model_path)
+            .with_input_size(config.default_input_width, config.default_input_height)
+            .with_confidence_threshold(config.default_confidence_threshold)
+            .with_nms_threshold(config.default_nms_threshold)
+    }
+
+    /// 设置模型输入尺寸
+    pub fn with_input_size(mut self, input_width: usize, input_height: usize) -> Self {
+        self.input_width = input_width;
+        self.input_height = input_height;
+        self
+    }
+
+    /// 设置置信度阈值
+    pub fn with_confidence_threshold(mut self, threshold: f32) -> Self {
+        self.confidence_threshold = Some(threshold);
+        self
+    }
+
+    /// 设置NMS阈值
+    pub fn with_nms_threshold(mut self, threshold: f32) -> Self {
+        self.nms_threshold = Some(threshold);
+        self
+    }
+
+    /// 设置[Perple::start_all]启动该流水线时使用的循环模式
+    pub fn with_loop_mode(mut self, mode: LoopMode) -> Self {
+        self.loop_mode = mode;
+        self
+    }
+
+    /// 设置循环间隔（毫秒）
+    pub fn with_interval_ms(mut self, interval_ms: u64) -> Self {
+        self.interval_ms = interval_ms;
+        self
+    }
+
+    /// 设置模型会话的线程和内存配置，未设置时与[YoloDetectorN::new]相同
+    pub fn with_model_options(mut self, options: ModelOptions) -> Self {
+        self.model_options = Some(options);
+        self
+    }
+
+    /// 设置该流水线在[PipelineScheduler]中的权重，默认为1
+    /// 
+    /// 只在通过[Perple::set_scheduler]启用调度器时生效。
+    pub fn with_scheduler_weight(mut self, weight: u32) -> Self {
+        self.scheduler_weight = weight;
+        self
+    }
+}
+
+/// [Perple]的构建器
+/// 
+/// 先在[PerpleConfig]中准备好全部参数，[build](Self::build)时统一检查、加载模型并应用，
+/// 参数不合法时不会加载模型。未指定数据流时自动创建新的数据流。
+pub struct PerpleBuilder<P: Payload<N> = (), const N: usize = DETECTIONS_CAPACITY> {
+    config: PerpleConfig,
+    img_stream: Option<Arc<Mutex<Stream<P::Frame>>>>,
+    bounds_stream: Option<Arc<Mutex<Stream<P::Output>>>>,
+}
+
+impl<P: Payload<N>, const N: usize> PerpleBuilder<P, N> {
+    /// 使用默认参数和指定模型创建构建器
+    pub fn new(model_path: &str) -> Self {
+        let mut config = PerpleConfig::default();
+        config.detector.model_path = model_path.to_string();
+        Self::from_config(config)
+    }
+
+    /// 以参数快照为起点创建构建器，模型路径取自`config.detector.model_path`
+    pub fn from_config(config: PerpleConfig) -> Self {
+        Self { config, img_stream: None, bounds_stream: None }
+    }
+
+    /// 以TOML配置文件为起点创建构建器，文件格式见[PerpleConfig::load_from_toml]
+    /// 
+    /// # 错误处理
+    /// 与[PerpleConfig::load_from_toml]相同
+    #[cfg(feature = "config-file")]
+    pub fn from_config_file(path: &str) -> Result<Self, PerpleError> {
+        Ok(Self::from_config(PerpleConfig::load_from_toml(path)?))
+    }
+
+    /// 使用外部传入的公用数据流
+    pub fn with_streams(mut self, img_stream: Arc<Mutex<Stream<P::Frame>>>, bounds_stream: Arc<Mutex<Stream<P::Output>>>) -> Self {
+        self.img_stream = Some(img_stream);
+        self.bounds_stream = Some(bounds_stream);
+        self
+    }
+
+    /// 设置检测器参数，模型路径一并替换
+    pub fn with_detector_config(mut self, detector: DetectorConfig) -> Self {
+        self.config.detector = detector;
+        self
+    }
+
+    /// 设置主循环的循环模式和间隔，参见[Perple::set_loop_schedule]
+    pub fn with_loop_schedule(mut self, mode: LoopMode, interval_ms: u64) -> Self {
+        self.config.loop_mode = mode;
+        self.config.loop_interval_ms = interval_ms;
+        self
+    }
+
+    /// 设置输入帧率上限，参见[Perple::set_max_input_fps]
+    pub fn with_max_input_fps(mut self, max_fps: Option<f32>) -> Self {
+        self.config.max_input_fps = max_fps;
+        self
+    }
+
+    /// 设置输出流已满时是否暂停检测循环，参见[Perple::with_backpressure]
+    pub fn with_backpressure(mut self, enabled: bool) -> Self {
+        self.config.backpressure = enabled;
+        self
+    }
+
+    /// 当前累积的参数
+    pub fn config(&self) -> &PerpleConfig {
+        &self.config
+    }
+
+    /// 检查参数、加载模型并创建实例
+    /// 
+    /// # 错误处理
+    /// 参数不合法时返回[PerpleError::InvalidParameter]且不加载模型；
+    /// 模型加载失败或不兼容时返回的错误与[YoloDetectorN::new]相同
+    pub fn build(self) -> Result<Perple<P, N>, PerpleError> {
+        self.config.validate()?;
+        let img_stream = self.img_stream.unwrap_or_else(|| Arc::new(Mutex::new(Stream::new())));
+        let bounds_stream = self.bounds_stream.unwrap_or_else(|| Arc::new(Mutex::new(Stream::new())));
+        Perple::from_config(img_stream, bounds_stream, &self.config)
+    }
+}
+
+/// [Perple::update_image]的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateResult {
+    /// 图像已写入输入流
+    Accepted,
+    /// 超出输入帧率上限，图像被拒绝或合并到最近一帧
+    RateLimited,
+    /// 输入流已满，图像被丢弃
+    QueueFull,
+}
+
+/// 超出输入帧率上限时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RateLimitPolicy {
+    /// 丢弃新图像
+    #[default]
+    Reject,
+    /// 用新图像替换输入流中尚未被读取的最新一帧，输入流为空时丢弃
+    CoalesceLatest,
+}
+
+/// [Perple]主流水线全部可调参数的快照
+/// 
+/// 包括检测器参数、主循环的循环模式和间隔、输入限流与背压策略以及推理超时等，
+/// 由[Perple::config]生成，用[Perple::apply_config]应用或[Perple::from_config]创建新实例。
+/// 回调、输出端、人数统计等运行时对象和附加流水线不在其中。
+/// 启用`config-file`特性时可保存为TOML文件，缺少的字段使用默认值。
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PerpleConfig {
+    /// [Perple::start_color_loop]使用的循环模式
+    pub loop_mode: LoopMode,
+    /// 主循环每次检测之间的间隔（毫秒）
+    pub loop_interval_ms: u64,
+    /// 输出流已满时是否暂停检测循环
+    pub backpressure: bool,
+    /// 输入帧率上限，`None`表示不限制
+    pub max_input_fps: Option<f32>,
+    /// 超出输入帧率上限时的处理方式
+    pub rate_limit_policy: RateLimitPolicy,
+    /// 检测数量统计的窗口大小（帧数）
+    pub histogram_window: usize,
+    /// 单次推理的超时时间（毫秒）
+    pub inference_timeout_ms: u64,
+    /// 运动检测阈值，`None`表示不做运动检测
+    pub motion_threshold: Option<f32>,
+    /// 单帧处理时间预算（毫秒），`None`表示不限制
+    pub frame_budget_ms: Option<u64>,
+    /// 检测器参数
+    pub detector: DetectorConfig,
+}
+
+impl Default for PerpleConfig {
+    fn default() -> Self {
+        Self {
+            loop_mode: LoopMode::Continuous,
+            loop_interval_ms: DEFAULT_LOOP_INTERVAL_MS,
+            backpressure: false,
+            max_input_fps: None,
+            rate_limit_policy: RateLimitPolicy::default(),
+            histogram_window: DEFAULT_HISTOGRAM_WINDOW,
+            inference_timeout_ms: DEFAULT_INFERENCE_TIMEOUT.as_millis() as u64,
+            motion_threshold: None,
+            frame_budget_ms: None,
+            detector: DetectorConfig::default(),
+        }
+    }
+}
+
+impl PerpleConfig {
+    /// 检查全部参数能否应用，[Perple::apply_config]在修改任何设置之前调用
+    /// 
+    /// # 错误处理
+    /// 检测器参数不合法（见[DetectorConfig::validate]）、输入帧率上限不是大于0的有限值、
+    /// 运动检测阈值为负或不是有限值，或窗口大小、推理超时、处理预算为0时返回[PerpleError::InvalidParameter]
+    pub fn validate(&self) -> Result<(), PerpleError> {
+        self.detector.validate()?;
+        if let Some(fps) = self.max_input_fps {
+            TokenBucket::new(fps, DEFAULT_RATE_LIMIT_BURST)?;
+        }
+        if let Some(threshold) = self.motion_threshold && !(threshold.is_finite() && threshold >= 0.0) {
+            return Err(PerpleError::InvalidParameter(format!("运动检测阈值必须是非负的有限值，实际为{}", threshold)));
+        }
+        for (name, value) in [
+            ("检测数量统计窗口", self.histogram_window as u64),
+            ("推理超时", self.inference_timeout_ms),
+            ("单帧处理预算", self.frame_budget_ms.unwrap_or(1)),
+        ] {
+            if value == 0 {
+                return Err(PerpleError::InvalidParameter(format!("{}不能为0", name)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "config-file")]
+impl PerpleConfig {
+    /// 从TOML文件读取，文件内容为[PerpleConfig::to_toml]的输出格式
+    /// 
+    /// # 错误处理
+    /// 读取失败返回[PerpleError::Io]，解析失败返回[PerpleError::Config]
+    pub fn load_from_toml(path: &str) -> Result<Self, PerpleError> {
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| PerpleError::Config(format!("无法解析配置文件 {}: {}", path, e)))
+    }
+    
+    /// 序列化为TOML文本
+    /// 
+    /// # 错误处理
+    /// 序列化失败时返回[PerpleError::Config]
+    pub fn to_toml(&self) -> Result<String, PerpleError> {
+        toml::to_string(self).map_err(|e| PerpleError::Config(e.to_string()))
+    }
+    
+    /// 保存为TOML文件
+    pub fn save_to_toml(&self, path: &str) -> Result<(), PerpleError> {
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+}
+
+/// 图像因限流被拒绝时的回调，参数为累计拒绝帧数
+pub type RateLimitCallback = Box<dyn Fn(u64) + Send + Sync>;
+
+/// 主流水线每产生一帧检测结果时的回调，参数为结果流中的元素
+pub type DetectionCallback<P = (), const N: usize = DETECTIONS_CAPACITY> = Box<dyn FnMut(&<P as Payload<N>>::Output) + Send>;
+
+/// 共享的输入图像流
+type FrameStream<P, const N: usize> = Arc<Mutex<Stream<<P as Payload<N>>::Frame>>>;
+
+/// 存在附加流水线时把`img_stream`中的图像分发给每条流水线（包括主流水线）专用的输入流
+/// 
+/// 这是图像进入各流水线的唯一分发点：无论图像由[Perple::update_frame]写入还是由外部直接写入
+/// `img_stream`，都在任一流水线的检测循环读取图像之前被分发，每条流水线收到相同的帧。
+/// 没有附加流水线时不分发，主流水线直接读取`img_stream`。
+struct InputTee<P: Payload<N>, const N: usize> {
+    source: FrameStream<P, N>,
+    /// 各流水线的输入流，主流水线排在最前；为空表示不分发
+    targets: Mutex<Vec<FrameStream<P, N>>>,
+}
+
+impl<P: Payload<N>, const N: usize> InputTee<P, N> {
+    fn new(source: FrameStream<P, N>) -> Self {
+        Self { source, targets: Mutex::new(Vec::new()) }
+    }
+
+    /// 取出`source`中的全部图像并复制到每个输入流，某个输入流已满时只丢弃该流水线的这一帧
+    fn distribute(&self) {
+        let targets = self.targets.lock().unwrap();
+        if targets.is_empty() {
+            return;
+        }
+        let mut source = self.source.lock().unwrap();
+        while let Some(frame) = source.read() {
+            let (payload, frame) = P::from_frame(frame);
+            for target in targets.iter().skip(1) {
+                let _ = target.lock().unwrap().write(payload.clone().into_frame(frame.clone()));
+            }
+            let _ = targets[0].lock().unwrap().write(payload.into_frame(frame));
+        }
+    }
+
+    /// 添加一条流水线的输入流；第一次添加时先为主流水线创建专用输入流并返回，调用方需让主流水线改读该流
+    fn add_target(&self, input_stream: FrameStream<P, N>) -> Option<FrameStream<P, N>> {
+        let mut targets = self.targets.lock().unwrap();
+        let main_input = targets.is_empty().then(|| {
+            let main_input = Arc::new(Mutex::new(Stream::new()));
+            targets.push(Arc::clone(&main_input));
+            main_input
+        });
+        targets.push(input_stream);
+        main_input
+    }
+
+    /// 所有流水线的输入流，不分发时为空
+    fn targets(&self) -> Vec<FrameStream<P, N>> {
+        self.targets.lock().unwrap().clone()
+    }
+}
+
+/// 一条附加检测流水线
+struct Pipeline<P: Payload<N>, const N: usize> {
+    name: String,
+    output_stream: Arc<Mutex<Stream<P::Output>>>,
+    color: Arc<Mutex<Color<P, N>>>,
+    color_loop: MultiLoop,
+    stats: Arc<PipelineStats>,
+    loop_mode: LoopMode,
+    interval_ms: u64,
+    scheduler_weight: u32,
+}
+
+/// 检测流水线的入口
+/// 
+/// 类型参数`P`为随每帧图像传递的[用户数据](Payload)，默认不附带数据，
+/// 此时`img_stream`和`bounds_stream`分别直接传递[Frame]和[Bounds]。
+/// 常量参数`N`为每帧检测结果的容量，默认为[DETECTIONS_CAPACITY]；检测器、结果流、输出端和回调使用同一容量，
+/// 例如`Perple<(), 8>`的结果流元素为[BoundsN<8>](BoundsN)。
+pub struct Perple<P: Payload<N> = (), const N: usize = DETECTIONS_CAPACITY> {
     /// 公用数据流，由上级管理
-    pub img_stream: Arc<Mutex<Stream<DynamicImage>>>,
-    pub bounds_stream: Arc<Mutex<Stream<Bounds>>>,
+    pub img_stream: Arc<Mutex<Stream<P::Frame>>>,
+    pub bounds_stream: Arc<Mutex<Stream<P::Output>>>,
 
     /// 内部模块私有数据
-    color: Arc<Mutex<Color>>,
+    color: Arc<Mutex<Color<P, N>>>,
     color_loop: MultiLoop,
+    /// 每帧检测数量的滚动统计，在每次推理后更新
+    histogram: Arc<Mutex<DetectionHistogram>>,
+    /// 运行统计，与color模块共享
+    stats: Arc<PipelineStats>,
+    /// 可选的人数统计器，在每次推理后用最新结果更新
+    person_counter: Arc<Mutex<Option<PersonCounter>>>,
+    /// 检测结果回调，在每次推理后用最新结果调用
+    detection_callbacks: Arc<Mutex<Vec<DetectionCallback<P, N>>>>,
+    /// 结果输出端，在每次推理后提交最新结果
+    sinks: Arc<Mutex<Vec<SinkRunner<N>>>>,
+    /// 快照保存器，在每次推理后输入本帧图像和结果
+    snapshots: Arc<Mutex<Vec<SnapshotSink>>>,
+    /// 检测结果信号，与color模块共享
+    detection_signal: Arc<SignalStream<STREAM_CAPACITY>>,
+    /// 附加检测流水线，按添加顺序排列
+    pipelines: Vec<Pipeline<P, N>>,
+    /// 存在附加流水线时向各流水线分发图像
+    tee: Arc<InputTee<P, N>>,
+    /// 输出流已满时是否暂停检测循环
+    backpressure: bool,
+    /// 输入帧率限流器，未设置上限时为`None`
+    rate_limiter: Mutex<Option<TokenBucket>>,
+    /// 超出输入帧率上限时的处理方式
+    rate_limit_policy: RateLimitPolicy,
+    /// 图像因限流被拒绝时的回调
+    rate_limit_callback: Option<RateLimitCallback>,
+    /// 各流水线共用的推理名额调度器，未启用时各循环独立运行
+    scheduler: Option<Arc<PipelineScheduler>>,
+    /// [start_color_loop](Perple::start_color_loop)使用的循环模式
+    loop_mode: LoopMode,
+    /// 主循环每次检测之间的间隔（毫秒）
+    loop_interval_ms: u64,
+    /// 最近一次写入的图像的帧序号
+    frame_seq: AtomicU64,
 }
 
 impl Perple {
     /// 推荐由外部传入公用数据流，减少拷贝和耦合
+    /// 
+    /// # 错误处理
+    /// 模型加载失败或与检测流程不兼容时返回Err
     pub fn new(
-        img_stream: Arc<Mutex<Stream<DynamicImage>>>,
+        img_stream: Arc<Mutex<Stream<Frame>>>,
         bounds_stream: Arc<Mutex<Stream<Bounds>>>,
         model_path: &str,
-    ) -> Self {
+    ) -> Result<Self, PerpleError> {
+        Self::from_streams(img_stream, bounds_stream, model_path)
+    }
+    
+    /// 更新图像流（推荐外部统一管理），参见[update_frame](Self::update_frame)
+    pub fn update_image(&self, new_image: DynamicImage) -> UpdateResult {
+        self.update_frame((), new_image)
+    }
+
+    /// 创建融合指定流水线结果的[Fuser]，各流水线的输出流接入融合器，融合结果写入新的输出流
+    /// 
+    /// 接入后这些流水线的结果由[Fuser::act]取走，不应再通过[results](Self::results)读取。
+    /// 同一帧在各流水线中的帧序号相同，融合器以此对齐结果。
+    /// 
+    /// # 参数
+    /// * `sources` - 参与融合的流水线名称，可以包括主流水线[DEFAULT_PIPELINE]
+    /// * `mode` - 融合方式
+    /// 
+    /// # 错误处理
+    /// 流水线名称不存在时返回Err
+    pub fn fuser(&self, sources: &[&str], mode: FusionMode) -> Result<Fuser, PerpleError> {
+        let mut fuser = Fuser::new(sources, mode, Arc::new(Mutex::new(Stream::new())));
+        for &name in sources {
+            let stream = self.results(name)
+                .ok_or_else(|| PerpleError::InvalidParameter(format!("流水线不存在: {}", name)))?;
+            fuser.connect(name, stream)?;
+        }
+        Ok(fuser)
+    }
+}
+
+impl<P: Payload<N>, const N: usize> Perple<P, N> {
+    /// 创建附带用户数据的实例，图像流和结果流的元素类型由`P`决定
+    /// 
+    /// ```no_run
+    /// use perple::Perple;
+    /// use perple::color::UserPayload;
+    /// use perple::utils::stream::Stream;
+    /// use std::sync::{Arc, Mutex};
+    /// 
+    /// #[derive(Debug, Clone, Default)]
+    /// struct CameraId(u32);
+    /// impl UserPayload for CameraId {}
+    /// 
+    /// let img_stream = Arc::new(Mutex::new(Stream::new()));
+    /// let bounds_stream = Arc::new(Mutex::new(Stream::new()));
+    /// let perple = Perple::<CameraId>::from_streams(img_stream, bounds_stream, "module/color/yolo11n.onnx")
+    ///     .expect("模型加载失败");
+    /// ```
+    /// 
+    /// # 错误处理
+    /// 模型加载失败或与检测流程不兼容时返回Err
+    pub fn from_streams(
+        img_stream: Arc<Mutex<Stream<P::Frame>>>,
+        bounds_stream: Arc<Mutex<Stream<P::Output>>>,
+        model_path: &str,
+    ) -> Result<Self, PerpleError> {
         let color = Color::new(
             Arc::clone(&img_stream),
             Arc::clone(&bounds_stream),
             model_path,
-        );
+        )?;
+        Ok(Self::from_color(img_stream, bounds_stream, color))
+    }
+
+    /// 创建共享输入流、使用另一个模型的新实例，用于A/B对比模型
+    /// 
+    /// 新实例与当前实例共用`img_stream`，但拥有独立的`bounds_stream`。
+    /// 不要让两个实例共用同一个输出流：两者会并发写入，结果帧交错且无法区分来源。
+    /// 
+    /// 注意输入流中的每帧图像只会被其中一个实例读取，两个实例处理的是不同的帧。
+    /// 需要两个模型处理完全相同的帧时，请使用[add_pipeline](Self::add_pipeline)。
+    /// 
+    /// # 参数
+    /// * `model_path` - 新模型的文件路径
+    /// 
+    /// # 错误处理
+    /// 模型加载失败或与检测流程不兼容时返回Err
+    pub fn clone_with_new_model(&self, model_path: &str) -> Result<Self, PerpleError> {
+        let detector = YoloDetectorN::new(model_path, DEFAULT_INPUT_WIDTH, DEFAULT_INPUT_HEIGHT)?;
+        let img_stream = Arc::clone(&self.img_stream);
+        let bounds_stream = Arc::new(Mutex::new(Stream::new()));
+        let color = Color::from_detector(Arc::clone(&img_stream), Arc::clone(&bounds_stream), detector)?;
+        Ok(Self::from_color(img_stream, bounds_stream, color))
+    }
+
+    /// 使用自定义检测函数创建实例，例如包装其他推理后端或在测试中模拟检测器
+    /// 
+    /// 检测函数的要求见[Color::from_fn]。没有检测器，因此[config](Self::config)返回`None`，
+    /// [self_test](Self::self_test)返回错误。
+    pub fn from_fn<F>(
+        img_stream: Arc<Mutex<Stream<P::Frame>>>,
+        bounds_stream: Arc<Mutex<Stream<P::Output>>>,
+        detect: F,
+    ) -> Self
+    where
+        F: FnMut(&DynamicImage) -> Result<BoundsN<N>, PerpleError> + Send + 'static,
+    {
+        let color = Color::from_fn(Arc::clone(&img_stream), Arc::clone(&bounds_stream), detect);
+        Self::from_color(img_stream, bounds_stream, color)
+    }
+
+    fn from_color(
+        img_stream: Arc<Mutex<Stream<P::Frame>>>,
+        bounds_stream: Arc<Mutex<Stream<P::Output>>>,
+        color: Color<P, N>,
+    ) -> Self {
+        let stats = Arc::clone(color.stats());
         
         Self {
+            tee: Arc::new(InputTee::new(Arc::clone(&img_stream))),
             img_stream,
             bounds_stream,
             color: Arc::new(Mutex::new(color)),
             color_loop: MultiLoop::new(),
+            histogram: Arc::new(Mutex::new(DetectionHistogram::default())),
+            stats,
+            person_counter: Arc::new(Mutex::new(None)),
+            detection_callbacks: Arc::new(Mutex::new(Vec::new())),
+            sinks: Arc::new(Mutex::new(Vec::new())),
+            snapshots: Arc::new(Mutex::new(Vec::new())),
+            detection_signal: Arc::new(SignalStream::new()),
+            pipelines: Vec::new(),
+            backpressure: false,
+            rate_limiter: Mutex::new(None),
+            rate_limit_policy: RateLimitPolicy::default(),
+            rate_limit_callback: None,
+            scheduler: None,
+            loop_mode: LoopMode::Continuous,
+            loop_interval_ms: DEFAULT_LOOP_INTERVAL_MS,
+            frame_seq: AtomicU64::new(0),
+        }
+    }
+
+    /// 设置是否启用背压控制
+    /// 
+    /// 启用后，检测循环在输出流已满时暂停读取新图像，直到下游取走结果，
+    /// 而不是读取图像、推理后再丢弃结果。对之后启动的循环生效。
+    pub fn with_backpressure(mut self, enabled: bool) -> Self {
+        self.backpressure = enabled;
+        self
+    }
+
+    /// 设置是否启用背压控制（可变引用版本）
+    pub fn set_backpressure(&mut self, enabled: bool) {
+        self.backpressure = enabled;
+    }
+
+    /// 添加一条附加检测流水线
+    /// 
+    /// 流水线使用独立的模型和输出流，与主流水线处理相同的图像。
+    /// 添加后需调用[start_all](Self::start_all)或[start_pipeline](Self::start_pipeline)才会开始检测。
+    /// 
+    /// # 参数
+    /// * `name` - 流水线名称，不能与已有流水线（包括主流水线[DEFAULT_PIPELINE]）重名
+    /// * `config` - 流水线配置
+    /// 
+    /// # 错误处理
+    /// 名称重复或检测器创建失败时返回Err
+    pub fn add_pipeline(&mut self, name: &str, config: PipelineConfig) -> Result<(), PerpleError> {
+        self.check_pipeline_name(name)?;
+        let mut detector = match &config.model_options {
+            Some(options) => YoloDetectorN::with_model_options(&config.model_path, config.input_width, config.input_height, options)?,
+            None => YoloDetectorN::new(&config.model_path, config.input_width, config.input_height)?,
+        };
+        if let Some(threshold) = config.confidence_threshold {
+            detector.set_confidence_threshold(threshold);
+        }
+        if let Some(threshold) = config.nms_threshold {
+            detector.set_nms_threshold(threshold);
         }
+        self.push_pipeline(name, &config, |input_stream, output_stream| Color::from_detector(input_stream, output_stream, detector))
+    }
+
+    /// 添加一条使用自定义检测函数的附加流水线，例如包装其他推理后端或在测试中模拟检测器
+    /// 
+    /// 检测函数的要求见[Color::from_fn]。`config`中只有循环模式、间隔和调度权重生效，
+    /// 模型路径、输入尺寸和阈值等模型相关的设置被忽略。
+    /// 
+    /// # 错误处理
+    /// 名称重复时返回Err
+    pub fn add_pipeline_fn<F>(&mut self, name: &str, config: PipelineConfig, detect: F) -> Result<(), PerpleError>
+    where
+        F: FnMut(&DynamicImage) -> Result<BoundsN<N>, PerpleError> + Send + 'static,
+    {
+        self.check_pipeline_name(name)?;
+        self.push_pipeline(name, &config, |input_stream, output_stream| Ok(Color::from_fn(input_stream, output_stream, detect)))
+    }
+
+    fn check_pipeline_name(&self, name: &str) -> Result<(), PerpleError> {
+        if name == DEFAULT_PIPELINE || self.pipeline(name).is_some() {
+            return Err(PerpleError::InvalidParameter(format!("流水线名称已存在: {}", name)));
+        }
+        Ok(())
+    }
+
+    /// 创建附加流水线的输入输出流并接入分发点；添加第一条附加流水线时主流水线改读专用输入流
+    fn push_pipeline<F>(&mut self, name: &str, config: &PipelineConfig, build: F) -> Result<(), PerpleError>
+    where
+        F: FnOnce(Arc<Mutex<Stream<P::Frame>>>, Arc<Mutex<Stream<P::Output>>>) -> Result<Color<P, N>, PerpleError>,
+    {
+        let input_stream = Arc::new(Mutex::new(Stream::new()));
+        let output_stream = Arc::new(Mutex::new(Stream::new()));
+        // 先创建流水线，失败时分发点和主流水线保持不变；流水线的输入流只由分发点持有和写入
+        let color = build(Arc::clone(&input_stream), Arc::clone(&output_stream))?;
+        if let Some(main_input) = self.tee.add_target(input_stream) {
+            self.color.lock().unwrap().set_input_stream(main_input);
+        }
+        let stats = Arc::clone(color.stats());
+        self.pipelines.push(Pipeline {
+            name: name.to_string(),
+            output_stream,
+            color: Arc::new(Mutex::new(color)),
+            color_loop: MultiLoop::new(),
+            stats,
+            loop_mode: config.loop_mode,
+            interval_ms: config.interval_ms,
+            scheduler_weight: config.scheduler_weight,
+        });
+        if let Some(scheduler) = &self.scheduler {
+            scheduler.register(name, config.scheduler_weight);
+        }
+        Ok(())
+    }
+
+    /// 全部流水线的名称，主流水线排在最前
+    pub fn pipeline_names(&self) -> Vec<&str> {
+        std::iter::once(DEFAULT_PIPELINE)
+            .chain(self.pipelines.iter().map(|p| p.name.as_str()))
+            .collect()
+    }
+
+    /// 获取指定流水线的输出流，名称不存在时返回`None`
+    pub fn results(&self, name: &str) -> Option<Arc<Mutex<Stream<P::Output>>>> {
+        if name == DEFAULT_PIPELINE {
+            return Some(Arc::clone(&self.bounds_stream));
+        }
+        self.pipeline(name).map(|p| Arc::clone(&p.output_stream))
+    }
+
+    /// 获取指定流水线的运行统计，名称不存在时返回`None`
+    pub fn pipeline_stats(&self, name: &str) -> Option<&PipelineStats> {
+        if name == DEFAULT_PIPELINE {
+            return Some(&self.stats);
+        }
+        self.pipeline(name).map(|p| p.stats.as_ref())
+    }
+
+    /// 按各自配置的循环模式启动指定的附加流水线
+    pub fn start_pipeline(&mut self, name: &str) -> Result<(), PerpleError> {
+        let pipeline = self.pipelines.iter_mut()
+            .find(|p| p.name == name)
+            .ok_or_else(|| PerpleError::InvalidParameter(format!("流水线不存在: {}", name)))?;
+        let color = Arc::clone(&pipeline.color);
+        let tee = Arc::clone(&self.tee);
+        let scheduled = scheduled(&self.scheduler, name);
+        let should_pause = pause_when_full(&pipeline.output_stream, self.backpressure);
+        pipeline.color_loop.start_with_backpressure(pipeline.loop_mode, move || {
+            let slot = scheduled.as_ref().map(|(scheduler, id)| scheduler.acquire(*id));
+            tee.distribute();
+            if color.lock().unwrap().act().is_some() && let Some(slot) = &slot {
+                slot.record_frame();
+            }
+        }, should_pause, pipeline.interval_ms)
+    }
+
+    /// 让全部流水线（包括主流水线）共用推理名额调度器，替换已有的调度器
+    /// 
+    /// 主流水线以[DEFAULT_PIPELINE]为名、权重1注册，附加流水线按名称和
+    /// [PipelineConfig::with_scheduler_weight]设置的权重注册，之后添加的流水线也会自动注册；
+    /// 可用[PipelineScheduler::set_weight]调整权重。只对此后启动的循环生效。
+    /// 一个调度器可以被多个[Perple]实例共用，此时流水线名称应互不相同。
+    pub fn set_scheduler(&mut self, scheduler: Arc<PipelineScheduler>) {
+        if scheduler.id(DEFAULT_PIPELINE).is_none() {
+            scheduler.register(DEFAULT_PIPELINE, 1);
+        }
+        for pipeline in &self.pipelines {
+            scheduler.register(&pipeline.name, pipeline.scheduler_weight);
+        }
+        self.scheduler = Some(scheduler);
+    }
+
+    /// 当前使用的推理名额调度器
+    pub fn scheduler(&self) -> Option<&Arc<PipelineScheduler>> {
+        self.scheduler.as_ref()
+    }
+
+    /// 指定流水线在调度器统计中的平均帧率，未启用调度器或名称不存在时返回`None`
+    pub fn pipeline_fps(&self, name: &str) -> Option<f32> {
+        self.scheduler.as_ref()?.achieved_fps(name)
+    }
+
+    /// 启动全部流水线
+    /// 
+    /// 主流水线按[set_loop_schedule](Self::set_loop_schedule)设置的循环模式（默认持续循环）启动，附加流水线按各自配置的循环模式启动，
+    /// 已在运行的流水线保持不变。
+    pub fn start_all(&mut self) -> Result<(), PerpleError> {
+        if !self.color_loop.is_running() {
+            self.start_color_loop()?;
+        }
+        let idle: Vec<String> = self.pipelines.iter()
+            .filter(|p| !p.color_loop.is_running())
+            .map(|p| p.name.clone())
+            .collect();
+        for name in idle {
+            self.start_pipeline(&name)?;
+        }
+        Ok(())
+    }
+
+    /// 停止全部流水线（包括主流水线）
+    pub fn stop_all(&mut self) {
+        self.color_loop.stop();
+        for pipeline in self.pipelines.iter_mut() {
+            pipeline.color_loop.stop();
+        }
+    }
+
+    /// 等待全部流水线的线程结束，返回遇到的第一个错误
+    pub fn join_all(&mut self) -> Result<(), PerpleError> {
+        let mut result = self.color_loop.join();
+        for pipeline in self.pipelines.iter_mut() {
+            let joined = pipeline.color_loop.join()
+                .map_err(|_| PerpleError::WorkerPanicked(format!("流水线{}", pipeline.name)));
+            result = result.and(joined);
+        }
+        result
+    }
+
+    fn pipeline(&self, name: &str) -> Option<&Pipeline<P, N>> {
+        self.pipelines.iter().find(|p| p.name == name)
     }
 
     /// 启动color模块的循环运行模式
     /// 支持按次数、按时间或持续循环
-    pub fn start_color_loop_with_mode(&mut self, mode: LoopMode) -> Result<(), String> {
+    pub fn start_color_loop_with_mode(&mut self, mode: LoopMode) -> Result<(), PerpleError> {
         // 创建闭包，捕获color的引用
         let color = Arc::clone(&self.color);
-        self.color_loop.start(mode, move || {
-            let mut color_guard = color.lock().unwrap();
-            color_guard.act();
-        }, 100) // 100ms间隔
+        let tee = Arc::clone(&self.tee);
+        let histogram = Arc::clone(&self.histogram);
+        let person_counter = Arc::clone(&self.person_counter);
+        let detection_callbacks = Arc::clone(&self.detection_callbacks);
+        let sinks = Arc::clone(&self.sinks);
+        let snapshots = Arc::clone(&self.snapshots);
+        let detection_signal = Arc::clone(&self.detection_signal);
+        let scheduled = scheduled(&self.scheduler, DEFAULT_PIPELINE);
+        let should_pause = pause_when_full(&self.bounds_stream, self.backpressure);
+        self.color_loop.start_with_backpressure(mode, move || {
+            let slot = scheduled.as_ref().map(|(scheduler, id)| scheduler.acquire(*id));
+            tee.distribute();
+            if color_step(&color, &histogram, &person_counter, &detection_callbacks, &sinks, &snapshots, &detection_signal) && let Some(slot) = &slot {
+                slot.record_frame();
+            }
+        }, should_pause, self.loop_interval_ms)
+    }
+    
+    /// 在当前线程阻塞地运行color模块的循环，直到循环结束
+    /// 
+    /// 适合按次数或按时间运行的场景，返回后即可读取结果，无需再调用[join_color_thread](Self::join_color_thread)。
+    /// [LoopMode::Continuous]模式下不会自行结束，请改用[start_color_loop](Self::start_color_loop)。
+    /// 
+    /// # 返回值
+    /// 循环的执行统计；主循环已在运行时返回Err
+    pub fn run_color_loop_blocking(&mut self, mode: LoopMode) -> Result<LoopStats, PerpleError> {
+        let (color, histogram, person_counter, detection_callbacks, sinks, snapshots, detection_signal) =
+            (&self.color, &self.histogram, &self.person_counter, &self.detection_callbacks, &self.sinks, &self.snapshots, &self.detection_signal);
+        let scheduled = scheduled(&self.scheduler, DEFAULT_PIPELINE);
+        self.color_loop.run_scoped(mode, self.loop_interval_ms, || {
+            let slot = scheduled.as_ref().map(|(scheduler, id)| scheduler.acquire(*id));
+            self.tee.distribute();
+            if color_step(color, histogram, person_counter, detection_callbacks, sinks, snapshots, detection_signal) && let Some(slot) = &slot {
+                slot.record_frame();
+            }
+            true
+        })
+    }
+    
+    /// 按[set_loop_schedule](Self::set_loop_schedule)设置的循环模式启动color模块（默认持续循环）
+    pub fn start_color_loop(&mut self) -> Result<(), PerpleError> {
+        self.start_color_loop_with_mode(self.loop_mode)
+    }
+    
+    /// 设置主循环的循环模式和间隔，只对此后启动的循环生效
+    /// 
+    /// # 参数
+    /// * `mode` - [start_color_loop](Self::start_color_loop)和[start_all](Self::start_all)使用的循环模式，默认持续循环
+    /// * `interval_ms` - 每次检测之间的间隔（毫秒），默认为[DEFAULT_LOOP_INTERVAL_MS]
+    pub fn set_loop_schedule(&mut self, mode: LoopMode, interval_ms: u64) {
+        self.loop_mode = mode;
+        self.loop_interval_ms = interval_ms;
+    }
+    
+    /// 主循环的循环模式
+    pub fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
     }
     
-    /// 启动color模块的循环运行模式（默认持续循环）
-    pub fn start_color_loop(&mut self) -> Result<(), String> {
-        self.start_color_loop_with_mode(LoopMode::Continuous)
+    /// 主循环每次检测之间的间隔（毫秒）
+    pub fn loop_interval_ms(&self) -> u64 {
+        self.loop_interval_ms
     }
     
     /// 启动指定次数的循环运行模式
-    pub fn start_color_loop_count(&mut self, count: usize) -> Result<(), String> {
+    pub fn start_color_loop_count(&mut self, count: usize) -> Result<(), PerpleError> {
         self.start_color_loop_with_mode(LoopMode::Count(count))
     }
     
     /// 启动指定时间的循环运行模式（毫秒）
-    pub fn start_color_loop_duration(&mut self, duration_ms: u64) -> Result<(), String> {
+    pub fn start_color_loop_duration(&mut self, duration_ms: u64) -> Result<(), PerpleError> {
         self.start_color_loop_with_mode(LoopMode::Duration(duration_ms))
     }
     
@@ -74,17 +871,399 @@ impl Perple {
         self.color_loop.is_running()
     }
 
-    /// 更新图像流（推荐外部统一管理）
-    pub fn update_image(&self, new_image: DynamicImage) {
+    /// 获取检测数量滚动统计的快照
+    pub fn detection_histogram(&self) -> DetectionHistogram {
+        self.histogram.lock().unwrap().clone()
+    }
+    
+    /// 获取运行统计（推理延迟直方图等），读取时无需加锁
+    pub fn stats(&self) -> &PipelineStats {
+        &self.stats
+    }
+    
+    /// 检查主流水线能否继续检测，参见[Color::health]
+    /// 
+    /// # 错误处理
+    /// 推理线程异常退出、检测器状态已丢失时返回[PerpleError::EngineLost]
+    pub fn health(&self) -> Result<(), PerpleError> {
+        self.color.lock().unwrap().health()
+    }
+    
+    /// 获取检测结果信号
+    /// 
+    /// 主流水线每有一帧结果写入`bounds_stream`就发出一个信号，发出时本帧的[检测回调](Self::on_detection)都已执行完毕；
+    /// 可用[SignalStream::wait_signal]无锁地判断是否有新结果，再去读取结果流。
+    pub fn detection_signal(&self) -> &Arc<SignalStream<STREAM_CAPACITY>> {
+        &self.detection_signal
+    }
+    
+    /// 启用人数统计，替换已有的统计器
+    /// 
+    /// 人数变化回调在检测线程中执行且持有统计器的锁，回调内不要调用本实例的人数统计方法。
+    /// 超出处理预算而降级的帧不计入人数统计。
+    pub fn set_person_counter(&self, counter: PersonCounter) {
+        *self.person_counter.lock().unwrap() = Some(counter);
+    }
+    
+    /// 当前（去抖动后的）人数，未启用人数统计时返回`None`
+    pub fn person_count(&self) -> Option<usize> {
+        self.person_counter.lock().unwrap().as_ref().map(|c| c.current_count())
+    }
+    
+    /// 上次重置以来的最大人数，未启用人数统计时返回`None`
+    pub fn max_person_count(&self) -> Option<usize> {
+        self.person_counter.lock().unwrap().as_ref().map(|c| c.max_count_since_reset())
+    }
+    
+    /// 重置最大人数统计
+    pub fn reset_person_counter(&self) {
+        if let Some(counter) = self.person_counter.lock().unwrap().as_mut() {
+            counter.reset();
+        }
+    }
+    
+    /// 注册检测结果回调，主流水线每产生一帧结果调用一次
+    /// 
+    /// 回调在检测线程中、本帧结果写入`bounds_stream`之前执行，收到的就是本帧结果；
+    /// 输出流已满、结果被丢弃时回调同样会收到该帧，
+    /// 超出处理预算而[降级](Bounds::is_degraded)的帧则不会调用回调。回调期间持有检测器的锁，不要在回调内调用本实例中需要访问检测器的方法（如[config](Self::config)）。
+    pub fn on_detection(&self, callback: DetectionCallback<P, N>) {
+        self.detection_callbacks.lock().unwrap().push(callback);
+    }
+    
+    /// 以异步通道接收主流水线的检测结果，通道容量为[STREAM_CAPACITY]
+    /// 
+    /// 需要启用`tokio`特性，参见[stream_detections_with_capacity](Self::stream_detections_with_capacity)。
+    #[cfg(feature = "tokio")]
+    pub fn stream_detections(&self) -> tokio::sync::mpsc::Receiver<P::Output> {
+        self.stream_detections_with_capacity(STREAM_CAPACITY)
+    }
+    
+    /// 以指定容量的异步通道接收主流水线的检测结果
+    /// 
+    /// 通过[on_detection](Self::on_detection)注册回调，将每帧结果复制一份发送到通道。
+    /// 发送不会阻塞检测线程：通道已满时丢弃本帧，接收端被丢弃后不再复制结果。
+    /// 
+    /// # 参数
+    /// * `capacity` - 通道容量，必须大于0
+    #[cfg(feature = "tokio")]
+    pub fn stream_detections_with_capacity(&self, capacity: usize) -> tokio::sync::mpsc::Receiver<P::Output> {
+        use tokio::sync::mpsc::error::TrySendError;
+        
+        let (sender, receiver) = tokio::sync::mpsc::channel(capacity);
+        self.on_detection(Box::new(move |output| {
+            if sender.is_closed() {
+                return;
+            }
+            if let Err(TrySendError::Full(_)) = sender.try_send(output.clone()) {
+                log::debug!("检测结果通道已满，丢弃本帧结果");
+            }
+        }));
+        receiver
+    }
+    
+    /// 添加结果输出端，使用默认的队列容量和丢弃策略
+    /// 
+    /// 输出端在独立线程中按顺序收到主流水线的每帧结果，[降级](Bounds::is_degraded)的帧除外。
+    /// 本实例被drop时先停止全部检测循环，再等待各输出端处理完剩余结果并关闭。
+    pub fn add_sink(&self, sink: Box<dyn ResultSink<N>>) {
+        self.add_sink_runner(SinkRunner::spawn(sink));
+    }
+    
+    /// 添加已启动的结果输出端，可自定义队列容量和队列满时的处理方式
+    pub fn add_sink_runner(&self, runner: SinkRunner<N>) {
+        self.sinks.lock().unwrap().push(runner);
+    }
+    
+    /// 添加快照保存器，画面中出现配置的类别时自动保存绘制了检测框的JPEG
+    /// 
+    /// 与输出端一样跳过降级帧，写盘错误通过[sink_errors](Self::sink_errors)取出。
+    /// 
+    /// # 错误处理
+    /// 无法创建输出目录时返回Err
+    pub fn add_snapshot_sink(&self, config: SnapshotConfig) -> Result<(), PerpleError> {
+        let sink = SnapshotSink::spawn(config)?;
+        self.snapshots.lock().unwrap().push(sink);
+        Ok(())
+    }
+    
+    /// 取出各输出端和快照保存器产生的全部错误
+    pub fn sink_errors(&self) -> Vec<PerpleError> {
+        let mut errors: Vec<PerpleError> = self.sinks.lock().unwrap().iter()
+            .flat_map(|sink| std::iter::from_fn(|| sink.try_recv_error()))
+            .collect();
+        errors.extend(self.snapshots.lock().unwrap().iter().flat_map(|sink| std::iter::from_fn(|| sink.try_recv_error())));
+        errors
+    }
+    
+    /// 调整检测数量统计的窗口大小（帧数）
+    pub fn set_histogram_window(&self, frames: usize) {
+        self.histogram.lock().unwrap().set_window(frames);
+    }
+    
+    /// 设置输入帧率上限，`None`表示不限制
+    /// 
+    /// 按令牌桶限流：平均帧率不超过`max_fps`，同时允许至多[DEFAULT_RATE_LIMIT_BURST]帧的短时突发。
+    /// 超出上限的图像按[set_rate_limit_policy](Self::set_rate_limit_policy)设置的方式处理，
+    /// 并计入[PipelineStats::rate_limited_frames]。
+    /// 
+    /// # 参数
+    /// * `max_fps` - 每秒最多接受的图像数，必须是大于0的有限值
+    /// 
+    /// # 错误处理
+    /// `max_fps`不合法时返回[PerpleError::InvalidParameter]，此时原有设置保持不变
+    pub fn set_max_input_fps(&mut self, max_fps: Option<f32>) -> Result<(), PerpleError> {
+        let limiter = max_fps.map(|fps| TokenBucket::new(fps, DEFAULT_RATE_LIMIT_BURST)).transpose()?;
+        *self.rate_limiter.get_mut().unwrap() = limiter;
+        Ok(())
+    }
+    
+    /// 获取输入帧率上限
+    pub fn max_input_fps(&self) -> Option<f32> {
+        self.rate_limiter.lock().unwrap().as_ref().map(|bucket| bucket.rate())
+    }
+    
+    /// 设置超出输入帧率上限时的处理方式，默认为[RateLimitPolicy::Reject]
+    pub fn set_rate_limit_policy(&mut self, policy: RateLimitPolicy) {
+        self.rate_limit_policy = policy;
+    }
+    
+    /// 设置图像因限流被拒绝时的回调，替换已有的回调
+    /// 
+    /// 回调在调用[update_frame](Self::update_frame)的线程中执行。
+    pub fn set_rate_limit_callback(&mut self, callback: RateLimitCallback) {
+        self.rate_limit_callback = Some(callback);
+    }
+    
+    /// 主流水线当前全部可调参数的快照
+    /// 
+    /// 检测器不可用（上次推理超时未返回，或使用[自定义检测函数](Self::from_fn)）时返回`None`。
+    pub fn config(&self) -> Option<PerpleConfig> {
+        let color = self.color.lock().unwrap();
+        let detector = color.model()?.config();
+        Some(self.pipeline_config(&color, detector))
+    }
+    
+    /// 由检测器参数和主流水线当前的其余参数组成快照
+    fn pipeline_config(&self, color: &Color<P, N>, detector: DetectorConfig) -> PerpleConfig {
+        PerpleConfig {
+            loop_mode: self.loop_mode,
+            loop_interval_ms: self.loop_interval_ms,
+            backpressure: self.backpressure,
+            max_input_fps: self.max_input_fps(),
+            rate_limit_policy: self.rate_limit_policy,
+            histogram_window: self.histogram.lock().unwrap().window_frames(),
+            inference_timeout_ms: color.inference_timeout().as_millis() as u64,
+            motion_threshold: color.motion_threshold(),
+            frame_budget_ms: color.frame_budget().map(|budget| budget.0.as_millis() as u64),
+            detector,
+        }
+    }
+    
+    /// 应用参数快照，检测器参数见[YoloDetectorN::apply_config](crate::color::YoloDetectorN::apply_config)
+    /// 
+    /// 循环模式和间隔只对此后启动的循环生效，背压设置只对此后启动的流水线生效。
+    /// 
+    /// # 错误处理
+    /// 参数不合法（见[PerpleConfig::validate]）或检测器不可用时返回[PerpleError::InvalidParameter]，
+    /// 所有参数在修改之前检查，出错时全部保持不变
+    pub fn apply_config(&mut self, config: &PerpleConfig) -> Result<(), PerpleError> {
+        config.validate()?;
+        let color = Arc::clone(&self.color);
+        let mut color = color.lock().unwrap();
+        color.model_mut()
+            .ok_or_else(|| PerpleError::InvalidParameter("检测器不可用，无法应用配置".to_string()))?
+            .apply_config(&config.detector)?;
+        self.apply_pipeline_config(&mut color, config)
+    }
+    
+    /// 应用快照中检测器以外的参数，调用前须已通过[PerpleConfig::validate]
+    fn apply_pipeline_config(&mut self, color: &mut Color<P, N>, config: &PerpleConfig) -> Result<(), PerpleError> {
+        let PerpleConfig {
+            loop_mode,
+            loop_interval_ms,
+            backpressure,
+            max_input_fps,
+            rate_limit_policy,
+            histogram_window,
+            inference_timeout_ms,
+            motion_threshold,
+            frame_budget_ms,
+            detector: _,
+        } = config;
+        color.set_inference_timeout(Duration::from_millis(*inference_timeout_ms));
+        color.set_motion_threshold(*motion_threshold);
+        color.set_frame_budget(frame_budget_ms.map(|ms| FrameBudget(Duration::from_millis(ms))));
+        self.set_loop_schedule(*loop_mode, *loop_interval_ms);
+        self.backpressure = *backpressure;
+        if *max_input_fps != self.max_input_fps() {
+            self.set_max_input_fps(*max_input_fps)?;
+        }
+        self.rate_limit_policy = *rate_limit_policy;
+        self.set_histogram_window(*histogram_window);
+        Ok(())
+    }
+    
+    /// 按参数快照创建实例：加载`config.detector.model_path`指定的模型并应用全部参数
+    /// 
+    /// # 错误处理
+    /// 与[YoloDetectorN::from_config](crate::color::YoloDetectorN::from_config)相同
+    pub fn from_config(
+        img_stream: Arc<Mutex<Stream<P::Frame>>>,
+        bounds_stream: Arc<Mutex<Stream<P::Output>>>,
+        config: &PerpleConfig,
+    ) -> Result<Self, PerpleError> {
+        config.validate()?;
+        let detector = YoloDetectorN::from_config(&config.detector)?;
+        let color = Color::from_detector(Arc::clone(&img_stream), Arc::clone(&bounds_stream), detector)?;
+        let mut perple = Self::from_color(img_stream, bounds_stream, color);
+        perple.apply_config(config)?;
+        Ok(perple)
+    }
+    
+    /// 按TOML配置文件创建实例，文件格式见[PerpleConfig::load_from_toml]
+    #[cfg(feature = "config-file")]
+    pub fn from_config_file(
+        img_stream: Arc<Mutex<Stream<P::Frame>>>,
+        bounds_stream: Arc<Mutex<Stream<P::Output>>>,
+        path: &str,
+    ) -> Result<Self, PerpleError> {
+        Self::from_config(img_stream, bounds_stream, &PerpleConfig::load_from_toml(path)?)
+    }
+    
+    /// 写入一帧附带用户数据的图像，数据会随该帧的检测结果一起输出
+    /// 
+    /// 存在附加流水线时，图像和数据在各流水线读取前由同一分发点复制给每条流水线，
+    /// 因此直接写入`img_stream`的图像同样会被所有流水线处理。
+    /// 设置了[输入帧率上限](Self::set_max_input_fps)时，超出上限的图像不会作为新帧写入。
+    /// 
+    /// 每次调用都分配一个新的[帧序号](Frame::seq)，包括被限流拒绝的图像，
+    /// 因此检测时能从序号的间断发现被丢弃的帧。
+    /// 
+    /// # 返回值
+    /// `img_stream`的写入结果；分发时某条流水线的输入流已满只丢弃该流水线的这一帧
+    pub fn update_frame(&self, payload: P, new_image: DynamicImage) -> UpdateResult {
+        let seq = self.frame_seq.fetch_add(1, Ordering::Relaxed) + 1;
+        let new_frame = Frame::new(new_image, seq);
+        let limited = self.rate_limiter.lock().unwrap().as_mut()
+            .is_some_and(|bucket| !bucket.try_acquire());
+        if limited {
+            if self.rate_limit_policy == RateLimitPolicy::CoalesceLatest {
+                let _ = self.img_stream.lock().unwrap().replace_latest(payload.into_frame(new_frame));
+            }
+            let rejected = self.stats.record_rate_limited_frame();
+            if let Some(callback) = &self.rate_limit_callback {
+                callback(rejected);
+            }
+            return UpdateResult::RateLimited;
+        }
+        
         let mut img_stream = self.img_stream.lock().unwrap();
-        let _ = img_stream.write(new_image);
+        match img_stream.write(payload.into_frame(new_frame)) {
+            Ok(()) => UpdateResult::Accepted,
+            Err(_) => UpdateResult::QueueFull,
+        }
+    }
+    
+    /// 最近一次写入的图像的[帧序号](Frame::seq)，尚未写入图像时为0
+    pub fn frame_seq(&self) -> u64 {
+        self.frame_seq.load(Ordering::Relaxed)
+    }
+    
+    /// 丢弃输入流中所有尚未处理的图像，例如断线重连后清除积压的过期帧
+    /// 
+    /// 只在清空期间短暂持有流的锁；检测循环只在读取图像时持有输入流的锁，
+    /// 因此正在推理的那一帧不受影响。已分发给附加流水线的副本一并清空，不计入返回值。
+    /// 
+    /// # 返回值
+    /// 返回主流水线尚未处理的图像数量（包括尚未分发的图像）
+    pub fn flush_input_stream(&self) -> usize {
+        let targets = self.tee.targets();
+        let mut flushed = self.img_stream.lock().unwrap().drain();
+        for (index, target) in targets.iter().enumerate() {
+            let drained = target.lock().unwrap().drain();
+            if index == 0 {
+                flushed += drained;
+            }
+        }
+        flushed
+    }
+    
+    /// 丢弃结果流中所有尚未被读取的检测结果
+    /// 
+    /// 与[flush_input_stream](Self::flush_input_stream)相同，只短暂持有流的锁。
+    /// 尚未消费的[检测结果信号](Self::detection_signal)一并清除，附加流水线的结果流不受影响。
+    /// 
+    /// # 返回值
+    /// 返回被丢弃的结果数量
+    pub fn flush_output_stream(&self) -> usize {
+        let mut bounds_stream = self.bounds_stream.lock().unwrap();
+        self.detection_signal.clear_signals();
+        bounds_stream.drain()
+    }
+    
+    /// 依次清空输入流和结果流，返回`(输入流丢弃数, 结果流丢弃数)`
+    pub fn flush_all(&self) -> (usize, usize) {
+        (self.flush_input_stream(), self.flush_output_stream())
     }
     
     /// 等待颜色处理线程结束
-    pub fn join_color_thread(&mut self) -> Result<(), String> {
+    pub fn join_color_thread(&mut self) -> Result<(), PerpleError> {
         self.color_loop.join()
     }
     
+    /// 根据最近的检测结果自动调整置信度阈值
+    /// 
+    /// 查看输出流中最近的若干帧结果（不会消费它们）：
+    /// - 平均每帧检测数少于`target_min_detections`时，将阈值降低一个步长，最低不低于下限
+    /// - 每一帧的检测数都达到高位时，将阈值提高一个步长，最高不超过上限
+    /// 
+    /// 适用于雾天、夜间、运动模糊等置信度整体偏低的场景。每次阈值变化都会记录info日志。
+    /// 
+    /// # 参数
+    /// * `target_min_detections` - 期望的每帧最少检测数
+    pub fn update_model_thresholds_from_results(&mut self, target_min_detections: usize) {
+        let (frame_counts, mut confidences) = {
+            let bounds_stream = self.bounds_stream.lock().unwrap();
+            let recent: Vec<&BoundsN<N>> = bounds_stream.peek_recent(ADAPTIVE_THRESHOLD_WINDOW)
+                .into_iter()
+                .map(P::bounds)
+                .collect();
+            let frame_counts: Vec<usize> = recent.iter().map(|bounds| bounds.len()).collect();
+            let confidences: Vec<f32> = recent.iter()
+                .flat_map(|bounds| bounds.iter().map(|d| d.confidence))
+                .collect();
+            (frame_counts, confidences)
+        };
+        if frame_counts.is_empty() {
+            return;
+        }
+        
+        let mean_count = frame_counts.iter().sum::<usize>() as f32 / frame_counts.len() as f32;
+        confidences.sort_unstable_by(|a, b| a.total_cmp(b));
+        let median_confidence = confidences.get(confidences.len() / 2).copied();
+        
+        let mut color = self.color.lock().unwrap();
+        let Some(current) = color.model().map(|model| model.confidence_threshold()) else {
+            return;
+        };
+        let updated = if mean_count < target_min_detections as f32 {
+            (current - ADAPTIVE_THRESHOLD_STEP).max(ADAPTIVE_THRESHOLD_MIN)
+        } else if frame_counts.iter().all(|&count| count >= ADAPTIVE_THRESHOLD_HIGH_COUNT) {
+            (current + ADAPTIVE_THRESHOLD_STEP).min(ADAPTIVE_THRESHOLD_MAX)
+        } else {
+            current
+        };
+        
+        if updated != current {
+            color.set_confidence_threshold(updated);
+            log::info!(
+                "置信度阈值调整: {:.2} -> {:.2} (平均每帧检测数: {:.1}, 置信度中位数: {:?})",
+                current, updated, mean_count, median_confidence
+            );
+        }
+    }
+    
     /// 等待直到有检测结果可用
     pub fn wait_for_result(&self, timeout_ms: u64) -> bool {
         let start = std::time::Instant::now();
@@ -99,4 +1278,593 @@ impl Perple {
         }
         false
     }
-}
\ No newline at end of file
+
+    /// 启动自检：按主流水线当前的模型路径、输入尺寸和阈值检查整个检测流程
+    /// 
+    /// 依次重新加载模型、校验输入输出形状、对合成图像推理一次、检查后处理结果、
+    /// 在与图像流和结果流同类型的临时数据流上写入并读出一个元素，并记录每一步的耗时。
+    /// 使用独立的模型会话，不影响正在运行的检测循环，也不会向公用数据流写入数据。
+    /// 
+    /// # 错误处理
+    /// 任一步骤失败时返回[PerpleError::SelfTest]，其中包含步骤名称和原始错误；
+    /// 推理线程超时未返回、无法读取模型配置时同样返回该错误（步骤为`load_model`）
+    pub fn self_test(&self) -> Result<SelfTestReport, PerpleError> {
+        let (model_path, input_width, input_height, thresholds) = {
+            let color = self.color.lock().unwrap();
+            let model = color.model().ok_or_else(|| PerpleError::SelfTest {
+                step: STEP_LOAD_MODEL,
+                source: Box::new(PerpleError::InvalidParameter("推理线程超时未返回，无法读取模型配置".to_string())),
+            })?;
+            (
+                model.model_path().to_string(),
+                model.input_width(),
+                model.input_height(),
+                (model.confidence_threshold(), model.nms_threshold()),
+            )
+        };
+        selftest::run::<P, N>(&model_path, input_width, input_height, Some(thresholds))
+    }
+
+    /// 在创建实例之前检查模型能否用于检测流程
+    /// 
+    /// 步骤与[self_test](Self::self_test)相同，使用默认输入尺寸和阈值。
+    /// [Perple::new]只报告模型加载失败，本方法还会用测试图像跑通一次完整的检测流程。
+    pub fn self_test_model(model_path: &str) -> Result<SelfTestReport, PerpleError> {
+        selftest::run::<P, N>(model_path, DEFAULT_INPUT_WIDTH, DEFAULT_INPUT_HEIGHT, None)
+    }
+}
+
+impl<P: Payload<N>, const N: usize> Drop for Perple<P, N> {
+    fn drop(&mut self) {
+        // 先停止检测循环，循环线程退出后输出端才会随最后一个引用被关闭
+        self.stop_all();
+        if let Err(e) = self.join_all() {
+            log::warn!("等待检测线程结束时发生错误: {}", e);
+        }
+    }
+}
+
+/// 主流水线的一次检测：推理一帧，更新检测数量统计和人数统计，调用检测结果回调并提交给输出端和快照保存器
+/// 
+/// 各项处理直接使用[Color::act_with_image]交出的本帧图像和结果，而不是事后从输出流中读取。
+/// 超出[处理预算](FrameBudget)或输入图像无法检测而[降级](Bounds::is_degraded)的帧只计入检测数量统计，
+/// 人数统计、回调和输出端都跳过该帧，避免可选步骤进一步拖慢已经超时的检测循环。
+/// 返回本次是否处理了一帧图像。
+fn color_step<P: Payload<N>, const N: usize>(
+    color: &Mutex<Color<P, N>>,
+    histogram: &Mutex<DetectionHistogram>,
+    person_counter: &Mutex<Option<PersonCounter>>,
+    detection_callbacks: &Mutex<Vec<DetectionCallback<P, N>>>,
+    sinks: &Mutex<Vec<SinkRunner<N>>>,
+    snapshots: &Mutex<Vec<SnapshotSink>>,
+    detection_signal: &SignalStream<STREAM_CAPACITY>,
+) -> bool {
+    let mut color = color.lock().unwrap();
+    // act_with_image读取到图像后才把帧号加1并交出结果，因此本帧的帧号是当前值加1
+    let frame = FrameMeta::now(color.frame_id() + 1);
+    let mut processed = false;
+    let written = color.act_with_image(|image, output| {
+        processed = true;
+        let bounds = P::bounds(output);
+        histogram.lock().unwrap().record(bounds.len());
+        if bounds.is_degraded() {
+            return;
+        }
+        if let Some(counter) = person_counter.lock().unwrap().as_mut() {
+            counter.update(bounds);
+        }
+        for callback in detection_callbacks.lock().unwrap().iter_mut() {
+            callback(output);
+        }
+        for sink in sinks.lock().unwrap().iter_mut() {
+            sink.submit(frame, bounds.clone());
+        }
+        for snapshot in snapshots.lock().unwrap().iter_mut() {
+            snapshot.observe(&frame, image, bounds);
+        }
+    });
+    // 检测回调先于信号执行：消费者收到信号时，本帧的回调都已返回
+    if written.is_some() {
+        detection_signal.signal();
+    }
+    processed
+}
+
+/// 启用调度器时，返回调度器及指定流水线在其中的编号
+fn scheduled(scheduler: &Option<Arc<PipelineScheduler>>, name: &str) -> Option<(Arc<PipelineScheduler>, usize)> {
+    let scheduler = scheduler.as_ref()?;
+    let id = scheduler.id(name)?;
+    Some((Arc::clone(scheduler), id))
+}
+
+/// 背压判断：启用时在输出流已满时返回`true`，未启用时总是返回`false`
+fn pause_when_full<T: Default + Send + 'static>(output_stream: &Arc<Mutex<Stream<T>>>, enabled: bool) -> impl Fn() -> bool + Send + 'static {
+    let output_stream = Arc::clone(output_stream);
+    move || enabled && output_stream.lock().unwrap().is_full()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{BoundingBox, Detection, ResultSink, UserPayload};
+    use crate::config::PERSON_CLASS_LABEL;
+
+    /// 每帧检测到`count`个行人的模拟检测函数，超出容量`N`的部分被丢弃
+    fn persons<const N: usize>(count: usize) -> impl FnMut(&DynamicImage) -> Result<BoundsN<N>, PerpleError> + Send + 'static {
+        move |_| {
+            Ok((0..count)
+                .map(|i| Detection::new(BoundingBox::new(i as f32 * 20.0, 0.0, i as f32 * 20.0 + 10.0, 10.0), 0, PERSON_CLASS_LABEL, 0.9))
+                .collect())
+        }
+    }
+
+    /// 使用模拟检测函数、主循环间隔为0的实例
+    fn stub_perple<F>(detect: F) -> Perple
+    where
+        F: FnMut(&DynamicImage) -> Result<Bounds, PerpleError> + Send + 'static,
+    {
+        let mut perple = Perple::from_fn(Arc::new(Mutex::new(Stream::new())), Arc::new(Mutex::new(Stream::new())), detect);
+        perple.set_loop_schedule(LoopMode::Continuous, 0);
+        perple
+    }
+
+    fn image() -> DynamicImage {
+        DynamicImage::new_rgb8(64, 48)
+    }
+
+    /// 记录收到的帧序号及是否已关闭
+    struct RecordingSink {
+        frames: Arc<Mutex<Vec<u64>>>,
+        closed: Arc<Mutex<bool>>,
+    }
+
+    impl ResultSink for RecordingSink {
+        fn on_result(&mut self, frame: &FrameMeta, _bounds: &Bounds) -> Result<(), PerpleError> {
+            self.frames.lock().unwrap().push(frame.frame_id);
+            Ok(())
+        }
+
+        fn close(&mut self) -> Result<(), PerpleError> {
+            *self.closed.lock().unwrap() = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn sinks_receive_each_frame_once_while_stream_is_consumed() {
+        let mut perple = stub_perple(persons(1));
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let closed = Arc::new(Mutex::new(false));
+        perple.add_sink(Box::new(RecordingSink { frames: Arc::clone(&frames), closed: Arc::clone(&closed) }));
+        
+        // 另一个线程不断取走结果，输出端不应因此漏掉或重复收到任何一帧
+        let bounds_stream = Arc::clone(&perple.bounds_stream);
+        let consumed = Arc::new(AtomicU64::new(0));
+        let consumer = {
+            let consumed = Arc::clone(&consumed);
+            thread::spawn(move || {
+                while consumed.load(Ordering::Relaxed) < 10 {
+                    if bounds_stream.lock().unwrap().read().is_some() {
+                        consumed.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            })
+        };
+        for _ in 0..10 {
+            assert_eq!(perple.update_image(image()), UpdateResult::Accepted);
+        }
+        let stats = perple.run_color_loop_blocking(LoopMode::Count(10)).unwrap();
+        assert_eq!(stats.iterations, 10);
+        consumer.join().unwrap();
+        
+        drop(perple);
+        assert_eq!(*frames.lock().unwrap(), (1..=10).collect::<Vec<_>>());
+        assert!(*closed.lock().unwrap());
+    }
+
+    #[test]
+    fn dropping_perple_stops_loop_and_closes_sinks() {
+        let mut perple = stub_perple(persons(2));
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let closed = Arc::new(Mutex::new(false));
+        perple.add_sink(Box::new(RecordingSink { frames: Arc::clone(&frames), closed: Arc::clone(&closed) }));
+        perple.start_color_loop().unwrap();
+        perple.update_image(image());
+        assert!(perple.wait_for_result(1000));
+        
+        drop(perple);
+        assert!(*closed.lock().unwrap());
+        assert_eq!(*frames.lock().unwrap(), [1]);
+    }
+
+    #[test]
+    fn backpressure_flag_pauses_detection_while_output_is_full() {
+        for enabled in [true, false] {
+            let calls = Arc::new(AtomicU64::new(0));
+            let counted = Arc::clone(&calls);
+            let mut perple = stub_perple(move |image| {
+                counted.fetch_add(1, Ordering::SeqCst);
+                persons(1)(image)
+            }).with_backpressure(enabled);
+            while !perple.bounds_stream.lock().unwrap().is_full() {
+                perple.bounds_stream.lock().unwrap().write(Bounds::new()).unwrap();
+            }
+            for _ in 0..3 {
+                perple.update_image(image());
+            }
+            perple.start_color_loop().unwrap();
+            thread::sleep(Duration::from_millis(50));
+            if !enabled {
+                // 未启用时照常推理，结果写入失败被丢弃
+                assert_eq!(calls.load(Ordering::SeqCst), 3);
+                continue;
+            }
+            assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+            // 下游取走一项后恰好处理一帧，输出流再次写满
+            perple.bounds_stream.lock().unwrap().read().unwrap();
+            for _ in 0..100 {
+                if calls.load(Ordering::SeqCst) > 0 {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+            thread::sleep(Duration::from_millis(30));
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+            assert!(perple.bounds_stream.lock().unwrap().is_full());
+        }
+    }
+
+    #[test]
+    fn detection_signal_follows_callbacks_for_written_frames() {
+        let mut perple = stub_perple(persons(2));
+        let seen = Arc::new(AtomicU64::new(0));
+        {
+            let seen = Arc::clone(&seen);
+            perple.on_detection(Box::new(move |_| {
+                seen.fetch_add(1, Ordering::SeqCst);
+            }));
+        }
+        perple.start_color_loop().unwrap();
+        perple.update_image(image());
+        while !perple.detection_signal().wait_signal() {
+            thread::yield_now();
+        }
+        // 收到信号时本帧的回调已经执行，结果也已写入结果流
+        assert_eq!(seen.load(Ordering::SeqCst), 1);
+        assert_eq!(perple.bounds_stream.lock().unwrap().read().map(|b| b.len()), Some(2));
+        perple.stop_color_loop();
+        perple.join_color_thread().unwrap();
+
+        // 结果流已满、结果被丢弃时回调照常执行，但不发出信号
+        while !perple.bounds_stream.lock().unwrap().is_full() {
+            perple.bounds_stream.lock().unwrap().write(Bounds::new()).unwrap();
+        }
+        perple.update_image(image());
+        perple.run_color_loop_blocking(LoopMode::Count(1)).unwrap();
+        assert_eq!(seen.load(Ordering::SeqCst), 2);
+        assert_eq!(perple.detection_signal().pending(), 0);
+    }
+
+    #[test]
+    fn person_counter_sees_every_frame_and_ignores_other_classes() {
+        // 行人数按2,2,0,2,2闪烁，并始终夹带一辆车
+        let mut script = vec![2, 2, 0, 2, 2].into_iter();
+        let mut perple = stub_perple(move |image| {
+            let mut bounds = persons(script.next().unwrap_or(0))(image)?;
+            bounds.push(Detection::new(BoundingBox::new(200.0, 0.0, 240.0, 30.0), 2, "car", 0.9));
+            Ok(bounds)
+        });
+        perple.set_person_counter(PersonCounter::new(3));
+        for _ in 0..5 {
+            perple.update_image(image());
+        }
+        perple.run_color_loop_blocking(LoopMode::Count(5)).unwrap();
+        assert_eq!(perple.person_count(), Some(2));
+        assert_eq!(perple.max_person_count(), Some(2));
+    }
+
+    #[test]
+    fn invalid_max_input_fps_keeps_previous_limit() {
+        let mut perple = stub_perple(persons(0));
+        perple.set_max_input_fps(Some(5.0)).unwrap();
+        for fps in [0.0, -2.0, f32::NAN] {
+            assert!(matches!(perple.set_max_input_fps(Some(fps)), Err(PerpleError::InvalidParameter(_))));
+        }
+        assert_eq!(perple.max_input_fps(), Some(5.0));
+        perple.set_max_input_fps(None).unwrap();
+        assert_eq!(perple.max_input_fps(), None);
+    }
+
+    /// 每个字段都不是默认值的参数快照
+    fn tuned_config() -> PerpleConfig {
+        PerpleConfig {
+            loop_mode: LoopMode::Count(7),
+            loop_interval_ms: 25,
+            backpressure: true,
+            max_input_fps: Some(12.5),
+            rate_limit_policy: RateLimitPolicy::CoalesceLatest,
+            histogram_window: 40,
+            inference_timeout_ms: 750,
+            motion_threshold: Some(0.02),
+            frame_budget_ms: Some(40),
+            detector: DetectorConfig {
+                model_path: "models/tuned.onnx".to_string(),
+                confidence_threshold: 0.35,
+                class_names: Some(vec!["person".to_string()]),
+                ..DetectorConfig::default()
+            },
+        }
+    }
+
+    #[test]
+    fn pipeline_config_round_trips_every_field() {
+        let config = tuned_config();
+        let defaults = PerpleConfig { detector: config.detector.clone(), ..PerpleConfig::default() };
+        // 逐字段确认快照与默认值不同，新增字段时需要在tuned_config中设置
+        let PerpleConfig {
+            loop_mode, loop_interval_ms, backpressure, max_input_fps, rate_limit_policy,
+            histogram_window, inference_timeout_ms, motion_threshold, frame_budget_ms, detector: _,
+        } = &config;
+        assert_ne!(*loop_mode, defaults.loop_mode);
+        assert_ne!(*loop_interval_ms, defaults.loop_interval_ms);
+        assert_ne!(*backpressure, defaults.backpressure);
+        assert_ne!(*max_input_fps, defaults.max_input_fps);
+        assert_ne!(*rate_limit_policy, defaults.rate_limit_policy);
+        assert_ne!(*histogram_window, defaults.histogram_window);
+        assert_ne!(*inference_timeout_ms, defaults.inference_timeout_ms);
+        assert_ne!(*motion_threshold, defaults.motion_threshold);
+        assert_ne!(*frame_budget_ms, defaults.frame_budget_ms);
+
+        let mut configured = stub_perple(persons(0));
+        let color = Arc::clone(&configured.color);
+        configured.apply_pipeline_config(&mut color.lock().unwrap(), &config).unwrap();
+        let snapshot = configured.pipeline_config(&color.lock().unwrap(), config.detector.clone());
+        assert_eq!(snapshot, config);
+
+        let mut restored = stub_perple(persons(0));
+        let color = Arc::clone(&restored.color);
+        restored.apply_pipeline_config(&mut color.lock().unwrap(), &snapshot).unwrap();
+        assert_eq!(restored.pipeline_config(&color.lock().unwrap(), snapshot.detector.clone()), snapshot);
+    }
+
+    #[test]
+    fn invalid_config_is_rejected_before_any_change() {
+        let mut perple = stub_perple(persons(0));
+        perple.set_max_input_fps(Some(5.0)).unwrap();
+        let before = (perple.loop_mode(), perple.loop_interval_ms(), perple.max_input_fps());
+
+        let invalid = [
+            PerpleConfig { max_input_fps: Some(0.0), ..tuned_config() },
+            PerpleConfig { max_input_fps: Some(f32::NAN), ..tuned_config() },
+            PerpleConfig { motion_threshold: Some(-1.0), ..tuned_config() },
+            PerpleConfig { histogram_window: 0, ..tuned_config() },
+            PerpleConfig { inference_timeout_ms: 0, ..tuned_config() },
+            PerpleConfig { frame_budget_ms: Some(0), ..tuned_config() },
+            PerpleConfig { detector: DetectorConfig { nms_threshold: 1.5, ..DetectorConfig::default() }, ..tuned_config() },
+        ];
+        for config in &invalid {
+            assert!(matches!(config.validate(), Err(PerpleError::InvalidParameter(_))), "{:?}", config);
+            assert!(matches!(perple.apply_config(config), Err(PerpleError::InvalidParameter(_))));
+            assert!(matches!(PerpleBuilder::<()>::from_config(config.clone()).build(), Err(PerpleError::InvalidParameter(_))));
+        }
+        assert_eq!((perple.loop_mode(), perple.loop_interval_ms(), perple.max_input_fps()), before);
+        assert!(tuned_config().validate().is_ok());
+    }
+
+    #[cfg(feature = "config-file")]
+    #[test]
+    fn builder_reads_config_file() {
+        let path = std::env::temp_dir().join(format!("perple_builder_{}.toml", std::process::id()));
+        let path = path.to_str().unwrap();
+        tuned_config().save_to_toml(path).unwrap();
+        let builder = PerpleBuilder::<()>::from_config_file(path);
+        std::fs::remove_file(path).unwrap();
+        let builder = builder.unwrap().with_loop_schedule(LoopMode::Continuous, 5);
+        assert_eq!(builder.config(), &PerpleConfig { loop_mode: LoopMode::Continuous, loop_interval_ms: 5, ..tuned_config() });
+    }
+
+    #[test]
+    fn fuser_combines_pipeline_results_per_frame() {
+        // 主流水线检测到左侧两人，附加流水线检测到右侧一人
+        let mut perple = stub_perple(persons(2));
+        let config = PipelineConfig::new("").with_loop_mode(LoopMode::Count(3)).with_interval_ms(0);
+        perple.add_pipeline_fn("accurate", config, |_| {
+            Ok([Detection::new(BoundingBox::new(100.0, 0.0, 110.0, 10.0), 0, PERSON_CLASS_LABEL, 0.8)].into_iter().collect())
+        }).unwrap();
+        assert!(matches!(perple.fuser(&[DEFAULT_PIPELINE, "missing"], FusionMode::Nms(0.5)), Err(PerpleError::InvalidParameter(_))));
+        let mut fuser = perple.fuser(&[DEFAULT_PIPELINE, "accurate"], FusionMode::Nms(0.5)).unwrap();
+
+        for _ in 0..3 {
+            perple.update_image(image());
+        }
+        perple.start_pipeline("accurate").unwrap();
+        perple.run_color_loop_blocking(LoopMode::Count(3)).unwrap();
+        perple.join_all().unwrap();
+
+        assert_eq!(fuser.act(), 3);
+        let mut fused = fuser.output_stream().lock().unwrap();
+        let frames: Vec<(u64, usize)> = std::iter::from_fn(|| fused.read())
+            .map(|bounds| (bounds.source_frame_seq(), bounds.len()))
+            .collect();
+        assert_eq!(frames, [(1, 3), (2, 3), (3, 3)]);
+        assert!(perple.results(DEFAULT_PIPELINE).unwrap().lock().unwrap().read().is_none());
+    }
+
+    #[test]
+    fn every_pipeline_sees_frames_from_both_entry_points() {
+        let mut perple = stub_perple(persons(1));
+        let config = PipelineConfig::new("").with_loop_mode(LoopMode::Count(4)).with_interval_ms(0);
+        perple.add_pipeline_fn("second", config.clone(), persons(2)).unwrap();
+        perple.add_pipeline_fn("third", config.clone(), persons(3)).unwrap();
+        assert!(matches!(perple.add_pipeline_fn("second", config, persons(1)), Err(PerpleError::InvalidParameter(_))));
+
+        // 一半图像经update_image写入，一半由外部直接写入img_stream
+        for _ in 0..2 {
+            assert_eq!(perple.update_image(image()), UpdateResult::Accepted);
+        }
+        let seq = perple.frame_seq();
+        for i in 1..=2 {
+            perple.img_stream.lock().unwrap().write(Frame::new(image(), seq + i)).unwrap();
+        }
+
+        perple.start_pipeline("second").unwrap();
+        perple.start_pipeline("third").unwrap();
+        assert!(matches!(perple.start_pipeline("missing"), Err(PerpleError::InvalidParameter(_))));
+        assert_eq!(perple.run_color_loop_blocking(LoopMode::Count(4)).unwrap().iterations, 4);
+        perple.join_all().unwrap();
+
+        for (name, expected) in [(DEFAULT_PIPELINE, 1), ("second", 2), ("third", 3)] {
+            let results = perple.results(name).unwrap();
+            let mut results = results.lock().unwrap();
+            let frames: Vec<(u64, usize)> = std::iter::from_fn(|| results.read())
+                .map(|bounds| (bounds.source_frame_seq(), bounds.len()))
+                .collect();
+            assert_eq!(frames, (1..=4).map(|seq| (seq, expected)).collect::<Vec<_>>(), "{}", name);
+        }
+        assert_eq!(perple.flush_input_stream(), 0);
+    }
+
+    #[test]
+    fn degraded_frames_skip_counter_callbacks_and_sinks() {
+        // 偶数帧耗时超出预算，并且检测到更多行人
+        let mut calls = 0;
+        let mut perple = stub_perple(move |image| {
+            calls += 1;
+            if calls % 2 == 0 {
+                thread::sleep(Duration::from_millis(30));
+                return persons(5)(image);
+            }
+            persons(1)(image)
+        });
+        perple.color.lock().unwrap().set_frame_budget(Some(FrameBudget(Duration::from_millis(15))));
+        perple.set_person_counter(PersonCounter::new(1));
+        let callback_frames = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&callback_frames);
+        perple.on_detection(Box::new(move |bounds: &Bounds| recorded.lock().unwrap().push(bounds.source_frame_seq())));
+        let sink_frames = Arc::new(Mutex::new(Vec::new()));
+        perple.add_sink(Box::new(RecordingSink { frames: Arc::clone(&sink_frames), closed: Arc::new(Mutex::new(false)) }));
+
+        for _ in 0..4 {
+            perple.update_image(image());
+        }
+        perple.run_color_loop_blocking(LoopMode::Count(4)).unwrap();
+
+        // 降级帧仍然写入结果流并被标记
+        let degraded: Vec<(u64, bool)> = {
+            let mut results = perple.bounds_stream.lock().unwrap();
+            std::iter::from_fn(|| results.read()).map(|b| (b.source_frame_seq(), b.is_degraded())).collect()
+        };
+        assert_eq!(degraded, vec![(1, false), (2, true), (3, false), (4, true)]);
+        assert_eq!(perple.stats().degraded_frames(), 2);
+        assert_eq!(perple.detection_histogram().len(), 4);
+        assert_eq!(perple.max_person_count(), Some(1));
+        assert_eq!(*callback_frames.lock().unwrap(), vec![1, 3]);
+        drop(perple);
+        assert_eq!(*sink_frames.lock().unwrap(), vec![1, 3]);
+    }
+
+    #[test]
+    fn snapshot_sink_saves_frames_where_person_appears() {
+        // 按帧依次检测到的行人数
+        let script = [0, 1, 1, 0, 2];
+        let mut calls = 0;
+        let mut perple = stub_perple(move |image| {
+            calls += 1;
+            persons(script[calls - 1])(image)
+        });
+        let dir = std::env::temp_dir().join(format!("perple_snapshot_loop_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = SnapshotConfig::new(&dir).with_cooldown(Duration::ZERO).with_filename_template("{frame_id}.jpg");
+        perple.add_snapshot_sink(config).unwrap();
+
+        for _ in 0..script.len() {
+            perple.update_image(image());
+        }
+        perple.run_color_loop_blocking(LoopMode::Count(script.len())).unwrap();
+        drop(perple);
+
+        let mut saved: Vec<String> = std::fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        saved.sort();
+        assert_eq!(saved, ["2.jpg", "5.jpg"]);
+        let snapshot = image::open(dir.join("2.jpg")).unwrap();
+        assert_eq!((snapshot.width(), snapshot.height()), (64, 48));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn small_capacity_flows_through_streams_and_sinks() {
+        struct CapacitySink(Arc<Mutex<Vec<(usize, usize)>>>);
+
+        impl ResultSink<4> for CapacitySink {
+            fn on_result(&mut self, _frame: &FrameMeta, bounds: &BoundsN<4>) -> Result<(), PerpleError> {
+                self.0.lock().unwrap().push((bounds.len(), bounds.capacity()));
+                Ok(())
+            }
+        }
+
+        let bounds_stream: Arc<Mutex<Stream<BoundsN<4>>>> = Arc::new(Mutex::new(Stream::new()));
+        let mut perple: Perple<(), 4> = Perple::from_fn(Arc::new(Mutex::new(Stream::new())), Arc::clone(&bounds_stream), persons(6));
+        perple.set_loop_schedule(LoopMode::Continuous, 0);
+        perple.set_person_counter(PersonCounter::new(1));
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        perple.add_sink(Box::new(CapacitySink(Arc::clone(&delivered))));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&seen);
+        perple.on_detection(Box::new(move |bounds: &BoundsN<4>| recorded.lock().unwrap().push(bounds.len())));
+
+        perple.update_frame((), image());
+        perple.run_color_loop_blocking(LoopMode::Count(1)).unwrap();
+        assert_eq!(perple.person_count(), Some(4));
+        assert_eq!(*seen.lock().unwrap(), [4]);
+        let output = bounds_stream.lock().unwrap().read().unwrap();
+        assert_eq!((output.len(), output.capacity()), (4, 4));
+        drop(perple);
+        assert_eq!(*delivered.lock().unwrap(), [(4, 4)]);
+    }
+
+    #[test]
+    fn custom_payload_round_trips_through_count_run() {
+        #[derive(Debug, Clone, Default, PartialEq)]
+        struct CameraInfo {
+            camera_id: u32,
+            pan: f32,
+        }
+
+        impl UserPayload for CameraInfo {}
+
+        let bounds_stream = Arc::new(Mutex::new(Stream::new()));
+        let mut perple: Perple<CameraInfo> = Perple::from_fn(Arc::new(Mutex::new(Stream::new())), Arc::clone(&bounds_stream), persons(2));
+        perple.set_loop_schedule(LoopMode::Continuous, 0);
+        let cameras = [CameraInfo { camera_id: 1, pan: 30.0 }, CameraInfo { camera_id: 2, pan: -45.0 }];
+        for camera in &cameras {
+            assert_eq!(perple.update_frame(camera.clone(), image()), UpdateResult::Accepted);
+        }
+        assert_eq!(perple.run_color_loop_blocking(LoopMode::Count(2)).unwrap().iterations, 2);
+
+        let mut bounds_stream = bounds_stream.lock().unwrap();
+        for (seq, camera) in (1..).zip(&cameras) {
+            let (payload, bounds) = bounds_stream.read().unwrap();
+            assert_eq!(&payload, camera);
+            assert_eq!((bounds.source_frame_seq(), bounds.len()), (seq, 2));
+        }
+        assert!(bounds_stream.read().is_none());
+    }
+
+    #[test]
+    fn histogram_records_each_inference() {
+        let mut counts = [3, 0, 5, 1].into_iter().cycle();
+        let mut perple = stub_perple(move |image| persons(counts.next().unwrap())(image));
+        perple.set_histogram_window(3);
+        for _ in 0..4 {
+            perple.update_image(image());
+        }
+        perple.run_color_loop_blocking(LoopMode::Count(4)).unwrap();
+        // 窗口为3，只保留后三帧的数量
+        let histogram = perple.detection_histogram();
+        assert_eq!((histogram.len(), histogram.window_frames()), (3, 3));
+        assert_eq!((histogram.max(), histogram.mean()), (5, 2.0));
+    }
+}
@@ -0,0 +1,20 @@
+//! 常用类型的统一导入
+//!
+//! 通过`use perple::prelude::*;`一次性导入稳定的公共接口：
+//!
+//! ```no_run
+//! use perple::prelude::*;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let image = load_image("path/to/image.jpg")?;
+//! let mut detector = YoloDetector::new("path/to/model.onnx", 640, 640)?;
+//! let bounds: Bounds = detector.detect(&image)?;
+//! let annotated = draw_detections(&image, bounds.as_slice());
+//! # Ok(())
+//! # }
+//! ```
+
+pub use crate::perple::Perple;
+pub use crate::color::{YoloDetector, Bounds, Detection, BoundingBox, load_image, draw_detections};
+pub use crate::utils::muloop::LoopMode;
+pub use crate::utils::stream::Stream;
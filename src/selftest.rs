@@ -0,0 +1,250 @@
+//! 启动自检模块
+//!
+//! 在处理第一帧真实图像之前检查模型路径、ONNX Runtime、模型输入输出形状、
+//! 推理与后处理以及数据流是否正常，入口为[Perple::self_test](crate::Perple::self_test)
+//! 和[Perple::self_test_model](crate::Perple::self_test_model)。
+
+use std::time::{Duration, Instant};
+
+use image::{DynamicImage, Rgb, RgbImage};
+
+use crate::color::Payload;
+use crate::color::YoloDetectorN;
+use crate::color::bounds::BoundsN;
+use crate::color::model::{load_model, model_shapes, validate_model};
+use crate::error::PerpleError;
+use crate::utils::stream::Stream;
+
+/// 加载模型文件并创建ONNX Runtime会话
+pub const STEP_LOAD_MODEL: &str = "load_model";
+/// 校验模型输入输出形状
+pub const STEP_VALIDATE_SHAPES: &str = "validate_shapes";
+/// 对合成图像执行一次推理（含NMS等后处理）
+pub const STEP_INFERENCE: &str = "inference";
+/// 检查后处理结果的坐标和置信度是否为有限值
+pub const STEP_POSTPROCESS: &str = "postprocess";
+/// 向图像流和结果流各写入并读出一个空元素
+pub const STEP_STREAMS: &str = "streams";
+
+/// 自检中单个步骤的耗时
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SelfTestStep {
+    /// 步骤名称，见本模块的`STEP_*`常量
+    pub name: &'static str,
+    /// 耗时（微秒）
+    pub duration_us: u64,
+}
+
+/// 启动自检的结果
+///
+/// 只有全部步骤成功时才会生成，任一步骤失败时返回[PerpleError::SelfTest]。
+/// 启用`serde`特性时可序列化，便于附在问题报告中。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SelfTestReport {
+    /// 模型文件路径
+    pub model_path: String,
+    /// 检测器输入宽度
+    pub input_width: usize,
+    /// 检测器输入高度
+    pub input_height: usize,
+    /// 模型第一个输入的形状，动态维度为-1
+    pub input_shape: Vec<i64>,
+    /// 模型第一个输出的形状，动态维度为-1
+    pub output_shape: Vec<i64>,
+    /// 合成图像上的检测数量，通常为0
+    pub detections: usize,
+    /// 按执行顺序排列的步骤耗时
+    pub steps: Vec<SelfTestStep>,
+}
+
+impl SelfTestReport {
+    /// 所有步骤的总耗时
+    pub fn total_duration(&self) -> Duration {
+        Duration::from_micros(self.steps.iter().map(|step| step.duration_us).sum())
+    }
+
+    /// 按名称查找步骤
+    pub fn step(&self, name: &str) -> Option<&SelfTestStep> {
+        self.steps.iter().find(|step| step.name == name)
+    }
+
+    /// 序列化为TOML文本
+    ///
+    /// # 错误处理
+    /// 序列化失败时返回[PerpleError::Config]
+    #[cfg(feature = "config-file")]
+    pub fn to_toml(&self) -> Result<String, PerpleError> {
+        toml::to_string(self).map_err(|e| PerpleError::Config(e.to_string()))
+    }
+
+    /// 执行一个步骤并记录耗时，失败时包装为[PerpleError::SelfTest]
+    fn run_step<T>(&mut self, name: &'static str, step: impl FnOnce() -> Result<T, PerpleError>) -> Result<T, PerpleError> {
+        let start = Instant::now();
+        let value = step().map_err(|e| PerpleError::SelfTest { step: name, source: Box::new(e) })?;
+        self.steps.push(SelfTestStep { name, duration_us: start.elapsed().as_micros() as u64 });
+        Ok(value)
+    }
+}
+
+/// 依次执行全部自检步骤
+///
+/// 使用独立加载的模型会话和临时数据流，不影响正在运行的检测循环。
+pub(crate) fn run<P: Payload<N>, const N: usize>(
+    model_path: &str,
+    input_width: usize,
+    input_height: usize,
+    thresholds: Option<(f32, f32)>,
+) -> Result<SelfTestReport, PerpleError> {
+    let mut report = SelfTestReport {
+        model_path: model_path.to_string(),
+        input_width,
+        input_height,
+        ..Default::default()
+    };
+
+    let session = report.run_step(STEP_LOAD_MODEL, || Ok(load_model(model_path)?))?;
+    (report.input_shape, report.output_shape) = model_shapes(&session);
+    report.run_step(STEP_VALIDATE_SHAPES, || validate_model(&session))?;
+
+    let mut detector = YoloDetectorN::<N>::from_session(session, model_path, input_width, input_height);
+    if let Some((confidence_threshold, nms_threshold)) = thresholds {
+        detector.set_confidence_threshold(confidence_threshold);
+        detector.set_nms_threshold(nms_threshold);
+    }
+    let image = synthetic_image(input_width as u32, input_height as u32);
+    let bounds = report.run_step(STEP_INFERENCE, || detector.detect_image(&image))?;
+    report.detections = report.run_step(STEP_POSTPROCESS, || check_detections(&bounds))?;
+
+    report.run_step(STEP_STREAMS, || {
+        exercise_stream::<P::Frame>()?;
+        exercise_stream::<P::Output>()
+    })?;
+
+    Ok(report)
+}
+
+/// 检查检测结果的坐标和置信度是否均为有限值，返回检测数量
+fn check_detections<const N: usize>(bounds: &BoundsN<N>) -> Result<usize, PerpleError> {
+    let invalid = bounds.iter().position(|detection| {
+        let b = &detection.bbox;
+        ![b.x1, b.y1, b.x2, b.y2, detection.confidence].iter().all(|v| v.is_finite())
+    });
+    match invalid {
+        Some(index) => Err(PerpleError::InvalidParameter(format!("第{}个检测结果包含非有限值", index))),
+        None => Ok(bounds.len()),
+    }
+}
+
+/// 向新建的数据流写入一个默认元素并读出
+fn exercise_stream<T: Default + Send>() -> Result<(), PerpleError> {
+    let mut stream = Stream::<T>::new();
+    stream.write(T::default()).map_err(|_| PerpleError::BufferFull { count: 0 })?;
+    match stream.read() {
+        Some(_) => Ok(()),
+        None => Err(PerpleError::InvalidParameter("写入的元素无法从数据流中读出".to_string())),
+    }
+}
+
+/// 生成用于推理的合成图像（水平、垂直两个方向的渐变）
+fn synthetic_image(width: u32, height: u32) -> DynamicImage {
+    let (w, h) = (width.max(1), height.max(1));
+    DynamicImage::ImageRgb8(RgbImage::from_fn(w, h, |x, y| {
+        Rgb([(x * 255 / w) as u8, (y * 255 / h) as u8, 128])
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Perple;
+    use crate::color::bounds::{BoundingBox, Bounds, Detection};
+
+    /// 仓库自带的模型
+    const MODEL_PATH: &str = "module/color/yolo11n.onnx";
+
+    fn step_of(result: Result<SelfTestReport, PerpleError>) -> &'static str {
+        match result {
+            Err(PerpleError::SelfTest { step, .. }) => step,
+            other => panic!("应返回自检错误: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bundled_model_passes_every_step() {
+        let report = Perple::<()>::self_test_model(MODEL_PATH).unwrap();
+        let steps: Vec<&str> = report.steps.iter().map(|step| step.name).collect();
+        assert_eq!(steps, [STEP_LOAD_MODEL, STEP_VALIDATE_SHAPES, STEP_INFERENCE, STEP_POSTPROCESS, STEP_STREAMS]);
+        assert_eq!(report.model_path, MODEL_PATH);
+        assert_eq!(report.input_shape.len(), 4);
+        assert_eq!(report.output_shape.len(), 3);
+        assert!(report.total_duration() >= Duration::from_micros(report.step(STEP_INFERENCE).unwrap().duration_us));
+    }
+
+    #[test]
+    fn missing_or_corrupt_model_fails_at_load() {
+        assert_eq!(step_of(Perple::<()>::self_test_model("module/color/missing.onnx")), STEP_LOAD_MODEL);
+
+        let path = std::env::temp_dir().join(format!("perple_selftest_corrupt_{}.onnx", std::process::id()));
+        std::fs::write(&path, b"not an onnx model").unwrap();
+        let result = Perple::<()>::self_test_model(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(step_of(result), STEP_LOAD_MODEL);
+    }
+
+    #[test]
+    fn failed_step_is_named_and_not_recorded() {
+        let mut report = SelfTestReport::default();
+        assert_eq!(report.run_step(STEP_LOAD_MODEL, || Ok(1)).unwrap(), 1);
+        let result = report.run_step(STEP_VALIDATE_SHAPES, || -> Result<(), _> {
+            Err(PerpleError::InvalidParameter("输出形状错误".to_string()))
+        });
+        match result {
+            Err(PerpleError::SelfTest { step, source }) => {
+                assert_eq!(step, STEP_VALIDATE_SHAPES);
+                assert!(matches!(*source, PerpleError::InvalidParameter(_)));
+            }
+            other => panic!("应返回自检错误: {:?}", other),
+        }
+        assert_eq!(report.steps.len(), 1);
+        assert!(report.step(STEP_VALIDATE_SHAPES).is_none());
+    }
+
+    #[test]
+    fn non_finite_detections_fail_postprocess_check() {
+        let mut bounds = Bounds::new();
+        bounds.push(Detection::new(BoundingBox::new(0.0, 0.0, 10.0, 10.0), 0, "person", 0.9));
+        assert_eq!(check_detections(&bounds).unwrap(), 1);
+
+        bounds.push(Detection::new(BoundingBox::new(0.0, f32::NAN, 10.0, 10.0), 0, "person", 0.9));
+        assert!(matches!(check_detections(&bounds), Err(PerpleError::InvalidParameter(_))));
+        bounds.clear();
+        bounds.push(Detection::new(BoundingBox::new(0.0, 0.0, 10.0, 10.0), 0, "person", f32::INFINITY));
+        assert!(check_detections(&bounds).is_err());
+    }
+
+    #[test]
+    fn streams_round_trip_default_items() {
+        assert!(exercise_stream::<<() as Payload>::Frame>().is_ok());
+        assert!(exercise_stream::<<() as Payload>::Output>().is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "config-file")]
+    fn report_serializes_to_toml() {
+        let report = SelfTestReport {
+            model_path: MODEL_PATH.to_string(),
+            input_width: 640,
+            input_height: 640,
+            input_shape: vec![1, 3, 640, 640],
+            output_shape: vec![1, 84, 8400],
+            detections: 0,
+            steps: vec![SelfTestStep { name: STEP_LOAD_MODEL, duration_us: 1500 }],
+        };
+        let toml = report.to_toml().unwrap();
+        assert!(toml.contains("model_path = \"module/color/yolo11n.onnx\""), "{}", toml);
+        assert!(toml.contains("name = \"load_model\""), "{}", toml);
+        assert_eq!(report.total_duration(), Duration::from_micros(1500));
+    }
+}
@@ -1,3 +1,6 @@
 pub mod stream;
 pub mod sort;
 pub mod muloop;
+pub mod stats;
+pub mod rate;
+pub mod sched;
@@ -1,9 +1,14 @@
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
+use crate::error::PerpleError;
+
 /// 循环模式枚举
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum LoopMode {
     /// 按次数循环
     Count(usize),
@@ -13,10 +18,21 @@ pub enum LoopMode {
     Continuous,
 }
 
+/// 循环结束时调用的回调
+type CompletionCallback = Box<dyn FnOnce() + Send>;
+
+/// 完成回调及循环线程是否已结束，两者在同一把锁下读写，避免回调在注册与线程退出之间丢失
+#[derive(Default)]
+struct Completion {
+    callback: Option<CompletionCallback>,
+    finished: bool,
+}
+
 /// 循环控制结构体
 pub struct MultiLoop {
     running: Arc<Mutex<bool>>,
     thread_handle: Option<thread::JoinHandle<()>>,
+    completion: Arc<Mutex<Completion>>,
 }
 
 impl MultiLoop {
@@ -25,6 +41,7 @@ impl MultiLoop {
         Self {
             running: Arc::new(Mutex::new(false)),
             thread_handle: None,
+            completion: Arc::new(Mutex::new(Completion::default())),
         }
     }
     
@@ -34,58 +51,140 @@ impl MultiLoop {
     /// * `mode` - 循环模式
     /// * `callback` - 每次循环执行的回调函数
     /// * `interval_ms` - 每次循环之间的间隔（毫秒）
-    pub fn start<F>(&mut self, mode: LoopMode, mut callback: F, interval_ms: u64) -> Result<(), String> 
+    /// 
+    /// # 错误处理
+    /// 循环已在运行时返回[PerpleError::LoopRunning]
+    pub fn start<F>(&mut self, mode: LoopMode, callback: F, interval_ms: u64) -> Result<(), PerpleError> 
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.start_with_backpressure(mode, callback, || false, interval_ms)
+    }
+    
+    /// 启动带背压控制的循环
+    /// 
+    /// 每次执行回调之前检查`should_pause`，返回`true`时以1ms为步长休眠，
+    /// 直到它返回`false`、循环被停止或（按时间循环时）时间耗尽。
+    /// 暂停期间不计入按次数循环的次数。
+    /// 
+    /// # 参数
+    /// * `mode` - 循环模式
+    /// * `callback` - 每次循环执行的回调函数
+    /// * `should_pause` - 下游无法接收新数据时返回`true`，例如输出流已满
+    /// * `interval_ms` - 每次循环之间的间隔（毫秒）
+    pub fn start_with_backpressure<F, G>(
+        &mut self,
+        mode: LoopMode,
+        mut callback: F,
+        should_pause: G,
+        interval_ms: u64,
+    ) -> Result<(), PerpleError>
     where
         F: FnMut() + Send + 'static,
+        G: Fn() -> bool + Send + 'static,
     {
         let mut running = self.running.lock().unwrap();
         if *running {
-            return Err("Loop is already running".to_string());
+            return Err(PerpleError::LoopRunning);
         }
         
         *running = true;
         drop(running); // 释放锁
         
         let loop_running = Arc::clone(&self.running);
+        let completion = Arc::clone(&self.completion);
+        completion.lock().unwrap().finished = false;
         
         self.thread_handle = Some(thread::spawn(move || {
-            match mode {
-                LoopMode::Count(count) => {
-                    let mut counter = 0;
-                    while *loop_running.lock().unwrap() && counter < count {
-                        callback();
-                        counter += 1;
-                        // 控制处理频率
-                        thread::sleep(Duration::from_millis(interval_ms));
-                    }
-                    // 循环结束后自动停止
-                    let mut running = loop_running.lock().unwrap();
-                    *running = false;
-                },
-                LoopMode::Duration(duration_ms) => {
-                    let start_time = std::time::Instant::now();
-                    while *loop_running.lock().unwrap() && start_time.elapsed().as_millis() < duration_ms as u128 {
-                        callback();
-                        // 控制处理频率
-                        thread::sleep(Duration::from_millis(interval_ms));
-                    }
-                    // 时间结束后自动停止
-                    let mut running = loop_running.lock().unwrap();
-                    *running = false;
-                },
-                LoopMode::Continuous => {
-                    while *loop_running.lock().unwrap() {
-                        callback();
-                        // 控制处理频率
-                        thread::sleep(Duration::from_millis(interval_ms));
-                    }
-                }
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                run_loop(mode, &loop_running, &should_pause, interval_ms, || {
+                    callback();
+                    true
+                })
+            }));
+            // 回调panic时run_loop来不及复位运行状态
+            if result.is_err() {
+                *loop_running.lock().unwrap() = false;
+            }
+            let on_complete = {
+                let mut completion = completion.lock().unwrap();
+                completion.finished = true;
+                completion.callback.take()
+            };
+            if let Some(on_complete) = on_complete {
+                on_complete();
+            }
+            // 完成回调调用之后再继续panic，由join报告为WorkerPanicked
+            if let Err(payload) = result {
+                panic::resume_unwind(payload);
             }
         }));
         
         Ok(())
     }
     
+    /// 在作用域线程中运行循环并阻塞到循环结束
+    /// 
+    /// 与[start](Self::start)不同，回调可以借用调用方栈上的数据，不需要`'static`。
+    /// 回调返回`false`时提前结束循环，[LoopMode::Continuous]模式只能以这种方式结束。
+    /// 
+    /// # 参数
+    /// * `mode` - 循环模式
+    /// * `interval_ms` - 每次循环之间的间隔（毫秒）
+    /// * `callback` - 每次循环执行的回调函数，返回是否继续
+    /// 
+    /// # 返回值
+    /// 循环的执行统计；循环已在运行时返回[PerpleError::LoopRunning]，回调发生panic时返回[PerpleError::WorkerPanicked]
+    pub fn run_scoped<F>(&mut self, mode: LoopMode, interval_ms: u64, callback: F) -> Result<LoopStats, PerpleError>
+    where
+        F: FnMut() -> bool + Send,
+    {
+        {
+            let mut running = self.running.lock().unwrap();
+            if *running {
+                return Err(PerpleError::LoopRunning);
+            }
+            *running = true;
+        }
+        
+        let running = &self.running;
+        let result = thread::scope(|scope| {
+            scope.spawn(|| run_loop(mode, running, &|| false, interval_ms, callback)).join()
+        });
+        *self.running.lock().unwrap() = false;
+        result.map_err(|_| PerpleError::WorkerPanicked("循环线程".to_string()))
+    }
+    
+    /// 设置循环结束时调用的回调，无需阻塞在[join](Self::join)上
+    /// 
+    /// 回调由[start](Self::start)或[start_with_backpressure](Self::start_with_backpressure)
+    /// 启动的循环线程在退出前调用，无论循环因[stop](Self::stop)、次数用尽、时间耗尽还是回调panic而结束，
+    /// 每次设置只调用一次。可以在启动之前或之后设置，再次设置会替换尚未调用的回调；
+    /// 设置时上一次启动的循环已经结束，则立即在当前线程调用。
+    /// [run_scoped](Self::run_scoped)不调用该回调。
+    /// 
+    /// # 示例
+    /// 
+    /// ```
+    /// use perple::utils::muloop::{LoopMode, MultiLoop};
+    /// use std::sync::mpsc;
+    /// 
+    /// let mut muloop = MultiLoop::new();
+    /// let (sender, receiver) = mpsc::channel();
+    /// muloop.start(LoopMode::Count(3), || {}, 1).unwrap();
+    /// muloop.on_complete(move || sender.send(()).unwrap());
+    /// receiver.recv().unwrap();
+    /// ```
+    pub fn on_complete<F: FnOnce() + Send + 'static>(&mut self, callback: F) {
+        let mut completion = self.completion.lock().unwrap();
+        if completion.finished {
+            drop(completion);
+            callback();
+        } else {
+            completion.callback = Some(Box::new(callback));
+        }
+    }
+    
     /// 停止循环
     pub fn stop(&mut self) {
         let mut running = self.running.lock().unwrap();
@@ -97,17 +196,194 @@ impl MultiLoop {
         *self.running.lock().unwrap()
     }
     
-    /// 等待线程结束
-    pub fn join(&mut self) -> Result<(), String> {
+    /// 等待线程结束，循环线程发生panic时返回[PerpleError::WorkerPanicked]
+    pub fn join(&mut self) -> Result<(), PerpleError> {
         if let Some(handle) = self.thread_handle.take() {
-            handle.join().map_err(|_| "Failed to join thread".to_string())?;
+            handle.join().map_err(|_| PerpleError::WorkerPanicked("循环线程".to_string()))?;
         }
         Ok(())
     }
 }
 
+/// 一次循环运行的统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopStats {
+    /// 回调执行的次数
+    pub iterations: usize,
+    /// 循环运行的总时长
+    pub elapsed: Duration,
+}
+
+/// 循环主体，由[MultiLoop::start_with_backpressure]和[MultiLoop::run_scoped]共用
+/// 
+/// 回调返回`false`时结束循环。
+fn run_loop<F, G>(mode: LoopMode, loop_running: &Mutex<bool>, should_pause: &G, interval_ms: u64, mut callback: F) -> LoopStats
+where
+    F: FnMut() -> bool,
+    G: Fn() -> bool,
+{
+    let start_time = std::time::Instant::now();
+    // 循环是否应继续：未被停止且（按时间循环时）未超时
+    let keep_going = || {
+        *loop_running.lock().unwrap() && match mode {
+            LoopMode::Duration(duration_ms) => start_time.elapsed().as_millis() < duration_ms as u128,
+            LoopMode::Count(_) | LoopMode::Continuous => true,
+        }
+    };
+    // 下游暂停期间等待，返回等待结束后循环是否应继续
+    let wait_while_paused = || {
+        while should_pause() {
+            if !keep_going() {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        keep_going()
+    };
+    
+    let mut iterations = 0;
+    let limit = match mode {
+        LoopMode::Count(count) => count,
+        LoopMode::Duration(_) | LoopMode::Continuous => usize::MAX,
+    };
+    while iterations < limit && wait_while_paused() {
+        let proceed = callback();
+        iterations += 1;
+        if !proceed {
+            break;
+        }
+        // 控制处理频率
+        thread::sleep(Duration::from_millis(interval_ms));
+    }
+    // 按次数和按时间循环结束后自动停止
+    if !matches!(mode, LoopMode::Continuous) {
+        *loop_running.lock().unwrap() = false;
+    }
+    
+    LoopStats { iterations, elapsed: start_time.elapsed() }
+}
+
 impl Default for MultiLoop {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+
+    #[test]
+    fn on_complete_fires_once_after_count_loop() {
+        let iterations = Arc::new(AtomicUsize::new(0));
+        let fired = Arc::new(AtomicUsize::new(0));
+        let (sender, receiver) = mpsc::channel();
+        let mut muloop = MultiLoop::new();
+        {
+            let iterations = Arc::clone(&iterations);
+            let fired = Arc::clone(&fired);
+            muloop.on_complete(move || {
+                fired.fetch_add(1, Ordering::SeqCst);
+                // 回调在最后一次循环之后才被调用
+                sender.send(iterations.load(Ordering::SeqCst)).unwrap();
+            });
+        }
+        let counted = Arc::clone(&iterations);
+        muloop.start(LoopMode::Count(3), move || {
+            counted.fetch_add(1, Ordering::SeqCst);
+        }, 1).unwrap();
+
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap(), 3);
+        muloop.join().unwrap();
+        assert!(!muloop.is_running());
+        assert_eq!(fired.load(Ordering::SeqCst), 1);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn run_scoped_mutates_borrowed_locals() {
+        let mut muloop = MultiLoop::new();
+        let mut counter = 0;
+        let mut seen = Vec::new();
+        let stats = muloop.run_scoped(LoopMode::Count(5), 0, || {
+            counter += 1;
+            seen.push(counter);
+            true
+        }).unwrap();
+        assert_eq!(counter, 5);
+        assert_eq!(seen, [1, 2, 3, 4, 5]);
+        assert_eq!(stats.iterations, 5);
+        assert!(!muloop.is_running());
+
+        // 回调返回false时提前结束
+        let mut remaining = 2;
+        let stats = muloop.run_scoped(LoopMode::Continuous, 0, || {
+            remaining -= 1;
+            remaining > 0
+        }).unwrap();
+        assert_eq!((remaining, stats.iterations), (0, 2));
+    }
+
+    #[test]
+    fn run_scoped_reports_panics_and_can_run_again() {
+        let mut muloop = MultiLoop::new();
+        let result = muloop.run_scoped(LoopMode::Count(3), 0, || panic!("回调失败"));
+        assert!(matches!(result, Err(PerpleError::WorkerPanicked(_))));
+        assert!(!muloop.is_running());
+
+        let mut counter = 0;
+        muloop.run_scoped(LoopMode::Count(2), 0, || { counter += 1; true }).unwrap();
+        assert_eq!(counter, 2);
+    }
+
+    #[test]
+    fn panicking_callback_resets_state_and_fires_on_complete() {
+        let (sender, receiver) = mpsc::channel();
+        let mut muloop = MultiLoop::new();
+        muloop.on_complete(move || sender.send(()).unwrap());
+        muloop.start(LoopMode::Continuous, || panic!("回调失败"), 1).unwrap();
+
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(matches!(muloop.join(), Err(PerpleError::WorkerPanicked(_))));
+        assert!(!muloop.is_running());
+
+        // 运行状态已复位，可以再次启动
+        let counter = Arc::new(AtomicUsize::new(0));
+        let counted = Arc::clone(&counter);
+        muloop.start(LoopMode::Count(2), move || {
+            counted.fetch_add(1, Ordering::SeqCst);
+        }, 0).unwrap();
+        muloop.join().unwrap();
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn backpressure_throttles_producer_to_slow_consumer() {
+        // 下游最多积压2项
+        let queued = Arc::new(AtomicUsize::new(0));
+        let produced = Arc::new(AtomicUsize::new(0));
+        let mut muloop = MultiLoop::new();
+        {
+            let (queued, produced) = (Arc::clone(&queued), Arc::clone(&produced));
+            let full = Arc::clone(&queued);
+            muloop.start_with_backpressure(LoopMode::Count(5), move || {
+                queued.fetch_add(1, Ordering::SeqCst);
+                produced.fetch_add(1, Ordering::SeqCst);
+            }, move || full.load(Ordering::SeqCst) >= 2, 0).unwrap();
+        }
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(produced.load(Ordering::SeqCst), 2);
+        assert!(muloop.is_running());
+
+        // 慢速消费者每次取走一项，生产者随之前进，积压始终不超过2项
+        while muloop.is_running() {
+            thread::sleep(Duration::from_millis(5));
+            assert!(queued.load(Ordering::SeqCst) <= 2);
+            let _ = queued.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+        }
+        muloop.join().unwrap();
+        assert_eq!(produced.load(Ordering::SeqCst), 5);
+    }
+}
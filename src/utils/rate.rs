@@ -0,0 +1,135 @@
+//! 限流模块
+//!
+//! 令牌桶限流器：令牌按固定速率补充，桶内最多积攒`burst`个令牌，
+//! 因此平均速率受限的同时允许短时间的突发。
+
+use std::time::Instant;
+
+use crate::error::PerpleError;
+
+/// 令牌桶限流器
+/// 
+/// 所有方法都有接受时间点参数的`_at`版本，便于用确定的时间驱动。
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    /// 每秒补充的令牌数
+    rate: f32,
+    /// 桶容量
+    burst: f32,
+    /// 当前令牌数
+    tokens: f32,
+    /// 上次补充令牌的时间
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// 创建一个装满令牌的限流器
+    /// 
+    /// # 参数
+    /// * `rate` - 每秒补充的令牌数，必须大于0
+    /// * `burst` - 桶容量，即允许的最大突发数量，不足1时按1处理
+    /// 
+    /// # 错误处理
+    /// `rate`不是大于0的有限值，或`burst`不是有限值时返回[PerpleError::InvalidParameter]
+    pub fn new(rate: f32, burst: f32) -> Result<Self, PerpleError> {
+        Self::new_at(rate, burst, Instant::now())
+    }
+    
+    /// 以指定时间点创建限流器，参见[TokenBucket::new]
+    pub fn new_at(rate: f32, burst: f32, now: Instant) -> Result<Self, PerpleError> {
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(PerpleError::InvalidParameter(format!("限流速率必须是大于0的有限值，实际为{}", rate)));
+        }
+        if !burst.is_finite() {
+            return Err(PerpleError::InvalidParameter(format!("限流突发数量必须是有限值，实际为{}", burst)));
+        }
+        let burst = burst.max(1.0);
+        Ok(Self { rate, burst, tokens: burst, last_refill: now })
+    }
+    
+    /// 尝试取走一个令牌，成功时返回`true`
+    pub fn try_acquire(&mut self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+    
+    /// 在指定时间点尝试取走一个令牌
+    /// 
+    /// 早于上次调用的时间点不会补充令牌。
+    pub fn try_acquire_at(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+    
+    /// 每秒补充的令牌数
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+    
+    /// 桶容量
+    pub fn burst(&self) -> f32 {
+        self.burst
+    }
+    
+    /// 指定时间点的可用令牌数
+    pub fn available_at(&mut self, now: Instant) -> f32 {
+        self.refill(now);
+        self.tokens
+    }
+    
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f32();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.last_refill = self.last_refill.max(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn rejects_invalid_rate_and_burst() {
+        for rate in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(matches!(TokenBucket::new(rate, 1.0), Err(PerpleError::InvalidParameter(_))), "rate={}", rate);
+        }
+        assert!(matches!(TokenBucket::new(1.0, f32::INFINITY), Err(PerpleError::InvalidParameter(_))));
+        assert_eq!(TokenBucket::new(1.0, 0.0).unwrap().burst(), 1.0);
+    }
+
+    #[test]
+    fn burst_then_steady_rate() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new_at(10.0, 3.0, start).unwrap();
+        assert!((0..3).all(|_| bucket.try_acquire_at(start)));
+        assert!(!bucket.try_acquire_at(start));
+
+        // 每100ms补充一个令牌
+        assert!(!bucket.try_acquire_at(start + Duration::from_millis(50)));
+        assert!(bucket.try_acquire_at(start + Duration::from_millis(100)));
+        assert!(!bucket.try_acquire_at(start + Duration::from_millis(150)));
+    }
+
+    #[test]
+    fn refill_is_capped_at_burst() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new_at(10.0, 2.0, start).unwrap();
+        bucket.try_acquire_at(start);
+        bucket.try_acquire_at(start);
+        assert_eq!(bucket.available_at(start + Duration::from_secs(60)), 2.0);
+    }
+
+    #[test]
+    fn earlier_time_does_not_refill() {
+        let start = Instant::now() + Duration::from_secs(1);
+        let mut bucket = TokenBucket::new_at(1.0, 1.0, start).unwrap();
+        assert!(bucket.try_acquire_at(start));
+        assert!(!bucket.try_acquire_at(start - Duration::from_millis(500)));
+        assert_eq!(bucket.available_at(start + Duration::from_millis(500)), 0.5);
+    }
+}
@@ -0,0 +1,324 @@
+//! 流水线调度模块
+//!
+//! 多条检测流水线共用有限的CPU时，各自的循环独立休眠和轮询，
+//! 先启动或推理更快的流水线可能长期占用CPU。[PipelineScheduler]为已注册的流水线
+//! 按权重轮流发放推理名额：同时等待的流水线中，按权重折算后获得名额最少的一条先执行。
+
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// 权重为1的流水线每获得一个名额前进的虚拟时间
+const STRIDE: u64 = 1 << 20;
+
+/// 流水线归还名额后，在这段时间内仍按其虚拟时间参与竞争
+/// 
+/// 归还名额的线程通常很快再次请求；若在它请求之前就把名额交给其他等待者，
+/// 两条流水线会严格交替执行，权重不起作用。
+pub const REACQUIRE_GRACE: Duration = Duration::from_millis(2);
+
+/// 单条流水线的调度状态
+struct Entry {
+    name: String,
+    weight: u32,
+    /// 虚拟时间，同时等待的流水线中最小者先获得名额
+    pass: u64,
+    waiting: bool,
+    /// 最近一次归还名额的时间，[REACQUIRE_GRACE]内仍视为在竞争
+    released_at: Option<Instant>,
+    slots: u64,
+    frames: u64,
+    since: Instant,
+}
+
+struct State {
+    entries: Vec<Entry>,
+    /// 空闲名额数
+    free: usize,
+    /// 最近一次发放名额时的虚拟时间，重新开始等待的流水线从这里起算
+    virtual_time: u64,
+}
+
+/// 多条流水线共用的推理名额调度器
+///
+/// 每条流水线先用[register](Self::register)注册并获得编号，每次推理前调用[acquire](Self::acquire)
+/// 取得名额，名额在返回的[SchedulerSlot]被释放时归还。
+/// 同时等待的流水线按权重比例获得名额（加权步幅调度）；空闲期间不积攒名额，
+/// 重新开始等待时与正在竞争的流水线从同一起点计算。
+/// 刚归还名额的流水线在[REACQUIRE_GRACE]内仍参与竞争，因此两次推理之间休眠更久的循环
+/// （例如[MultiLoop](crate::utils::muloop::MultiLoop)的间隔较长时）按先到先得获得名额。
+///
+/// 同一线程持有名额时不要再次调用[acquire](Self::acquire)，否则名额不足时会死锁。
+///
+/// # 示例
+///
+/// ```
+/// use perple::utils::sched::PipelineScheduler;
+///
+/// let scheduler = PipelineScheduler::new(1);
+/// let person = scheduler.register("person", 2);
+/// let vehicle = scheduler.register("vehicle", 1);
+/// {
+///     let slot = scheduler.acquire(person);
+///     // 执行一次推理……
+///     slot.record_frame();
+/// }
+/// drop(scheduler.acquire(vehicle));
+/// assert_eq!(scheduler.shares().len(), 2);
+/// ```
+pub struct PipelineScheduler {
+    state: Mutex<State>,
+    available: Condvar,
+    slots: usize,
+}
+
+/// 一条流水线的调度统计
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipelineShare {
+    /// 注册时的名称
+    pub name: String,
+    /// 权重
+    pub weight: u32,
+    /// 累计获得的名额数
+    pub slots: u64,
+    /// 累计完成的帧数，见[SchedulerSlot::record_frame]
+    pub frames: u64,
+    /// 自注册或上次[reset_metrics](PipelineScheduler::reset_metrics)以来的平均帧率
+    pub fps: f32,
+}
+
+impl PipelineScheduler {
+    /// 创建调度器
+    ///
+    /// # 参数
+    /// * `slots` - 可同时执行推理的流水线数，至少为1
+    pub fn new(slots: usize) -> Self {
+        let slots = slots.max(1);
+        Self {
+            state: Mutex::new(State { entries: Vec::new(), free: slots, virtual_time: 0 }),
+            available: Condvar::new(),
+            slots,
+        }
+    }
+
+    /// 可同时执行推理的流水线数
+    pub fn slots(&self) -> usize {
+        self.slots
+    }
+
+    /// 注册一条流水线并返回其编号，名称已注册时只更新权重并返回原编号
+    ///
+    /// 权重为0时按1处理。
+    pub fn register(&self, name: &str, weight: u32) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let weight = weight.max(1);
+        if let Some(id) = state.entries.iter().position(|e| e.name == name) {
+            state.entries[id].weight = weight;
+            return id;
+        }
+        let pass = state.virtual_time;
+        state.entries.push(Entry {
+            name: name.to_string(),
+            weight,
+            pass,
+            waiting: false,
+            released_at: None,
+            slots: 0,
+            frames: 0,
+            since: Instant::now(),
+        });
+        state.entries.len() - 1
+    }
+
+    /// 按名称查找已注册流水线的编号
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.state.lock().unwrap().entries.iter().position(|e| e.name == name)
+    }
+
+    /// 修改已注册流水线的权重，名称未注册时返回`false`
+    pub fn set_weight(&self, name: &str, weight: u32) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.entries.iter_mut().find(|e| e.name == name) {
+            Some(entry) => {
+                entry.weight = weight.max(1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 等待并取得一个推理名额
+    ///
+    /// # 参数
+    /// * `id` - [register](Self::register)返回的编号
+    ///
+    /// # Panics
+    /// `id`不是已注册的编号时panic
+    pub fn acquire(&self, id: usize) -> SchedulerSlot<'_> {
+        let mut state = self.state.lock().unwrap();
+        assert!(id < state.entries.len(), "流水线编号未注册: {}", id);
+        let virtual_time = state.virtual_time;
+        let entry = &mut state.entries[id];
+        entry.waiting = true;
+        entry.released_at = None;
+        entry.pass = entry.pass.max(virtual_time);
+
+        loop {
+            let now = Instant::now();
+            let next = state.entries.iter().enumerate()
+                .filter(|(_, e)| e.waiting || e.released_at.is_some_and(|t| now < t + REACQUIRE_GRACE))
+                .min_by_key(|(i, e)| (e.pass, *i))
+                .map(|(i, _)| i);
+            if state.free > 0 && next == Some(id) {
+                break;
+            }
+            // 名额为刚归还的流水线保留时，最多等到保留期结束
+            state = match next.and_then(|i| state.entries[i].released_at) {
+                Some(released_at) => self.available.wait_timeout(state, (released_at + REACQUIRE_GRACE).saturating_duration_since(now)).unwrap().0,
+                None => self.available.wait(state).unwrap(),
+            };
+        }
+
+        state.free -= 1;
+        let entry = &mut state.entries[id];
+        entry.waiting = false;
+        entry.slots += 1;
+        let pass = entry.pass;
+        entry.pass += STRIDE / entry.weight as u64;
+        state.virtual_time = pass;
+        // 还有空闲名额时让下一条等待的流水线继续竞争
+        self.available.notify_all();
+        SchedulerSlot { scheduler: self, id }
+    }
+
+    /// 各流水线的调度统计，按注册顺序排列
+    pub fn shares(&self) -> Vec<PipelineShare> {
+        let state = self.state.lock().unwrap();
+        state.entries.iter().map(|e| {
+            let elapsed = e.since.elapsed().as_secs_f32();
+            PipelineShare {
+                name: e.name.clone(),
+                weight: e.weight,
+                slots: e.slots,
+                frames: e.frames,
+                fps: if elapsed > 0.0 { e.frames as f32 / elapsed } else { 0.0 },
+            }
+        }).collect()
+    }
+
+    /// 指定流水线的平均帧率，名称未注册时返回`None`
+    pub fn achieved_fps(&self, name: &str) -> Option<f32> {
+        self.shares().into_iter().find(|s| s.name == name).map(|s| s.fps)
+    }
+
+    /// 清零所有流水线的名额数和帧数，帧率从现在起重新计算
+    pub fn reset_metrics(&self) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        for entry in state.entries.iter_mut() {
+            entry.slots = 0;
+            entry.frames = 0;
+            entry.since = now;
+        }
+    }
+
+    fn release(&self, id: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.free += 1;
+        state.entries[id].released_at = Some(Instant::now());
+        self.available.notify_all();
+    }
+}
+
+impl std::fmt::Debug for PipelineScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("PipelineScheduler")
+            .field("slots", &self.slots)
+            .field("free", &state.free)
+            .field("pipelines", &state.entries.iter().map(|e| (&e.name, e.weight)).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// [PipelineScheduler::acquire]取得的推理名额，释放时归还
+#[derive(Debug)]
+pub struct SchedulerSlot<'a> {
+    scheduler: &'a PipelineScheduler,
+    id: usize,
+}
+
+impl SchedulerSlot<'_> {
+    /// 记录本次名额内完成了一帧推理，计入该流水线的帧率
+    ///
+    /// 名额内没有可处理的图像时不要调用，避免空转被计为帧率。
+    pub fn record_frame(&self) {
+        self.scheduler.state.lock().unwrap().entries[self.id].frames += 1;
+    }
+}
+
+impl Drop for SchedulerSlot<'_> {
+    fn drop(&mut self) {
+        self.scheduler.release(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    /// 两条推理耗时不同的模拟流水线共用一个名额，持续竞争`duration`后返回各自获得的名额数
+    fn compete(weights: (u32, u32), duration: Duration) -> (u64, u64) {
+        let scheduler = Arc::new(PipelineScheduler::new(1));
+        let running = Arc::new(AtomicBool::new(true));
+        let pipelines = [("fast", weights.0, Duration::from_micros(200)), ("slow", weights.1, Duration::from_millis(2))];
+        let handles: Vec<_> = pipelines.into_iter().map(|(name, weight, inference)| {
+            let id = scheduler.register(name, weight);
+            let (scheduler, running) = (Arc::clone(&scheduler), Arc::clone(&running));
+            thread::spawn(move || {
+                while running.load(Ordering::SeqCst) {
+                    let slot = scheduler.acquire(id);
+                    thread::sleep(inference);
+                    slot.record_frame();
+                }
+            })
+        }).collect();
+        thread::sleep(duration);
+        running.store(false, Ordering::SeqCst);
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let shares = scheduler.shares();
+        assert!(shares.iter().all(|share| share.frames == share.slots && share.fps > 0.0));
+        assert!(scheduler.achieved_fps("fast").unwrap() > 0.0);
+        (shares[0].slots, shares[1].slots)
+    }
+
+    #[test]
+    fn equal_weights_share_slots_regardless_of_speed() {
+        let (fast, slow) = compete((1, 1), Duration::from_millis(300));
+        // 推理快的流水线不会占满名额
+        assert!(fast.abs_diff(slow) <= 2, "fast={} slow={}", fast, slow);
+    }
+
+    #[test]
+    fn slots_follow_configured_weights() {
+        let (fast, slow) = compete((1, 3), Duration::from_millis(400));
+        let ratio = slow as f32 / fast as f32;
+        assert!((2.0..=4.0).contains(&ratio), "fast={} slow={}", fast, slow);
+    }
+
+    #[test]
+    fn register_is_idempotent_and_updates_weight() {
+        let scheduler = PipelineScheduler::new(0);
+        assert_eq!(scheduler.slots(), 1);
+        let id = scheduler.register("person", 0);
+        assert_eq!(scheduler.register("person", 4), id);
+        assert_eq!(scheduler.shares()[0].weight, 4);
+        assert!(!scheduler.set_weight("vehicle", 2));
+        assert_eq!(scheduler.id("vehicle"), None);
+    }
+}
@@ -127,4 +127,227 @@ where
             stack.push((left, end));
         }
     }
-}
\ No newline at end of file
+}
+
+/// [adaptive_group_sort_by]改用插入排序的组数上限
+pub const INSERTION_SORT_MAX_GROUPS: usize = 16;
+
+/// 按组插入排序，参数与[group_sort_by]相同
+/// 
+/// 逐组向前插入，每次交换整组元素。排序是稳定的；组数很少时没有快速排序的栈和分区开销。
+pub fn insertion_sort_groups_by<T, F>(arr: &mut [T], split: usize, offset: usize, compare: F)
+where
+    F: Fn(&T, &T) -> std::cmp::Ordering,
+{
+    if arr.len() < split || offset >= split || !arr.len().is_multiple_of(split) {
+        return;
+    }
+    let groups = arr.len() / split;
+    for i in 1..groups {
+        let mut j = i;
+        while j > 0 && compare(&arr[(j - 1) * split + offset], &arr[j * split + offset]) == std::cmp::Ordering::Greater {
+            for order in 0..split {
+                arr.swap((j - 1) * split + order, j * split + order);
+            }
+            j -= 1;
+        }
+    }
+}
+
+/// 按组数选择排序算法：不超过[INSERTION_SORT_MAX_GROUPS]组时使用[insertion_sort_groups_by]，
+/// 否则使用[group_sort_by]
+pub fn adaptive_group_sort_by<T, F>(arr: &mut [T], split: usize, offset: usize, compare: F)
+where
+    F: Fn(&T, &T) -> std::cmp::Ordering,
+{
+    if split == 0 {
+        return;
+    }
+    if arr.len() / split <= INSERTION_SORT_MAX_GROUPS {
+        insertion_sort_groups_by(arr, split, offset, compare);
+    } else {
+        group_sort_by(arr, split, offset, compare);
+    }
+}
+
+/// 只对排序后位于最前面的`k`组做部分排序
+/// 
+/// 与[group_sort_by]相同，数组按每`split`个元素为一组，以组内第`offset`个元素为排序键。
+/// 调用后前`k`组保证是按`compare`排在最前面的`k`组且已有序，其余组的顺序不做保证。
+/// 适合只关心置信度最高的少量检测框的场景（例如NMS），比完整排序更快。
+/// `k`不小于组数时等价于[adaptive_group_sort_by]，前`k`组的排序同样按组数选择算法。
+pub fn partial_group_sort_by<T, F>(arr: &mut [T], split: usize, offset: usize, k: usize, compare: F)
+where
+    F: Fn(&T, &T) -> std::cmp::Ordering,
+{
+    if arr.len() < split || offset >= split || !arr.len().is_multiple_of(split) {
+        return;
+    }
+    let groups = arr.len() / split;
+    if k == 0 {
+        return;
+    }
+    if k >= groups {
+        adaptive_group_sort_by(arr, split, offset, compare);
+        return;
+    }
+
+    // 先在组索引上做快速选择，找出排在前k位的组
+    let mut indices: Vec<usize> = (0..groups).collect();
+    indices.select_nth_unstable_by(k - 1, |&a, &b| compare(&arr[a * split + offset], &arr[b * split + offset]));
+
+    // 把选中的组依次交换到前k个位置，position记录每个原始组当前所在位置
+    let mut position: Vec<usize> = (0..groups).collect();
+    let mut occupant: Vec<usize> = (0..groups).collect();
+    for (target, &group) in indices[..k].iter().enumerate() {
+        let current = position[group];
+        if current != target {
+            for order in 0..split {
+                arr.swap(target * split + order, current * split + order);
+            }
+            let displaced = occupant[target];
+            occupant[current] = displaced;
+            position[displaced] = current;
+            occupant[target] = group;
+            position[group] = target;
+        }
+    }
+
+    adaptive_group_sort_by(&mut arr[..k * split], split, offset, compare);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering;
+
+    /// 每组为`[编号, 编号*2, 编号*3, 置信度]`，以第3个元素为排序键
+    const SPLIT: usize = 4;
+    const OFFSET: usize = 3;
+
+    fn rows(confidences: &[f32]) -> Vec<f32> {
+        confidences.iter().enumerate()
+            .flat_map(|(id, &confidence)| [id as f32, id as f32 * 2.0, id as f32 * 3.0, confidence])
+            .collect()
+    }
+
+    /// 有重复值的确定性置信度序列
+    fn scrambled(groups: usize) -> Vec<f32> {
+        (0..groups).map(|i| ((i * 37) % 23) as f32 / 23.0).collect()
+    }
+
+    fn descending(a: &f32, b: &f32) -> Ordering {
+        b.total_cmp(a)
+    }
+
+    /// 用标准库的稳定排序整组排序，作为对照
+    fn reference(data: &[f32]) -> Vec<f32> {
+        let mut groups: Vec<&[f32]> = data.chunks_exact(SPLIT).collect();
+        groups.sort_by(|a, b| descending(&a[OFFSET], &b[OFFSET]));
+        groups.concat()
+    }
+
+    fn keys(data: &[f32]) -> Vec<u32> {
+        data.chunks_exact(SPLIT).map(|group| group[OFFSET].to_bits()).collect()
+    }
+
+    /// 排序只整组移动：每组内容保持不变，且各组仍是原来的那些组
+    fn assert_groups_intact(sorted: &[f32], original: &[f32]) {
+        let ids = |data: &[f32]| {
+            let mut ids: Vec<u32> = data.chunks_exact(SPLIT).map(|group| group[0] as u32).collect();
+            ids.sort_unstable();
+            ids
+        };
+        assert_eq!(ids(sorted), ids(original));
+        for group in sorted.chunks_exact(SPLIT) {
+            let id = group[0] as usize;
+            let bits = |values: &[f32]| values.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+            assert_eq!(bits(&group[1..]), bits(&[id as f32 * 2.0, id as f32 * 3.0, original[id * SPLIT + OFFSET]]));
+        }
+    }
+
+    #[test]
+    fn partial_sort_matches_full_sort_prefix() {
+        for groups in [1, 2, 5, 16, 17, 40] {
+            let original = rows(&scrambled(groups));
+            let expected = keys(&reference(&original));
+            for k in 0..=groups + 2 {
+                let mut data = original.clone();
+                partial_group_sort_by(&mut data, SPLIT, OFFSET, k, descending);
+                assert_groups_intact(&data, &original);
+                let sorted = k.min(groups);
+                assert_eq!(keys(&data)[..sorted], expected[..sorted], "groups={} k={}", groups, k);
+            }
+        }
+    }
+
+    #[test]
+    fn partial_sort_handles_empty_and_equal_keys() {
+        let mut empty: Vec<f32> = Vec::new();
+        partial_group_sort_by(&mut empty, SPLIT, OFFSET, 3, descending);
+        assert!(empty.is_empty());
+
+        for groups in [3, 30] {
+            let original = rows(&vec![0.5; groups]);
+            for k in [1, groups / 2, groups, groups + 1] {
+                let mut data = original.clone();
+                partial_group_sort_by(&mut data, SPLIT, OFFSET, k, descending);
+                assert_groups_intact(&data, &original);
+            }
+        }
+    }
+
+    #[test]
+    fn partial_sort_with_nan_confidences() {
+        let mut confidences = scrambled(30);
+        for i in [0, 7, 19, 29] {
+            confidences[i] = f32::NAN;
+        }
+        let original = rows(&confidences);
+
+        // 全序比较下NaN排在最前，结果与标准库排序一致
+        let expected = keys(&reference(&original));
+        for k in [1, 4, 10, 30] {
+            let mut data = original.clone();
+            partial_group_sort_by(&mut data, SPLIT, OFFSET, k, descending);
+            assert_groups_intact(&data, &original);
+            assert_eq!(keys(&data)[..k], expected[..k], "k={}", k);
+        }
+
+        // NMS使用的比较函数把NaN视为与任何值相等，顺序不确定，但不会panic或拆散分组
+        for k in [1, 4, 10, 30] {
+            let mut data = original.clone();
+            partial_group_sort_by(&mut data, SPLIT, OFFSET, k, |a: &f32, b: &f32| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+            assert_groups_intact(&data, &original);
+        }
+    }
+
+    #[test]
+    fn insertion_sort_is_stable_like_sort_by() {
+        for groups in [0, 1, 2, 7, INSERTION_SORT_MAX_GROUPS, 40] {
+            let original = rows(&scrambled(groups));
+            let mut data = original.clone();
+            insertion_sort_groups_by(&mut data, SPLIT, OFFSET, descending);
+            // 稳定排序，相同置信度的组保持原有先后，结果与对照完全一致
+            assert_eq!(data, reference(&original), "groups={}", groups);
+        }
+    }
+
+    #[test]
+    fn adaptive_sort_matches_sort_by_around_threshold() {
+        let max = INSERTION_SORT_MAX_GROUPS;
+        for groups in [max - 1, max, max + 1, max * 4] {
+            for confidences in [scrambled(groups), vec![0.5; groups], (0..groups).map(|i| i as f32).collect()] {
+                let original = rows(&confidences);
+                let mut data = original.clone();
+                adaptive_group_sort_by(&mut data, SPLIT, OFFSET, descending);
+                assert_groups_intact(&data, &original);
+                assert_eq!(keys(&data), keys(&reference(&original)), "groups={}", groups);
+                // 不超过阈值时走插入排序，结果稳定
+                if groups <= max {
+                    assert_eq!(data, reference(&original));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! 统计模块
+//! 
+//! 提供检测数量随时间变化的滚动窗口统计，以及推理延迟直方图。
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// 检测数量滚动窗口直方图
+/// 
+/// 记录最近`window_frames`帧的检测数量，用于分析场景中的人数分布。
+#[derive(Debug, Clone)]
+pub struct DetectionHistogram {
+    window_frames: usize,
+    counts: VecDeque<usize>,
+}
+
+impl DetectionHistogram {
+    /// 创建一个新的直方图
+    /// 
+    /// # 参数
+    /// * `window_frames` - 窗口大小（帧数），至少为1
+    pub fn new(window_frames: usize) -> Self {
+        let window_frames = window_frames.max(1);
+        Self {
+            window_frames,
+            counts: VecDeque::with_capacity(window_frames),
+        }
+    }
+    
+    /// 记录一帧的检测数量，超出窗口的最旧记录会被丢弃
+    pub fn record(&mut self, count: usize) {
+        if self.counts.len() == self.window_frames {
+            self.counts.pop_front();
+        }
+        self.counts.push_back(count);
+    }
+    
+    /// 窗口内的平均检测数量，没有记录时返回0.0
+    pub fn mean(&self) -> f64 {
+        if self.counts.is_empty() {
+            return 0.0;
+        }
+        self.counts.iter().sum::<usize>() as f64 / self.counts.len() as f64
+    }
+    
+    /// 窗口内检测数量的95百分位数（最近秩法），没有记录时返回0
+    pub fn percentile_95(&self) -> usize {
+        if self.counts.is_empty() {
+            return 0;
+        }
+        let mut sorted: Vec<usize> = self.counts.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (sorted.len() as f64 * 0.95).ceil() as usize;
+        sorted[rank.saturating_sub(1)]
+    }
+    
+    /// 窗口内的最大检测数量，没有记录时返回0
+    pub fn max(&self) -> usize {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+    
+    /// 调整窗口大小，缩小时丢弃最旧的记录
+    pub fn set_window(&mut self, window_frames: usize) {
+        self.window_frames = window_frames.max(1);
+        while self.counts.len() > self.window_frames {
+            self.counts.pop_front();
+        }
+    }
+    
+    /// 窗口大小（帧数）
+    pub fn window_frames(&self) -> usize {
+        self.window_frames
+    }
+    
+    /// 当前记录的帧数
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+    
+    /// 是否还没有任何记录
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+}
+
+impl Default for DetectionHistogram {
+    fn default() -> Self {
+        Self::new(crate::config::DEFAULT_HISTOGRAM_WINDOW)
+    }
+}
+
+/// 延迟直方图各桶的上界（微秒），从1ms到约2s按√2倍递增
+const LATENCY_BUCKET_BOUNDS_US: [u64; 23] = [
+    1_000, 1_414, 2_000, 2_828, 4_000, 5_657, 8_000, 11_314, 16_000, 22_627, 32_000, 45_255,
+    64_000, 90_510, 128_000, 181_019, 256_000, 362_039, 512_000, 724_077, 1_024_000, 1_448_155,
+    2_048_000,
+];
+
+/// 延迟直方图的桶数量（最后一个桶收集超过最大上界的样本）
+pub const LATENCY_BUCKETS: usize = LATENCY_BUCKET_BOUNDS_US.len() + 1;
+
+/// 固定分桶的推理延迟直方图
+/// 
+/// 桶边界按对数刻度从1ms到约2s分布。记录样本只涉及原子自增，
+/// 不加锁也不分配内存，可以在推理热循环中直接调用。
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS],
+    count: AtomicU64,
+    sum_us: AtomicU64,
+}
+
+impl LatencyHistogram {
+    /// 创建一个空的直方图
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 第`index`个桶的上界，最后一个桶没有上界，返回`None`
+    pub fn bucket_upper_bound(index: usize) -> Option<Duration> {
+        LATENCY_BUCKET_BOUNDS_US.get(index).map(|&us| Duration::from_micros(us))
+    }
+    
+    /// 延迟所属桶的索引（上界包含在桶内）
+    pub fn bucket_index(latency: Duration) -> usize {
+        let us = latency.as_micros().min(u64::MAX as u128) as u64;
+        LATENCY_BUCKET_BOUNDS_US.partition_point(|&bound| bound < us)
+    }
+    
+    /// 记录一次延迟
+    pub fn record(&self, latency: Duration) {
+        let us = latency.as_micros().min(u64::MAX as u128) as u64;
+        self.buckets[Self::bucket_index(latency)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(us, Ordering::Relaxed);
+    }
+    
+    /// 已记录的样本数
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+    
+    /// 各桶当前的样本数
+    pub fn bucket_counts(&self) -> [u64; LATENCY_BUCKETS] {
+        std::array::from_fn(|i| self.buckets[i].load(Ordering::Relaxed))
+    }
+    
+    /// 平均延迟，没有样本时返回0
+    pub fn mean(&self) -> Duration {
+        let count = self.count();
+        if count == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_micros(self.sum_us.load(Ordering::Relaxed) / count)
+    }
+    
+    /// 估计第`p`百分位延迟（`p`取值0~100）
+    /// 
+    /// 在目标样本所在的桶内做线性插值；落在最后一个（无上界）桶时返回最大上界。
+    /// 没有样本时返回0。
+    pub fn percentile(&self, p: f64) -> Duration {
+        let counts = self.bucket_counts();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return Duration::ZERO;
+        }
+        
+        let rank = (p.clamp(0.0, 100.0) / 100.0) * total as f64;
+        let mut cumulative = 0u64;
+        for (index, &count) in counts.iter().enumerate() {
+            if count == 0 || ((cumulative + count) as f64) < rank {
+                cumulative += count;
+                continue;
+            }
+            let lower = if index == 0 { 0 } else { LATENCY_BUCKET_BOUNDS_US[index - 1] };
+            let Some(&upper) = LATENCY_BUCKET_BOUNDS_US.get(index) else {
+                return Duration::from_micros(lower);
+            };
+            let fraction = ((rank - cumulative as f64) / count as f64).clamp(0.0, 1.0);
+            return Duration::from_micros(lower + ((upper - lower) as f64 * fraction) as u64);
+        }
+        Duration::from_micros(LATENCY_BUCKET_BOUNDS_US[LATENCY_BUCKET_BOUNDS_US.len() - 1])
+    }
+    
+    /// 清空所有样本
+    pub fn reset(&self) {
+        for bucket in &self.buckets {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.count.store(0, Ordering::Relaxed);
+        self.sum_us.store(0, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Display for LatencyHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "n={} mean={:.1?} p50={:.1?} p95={:.1?} p99={:.1?}",
+            self.count(),
+            self.mean(),
+            self.percentile(50.0),
+            self.percentile(95.0),
+            self.percentile(99.0)
+        )
+    }
+}
+
+/// 检测流水线运行统计
+/// 
+/// 由推理线程更新、外部线程读取，内部全部使用原子类型。
+#[derive(Debug, Default)]
+pub struct PipelineStats {
+    latency: LatencyHistogram,
+    degraded_frames: AtomicU64,
+    invalid_frames: AtomicU64,
+    rate_limited_frames: AtomicU64,
+    engine_lost: AtomicBool,
+}
+
+impl PipelineStats {
+    /// 创建一个空的统计
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 单帧推理延迟直方图
+    pub fn latency_histogram(&self) -> &LatencyHistogram {
+        &self.latency
+    }
+    
+    /// 记录一帧因超出处理预算而降级
+    pub fn record_degraded_frame(&self) {
+        self.degraded_frames.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// 累计降级帧数
+    pub fn degraded_frames(&self) -> u64 {
+        self.degraded_frames.load(Ordering::Relaxed)
+    }
+    
+    /// 记录一帧因输入图像无法检测（面积为0或尺寸过小）被跳过
+    pub fn record_invalid_frame(&self) {
+        self.invalid_frames.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// 累计因输入图像无法检测被跳过的帧数
+    pub fn invalid_frames(&self) -> u64 {
+        self.invalid_frames.load(Ordering::Relaxed)
+    }
+    
+    /// 记录一帧因超出输入帧率上限被拒绝，返回累计拒绝帧数
+    pub fn record_rate_limited_frame(&self) -> u64 {
+        self.rate_limited_frames.fetch_add(1, Ordering::Relaxed) + 1
+    }
+    
+    /// 累计因超出输入帧率上限被拒绝的帧数
+    pub fn rate_limited_frames(&self) -> u64 {
+        self.rate_limited_frames.load(Ordering::Relaxed)
+    }
+    
+    /// 记录推理线程异常退出、检测器状态已丢失
+    pub fn record_engine_lost(&self) {
+        self.engine_lost.store(true, Ordering::Relaxed);
+    }
+    
+    /// 检测器状态是否已因推理线程异常退出而丢失
+    pub fn engine_lost(&self) -> bool {
+        self.engine_lost.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detection_histogram_percentile_over_100_frames() {
+        let mut histogram = DetectionHistogram::new(100);
+        // 37与100互质，得到1..=100的一个打乱顺序的排列
+        for i in 0..100 {
+            histogram.record(i * 37 % 100 + 1);
+        }
+        // 最近秩法：第ceil(0.95 * 100) = 95小的值
+        assert_eq!(histogram.percentile_95(), 95);
+        assert_eq!(histogram.mean(), 50.5);
+        assert_eq!(histogram.max(), 100);
+        assert_eq!(histogram.len(), 100);
+
+        // 超出窗口后丢弃最旧的记录
+        histogram.record(0);
+        assert_eq!(histogram.len(), 100);
+        assert_eq!(histogram.max(), 100);
+        assert_eq!(histogram.mean(), (5050 - 1) as f64 / 100.0);
+    }
+
+    #[test]
+    fn detection_histogram_window_shrinks_from_oldest() {
+        let mut histogram = DetectionHistogram::new(10);
+        assert_eq!((histogram.mean(), histogram.percentile_95(), histogram.max()), (0.0, 0, 0));
+        for count in [9, 1, 2, 3] {
+            histogram.record(count);
+        }
+        histogram.set_window(3);
+        assert_eq!(histogram.window_frames(), 3);
+        assert_eq!((histogram.max(), histogram.percentile_95(), histogram.mean()), (3, 3, 2.0));
+        histogram.set_window(0);
+        assert_eq!((histogram.window_frames(), histogram.len()), (1, 1));
+    }
+
+    #[test]
+    fn bucket_upper_bounds_are_inclusive() {
+        assert_eq!(LatencyHistogram::bucket_index(Duration::ZERO), 0);
+        assert_eq!(LatencyHistogram::bucket_index(Duration::from_millis(1)), 0);
+        assert_eq!(LatencyHistogram::bucket_index(Duration::from_micros(1_001)), 1);
+        assert_eq!(LatencyHistogram::bucket_index(Duration::from_millis(2)), 2);
+        assert_eq!(LatencyHistogram::bucket_index(Duration::from_micros(2_048_000)), LATENCY_BUCKETS - 2);
+        assert_eq!(LatencyHistogram::bucket_index(Duration::from_secs(60)), LATENCY_BUCKETS - 1);
+        assert_eq!(LatencyHistogram::bucket_upper_bound(0), Some(Duration::from_millis(1)));
+        assert_eq!(LatencyHistogram::bucket_upper_bound(LATENCY_BUCKETS - 1), None);
+    }
+
+    #[test]
+    fn percentile_interpolates_within_bucket() {
+        let histogram = LatencyHistogram::new();
+        // 4个样本都落在(16ms, 22.627ms]桶中
+        for _ in 0..4 {
+            histogram.record(Duration::from_millis(20));
+        }
+        assert_eq!(histogram.percentile(0.0), Duration::from_micros(16_000));
+        assert_eq!(histogram.percentile(50.0), Duration::from_micros(16_000 + (6_627 / 2)));
+        assert_eq!(histogram.percentile(100.0), Duration::from_micros(22_627));
+        assert_eq!(histogram.mean(), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn percentile_spans_buckets_and_overflow() {
+        let histogram = LatencyHistogram::new();
+        for _ in 0..9 {
+            histogram.record(Duration::from_micros(500));
+        }
+        histogram.record(Duration::from_secs(10));
+        assert_eq!(histogram.count(), 10);
+        assert_eq!(histogram.percentile(50.0), Duration::from_micros(1_000 * 5 / 9));
+        assert_eq!(histogram.percentile(99.0), Duration::from_micros(2_048_000));
+
+        histogram.reset();
+        assert_eq!(histogram.count(), 0);
+        assert_eq!(histogram.percentile(95.0), Duration::ZERO);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::config::STREAM_CAPACITY;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::error::PerpleError;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::mem::MaybeUninit;
 
 
@@ -7,12 +8,19 @@ use std::mem::MaybeUninit;
 /// 推荐使用方法：
 /// 获取写入位置的可变引用 -> 填充数据 -> 提交写入操作
 /// 获取读取位置的引用 -> 处理数据 -> 提交读取操作
-
-
+///
+/// 所有槽位始终保存已初始化的`Option<T>`：读取时用`None`替换取出的元素，
+/// 写入时释放槽位中残留的旧值，因此元素不会被泄漏或重复释放。
 pub struct Stream<T: Default + Send> {
     pool: [MaybeUninit<Option<T>>; STREAM_CAPACITY],
     read_index: AtomicUsize,
     write_index: AtomicUsize,
+    /// 累计写入的元素个数（仅用于监控，Relaxed顺序）
+    writes_total: AtomicU64,
+    /// 累计读取的元素个数
+    reads_total: AtomicU64,
+    /// 累计因缓冲区已满被丢弃的旧元素个数
+    drops_total: AtomicU64,
 }
 
 impl<T: Default + Send> Stream<T> { 
@@ -22,14 +30,17 @@ impl<T: Default + Send> Stream<T> {
             unsafe { MaybeUninit::uninit().assume_init() };
         
         // 初始化所有元素
-        for i in 0..STREAM_CAPACITY {
-            pool[i] = MaybeUninit::new(None);
+        for slot in pool.iter_mut() {
+            *slot = MaybeUninit::new(None);
         }
         
         Self {
             pool,
             read_index: AtomicUsize::new(0),
             write_index: AtomicUsize::new(0),
+            writes_total: AtomicU64::new(0),
+            reads_total: AtomicU64::new(0),
+            drops_total: AtomicU64::new(0),
         }
     }
     
@@ -61,6 +72,7 @@ impl<T: Default + Send> Stream<T> {
         
         // 更新写索引
         self.write_index.store(next_index, Ordering::Release);
+        self.writes_total.fetch_add(1, Ordering::Relaxed);
         Ok(())
     }
     
@@ -91,6 +103,7 @@ impl<T: Default + Send> Stream<T> {
         let next_index = (current_read + 1) % STREAM_CAPACITY;
         // 更新读索引
         self.read_index.store(next_index, Ordering::Release);
+        self.reads_total.fetch_add(1, Ordering::Relaxed);
         Ok(())
     }
     
@@ -111,16 +124,52 @@ impl<T: Default + Send> Stream<T> {
                 Ordering::Release, 
                 Ordering::Relaxed
             ).is_ok() {
-                // 安全地写入数据
+                // 安全地写入数据，槽位中残留的旧值被正常释放
                 unsafe {
-                    self.pool[current_write].as_mut_ptr().write(Some(item));
+                    *self.pool[current_write].as_mut_ptr() = Some(item);
                 }
+                self.writes_total.fetch_add(1, Ordering::Relaxed);
                 return Ok(());
             }
             // 如果更新失败，重新尝试
         }
     }
     
+    /// 批量写入多个元素
+    /// 
+    /// 只在开始时读取一次读索引来确定可用容量，随后将元素连续写入环形缓冲区，
+    /// 最后一次性更新写索引，使这批元素对读取方同时可见。
+    /// 超出可用容量的元素不会被写入。
+    /// 
+    /// # 返回值
+    /// 返回成功写入的元素个数；若缓冲区已满、一个元素都无法写入，则返回`PerpleError::BufferFull`
+    pub fn write_batch(&mut self, items: impl IntoIterator<Item = T>) -> Result<usize, PerpleError> {
+        let current_read = self.read_index.load(Ordering::Acquire);
+        let current_write = self.write_index.load(Ordering::Acquire);
+        
+        // 环形缓冲区保留一个空位用于区分满和空
+        let used = (current_write + STREAM_CAPACITY - current_read) % STREAM_CAPACITY;
+        let available = STREAM_CAPACITY - 1 - used;
+        if available == 0 {
+            return Err(PerpleError::BufferFull { count: 0 });
+        }
+        
+        let mut count = 0;
+        for item in items.into_iter().take(available) {
+            let index = (current_write + count) % STREAM_CAPACITY;
+            // 安全地写入数据
+            unsafe {
+                *self.pool[index].as_mut_ptr() = Some(item);
+            }
+            count += 1;
+        }
+        
+        // 一次性提交整批写入
+        self.write_index.store((current_write + count) % STREAM_CAPACITY, Ordering::Release);
+        self.writes_total.fetch_add(count as u64, Ordering::Relaxed);
+        Ok(count)
+    }
+    
     pub fn read(&mut self) -> Option<T> {
         loop {
             let current_read = self.read_index.load(Ordering::Acquire);
@@ -139,16 +188,38 @@ impl<T: Default + Send> Stream<T> {
                 Ordering::Release, 
                 Ordering::Relaxed
             ).is_ok() {
-                // 安全地读取数据
+                // 取出数据并在槽位中留下None，避免之后覆盖写入时重复释放
                 let item = unsafe {
-                    self.pool[current_read].assume_init_read()
+                    std::ptr::replace(self.pool[current_read].as_mut_ptr(), None)
                 };
+                self.reads_total.fetch_add(1, Ordering::Relaxed);
                 return item;
             }
             // 如果更新失败，重新尝试
         }
     }
     
+    /// 查看最近写入且尚未被读取的至多`n`个元素，不会移动读索引
+    /// 
+    /// # 返回值
+    /// 按写入顺序（从旧到新）返回元素引用
+    pub fn peek_recent(&self, n: usize) -> Vec<&T> {
+        let current_read = self.read_index.load(Ordering::Acquire);
+        let current_write = self.write_index.load(Ordering::Acquire);
+        
+        let pending = (current_write + STREAM_CAPACITY - current_read) % STREAM_CAPACITY;
+        let count = pending.min(n);
+        let start = (current_write + STREAM_CAPACITY - count) % STREAM_CAPACITY;
+        
+        (0..count)
+            .filter_map(|offset| {
+                let index = (start + offset) % STREAM_CAPACITY;
+                // 安全地返回引用，[read, write)区间内的元素均已初始化
+                unsafe { (*self.pool[index].as_ptr()).as_ref() }
+            })
+            .collect()
+    }
+    
     /// 检查流中是否有数据
     pub fn has_data(&self) -> bool {
         let current_read = self.read_index.load(Ordering::Acquire);
@@ -156,38 +227,405 @@ impl<T: Default + Send> Stream<T> {
         current_read != current_write
     }
     
-    /// 直接写入到指定索引位置，无额外拷贝
-    /// 通过读写标记保障数据一致性
+    /// 写入一个元素，缓冲区已满时先丢弃最旧的元素
+    /// 
+    /// 适合只关心最新数据的场景（例如实时画面），被丢弃的元素计入[drops_total](Self::drops_total)。
+    pub fn write_or_drop_oldest(&mut self, item: T) {
+        if self.is_full() {
+            let current_read = self.read_index.load(Ordering::Acquire);
+            // 丢弃最旧的元素，槽位中留下None
+            unsafe {
+                *self.pool[current_read].as_mut_ptr() = None;
+            }
+            self.read_index.store((current_read + 1) % STREAM_CAPACITY, Ordering::Release);
+            self.drops_total.fetch_add(1, Ordering::Relaxed);
+        }
+        // 已腾出空位，且持有&mut self时不会有并发写入
+        let _ = self.write(item);
+    }
+    
+    /// 丢弃所有尚未被读取的元素
+    /// 
+    /// 被丢弃的元素计入[drops_total](Self::drops_total)而非[reads_total](Self::reads_total)。
+    /// 
+    /// # 返回值
+    /// 返回丢弃的元素个数
+    pub fn drain(&mut self) -> usize {
+        let mut count = 0;
+        while self.read().is_some() {
+            count += 1;
+        }
+        self.reads_total.fetch_sub(count as u64, Ordering::Relaxed);
+        self.drops_total.fetch_add(count as u64, Ordering::Relaxed);
+        count
+    }
+    
+    /// 用`item`替换最近写入且尚未被读取的元素，不移动读写索引，也不改变各项计数
+    /// 
+    /// 流为空时不做任何修改，原样返回`Err(item)`。
+    pub fn replace_latest(&mut self, item: T) -> Result<(), T> {
+        if !self.has_data() {
+            return Err(item);
+        }
+        let current_write = self.write_index.load(Ordering::Acquire);
+        let latest = (current_write + STREAM_CAPACITY - 1) % STREAM_CAPACITY;
+        // 安全性：[read, write)区间内的槽位均已初始化，赋值时释放旧元素
+        unsafe {
+            *self.pool[latest].as_mut_ptr() = Some(item);
+        }
+        Ok(())
+    }
+    
+    /// 累计写入的元素个数
+    pub fn writes_total(&self) -> u64 {
+        self.writes_total.load(Ordering::Relaxed)
+    }
+    
+    /// 累计读取的元素个数（不含被[write_or_drop_oldest](Self::write_or_drop_oldest)或[drain](Self::drain)丢弃的元素）
+    pub fn reads_total(&self) -> u64 {
+        self.reads_total.load(Ordering::Relaxed)
+    }
+    
+    /// 累计被[write_or_drop_oldest](Self::write_or_drop_oldest)或[drain](Self::drain)丢弃的元素个数
+    pub fn drops_total(&self) -> u64 {
+        self.drops_total.load(Ordering::Relaxed)
+    }
+    
+    /// 由计数器估算的待读取元素个数（写入数 - 读取数 - 丢弃数）
+    /// 
+    /// 计数器按Relaxed顺序更新，并发读写时只是近似值。
+    pub fn pending(&self) -> usize {
+        self.writes_total()
+            .saturating_sub(self.reads_total())
+            .saturating_sub(self.drops_total()) as usize
+    }
+    
+    /// 丢弃率：丢弃数 / 写入数，尚无写入时为0
+    pub fn drop_rate(&self) -> f64 {
+        let writes = self.writes_total();
+        if writes == 0 {
+            return 0.0;
+        }
+        self.drops_total() as f64 / writes as f64
+    }
+    
+    /// 检查流是否已满，已满时写入会失败
+    pub fn is_full(&self) -> bool {
+        let current_read = self.read_index.load(Ordering::Acquire);
+        let current_write = self.write_index.load(Ordering::Acquire);
+        (current_write + 1) % STREAM_CAPACITY == current_read
+    }
+    
+    /// 直接在写入位置上填充数据，无额外拷贝
+    /// 
+    /// 先预留写入位置，`writer`正常返回后才提交写索引，读取端不会看到未写完的数据。
+    /// 
+    /// # Panics
+    /// `writer`发生panic时槽位被重置为`None`，写索引和写入计数保持不变，
+    /// panic继续向上传播；流中已有的元素不受影响，之后仍可正常读写。
     pub fn write_direct<F>(&mut self, writer: F) -> Result<(), &'static str>
     where
         F: FnOnce(&mut Option<T>),
     {
-        loop {
-            let current_read = self.read_index.load(Ordering::Acquire);
-            let current_write = self.write_index.load(Ordering::Acquire);
-            
-            let next_index = (current_write + 1) % STREAM_CAPACITY;
-            if next_index == current_read {
-                return Err("缓冲区已满");
-            }
-            
-            // 尝试更新写索引
-            if self.write_index.compare_exchange(
-                current_write, 
-                next_index, 
-                Ordering::Release, 
-                Ordering::Relaxed
-            ).is_ok() {
-                // 直接操作数据
-                unsafe {
-                    writer(&mut *self.pool[current_write].as_mut_ptr());
-                }
-                return Ok(());
-            }
+        let slot = self.get_write_mut()?;
+        let guard = SlotRollback(slot);
+        writer(&mut *guard.0);
+        std::mem::forget(guard);
+        self.commit_write()
+    }
+    
+    /// 与[write_direct](Self::write_direct)相同，但`writer`可以返回错误放弃本次写入
+    /// 
+    /// `writer`返回Err时与发生panic一样回滚：槽位被重置为`None`，写索引和写入计数保持不变。
+    /// 填充过程可能失败时应使用本方法返回错误，而不是在`writer`中panic再由调用方捕获。
+    /// 
+    /// # 错误处理
+    /// 缓冲区已满时返回[PerpleError::BufferFull]且不调用`writer`；`writer`返回的错误原样返回
+    pub fn try_write_direct<F>(&mut self, writer: F) -> Result<(), PerpleError>
+    where
+        F: FnOnce(&mut Option<T>) -> Result<(), PerpleError>,
+    {
+        let slot = self.get_write_mut().map_err(|_| PerpleError::BufferFull { count: 0 })?;
+        let guard = SlotRollback(slot);
+        writer(&mut *guard.0)?;
+        std::mem::forget(guard);
+        self.commit_write().map_err(|_| PerpleError::BufferFull { count: 0 })
+    }
+}
+
+impl<T: Default + Send> Default for Stream<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Default + Send> Drop for Stream<T> {
+    fn drop(&mut self) {
+        for slot in &mut self.pool {
+            // 安全性：所有槽位在构造时初始化，之后只会被整体替换而不会被移出
+            unsafe { slot.assume_init_drop() };
         }
     }
 }
 
+/// 写入过程中发生panic时清空预留的槽位，避免残留写了一半的数据
+struct SlotRollback<'a, T>(&'a mut Option<T>);
+
+impl<T> Drop for SlotRollback<'_, T> {
+    fn drop(&mut self) {
+        *self.0 = None;
+    }
+}
+
 impl<T: Default + Send + Clone> Stream<T> {
     // 克隆实现等其他方法...
-}
\ No newline at end of file
+}
+/// 纯信号流，只记录待处理信号的个数而不携带数据
+/// 
+/// 适合“有新结果可读”这类通知场景，内部只有一个原子计数器，
+/// 发送和接收都无需加锁。待处理信号最多累积`N`个，超出的信号被合并。
+pub struct SignalStream<const N: usize> {
+    pending: AtomicUsize,
+}
+
+impl<const N: usize> SignalStream<N> {
+    /// 创建没有待处理信号的信号流
+    pub fn new() -> Self {
+        Self {
+            pending: AtomicUsize::new(0),
+        }
+    }
+    
+    /// 发送一个信号，待处理信号已达到`N`个时忽略
+    pub fn signal(&self) {
+        let _ = self.pending.fetch_update(Ordering::Release, Ordering::Relaxed, |count| {
+            (count < N).then_some(count + 1)
+        });
+    }
+    
+    /// 尝试消费一个信号（不阻塞）
+    /// 
+    /// # 返回值
+    /// 有待处理信号并成功消费时返回`true`
+    pub fn wait_signal(&self) -> bool {
+        self.pending
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| count.checked_sub(1))
+            .is_ok()
+    }
+    
+    /// 清空所有待处理信号
+    /// 
+    /// # 返回值
+    /// 返回被清空的信号个数
+    pub fn clear_signals(&self) -> usize {
+        self.pending.swap(0, Ordering::AcqRel)
+    }
+    
+    /// 待处理信号的个数
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Acquire)
+    }
+}
+
+impl<const N: usize> Default for SignalStream<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::Arc;
+
+    fn filled(values: &[u32]) -> Stream<Vec<u32>> {
+        let mut stream = Stream::new();
+        for &value in values {
+            stream.write(vec![value]).unwrap();
+        }
+        stream
+    }
+
+    fn drain(stream: &mut Stream<Vec<u32>>) -> Vec<Vec<u32>> {
+        std::iter::from_fn(|| stream.read()).collect()
+    }
+
+    #[test]
+    fn panicking_writer_leaves_stream_usable() {
+        let mut stream = filled(&[1, 2]);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            stream.write_direct(|slot| {
+                *slot = Some(vec![99]);
+                panic!("模拟写入过程中panic");
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!((stream.writes_total(), stream.pending()), (2, 2));
+
+        // 写了一半的数据不会被读到，之后的写入使用同一个槽位
+        stream.write_direct(|slot| *slot = Some(vec![3])).unwrap();
+        assert_eq!(drain(&mut stream), [vec![1], vec![2], vec![3]]);
+        assert!(stream.read().is_none());
+    }
+
+    #[test]
+    fn failing_writer_rolls_back_and_propagates_error() {
+        let mut stream = filled(&[1]);
+        let result = stream.try_write_direct(|slot| {
+            *slot = Some(vec![99]);
+            Err(PerpleError::InvalidParameter("模拟填充失败".to_string()))
+        });
+        assert!(matches!(result, Err(PerpleError::InvalidParameter(_))));
+        assert_eq!((stream.writes_total(), stream.pending()), (1, 1));
+
+        stream.try_write_direct(|slot| {
+            *slot = Some(vec![2]);
+            Ok(())
+        }).unwrap();
+        assert_eq!(drain(&mut stream), [vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn full_stream_rejects_direct_writes_without_calling_writer() {
+        let values: Vec<u32> = (0..STREAM_CAPACITY as u32 - 1).collect();
+        let mut stream = filled(&values);
+        assert!(stream.is_full());
+        let mut called = false;
+        assert!(stream.write_direct(|_| called = true).is_err());
+        let result = stream.try_write_direct(|_| {
+            called = true;
+            Ok(())
+        });
+        assert!(matches!(result, Err(PerpleError::BufferFull { count: 0 })));
+        assert!(!called);
+        assert_eq!(drain(&mut stream).len(), values.len());
+    }
+
+    #[test]
+    fn items_are_dropped_exactly_once() {
+        let item = Arc::new(());
+        let mut stream: Stream<Arc<()>> = Stream::new();
+        for _ in 0..5 {
+            stream.write(Arc::clone(&item)).unwrap();
+        }
+        // 读出的元素由调用方释放，回滚的槽位不残留引用
+        drop(stream.read());
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+            stream.write_direct(|slot| {
+                *slot = Some(Arc::clone(&item));
+                panic!("模拟写入过程中panic");
+            })
+        }));
+        let _ = stream.try_write_direct(|slot| {
+            *slot = Some(Arc::clone(&item));
+            Err(PerpleError::InvalidParameter("模拟填充失败".to_string()))
+        });
+        assert_eq!(Arc::strong_count(&item), 5);
+
+        // 绕过一整圈，覆盖写入已读出的槽位
+        for _ in 0..STREAM_CAPACITY * 2 {
+            drop(stream.read());
+            stream.write(Arc::clone(&item)).unwrap();
+        }
+        assert_eq!(Arc::strong_count(&item), 5);
+        drop(stream);
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn drop_oldest_keeps_latest_items_and_counts_drops() {
+        let mut stream: Stream<Vec<u32>> = Stream::new();
+        assert_eq!(stream.drop_rate(), 0.0);
+        // 流最多容纳STREAM_CAPACITY - 1个元素，写入更多时最旧的被丢弃，索引多次绕回
+        let total = STREAM_CAPACITY as u32 * 3;
+        for value in 0..total {
+            stream.write_or_drop_oldest(vec![value]);
+        }
+        let kept = STREAM_CAPACITY as u32 - 1;
+        let dropped = total - kept;
+        assert!(stream.is_full());
+        assert_eq!(stream.pending(), kept as usize);
+        assert_eq!(stream.drops_total(), dropped as u64);
+        assert_eq!(stream.drop_rate(), dropped as f64 / total as f64);
+
+        assert_eq!(stream.read(), Some(vec![dropped]));
+        assert_eq!(stream.pending(), kept as usize - 1);
+        // 清空剩余元素计入丢弃而非读取
+        assert_eq!(stream.drain(), kept as usize - 1);
+        assert!(!stream.has_data());
+        assert_eq!(stream.pending(), 0);
+        assert_eq!((stream.reads_total(), stream.drops_total()), (1, total as u64 - 1));
+        assert_eq!(stream.drain(), 0);
+
+        // 清空后继续写入，按写入顺序读出
+        for value in [7, 8, 9] {
+            stream.write_or_drop_oldest(vec![value]);
+        }
+        assert_eq!(drain(&mut stream), [vec![7], vec![8], vec![9]]);
+        assert_eq!(stream.drops_total(), total as u64 - 1);
+    }
+
+    #[test]
+    fn write_batch_stops_at_free_space() {
+        // 先读出一部分，使批量写入跨过缓冲区末尾
+        let mut stream = filled(&[100, 101, 102, 103, 104, 105, 106, 107, 108, 109]);
+        for _ in 0..8 {
+            stream.read().unwrap();
+        }
+        let free = STREAM_CAPACITY - 1 - 2;
+        let written = stream.write_batch((0..20).map(|value| vec![value])).unwrap();
+        assert_eq!(written, free);
+        assert!(stream.is_full());
+        assert_eq!(stream.writes_total(), 10 + free as u64);
+        assert!(matches!(stream.write_batch([vec![99]]), Err(PerpleError::BufferFull { count: 0 })));
+
+        let expected: Vec<Vec<u32>> = [108, 109].into_iter().chain(0..free as u32).map(|value| vec![value]).collect();
+        assert_eq!(drain(&mut stream), expected);
+        assert_eq!(stream.write_batch(std::iter::empty()).unwrap(), 0);
+    }
+
+    #[test]
+    fn signals_accumulate_up_to_capacity_and_are_consumed_one_by_one() {
+        let signals = SignalStream::<3>::new();
+        assert!(!signals.wait_signal());
+        for _ in 0..5 {
+            signals.signal();
+        }
+        // 超出容量的信号被合并
+        assert_eq!(signals.pending(), 3);
+        assert!(signals.wait_signal());
+        assert_eq!(signals.pending(), 2);
+
+        assert_eq!(signals.clear_signals(), 2);
+        assert!(!signals.wait_signal());
+        assert_eq!(signals.clear_signals(), 0);
+    }
+
+    #[test]
+    fn every_signal_from_another_thread_is_received_once() {
+        let signals = Arc::new(SignalStream::<4>::new());
+        let sender = {
+            let signals = Arc::clone(&signals);
+            std::thread::spawn(move || {
+                for _ in 0..100 {
+                    // 等待接收方消费，避免信号被合并
+                    while signals.pending() == 4 {
+                        std::thread::yield_now();
+                    }
+                    signals.signal();
+                }
+            })
+        };
+        let mut received = 0;
+        while received < 100 {
+            if signals.wait_signal() {
+                received += 1;
+            } else {
+                std::thread::yield_now();
+            }
+        }
+        sender.join().unwrap();
+        assert_eq!(signals.pending(), 0);
+    }
+}
@@ -0,0 +1,119 @@
+//! 边界框容器的堆分配与析构次数检查
+//!
+//! 计数分配器需要注册为`#[global_allocator]`，单独放在一个测试二进制中，避免影响库内其他测试。
+
+use perple::color::bounds::{Bounds, BoundsN};
+use perple::{BoundingBox, Detection};
+
+/// 只统计开启了统计的线程中的堆分配，用于检查构造是否分配内存以及检测结果是否恰好析构一次
+struct CountingAllocator;
+
+thread_local! {
+    static TRACKING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    static ALLOCATED: std::cell::Cell<isize> = const { std::cell::Cell::new(0) };
+    static FREED: std::cell::Cell<isize> = const { std::cell::Cell::new(0) };
+}
+
+fn count(counter: &'static std::thread::LocalKey<std::cell::Cell<isize>>) {
+    if TRACKING.try_with(|tracking| tracking.get()).unwrap_or(false) {
+        counter.with(|c| c.set(c.get() + 1));
+    }
+}
+
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        count(&ALLOCATED);
+        unsafe { std::alloc::System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        count(&FREED);
+        unsafe { std::alloc::System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// 执行`f`，返回结果以及期间的(分配次数, 释放次数)
+fn heap_during<R>(f: impl FnOnce() -> R) -> (R, (isize, isize)) {
+    ALLOCATED.with(|c| c.set(0));
+    FREED.with(|c| c.set(0));
+    TRACKING.with(|tracking| tracking.set(true));
+    let result = f();
+    TRACKING.with(|tracking| tracking.set(false));
+    (result, (ALLOCATED.with(|c| c.get()), FREED.with(|c| c.get())))
+}
+
+/// 类别名占用一块堆内存的检测结果，释放次数即析构次数
+fn named(class_id: usize) -> Detection {
+    Detection::new(BoundingBox::new(0.0, 0.0, 10.0, 10.0), class_id, format!("class{}", class_id), 0.9)
+}
+
+#[test]
+fn constructing_bounds_does_not_allocate() {
+    let ((bounds, default, small), heap) = heap_during(|| (Bounds::new(), Bounds::default(), BoundsN::<4>::new()));
+    assert_eq!(heap, (0, 0));
+    let (_, heap) = heap_during(move || drop((bounds, default, small)));
+    assert_eq!(heap, (0, 0));
+    let (_, heap) = heap_during(|| drop(Detection::default()));
+    assert_eq!(heap, (0, 0));
+}
+
+#[test]
+fn owned_detections_are_dropped_exactly_once() {
+    let mut bounds = Bounds::new();
+    for class_id in 0..6 {
+        bounds.push(named(class_id));
+    }
+    let (_, heap) = heap_during(|| bounds.retain(|d| d.class_id % 2 == 0));
+    assert_eq!((bounds.len(), heap), (3, (0, 3)));
+    let (_, heap) = heap_during(|| bounds.clear());
+    assert_eq!((bounds.len(), heap), (0, (0, 3)));
+
+    // 超出容量时被拒绝的检测结果立即析构
+    let mut small = BoundsN::<2>::new();
+    let (a, b, c) = (named(0), named(1), named(2));
+    let (_, heap) = heap_during(|| {
+        small.push(a);
+        small.push(b);
+        small.push(c);
+    });
+    assert_eq!((small.len(), heap), (2, (0, 1)));
+
+    // 克隆为每个元素分配一份，原容器和克隆各自析构自己的元素
+    let (copy, heap) = heap_during(|| small.clone());
+    assert_eq!(heap, (2, 0));
+    let (_, heap) = heap_during(move || drop((small, copy)));
+    assert_eq!(heap, (0, 4));
+}
+
+#[test]
+fn partially_consumed_into_iter_drops_the_rest() {
+    let bounds: BoundsN<4> = (0..4).map(named).collect();
+    let (first, heap) = heap_during(|| {
+        let mut iter = bounds.into_iter();
+        let first = iter.next().unwrap();
+        drop(iter);
+        first
+    });
+    assert_eq!((first.class_id, heap), (0, (0, 3)));
+    let (_, heap) = heap_during(move || drop(first));
+    assert_eq!(heap, (0, 1));
+}
+
+#[test]
+fn panicking_retain_predicate_never_drops_twice() {
+    let mut bounds: Bounds = (0..4).map(named).collect();
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        bounds.retain(|d| {
+            assert!(d.class_id != 2, "模拟判断函数panic");
+            d.class_id != 1
+        })
+    }));
+    assert!(result.is_err());
+    // 已整理好的前缀仍然有效，其余元素被泄漏而不是重复释放
+    assert_eq!(bounds.iter().map(|d| d.class_id).collect::<Vec<_>>(), [0]);
+    let (_, heap) = heap_during(move || drop(bounds));
+    assert_eq!(heap, (0, 1));
+}
@@ -0,0 +1,39 @@
+//! 公共接口的编译检查
+//!
+//! 只通过`perple::prelude`导入；prelude中的条目被移除、改名或改变签名时本测试无法编译。
+
+use perple::prelude::*;
+
+#[test]
+fn prelude_covers_supported_surface() {
+    let mut bounds = Bounds::new();
+    bounds.push(Detection::new(BoundingBox::new(4.0, 4.0, 20.0, 24.0), 0, "person", 0.9));
+    assert_eq!(bounds.len(), 1);
+
+    let stream: Stream<Bounds> = Stream::new();
+    let stream = std::sync::Arc::new(std::sync::Mutex::new(stream));
+    stream.lock().unwrap().write(bounds.clone()).unwrap();
+    assert_eq!(stream.lock().unwrap().read().map(|b| b.len()), Some(1));
+
+    let image = image::DynamicImage::new_rgb8(32, 32);
+    let annotated = draw_detections(&image, bounds.as_slice());
+    assert_eq!((annotated.width(), annotated.height()), (32, 32));
+    assert_ne!(annotated.to_rgb8().as_raw(), image.to_rgb8().as_raw());
+    assert!(load_image("missing/prelude_test.jpg").is_err());
+
+    assert!(matches!(LoopMode::Count(3), LoopMode::Count(3)));
+
+    // 需要模型文件的入口只检查路径和签名
+    let _: fn(&str, usize, usize) -> Result<YoloDetector, perple::PerpleError> = YoloDetector::new;
+    let _ = Perple::new;
+}
+
+#[test]
+fn root_reexports_match_prelude() {
+    // 示例使用的根路径与prelude指向相同的类型
+    let mode: perple::LoopMode = LoopMode::Continuous;
+    let detection: perple::Detection = Detection::new(perple::BoundingBox::default(), 0, "person", 0.5);
+    assert!(matches!(mode, LoopMode::Continuous));
+    assert_eq!(detection.bbox, BoundingBox::default());
+    let _: fn(&str) -> Result<image::DynamicImage, Box<dyn std::error::Error>> = perple::load_image;
+}